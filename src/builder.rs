@@ -0,0 +1,487 @@
+use std::backtrace::Backtrace;
+
+use crate::instructions::Instr;
+use crate::modules::*;
+use crate::types::*;
+use crate::{Encode, Error, IB};
+
+macro_rules! handle {
+    ($name:ident, $idx:ty) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name($idx);
+
+        impl $name {
+            pub fn index(self) -> $idx {
+                self.0
+            }
+        }
+    };
+}
+
+handle!(TypeHandle, TypeIdx);
+handle!(FuncHandle, FuncIdx);
+handle!(TableHandle, TableIdx);
+handle!(MemHandle, MemIdx);
+handle!(GlobalHandle, GlobalIdx);
+handle!(ElemHandle, ElemIdx);
+handle!(DataHandle, DataIdx);
+
+impl From<FuncHandle> for ExportDesc {
+    fn from(value: FuncHandle) -> Self {
+        ExportDesc::FuncIdx(value.0)
+    }
+}
+
+impl From<TableHandle> for ExportDesc {
+    fn from(value: TableHandle) -> Self {
+        ExportDesc::TableIdx(value.0)
+    }
+}
+
+impl From<MemHandle> for ExportDesc {
+    fn from(value: MemHandle) -> Self {
+        ExportDesc::MemIdx(value.0)
+    }
+}
+
+impl From<GlobalHandle> for ExportDesc {
+    fn from(value: GlobalHandle) -> Self {
+        ExportDesc::GlobalIdx(value.0)
+    }
+}
+
+/// Assembles a `Module` from its parts.
+///
+/// Imports of a kind have to be added before any definition of that kind,
+/// since they come first in the index space, otherwise `build` fails.
+#[derive(Debug, Default)]
+pub struct ModuleBuilder {
+    types: TypeSec,
+    imports: ImportSec,
+    funcs: FuncSec,
+    codes: Vec<Option<Code>>,
+    tables: TableSec,
+    mems: MemSec,
+    globals: GlobalSec,
+    exports: ExportSec,
+    start: Option<FuncIdx>,
+    elems: ElemSec,
+    datas: DataSec,
+    customs: Vec<CustomSec>,
+    misordered: Option<String>,
+}
+
+impl ModuleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a type in its own recursion group, returning the existing index if an equal type was
+    /// already added.
+    pub fn add_type(&mut self, ty: impl Into<SubType>) -> TypeHandle {
+        let ty = RecType(vec![ty.into()]);
+        match self.types.iter().position(|other| *other == ty) {
+            Some(idx) => TypeHandle(idx as u32),
+            None => {
+                self.types.push(ty);
+                TypeHandle(self.types.len() as u32 - 1)
+            }
+        }
+    }
+
+    fn imported(&self, kind: impl Fn(&ImportDesc) -> bool) -> u32 {
+        self.imports
+            .iter()
+            .filter(|import| kind(&import.desc))
+            .count() as u32
+    }
+
+    fn import(&mut self, module: &str, name: &str, desc: ImportDesc, defined: usize) -> u32 {
+        if defined != 0 && self.misordered.is_none() {
+            self.misordered = Some(format!(
+                "import {module}.{name} added after a definition of the same kind"
+            ));
+        }
+        let idx =
+            self.imported(|other| std::mem::discriminant(other) == std::mem::discriminant(&desc));
+        self.imports.push(Import {
+            module: module.to_string(),
+            name: name.to_string(),
+            desc,
+        });
+        idx
+    }
+
+    pub fn import_func(&mut self, module: &str, name: &str, ty: FuncType) -> FuncHandle {
+        let ty = self.add_type(ty);
+        let defined = self.funcs.len();
+        FuncHandle(self.import(module, name, ImportDesc::TypeIdx(ty.0), defined))
+    }
+
+    pub fn import_table(&mut self, module: &str, name: &str, ty: TableType) -> TableHandle {
+        let defined = self.tables.len();
+        TableHandle(self.import(module, name, ImportDesc::TableType(ty), defined))
+    }
+
+    pub fn import_memory(&mut self, module: &str, name: &str, ty: MemType) -> MemHandle {
+        let defined = self.mems.len();
+        MemHandle(self.import(module, name, ImportDesc::MemType(ty), defined))
+    }
+
+    pub fn import_global(&mut self, module: &str, name: &str, ty: GlobalType) -> GlobalHandle {
+        let defined = self.globals.len();
+        GlobalHandle(self.import(module, name, ImportDesc::GlobalType(ty), defined))
+    }
+
+    /// Reserves the index of a new function, the body is given later with `define`.
+    /// Knowing the handle up front allows recursive and mutually recursive calls.
+    pub fn function(&mut self, ty: FuncType) -> FunctionBuilder {
+        let params = ty.0.len() as u32;
+        let ty = self.add_type(ty);
+        let handle = FuncHandle(
+            self.imported(|desc| matches!(desc, ImportDesc::TypeIdx(_))) + self.funcs.len() as u32,
+        );
+        self.funcs.push(ty.0);
+        self.codes.push(None);
+        FunctionBuilder {
+            handle,
+            params,
+            locals: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn define(&mut self, func: FunctionBuilder) -> FuncHandle {
+        let imported = self.imported(|desc| matches!(desc, ImportDesc::TypeIdx(_)));
+        let body = Func(func.locals, Expr(func.body));
+        let mut content = IB::new();
+        body.encode(&mut content);
+        self.codes[(func.handle.0 - imported) as usize] = Some(Code(content.len() as u32, body));
+        func.handle
+    }
+
+    pub fn table(&mut self, ty: TableType) -> TableHandle {
+        self.tables.push(ty);
+        TableHandle(
+            self.imported(|desc| matches!(desc, ImportDesc::TableType(_)))
+                + self.tables.len() as u32
+                - 1,
+        )
+    }
+
+    pub fn memory(&mut self, ty: MemType) -> MemHandle {
+        self.mems.push(ty);
+        MemHandle(
+            self.imported(|desc| matches!(desc, ImportDesc::MemType(_))) + self.mems.len() as u32
+                - 1,
+        )
+    }
+
+    pub fn global(&mut self, ty: GlobalType, init: Expr) -> GlobalHandle {
+        self.globals.push((ty, init));
+        GlobalHandle(
+            self.imported(|desc| matches!(desc, ImportDesc::GlobalType(_)))
+                + self.globals.len() as u32
+                - 1,
+        )
+    }
+
+    pub fn export(&mut self, name: &str, desc: impl Into<ExportDesc>) {
+        self.exports.push((name.to_string(), desc.into()));
+    }
+
+    pub fn start(&mut self, func: FuncHandle) {
+        self.start = Some(func.0);
+    }
+
+    fn elem(&mut self, elem: Elem) -> ElemHandle {
+        self.elems.push(elem);
+        ElemHandle(self.elems.len() as u32 - 1)
+    }
+
+    /// An active segment of function references copied into `table` at `offset`.
+    pub fn active_elem(
+        &mut self,
+        table: TableHandle,
+        offset: Expr,
+        funcs: &[FuncHandle],
+    ) -> ElemHandle {
+        let funcs = funcs.iter().map(|func| func.0).collect();
+        self.elem(match table.0 {
+            0 => Elem::A(offset, funcs),
+            table => Elem::C(table, offset, 0, funcs),
+        })
+    }
+
+    pub fn passive_elem(&mut self, funcs: &[FuncHandle]) -> ElemHandle {
+        self.elem(Elem::B(0, funcs.iter().map(|func| func.0).collect()))
+    }
+
+    /// Declares functions referenced by `ref.func` without placing them in a table.
+    pub fn declared_elem(&mut self, funcs: &[FuncHandle]) -> ElemHandle {
+        self.elem(Elem::D(0, funcs.iter().map(|func| func.0).collect()))
+    }
+
+    fn data(&mut self, data: Data) -> DataHandle {
+        self.datas.push(data);
+        DataHandle(self.datas.len() as u32 - 1)
+    }
+
+    pub fn active_data(
+        &mut self,
+        mem: MemHandle,
+        offset: Expr,
+        bytes: impl Into<Vec<u8>>,
+    ) -> DataHandle {
+        self.data(match mem.0 {
+            0 => Data::A(offset, bytes.into()),
+            mem => Data::C(mem, offset, bytes.into()),
+        })
+    }
+
+    pub fn passive_data(&mut self, bytes: impl Into<Vec<u8>>) -> DataHandle {
+        self.data(Data::B(bytes.into()))
+    }
+
+    pub fn custom(&mut self, sec: CustomSec) {
+        self.customs.push(sec);
+    }
+
+    /// Fails if an import was added too late or a function reserved with `function` was never
+    /// defined.
+    pub fn build(self) -> Result<Module, Error> {
+        if let Some(msg) = self.misordered {
+            return Err(Error::InvalidModule(Backtrace::capture(), msg));
+        }
+        let imported = self.imported(|desc| matches!(desc, ImportDesc::TypeIdx(_)));
+        let codes = self
+            .codes
+            .into_iter()
+            .zip(imported..)
+            .map(|(code, idx)| {
+                code.ok_or_else(|| {
+                    Error::InvalidModule(
+                        Backtrace::capture(),
+                        format!("function {idx} was never defined"),
+                    )
+                })
+            })
+            .collect::<Result<CodeSec, _>>()?;
+        let data_count = self.datas.iter().any(|data| matches!(data, Data::B(_)))
+            || codes.iter().any(|code| uses_data(&code.1 .1 .0));
+
+        let mut sections = Vec::new();
+        if !self.types.is_empty() {
+            sections.push(Section::Type(self.types));
+        }
+        if !self.imports.is_empty() {
+            sections.push(Section::Import(self.imports));
+        }
+        if !self.funcs.is_empty() {
+            sections.push(Section::Function(self.funcs));
+        }
+        if !self.tables.is_empty() {
+            sections.push(Section::Table(self.tables));
+        }
+        if !self.mems.is_empty() {
+            sections.push(Section::Memory(self.mems));
+        }
+        if !self.globals.is_empty() {
+            sections.push(Section::Global(self.globals));
+        }
+        if !self.exports.is_empty() {
+            sections.push(Section::Export(self.exports));
+        }
+        if let Some(start) = self.start {
+            sections.push(Section::Start(start));
+        }
+        if !self.elems.is_empty() {
+            sections.push(Section::Element(self.elems));
+        }
+        if data_count {
+            sections.push(Section::DataCountSection(self.datas.len() as u32));
+        }
+        if !codes.is_empty() {
+            sections.push(Section::Code(codes));
+        }
+        if !self.datas.is_empty() {
+            sections.push(Section::Data(self.datas));
+        }
+        sections.extend(self.customs.into_iter().map(Section::Custom));
+        Ok(Module {
+            magic: u32::from_le_bytes(*b"\0asm"),
+            version: 1,
+            sections,
+            layout: None,
+            custom_errors: Vec::new(),
+        })
+    }
+}
+
+fn uses_data(instrs: &[Instr]) -> bool {
+    instrs.iter().any(|instr| match instr {
+        Instr::MemoryInit(_, _)
+        | Instr::DataDrop(_)
+        | Instr::ArrayNewData(_, _)
+        | Instr::ArrayInitData(_, _) => true,
+        Instr::Block(_, body) | Instr::Loop(_, body) | Instr::If(_, body) => uses_data(body),
+        Instr::IfElse(_, then, other) => uses_data(then) || uses_data(other),
+        Instr::Try(_, body, catches, catch_all) => {
+            uses_data(body)
+                || catches.iter().any(|(_, handler)| uses_data(handler))
+                || catch_all.as_deref().is_some_and(uses_data)
+        }
+        Instr::TryDelegate(_, body, _) | Instr::TryTable(_, _, body) => uses_data(body),
+        _ => false,
+    })
+}
+
+/// Locals and body of a function reserved with `ModuleBuilder::function`.
+#[derive(Debug)]
+pub struct FunctionBuilder {
+    handle: FuncHandle,
+    params: u32,
+    locals: Vec<Locals>,
+    body: Vec<Instr>,
+}
+
+impl FunctionBuilder {
+    pub fn handle(&self) -> FuncHandle {
+        self.handle
+    }
+
+    /// Adds a local, returning its index after the params.
+    pub fn local(&mut self, ty: ValType) -> LocalIdx {
+        let idx = self.params + self.locals.iter().map(|locals| locals.0).sum::<u32>();
+        match self.locals.last_mut() {
+            Some(Locals(n, last)) if *last == ty => *n += 1,
+            _ => self.locals.push(Locals(1, ty)),
+        }
+        idx
+    }
+
+    pub fn instr(&mut self, instr: Instr) -> &mut Self {
+        self.body.push(instr);
+        self
+    }
+
+    pub fn instrs(&mut self, instrs: impl IntoIterator<Item = Instr>) -> &mut Self {
+        self.body.extend(instrs);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ModuleBuilder;
+    use crate::instructions::{BlockType, Instr};
+    use crate::modules::{Expr, Module, Section};
+    use crate::types::{
+        CompType, FieldType, FuncType, GlobalType, Limits, NumType, StorageType, SubType, ValType,
+    };
+    use crate::validate::validate;
+    use crate::{Encode, Parse};
+
+    #[test]
+    fn build() {
+        let i32 = ValType::NumType(NumType::I32);
+        let mut builder = ModuleBuilder::new();
+        let log = builder.import_func("env", "log", FuncType(vec![i32], vec![]));
+        let mem = builder.memory(Limits(1, None, false, false));
+        let msg = builder.passive_data(*b"hello");
+
+        let mut sum = builder.function(FuncType(vec![i32], vec![i32]));
+        let acc = sum.local(i32);
+        let me = sum.handle();
+        sum.instrs([
+            Instr::LocalGet(0),
+            Instr::I32Eqz,
+            Instr::IfElse(
+                BlockType::ValType(i32),
+                vec![Instr::I32Const(0)],
+                vec![
+                    Instr::LocalGet(0),
+                    Instr::I32Const(1),
+                    Instr::I32Sub,
+                    Instr::Call(me.index()),
+                    Instr::LocalGet(0),
+                    Instr::I32Add,
+                    Instr::LocalTee(acc),
+                    Instr::Call(log.index()),
+                    Instr::LocalGet(acc),
+                ],
+            ),
+        ]);
+        let mut init = builder.function(FuncType(vec![], vec![]));
+        init.instrs([
+            Instr::I32Const(0),
+            Instr::I32Const(0),
+            Instr::I32Const(5),
+            Instr::MemoryInit(msg.index(), 0),
+        ]);
+        let init = builder.define(init);
+        assert_eq!(builder.define(sum), me);
+        builder.export("sum", me);
+        builder.export("memory", mem);
+        builder.start(init);
+        builder.global(GlobalType(false, i32), Expr(vec![Instr::I32Const(7)]));
+
+        assert_eq!((log.index(), me.index(), init.index()), (0, 1, 2));
+        assert_eq!(acc, 1);
+        assert_eq!(builder.add_type(FuncType(vec![i32], vec![])).index(), 0);
+
+        let module = builder.build().unwrap();
+        assert!(validate(&module).is_ok());
+        assert_eq!(module.types().len(), 3);
+        assert_eq!(module.func_type(1), Some(&FuncType(vec![i32], vec![i32])));
+
+        let mut bytes = Vec::new();
+        module.encode(&mut bytes);
+        let expected = bytes.clone();
+        let decoded = Module::parse(&mut bytes).unwrap();
+        let mut again = Vec::new();
+        decoded.encode(&mut again);
+        assert_eq!(again, expected);
+    }
+
+    #[test]
+    fn data_count() {
+        let mut builder = ModuleBuilder::new();
+        let bytes_ty = builder.add_type(SubType(
+            true,
+            Vec::new(),
+            CompType::Array(FieldType(StorageType::I8, true)),
+        ));
+        let mem = builder.memory(Limits(1, None, false, false));
+        let bytes = builder.active_data(mem, Expr(vec![Instr::I32Const(0)]), *b"abc");
+        let mut func = builder.function(FuncType(vec![], vec![]));
+        func.instrs([
+            Instr::I32Const(0),
+            Instr::I32Const(3),
+            Instr::ArrayNewData(bytes_ty.index(), bytes.index()),
+            Instr::Drop,
+        ]);
+        builder.define(func);
+        let module = builder.build().unwrap();
+        validate(&module).unwrap();
+        assert!(module
+            .sections
+            .iter()
+            .any(|section| matches!(section, Section::DataCountSection(1))));
+    }
+
+    #[test]
+    fn misuse() {
+        let i32 = ValType::NumType(NumType::I32);
+        let mut builder = ModuleBuilder::new();
+        builder.global(GlobalType(false, i32), Expr(vec![Instr::I32Const(0)]));
+        builder.import_global("env", "g", GlobalType(false, i32));
+        assert!(builder.build().is_err());
+
+        let mut builder = ModuleBuilder::new();
+        builder.import_func("env", "f", FuncType(vec![], vec![]));
+        builder.function(FuncType(vec![], vec![]));
+        let err = builder.build().unwrap_err();
+        assert!(format!("{err:?}").contains("function 1 was never defined"));
+    }
+}
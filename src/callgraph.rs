@@ -0,0 +1,366 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::custom::NameSec;
+use crate::instructions::Instr;
+use crate::modules::{Elem, ExportDesc, Expr, FuncIdx, Module, TypeIdx};
+use crate::Parse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CallKind {
+    /// `call` or `return_call`.
+    Direct,
+    /// A possible target of `call_indirect` or `call_ref`.
+    Indirect,
+}
+
+/// Who calls whom, with imported functions as leaves and exports and the start function as roots.
+///
+/// Indirect calls are resolved conservatively: every function placed in an element segment or
+/// referenced by `ref.func` whose type matches the call is a possible target.
+#[derive(Debug, Clone)]
+pub struct CallGraph {
+    imported: u32,
+    names: Vec<String>,
+    callees: Vec<BTreeMap<FuncIdx, CallKind>>,
+    roots: BTreeSet<FuncIdx>,
+    address_taken: BTreeSet<FuncIdx>,
+}
+
+/// Calls `f` on every instruction of `instrs`, including those of nested blocks.
+pub(crate) fn visit(instrs: &[Instr], f: &mut impl FnMut(&Instr)) {
+    for instr in instrs {
+        f(instr);
+        for block in instr.blocks() {
+            visit(block, f);
+        }
+    }
+}
+
+/// Immediate dominators of the nodes reachable from `entry`, using the iterative algorithm of
+/// Cooper, Harvey and Kennedy. The entry is its own dominator and unreachable nodes have none.
+pub(crate) fn dominators(succs: &[Vec<usize>], entry: usize) -> Vec<Option<usize>> {
+    // Postorder numbering by an iterative depth-first search.
+    let mut order = vec![usize::MAX; succs.len()];
+    let mut postorder = Vec::new();
+    let mut visited = vec![false; succs.len()];
+    let mut stack = vec![(entry, 0)];
+    visited[entry] = true;
+    while let Some((node, next)) = stack.pop() {
+        if let Some(&succ) = succs[node].get(next) {
+            stack.push((node, next + 1));
+            if !visited[succ] {
+                visited[succ] = true;
+                stack.push((succ, 0));
+            }
+        } else {
+            order[node] = postorder.len();
+            postorder.push(node);
+        }
+    }
+
+    let mut preds = vec![Vec::new(); succs.len()];
+    for (node, targets) in succs.iter().enumerate() {
+        for &succ in targets {
+            preds[succ].push(node);
+        }
+    }
+
+    let mut idom = vec![None; succs.len()];
+    idom[entry] = Some(entry);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &node in postorder.iter().rev().filter(|&&node| node != entry) {
+            let mut new = None;
+            for &pred in &preds[node] {
+                if idom[pred].is_none() {
+                    continue;
+                }
+                new = Some(match new {
+                    None => pred,
+                    Some(mut other) => {
+                        let mut pred = pred;
+                        while pred != other {
+                            while order[pred] < order[other] {
+                                pred = idom[pred].unwrap();
+                            }
+                            while order[other] < order[pred] {
+                                other = idom[other].unwrap();
+                            }
+                        }
+                        pred
+                    }
+                });
+            }
+            if new.is_some() && idom[node] != new {
+                idom[node] = new;
+                changed = true;
+            }
+        }
+    }
+    idom
+}
+
+fn ref_funcs(expr: &Expr, funcs: &mut BTreeSet<FuncIdx>) {
+    visit(&expr.0, &mut |instr| {
+        if let Instr::RefFunc(func) = instr {
+            funcs.insert(*func);
+        }
+    })
+}
+
+impl CallGraph {
+    pub fn new(module: &Module) -> Self {
+        let imported = module.imported_funcs().count() as u32;
+        let count = imported as usize + module.funcs().len();
+
+        let mut address_taken = BTreeSet::new();
+        for (_, init) in module.globals() {
+            ref_funcs(init, &mut address_taken);
+        }
+        for code in module.codes() {
+            ref_funcs(&code.1 .1, &mut address_taken);
+        }
+        for elem in module.elems() {
+            match elem {
+                Elem::A(_, funcs)
+                | Elem::B(_, funcs)
+                | Elem::C(_, _, _, funcs)
+                | Elem::D(_, funcs) => address_taken.extend(funcs),
+                Elem::E(_, exprs)
+                | Elem::F(_, exprs)
+                | Elem::G(_, _, _, exprs)
+                | Elem::H(_, exprs) => {
+                    for expr in exprs {
+                        ref_funcs(expr, &mut address_taken);
+                    }
+                }
+            }
+        }
+
+        // With subtyping a function may be called through any of its supertypes.
+        let types = module.types();
+        let subtyping = types.iter().any(|ty| !ty.1.is_empty());
+        let targets = |ty: TypeIdx| -> Vec<FuncIdx> {
+            let expected = types.get(ty as usize).and_then(|ty| ty.func());
+            address_taken
+                .iter()
+                .copied()
+                .filter(|func| subtyping || module.func_type(*func) == expected)
+                .collect()
+        };
+
+        let mut callees = vec![BTreeMap::new(); count];
+        for (idx, code) in module.codes().iter().enumerate() {
+            let calls = &mut callees[imported as usize + idx];
+            visit(&code.1 .1 .0, &mut |instr| match instr {
+                Instr::Call(func) | Instr::ReturnCall(func) => {
+                    calls.insert(*func, CallKind::Direct);
+                }
+                Instr::CallIndirect(ty, _)
+                | Instr::ReturnCallIndirect(ty, _)
+                | Instr::CallRef(ty)
+                | Instr::ReturnCallRef(ty) => {
+                    for func in targets(*ty) {
+                        calls.entry(func).or_insert(CallKind::Indirect);
+                    }
+                }
+                _ => {}
+            });
+        }
+
+        let mut roots: BTreeSet<_> = module
+            .exports()
+            .iter()
+            .filter_map(|(_, desc)| match desc {
+                ExportDesc::FuncIdx(func) => Some(*func),
+                _ => None,
+            })
+            .collect();
+        roots.extend(module.start());
+
+        let mut names: Vec<_> = module
+            .imported_funcs()
+            .map(|(import, _)| format!("{}.{}", import.module, import.name))
+            .collect();
+        names.extend((imported..count as u32).map(|func| format!("func{func}")));
+        if let Some(Ok(sec)) = module.custom_sec(NameSec::NAME).map(NameSec::parse) {
+            for (func, name) in sec.funcs {
+                if let Some(slot) = names.get_mut(func as usize) {
+                    *slot = name;
+                }
+            }
+        }
+
+        Self {
+            imported,
+            names,
+            callees,
+            roots,
+            address_taken,
+        }
+    }
+
+    /// Number of functions, imported ones included.
+    pub fn len(&self) -> usize {
+        self.callees.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.callees.is_empty()
+    }
+
+    pub fn is_import(&self, func: FuncIdx) -> bool {
+        func < self.imported
+    }
+
+    /// Name from the name section, `module.name` for imports, or `func{idx}`.
+    pub fn name(&self, func: FuncIdx) -> &str {
+        &self.names[func as usize]
+    }
+
+    pub fn roots(&self) -> &BTreeSet<FuncIdx> {
+        &self.roots
+    }
+
+    /// Functions in element segments or referenced by `ref.func`.
+    pub fn address_taken(&self) -> &BTreeSet<FuncIdx> {
+        &self.address_taken
+    }
+
+    pub fn callees(&self, func: FuncIdx) -> impl Iterator<Item = (FuncIdx, CallKind)> + '_ {
+        self.callees
+            .get(func as usize)
+            .into_iter()
+            .flat_map(|calls| calls.iter().map(|(func, kind)| (*func, *kind)))
+    }
+
+    pub fn callers(&self, func: FuncIdx) -> Vec<(FuncIdx, CallKind)> {
+        self.callees
+            .iter()
+            .enumerate()
+            .filter_map(|(caller, calls)| Some((caller as FuncIdx, *calls.get(&func)?)))
+            .collect()
+    }
+
+    /// Functions reachable from the roots, the roots included.
+    pub fn reachable(&self) -> BTreeSet<FuncIdx> {
+        self.reachable_from(self.roots.iter().copied())
+    }
+
+    pub fn reachable_from(&self, from: impl IntoIterator<Item = FuncIdx>) -> BTreeSet<FuncIdx> {
+        let mut seen = BTreeSet::new();
+        let mut stack: Vec<_> = from.into_iter().collect();
+        while let Some(func) = stack.pop() {
+            if seen.insert(func) {
+                stack.extend(self.callees(func).map(|(callee, _)| callee));
+            }
+        }
+        seen
+    }
+
+    /// Whether `to` may be called, directly or transitively, from `from`.
+    pub fn is_reachable(&self, from: FuncIdx, to: FuncIdx) -> bool {
+        self.reachable_from(self.callees(from).map(|(callee, _)| callee))
+            .contains(&to)
+    }
+
+    /// Immediate dominator of each function: the one all call paths from the roots go through.
+    /// Roots, which are only dominated by the module's entry points as a whole, and unreachable
+    /// functions have none.
+    pub fn dominators(&self) -> Vec<Option<FuncIdx>> {
+        let entry = self.len();
+        let mut succs: Vec<Vec<usize>> = self
+            .callees
+            .iter()
+            .map(|calls| calls.keys().map(|func| *func as usize).collect())
+            .collect();
+        succs.push(self.roots.iter().map(|func| *func as usize).collect());
+        let mut idom = dominators(&succs, entry);
+        idom.pop();
+        idom.into_iter()
+            .map(|idom| {
+                idom.filter(|idom| *idom != entry)
+                    .map(|idom| idom as FuncIdx)
+            })
+            .collect()
+    }
+
+    /// Graphviz rendering: imports are boxes, roots have a double border and indirect calls are
+    /// dashed.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph calls {\n");
+        for func in 0..self.len() as FuncIdx {
+            let name = self.name(func).replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = write!(out, "    f{func} [label=\"{name}\"");
+            if self.is_import(func) {
+                out.push_str(", shape=box");
+            }
+            if self.roots.contains(&func) {
+                out.push_str(", peripheries=2");
+            }
+            out.push_str("];\n");
+        }
+        for func in 0..self.len() as FuncIdx {
+            for (callee, kind) in self.callees(func) {
+                let _ = write!(out, "    f{func} -> f{callee}");
+                if kind == CallKind::Indirect {
+                    out.push_str(" [style=dashed]");
+                }
+                out.push_str(";\n");
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallGraph, CallKind};
+    use crate::modules::Module;
+    use crate::Parse;
+
+    #[test]
+    fn call_graph() {
+        let module = Module::parse(
+            r#"(module
+                (type $v (func))
+                (import "env" "log" (func $log))
+                (table 2 funcref)
+                (elem (i32.const 0) $a $b)
+                (func $main (export "main")
+                    (call $helper)
+                    (call_indirect (type $v) (i32.const 0)))
+                (func $helper (call $log))
+                (func $a)
+                (func $b (param i32))
+                (func $dead (call $helper)))"#,
+        )
+        .unwrap();
+        let graph = CallGraph::new(&module);
+        assert_eq!(graph.len(), 6);
+        assert!(graph.is_import(0));
+        assert_eq!(graph.roots().iter().copied().collect::<Vec<_>>(), [1]);
+        assert_eq!(
+            graph.callees(1).collect::<Vec<_>>(),
+            [(2, CallKind::Direct), (3, CallKind::Indirect)]
+        );
+        assert_eq!(
+            graph.callers(2),
+            [(1, CallKind::Direct), (5, CallKind::Direct)]
+        );
+        assert_eq!(
+            graph.reachable().into_iter().collect::<Vec<_>>(),
+            [0, 1, 2, 3]
+        );
+        assert!(graph.is_reachable(1, 0));
+        assert!(!graph.is_reachable(2, 1));
+        assert_eq!(graph.name(1), "main");
+        let dot = graph.to_dot();
+        assert!(dot.contains("f0 [label=\"log\", shape=box];"));
+        assert!(dot.contains("f1 [label=\"main\", peripheries=2];"));
+        assert!(dot.contains("f1 -> f3 [style=dashed];"));
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::callgraph::dominators;
+use crate::instructions::{Catch, Instr};
+use crate::modules::Expr;
+
+pub type BlockId = usize;
+
+/// Straight-line instructions. A branch, `return` or `unreachable` can only be the last one;
+/// structured instructions are replaced by the edges they induce.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BasicBlock {
+    pub instrs: Vec<Instr>,
+    pub succs: Vec<BlockId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+    pub header: BlockId,
+    pub blocks: BTreeSet<BlockId>,
+    /// Index of the innermost enclosing loop.
+    pub parent: Option<usize>,
+}
+
+/// Control flow graph of a function body. Block 0 is the entry and the empty block `exit` is
+/// where `return` and the end of the body lead. Exceptions are approximated by edges from the
+/// start of a `try` body to each of its handlers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+    pub exit: BlockId,
+}
+
+struct Builder {
+    blocks: Vec<BasicBlock>,
+    /// Branch target of each enclosing label, innermost last.
+    labels: Vec<BlockId>,
+    exit: BlockId,
+}
+
+impl Builder {
+    fn block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock::default());
+        self.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: BlockId, to: BlockId) {
+        if !self.blocks[from].succs.contains(&to) {
+            self.blocks[from].succs.push(to);
+        }
+    }
+
+    fn label(&self, label: u32) -> BlockId {
+        self.labels[self.labels.len() - 1 - label as usize]
+    }
+
+    /// Continues `to` with the end of a nested body, if control can reach it.
+    fn join(&mut self, end: Option<BlockId>, to: BlockId) {
+        if let Some(end) = end {
+            self.edge(end, to);
+        }
+    }
+
+    /// Adds `instrs` starting in `current`, returning the block control falls out of, if any.
+    fn body(&mut self, instrs: &[Instr], mut current: Option<BlockId>) -> Option<BlockId> {
+        for instr in instrs {
+            // Code after an unconditional branch gets a block without predecessors.
+            let cur = match current {
+                Some(cur) => cur,
+                None => self.block(),
+            };
+            current = Some(cur);
+            match instr {
+                Instr::Block(_, body) | Instr::TryDelegate(_, body, _) => {
+                    let after = self.block();
+                    self.labels.push(after);
+                    let end = self.body(body, Some(cur));
+                    self.labels.pop();
+                    self.join(end, after);
+                    current = Some(after);
+                }
+                Instr::Loop(_, body) => {
+                    let header = self.block();
+                    self.edge(cur, header);
+                    self.labels.push(header);
+                    let end = self.body(body, Some(header));
+                    self.labels.pop();
+                    let after = self.block();
+                    self.join(end, after);
+                    current = Some(after);
+                }
+                Instr::If(_, then) => {
+                    let (after, start) = (self.block(), self.block());
+                    self.edge(cur, start);
+                    self.edge(cur, after);
+                    self.labels.push(after);
+                    let end = self.body(then, Some(start));
+                    self.labels.pop();
+                    self.join(end, after);
+                    current = Some(after);
+                }
+                Instr::IfElse(_, then, other) => {
+                    let after = self.block();
+                    self.labels.push(after);
+                    for arm in [then, other] {
+                        let start = self.block();
+                        self.edge(cur, start);
+                        let end = self.body(arm, Some(start));
+                        self.join(end, after);
+                    }
+                    self.labels.pop();
+                    current = Some(after);
+                }
+                Instr::Try(_, body, catches, catch_all) => {
+                    let (after, start) = (self.block(), self.block());
+                    self.edge(cur, start);
+                    self.labels.push(after);
+                    let end = self.body(body, Some(start));
+                    self.join(end, after);
+                    for handler in catches.iter().map(|(_, handler)| handler).chain(catch_all) {
+                        let entry = self.block();
+                        self.edge(start, entry);
+                        let end = self.body(handler, Some(entry));
+                        self.join(end, after);
+                    }
+                    self.labels.pop();
+                    current = Some(after);
+                }
+                Instr::TryTable(_, catches, body) => {
+                    let (after, start) = (self.block(), self.block());
+                    self.edge(cur, start);
+                    for catch in catches {
+                        let (Catch::Catch(_, label)
+                        | Catch::CatchRef(_, label)
+                        | Catch::CatchAll(label)
+                        | Catch::CatchAllRef(label)) = catch;
+                        let target = self.label(*label);
+                        self.edge(start, target);
+                    }
+                    self.labels.push(after);
+                    let end = self.body(body, Some(start));
+                    self.labels.pop();
+                    self.join(end, after);
+                    current = Some(after);
+                }
+                Instr::Br(label) => {
+                    self.blocks[cur].instrs.push(instr.clone());
+                    let target = self.label(*label);
+                    self.edge(cur, target);
+                    current = None;
+                }
+                Instr::BrIf(label)
+                | Instr::BrOnNull(label)
+                | Instr::BrOnNonNull(label)
+                | Instr::BrOnCast(label, _, _)
+                | Instr::BrOnCastFail(label, _, _) => {
+                    self.blocks[cur].instrs.push(instr.clone());
+                    let target = self.label(*label);
+                    self.edge(cur, target);
+                    let next = self.block();
+                    self.edge(cur, next);
+                    current = Some(next);
+                }
+                Instr::BrTable(labels, default) => {
+                    self.blocks[cur].instrs.push(instr.clone());
+                    for label in labels.iter().chain([default]) {
+                        let target = self.label(*label);
+                        self.edge(cur, target);
+                    }
+                    current = None;
+                }
+                Instr::Return
+                | Instr::ReturnCall(_)
+                | Instr::ReturnCallIndirect(_, _)
+                | Instr::ReturnCallRef(_) => {
+                    self.blocks[cur].instrs.push(instr.clone());
+                    self.edge(cur, self.exit);
+                    current = None;
+                }
+                Instr::UnReachable | Instr::Throw(_) | Instr::Rethrow(_) | Instr::ThrowRef => {
+                    self.blocks[cur].instrs.push(instr.clone());
+                    current = None;
+                }
+                _ => self.blocks[cur].instrs.push(instr.clone()),
+            }
+        }
+        current
+    }
+}
+
+impl Cfg {
+    pub fn new(body: &Expr) -> Self {
+        let mut builder = Builder {
+            blocks: vec![BasicBlock::default(), BasicBlock::default()],
+            labels: vec![1],
+            exit: 1,
+        };
+        let end = builder.body(&body.0, Some(0));
+        builder.join(end, 1);
+        Self {
+            blocks: builder.blocks,
+            exit: 1,
+        }
+    }
+
+    pub fn preds(&self, block: BlockId) -> Vec<BlockId> {
+        (0..self.blocks.len())
+            .filter(|pred| self.blocks[*pred].succs.contains(&block))
+            .collect()
+    }
+
+    /// Immediate dominator of each block. The entry and unreachable blocks have none.
+    pub fn dominators(&self) -> Vec<Option<BlockId>> {
+        let succs: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|block| block.succs.clone())
+            .collect();
+        let mut idom = dominators(&succs, 0);
+        idom[0] = None;
+        idom
+    }
+
+    /// Whether every path from the entry to `block` goes through `dom`.
+    pub fn dominates(&self, dom: BlockId, block: BlockId) -> bool {
+        let idom = self.dominators();
+        let mut current = Some(block);
+        while let Some(block) = current {
+            if block == dom {
+                return true;
+            }
+            current = idom[block];
+        }
+        false
+    }
+
+    /// Natural loops, one per header, outer loops before the loops they contain.
+    pub fn loops(&self) -> Vec<Loop> {
+        let idom = self.dominators();
+        let dominates = |dom: BlockId, mut block: BlockId| loop {
+            if block == dom {
+                break true;
+            }
+            match idom[block] {
+                Some(up) => block = up,
+                None => break false,
+            }
+        };
+
+        let mut loops: Vec<Loop> = Vec::new();
+        for (tail, block) in self.blocks.iter().enumerate() {
+            for &header in &block.succs {
+                if !dominates(header, tail) {
+                    continue;
+                }
+                // Everything that reaches the back edge without going through the header.
+                let mut blocks = BTreeSet::from([header]);
+                let mut stack = vec![tail];
+                while let Some(block) = stack.pop() {
+                    let reachable = block == 0 || idom[block].is_some();
+                    if reachable && blocks.insert(block) {
+                        stack.extend(self.preds(block));
+                    }
+                }
+                match loops.iter_mut().find(|other| other.header == header) {
+                    Some(other) => other.blocks.extend(blocks),
+                    None => loops.push(Loop {
+                        header,
+                        blocks,
+                        parent: None,
+                    }),
+                }
+            }
+        }
+
+        loops.sort_by_key(|lp| std::cmp::Reverse(lp.blocks.len()));
+        for inner in 0..loops.len() {
+            loops[inner].parent = (0..inner).rev().find(|outer| {
+                loops[*outer].blocks.contains(&loops[inner].header)
+                    && loops[*outer].header != loops[inner].header
+            });
+        }
+        loops
+    }
+
+    /// Number of loops each block is part of.
+    pub fn loop_depth(&self) -> Vec<usize> {
+        let mut depth = vec![0; self.blocks.len()];
+        for lp in self.loops() {
+            for block in lp.blocks {
+                depth[block] += 1;
+            }
+        }
+        depth
+    }
+
+    /// Graphviz rendering with the instruction names of each block.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph cfg {\n    node [shape=box];\n");
+        for (id, block) in self.blocks.iter().enumerate() {
+            let _ = write!(out, "    b{id} [label=\"b{id}\\l");
+            for instr in &block.instrs {
+                let _ = write!(out, "{}\\l", instr.name());
+            }
+            if id == self.exit {
+                out.push_str("exit\\l");
+            }
+            out.push_str("\"];\n");
+        }
+        for (id, block) in self.blocks.iter().enumerate() {
+            for succ in &block.succs {
+                let _ = writeln!(out, "    b{id} -> b{succ};");
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cfg;
+    use crate::modules::Module;
+    use crate::Parse;
+
+    #[test]
+    fn cfg() {
+        let module = Module::parse(
+            r#"(module
+                (func (param i32) (result i32)
+                    (block $done
+                        (loop $outer
+                            (loop $inner
+                                (br_if $inner (local.get 0))
+                                (br_if $done (i32.eqz (local.get 0))))
+                            (br $outer)))
+                    (if (local.get 0) (then (return (i32.const 1))))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        let cfg = Cfg::new(&module.codes()[0].1 .1);
+
+        // Every block but the exit can reach the exit, and the exit has no successors.
+        assert!(cfg.blocks[cfg.exit].succs.is_empty());
+        assert_eq!(cfg.preds(cfg.exit).len(), 2);
+        let idom = cfg.dominators();
+        assert_eq!(idom[0], None);
+        assert!((1..cfg.blocks.len())
+            .filter(|block| idom[*block].is_some())
+            .all(|block| cfg.dominates(0, block)));
+
+        let loops = cfg.loops();
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].parent, None);
+        assert_eq!(loops[1].parent, Some(0));
+        assert!(loops[1].blocks.is_subset(&loops[0].blocks));
+        assert!(cfg.dominates(loops[0].header, loops[1].header));
+        let depth = cfg.loop_depth();
+        assert_eq!(depth[loops[1].header], 2);
+        assert_eq!(depth[cfg.exit], 0);
+
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph cfg {"));
+        assert!(dot.contains("br_if\\l"));
+        assert!(dot.contains(&format!("b{} [label=\"b{0}\\lexit\\l\"];", cfg.exit)));
+    }
+}
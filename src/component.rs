@@ -0,0 +1,710 @@
+use std::backtrace::Backtrace;
+
+use crate::instructions::read_byte;
+use crate::modules::{CustomSec, Import, ImportDesc, Module};
+use crate::types::RecType;
+use crate::{Buffer, Error, Parse, IB};
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::InvalidComponent(Backtrace::capture(), msg.into())
+}
+
+/// `0x00` for absent, `0x01` followed by the value for present.
+fn optional<T: for<'a> Parse<&'a mut IB>>(data: &mut IB) -> Result<Option<T>, Error> {
+    match read_byte(data)? {
+        0x00 => Ok(None),
+        0x01 => Ok(Some(T::parse(data)?)),
+        byte => Err(invalid(format!("invalid optional flag {byte:#x}"))),
+    }
+}
+
+/// A component binary: the preamble and its sections in order.
+#[derive(Debug)]
+pub struct Component {
+    pub version: u16,
+    pub layer: u16,
+    pub sections: Vec<ComponentSection>,
+}
+
+#[derive(Debug)]
+pub enum ComponentSection {
+    Custom(CustomSec),
+    CoreModule(Module),
+    CoreInstance(Vec<CoreInstance>),
+    CoreType(Vec<CoreType>),
+    Component(Component),
+    Instance(Vec<Instance>),
+    Alias(Vec<Alias>),
+    Type(Vec<DefType>),
+    Canon(Vec<Canon>),
+    Start(Start),
+    Import(Vec<ComponentImport>),
+    Export(Vec<ComponentExport>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoreSort {
+    Func,
+    Table,
+    Memory,
+    Global,
+    Tag,
+    Type,
+    Module,
+    Instance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoreSortIdx(pub CoreSort, pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    Core(CoreSort),
+    Func,
+    Value,
+    Type,
+    Component,
+    Instance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortIdx(pub Sort, pub u32);
+
+/// Import or export name and its optional version suffix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternName(pub String, pub Option<String>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoreInstance {
+    /// Module index and the instances providing its imports, by module name.
+    Instantiate(u32, Vec<(String, u32)>),
+    FromExports(Vec<(String, CoreSortIdx)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instance {
+    Instantiate(u32, Vec<(String, SortIdx)>),
+    FromExports(Vec<(ExternName, SortIdx)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alias(pub Sort, pub AliasTarget);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AliasTarget {
+    Export(u32, String),
+    CoreExport(u32, String),
+    /// Enclosing component count and index in that component.
+    Outer(u32, u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoreType {
+    Rec(RecType),
+    Module(Vec<ModuleDecl>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModuleDecl {
+    Import(Import),
+    Type(CoreType),
+    /// Outer alias: sort, enclosing count and index.
+    Alias(CoreSort, u32, u32),
+    Export(String, ImportDesc),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimValType {
+    Bool,
+    S8,
+    U8,
+    S16,
+    U16,
+    S32,
+    U32,
+    S64,
+    U64,
+    F32,
+    F64,
+    Char,
+    String,
+    ErrorContext,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentValType {
+    Primitive(PrimValType),
+    Type(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefValType {
+    Primitive(PrimValType),
+    Record(Vec<(String, ComponentValType)>),
+    Variant(Vec<(String, Option<ComponentValType>)>),
+    List(ComponentValType),
+    Tuple(Vec<ComponentValType>),
+    Flags(Vec<String>),
+    Enum(Vec<String>),
+    Option(ComponentValType),
+    Result(Option<ComponentValType>, Option<ComponentValType>),
+    Own(u32),
+    Borrow(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Results {
+    Unnamed(ComponentValType),
+    Named(Vec<(String, ComponentValType)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentFuncType(pub Vec<(String, ComponentValType)>, pub Results);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefType {
+    DefVal(DefValType),
+    Func(ComponentFuncType),
+    Component(Vec<ComponentDecl>),
+    Instance(Vec<InstanceDecl>),
+    /// Resource with an `i32` representation and optional destructor.
+    Resource(Option<u32>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComponentDecl {
+    Import(ComponentImport),
+    Instance(InstanceDecl),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstanceDecl {
+    CoreType(CoreType),
+    Type(DefType),
+    Alias(Alias),
+    Export(ExternName, ExternDesc),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExternDesc {
+    Module(u32),
+    Func(u32),
+    Value(ValueBound),
+    Type(TypeBound),
+    Component(u32),
+    Instance(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueBound {
+    Eq(u32),
+    Type(ComponentValType),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeBound {
+    Eq(u32),
+    SubResource,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Canon {
+    /// Core function, options and component function type.
+    Lift(u32, Vec<CanonOpt>, u32),
+    Lower(u32, Vec<CanonOpt>),
+    ResourceNew(u32),
+    ResourceDrop(u32),
+    ResourceRep(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CanonOpt {
+    Utf8,
+    Utf16,
+    CompactUtf16,
+    Memory(u32),
+    Realloc(u32),
+    PostReturn(u32),
+    Async,
+    Callback(u32),
+}
+
+/// Function, value arguments and number of results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Start(pub u32, pub Vec<u32>, pub u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentImport(pub ExternName, pub ExternDesc);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentExport(pub ExternName, pub SortIdx, pub Option<ExternDesc>);
+
+impl Component {
+    /// Whether `data` starts with the preamble of a component rather than a core module.
+    pub fn is_component(data: &[u8]) -> bool {
+        data.len() >= 8 && data[..4] == *b"\0asm" && data[6..8] == [1, 0]
+    }
+}
+
+impl Parse<&mut IB> for Component {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        if data.len() < 8 {
+            return Err(Error::EndOfBuffer(Backtrace::capture()));
+        }
+        if !Self::is_component(data) {
+            return Err(invalid("not a component"));
+        }
+        let preamble: Vec<u8> = data.drain(..8).collect();
+        let version = u16::from_le_bytes([preamble[4], preamble[5]]);
+        let layer = u16::from_le_bytes([preamble[6], preamble[7]]);
+
+        let mut sections = Vec::new();
+        while !data.is_empty() {
+            sections.push(ComponentSection::parse(data)?);
+        }
+        Ok(Self {
+            version,
+            layer,
+            sections,
+        })
+    }
+}
+
+impl Parse<&mut IB> for ComponentSection {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        let id = read_byte(data)?;
+        let size = u32::parse(data)? as usize;
+        if size > data.len() {
+            return Err(Error::EndOfBuffer(Backtrace::capture()));
+        }
+        let mut content: IB = data.drain(..size).collect();
+        let section = match id {
+            0 => {
+                let name = String::parse(&mut content)?;
+                Self::Custom(CustomSec(name, std::mem::take(&mut content)))
+            }
+            1 => Self::CoreModule(Module::parse(&mut content)?),
+            2 => Self::CoreInstance(Vec::parse(&mut content)?),
+            3 => Self::CoreType(Vec::parse(&mut content)?),
+            4 => Self::Component(Component::parse(&mut content)?),
+            5 => Self::Instance(Vec::parse(&mut content)?),
+            6 => Self::Alias(Vec::parse(&mut content)?),
+            7 => Self::Type(Vec::parse(&mut content)?),
+            8 => Self::Canon(Vec::parse(&mut content)?),
+            9 => Self::Start(Start::parse(&mut content)?),
+            10 => Self::Import(Vec::parse(&mut content)?),
+            11 => Self::Export(Vec::parse(&mut content)?),
+            _ => return Err(invalid(format!("unknown section {id}"))),
+        };
+        if !content.is_empty() {
+            return Err(invalid(format!("section {id} size mismatch")));
+        }
+        Ok(section)
+    }
+}
+
+impl Parse<&mut IB> for CoreSort {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => Self::Func,
+            0x01 => Self::Table,
+            0x02 => Self::Memory,
+            0x03 => Self::Global,
+            0x04 => Self::Tag,
+            0x10 => Self::Type,
+            0x11 => Self::Module,
+            0x12 => Self::Instance,
+            byte => return Err(invalid(format!("invalid core sort {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for CoreSortIdx {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(Self(CoreSort::parse(data)?, u32::parse(data)?))
+    }
+}
+
+impl Parse<&mut IB> for Sort {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => Self::Core(CoreSort::parse(data)?),
+            0x01 => Self::Func,
+            0x02 => Self::Value,
+            0x03 => Self::Type,
+            0x04 => Self::Component,
+            0x05 => Self::Instance,
+            byte => return Err(invalid(format!("invalid sort {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for SortIdx {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(Self(Sort::parse(data)?, u32::parse(data)?))
+    }
+}
+
+impl Parse<&mut IB> for ExternName {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => Self(String::parse(data)?, None),
+            0x01 => Self(String::parse(data)?, Some(String::parse(data)?)),
+            byte => return Err(invalid(format!("invalid name kind {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for CoreInstance {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => {
+                let module = u32::parse(data)?;
+                let mut args = Vec::new();
+                for _ in 0..u32::parse(data)? {
+                    let name = String::parse(data)?;
+                    if read_byte(data)? != 0x12 {
+                        return Err(invalid("core instantiate argument must be an instance"));
+                    }
+                    args.push((name, u32::parse(data)?));
+                }
+                Self::Instantiate(module, args)
+            }
+            0x01 => Self::FromExports(Vec::parse(&mut *data)?),
+            byte => return Err(invalid(format!("invalid core instance {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for Instance {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => Self::Instantiate(u32::parse(data)?, Vec::parse(&mut *data)?),
+            0x01 => Self::FromExports(Vec::parse(&mut *data)?),
+            byte => return Err(invalid(format!("invalid instance {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for Alias {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        let sort = Sort::parse(data)?;
+        let target = match read_byte(data)? {
+            0x00 => AliasTarget::Export(u32::parse(data)?, String::parse(data)?),
+            0x01 => AliasTarget::CoreExport(u32::parse(data)?, String::parse(data)?),
+            0x02 => AliasTarget::Outer(u32::parse(data)?, u32::parse(data)?),
+            byte => return Err(invalid(format!("invalid alias target {byte:#x}"))),
+        };
+        Ok(Self(sort, target))
+    }
+}
+
+impl Parse<&mut IB> for CoreType {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        match data.first() {
+            Some(0x50) => {
+                data.drain(..1);
+                Ok(Self::Module(Vec::parse(&mut *data)?))
+            }
+            _ => Ok(Self::Rec(RecType::parse(data)?)),
+        }
+    }
+}
+
+impl Parse<&mut IB> for ModuleDecl {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => Self::Import(Import::parse(data)?),
+            0x01 => Self::Type(CoreType::parse(data)?),
+            0x02 => {
+                let sort = CoreSort::parse(data)?;
+                if read_byte(data)? != 0x01 {
+                    return Err(invalid("module type aliases must be outer aliases"));
+                }
+                Self::Alias(sort, u32::parse(data)?, u32::parse(data)?)
+            }
+            0x03 => Self::Export(String::parse(data)?, ImportDesc::parse(data)?),
+            byte => return Err(invalid(format!("invalid module declaration {byte:#x}"))),
+        })
+    }
+}
+
+impl PrimValType {
+    fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            0x7F => Self::Bool,
+            0x7E => Self::S8,
+            0x7D => Self::U8,
+            0x7C => Self::S16,
+            0x7B => Self::U16,
+            0x7A => Self::S32,
+            0x79 => Self::U32,
+            0x78 => Self::S64,
+            0x77 => Self::U64,
+            0x76 => Self::F32,
+            0x75 => Self::F64,
+            0x74 => Self::Char,
+            0x73 => Self::String,
+            0x64 => Self::ErrorContext,
+            _ => return None,
+        })
+    }
+}
+
+impl Parse<&mut IB> for ComponentValType {
+    /// Type indices and primitive codes share an s33 encoding, primitives
+    /// being the negative values.
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        let value = data.read_sleb128(33);
+        if value >= 0 {
+            return Ok(Self::Type(value as u32));
+        }
+        let code = value as u8 & 0x7F;
+        PrimValType::from_code(code)
+            .map(Self::Primitive)
+            .ok_or_else(|| invalid(format!("invalid value type {code:#x}")))
+    }
+}
+
+impl Parse<&mut IB> for DefType {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        let byte = *data
+            .first()
+            .ok_or_else(|| Error::EndOfBuffer(Backtrace::capture()))?;
+        if let Some(prim) = PrimValType::from_code(byte) {
+            data.drain(..1);
+            return Ok(Self::DefVal(DefValType::Primitive(prim)));
+        }
+        data.drain(..1);
+        Ok(match byte {
+            0x72 => Self::DefVal(DefValType::Record(Vec::parse(&mut *data)?)),
+            0x71 => {
+                let mut cases = Vec::new();
+                for _ in 0..u32::parse(data)? {
+                    let label = String::parse(data)?;
+                    let ty = optional(data)?;
+                    if read_byte(data)? != 0x00 {
+                        return Err(invalid("variant cases cannot refine other cases"));
+                    }
+                    cases.push((label, ty));
+                }
+                Self::DefVal(DefValType::Variant(cases))
+            }
+            0x70 => Self::DefVal(DefValType::List(ComponentValType::parse(data)?)),
+            0x6F => Self::DefVal(DefValType::Tuple(Vec::parse(&mut *data)?)),
+            0x6E => Self::DefVal(DefValType::Flags(Vec::parse(&mut *data)?)),
+            0x6D => Self::DefVal(DefValType::Enum(Vec::parse(&mut *data)?)),
+            0x6B => Self::DefVal(DefValType::Option(ComponentValType::parse(data)?)),
+            0x6A => Self::DefVal(DefValType::Result(optional(data)?, optional(data)?)),
+            0x69 => Self::DefVal(DefValType::Own(u32::parse(data)?)),
+            0x68 => Self::DefVal(DefValType::Borrow(u32::parse(data)?)),
+            0x40 => {
+                let params = Vec::parse(&mut *data)?;
+                let results = match read_byte(data)? {
+                    0x00 => Results::Unnamed(ComponentValType::parse(data)?),
+                    0x01 => Results::Named(Vec::parse(&mut *data)?),
+                    byte => return Err(invalid(format!("invalid result list {byte:#x}"))),
+                };
+                Self::Func(ComponentFuncType(params, results))
+            }
+            0x41 => Self::Component(Vec::parse(&mut *data)?),
+            0x42 => Self::Instance(Vec::parse(&mut *data)?),
+            0x3F => {
+                if read_byte(data)? != 0x7F {
+                    return Err(invalid("resource representation must be i32"));
+                }
+                Self::Resource(optional(data)?)
+            }
+            _ => return Err(invalid(format!("invalid type {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for ComponentDecl {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        match data.first() {
+            Some(0x03) => {
+                data.drain(..1);
+                Ok(Self::Import(ComponentImport::parse(data)?))
+            }
+            _ => Ok(Self::Instance(InstanceDecl::parse(data)?)),
+        }
+    }
+}
+
+impl Parse<&mut IB> for InstanceDecl {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => Self::CoreType(CoreType::parse(data)?),
+            0x01 => Self::Type(DefType::parse(data)?),
+            0x02 => Self::Alias(Alias::parse(data)?),
+            0x04 => Self::Export(ExternName::parse(data)?, ExternDesc::parse(data)?),
+            byte => return Err(invalid(format!("invalid declaration {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for ExternDesc {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => {
+                if read_byte(data)? != 0x11 {
+                    return Err(invalid("core extern must be a module"));
+                }
+                Self::Module(u32::parse(data)?)
+            }
+            0x01 => Self::Func(u32::parse(data)?),
+            0x02 => Self::Value(match read_byte(data)? {
+                0x00 => ValueBound::Eq(u32::parse(data)?),
+                0x01 => ValueBound::Type(ComponentValType::parse(data)?),
+                byte => return Err(invalid(format!("invalid value bound {byte:#x}"))),
+            }),
+            0x03 => Self::Type(match read_byte(data)? {
+                0x00 => TypeBound::Eq(u32::parse(data)?),
+                0x01 => TypeBound::SubResource,
+                byte => return Err(invalid(format!("invalid type bound {byte:#x}"))),
+            }),
+            0x04 => Self::Component(u32::parse(data)?),
+            0x05 => Self::Instance(u32::parse(data)?),
+            byte => return Err(invalid(format!("invalid extern kind {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for Canon {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => {
+                if read_byte(data)? != 0x00 {
+                    return Err(invalid("canon lift must lift a core function"));
+                }
+                Self::Lift(
+                    u32::parse(data)?,
+                    Vec::parse(&mut *data)?,
+                    u32::parse(data)?,
+                )
+            }
+            0x01 => {
+                if read_byte(data)? != 0x00 {
+                    return Err(invalid("canon lower must lower a function"));
+                }
+                Self::Lower(u32::parse(data)?, Vec::parse(&mut *data)?)
+            }
+            0x02 => Self::ResourceNew(u32::parse(data)?),
+            0x03 => Self::ResourceDrop(u32::parse(data)?),
+            0x04 => Self::ResourceRep(u32::parse(data)?),
+            byte => return Err(invalid(format!("invalid canonical function {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for CanonOpt {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(data)? {
+            0x00 => Self::Utf8,
+            0x01 => Self::Utf16,
+            0x02 => Self::CompactUtf16,
+            0x03 => Self::Memory(u32::parse(data)?),
+            0x04 => Self::Realloc(u32::parse(data)?),
+            0x05 => Self::PostReturn(u32::parse(data)?),
+            0x06 => Self::Async,
+            0x07 => Self::Callback(u32::parse(data)?),
+            byte => return Err(invalid(format!("invalid canonical option {byte:#x}"))),
+        })
+    }
+}
+
+impl Parse<&mut IB> for Start {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(Self(
+            u32::parse(data)?,
+            Vec::parse(&mut *data)?,
+            u32::parse(data)?,
+        ))
+    }
+}
+
+impl Parse<&mut IB> for ComponentImport {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(Self(ExternName::parse(data)?, ExternDesc::parse(data)?))
+    }
+}
+
+impl Parse<&mut IB> for ComponentExport {
+    fn parse(data: &mut IB) -> Result<Self, Error> {
+        Ok(Self(
+            ExternName::parse(data)?,
+            SortIdx::parse(data)?,
+            optional(data)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_component() {
+        let preamble = [0x00, 0x61, 0x73, 0x6D, 0x0D, 0x00, 0x01, 0x00];
+        let mut data = preamble.to_vec();
+        // Core module with an empty body.
+        data.extend([0x01, 0x08, 0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00]);
+        // (type (record (field "x" u32))) (type (func (param "a" u32) (result string)))
+        data.extend([0x07, 0x0D, 0x02, 0x72, 0x01, 0x01, b'x', 0x79]);
+        data.extend([0x40, 0x01, 0x01, b'a', 0x79, 0x00, 0x73]);
+        // (import "f" (func (type 1)))
+        data.extend([0x0A, 0x06, 0x01, 0x00, 0x01, b'f', 0x01, 0x01]);
+        // (export "g" (func 0))
+        data.extend([0x0B, 0x07, 0x01, 0x00, 0x01, b'g', 0x01, 0x00, 0x00]);
+        // Empty nested component.
+        data.extend([0x04, 0x08]);
+        data.extend(preamble);
+
+        assert!(Component::is_component(&data));
+        let component = Component::parse(&mut data).unwrap();
+        assert_eq!((component.version, component.layer), (0x0D, 1));
+        let [ComponentSection::CoreModule(module), ComponentSection::Type(types), ComponentSection::Import(imports), ComponentSection::Export(exports), ComponentSection::Component(nested)] =
+            &component.sections[..]
+        else {
+            panic!("{:?}", component.sections);
+        };
+        assert!(module.sections.is_empty());
+        assert_eq!(
+            types[..],
+            [
+                DefType::DefVal(DefValType::Record(vec![(
+                    "x".to_string(),
+                    ComponentValType::Primitive(PrimValType::U32)
+                )])),
+                DefType::Func(ComponentFuncType(
+                    vec![(
+                        "a".to_string(),
+                        ComponentValType::Primitive(PrimValType::U32)
+                    )],
+                    Results::Unnamed(ComponentValType::Primitive(PrimValType::String))
+                )),
+            ]
+        );
+        assert_eq!(
+            imports[..],
+            [ComponentImport(
+                ExternName("f".to_string(), None),
+                ExternDesc::Func(1)
+            )]
+        );
+        assert_eq!(
+            exports[..],
+            [ComponentExport(
+                ExternName("g".to_string(), None),
+                SortIdx(Sort::Func, 0),
+                None
+            )]
+        );
+        assert!(nested.sections.is_empty());
+
+        let mut module = vec![0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+        assert!(!Component::is_component(&module));
+        assert!(Component::parse(&mut module).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::modules::{CustomPayload, CustomSec, DecodedCustomSec, Section};
+use crate::{Encode, Error, Parse, IB};
+
+type Decoder = Box<dyn Fn(&CustomSec) -> Result<Box<dyn CustomPayload>, Error>>;
+
+/// Decoders for custom sections, keyed by section name.
+/// Sections without a registered decoder stay as raw `Section::Custom`.
+#[derive(Default)]
+pub struct CustomRegistry {
+    decoders: HashMap<String, Decoder>,
+}
+
+impl CustomRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T>(&mut self, name: impl Into<String>)
+    where
+        T: for<'a> Parse<&'a CustomSec> + Debug + 'static,
+    {
+        self.register_fn(name, |sec| T::parse(sec))
+    }
+
+    pub fn register_fn<T, F>(&mut self, name: impl Into<String>, decoder: F)
+    where
+        T: Debug + 'static,
+        F: Fn(&CustomSec) -> Result<T, Error> + 'static,
+    {
+        self.decoders.insert(
+            name.into(),
+            Box::new(move |sec| Ok(Box::new(decoder(sec)?) as Box<dyn CustomPayload>)),
+        );
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.decoders.contains_key(name)
+    }
+
+    /// A section its decoder rejects stays raw, returned with the error.
+    pub fn decode(&self, sec: CustomSec) -> (Section, Option<Error>) {
+        match self.decoders.get(&sec.0) {
+            Some(decoder) => match decoder(&sec) {
+                Ok(payload) => (
+                    Section::DecodedCustom(DecodedCustomSec { sec, payload }),
+                    None,
+                ),
+                Err(err) => (Section::Custom(sec), Some(err)),
+            },
+            None => (Section::Custom(sec), None),
+        }
+    }
+}
+
+pub type NameMap = Vec<(u32, String)>;
+pub type IndirectNameMap = Vec<(u32, NameMap)>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NameSec {
+    pub module: Option<String>,
+    pub funcs: NameMap,
+    pub locals: IndirectNameMap,
+    pub labels: IndirectNameMap,
+    pub types: NameMap,
+    pub tables: NameMap,
+    pub mems: NameMap,
+    pub globals: NameMap,
+    pub elems: NameMap,
+    pub datas: NameMap,
+    pub fields: IndirectNameMap,
+    pub tags: NameMap,
+}
+
+impl NameSec {
+    pub const NAME: &'static str = "name";
+}
+
+impl Parse<&CustomSec> for NameSec {
+    fn parse(sec: &CustomSec) -> Result<Self, Error> {
+        let mut data = payload(sec, Self::NAME)?;
+        let mut names = Self::default();
+        while !data.is_empty() {
+            let id = data.drain(..1).next().unwrap();
+            let size = u32::parse(&mut data)? as usize;
+            if data.len() < size {
+                return Err(Error::EndOfBuffer(Backtrace::capture()));
+            }
+            let mut sub: IB = data.drain(..size).collect();
+            match id {
+                0 => names.module = Some(String::parse(&mut sub)?),
+                1 => names.funcs = NameMap::parse(&mut sub)?,
+                2 => names.locals = IndirectNameMap::parse(&mut sub)?,
+                3 => names.labels = IndirectNameMap::parse(&mut sub)?,
+                4 => names.types = NameMap::parse(&mut sub)?,
+                5 => names.tables = NameMap::parse(&mut sub)?,
+                6 => names.mems = NameMap::parse(&mut sub)?,
+                7 => names.globals = NameMap::parse(&mut sub)?,
+                8 => names.elems = NameMap::parse(&mut sub)?,
+                9 => names.datas = NameMap::parse(&mut sub)?,
+                10 => names.fields = IndirectNameMap::parse(&mut sub)?,
+                11 => names.tags = NameMap::parse(&mut sub)?,
+                _ => {}
+            }
+        }
+        Ok(names)
+    }
+}
+
+impl From<&NameSec> for CustomSec {
+    fn from(value: &NameSec) -> Self {
+        fn sub<T: Encode>(data: &mut IB, id: u8, names: &Vec<T>) {
+            if names.is_empty() {
+                return;
+            }
+            let mut content = IB::new();
+            names.encode(&mut content);
+            data.push(id);
+            (content.len() as u32).encode(data);
+            data.extend(content);
+        }
+
+        let mut data = IB::new();
+        if let Some(module) = &value.module {
+            let mut content = IB::new();
+            module.encode(&mut content);
+            data.push(0);
+            (content.len() as u32).encode(&mut data);
+            data.extend(content);
+        }
+        sub(&mut data, 1, &value.funcs);
+        sub(&mut data, 2, &value.locals);
+        sub(&mut data, 3, &value.labels);
+        sub(&mut data, 4, &value.types);
+        sub(&mut data, 5, &value.tables);
+        sub(&mut data, 6, &value.mems);
+        sub(&mut data, 7, &value.globals);
+        sub(&mut data, 8, &value.elems);
+        sub(&mut data, 9, &value.datas);
+        sub(&mut data, 10, &value.fields);
+        sub(&mut data, 11, &value.tags);
+        CustomSec(NameSec::NAME.to_string(), data)
+    }
+}
+
+impl Encode for NameSec {
+    fn encode(&self, data: &mut IB) {
+        CustomSec::from(self).encode(data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMappingUrl(pub String);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalDebugInfo(pub String);
+
+impl SourceMappingUrl {
+    pub const NAME: &'static str = "sourceMappingURL";
+}
+
+impl ExternalDebugInfo {
+    pub const NAME: &'static str = "external_debug_info";
+}
+
+fn payload(sec: &CustomSec, name: &str) -> Result<IB, Error> {
+    if sec.0 != name {
+        return Err(Error::InvalidCustomSec(Backtrace::capture(), sec.0.clone()));
+    }
+    Ok(sec.1.clone())
+}
+
+impl Parse<&CustomSec> for SourceMappingUrl {
+    fn parse(sec: &CustomSec) -> Result<Self, Error> {
+        let mut data = payload(sec, Self::NAME)?;
+        Ok(Self(String::parse(&mut data)?))
+    }
+}
+
+impl Parse<&CustomSec> for ExternalDebugInfo {
+    fn parse(sec: &CustomSec) -> Result<Self, Error> {
+        let mut data = payload(sec, Self::NAME)?;
+        Ok(Self(String::parse(&mut data)?))
+    }
+}
+
+impl Encode for SourceMappingUrl {
+    fn encode(&self, data: &mut IB) {
+        CustomSec::from(self).encode(data)
+    }
+}
+
+impl Encode for ExternalDebugInfo {
+    fn encode(&self, data: &mut IB) {
+        CustomSec::from(self).encode(data)
+    }
+}
+
+impl From<&SourceMappingUrl> for CustomSec {
+    fn from(value: &SourceMappingUrl) -> Self {
+        let mut data = IB::new();
+        value.0.encode(&mut data);
+        CustomSec(SourceMappingUrl::NAME.to_string(), data)
+    }
+}
+
+impl From<&ExternalDebugInfo> for CustomSec {
+    fn from(value: &ExternalDebugInfo) -> Self {
+        let mut data = IB::new();
+        value.0.encode(&mut data);
+        CustomSec(ExternalDebugInfo::NAME.to_string(), data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::backtrace::Backtrace;
+
+    use super::{CustomRegistry, SourceMappingUrl};
+    use crate::modules::{CustomSec, Module, Section};
+    use crate::{Encode, Error, Parse};
+
+    #[derive(Debug, PartialEq)]
+    struct Build(String);
+
+    #[test]
+    fn decode() {
+        let mut registry = CustomRegistry::new();
+        registry.register_fn("build", |sec| {
+            String::from_utf8(sec.1.clone())
+                .map(Build)
+                .map_err(|err| Error::InvalidCustomSec(Backtrace::capture(), err.to_string()))
+        });
+        registry.register::<SourceMappingUrl>(SourceMappingUrl::NAME);
+
+        let mut bytes = vec![0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+        for (i, sec) in [
+            CustomSec("build".to_string(), b"release".to_vec()),
+            CustomSec::from(&SourceMappingUrl("main.wasm.map".to_string())),
+            CustomSec("other".to_string(), vec![1, 2, 3]),
+            // Kept raw rather than failing the module.
+            CustomSec("build".to_string(), vec![0xff]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut content = Vec::new();
+            sec.encode(&mut content);
+            bytes.push(0);
+            (content.len() as u32).encode(&mut bytes);
+            bytes.extend(content);
+            if i == 0 {
+                bytes.extend([1, 1, 0]);
+            }
+        }
+
+        let module = Module::parse((&mut bytes, &registry)).unwrap();
+        assert_eq!(module.sections.len(), 5);
+        assert!(matches!(module.sections[0], Section::DecodedCustom(_)));
+        assert!(matches!(module.sections[1], Section::Type(_)));
+        assert!(matches!(module.sections[3], Section::Custom(_)));
+        assert!(matches!(module.sections[4], Section::Custom(_)));
+        assert_eq!(module.custom_errors.len(), 1);
+        assert_eq!(module.custom_errors[0].0, "build");
+        assert_eq!(
+            module.custom_payload::<Build>("build"),
+            Some(&Build("release".to_string()))
+        );
+        assert_eq!(
+            module.custom_payload::<SourceMappingUrl>(SourceMappingUrl::NAME),
+            Some(&SourceMappingUrl("main.wasm.map".to_string()))
+        );
+        assert_eq!(module.custom_sec("other").unwrap().1, vec![1, 2, 3]);
+    }
+}
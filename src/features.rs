@@ -0,0 +1,629 @@
+use std::backtrace::Backtrace;
+
+use crate::instructions::{BlockType, Instr};
+use crate::modules::*;
+use crate::types::*;
+use crate::Error;
+
+/// A proposal that extends the 1.0 specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    MutableGlobal,
+    SignExtension,
+    SaturatingFloatToInt,
+    MultiValue,
+    BulkMemory,
+    ReferenceTypes,
+    Simd,
+    RelaxedSimd,
+    Threads,
+    TailCall,
+    Exceptions,
+    FunctionReferences,
+    Gc,
+    Memory64,
+    MultiMemory,
+    ExtendedConst,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 16] = [
+        Self::MutableGlobal,
+        Self::SignExtension,
+        Self::SaturatingFloatToInt,
+        Self::MultiValue,
+        Self::BulkMemory,
+        Self::ReferenceTypes,
+        Self::Simd,
+        Self::RelaxedSimd,
+        Self::Threads,
+        Self::TailCall,
+        Self::Exceptions,
+        Self::FunctionReferences,
+        Self::Gc,
+        Self::Memory64,
+        Self::MultiMemory,
+        Self::ExtendedConst,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MutableGlobal => "mutable-global",
+            Self::SignExtension => "sign-extension",
+            Self::SaturatingFloatToInt => "saturating-float-to-int",
+            Self::MultiValue => "multi-value",
+            Self::BulkMemory => "bulk-memory",
+            Self::ReferenceTypes => "reference-types",
+            Self::Simd => "simd",
+            Self::RelaxedSimd => "relaxed-simd",
+            Self::Threads => "threads",
+            Self::TailCall => "tail-call",
+            Self::Exceptions => "exceptions",
+            Self::FunctionReferences => "function-references",
+            Self::Gc => "gc",
+            Self::Memory64 => "memory64",
+            Self::MultiMemory => "multi-memory",
+            Self::ExtendedConst => "extended-const",
+        }
+    }
+}
+
+/// Proposals a module may use, everything is enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmFeatures {
+    pub mutable_global: bool,
+    pub sign_extension: bool,
+    pub saturating_float_to_int: bool,
+    pub multi_value: bool,
+    pub bulk_memory: bool,
+    pub reference_types: bool,
+    pub simd: bool,
+    pub relaxed_simd: bool,
+    pub threads: bool,
+    pub tail_call: bool,
+    pub exceptions: bool,
+    pub function_references: bool,
+    pub gc: bool,
+    pub memory64: bool,
+    pub multi_memory: bool,
+    pub extended_const: bool,
+}
+
+impl Default for WasmFeatures {
+    fn default() -> Self {
+        let mut features = Self::mvp();
+        for feature in Feature::ALL {
+            features.set(feature, true);
+        }
+        features
+    }
+}
+
+impl WasmFeatures {
+    /// The 1.0 specification without any proposal.
+    pub fn mvp() -> Self {
+        Self {
+            mutable_global: false,
+            sign_extension: false,
+            saturating_float_to_int: false,
+            multi_value: false,
+            bulk_memory: false,
+            reference_types: false,
+            simd: false,
+            relaxed_simd: false,
+            threads: false,
+            tail_call: false,
+            exceptions: false,
+            function_references: false,
+            gc: false,
+            memory64: false,
+            multi_memory: false,
+            extended_const: false,
+        }
+    }
+
+    fn flag(&mut self, feature: Feature) -> &mut bool {
+        match feature {
+            Feature::MutableGlobal => &mut self.mutable_global,
+            Feature::SignExtension => &mut self.sign_extension,
+            Feature::SaturatingFloatToInt => &mut self.saturating_float_to_int,
+            Feature::MultiValue => &mut self.multi_value,
+            Feature::BulkMemory => &mut self.bulk_memory,
+            Feature::ReferenceTypes => &mut self.reference_types,
+            Feature::Simd => &mut self.simd,
+            Feature::RelaxedSimd => &mut self.relaxed_simd,
+            Feature::Threads => &mut self.threads,
+            Feature::TailCall => &mut self.tail_call,
+            Feature::Exceptions => &mut self.exceptions,
+            Feature::FunctionReferences => &mut self.function_references,
+            Feature::Gc => &mut self.gc,
+            Feature::Memory64 => &mut self.memory64,
+            Feature::MultiMemory => &mut self.multi_memory,
+            Feature::ExtendedConst => &mut self.extended_const,
+        }
+    }
+
+    pub fn enabled(&self, feature: Feature) -> bool {
+        let mut features = *self;
+        *features.flag(feature)
+    }
+
+    pub fn set(&mut self, feature: Feature, enabled: bool) {
+        *self.flag(feature) = enabled;
+    }
+
+    /// The smallest set of proposals `module` needs, the inverse of `check`.
+    pub fn required(module: &Module) -> Self {
+        let mut features = Self::mvp();
+        // Recording a use cannot fail.
+        let _ = uses(module, &mut |feature, _| {
+            features.set(feature, true);
+            Ok(())
+        });
+        features
+    }
+
+    /// Enabled proposals, in the order of `Feature::ALL`.
+    pub fn features(&self) -> Vec<Feature> {
+        Feature::ALL
+            .into_iter()
+            .filter(|feature| self.enabled(*feature))
+            .collect()
+    }
+
+    /// Rejects modules that use a disabled proposal, naming the proposal and
+    /// the first instruction or type that needs it.
+    pub fn check(&self, module: &Module) -> Result<(), Error> {
+        uses(module, &mut |feature, what| match self.enabled(feature) {
+            true => Ok(()),
+            false => Err(Error::InvalidModule(
+                Backtrace::capture(),
+                format!("{} support is not enabled: {what}", feature.name()),
+            )),
+        })
+    }
+}
+
+type Visitor<'a> = dyn FnMut(Feature, String) -> Result<(), Error> + 'a;
+
+/// Reports every use of a proposal in `module` to `f`.
+fn uses(module: &Module, f: &mut Visitor) -> Result<(), Error> {
+    let types = module.types();
+    for (idx, ty) in types.iter().enumerate() {
+        let what = || format!("type {idx}");
+        if !ty.0 || !ty.1.is_empty() {
+            f(Feature::Gc, what())?;
+        }
+        match &ty.2 {
+            CompType::Func(func) => {
+                if func.1.len() > 1 {
+                    f(Feature::MultiValue, what())?;
+                }
+                for ty in func.0.iter().chain(&func.1) {
+                    val(*ty, f)?;
+                }
+            }
+            CompType::Struct(fields) => {
+                f(Feature::Gc, what())?;
+                for field in fields {
+                    val(field.0.unpacked(), f)?;
+                }
+            }
+            CompType::Array(field) => {
+                f(Feature::Gc, what())?;
+                val(field.0.unpacked(), f)?;
+            }
+        }
+    }
+    for sec in &module.sections {
+        if let Section::Type(recs) = sec {
+            if let Some(rec) = recs.iter().find(|rec| rec.0.len() > 1) {
+                f(Feature::Gc, format!("rec group of {} types", rec.0.len()))?;
+            }
+        }
+    }
+
+    let mut globals = Vec::new();
+    for import in module.imports() {
+        let what = || format!("import {}.{}", import.module, import.name);
+        match &import.desc {
+            ImportDesc::TypeIdx(_) => {}
+            ImportDesc::TableType(table) => table_type(table, f)?,
+            ImportDesc::MemType(mem) => mem_type(mem, f)?,
+            ImportDesc::GlobalType(global) => {
+                if global.0 {
+                    f(Feature::MutableGlobal, what())?;
+                }
+                val(global.1, f)?;
+                globals.push(global);
+            }
+            ImportDesc::TagType(_) => f(Feature::Exceptions, what())?,
+        }
+    }
+    for table in module.tables() {
+        table_type(table, f)?;
+    }
+    for mem in module.mems() {
+        mem_type(mem, f)?;
+    }
+    let tables = module.imported_tables().count() + module.tables().len();
+    if tables > 1 {
+        f(Feature::ReferenceTypes, format!("{tables} tables"))?;
+    }
+    let mems = module.imported_mems().count() + module.mems().len();
+    if mems > 1 {
+        f(Feature::MultiMemory, format!("{mems} memories"))?;
+    }
+    if !module.tags().is_empty() {
+        f(Feature::Exceptions, "tag section".to_string())?;
+    }
+    for (ty, init) in module.globals() {
+        val(ty.1, f)?;
+        const_expr(init, &types, f)?;
+        globals.push(ty);
+    }
+    for (name, desc) in module.exports() {
+        if let ExportDesc::GlobalIdx(idx) = desc {
+            if globals.get(*idx as usize).is_some_and(|global| global.0) {
+                f(Feature::MutableGlobal, format!("export {name}"))?;
+            }
+        }
+    }
+
+    for elem in module.elems() {
+        let (offset, exprs) = match elem {
+            Elem::A(offset, _) => (Some(offset), &[][..]),
+            Elem::B(_, _) | Elem::D(_, _) => (None, &[][..]),
+            Elem::C(_, offset, _, _) => (Some(offset), &[][..]),
+            Elem::E(offset, exprs) => (Some(offset), &exprs[..]),
+            Elem::F(_, exprs) | Elem::H(_, exprs) => (None, &exprs[..]),
+            Elem::G(_, offset, _, exprs) => (Some(offset), &exprs[..]),
+        };
+        match elem {
+            Elem::A(_, _) => {}
+            Elem::B(_, _) | Elem::F(_, _) => {
+                f(Feature::BulkMemory, "passive element segment".to_string())?
+            }
+            _ => f(Feature::ReferenceTypes, "element segment".to_string())?,
+        }
+        if let Elem::F(ty, _) | Elem::G(_, _, ty, _) | Elem::H(ty, _) = elem {
+            reference(*ty, f)?;
+        }
+        for expr in offset.into_iter().chain(exprs) {
+            const_expr(expr, &types, f)?;
+        }
+    }
+    for data in module.datas() {
+        match data {
+            Data::A(offset, _) => const_expr(offset, &types, f)?,
+            Data::B(_) => f(Feature::BulkMemory, "passive data segment".to_string())?,
+            Data::C(mem, offset, _) => {
+                if *mem != 0 {
+                    f(
+                        Feature::MultiMemory,
+                        format!("data segment for memory {mem}"),
+                    )?;
+                }
+                const_expr(offset, &types, f)?;
+            }
+        }
+    }
+    for code in module.codes() {
+        for locals in &code.1 .0 {
+            val(locals.1, f)?;
+        }
+        instrs(&code.1 .1 .0, &types, f)?;
+    }
+    Ok(())
+}
+
+fn val(ty: ValType, f: &mut Visitor) -> Result<(), Error> {
+    match ty {
+        ValType::VecType(_) => f(Feature::Simd, "v128".to_string()),
+        ValType::RefType(ty) => {
+            f(Feature::ReferenceTypes, format!("{ty:?}"))?;
+            reference(ty, f)
+        }
+        ValType::NumType(_) => Ok(()),
+    }
+}
+
+/// Proposals needed by a reference type beyond `funcref` and `externref`.
+fn reference(ty: RefType, f: &mut Visitor) -> Result<(), Error> {
+    let what = || format!("{ty:?}");
+    if !ty.0 || matches!(ty.1, HeapType::Type(_)) {
+        f(Feature::FunctionReferences, what())?;
+    }
+    match ty.1 {
+        HeapType::Func | HeapType::Type(_) => Ok(()),
+        HeapType::Extern => f(Feature::ReferenceTypes, what()),
+        HeapType::Exn | HeapType::NoExn => f(Feature::Exceptions, what()),
+        _ => f(Feature::Gc, what()),
+    }
+}
+
+fn table_type(table: &TableType, f: &mut Visitor) -> Result<(), Error> {
+    reference(table.0, f)?;
+    if table.1 .3 {
+        f(Feature::Memory64, "table with i64 index".to_string())?;
+    }
+    Ok(())
+}
+
+fn mem_type(mem: &MemType, f: &mut Visitor) -> Result<(), Error> {
+    if mem.2 {
+        f(Feature::Threads, "shared memory".to_string())?;
+    }
+    if mem.3 {
+        f(Feature::Memory64, "memory with i64 index".to_string())?;
+    }
+    Ok(())
+}
+
+/// Arithmetic in constant expressions comes from the extended constant
+/// expressions proposal.
+fn const_expr(expr: &Expr, types: &[&SubType], f: &mut Visitor) -> Result<(), Error> {
+    for instr in &expr.0 {
+        if let Instr::I32Add
+        | Instr::I32Sub
+        | Instr::I32Mul
+        | Instr::I64Add
+        | Instr::I64Sub
+        | Instr::I64Mul = instr
+        {
+            f(Feature::ExtendedConst, instr.name().to_string())?;
+        }
+    }
+    instrs(&expr.0, types, f)
+}
+
+/// Whether a block type needs multiple values: block types given by index
+/// only do when the type has parameters or more than one result.
+fn multi_value(ty: &BlockType, types: &[&SubType]) -> bool {
+    match ty {
+        BlockType::X(idx) => {
+            let func = usize::try_from(*idx)
+                .ok()
+                .and_then(|idx| types.get(idx))
+                .and_then(|ty| ty.func());
+            func.is_none_or(|func| !func.0.is_empty() || func.1.len() > 1)
+        }
+        BlockType::Empty | BlockType::ValType(_) => false,
+    }
+}
+
+fn instrs(instrs: &[Instr], types: &[&SubType], f: &mut Visitor) -> Result<(), Error> {
+    for instr in instrs {
+        for feature in instr_features(instr) {
+            f(feature, instr.name().to_string())?;
+        }
+        match instr {
+            Instr::Block(ty, _)
+            | Instr::Loop(ty, _)
+            | Instr::If(ty, _)
+            | Instr::IfElse(ty, _, _)
+            | Instr::Try(ty, _, _, _)
+            | Instr::TryDelegate(ty, _, _)
+            | Instr::TryTable(ty, _, _) => match ty {
+                BlockType::ValType(ty) => val(*ty, f)?,
+                ty if multi_value(ty, types) => {
+                    f(Feature::MultiValue, format!("{} type", instr.name()))?
+                }
+                _ => {}
+            },
+            Instr::RefNull(heap) => reference(RefType(true, *heap), f)?,
+            Instr::SelectType(types) => {
+                for ty in types {
+                    val(*ty, f)?;
+                }
+            }
+            Instr::RefTest(ty) | Instr::RefCast(ty) => reference(*ty, f)?,
+            Instr::BrOnCast(_, from, to) | Instr::BrOnCastFail(_, from, to) => {
+                reference(*from, f)?;
+                reference(*to, f)?;
+            }
+            _ => {}
+        }
+        let mem = match instr {
+            Instr::MemorySize(mem)
+            | Instr::MemoryGrow(mem)
+            | Instr::MemoryFill(mem)
+            | Instr::MemoryInit(_, mem) => *mem,
+            Instr::MemoryCopy(dst, src) => *dst.max(src),
+            instr => instr.memarg().map_or(0, |memarg| memarg.2),
+        };
+        if mem != 0 {
+            f(Feature::MultiMemory, instr.name().to_string())?;
+        }
+        for block in instr.blocks() {
+            self::instrs(block, types, f)?;
+        }
+    }
+    Ok(())
+}
+
+/// Proposals that introduced an instruction.
+fn instr_features(instr: &Instr) -> Vec<Feature> {
+    if instr.is_relaxed_simd() {
+        return vec![Feature::Simd, Feature::RelaxedSimd];
+    }
+    if instr.is_simd() {
+        return vec![Feature::Simd];
+    }
+    if instr.is_atomic() {
+        return vec![Feature::Threads];
+    }
+    match instr {
+        Instr::I32Extend8S
+        | Instr::I32Extend16S
+        | Instr::I64Extend8S
+        | Instr::I64Extend16S
+        | Instr::I64Extend32S => vec![Feature::SignExtension],
+        Instr::I32TruncSatF32S
+        | Instr::I32TruncSatF32U
+        | Instr::I32TruncSatF64S
+        | Instr::I32TruncSatF64U
+        | Instr::I64TruncSatF32S
+        | Instr::I64TruncSatF32U
+        | Instr::I64TructSatF64S
+        | Instr::I64TructSatF64U => vec![Feature::SaturatingFloatToInt],
+        Instr::MemoryInit(_, _)
+        | Instr::DataDrop(_)
+        | Instr::MemoryCopy(_, _)
+        | Instr::MemoryFill(_)
+        | Instr::TableInit(_, _)
+        | Instr::ElemDrop(_)
+        | Instr::TableCopy(_, _) => vec![Feature::BulkMemory],
+        Instr::TableGet(_)
+        | Instr::TableSet(_)
+        | Instr::TableSize(_)
+        | Instr::TableGrow(_)
+        | Instr::TableFill(_)
+        | Instr::RefNull(_)
+        | Instr::RefIsNull
+        | Instr::RefFunc(_)
+        | Instr::SelectType(_) => vec![Feature::ReferenceTypes],
+        Instr::CallIndirect(_, table) if *table != 0 => vec![Feature::ReferenceTypes],
+        Instr::ReturnCall(_) | Instr::ReturnCallIndirect(_, _) => vec![Feature::TailCall],
+        Instr::ReturnCallRef(_) => vec![Feature::TailCall, Feature::FunctionReferences],
+        Instr::CallRef(_) | Instr::RefAsNonNull | Instr::BrOnNull(_) | Instr::BrOnNonNull(_) => {
+            vec![Feature::FunctionReferences]
+        }
+        Instr::Try(_, _, _, _)
+        | Instr::TryDelegate(_, _, _)
+        | Instr::Throw(_)
+        | Instr::Rethrow(_)
+        | Instr::ThrowRef
+        | Instr::TryTable(_, _, _) => vec![Feature::Exceptions],
+        Instr::RefEq
+        | Instr::StructNew(_)
+        | Instr::StructNewDefault(_)
+        | Instr::StructGet(_, _)
+        | Instr::StructGetS(_, _)
+        | Instr::StructGetU(_, _)
+        | Instr::StructSet(_, _)
+        | Instr::ArrayNew(_)
+        | Instr::ArrayNewDefault(_)
+        | Instr::ArrayNewFixed(_, _)
+        | Instr::ArrayNewData(_, _)
+        | Instr::ArrayNewElem(_, _)
+        | Instr::ArrayGet(_)
+        | Instr::ArrayGetS(_)
+        | Instr::ArrayGetU(_)
+        | Instr::ArraySet(_)
+        | Instr::ArrayLen
+        | Instr::ArrayFill(_)
+        | Instr::ArrayCopy(_, _)
+        | Instr::ArrayInitData(_, _)
+        | Instr::ArrayInitElem(_, _)
+        | Instr::RefTest(_)
+        | Instr::RefCast(_)
+        | Instr::BrOnCast(_, _, _)
+        | Instr::BrOnCastFail(_, _, _)
+        | Instr::AnyConvertExtern
+        | Instr::ExternConvertAny
+        | Instr::RefI31
+        | Instr::I31GetS
+        | Instr::I31GetU => vec![Feature::Gc],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Feature, WasmFeatures};
+    use crate::modules::Module;
+    use crate::Parse;
+
+    fn check(features: &WasmFeatures, wat: &str) -> Result<(), String> {
+        features
+            .check(&Module::parse(wat).unwrap())
+            .map_err(|err| match err {
+                crate::Error::InvalidModule(_, msg) => msg,
+                err => panic!("{err:?}"),
+            })
+    }
+
+    #[test]
+    fn gating() {
+        let mvp = WasmFeatures::mvp();
+        check(&mvp, "(module (func (result i32) (i32.const 1)))").unwrap();
+
+        let disabled = [
+            (
+                "(func (drop (i8x16.splat (i32.const 0))))",
+                "simd support is not enabled: i8x16.splat",
+            ),
+            (
+                "(memory 1 1 shared)",
+                "threads support is not enabled: shared memory",
+            ),
+            (
+                "(func (result i32 i32) (i32.const 0) (i32.const 0))",
+                "multi-value support is not enabled: type 0",
+            ),
+            (
+                "(func (return_call 0))",
+                "tail-call support is not enabled: return_call",
+            ),
+            (
+                "(global (mut i32) (i32.const 0)) (export \"g\" (global 0))",
+                "mutable-global support is not enabled: export g",
+            ),
+        ];
+        for (wat, msg) in disabled {
+            assert_eq!(check(&mvp, wat), Err(msg.to_string()), "{wat}");
+            check(&WasmFeatures::default(), wat).unwrap();
+        }
+
+        let simd = WasmFeatures {
+            simd: true,
+            ..WasmFeatures::mvp()
+        };
+        let relaxed = "(func (drop (f32x4.relaxed_min (v128.const i32x4 0 0 0 0) (v128.const i32x4 0 0 0 0))))";
+        assert_eq!(
+            check(&simd, relaxed),
+            Err("relaxed-simd support is not enabled: f32x4.relaxed_min".to_string())
+        );
+    }
+
+    #[test]
+    fn required() {
+        let module = Module::parse(
+            r#"(module
+                 (memory 1 1 shared)
+                 (func (param v128) (result i32 i32)
+                   (i32.extend8_s (i32.const 0))
+                   (i32.trunc_sat_f32_s (f32.const 0))
+                   (memory.fill (i32.const 0) (i32.const 0) (i32.const 0))))"#,
+        )
+        .unwrap();
+        assert_eq!(
+            WasmFeatures::required(&module).features(),
+            [
+                Feature::SignExtension,
+                Feature::SaturatingFloatToInt,
+                Feature::MultiValue,
+                Feature::BulkMemory,
+                Feature::Simd,
+                Feature::Threads,
+            ]
+        );
+        let mvp = Module::parse("(module (func (result i32) (i32.const 0)))").unwrap();
+        assert_eq!(WasmFeatures::required(&mvp), WasmFeatures::mvp());
+
+        let single = Module::parse(
+            "(module (type $t (func (result i32))) (func (result i32) (block (type $t) (i32.const 0))))",
+        )
+        .unwrap();
+        assert_eq!(WasmFeatures::required(&single), WasmFeatures::mvp());
+        let params = Module::parse(
+            "(module (type $t (func (param i32) (result i32))) (func (result i32) (i32.const 0) (block (type $t))))",
+        )
+        .unwrap();
+        assert_eq!(
+            WasmFeatures::required(&params).features(),
+            [Feature::MultiValue]
+        );
+    }
+}
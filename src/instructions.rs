@@ -1,19 +1,23 @@
 use std::backtrace::Backtrace;
 
 use crate::{
-    modules::{DataIdx, ElemIdx, FuncIdx, GlobalIdx, LabelIdx, LocalIdx, TableIdx, TypeIdx},
-    types::ValType,
-    Buffer, Error, Parse, IB,
+    modules::{
+        DataIdx, ElemIdx, FieldIdx, FuncIdx, GlobalIdx, LabelIdx, LocalIdx, MemIdx, TableIdx,
+        TagIdx, TypeIdx,
+    },
+    types::{HeapType, RefType, ValType},
+    Buffer, Encode, Error, Parse, IB,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BlockType {
     Empty,
     ValType(ValType),
     X(i64),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(non_camel_case_types)]
 pub enum Instr {
     UnReachable,
     Nop,
@@ -21,15 +25,64 @@ pub enum Instr {
     Loop(BlockType, Vec<Instr>),
     If(BlockType, Vec<Instr>),
     IfElse(BlockType, Vec<Instr>, Vec<Instr>),
+    /// Legacy exception handling: body, `catch` clauses and `catch_all`.
+    Try(
+        BlockType,
+        Vec<Instr>,
+        Vec<(TagIdx, Vec<Instr>)>,
+        Option<Vec<Instr>>,
+    ),
+    TryDelegate(BlockType, Vec<Instr>, LabelIdx),
+    Throw(TagIdx),
+    Rethrow(LabelIdx),
+    ThrowRef,
+    TryTable(BlockType, Vec<Catch>, Vec<Instr>),
     Br(LabelIdx),
     BrIf(LabelIdx),
     BrTable(Vec<LabelIdx>, LabelIdx),
     Return,
     Call(FuncIdx),
     CallIndirect(TypeIdx, TableIdx),
-    RefNull(u32),
+    ReturnCall(FuncIdx),
+    ReturnCallIndirect(TypeIdx, TableIdx),
+    CallRef(TypeIdx),
+    ReturnCallRef(TypeIdx),
+    RefNull(HeapType),
     RefIsNull,
     RefFunc(u32),
+    RefAsNonNull,
+    BrOnNull(LabelIdx),
+    BrOnNonNull(LabelIdx),
+    RefEq,
+    StructNew(TypeIdx),
+    StructNewDefault(TypeIdx),
+    StructGet(TypeIdx, FieldIdx),
+    StructGetS(TypeIdx, FieldIdx),
+    StructGetU(TypeIdx, FieldIdx),
+    StructSet(TypeIdx, FieldIdx),
+    ArrayNew(TypeIdx),
+    ArrayNewDefault(TypeIdx),
+    ArrayNewFixed(TypeIdx, u32),
+    ArrayNewData(TypeIdx, DataIdx),
+    ArrayNewElem(TypeIdx, ElemIdx),
+    ArrayGet(TypeIdx),
+    ArrayGetS(TypeIdx),
+    ArrayGetU(TypeIdx),
+    ArraySet(TypeIdx),
+    ArrayLen,
+    ArrayFill(TypeIdx),
+    ArrayCopy(TypeIdx, TypeIdx),
+    ArrayInitData(TypeIdx, DataIdx),
+    ArrayInitElem(TypeIdx, ElemIdx),
+    RefTest(RefType),
+    RefCast(RefType),
+    BrOnCast(LabelIdx, RefType, RefType),
+    BrOnCastFail(LabelIdx, RefType, RefType),
+    AnyConvertExtern,
+    ExternConvertAny,
+    RefI31,
+    I31GetS,
+    I31GetU,
     Drop,
     Select,
     SelectType(Vec<ValType>),
@@ -69,12 +122,12 @@ pub enum Instr {
     I64Store8(MemArg),
     I64Store16(MemArg),
     I64Store32(MemArg),
-    MemorySize,
-    MemoryGrow,
-    MemoryInit(DataIdx),
+    MemorySize(MemIdx),
+    MemoryGrow(MemIdx),
+    MemoryInit(DataIdx, MemIdx),
     DataDrop(DataIdx),
-    MemoryCopy,
-    MemoryFill,
+    MemoryCopy(MemIdx, MemIdx),
+    MemoryFill(MemIdx),
 
     I32Const(i32),
     I64Const(i64),
@@ -278,19 +331,3208 @@ pub enum Instr {
     F32X4_Splat,
     F64X2_Splat,
 
-    I8X16_Eq, // TODO Continue: All other vector instructions are plain opcodes without any immediates.
+    I8X16_Eq,
+    I8X16_Ne,
+    I8X16_Lt_S,
+    I8X16_Lt_U,
+    I8X16_Gt_S,
+    I8X16_Gt_U,
+    I8X16_Le_S,
+    I8X16_Le_U,
+    I8X16_Ge_S,
+    I8X16_Ge_U,
+    I16X8_Eq,
+    I16X8_Ne,
+    I16X8_Lt_S,
+    I16X8_Lt_U,
+    I16X8_Gt_S,
+    I16X8_Gt_U,
+    I16X8_Le_S,
+    I16X8_Le_U,
+    I16X8_Ge_S,
+    I16X8_Ge_U,
+    I32X4_Eq,
+    I32X4_Ne,
+    I32X4_Lt_S,
+    I32X4_Lt_U,
+    I32X4_Gt_S,
+    I32X4_Gt_U,
+    I32X4_Le_S,
+    I32X4_Le_U,
+    I32X4_Ge_S,
+    I32X4_Ge_U,
+    F32X4_Eq,
+    F32X4_Ne,
+    F32X4_Lt,
+    F32X4_Gt,
+    F32X4_Le,
+    F32X4_Ge,
+    F64X2_Eq,
+    F64X2_Ne,
+    F64X2_Lt,
+    F64X2_Gt,
+    F64X2_Le,
+    F64X2_Ge,
+    V128_Not,
+    V128_And,
+    V128_Andnot,
+    V128_Or,
+    V128_Xor,
+    V128_Bitselect,
+    V128_Any_True,
+    F32X4_Demote_F64X2_Zero,
+    F64X2_Promote_Low_F32X4,
+    I8X16_Abs,
+    I8X16_Neg,
+    I8X16_Popcnt,
+    I8X16_All_True,
+    I8X16_Bitmask,
+    I8X16_Narrow_I16X8_S,
+    I8X16_Narrow_I16X8_U,
+    F32X4_Ceil,
+    F32X4_Floor,
+    F32X4_Trunc,
+    F32X4_Nearest,
+    I8X16_Shl,
+    I8X16_Shr_S,
+    I8X16_Shr_U,
+    I8X16_Add,
+    I8X16_Add_Sat_S,
+    I8X16_Add_Sat_U,
+    I8X16_Sub,
+    I8X16_Sub_Sat_S,
+    I8X16_Sub_Sat_U,
+    F64X2_Ceil,
+    F64X2_Floor,
+    I8X16_Min_S,
+    I8X16_Min_U,
+    I8X16_Max_S,
+    I8X16_Max_U,
+    F64X2_Trunc,
+    I8X16_Avgr_U,
+    I16X8_Extadd_Pairwise_I8X16_S,
+    I16X8_Extadd_Pairwise_I8X16_U,
+    I32X4_Extadd_Pairwise_I16X8_S,
+    I32X4_Extadd_Pairwise_I16X8_U,
+    I16X8_Abs,
+    I16X8_Neg,
+    I16X8_Q15mulr_Sat_S,
+    I16X8_All_True,
+    I16X8_Bitmask,
+    I16X8_Narrow_I32X4_S,
+    I16X8_Narrow_I32X4_U,
+    I16X8_Extend_Low_I8X16_S,
+    I16X8_Extend_High_I8X16_S,
+    I16X8_Extend_Low_I8X16_U,
+    I16X8_Extend_High_I8X16_U,
+    I16X8_Shl,
+    I16X8_Shr_S,
+    I16X8_Shr_U,
+    I16X8_Add,
+    I16X8_Add_Sat_S,
+    I16X8_Add_Sat_U,
+    I16X8_Sub,
+    I16X8_Sub_Sat_S,
+    I16X8_Sub_Sat_U,
+    F64X2_Nearest,
+    I16X8_Mul,
+    I16X8_Min_S,
+    I16X8_Min_U,
+    I16X8_Max_S,
+    I16X8_Max_U,
+    I16X8_Avgr_U,
+    I16X8_Extmul_Low_I8X16_S,
+    I16X8_Extmul_High_I8X16_S,
+    I16X8_Extmul_Low_I8X16_U,
+    I16X8_Extmul_High_I8X16_U,
+    I32X4_Abs,
+    I32X4_Neg,
+    I32X4_All_True,
+    I32X4_Bitmask,
+    I32X4_Extend_Low_I16X8_S,
+    I32X4_Extend_High_I16X8_S,
+    I32X4_Extend_Low_I16X8_U,
+    I32X4_Extend_High_I16X8_U,
+    I32X4_Shl,
+    I32X4_Shr_S,
+    I32X4_Shr_U,
+    I32X4_Add,
+    I32X4_Sub,
+    I32X4_Mul,
+    I32X4_Min_S,
+    I32X4_Min_U,
+    I32X4_Max_S,
+    I32X4_Max_U,
+    I32X4_Dot_I16X8_S,
+    I32X4_Extmul_Low_I16X8_S,
+    I32X4_Extmul_High_I16X8_S,
+    I32X4_Extmul_Low_I16X8_U,
+    I32X4_Extmul_High_I16X8_U,
+    I64X2_Abs,
+    I64X2_Neg,
+    I64X2_All_True,
+    I64X2_Bitmask,
+    I64X2_Extend_Low_I32X4_S,
+    I64X2_Extend_High_I32X4_S,
+    I64X2_Extend_Low_I32X4_U,
+    I64X2_Extend_High_I32X4_U,
+    I64X2_Shl,
+    I64X2_Shr_S,
+    I64X2_Shr_U,
+    I64X2_Add,
+    I64X2_Sub,
+    I64X2_Mul,
+    I64X2_Eq,
+    I64X2_Ne,
+    I64X2_Lt_S,
+    I64X2_Gt_S,
+    I64X2_Le_S,
+    I64X2_Ge_S,
+    I64X2_Extmul_Low_I32X4_S,
+    I64X2_Extmul_High_I32X4_S,
+    I64X2_Extmul_Low_I32X4_U,
+    I64X2_Extmul_High_I32X4_U,
+    F32X4_Abs,
+    F32X4_Neg,
+    F32X4_Sqrt,
+    F32X4_Add,
+    F32X4_Sub,
+    F32X4_Mul,
+    F32X4_Div,
+    F32X4_Min,
+    F32X4_Max,
+    F32X4_Pmin,
+    F32X4_Pmax,
+    F64X2_Abs,
+    F64X2_Neg,
+    F64X2_Sqrt,
+    F64X2_Add,
+    F64X2_Sub,
+    F64X2_Mul,
+    F64X2_Div,
+    F64X2_Min,
+    F64X2_Max,
+    F64X2_Pmin,
+    F64X2_Pmax,
+    I32X4_Trunc_Sat_F32X4_S,
+    I32X4_Trunc_Sat_F32X4_U,
+    F32X4_Convert_I32X4_S,
+    F32X4_Convert_I32X4_U,
+    I32X4_Trunc_Sat_F64X2_S_Zero,
+    I32X4_Trunc_Sat_F64X2_U_Zero,
+    F64X2_Convert_Low_I32X4_S,
+    F64X2_Convert_Low_I32X4_U,
+
+    I8X16_Relaxed_Swizzle,
+    I32X4_Relaxed_Trunc_F32X4_S,
+    I32X4_Relaxed_Trunc_F32X4_U,
+    I32X4_Relaxed_Trunc_F64X2_S_Zero,
+    I32X4_Relaxed_Trunc_F64X2_U_Zero,
+    F32X4_Relaxed_Madd,
+    F32X4_Relaxed_Nmadd,
+    F64X2_Relaxed_Madd,
+    F64X2_Relaxed_Nmadd,
+    I8X16_Relaxed_Laneselect,
+    I16X8_Relaxed_Laneselect,
+    I32X4_Relaxed_Laneselect,
+    I64X2_Relaxed_Laneselect,
+    F32X4_Relaxed_Min,
+    F32X4_Relaxed_Max,
+    F64X2_Relaxed_Min,
+    F64X2_Relaxed_Max,
+    I16X8_Relaxed_Q15mulr_S,
+    I16X8_Relaxed_Dot_I8X16_I7X16_S,
+    I32X4_Relaxed_Dot_I8X16_I7X16_Add_S,
+
+    MemoryAtomicNotify(MemArg),
+    MemoryAtomicWait32(MemArg),
+    MemoryAtomicWait64(MemArg),
+    I32AtomicLoad(MemArg),
+    I64AtomicLoad(MemArg),
+    I32AtomicLoad8U(MemArg),
+    I32AtomicLoad16U(MemArg),
+    I64AtomicLoad8U(MemArg),
+    I64AtomicLoad16U(MemArg),
+    I64AtomicLoad32U(MemArg),
+    I32AtomicStore(MemArg),
+    I64AtomicStore(MemArg),
+    I32AtomicStore8(MemArg),
+    I32AtomicStore16(MemArg),
+    I64AtomicStore8(MemArg),
+    I64AtomicStore16(MemArg),
+    I64AtomicStore32(MemArg),
+    I32AtomicRmwAdd(MemArg),
+    I64AtomicRmwAdd(MemArg),
+    I32AtomicRmw8AddU(MemArg),
+    I32AtomicRmw16AddU(MemArg),
+    I64AtomicRmw8AddU(MemArg),
+    I64AtomicRmw16AddU(MemArg),
+    I64AtomicRmw32AddU(MemArg),
+    I32AtomicRmwSub(MemArg),
+    I64AtomicRmwSub(MemArg),
+    I32AtomicRmw8SubU(MemArg),
+    I32AtomicRmw16SubU(MemArg),
+    I64AtomicRmw8SubU(MemArg),
+    I64AtomicRmw16SubU(MemArg),
+    I64AtomicRmw32SubU(MemArg),
+    I32AtomicRmwAnd(MemArg),
+    I64AtomicRmwAnd(MemArg),
+    I32AtomicRmw8AndU(MemArg),
+    I32AtomicRmw16AndU(MemArg),
+    I64AtomicRmw8AndU(MemArg),
+    I64AtomicRmw16AndU(MemArg),
+    I64AtomicRmw32AndU(MemArg),
+    I32AtomicRmwOr(MemArg),
+    I64AtomicRmwOr(MemArg),
+    I32AtomicRmw8OrU(MemArg),
+    I32AtomicRmw16OrU(MemArg),
+    I64AtomicRmw8OrU(MemArg),
+    I64AtomicRmw16OrU(MemArg),
+    I64AtomicRmw32OrU(MemArg),
+    I32AtomicRmwXor(MemArg),
+    I64AtomicRmwXor(MemArg),
+    I32AtomicRmw8XorU(MemArg),
+    I32AtomicRmw16XorU(MemArg),
+    I64AtomicRmw8XorU(MemArg),
+    I64AtomicRmw16XorU(MemArg),
+    I64AtomicRmw32XorU(MemArg),
+    I32AtomicRmwXchg(MemArg),
+    I64AtomicRmwXchg(MemArg),
+    I32AtomicRmw8XchgU(MemArg),
+    I32AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw8XchgU(MemArg),
+    I64AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw32XchgU(MemArg),
+    I32AtomicRmwCmpxchg(MemArg),
+    I64AtomicRmwCmpxchg(MemArg),
+    I32AtomicRmw8CmpxchgU(MemArg),
+    I32AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw8CmpxchgU(MemArg),
+    I64AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw32CmpxchgU(MemArg),
+    AtomicFence,
 }
 
 pub type LaneIdx = u8;
 
-#[derive(Debug)]
-pub struct MemArg(pub u32, pub u32);
+/// Handler clause of `try_table`: where to branch when an exception is caught.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Catch {
+    Catch(TagIdx, LabelIdx),
+    CatchRef(TagIdx, LabelIdx),
+    CatchAll(LabelIdx),
+    CatchAllRef(LabelIdx),
+}
+
+impl Catch {
+    pub fn label(&self) -> LabelIdx {
+        match self {
+            Self::Catch(_, label)
+            | Self::CatchRef(_, label)
+            | Self::CatchAll(label)
+            | Self::CatchAllRef(label) => *label,
+        }
+    }
+
+    pub fn tag(&self) -> Option<TagIdx> {
+        match self {
+            Self::Catch(tag, _) | Self::CatchRef(tag, _) => Some(*tag),
+            Self::CatchAll(_) | Self::CatchAllRef(_) => None,
+        }
+    }
+
+    /// Whether the caught exception is passed to the label as an `exnref`.
+    pub fn is_ref(&self) -> bool {
+        matches!(self, Self::CatchRef(_, _) | Self::CatchAllRef(_))
+    }
+}
+
+/// Alignment exponent, offset and memory index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemArg(pub u32, pub u64, pub MemIdx);
+
+impl Instr {
+    /// Opcode after the 0xFB prefix of GC instructions. `ref.test` and
+    /// `ref.cast` add one for a nullable target type.
+    fn gc_opcode(&self) -> u32 {
+        match self {
+            Self::StructNew(_) => 0,
+            Self::StructNewDefault(_) => 1,
+            Self::StructGet(_, _) => 2,
+            Self::StructGetS(_, _) => 3,
+            Self::StructGetU(_, _) => 4,
+            Self::StructSet(_, _) => 5,
+            Self::ArrayNew(_) => 6,
+            Self::ArrayNewDefault(_) => 7,
+            Self::ArrayNewFixed(_, _) => 8,
+            Self::ArrayNewData(_, _) => 9,
+            Self::ArrayNewElem(_, _) => 10,
+            Self::ArrayGet(_) => 11,
+            Self::ArrayGetS(_) => 12,
+            Self::ArrayGetU(_) => 13,
+            Self::ArraySet(_) => 14,
+            Self::ArrayLen => 15,
+            Self::ArrayFill(_) => 16,
+            Self::ArrayCopy(_, _) => 17,
+            Self::ArrayInitData(_, _) => 18,
+            Self::ArrayInitElem(_, _) => 19,
+            Self::RefTest(_) => 20,
+            Self::RefCast(_) => 22,
+            Self::BrOnCast(_, _, _) => 24,
+            Self::BrOnCastFail(_, _, _) => 25,
+            Self::AnyConvertExtern => 26,
+            Self::ExternConvertAny => 27,
+            Self::RefI31 => 28,
+            Self::I31GetS => 29,
+            Self::I31GetU => 30,
+            _ => unreachable!("{} is not a GC instruction", self.name()),
+        }
+    }
+
+    /// Opcode after the 0xFD prefix of SIMD instructions without immediates.
+    fn simd_opcode(&self) -> u32 {
+        match self {
+            Self::I8X16_Ne => 36,
+            Self::I8X16_Lt_S => 37,
+            Self::I8X16_Lt_U => 38,
+            Self::I8X16_Gt_S => 39,
+            Self::I8X16_Gt_U => 40,
+            Self::I8X16_Le_S => 41,
+            Self::I8X16_Le_U => 42,
+            Self::I8X16_Ge_S => 43,
+            Self::I8X16_Ge_U => 44,
+            Self::I16X8_Eq => 45,
+            Self::I16X8_Ne => 46,
+            Self::I16X8_Lt_S => 47,
+            Self::I16X8_Lt_U => 48,
+            Self::I16X8_Gt_S => 49,
+            Self::I16X8_Gt_U => 50,
+            Self::I16X8_Le_S => 51,
+            Self::I16X8_Le_U => 52,
+            Self::I16X8_Ge_S => 53,
+            Self::I16X8_Ge_U => 54,
+            Self::I32X4_Eq => 55,
+            Self::I32X4_Ne => 56,
+            Self::I32X4_Lt_S => 57,
+            Self::I32X4_Lt_U => 58,
+            Self::I32X4_Gt_S => 59,
+            Self::I32X4_Gt_U => 60,
+            Self::I32X4_Le_S => 61,
+            Self::I32X4_Le_U => 62,
+            Self::I32X4_Ge_S => 63,
+            Self::I32X4_Ge_U => 64,
+            Self::F32X4_Eq => 65,
+            Self::F32X4_Ne => 66,
+            Self::F32X4_Lt => 67,
+            Self::F32X4_Gt => 68,
+            Self::F32X4_Le => 69,
+            Self::F32X4_Ge => 70,
+            Self::F64X2_Eq => 71,
+            Self::F64X2_Ne => 72,
+            Self::F64X2_Lt => 73,
+            Self::F64X2_Gt => 74,
+            Self::F64X2_Le => 75,
+            Self::F64X2_Ge => 76,
+            Self::V128_Not => 77,
+            Self::V128_And => 78,
+            Self::V128_Andnot => 79,
+            Self::V128_Or => 80,
+            Self::V128_Xor => 81,
+            Self::V128_Bitselect => 82,
+            Self::V128_Any_True => 83,
+            Self::F32X4_Demote_F64X2_Zero => 94,
+            Self::F64X2_Promote_Low_F32X4 => 95,
+            Self::I8X16_Abs => 96,
+            Self::I8X16_Neg => 97,
+            Self::I8X16_Popcnt => 98,
+            Self::I8X16_All_True => 99,
+            Self::I8X16_Bitmask => 100,
+            Self::I8X16_Narrow_I16X8_S => 101,
+            Self::I8X16_Narrow_I16X8_U => 102,
+            Self::F32X4_Ceil => 103,
+            Self::F32X4_Floor => 104,
+            Self::F32X4_Trunc => 105,
+            Self::F32X4_Nearest => 106,
+            Self::I8X16_Shl => 107,
+            Self::I8X16_Shr_S => 108,
+            Self::I8X16_Shr_U => 109,
+            Self::I8X16_Add => 110,
+            Self::I8X16_Add_Sat_S => 111,
+            Self::I8X16_Add_Sat_U => 112,
+            Self::I8X16_Sub => 113,
+            Self::I8X16_Sub_Sat_S => 114,
+            Self::I8X16_Sub_Sat_U => 115,
+            Self::F64X2_Ceil => 116,
+            Self::F64X2_Floor => 117,
+            Self::I8X16_Min_S => 118,
+            Self::I8X16_Min_U => 119,
+            Self::I8X16_Max_S => 120,
+            Self::I8X16_Max_U => 121,
+            Self::F64X2_Trunc => 122,
+            Self::I8X16_Avgr_U => 123,
+            Self::I16X8_Extadd_Pairwise_I8X16_S => 124,
+            Self::I16X8_Extadd_Pairwise_I8X16_U => 125,
+            Self::I32X4_Extadd_Pairwise_I16X8_S => 126,
+            Self::I32X4_Extadd_Pairwise_I16X8_U => 127,
+            Self::I16X8_Abs => 128,
+            Self::I16X8_Neg => 129,
+            Self::I16X8_Q15mulr_Sat_S => 130,
+            Self::I16X8_All_True => 131,
+            Self::I16X8_Bitmask => 132,
+            Self::I16X8_Narrow_I32X4_S => 133,
+            Self::I16X8_Narrow_I32X4_U => 134,
+            Self::I16X8_Extend_Low_I8X16_S => 135,
+            Self::I16X8_Extend_High_I8X16_S => 136,
+            Self::I16X8_Extend_Low_I8X16_U => 137,
+            Self::I16X8_Extend_High_I8X16_U => 138,
+            Self::I16X8_Shl => 139,
+            Self::I16X8_Shr_S => 140,
+            Self::I16X8_Shr_U => 141,
+            Self::I16X8_Add => 142,
+            Self::I16X8_Add_Sat_S => 143,
+            Self::I16X8_Add_Sat_U => 144,
+            Self::I16X8_Sub => 145,
+            Self::I16X8_Sub_Sat_S => 146,
+            Self::I16X8_Sub_Sat_U => 147,
+            Self::F64X2_Nearest => 148,
+            Self::I16X8_Mul => 149,
+            Self::I16X8_Min_S => 150,
+            Self::I16X8_Min_U => 151,
+            Self::I16X8_Max_S => 152,
+            Self::I16X8_Max_U => 153,
+            Self::I16X8_Avgr_U => 155,
+            Self::I16X8_Extmul_Low_I8X16_S => 156,
+            Self::I16X8_Extmul_High_I8X16_S => 157,
+            Self::I16X8_Extmul_Low_I8X16_U => 158,
+            Self::I16X8_Extmul_High_I8X16_U => 159,
+            Self::I32X4_Abs => 160,
+            Self::I32X4_Neg => 161,
+            Self::I32X4_All_True => 163,
+            Self::I32X4_Bitmask => 164,
+            Self::I32X4_Extend_Low_I16X8_S => 167,
+            Self::I32X4_Extend_High_I16X8_S => 168,
+            Self::I32X4_Extend_Low_I16X8_U => 169,
+            Self::I32X4_Extend_High_I16X8_U => 170,
+            Self::I32X4_Shl => 171,
+            Self::I32X4_Shr_S => 172,
+            Self::I32X4_Shr_U => 173,
+            Self::I32X4_Add => 174,
+            Self::I32X4_Sub => 177,
+            Self::I32X4_Mul => 181,
+            Self::I32X4_Min_S => 182,
+            Self::I32X4_Min_U => 183,
+            Self::I32X4_Max_S => 184,
+            Self::I32X4_Max_U => 185,
+            Self::I32X4_Dot_I16X8_S => 186,
+            Self::I32X4_Extmul_Low_I16X8_S => 188,
+            Self::I32X4_Extmul_High_I16X8_S => 189,
+            Self::I32X4_Extmul_Low_I16X8_U => 190,
+            Self::I32X4_Extmul_High_I16X8_U => 191,
+            Self::I64X2_Abs => 192,
+            Self::I64X2_Neg => 193,
+            Self::I64X2_All_True => 195,
+            Self::I64X2_Bitmask => 196,
+            Self::I64X2_Extend_Low_I32X4_S => 199,
+            Self::I64X2_Extend_High_I32X4_S => 200,
+            Self::I64X2_Extend_Low_I32X4_U => 201,
+            Self::I64X2_Extend_High_I32X4_U => 202,
+            Self::I64X2_Shl => 203,
+            Self::I64X2_Shr_S => 204,
+            Self::I64X2_Shr_U => 205,
+            Self::I64X2_Add => 206,
+            Self::I64X2_Sub => 209,
+            Self::I64X2_Mul => 213,
+            Self::I64X2_Eq => 214,
+            Self::I64X2_Ne => 215,
+            Self::I64X2_Lt_S => 216,
+            Self::I64X2_Gt_S => 217,
+            Self::I64X2_Le_S => 218,
+            Self::I64X2_Ge_S => 219,
+            Self::I64X2_Extmul_Low_I32X4_S => 220,
+            Self::I64X2_Extmul_High_I32X4_S => 221,
+            Self::I64X2_Extmul_Low_I32X4_U => 222,
+            Self::I64X2_Extmul_High_I32X4_U => 223,
+            Self::F32X4_Abs => 224,
+            Self::F32X4_Neg => 225,
+            Self::F32X4_Sqrt => 227,
+            Self::F32X4_Add => 228,
+            Self::F32X4_Sub => 229,
+            Self::F32X4_Mul => 230,
+            Self::F32X4_Div => 231,
+            Self::F32X4_Min => 232,
+            Self::F32X4_Max => 233,
+            Self::F32X4_Pmin => 234,
+            Self::F32X4_Pmax => 235,
+            Self::F64X2_Abs => 236,
+            Self::F64X2_Neg => 237,
+            Self::F64X2_Sqrt => 239,
+            Self::F64X2_Add => 240,
+            Self::F64X2_Sub => 241,
+            Self::F64X2_Mul => 242,
+            Self::F64X2_Div => 243,
+            Self::F64X2_Min => 244,
+            Self::F64X2_Max => 245,
+            Self::F64X2_Pmin => 246,
+            Self::F64X2_Pmax => 247,
+            Self::I32X4_Trunc_Sat_F32X4_S => 248,
+            Self::I32X4_Trunc_Sat_F32X4_U => 249,
+            Self::F32X4_Convert_I32X4_S => 250,
+            Self::F32X4_Convert_I32X4_U => 251,
+            Self::I32X4_Trunc_Sat_F64X2_S_Zero => 252,
+            Self::I32X4_Trunc_Sat_F64X2_U_Zero => 253,
+            Self::F64X2_Convert_Low_I32X4_S => 254,
+            Self::F64X2_Convert_Low_I32X4_U => 255,
+            Self::I8X16_Relaxed_Swizzle => 0x100,
+            Self::I32X4_Relaxed_Trunc_F32X4_S => 0x101,
+            Self::I32X4_Relaxed_Trunc_F32X4_U => 0x102,
+            Self::I32X4_Relaxed_Trunc_F64X2_S_Zero => 0x103,
+            Self::I32X4_Relaxed_Trunc_F64X2_U_Zero => 0x104,
+            Self::F32X4_Relaxed_Madd => 0x105,
+            Self::F32X4_Relaxed_Nmadd => 0x106,
+            Self::F64X2_Relaxed_Madd => 0x107,
+            Self::F64X2_Relaxed_Nmadd => 0x108,
+            Self::I8X16_Relaxed_Laneselect => 0x109,
+            Self::I16X8_Relaxed_Laneselect => 0x10a,
+            Self::I32X4_Relaxed_Laneselect => 0x10b,
+            Self::I64X2_Relaxed_Laneselect => 0x10c,
+            Self::F32X4_Relaxed_Min => 0x10d,
+            Self::F32X4_Relaxed_Max => 0x10e,
+            Self::F64X2_Relaxed_Min => 0x10f,
+            Self::F64X2_Relaxed_Max => 0x110,
+            Self::I16X8_Relaxed_Q15mulr_S => 0x111,
+            Self::I16X8_Relaxed_Dot_I8X16_I7X16_S => 0x112,
+            Self::I32X4_Relaxed_Dot_I8X16_I7X16_Add_S => 0x113,
+            _ => unreachable!("{} is not a SIMD instruction", self.name()),
+        }
+    }
+
+    /// Whether this is one of the relaxed SIMD instructions, whose results
+    /// may differ between engines.
+    /// Instructions with the 0xfd prefix, including the relaxed ones.
+    pub fn is_simd(&self) -> bool {
+        matches!(
+            self,
+            Self::V128_Load(..)
+                | Self::V128_Load_8x8_S(..)
+                | Self::V128_Load_8x8_U(..)
+                | Self::V128_Load_16x4_S(..)
+                | Self::V128_Load_16x4_U(..)
+                | Self::V128_Load_32x2_S(..)
+                | Self::V128_Load_32x2_U(..)
+                | Self::V128_Load_8_Splat(..)
+                | Self::V128_Load_16_Splat(..)
+                | Self::V128_Load_32_Splat(..)
+                | Self::V128_Load_64_Splat(..)
+                | Self::V128_Load_32_Zero(..)
+                | Self::V128_Load_64_Zero(..)
+                | Self::V128_Store(..)
+                | Self::V128_Load_8_Lane(..)
+                | Self::V128_Load_16_Lane(..)
+                | Self::V128_Load_32_Lane(..)
+                | Self::V128_Load_64_Lane(..)
+                | Self::V128_Store_8_Lane(..)
+                | Self::V128_Store_16_Lane(..)
+                | Self::V128_Store_32_Lane(..)
+                | Self::V128_Store_64_Lane(..)
+                | Self::V128_Const(..)
+                | Self::I8X16_Shuffle(..)
+                | Self::I8X16_Extract_Lane_S(..)
+                | Self::I8X16_Extract_Lane_U(..)
+                | Self::I8X16_Replace_Lane(..)
+                | Self::I16X8_Extract_Lane_S(..)
+                | Self::I16X8_Extract_Lane_U(..)
+                | Self::I16X8_Replace_Lane(..)
+                | Self::I32X4_Extract_Lane(..)
+                | Self::I32X4_Replace_Lane(..)
+                | Self::I64X2_Extract_Lane(..)
+                | Self::I64X2_Replace_Lane(..)
+                | Self::F32X4_Extract_Lane(..)
+                | Self::F32X4_Replace_Lane(..)
+                | Self::F64X2_Extract_Lane(..)
+                | Self::F64X2_Replace_Lane(..)
+                | Self::I8x16_Swizzle
+                | Self::I8X16_Splat
+                | Self::I16X8_Splat
+                | Self::I32X4_Splat
+                | Self::I64X2_Splat
+                | Self::F32X4_Splat
+                | Self::F64X2_Splat
+                | Self::I8X16_Eq
+                | Self::I8X16_Ne
+                | Self::I8X16_Lt_S
+                | Self::I8X16_Lt_U
+                | Self::I8X16_Gt_S
+                | Self::I8X16_Gt_U
+                | Self::I8X16_Le_S
+                | Self::I8X16_Le_U
+                | Self::I8X16_Ge_S
+                | Self::I8X16_Ge_U
+                | Self::I16X8_Eq
+                | Self::I16X8_Ne
+                | Self::I16X8_Lt_S
+                | Self::I16X8_Lt_U
+                | Self::I16X8_Gt_S
+                | Self::I16X8_Gt_U
+                | Self::I16X8_Le_S
+                | Self::I16X8_Le_U
+                | Self::I16X8_Ge_S
+                | Self::I16X8_Ge_U
+                | Self::I32X4_Eq
+                | Self::I32X4_Ne
+                | Self::I32X4_Lt_S
+                | Self::I32X4_Lt_U
+                | Self::I32X4_Gt_S
+                | Self::I32X4_Gt_U
+                | Self::I32X4_Le_S
+                | Self::I32X4_Le_U
+                | Self::I32X4_Ge_S
+                | Self::I32X4_Ge_U
+                | Self::F32X4_Eq
+                | Self::F32X4_Ne
+                | Self::F32X4_Lt
+                | Self::F32X4_Gt
+                | Self::F32X4_Le
+                | Self::F32X4_Ge
+                | Self::F64X2_Eq
+                | Self::F64X2_Ne
+                | Self::F64X2_Lt
+                | Self::F64X2_Gt
+                | Self::F64X2_Le
+                | Self::F64X2_Ge
+                | Self::V128_Not
+                | Self::V128_And
+                | Self::V128_Andnot
+                | Self::V128_Or
+                | Self::V128_Xor
+                | Self::V128_Bitselect
+                | Self::V128_Any_True
+                | Self::F32X4_Demote_F64X2_Zero
+                | Self::F64X2_Promote_Low_F32X4
+                | Self::I8X16_Abs
+                | Self::I8X16_Neg
+                | Self::I8X16_Popcnt
+                | Self::I8X16_All_True
+                | Self::I8X16_Bitmask
+                | Self::I8X16_Narrow_I16X8_S
+                | Self::I8X16_Narrow_I16X8_U
+                | Self::F32X4_Ceil
+                | Self::F32X4_Floor
+                | Self::F32X4_Trunc
+                | Self::F32X4_Nearest
+                | Self::I8X16_Shl
+                | Self::I8X16_Shr_S
+                | Self::I8X16_Shr_U
+                | Self::I8X16_Add
+                | Self::I8X16_Add_Sat_S
+                | Self::I8X16_Add_Sat_U
+                | Self::I8X16_Sub
+                | Self::I8X16_Sub_Sat_S
+                | Self::I8X16_Sub_Sat_U
+                | Self::F64X2_Ceil
+                | Self::F64X2_Floor
+                | Self::I8X16_Min_S
+                | Self::I8X16_Min_U
+                | Self::I8X16_Max_S
+                | Self::I8X16_Max_U
+                | Self::F64X2_Trunc
+                | Self::I8X16_Avgr_U
+                | Self::I16X8_Extadd_Pairwise_I8X16_S
+                | Self::I16X8_Extadd_Pairwise_I8X16_U
+                | Self::I32X4_Extadd_Pairwise_I16X8_S
+                | Self::I32X4_Extadd_Pairwise_I16X8_U
+                | Self::I16X8_Abs
+                | Self::I16X8_Neg
+                | Self::I16X8_Q15mulr_Sat_S
+                | Self::I16X8_All_True
+                | Self::I16X8_Bitmask
+                | Self::I16X8_Narrow_I32X4_S
+                | Self::I16X8_Narrow_I32X4_U
+                | Self::I16X8_Extend_Low_I8X16_S
+                | Self::I16X8_Extend_High_I8X16_S
+                | Self::I16X8_Extend_Low_I8X16_U
+                | Self::I16X8_Extend_High_I8X16_U
+                | Self::I16X8_Shl
+                | Self::I16X8_Shr_S
+                | Self::I16X8_Shr_U
+                | Self::I16X8_Add
+                | Self::I16X8_Add_Sat_S
+                | Self::I16X8_Add_Sat_U
+                | Self::I16X8_Sub
+                | Self::I16X8_Sub_Sat_S
+                | Self::I16X8_Sub_Sat_U
+                | Self::F64X2_Nearest
+                | Self::I16X8_Mul
+                | Self::I16X8_Min_S
+                | Self::I16X8_Min_U
+                | Self::I16X8_Max_S
+                | Self::I16X8_Max_U
+                | Self::I16X8_Avgr_U
+                | Self::I16X8_Extmul_Low_I8X16_S
+                | Self::I16X8_Extmul_High_I8X16_S
+                | Self::I16X8_Extmul_Low_I8X16_U
+                | Self::I16X8_Extmul_High_I8X16_U
+                | Self::I32X4_Abs
+                | Self::I32X4_Neg
+                | Self::I32X4_All_True
+                | Self::I32X4_Bitmask
+                | Self::I32X4_Extend_Low_I16X8_S
+                | Self::I32X4_Extend_High_I16X8_S
+                | Self::I32X4_Extend_Low_I16X8_U
+                | Self::I32X4_Extend_High_I16X8_U
+                | Self::I32X4_Shl
+                | Self::I32X4_Shr_S
+                | Self::I32X4_Shr_U
+                | Self::I32X4_Add
+                | Self::I32X4_Sub
+                | Self::I32X4_Mul
+                | Self::I32X4_Min_S
+                | Self::I32X4_Min_U
+                | Self::I32X4_Max_S
+                | Self::I32X4_Max_U
+                | Self::I32X4_Dot_I16X8_S
+                | Self::I32X4_Extmul_Low_I16X8_S
+                | Self::I32X4_Extmul_High_I16X8_S
+                | Self::I32X4_Extmul_Low_I16X8_U
+                | Self::I32X4_Extmul_High_I16X8_U
+                | Self::I64X2_Abs
+                | Self::I64X2_Neg
+                | Self::I64X2_All_True
+                | Self::I64X2_Bitmask
+                | Self::I64X2_Extend_Low_I32X4_S
+                | Self::I64X2_Extend_High_I32X4_S
+                | Self::I64X2_Extend_Low_I32X4_U
+                | Self::I64X2_Extend_High_I32X4_U
+                | Self::I64X2_Shl
+                | Self::I64X2_Shr_S
+                | Self::I64X2_Shr_U
+                | Self::I64X2_Add
+                | Self::I64X2_Sub
+                | Self::I64X2_Mul
+                | Self::I64X2_Eq
+                | Self::I64X2_Ne
+                | Self::I64X2_Lt_S
+                | Self::I64X2_Gt_S
+                | Self::I64X2_Le_S
+                | Self::I64X2_Ge_S
+                | Self::I64X2_Extmul_Low_I32X4_S
+                | Self::I64X2_Extmul_High_I32X4_S
+                | Self::I64X2_Extmul_Low_I32X4_U
+                | Self::I64X2_Extmul_High_I32X4_U
+                | Self::F32X4_Abs
+                | Self::F32X4_Neg
+                | Self::F32X4_Sqrt
+                | Self::F32X4_Add
+                | Self::F32X4_Sub
+                | Self::F32X4_Mul
+                | Self::F32X4_Div
+                | Self::F32X4_Min
+                | Self::F32X4_Max
+                | Self::F32X4_Pmin
+                | Self::F32X4_Pmax
+                | Self::F64X2_Abs
+                | Self::F64X2_Neg
+                | Self::F64X2_Sqrt
+                | Self::F64X2_Add
+                | Self::F64X2_Sub
+                | Self::F64X2_Mul
+                | Self::F64X2_Div
+                | Self::F64X2_Min
+                | Self::F64X2_Max
+                | Self::F64X2_Pmin
+                | Self::F64X2_Pmax
+                | Self::I32X4_Trunc_Sat_F32X4_S
+                | Self::I32X4_Trunc_Sat_F32X4_U
+                | Self::F32X4_Convert_I32X4_S
+                | Self::F32X4_Convert_I32X4_U
+                | Self::I32X4_Trunc_Sat_F64X2_S_Zero
+                | Self::I32X4_Trunc_Sat_F64X2_U_Zero
+                | Self::F64X2_Convert_Low_I32X4_S
+                | Self::F64X2_Convert_Low_I32X4_U
+                | Self::I8X16_Relaxed_Swizzle
+                | Self::I32X4_Relaxed_Trunc_F32X4_S
+                | Self::I32X4_Relaxed_Trunc_F32X4_U
+                | Self::I32X4_Relaxed_Trunc_F64X2_S_Zero
+                | Self::I32X4_Relaxed_Trunc_F64X2_U_Zero
+                | Self::F32X4_Relaxed_Madd
+                | Self::F32X4_Relaxed_Nmadd
+                | Self::F64X2_Relaxed_Madd
+                | Self::F64X2_Relaxed_Nmadd
+                | Self::I8X16_Relaxed_Laneselect
+                | Self::I16X8_Relaxed_Laneselect
+                | Self::I32X4_Relaxed_Laneselect
+                | Self::I64X2_Relaxed_Laneselect
+                | Self::F32X4_Relaxed_Min
+                | Self::F32X4_Relaxed_Max
+                | Self::F64X2_Relaxed_Min
+                | Self::F64X2_Relaxed_Max
+                | Self::I16X8_Relaxed_Q15mulr_S
+                | Self::I16X8_Relaxed_Dot_I8X16_I7X16_S
+                | Self::I32X4_Relaxed_Dot_I8X16_I7X16_Add_S
+        )
+    }
+
+    pub fn is_relaxed_simd(&self) -> bool {
+        matches!(
+            self,
+            Self::I8X16_Relaxed_Swizzle
+                | Self::I32X4_Relaxed_Trunc_F32X4_S
+                | Self::I32X4_Relaxed_Trunc_F32X4_U
+                | Self::I32X4_Relaxed_Trunc_F64X2_S_Zero
+                | Self::I32X4_Relaxed_Trunc_F64X2_U_Zero
+                | Self::F32X4_Relaxed_Madd
+                | Self::F32X4_Relaxed_Nmadd
+                | Self::F64X2_Relaxed_Madd
+                | Self::F64X2_Relaxed_Nmadd
+                | Self::I8X16_Relaxed_Laneselect
+                | Self::I16X8_Relaxed_Laneselect
+                | Self::I32X4_Relaxed_Laneselect
+                | Self::I64X2_Relaxed_Laneselect
+                | Self::F32X4_Relaxed_Min
+                | Self::F32X4_Relaxed_Max
+                | Self::F64X2_Relaxed_Min
+                | Self::F64X2_Relaxed_Max
+                | Self::I16X8_Relaxed_Q15mulr_S
+                | Self::I16X8_Relaxed_Dot_I8X16_I7X16_S
+                | Self::I32X4_Relaxed_Dot_I8X16_I7X16_Add_S
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Block(_, _) => "block",
+            Self::Loop(_, _) => "loop",
+            Self::If(_, _) | Self::IfElse(_, _, _) => "if",
+            Self::Try(_, _, _, _) | Self::TryDelegate(_, _, _) => "try",
+            Self::Throw(_) => "throw",
+            Self::Rethrow(_) => "rethrow",
+            Self::ThrowRef => "throw_ref",
+            Self::TryTable(_, _, _) => "try_table",
+            Self::Br(_) => "br",
+            Self::BrIf(_) => "br_if",
+            Self::BrTable(_, _) => "br_table",
+            Self::Call(_) => "call",
+            Self::CallIndirect(_, _) => "call_indirect",
+            Self::ReturnCall(_) => "return_call",
+            Self::ReturnCallIndirect(_, _) => "return_call_indirect",
+            Self::CallRef(_) => "call_ref",
+            Self::ReturnCallRef(_) => "return_call_ref",
+            Self::RefNull(_) => "ref.null",
+            Self::RefFunc(_) => "ref.func",
+            Self::RefAsNonNull => "ref.as_non_null",
+            Self::BrOnNull(_) => "br_on_null",
+            Self::BrOnNonNull(_) => "br_on_non_null",
+            Self::RefEq => "ref.eq",
+            Self::StructNew(_) => "struct.new",
+            Self::StructNewDefault(_) => "struct.new_default",
+            Self::StructGet(_, _) => "struct.get",
+            Self::StructGetS(_, _) => "struct.get_s",
+            Self::StructGetU(_, _) => "struct.get_u",
+            Self::StructSet(_, _) => "struct.set",
+            Self::ArrayNew(_) => "array.new",
+            Self::ArrayNewDefault(_) => "array.new_default",
+            Self::ArrayNewFixed(_, _) => "array.new_fixed",
+            Self::ArrayNewData(_, _) => "array.new_data",
+            Self::ArrayNewElem(_, _) => "array.new_elem",
+            Self::ArrayGet(_) => "array.get",
+            Self::ArrayGetS(_) => "array.get_s",
+            Self::ArrayGetU(_) => "array.get_u",
+            Self::ArraySet(_) => "array.set",
+            Self::ArrayLen => "array.len",
+            Self::ArrayFill(_) => "array.fill",
+            Self::ArrayCopy(_, _) => "array.copy",
+            Self::ArrayInitData(_, _) => "array.init_data",
+            Self::ArrayInitElem(_, _) => "array.init_elem",
+            Self::RefTest(_) => "ref.test",
+            Self::RefCast(_) => "ref.cast",
+            Self::BrOnCast(_, _, _) => "br_on_cast",
+            Self::BrOnCastFail(_, _, _) => "br_on_cast_fail",
+            Self::AnyConvertExtern => "any.convert_extern",
+            Self::ExternConvertAny => "extern.convert_any",
+            Self::RefI31 => "ref.i31",
+            Self::I31GetS => "i31.get_s",
+            Self::I31GetU => "i31.get_u",
+            Self::SelectType(_) => "select",
+            Self::LocalGet(_) => "local.get",
+            Self::LocalSet(_) => "local.set",
+            Self::LocalTee(_) => "local.tee",
+            Self::GlobalGet(_) => "global.get",
+            Self::GlobalSet(_) => "global.set",
+            Self::TableGet(_) => "table.get",
+            Self::TableSet(_) => "table.set",
+            Self::TableInit(_, _) => "table.init",
+            Self::ElemDrop(_) => "elem.drop",
+            Self::TableCopy(_, _) => "table.copy",
+            Self::TableGrow(_) => "table.grow",
+            Self::TableSize(_) => "table.size",
+            Self::TableFill(_) => "table.fill",
+            Self::MemorySize(_) => "memory.size",
+            Self::MemoryGrow(_) => "memory.grow",
+            Self::MemoryInit(_, _) => "memory.init",
+            Self::DataDrop(_) => "data.drop",
+            Self::MemoryCopy(_, _) => "memory.copy",
+            Self::MemoryFill(_) => "memory.fill",
+            Self::I32Const(_) => "i32.const",
+            Self::I64Const(_) => "i64.const",
+            Self::F32Const(_) => "f32.const",
+            Self::F64Const(_) => "f64.const",
+            Self::V128_Const(_) => "v128.const",
+            Self::I8X16_Shuffle(_) => "i8x16.shuffle",
+            Self::UnReachable => "unreachable",
+            Self::Nop => "nop",
+            Self::Return => "return",
+            Self::RefIsNull => "ref.is_null",
+            Self::Drop => "drop",
+            Self::Select => "select",
+            Self::I32Load(_) => "i32.load",
+            Self::I64Load(_) => "i64.load",
+            Self::F32Load(_) => "f32.load",
+            Self::F64Load(_) => "f64.load",
+            Self::I32load8S(_) => "i32.load8_s",
+            Self::I32Load8_u(_) => "i32.load8_u",
+            Self::I32Load16_s(_) => "i32.load16_s",
+            Self::I32Load16_u(_) => "i32.load16_u",
+            Self::I64Load8_s(_) => "i64.load8_s",
+            Self::I64Load8_u(_) => "i64.load8_u",
+            Self::I64Load16_s(_) => "i64.load16_s",
+            Self::I64Load16_u(_) => "i64.load16_u",
+            Self::I64Load32_s(_) => "i64.load32_s",
+            Self::I64Load32_u(_) => "i64.load32_u",
+            Self::I32Store(_) => "i32.store",
+            Self::I64Store(_) => "i64.store",
+            Self::F32Store(_) => "f32.store",
+            Self::F64Store(_) => "f64.store",
+            Self::I32Store8(_) => "i32.store8",
+            Self::I32Store16(_) => "i32.store16",
+            Self::I64Store8(_) => "i64.store8",
+            Self::I64Store16(_) => "i64.store16",
+            Self::I64Store32(_) => "i64.store32",
+            Self::I32Eqz => "i32.eqz",
+            Self::I32Eq => "i32.eq",
+            Self::I32Ne => "i32.ne",
+            Self::I32Lts => "i32.lt_s",
+            Self::I32Ltu => "i32.lt_u",
+            Self::I32Gts => "i32.gt_s",
+            Self::I32Gtu => "i32.gt_u",
+            Self::I32Les => "i32.le_s",
+            Self::I32Leu => "i32.le_u",
+            Self::I32Ges => "i32.ge_s",
+            Self::I32Geu => "i32.ge_u",
+            Self::I64Eqz => "i64.eqz",
+            Self::I64Eq => "i64.eq",
+            Self::I64Ne => "i64.ne",
+            Self::I64Lts => "i64.lt_s",
+            Self::I64Ltu => "i64.lt_u",
+            Self::I64Gts => "i64.gt_s",
+            Self::I64Gtu => "i64.gt_u",
+            Self::I64Les => "i64.le_s",
+            Self::I64Leu => "i64.le_u",
+            Self::I64Ges => "i64.ge_s",
+            Self::I64Geu => "i64.ge_u",
+            Self::F32Eq => "f32.eq",
+            Self::F32Ne => "f32.ne",
+            Self::F32Lt => "f32.lt",
+            Self::F32Gt => "f32.gt",
+            Self::F32Le => "f32.le",
+            Self::F32Ge => "f32.ge",
+            Self::F64Eq => "f64.eq",
+            Self::F64Ne => "f64.ne",
+            Self::F64Lt => "f64.lt",
+            Self::F64Gt => "f64.gt",
+            Self::F64Le => "f64.le",
+            Self::F64Ge => "f64.ge",
+            Self::I32Clz => "i32.clz",
+            Self::I32Ctz => "i32.ctz",
+            Self::I32PopcCnt => "i32.popcnt",
+            Self::I32Add => "i32.add",
+            Self::I32Sub => "i32.sub",
+            Self::I32Mul => "i32.mul",
+            Self::I32Divs => "i32.div_s",
+            Self::I32Divu => "i32.div_u",
+            Self::I32RemS => "i32.rem_s",
+            Self::I32Remu => "i32.rem_u",
+            Self::I32And => "i32.and",
+            Self::I32Or => "i32.or",
+            Self::I32Xor => "i32.xor",
+            Self::I32Shl => "i32.shl",
+            Self::I32Shrs => "i32.shr_s",
+            Self::I32Sgru => "i32.shr_u",
+            Self::I32Rotl => "i32.rotl",
+            Self::I32Rotr => "i32.rotr",
+            Self::I64Clz => "i64.clz",
+            Self::I64Ctz => "i64.ctz",
+            Self::I64PopcCnt => "i64.popcnt",
+            Self::I64Add => "i64.add",
+            Self::I64Sub => "i64.sub",
+            Self::I64Mul => "i64.mul",
+            Self::I64Divs => "i64.div_s",
+            Self::I64Divu => "i64.div_u",
+            Self::I64RemS => "i64.rem_s",
+            Self::I64Remu => "i64.rem_u",
+            Self::I64And => "i64.and",
+            Self::I64Or => "i64.or",
+            Self::I64Xor => "i64.xor",
+            Self::I64Shl => "i64.shl",
+            Self::I64Shrs => "i64.shr_s",
+            Self::I64Sgru => "i64.shr_u",
+            Self::I64Rotl => "i64.rotl",
+            Self::I64Rotr => "i64.rotr",
+            Self::F32Abs => "f32.abs",
+            Self::F32Neg => "f32.neg",
+            Self::F32Ceil => "f32.ceil",
+            Self::F32Floor => "f32.floor",
+            Self::F32Trunc => "f32.trunc",
+            Self::F32Nearest => "f32.nearest",
+            Self::F32Sqrt => "f32.sqrt",
+            Self::F32Add => "f32.add",
+            Self::F32Sub => "f32.sub",
+            Self::F32Mul => "f32.mul",
+            Self::F32Div => "f32.div",
+            Self::F32Min => "f32.min",
+            Self::F32Max => "f32.max",
+            Self::F32CopySig => "f32.copysign",
+            Self::F64Abs => "f64.abs",
+            Self::F64Neg => "f64.neg",
+            Self::F64Ceil => "f64.ceil",
+            Self::F64Floor => "f64.floor",
+            Self::F64Trunc => "f64.trunc",
+            Self::F64Nearest => "f64.nearest",
+            Self::F64Sqrt => "f64.sqrt",
+            Self::F64Add => "f64.add",
+            Self::F64Sub => "f64.sub",
+            Self::F64Mul => "f64.mul",
+            Self::F64Div => "f64.div",
+            Self::F64Min => "f64.min",
+            Self::F64Max => "f64.max",
+            Self::F64CopySig => "f64.copysign",
+            Self::I32WrapI64 => "i32.wrap_i64",
+            Self::I32TruncF32S => "i32.trunc_f32_s",
+            Self::I32TruncF32U => "i32.trunc_f32_u",
+            Self::I32TruncF64S => "i32.trunc_f64_s",
+            Self::I32TruncF64U => "i32.trunc_f64_u",
+            Self::I64ExtendI32S => "i64.extend_i32_s",
+            Self::I64ExtendI32U => "i64.extend_i32_u",
+            Self::I64TruncF32S => "i64.trunc_f32_s",
+            Self::I64TruncF32U => "i64.trunc_f32_u",
+            Self::I64TruncF64S => "i64.trunc_f64_s",
+            Self::I64TruncF64U => "i64.trunc_f64_u",
+            Self::F32ConvertI32S => "f32.convert_i32_s",
+            Self::F32ConvertI32U => "f32.convert_i32_u",
+            Self::F32ConvertI64S => "f32.convert_i64_s",
+            Self::F32ConvertI64U => "f32.convert_i64_u",
+            Self::F32DenoteF64 => "f32.demote_f64",
+            Self::F64ConvertI32S => "f64.convert_i32_s",
+            Self::F64ConvertI32U => "f64.convert_i32_u",
+            Self::F64ConvertI64S => "f64.convert_i64_s",
+            Self::F64ConvertI64U => "f64.convert_i64_u",
+            Self::F64PromoteF32 => "f64.promote_f32",
+            Self::I32ReinterpetF32 => "i32.reinterpret_f32",
+            Self::I64ReinterpetF64 => "i64.reinterpret_f64",
+            Self::F32ReinterpetI32 => "f32.reinterpret_i32",
+            Self::F64RetineroetI64 => "f64.reinterpret_i64",
+            Self::I32Extend8S => "i32.extend8_s",
+            Self::I32Extend16S => "i32.extend16_s",
+            Self::I64Extend8S => "i64.extend8_s",
+            Self::I64Extend16S => "i64.extend16_s",
+            Self::I64Extend32S => "i64.extend32_s",
+            Self::I32TruncSatF32S => "i32.trunc_sat_f32_s",
+            Self::I32TruncSatF32U => "i32.trunc_sat_f32_u",
+            Self::I32TruncSatF64S => "i32.trunc_sat_f64_s",
+            Self::I32TruncSatF64U => "i32.trunc_sat_f64_u",
+            Self::I64TruncSatF32S => "i64.trunc_sat_f32_s",
+            Self::I64TruncSatF32U => "i64.trunc_sat_f32_u",
+            Self::I64TructSatF64S => "i64.trunc_sat_f64_s",
+            Self::I64TructSatF64U => "i64.trunc_sat_f64_u",
+            Self::V128_Load(_) => "v128.load",
+            Self::V128_Load_8x8_S(_) => "v128.load8x8_s",
+            Self::V128_Load_8x8_U(_) => "v128.load8x8_u",
+            Self::V128_Load_16x4_S(_) => "v128.load16x4_s",
+            Self::V128_Load_16x4_U(_) => "v128.load16x4_u",
+            Self::V128_Load_32x2_S(_) => "v128.load32x2_s",
+            Self::V128_Load_32x2_U(_) => "v128.load32x2_u",
+            Self::V128_Load_8_Splat(_) => "v128.load8_splat",
+            Self::V128_Load_16_Splat(_) => "v128.load16_splat",
+            Self::V128_Load_32_Splat(_) => "v128.load32_splat",
+            Self::V128_Load_64_Splat(_) => "v128.load64_splat",
+            Self::V128_Store(_) => "v128.store",
+            Self::V128_Load_32_Zero(_) => "v128.load32_zero",
+            Self::V128_Load_64_Zero(_) => "v128.load64_zero",
+            Self::V128_Load_8_Lane(_, _) => "v128.load8_lane",
+            Self::V128_Load_16_Lane(_, _) => "v128.load16_lane",
+            Self::V128_Load_32_Lane(_, _) => "v128.load32_lane",
+            Self::V128_Load_64_Lane(_, _) => "v128.load64_lane",
+            Self::V128_Store_8_Lane(_, _) => "v128.store8_lane",
+            Self::V128_Store_16_Lane(_, _) => "v128.store16_lane",
+            Self::V128_Store_32_Lane(_, _) => "v128.store32_lane",
+            Self::V128_Store_64_Lane(_, _) => "v128.store64_lane",
+            Self::I8X16_Extract_Lane_S(_) => "i8x16.extract_lane_s",
+            Self::I8X16_Extract_Lane_U(_) => "i8x16.extract_lane_u",
+            Self::I8X16_Replace_Lane(_) => "i8x16.replace_lane",
+            Self::I16X8_Extract_Lane_S(_) => "i16x8.extract_lane_s",
+            Self::I16X8_Extract_Lane_U(_) => "i16x8.extract_lane_u",
+            Self::I16X8_Replace_Lane(_) => "i16x8.replace_lane",
+            Self::I32X4_Extract_Lane(_) => "i32x4.extract_lane",
+            Self::I32X4_Replace_Lane(_) => "i32x4.replace_lane",
+            Self::I64X2_Extract_Lane(_) => "i64x2.extract_lane",
+            Self::I64X2_Replace_Lane(_) => "i64x2.replace_lane",
+            Self::F32X4_Extract_Lane(_) => "f32x4.extract_lane",
+            Self::F32X4_Replace_Lane(_) => "f32x4.replace_lane",
+            Self::F64X2_Extract_Lane(_) => "f64x2.extract_lane",
+            Self::F64X2_Replace_Lane(_) => "f64x2.replace_lane",
+            Self::I8x16_Swizzle => "i8x16.swizzle",
+            Self::I8X16_Splat => "i8x16.splat",
+            Self::I16X8_Splat => "i16x8.splat",
+            Self::I32X4_Splat => "i32x4.splat",
+            Self::I64X2_Splat => "i64x2.splat",
+            Self::F32X4_Splat => "f32x4.splat",
+            Self::F64X2_Splat => "f64x2.splat",
+            Self::I8X16_Eq => "i8x16.eq",
+            Self::I8X16_Ne => "i8x16.ne",
+            Self::I8X16_Lt_S => "i8x16.lt_s",
+            Self::I8X16_Lt_U => "i8x16.lt_u",
+            Self::I8X16_Gt_S => "i8x16.gt_s",
+            Self::I8X16_Gt_U => "i8x16.gt_u",
+            Self::I8X16_Le_S => "i8x16.le_s",
+            Self::I8X16_Le_U => "i8x16.le_u",
+            Self::I8X16_Ge_S => "i8x16.ge_s",
+            Self::I8X16_Ge_U => "i8x16.ge_u",
+            Self::I16X8_Eq => "i16x8.eq",
+            Self::I16X8_Ne => "i16x8.ne",
+            Self::I16X8_Lt_S => "i16x8.lt_s",
+            Self::I16X8_Lt_U => "i16x8.lt_u",
+            Self::I16X8_Gt_S => "i16x8.gt_s",
+            Self::I16X8_Gt_U => "i16x8.gt_u",
+            Self::I16X8_Le_S => "i16x8.le_s",
+            Self::I16X8_Le_U => "i16x8.le_u",
+            Self::I16X8_Ge_S => "i16x8.ge_s",
+            Self::I16X8_Ge_U => "i16x8.ge_u",
+            Self::I32X4_Eq => "i32x4.eq",
+            Self::I32X4_Ne => "i32x4.ne",
+            Self::I32X4_Lt_S => "i32x4.lt_s",
+            Self::I32X4_Lt_U => "i32x4.lt_u",
+            Self::I32X4_Gt_S => "i32x4.gt_s",
+            Self::I32X4_Gt_U => "i32x4.gt_u",
+            Self::I32X4_Le_S => "i32x4.le_s",
+            Self::I32X4_Le_U => "i32x4.le_u",
+            Self::I32X4_Ge_S => "i32x4.ge_s",
+            Self::I32X4_Ge_U => "i32x4.ge_u",
+            Self::F32X4_Eq => "f32x4.eq",
+            Self::F32X4_Ne => "f32x4.ne",
+            Self::F32X4_Lt => "f32x4.lt",
+            Self::F32X4_Gt => "f32x4.gt",
+            Self::F32X4_Le => "f32x4.le",
+            Self::F32X4_Ge => "f32x4.ge",
+            Self::F64X2_Eq => "f64x2.eq",
+            Self::F64X2_Ne => "f64x2.ne",
+            Self::F64X2_Lt => "f64x2.lt",
+            Self::F64X2_Gt => "f64x2.gt",
+            Self::F64X2_Le => "f64x2.le",
+            Self::F64X2_Ge => "f64x2.ge",
+            Self::V128_Not => "v128.not",
+            Self::V128_And => "v128.and",
+            Self::V128_Andnot => "v128.andnot",
+            Self::V128_Or => "v128.or",
+            Self::V128_Xor => "v128.xor",
+            Self::V128_Bitselect => "v128.bitselect",
+            Self::V128_Any_True => "v128.any_true",
+            Self::F32X4_Demote_F64X2_Zero => "f32x4.demote_f64x2_zero",
+            Self::F64X2_Promote_Low_F32X4 => "f64x2.promote_low_f32x4",
+            Self::I8X16_Abs => "i8x16.abs",
+            Self::I8X16_Neg => "i8x16.neg",
+            Self::I8X16_Popcnt => "i8x16.popcnt",
+            Self::I8X16_All_True => "i8x16.all_true",
+            Self::I8X16_Bitmask => "i8x16.bitmask",
+            Self::I8X16_Narrow_I16X8_S => "i8x16.narrow_i16x8_s",
+            Self::I8X16_Narrow_I16X8_U => "i8x16.narrow_i16x8_u",
+            Self::F32X4_Ceil => "f32x4.ceil",
+            Self::F32X4_Floor => "f32x4.floor",
+            Self::F32X4_Trunc => "f32x4.trunc",
+            Self::F32X4_Nearest => "f32x4.nearest",
+            Self::I8X16_Shl => "i8x16.shl",
+            Self::I8X16_Shr_S => "i8x16.shr_s",
+            Self::I8X16_Shr_U => "i8x16.shr_u",
+            Self::I8X16_Add => "i8x16.add",
+            Self::I8X16_Add_Sat_S => "i8x16.add_sat_s",
+            Self::I8X16_Add_Sat_U => "i8x16.add_sat_u",
+            Self::I8X16_Sub => "i8x16.sub",
+            Self::I8X16_Sub_Sat_S => "i8x16.sub_sat_s",
+            Self::I8X16_Sub_Sat_U => "i8x16.sub_sat_u",
+            Self::F64X2_Ceil => "f64x2.ceil",
+            Self::F64X2_Floor => "f64x2.floor",
+            Self::I8X16_Min_S => "i8x16.min_s",
+            Self::I8X16_Min_U => "i8x16.min_u",
+            Self::I8X16_Max_S => "i8x16.max_s",
+            Self::I8X16_Max_U => "i8x16.max_u",
+            Self::F64X2_Trunc => "f64x2.trunc",
+            Self::I8X16_Avgr_U => "i8x16.avgr_u",
+            Self::I16X8_Extadd_Pairwise_I8X16_S => "i16x8.extadd_pairwise_i8x16_s",
+            Self::I16X8_Extadd_Pairwise_I8X16_U => "i16x8.extadd_pairwise_i8x16_u",
+            Self::I32X4_Extadd_Pairwise_I16X8_S => "i32x4.extadd_pairwise_i16x8_s",
+            Self::I32X4_Extadd_Pairwise_I16X8_U => "i32x4.extadd_pairwise_i16x8_u",
+            Self::I16X8_Abs => "i16x8.abs",
+            Self::I16X8_Neg => "i16x8.neg",
+            Self::I16X8_Q15mulr_Sat_S => "i16x8.q15mulr_sat_s",
+            Self::I16X8_All_True => "i16x8.all_true",
+            Self::I16X8_Bitmask => "i16x8.bitmask",
+            Self::I16X8_Narrow_I32X4_S => "i16x8.narrow_i32x4_s",
+            Self::I16X8_Narrow_I32X4_U => "i16x8.narrow_i32x4_u",
+            Self::I16X8_Extend_Low_I8X16_S => "i16x8.extend_low_i8x16_s",
+            Self::I16X8_Extend_High_I8X16_S => "i16x8.extend_high_i8x16_s",
+            Self::I16X8_Extend_Low_I8X16_U => "i16x8.extend_low_i8x16_u",
+            Self::I16X8_Extend_High_I8X16_U => "i16x8.extend_high_i8x16_u",
+            Self::I16X8_Shl => "i16x8.shl",
+            Self::I16X8_Shr_S => "i16x8.shr_s",
+            Self::I16X8_Shr_U => "i16x8.shr_u",
+            Self::I16X8_Add => "i16x8.add",
+            Self::I16X8_Add_Sat_S => "i16x8.add_sat_s",
+            Self::I16X8_Add_Sat_U => "i16x8.add_sat_u",
+            Self::I16X8_Sub => "i16x8.sub",
+            Self::I16X8_Sub_Sat_S => "i16x8.sub_sat_s",
+            Self::I16X8_Sub_Sat_U => "i16x8.sub_sat_u",
+            Self::F64X2_Nearest => "f64x2.nearest",
+            Self::I16X8_Mul => "i16x8.mul",
+            Self::I16X8_Min_S => "i16x8.min_s",
+            Self::I16X8_Min_U => "i16x8.min_u",
+            Self::I16X8_Max_S => "i16x8.max_s",
+            Self::I16X8_Max_U => "i16x8.max_u",
+            Self::I16X8_Avgr_U => "i16x8.avgr_u",
+            Self::I16X8_Extmul_Low_I8X16_S => "i16x8.extmul_low_i8x16_s",
+            Self::I16X8_Extmul_High_I8X16_S => "i16x8.extmul_high_i8x16_s",
+            Self::I16X8_Extmul_Low_I8X16_U => "i16x8.extmul_low_i8x16_u",
+            Self::I16X8_Extmul_High_I8X16_U => "i16x8.extmul_high_i8x16_u",
+            Self::I32X4_Abs => "i32x4.abs",
+            Self::I32X4_Neg => "i32x4.neg",
+            Self::I32X4_All_True => "i32x4.all_true",
+            Self::I32X4_Bitmask => "i32x4.bitmask",
+            Self::I32X4_Extend_Low_I16X8_S => "i32x4.extend_low_i16x8_s",
+            Self::I32X4_Extend_High_I16X8_S => "i32x4.extend_high_i16x8_s",
+            Self::I32X4_Extend_Low_I16X8_U => "i32x4.extend_low_i16x8_u",
+            Self::I32X4_Extend_High_I16X8_U => "i32x4.extend_high_i16x8_u",
+            Self::I32X4_Shl => "i32x4.shl",
+            Self::I32X4_Shr_S => "i32x4.shr_s",
+            Self::I32X4_Shr_U => "i32x4.shr_u",
+            Self::I32X4_Add => "i32x4.add",
+            Self::I32X4_Sub => "i32x4.sub",
+            Self::I32X4_Mul => "i32x4.mul",
+            Self::I32X4_Min_S => "i32x4.min_s",
+            Self::I32X4_Min_U => "i32x4.min_u",
+            Self::I32X4_Max_S => "i32x4.max_s",
+            Self::I32X4_Max_U => "i32x4.max_u",
+            Self::I32X4_Dot_I16X8_S => "i32x4.dot_i16x8_s",
+            Self::I32X4_Extmul_Low_I16X8_S => "i32x4.extmul_low_i16x8_s",
+            Self::I32X4_Extmul_High_I16X8_S => "i32x4.extmul_high_i16x8_s",
+            Self::I32X4_Extmul_Low_I16X8_U => "i32x4.extmul_low_i16x8_u",
+            Self::I32X4_Extmul_High_I16X8_U => "i32x4.extmul_high_i16x8_u",
+            Self::I64X2_Abs => "i64x2.abs",
+            Self::I64X2_Neg => "i64x2.neg",
+            Self::I64X2_All_True => "i64x2.all_true",
+            Self::I64X2_Bitmask => "i64x2.bitmask",
+            Self::I64X2_Extend_Low_I32X4_S => "i64x2.extend_low_i32x4_s",
+            Self::I64X2_Extend_High_I32X4_S => "i64x2.extend_high_i32x4_s",
+            Self::I64X2_Extend_Low_I32X4_U => "i64x2.extend_low_i32x4_u",
+            Self::I64X2_Extend_High_I32X4_U => "i64x2.extend_high_i32x4_u",
+            Self::I64X2_Shl => "i64x2.shl",
+            Self::I64X2_Shr_S => "i64x2.shr_s",
+            Self::I64X2_Shr_U => "i64x2.shr_u",
+            Self::I64X2_Add => "i64x2.add",
+            Self::I64X2_Sub => "i64x2.sub",
+            Self::I64X2_Mul => "i64x2.mul",
+            Self::I64X2_Eq => "i64x2.eq",
+            Self::I64X2_Ne => "i64x2.ne",
+            Self::I64X2_Lt_S => "i64x2.lt_s",
+            Self::I64X2_Gt_S => "i64x2.gt_s",
+            Self::I64X2_Le_S => "i64x2.le_s",
+            Self::I64X2_Ge_S => "i64x2.ge_s",
+            Self::I64X2_Extmul_Low_I32X4_S => "i64x2.extmul_low_i32x4_s",
+            Self::I64X2_Extmul_High_I32X4_S => "i64x2.extmul_high_i32x4_s",
+            Self::I64X2_Extmul_Low_I32X4_U => "i64x2.extmul_low_i32x4_u",
+            Self::I64X2_Extmul_High_I32X4_U => "i64x2.extmul_high_i32x4_u",
+            Self::F32X4_Abs => "f32x4.abs",
+            Self::F32X4_Neg => "f32x4.neg",
+            Self::F32X4_Sqrt => "f32x4.sqrt",
+            Self::F32X4_Add => "f32x4.add",
+            Self::F32X4_Sub => "f32x4.sub",
+            Self::F32X4_Mul => "f32x4.mul",
+            Self::F32X4_Div => "f32x4.div",
+            Self::F32X4_Min => "f32x4.min",
+            Self::F32X4_Max => "f32x4.max",
+            Self::F32X4_Pmin => "f32x4.pmin",
+            Self::F32X4_Pmax => "f32x4.pmax",
+            Self::F64X2_Abs => "f64x2.abs",
+            Self::F64X2_Neg => "f64x2.neg",
+            Self::F64X2_Sqrt => "f64x2.sqrt",
+            Self::F64X2_Add => "f64x2.add",
+            Self::F64X2_Sub => "f64x2.sub",
+            Self::F64X2_Mul => "f64x2.mul",
+            Self::F64X2_Div => "f64x2.div",
+            Self::F64X2_Min => "f64x2.min",
+            Self::F64X2_Max => "f64x2.max",
+            Self::F64X2_Pmin => "f64x2.pmin",
+            Self::F64X2_Pmax => "f64x2.pmax",
+            Self::I32X4_Trunc_Sat_F32X4_S => "i32x4.trunc_sat_f32x4_s",
+            Self::I32X4_Trunc_Sat_F32X4_U => "i32x4.trunc_sat_f32x4_u",
+            Self::F32X4_Convert_I32X4_S => "f32x4.convert_i32x4_s",
+            Self::F32X4_Convert_I32X4_U => "f32x4.convert_i32x4_u",
+            Self::I32X4_Trunc_Sat_F64X2_S_Zero => "i32x4.trunc_sat_f64x2_s_zero",
+            Self::I32X4_Trunc_Sat_F64X2_U_Zero => "i32x4.trunc_sat_f64x2_u_zero",
+            Self::F64X2_Convert_Low_I32X4_S => "f64x2.convert_low_i32x4_s",
+            Self::F64X2_Convert_Low_I32X4_U => "f64x2.convert_low_i32x4_u",
+            Self::I8X16_Relaxed_Swizzle => "i8x16.relaxed_swizzle",
+            Self::I32X4_Relaxed_Trunc_F32X4_S => "i32x4.relaxed_trunc_f32x4_s",
+            Self::I32X4_Relaxed_Trunc_F32X4_U => "i32x4.relaxed_trunc_f32x4_u",
+            Self::I32X4_Relaxed_Trunc_F64X2_S_Zero => "i32x4.relaxed_trunc_f64x2_s_zero",
+            Self::I32X4_Relaxed_Trunc_F64X2_U_Zero => "i32x4.relaxed_trunc_f64x2_u_zero",
+            Self::F32X4_Relaxed_Madd => "f32x4.relaxed_madd",
+            Self::F32X4_Relaxed_Nmadd => "f32x4.relaxed_nmadd",
+            Self::F64X2_Relaxed_Madd => "f64x2.relaxed_madd",
+            Self::F64X2_Relaxed_Nmadd => "f64x2.relaxed_nmadd",
+            Self::I8X16_Relaxed_Laneselect => "i8x16.relaxed_laneselect",
+            Self::I16X8_Relaxed_Laneselect => "i16x8.relaxed_laneselect",
+            Self::I32X4_Relaxed_Laneselect => "i32x4.relaxed_laneselect",
+            Self::I64X2_Relaxed_Laneselect => "i64x2.relaxed_laneselect",
+            Self::F32X4_Relaxed_Min => "f32x4.relaxed_min",
+            Self::F32X4_Relaxed_Max => "f32x4.relaxed_max",
+            Self::F64X2_Relaxed_Min => "f64x2.relaxed_min",
+            Self::F64X2_Relaxed_Max => "f64x2.relaxed_max",
+            Self::I16X8_Relaxed_Q15mulr_S => "i16x8.relaxed_q15mulr_s",
+            Self::I16X8_Relaxed_Dot_I8X16_I7X16_S => "i16x8.relaxed_dot_i8x16_i7x16_s",
+            Self::I32X4_Relaxed_Dot_I8X16_I7X16_Add_S => "i32x4.relaxed_dot_i8x16_i7x16_add_s",
+            Self::MemoryAtomicNotify(_) => "memory.atomic.notify",
+            Self::MemoryAtomicWait32(_) => "memory.atomic.wait32",
+            Self::MemoryAtomicWait64(_) => "memory.atomic.wait64",
+            Self::I32AtomicLoad(_) => "i32.atomic.load",
+            Self::I64AtomicLoad(_) => "i64.atomic.load",
+            Self::I32AtomicLoad8U(_) => "i32.atomic.load8_u",
+            Self::I32AtomicLoad16U(_) => "i32.atomic.load16_u",
+            Self::I64AtomicLoad8U(_) => "i64.atomic.load8_u",
+            Self::I64AtomicLoad16U(_) => "i64.atomic.load16_u",
+            Self::I64AtomicLoad32U(_) => "i64.atomic.load32_u",
+            Self::I32AtomicStore(_) => "i32.atomic.store",
+            Self::I64AtomicStore(_) => "i64.atomic.store",
+            Self::I32AtomicStore8(_) => "i32.atomic.store8",
+            Self::I32AtomicStore16(_) => "i32.atomic.store16",
+            Self::I64AtomicStore8(_) => "i64.atomic.store8",
+            Self::I64AtomicStore16(_) => "i64.atomic.store16",
+            Self::I64AtomicStore32(_) => "i64.atomic.store32",
+            Self::I32AtomicRmwAdd(_) => "i32.atomic.rmw.add",
+            Self::I64AtomicRmwAdd(_) => "i64.atomic.rmw.add",
+            Self::I32AtomicRmw8AddU(_) => "i32.atomic.rmw8.add_u",
+            Self::I32AtomicRmw16AddU(_) => "i32.atomic.rmw16.add_u",
+            Self::I64AtomicRmw8AddU(_) => "i64.atomic.rmw8.add_u",
+            Self::I64AtomicRmw16AddU(_) => "i64.atomic.rmw16.add_u",
+            Self::I64AtomicRmw32AddU(_) => "i64.atomic.rmw32.add_u",
+            Self::I32AtomicRmwSub(_) => "i32.atomic.rmw.sub",
+            Self::I64AtomicRmwSub(_) => "i64.atomic.rmw.sub",
+            Self::I32AtomicRmw8SubU(_) => "i32.atomic.rmw8.sub_u",
+            Self::I32AtomicRmw16SubU(_) => "i32.atomic.rmw16.sub_u",
+            Self::I64AtomicRmw8SubU(_) => "i64.atomic.rmw8.sub_u",
+            Self::I64AtomicRmw16SubU(_) => "i64.atomic.rmw16.sub_u",
+            Self::I64AtomicRmw32SubU(_) => "i64.atomic.rmw32.sub_u",
+            Self::I32AtomicRmwAnd(_) => "i32.atomic.rmw.and",
+            Self::I64AtomicRmwAnd(_) => "i64.atomic.rmw.and",
+            Self::I32AtomicRmw8AndU(_) => "i32.atomic.rmw8.and_u",
+            Self::I32AtomicRmw16AndU(_) => "i32.atomic.rmw16.and_u",
+            Self::I64AtomicRmw8AndU(_) => "i64.atomic.rmw8.and_u",
+            Self::I64AtomicRmw16AndU(_) => "i64.atomic.rmw16.and_u",
+            Self::I64AtomicRmw32AndU(_) => "i64.atomic.rmw32.and_u",
+            Self::I32AtomicRmwOr(_) => "i32.atomic.rmw.or",
+            Self::I64AtomicRmwOr(_) => "i64.atomic.rmw.or",
+            Self::I32AtomicRmw8OrU(_) => "i32.atomic.rmw8.or_u",
+            Self::I32AtomicRmw16OrU(_) => "i32.atomic.rmw16.or_u",
+            Self::I64AtomicRmw8OrU(_) => "i64.atomic.rmw8.or_u",
+            Self::I64AtomicRmw16OrU(_) => "i64.atomic.rmw16.or_u",
+            Self::I64AtomicRmw32OrU(_) => "i64.atomic.rmw32.or_u",
+            Self::I32AtomicRmwXor(_) => "i32.atomic.rmw.xor",
+            Self::I64AtomicRmwXor(_) => "i64.atomic.rmw.xor",
+            Self::I32AtomicRmw8XorU(_) => "i32.atomic.rmw8.xor_u",
+            Self::I32AtomicRmw16XorU(_) => "i32.atomic.rmw16.xor_u",
+            Self::I64AtomicRmw8XorU(_) => "i64.atomic.rmw8.xor_u",
+            Self::I64AtomicRmw16XorU(_) => "i64.atomic.rmw16.xor_u",
+            Self::I64AtomicRmw32XorU(_) => "i64.atomic.rmw32.xor_u",
+            Self::I32AtomicRmwXchg(_) => "i32.atomic.rmw.xchg",
+            Self::I64AtomicRmwXchg(_) => "i64.atomic.rmw.xchg",
+            Self::I32AtomicRmw8XchgU(_) => "i32.atomic.rmw8.xchg_u",
+            Self::I32AtomicRmw16XchgU(_) => "i32.atomic.rmw16.xchg_u",
+            Self::I64AtomicRmw8XchgU(_) => "i64.atomic.rmw8.xchg_u",
+            Self::I64AtomicRmw16XchgU(_) => "i64.atomic.rmw16.xchg_u",
+            Self::I64AtomicRmw32XchgU(_) => "i64.atomic.rmw32.xchg_u",
+            Self::I32AtomicRmwCmpxchg(_) => "i32.atomic.rmw.cmpxchg",
+            Self::I64AtomicRmwCmpxchg(_) => "i64.atomic.rmw.cmpxchg",
+            Self::I32AtomicRmw8CmpxchgU(_) => "i32.atomic.rmw8.cmpxchg_u",
+            Self::I32AtomicRmw16CmpxchgU(_) => "i32.atomic.rmw16.cmpxchg_u",
+            Self::I64AtomicRmw8CmpxchgU(_) => "i64.atomic.rmw8.cmpxchg_u",
+            Self::I64AtomicRmw16CmpxchgU(_) => "i64.atomic.rmw16.cmpxchg_u",
+            Self::I64AtomicRmw32CmpxchgU(_) => "i64.atomic.rmw32.cmpxchg_u",
+            Self::AtomicFence => "atomic.fence",
+        }
+    }
+
+    /// Whether this is one of the threads proposal's atomic instructions.
+    pub fn is_atomic(&self) -> bool {
+        matches!(
+            self,
+            Self::MemoryAtomicNotify(..)
+                | Self::MemoryAtomicWait32(..)
+                | Self::MemoryAtomicWait64(..)
+                | Self::I32AtomicLoad(..)
+                | Self::I64AtomicLoad(..)
+                | Self::I32AtomicLoad8U(..)
+                | Self::I32AtomicLoad16U(..)
+                | Self::I64AtomicLoad8U(..)
+                | Self::I64AtomicLoad16U(..)
+                | Self::I64AtomicLoad32U(..)
+                | Self::I32AtomicStore(..)
+                | Self::I64AtomicStore(..)
+                | Self::I32AtomicStore8(..)
+                | Self::I32AtomicStore16(..)
+                | Self::I64AtomicStore8(..)
+                | Self::I64AtomicStore16(..)
+                | Self::I64AtomicStore32(..)
+                | Self::I32AtomicRmwAdd(..)
+                | Self::I64AtomicRmwAdd(..)
+                | Self::I32AtomicRmw8AddU(..)
+                | Self::I32AtomicRmw16AddU(..)
+                | Self::I64AtomicRmw8AddU(..)
+                | Self::I64AtomicRmw16AddU(..)
+                | Self::I64AtomicRmw32AddU(..)
+                | Self::I32AtomicRmwSub(..)
+                | Self::I64AtomicRmwSub(..)
+                | Self::I32AtomicRmw8SubU(..)
+                | Self::I32AtomicRmw16SubU(..)
+                | Self::I64AtomicRmw8SubU(..)
+                | Self::I64AtomicRmw16SubU(..)
+                | Self::I64AtomicRmw32SubU(..)
+                | Self::I32AtomicRmwAnd(..)
+                | Self::I64AtomicRmwAnd(..)
+                | Self::I32AtomicRmw8AndU(..)
+                | Self::I32AtomicRmw16AndU(..)
+                | Self::I64AtomicRmw8AndU(..)
+                | Self::I64AtomicRmw16AndU(..)
+                | Self::I64AtomicRmw32AndU(..)
+                | Self::I32AtomicRmwOr(..)
+                | Self::I64AtomicRmwOr(..)
+                | Self::I32AtomicRmw8OrU(..)
+                | Self::I32AtomicRmw16OrU(..)
+                | Self::I64AtomicRmw8OrU(..)
+                | Self::I64AtomicRmw16OrU(..)
+                | Self::I64AtomicRmw32OrU(..)
+                | Self::I32AtomicRmwXor(..)
+                | Self::I64AtomicRmwXor(..)
+                | Self::I32AtomicRmw8XorU(..)
+                | Self::I32AtomicRmw16XorU(..)
+                | Self::I64AtomicRmw8XorU(..)
+                | Self::I64AtomicRmw16XorU(..)
+                | Self::I64AtomicRmw32XorU(..)
+                | Self::I32AtomicRmwXchg(..)
+                | Self::I64AtomicRmwXchg(..)
+                | Self::I32AtomicRmw8XchgU(..)
+                | Self::I32AtomicRmw16XchgU(..)
+                | Self::I64AtomicRmw8XchgU(..)
+                | Self::I64AtomicRmw16XchgU(..)
+                | Self::I64AtomicRmw32XchgU(..)
+                | Self::I32AtomicRmwCmpxchg(..)
+                | Self::I64AtomicRmwCmpxchg(..)
+                | Self::I32AtomicRmw8CmpxchgU(..)
+                | Self::I32AtomicRmw16CmpxchgU(..)
+                | Self::I64AtomicRmw8CmpxchgU(..)
+                | Self::I64AtomicRmw16CmpxchgU(..)
+                | Self::I64AtomicRmw32CmpxchgU(..)
+                | Self::AtomicFence
+        )
+    }
+
+    /// Instruction sequences nested in a block instruction, in order.
+    pub fn blocks(&self) -> Vec<&[Instr]> {
+        match self {
+            Self::Block(_, body)
+            | Self::Loop(_, body)
+            | Self::If(_, body)
+            | Self::TryDelegate(_, body, _)
+            | Self::TryTable(_, _, body) => vec![body],
+            Self::IfElse(_, then, other) => vec![then, other],
+            Self::Try(_, body, catches, catch_all) => {
+                let mut blocks = vec![&body[..]];
+                blocks.extend(catches.iter().map(|(_, handler)| &handler[..]));
+                blocks.extend(catch_all.as_deref());
+                blocks
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn blocks_mut(&mut self) -> Vec<&mut Vec<Instr>> {
+        match self {
+            Self::Block(_, body)
+            | Self::Loop(_, body)
+            | Self::If(_, body)
+            | Self::TryDelegate(_, body, _)
+            | Self::TryTable(_, _, body) => vec![body],
+            Self::IfElse(_, then, other) => vec![then, other],
+            Self::Try(_, body, catches, catch_all) => {
+                let mut blocks = vec![body];
+                blocks.extend(catches.iter_mut().map(|(_, handler)| handler));
+                blocks.extend(catch_all.as_mut());
+                blocks
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn memarg(&self) -> Option<&MemArg> {
+        match self {
+            Self::I32Load(memarg)
+            | Self::I64Load(memarg)
+            | Self::F32Load(memarg)
+            | Self::F64Load(memarg)
+            | Self::I32load8S(memarg)
+            | Self::I32Load8_u(memarg)
+            | Self::I32Load16_s(memarg)
+            | Self::I32Load16_u(memarg)
+            | Self::I64Load8_s(memarg)
+            | Self::I64Load8_u(memarg)
+            | Self::I64Load16_s(memarg)
+            | Self::I64Load16_u(memarg)
+            | Self::I64Load32_s(memarg)
+            | Self::I64Load32_u(memarg)
+            | Self::I32Store(memarg)
+            | Self::I64Store(memarg)
+            | Self::F32Store(memarg)
+            | Self::F64Store(memarg)
+            | Self::I32Store8(memarg)
+            | Self::I32Store16(memarg)
+            | Self::I64Store8(memarg)
+            | Self::I64Store16(memarg)
+            | Self::I64Store32(memarg)
+            | Self::V128_Load(memarg)
+            | Self::V128_Load_8x8_S(memarg)
+            | Self::V128_Load_8x8_U(memarg)
+            | Self::V128_Load_16x4_S(memarg)
+            | Self::V128_Load_16x4_U(memarg)
+            | Self::V128_Load_32x2_S(memarg)
+            | Self::V128_Load_32x2_U(memarg)
+            | Self::V128_Load_8_Splat(memarg)
+            | Self::V128_Load_16_Splat(memarg)
+            | Self::V128_Load_32_Splat(memarg)
+            | Self::V128_Load_64_Splat(memarg)
+            | Self::V128_Store(memarg)
+            | Self::V128_Load_32_Zero(memarg)
+            | Self::V128_Load_64_Zero(memarg)
+            | Self::V128_Load_8_Lane(memarg, _)
+            | Self::V128_Load_16_Lane(memarg, _)
+            | Self::V128_Load_32_Lane(memarg, _)
+            | Self::V128_Load_64_Lane(memarg, _)
+            | Self::V128_Store_8_Lane(memarg, _)
+            | Self::V128_Store_16_Lane(memarg, _)
+            | Self::V128_Store_32_Lane(memarg, _)
+            | Self::V128_Store_64_Lane(memarg, _)
+            | Self::MemoryAtomicNotify(memarg)
+            | Self::MemoryAtomicWait32(memarg)
+            | Self::MemoryAtomicWait64(memarg)
+            | Self::I32AtomicLoad(memarg)
+            | Self::I64AtomicLoad(memarg)
+            | Self::I32AtomicLoad8U(memarg)
+            | Self::I32AtomicLoad16U(memarg)
+            | Self::I64AtomicLoad8U(memarg)
+            | Self::I64AtomicLoad16U(memarg)
+            | Self::I64AtomicLoad32U(memarg)
+            | Self::I32AtomicStore(memarg)
+            | Self::I64AtomicStore(memarg)
+            | Self::I32AtomicStore8(memarg)
+            | Self::I32AtomicStore16(memarg)
+            | Self::I64AtomicStore8(memarg)
+            | Self::I64AtomicStore16(memarg)
+            | Self::I64AtomicStore32(memarg)
+            | Self::I32AtomicRmwAdd(memarg)
+            | Self::I64AtomicRmwAdd(memarg)
+            | Self::I32AtomicRmw8AddU(memarg)
+            | Self::I32AtomicRmw16AddU(memarg)
+            | Self::I64AtomicRmw8AddU(memarg)
+            | Self::I64AtomicRmw16AddU(memarg)
+            | Self::I64AtomicRmw32AddU(memarg)
+            | Self::I32AtomicRmwSub(memarg)
+            | Self::I64AtomicRmwSub(memarg)
+            | Self::I32AtomicRmw8SubU(memarg)
+            | Self::I32AtomicRmw16SubU(memarg)
+            | Self::I64AtomicRmw8SubU(memarg)
+            | Self::I64AtomicRmw16SubU(memarg)
+            | Self::I64AtomicRmw32SubU(memarg)
+            | Self::I32AtomicRmwAnd(memarg)
+            | Self::I64AtomicRmwAnd(memarg)
+            | Self::I32AtomicRmw8AndU(memarg)
+            | Self::I32AtomicRmw16AndU(memarg)
+            | Self::I64AtomicRmw8AndU(memarg)
+            | Self::I64AtomicRmw16AndU(memarg)
+            | Self::I64AtomicRmw32AndU(memarg)
+            | Self::I32AtomicRmwOr(memarg)
+            | Self::I64AtomicRmwOr(memarg)
+            | Self::I32AtomicRmw8OrU(memarg)
+            | Self::I32AtomicRmw16OrU(memarg)
+            | Self::I64AtomicRmw8OrU(memarg)
+            | Self::I64AtomicRmw16OrU(memarg)
+            | Self::I64AtomicRmw32OrU(memarg)
+            | Self::I32AtomicRmwXor(memarg)
+            | Self::I64AtomicRmwXor(memarg)
+            | Self::I32AtomicRmw8XorU(memarg)
+            | Self::I32AtomicRmw16XorU(memarg)
+            | Self::I64AtomicRmw8XorU(memarg)
+            | Self::I64AtomicRmw16XorU(memarg)
+            | Self::I64AtomicRmw32XorU(memarg)
+            | Self::I32AtomicRmwXchg(memarg)
+            | Self::I64AtomicRmwXchg(memarg)
+            | Self::I32AtomicRmw8XchgU(memarg)
+            | Self::I32AtomicRmw16XchgU(memarg)
+            | Self::I64AtomicRmw8XchgU(memarg)
+            | Self::I64AtomicRmw16XchgU(memarg)
+            | Self::I64AtomicRmw32XchgU(memarg)
+            | Self::I32AtomicRmwCmpxchg(memarg)
+            | Self::I64AtomicRmwCmpxchg(memarg)
+            | Self::I32AtomicRmw8CmpxchgU(memarg)
+            | Self::I32AtomicRmw16CmpxchgU(memarg)
+            | Self::I64AtomicRmw8CmpxchgU(memarg)
+            | Self::I64AtomicRmw16CmpxchgU(memarg)
+            | Self::I64AtomicRmw32CmpxchgU(memarg) => Some(memarg),
+            _ => None,
+        }
+    }
+
+    pub fn memarg_mut(&mut self) -> Option<&mut MemArg> {
+        match self {
+            Self::I32Load(memarg)
+            | Self::I64Load(memarg)
+            | Self::F32Load(memarg)
+            | Self::F64Load(memarg)
+            | Self::I32load8S(memarg)
+            | Self::I32Load8_u(memarg)
+            | Self::I32Load16_s(memarg)
+            | Self::I32Load16_u(memarg)
+            | Self::I64Load8_s(memarg)
+            | Self::I64Load8_u(memarg)
+            | Self::I64Load16_s(memarg)
+            | Self::I64Load16_u(memarg)
+            | Self::I64Load32_s(memarg)
+            | Self::I64Load32_u(memarg)
+            | Self::I32Store(memarg)
+            | Self::I64Store(memarg)
+            | Self::F32Store(memarg)
+            | Self::F64Store(memarg)
+            | Self::I32Store8(memarg)
+            | Self::I32Store16(memarg)
+            | Self::I64Store8(memarg)
+            | Self::I64Store16(memarg)
+            | Self::I64Store32(memarg)
+            | Self::V128_Load(memarg)
+            | Self::V128_Load_8x8_S(memarg)
+            | Self::V128_Load_8x8_U(memarg)
+            | Self::V128_Load_16x4_S(memarg)
+            | Self::V128_Load_16x4_U(memarg)
+            | Self::V128_Load_32x2_S(memarg)
+            | Self::V128_Load_32x2_U(memarg)
+            | Self::V128_Load_8_Splat(memarg)
+            | Self::V128_Load_16_Splat(memarg)
+            | Self::V128_Load_32_Splat(memarg)
+            | Self::V128_Load_64_Splat(memarg)
+            | Self::V128_Store(memarg)
+            | Self::V128_Load_32_Zero(memarg)
+            | Self::V128_Load_64_Zero(memarg)
+            | Self::V128_Load_8_Lane(memarg, _)
+            | Self::V128_Load_16_Lane(memarg, _)
+            | Self::V128_Load_32_Lane(memarg, _)
+            | Self::V128_Load_64_Lane(memarg, _)
+            | Self::V128_Store_8_Lane(memarg, _)
+            | Self::V128_Store_16_Lane(memarg, _)
+            | Self::V128_Store_32_Lane(memarg, _)
+            | Self::V128_Store_64_Lane(memarg, _)
+            | Self::MemoryAtomicNotify(memarg)
+            | Self::MemoryAtomicWait32(memarg)
+            | Self::MemoryAtomicWait64(memarg)
+            | Self::I32AtomicLoad(memarg)
+            | Self::I64AtomicLoad(memarg)
+            | Self::I32AtomicLoad8U(memarg)
+            | Self::I32AtomicLoad16U(memarg)
+            | Self::I64AtomicLoad8U(memarg)
+            | Self::I64AtomicLoad16U(memarg)
+            | Self::I64AtomicLoad32U(memarg)
+            | Self::I32AtomicStore(memarg)
+            | Self::I64AtomicStore(memarg)
+            | Self::I32AtomicStore8(memarg)
+            | Self::I32AtomicStore16(memarg)
+            | Self::I64AtomicStore8(memarg)
+            | Self::I64AtomicStore16(memarg)
+            | Self::I64AtomicStore32(memarg)
+            | Self::I32AtomicRmwAdd(memarg)
+            | Self::I64AtomicRmwAdd(memarg)
+            | Self::I32AtomicRmw8AddU(memarg)
+            | Self::I32AtomicRmw16AddU(memarg)
+            | Self::I64AtomicRmw8AddU(memarg)
+            | Self::I64AtomicRmw16AddU(memarg)
+            | Self::I64AtomicRmw32AddU(memarg)
+            | Self::I32AtomicRmwSub(memarg)
+            | Self::I64AtomicRmwSub(memarg)
+            | Self::I32AtomicRmw8SubU(memarg)
+            | Self::I32AtomicRmw16SubU(memarg)
+            | Self::I64AtomicRmw8SubU(memarg)
+            | Self::I64AtomicRmw16SubU(memarg)
+            | Self::I64AtomicRmw32SubU(memarg)
+            | Self::I32AtomicRmwAnd(memarg)
+            | Self::I64AtomicRmwAnd(memarg)
+            | Self::I32AtomicRmw8AndU(memarg)
+            | Self::I32AtomicRmw16AndU(memarg)
+            | Self::I64AtomicRmw8AndU(memarg)
+            | Self::I64AtomicRmw16AndU(memarg)
+            | Self::I64AtomicRmw32AndU(memarg)
+            | Self::I32AtomicRmwOr(memarg)
+            | Self::I64AtomicRmwOr(memarg)
+            | Self::I32AtomicRmw8OrU(memarg)
+            | Self::I32AtomicRmw16OrU(memarg)
+            | Self::I64AtomicRmw8OrU(memarg)
+            | Self::I64AtomicRmw16OrU(memarg)
+            | Self::I64AtomicRmw32OrU(memarg)
+            | Self::I32AtomicRmwXor(memarg)
+            | Self::I64AtomicRmwXor(memarg)
+            | Self::I32AtomicRmw8XorU(memarg)
+            | Self::I32AtomicRmw16XorU(memarg)
+            | Self::I64AtomicRmw8XorU(memarg)
+            | Self::I64AtomicRmw16XorU(memarg)
+            | Self::I64AtomicRmw32XorU(memarg)
+            | Self::I32AtomicRmwXchg(memarg)
+            | Self::I64AtomicRmwXchg(memarg)
+            | Self::I32AtomicRmw8XchgU(memarg)
+            | Self::I32AtomicRmw16XchgU(memarg)
+            | Self::I64AtomicRmw8XchgU(memarg)
+            | Self::I64AtomicRmw16XchgU(memarg)
+            | Self::I64AtomicRmw32XchgU(memarg)
+            | Self::I32AtomicRmwCmpxchg(memarg)
+            | Self::I64AtomicRmwCmpxchg(memarg)
+            | Self::I32AtomicRmw8CmpxchgU(memarg)
+            | Self::I32AtomicRmw16CmpxchgU(memarg)
+            | Self::I64AtomicRmw8CmpxchgU(memarg)
+            | Self::I64AtomicRmw16CmpxchgU(memarg)
+            | Self::I64AtomicRmw32CmpxchgU(memarg) => Some(memarg),
+            _ => None,
+        }
+    }
+
+    pub fn lane_mut(&mut self) -> Option<&mut LaneIdx> {
+        match self {
+            Self::I8X16_Extract_Lane_S(lane)
+            | Self::I8X16_Extract_Lane_U(lane)
+            | Self::I8X16_Replace_Lane(lane)
+            | Self::I16X8_Extract_Lane_S(lane)
+            | Self::I16X8_Extract_Lane_U(lane)
+            | Self::I16X8_Replace_Lane(lane)
+            | Self::I32X4_Extract_Lane(lane)
+            | Self::I32X4_Replace_Lane(lane)
+            | Self::I64X2_Extract_Lane(lane)
+            | Self::I64X2_Replace_Lane(lane)
+            | Self::F32X4_Extract_Lane(lane)
+            | Self::F32X4_Replace_Lane(lane)
+            | Self::F64X2_Extract_Lane(lane)
+            | Self::F64X2_Replace_Lane(lane)
+            | Self::V128_Load_8_Lane(_, lane)
+            | Self::V128_Load_16_Lane(_, lane)
+            | Self::V128_Load_32_Lane(_, lane)
+            | Self::V128_Load_64_Lane(_, lane)
+            | Self::V128_Store_8_Lane(_, lane)
+            | Self::V128_Store_16_Lane(_, lane)
+            | Self::V128_Store_32_Lane(_, lane)
+            | Self::V128_Store_64_Lane(_, lane) => Some(lane),
+            _ => None,
+        }
+    }
+}
+
+impl Encode for MemArg {
+    fn encode(&self, data: &mut IB) {
+        if self.2 == 0 {
+            self.0.encode(data);
+        } else {
+            (self.0 | 0x40).encode(data);
+            self.2.encode(data);
+        }
+        self.1.encode(data);
+    }
+}
+
+impl Encode for BlockType {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::Empty => data.push(0x40),
+            Self::ValType(valtype) => valtype.encode(data),
+            Self::X(idx) => data.write_sleb128(*idx),
+        }
+    }
+}
+
+impl Encode for Catch {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::Catch(tag, label) => {
+                data.push(0x00);
+                tag.encode(data);
+                label.encode(data);
+            }
+            Self::CatchRef(tag, label) => {
+                data.push(0x01);
+                tag.encode(data);
+                label.encode(data);
+            }
+            Self::CatchAll(label) => {
+                data.push(0x02);
+                label.encode(data);
+            }
+            Self::CatchAllRef(label) => {
+                data.push(0x03);
+                label.encode(data);
+            }
+        }
+    }
+}
+
+fn encode_block(instrs: &[Instr], data: &mut IB) {
+    for instr in instrs {
+        instr.encode(data);
+    }
+    data.push(0x0B);
+}
+
+impl Encode for Instr {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::Block(block_type, instrs) => {
+                data.push(0x02);
+                block_type.encode(data);
+                encode_block(instrs, data);
+            }
+            Self::Loop(block_type, instrs) => {
+                data.push(0x03);
+                block_type.encode(data);
+                encode_block(instrs, data);
+            }
+            Self::If(block_type, instrs) => {
+                data.push(0x04);
+                block_type.encode(data);
+                encode_block(instrs, data);
+            }
+            Self::IfElse(block_type, instrs, instrs2) => {
+                data.push(0x04);
+                block_type.encode(data);
+                for instr in instrs {
+                    instr.encode(data);
+                }
+                data.push(0x05);
+                encode_block(instrs2, data);
+            }
+            Self::Try(block_type, instrs, catches, catch_all) => {
+                data.push(0x06);
+                block_type.encode(data);
+                for instr in instrs {
+                    instr.encode(data);
+                }
+                for (tag, instrs) in catches {
+                    data.push(0x07);
+                    tag.encode(data);
+                    for instr in instrs {
+                        instr.encode(data);
+                    }
+                }
+                if let Some(instrs) = catch_all {
+                    data.push(0x19);
+                    for instr in instrs {
+                        instr.encode(data);
+                    }
+                }
+                data.push(0x0B);
+            }
+            Self::TryDelegate(block_type, instrs, label) => {
+                data.push(0x06);
+                block_type.encode(data);
+                for instr in instrs {
+                    instr.encode(data);
+                }
+                data.push(0x18);
+                label.encode(data);
+            }
+            Self::Throw(tag) => {
+                data.push(0x08);
+                tag.encode(data);
+            }
+            Self::Rethrow(label) => {
+                data.push(0x09);
+                label.encode(data);
+            }
+            Self::ThrowRef => data.push(0x0A),
+            Self::TryTable(block_type, catches, instrs) => {
+                data.push(0x1F);
+                block_type.encode(data);
+                catches.encode(data);
+                encode_block(instrs, data);
+            }
+            Self::Br(label) => {
+                data.push(0x0C);
+                label.encode(data);
+            }
+            Self::BrIf(label) => {
+                data.push(0x0D);
+                label.encode(data);
+            }
+            Self::BrTable(labels, label) => {
+                data.push(0x0E);
+                labels.encode(data);
+                label.encode(data);
+            }
+            Self::Call(func) => {
+                data.push(0x10);
+                func.encode(data);
+            }
+            Self::CallIndirect(ty, table) => {
+                data.push(0x11);
+                ty.encode(data);
+                table.encode(data);
+            }
+            Self::ReturnCall(func) => {
+                data.push(0x12);
+                func.encode(data);
+            }
+            Self::ReturnCallIndirect(ty, table) => {
+                data.push(0x13);
+                ty.encode(data);
+                table.encode(data);
+            }
+            Self::CallRef(ty) => {
+                data.push(0x14);
+                ty.encode(data);
+            }
+            Self::ReturnCallRef(ty) => {
+                data.push(0x15);
+                ty.encode(data);
+            }
+            Self::BrOnNull(label) => {
+                data.push(0xD5);
+                label.encode(data);
+            }
+            Self::RefEq => data.push(0xD3),
+            Self::StructNew(ty)
+            | Self::StructNewDefault(ty)
+            | Self::ArrayNew(ty)
+            | Self::ArrayNewDefault(ty)
+            | Self::ArrayGet(ty)
+            | Self::ArrayGetS(ty)
+            | Self::ArrayGetU(ty)
+            | Self::ArraySet(ty)
+            | Self::ArrayFill(ty) => {
+                data.push(0xFB);
+                self.gc_opcode().encode(data);
+                ty.encode(data);
+            }
+            Self::StructGet(a, b)
+            | Self::StructGetS(a, b)
+            | Self::StructGetU(a, b)
+            | Self::StructSet(a, b)
+            | Self::ArrayNewFixed(a, b)
+            | Self::ArrayNewData(a, b)
+            | Self::ArrayNewElem(a, b)
+            | Self::ArrayCopy(a, b)
+            | Self::ArrayInitData(a, b)
+            | Self::ArrayInitElem(a, b) => {
+                data.push(0xFB);
+                self.gc_opcode().encode(data);
+                a.encode(data);
+                b.encode(data);
+            }
+            Self::RefTest(ty) | Self::RefCast(ty) => {
+                data.push(0xFB);
+                (self.gc_opcode() + ty.0 as u32).encode(data);
+                ty.1.encode(data);
+            }
+            Self::BrOnCast(label, from, to) | Self::BrOnCastFail(label, from, to) => {
+                data.push(0xFB);
+                self.gc_opcode().encode(data);
+                data.push(from.0 as u8 | (to.0 as u8) << 1);
+                label.encode(data);
+                from.1.encode(data);
+                to.1.encode(data);
+            }
+            Self::ArrayLen
+            | Self::AnyConvertExtern
+            | Self::ExternConvertAny
+            | Self::RefI31
+            | Self::I31GetS
+            | Self::I31GetU => {
+                data.push(0xFB);
+                self.gc_opcode().encode(data);
+            }
+            Self::BrOnNonNull(label) => {
+                data.push(0xD6);
+                label.encode(data);
+            }
+            Self::RefNull(ty) => {
+                data.push(0xD0);
+                ty.encode(data);
+            }
+            Self::RefFunc(func) => {
+                data.push(0xD2);
+                func.encode(data);
+            }
+            Self::SelectType(types) => {
+                data.push(0x1C);
+                types.encode(data);
+            }
+            Self::LocalGet(idx) => {
+                data.push(0x20);
+                idx.encode(data);
+            }
+            Self::LocalSet(idx) => {
+                data.push(0x21);
+                idx.encode(data);
+            }
+            Self::LocalTee(idx) => {
+                data.push(0x22);
+                idx.encode(data);
+            }
+            Self::GlobalGet(idx) => {
+                data.push(0x23);
+                idx.encode(data);
+            }
+            Self::GlobalSet(idx) => {
+                data.push(0x24);
+                idx.encode(data);
+            }
+            Self::TableGet(idx) => {
+                data.push(0x25);
+                idx.encode(data);
+            }
+            Self::TableSet(idx) => {
+                data.push(0x26);
+                idx.encode(data);
+            }
+            Self::MemorySize(mem) => {
+                data.push(0x3F);
+                mem.encode(data);
+            }
+            Self::MemoryGrow(mem) => {
+                data.push(0x40);
+                mem.encode(data);
+            }
+            Self::I32Const(value) => {
+                data.push(0x41);
+                value.encode(data);
+            }
+            Self::I64Const(value) => {
+                data.push(0x42);
+                value.encode(data);
+            }
+            Self::F32Const(value) => {
+                data.push(0x43);
+                value.encode(data);
+            }
+            Self::F64Const(value) => {
+                data.push(0x44);
+                value.encode(data);
+            }
+            Self::MemoryInit(idx, mem) => {
+                data.push(0xFC);
+                8u32.encode(data);
+                idx.encode(data);
+                mem.encode(data);
+            }
+            Self::DataDrop(idx) => {
+                data.push(0xFC);
+                9u32.encode(data);
+                idx.encode(data);
+            }
+            Self::MemoryCopy(dst, src) => {
+                data.push(0xFC);
+                10u32.encode(data);
+                dst.encode(data);
+                src.encode(data);
+            }
+            Self::MemoryFill(mem) => {
+                data.push(0xFC);
+                11u32.encode(data);
+                mem.encode(data);
+            }
+            Self::TableInit(elem, table) => {
+                data.push(0xFC);
+                12u32.encode(data);
+                elem.encode(data);
+                table.encode(data);
+            }
+            Self::ElemDrop(elem) => {
+                data.push(0xFC);
+                13u32.encode(data);
+                elem.encode(data);
+            }
+            Self::TableCopy(a, b) => {
+                data.push(0xFC);
+                14u32.encode(data);
+                a.encode(data);
+                b.encode(data);
+            }
+            Self::TableGrow(table) => {
+                data.push(0xFC);
+                15u32.encode(data);
+                table.encode(data);
+            }
+            Self::TableSize(table) => {
+                data.push(0xFC);
+                16u32.encode(data);
+                table.encode(data);
+            }
+            Self::TableFill(table) => {
+                data.push(0xFC);
+                17u32.encode(data);
+                table.encode(data);
+            }
+            Self::V128_Const(bytes) => {
+                data.push(0xFD);
+                12u32.encode(data);
+                data.extend_from_slice(bytes);
+            }
+            Self::I8X16_Shuffle(lanes) => {
+                data.push(0xFD);
+                13u32.encode(data);
+                data.extend_from_slice(lanes);
+            }
+            Self::MemoryAtomicNotify(memarg) => {
+                data.push(0xFE);
+                0x00u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::MemoryAtomicWait32(memarg) => {
+                data.push(0xFE);
+                0x01u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::MemoryAtomicWait64(memarg) => {
+                data.push(0xFE);
+                0x02u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicLoad(memarg) => {
+                data.push(0xFE);
+                0x10u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicLoad(memarg) => {
+                data.push(0xFE);
+                0x11u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicLoad8U(memarg) => {
+                data.push(0xFE);
+                0x12u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicLoad16U(memarg) => {
+                data.push(0xFE);
+                0x13u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicLoad8U(memarg) => {
+                data.push(0xFE);
+                0x14u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicLoad16U(memarg) => {
+                data.push(0xFE);
+                0x15u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicLoad32U(memarg) => {
+                data.push(0xFE);
+                0x16u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicStore(memarg) => {
+                data.push(0xFE);
+                0x17u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicStore(memarg) => {
+                data.push(0xFE);
+                0x18u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicStore8(memarg) => {
+                data.push(0xFE);
+                0x19u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicStore16(memarg) => {
+                data.push(0xFE);
+                0x1Au32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicStore8(memarg) => {
+                data.push(0xFE);
+                0x1Bu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicStore16(memarg) => {
+                data.push(0xFE);
+                0x1Cu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicStore32(memarg) => {
+                data.push(0xFE);
+                0x1Du32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmwAdd(memarg) => {
+                data.push(0xFE);
+                0x1Eu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmwAdd(memarg) => {
+                data.push(0xFE);
+                0x1Fu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw8AddU(memarg) => {
+                data.push(0xFE);
+                0x20u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw16AddU(memarg) => {
+                data.push(0xFE);
+                0x21u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw8AddU(memarg) => {
+                data.push(0xFE);
+                0x22u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw16AddU(memarg) => {
+                data.push(0xFE);
+                0x23u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw32AddU(memarg) => {
+                data.push(0xFE);
+                0x24u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmwSub(memarg) => {
+                data.push(0xFE);
+                0x25u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmwSub(memarg) => {
+                data.push(0xFE);
+                0x26u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw8SubU(memarg) => {
+                data.push(0xFE);
+                0x27u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw16SubU(memarg) => {
+                data.push(0xFE);
+                0x28u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw8SubU(memarg) => {
+                data.push(0xFE);
+                0x29u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw16SubU(memarg) => {
+                data.push(0xFE);
+                0x2Au32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw32SubU(memarg) => {
+                data.push(0xFE);
+                0x2Bu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmwAnd(memarg) => {
+                data.push(0xFE);
+                0x2Cu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmwAnd(memarg) => {
+                data.push(0xFE);
+                0x2Du32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw8AndU(memarg) => {
+                data.push(0xFE);
+                0x2Eu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw16AndU(memarg) => {
+                data.push(0xFE);
+                0x2Fu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw8AndU(memarg) => {
+                data.push(0xFE);
+                0x30u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw16AndU(memarg) => {
+                data.push(0xFE);
+                0x31u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw32AndU(memarg) => {
+                data.push(0xFE);
+                0x32u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmwOr(memarg) => {
+                data.push(0xFE);
+                0x33u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmwOr(memarg) => {
+                data.push(0xFE);
+                0x34u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw8OrU(memarg) => {
+                data.push(0xFE);
+                0x35u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw16OrU(memarg) => {
+                data.push(0xFE);
+                0x36u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw8OrU(memarg) => {
+                data.push(0xFE);
+                0x37u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw16OrU(memarg) => {
+                data.push(0xFE);
+                0x38u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw32OrU(memarg) => {
+                data.push(0xFE);
+                0x39u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmwXor(memarg) => {
+                data.push(0xFE);
+                0x3Au32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmwXor(memarg) => {
+                data.push(0xFE);
+                0x3Bu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw8XorU(memarg) => {
+                data.push(0xFE);
+                0x3Cu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw16XorU(memarg) => {
+                data.push(0xFE);
+                0x3Du32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw8XorU(memarg) => {
+                data.push(0xFE);
+                0x3Eu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw16XorU(memarg) => {
+                data.push(0xFE);
+                0x3Fu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw32XorU(memarg) => {
+                data.push(0xFE);
+                0x40u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmwXchg(memarg) => {
+                data.push(0xFE);
+                0x41u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmwXchg(memarg) => {
+                data.push(0xFE);
+                0x42u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw8XchgU(memarg) => {
+                data.push(0xFE);
+                0x43u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw16XchgU(memarg) => {
+                data.push(0xFE);
+                0x44u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw8XchgU(memarg) => {
+                data.push(0xFE);
+                0x45u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw16XchgU(memarg) => {
+                data.push(0xFE);
+                0x46u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw32XchgU(memarg) => {
+                data.push(0xFE);
+                0x47u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmwCmpxchg(memarg) => {
+                data.push(0xFE);
+                0x48u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmwCmpxchg(memarg) => {
+                data.push(0xFE);
+                0x49u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw8CmpxchgU(memarg) => {
+                data.push(0xFE);
+                0x4Au32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I32AtomicRmw16CmpxchgU(memarg) => {
+                data.push(0xFE);
+                0x4Bu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw8CmpxchgU(memarg) => {
+                data.push(0xFE);
+                0x4Cu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw16CmpxchgU(memarg) => {
+                data.push(0xFE);
+                0x4Du32.encode(data);
+                memarg.encode(data);
+            }
+            Self::I64AtomicRmw32CmpxchgU(memarg) => {
+                data.push(0xFE);
+                0x4Eu32.encode(data);
+                memarg.encode(data);
+            }
+            Self::AtomicFence => {
+                data.push(0xFE);
+                0x03u32.encode(data);
+                data.push(0x00);
+            }
+            Self::UnReachable => data.push(0x00),
+            Self::Nop => data.push(0x01),
+            Self::Return => data.push(0x0F),
+            Self::RefIsNull => data.push(0xD1),
+            Self::RefAsNonNull => data.push(0xD4),
+            Self::Drop => data.push(0x1A),
+            Self::Select => data.push(0x1B),
+            Self::I32Load(memarg) => {
+                data.push(0x28);
+                memarg.encode(data);
+            }
+            Self::I64Load(memarg) => {
+                data.push(0x29);
+                memarg.encode(data);
+            }
+            Self::F32Load(memarg) => {
+                data.push(0x2A);
+                memarg.encode(data);
+            }
+            Self::F64Load(memarg) => {
+                data.push(0x2B);
+                memarg.encode(data);
+            }
+            Self::I32load8S(memarg) => {
+                data.push(0x2C);
+                memarg.encode(data);
+            }
+            Self::I32Load8_u(memarg) => {
+                data.push(0x2D);
+                memarg.encode(data);
+            }
+            Self::I32Load16_s(memarg) => {
+                data.push(0x2E);
+                memarg.encode(data);
+            }
+            Self::I32Load16_u(memarg) => {
+                data.push(0x2F);
+                memarg.encode(data);
+            }
+            Self::I64Load8_s(memarg) => {
+                data.push(0x30);
+                memarg.encode(data);
+            }
+            Self::I64Load8_u(memarg) => {
+                data.push(0x31);
+                memarg.encode(data);
+            }
+            Self::I64Load16_s(memarg) => {
+                data.push(0x32);
+                memarg.encode(data);
+            }
+            Self::I64Load16_u(memarg) => {
+                data.push(0x33);
+                memarg.encode(data);
+            }
+            Self::I64Load32_s(memarg) => {
+                data.push(0x34);
+                memarg.encode(data);
+            }
+            Self::I64Load32_u(memarg) => {
+                data.push(0x35);
+                memarg.encode(data);
+            }
+            Self::I32Store(memarg) => {
+                data.push(0x36);
+                memarg.encode(data);
+            }
+            Self::I64Store(memarg) => {
+                data.push(0x37);
+                memarg.encode(data);
+            }
+            Self::F32Store(memarg) => {
+                data.push(0x38);
+                memarg.encode(data);
+            }
+            Self::F64Store(memarg) => {
+                data.push(0x39);
+                memarg.encode(data);
+            }
+            Self::I32Store8(memarg) => {
+                data.push(0x3A);
+                memarg.encode(data);
+            }
+            Self::I32Store16(memarg) => {
+                data.push(0x3B);
+                memarg.encode(data);
+            }
+            Self::I64Store8(memarg) => {
+                data.push(0x3C);
+                memarg.encode(data);
+            }
+            Self::I64Store16(memarg) => {
+                data.push(0x3D);
+                memarg.encode(data);
+            }
+            Self::I64Store32(memarg) => {
+                data.push(0x3E);
+                memarg.encode(data);
+            }
+            Self::I32Eqz => data.push(0x45),
+            Self::I32Eq => data.push(0x46),
+            Self::I32Ne => data.push(0x47),
+            Self::I32Lts => data.push(0x48),
+            Self::I32Ltu => data.push(0x49),
+            Self::I32Gts => data.push(0x4A),
+            Self::I32Gtu => data.push(0x4B),
+            Self::I32Les => data.push(0x4C),
+            Self::I32Leu => data.push(0x4D),
+            Self::I32Ges => data.push(0x4E),
+            Self::I32Geu => data.push(0x4F),
+            Self::I64Eqz => data.push(0x50),
+            Self::I64Eq => data.push(0x51),
+            Self::I64Ne => data.push(0x52),
+            Self::I64Lts => data.push(0x53),
+            Self::I64Ltu => data.push(0x54),
+            Self::I64Gts => data.push(0x55),
+            Self::I64Gtu => data.push(0x56),
+            Self::I64Les => data.push(0x57),
+            Self::I64Leu => data.push(0x58),
+            Self::I64Ges => data.push(0x59),
+            Self::I64Geu => data.push(0x5A),
+            Self::F32Eq => data.push(0x5B),
+            Self::F32Ne => data.push(0x5C),
+            Self::F32Lt => data.push(0x5D),
+            Self::F32Gt => data.push(0x5E),
+            Self::F32Le => data.push(0x5F),
+            Self::F32Ge => data.push(0x60),
+            Self::F64Eq => data.push(0x61),
+            Self::F64Ne => data.push(0x62),
+            Self::F64Lt => data.push(0x63),
+            Self::F64Gt => data.push(0x64),
+            Self::F64Le => data.push(0x65),
+            Self::F64Ge => data.push(0x66),
+            Self::I32Clz => data.push(0x67),
+            Self::I32Ctz => data.push(0x68),
+            Self::I32PopcCnt => data.push(0x69),
+            Self::I32Add => data.push(0x6A),
+            Self::I32Sub => data.push(0x6B),
+            Self::I32Mul => data.push(0x6C),
+            Self::I32Divs => data.push(0x6D),
+            Self::I32Divu => data.push(0x6E),
+            Self::I32RemS => data.push(0x6F),
+            Self::I32Remu => data.push(0x70),
+            Self::I32And => data.push(0x71),
+            Self::I32Or => data.push(0x72),
+            Self::I32Xor => data.push(0x73),
+            Self::I32Shl => data.push(0x74),
+            Self::I32Shrs => data.push(0x75),
+            Self::I32Sgru => data.push(0x76),
+            Self::I32Rotl => data.push(0x77),
+            Self::I32Rotr => data.push(0x78),
+            Self::I64Clz => data.push(0x79),
+            Self::I64Ctz => data.push(0x7A),
+            Self::I64PopcCnt => data.push(0x7B),
+            Self::I64Add => data.push(0x7C),
+            Self::I64Sub => data.push(0x7D),
+            Self::I64Mul => data.push(0x7E),
+            Self::I64Divs => data.push(0x7F),
+            Self::I64Divu => data.push(0x80),
+            Self::I64RemS => data.push(0x81),
+            Self::I64Remu => data.push(0x82),
+            Self::I64And => data.push(0x83),
+            Self::I64Or => data.push(0x84),
+            Self::I64Xor => data.push(0x85),
+            Self::I64Shl => data.push(0x86),
+            Self::I64Shrs => data.push(0x87),
+            Self::I64Sgru => data.push(0x88),
+            Self::I64Rotl => data.push(0x89),
+            Self::I64Rotr => data.push(0x8A),
+            Self::F32Abs => data.push(0x8B),
+            Self::F32Neg => data.push(0x8C),
+            Self::F32Ceil => data.push(0x8D),
+            Self::F32Floor => data.push(0x8E),
+            Self::F32Trunc => data.push(0x8F),
+            Self::F32Nearest => data.push(0x90),
+            Self::F32Sqrt => data.push(0x91),
+            Self::F32Add => data.push(0x92),
+            Self::F32Sub => data.push(0x93),
+            Self::F32Mul => data.push(0x94),
+            Self::F32Div => data.push(0x95),
+            Self::F32Min => data.push(0x96),
+            Self::F32Max => data.push(0x97),
+            Self::F32CopySig => data.push(0x98),
+            Self::F64Abs => data.push(0x99),
+            Self::F64Neg => data.push(0x9A),
+            Self::F64Ceil => data.push(0x9B),
+            Self::F64Floor => data.push(0x9C),
+            Self::F64Trunc => data.push(0x9D),
+            Self::F64Nearest => data.push(0x9E),
+            Self::F64Sqrt => data.push(0x9F),
+            Self::F64Add => data.push(0xA0),
+            Self::F64Sub => data.push(0xA1),
+            Self::F64Mul => data.push(0xA2),
+            Self::F64Div => data.push(0xA3),
+            Self::F64Min => data.push(0xA4),
+            Self::F64Max => data.push(0xA5),
+            Self::F64CopySig => data.push(0xA6),
+            Self::I32WrapI64 => data.push(0xA7),
+            Self::I32TruncF32S => data.push(0xA8),
+            Self::I32TruncF32U => data.push(0xA9),
+            Self::I32TruncF64S => data.push(0xAA),
+            Self::I32TruncF64U => data.push(0xAB),
+            Self::I64ExtendI32S => data.push(0xAC),
+            Self::I64ExtendI32U => data.push(0xAD),
+            Self::I64TruncF32S => data.push(0xAE),
+            Self::I64TruncF32U => data.push(0xAF),
+            Self::I64TruncF64S => data.push(0xB0),
+            Self::I64TruncF64U => data.push(0xB1),
+            Self::F32ConvertI32S => data.push(0xB2),
+            Self::F32ConvertI32U => data.push(0xB3),
+            Self::F32ConvertI64S => data.push(0xB4),
+            Self::F32ConvertI64U => data.push(0xB5),
+            Self::F32DenoteF64 => data.push(0xB6),
+            Self::F64ConvertI32S => data.push(0xB7),
+            Self::F64ConvertI32U => data.push(0xB8),
+            Self::F64ConvertI64S => data.push(0xB9),
+            Self::F64ConvertI64U => data.push(0xBA),
+            Self::F64PromoteF32 => data.push(0xBB),
+            Self::I32ReinterpetF32 => data.push(0xBC),
+            Self::I64ReinterpetF64 => data.push(0xBD),
+            Self::F32ReinterpetI32 => data.push(0xBE),
+            Self::F64RetineroetI64 => data.push(0xBF),
+            Self::I32Extend8S => data.push(0xC0),
+            Self::I32Extend16S => data.push(0xC1),
+            Self::I64Extend8S => data.push(0xC2),
+            Self::I64Extend16S => data.push(0xC3),
+            Self::I64Extend32S => data.push(0xC4),
+            Self::I32TruncSatF32S => {
+                data.push(0xFC);
+                0u32.encode(data);
+            }
+            Self::I32TruncSatF32U => {
+                data.push(0xFC);
+                1u32.encode(data);
+            }
+            Self::I32TruncSatF64S => {
+                data.push(0xFC);
+                2u32.encode(data);
+            }
+            Self::I32TruncSatF64U => {
+                data.push(0xFC);
+                3u32.encode(data);
+            }
+            Self::I64TruncSatF32S => {
+                data.push(0xFC);
+                4u32.encode(data);
+            }
+            Self::I64TruncSatF32U => {
+                data.push(0xFC);
+                5u32.encode(data);
+            }
+            Self::I64TructSatF64S => {
+                data.push(0xFC);
+                6u32.encode(data);
+            }
+            Self::I64TructSatF64U => {
+                data.push(0xFC);
+                7u32.encode(data);
+            }
+            Self::V128_Load(memarg) => {
+                data.push(0xFD);
+                0u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_8x8_S(memarg) => {
+                data.push(0xFD);
+                1u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_8x8_U(memarg) => {
+                data.push(0xFD);
+                2u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_16x4_S(memarg) => {
+                data.push(0xFD);
+                3u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_16x4_U(memarg) => {
+                data.push(0xFD);
+                4u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_32x2_S(memarg) => {
+                data.push(0xFD);
+                5u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_32x2_U(memarg) => {
+                data.push(0xFD);
+                6u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_8_Splat(memarg) => {
+                data.push(0xFD);
+                7u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_16_Splat(memarg) => {
+                data.push(0xFD);
+                8u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_32_Splat(memarg) => {
+                data.push(0xFD);
+                9u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_64_Splat(memarg) => {
+                data.push(0xFD);
+                10u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Store(memarg) => {
+                data.push(0xFD);
+                11u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_32_Zero(memarg) => {
+                data.push(0xFD);
+                92u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_64_Zero(memarg) => {
+                data.push(0xFD);
+                93u32.encode(data);
+                memarg.encode(data);
+            }
+            Self::V128_Load_8_Lane(memarg, lane) => {
+                data.push(0xFD);
+                84u32.encode(data);
+                memarg.encode(data);
+                data.push(*lane);
+            }
+            Self::V128_Load_16_Lane(memarg, lane) => {
+                data.push(0xFD);
+                85u32.encode(data);
+                memarg.encode(data);
+                data.push(*lane);
+            }
+            Self::V128_Load_32_Lane(memarg, lane) => {
+                data.push(0xFD);
+                86u32.encode(data);
+                memarg.encode(data);
+                data.push(*lane);
+            }
+            Self::V128_Load_64_Lane(memarg, lane) => {
+                data.push(0xFD);
+                87u32.encode(data);
+                memarg.encode(data);
+                data.push(*lane);
+            }
+            Self::V128_Store_8_Lane(memarg, lane) => {
+                data.push(0xFD);
+                88u32.encode(data);
+                memarg.encode(data);
+                data.push(*lane);
+            }
+            Self::V128_Store_16_Lane(memarg, lane) => {
+                data.push(0xFD);
+                89u32.encode(data);
+                memarg.encode(data);
+                data.push(*lane);
+            }
+            Self::V128_Store_32_Lane(memarg, lane) => {
+                data.push(0xFD);
+                90u32.encode(data);
+                memarg.encode(data);
+                data.push(*lane);
+            }
+            Self::V128_Store_64_Lane(memarg, lane) => {
+                data.push(0xFD);
+                91u32.encode(data);
+                memarg.encode(data);
+                data.push(*lane);
+            }
+            Self::I8X16_Extract_Lane_S(lane) => {
+                data.push(0xFD);
+                21u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I8X16_Extract_Lane_U(lane) => {
+                data.push(0xFD);
+                22u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I8X16_Replace_Lane(lane) => {
+                data.push(0xFD);
+                23u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I16X8_Extract_Lane_S(lane) => {
+                data.push(0xFD);
+                24u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I16X8_Extract_Lane_U(lane) => {
+                data.push(0xFD);
+                25u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I16X8_Replace_Lane(lane) => {
+                data.push(0xFD);
+                26u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I32X4_Extract_Lane(lane) => {
+                data.push(0xFD);
+                27u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I32X4_Replace_Lane(lane) => {
+                data.push(0xFD);
+                28u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I64X2_Extract_Lane(lane) => {
+                data.push(0xFD);
+                29u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I64X2_Replace_Lane(lane) => {
+                data.push(0xFD);
+                30u32.encode(data);
+                data.push(*lane);
+            }
+            Self::F32X4_Extract_Lane(lane) => {
+                data.push(0xFD);
+                31u32.encode(data);
+                data.push(*lane);
+            }
+            Self::F32X4_Replace_Lane(lane) => {
+                data.push(0xFD);
+                32u32.encode(data);
+                data.push(*lane);
+            }
+            Self::F64X2_Extract_Lane(lane) => {
+                data.push(0xFD);
+                33u32.encode(data);
+                data.push(*lane);
+            }
+            Self::F64X2_Replace_Lane(lane) => {
+                data.push(0xFD);
+                34u32.encode(data);
+                data.push(*lane);
+            }
+            Self::I8x16_Swizzle => {
+                data.push(0xFD);
+                14u32.encode(data);
+            }
+            Self::I8X16_Splat => {
+                data.push(0xFD);
+                15u32.encode(data);
+            }
+            Self::I16X8_Splat => {
+                data.push(0xFD);
+                16u32.encode(data);
+            }
+            Self::I32X4_Splat => {
+                data.push(0xFD);
+                17u32.encode(data);
+            }
+            Self::I64X2_Splat => {
+                data.push(0xFD);
+                18u32.encode(data);
+            }
+            Self::F32X4_Splat => {
+                data.push(0xFD);
+                19u32.encode(data);
+            }
+            Self::F64X2_Splat => {
+                data.push(0xFD);
+                20u32.encode(data);
+            }
+            Self::I8X16_Eq => {
+                data.push(0xFD);
+                35u32.encode(data);
+            }
+            Self::I8X16_Ne
+            | Self::I8X16_Lt_S
+            | Self::I8X16_Lt_U
+            | Self::I8X16_Gt_S
+            | Self::I8X16_Gt_U
+            | Self::I8X16_Le_S
+            | Self::I8X16_Le_U
+            | Self::I8X16_Ge_S
+            | Self::I8X16_Ge_U
+            | Self::I16X8_Eq
+            | Self::I16X8_Ne
+            | Self::I16X8_Lt_S
+            | Self::I16X8_Lt_U
+            | Self::I16X8_Gt_S
+            | Self::I16X8_Gt_U
+            | Self::I16X8_Le_S
+            | Self::I16X8_Le_U
+            | Self::I16X8_Ge_S
+            | Self::I16X8_Ge_U
+            | Self::I32X4_Eq
+            | Self::I32X4_Ne
+            | Self::I32X4_Lt_S
+            | Self::I32X4_Lt_U
+            | Self::I32X4_Gt_S
+            | Self::I32X4_Gt_U
+            | Self::I32X4_Le_S
+            | Self::I32X4_Le_U
+            | Self::I32X4_Ge_S
+            | Self::I32X4_Ge_U
+            | Self::F32X4_Eq
+            | Self::F32X4_Ne
+            | Self::F32X4_Lt
+            | Self::F32X4_Gt
+            | Self::F32X4_Le
+            | Self::F32X4_Ge
+            | Self::F64X2_Eq
+            | Self::F64X2_Ne
+            | Self::F64X2_Lt
+            | Self::F64X2_Gt
+            | Self::F64X2_Le
+            | Self::F64X2_Ge
+            | Self::V128_Not
+            | Self::V128_And
+            | Self::V128_Andnot
+            | Self::V128_Or
+            | Self::V128_Xor
+            | Self::V128_Bitselect
+            | Self::V128_Any_True
+            | Self::F32X4_Demote_F64X2_Zero
+            | Self::F64X2_Promote_Low_F32X4
+            | Self::I8X16_Abs
+            | Self::I8X16_Neg
+            | Self::I8X16_Popcnt
+            | Self::I8X16_All_True
+            | Self::I8X16_Bitmask
+            | Self::I8X16_Narrow_I16X8_S
+            | Self::I8X16_Narrow_I16X8_U
+            | Self::F32X4_Ceil
+            | Self::F32X4_Floor
+            | Self::F32X4_Trunc
+            | Self::F32X4_Nearest
+            | Self::I8X16_Shl
+            | Self::I8X16_Shr_S
+            | Self::I8X16_Shr_U
+            | Self::I8X16_Add
+            | Self::I8X16_Add_Sat_S
+            | Self::I8X16_Add_Sat_U
+            | Self::I8X16_Sub
+            | Self::I8X16_Sub_Sat_S
+            | Self::I8X16_Sub_Sat_U
+            | Self::F64X2_Ceil
+            | Self::F64X2_Floor
+            | Self::I8X16_Min_S
+            | Self::I8X16_Min_U
+            | Self::I8X16_Max_S
+            | Self::I8X16_Max_U
+            | Self::F64X2_Trunc
+            | Self::I8X16_Avgr_U
+            | Self::I16X8_Extadd_Pairwise_I8X16_S
+            | Self::I16X8_Extadd_Pairwise_I8X16_U
+            | Self::I32X4_Extadd_Pairwise_I16X8_S
+            | Self::I32X4_Extadd_Pairwise_I16X8_U
+            | Self::I16X8_Abs
+            | Self::I16X8_Neg
+            | Self::I16X8_Q15mulr_Sat_S
+            | Self::I16X8_All_True
+            | Self::I16X8_Bitmask
+            | Self::I16X8_Narrow_I32X4_S
+            | Self::I16X8_Narrow_I32X4_U
+            | Self::I16X8_Extend_Low_I8X16_S
+            | Self::I16X8_Extend_High_I8X16_S
+            | Self::I16X8_Extend_Low_I8X16_U
+            | Self::I16X8_Extend_High_I8X16_U
+            | Self::I16X8_Shl
+            | Self::I16X8_Shr_S
+            | Self::I16X8_Shr_U
+            | Self::I16X8_Add
+            | Self::I16X8_Add_Sat_S
+            | Self::I16X8_Add_Sat_U
+            | Self::I16X8_Sub
+            | Self::I16X8_Sub_Sat_S
+            | Self::I16X8_Sub_Sat_U
+            | Self::F64X2_Nearest
+            | Self::I16X8_Mul
+            | Self::I16X8_Min_S
+            | Self::I16X8_Min_U
+            | Self::I16X8_Max_S
+            | Self::I16X8_Max_U
+            | Self::I16X8_Avgr_U
+            | Self::I16X8_Extmul_Low_I8X16_S
+            | Self::I16X8_Extmul_High_I8X16_S
+            | Self::I16X8_Extmul_Low_I8X16_U
+            | Self::I16X8_Extmul_High_I8X16_U
+            | Self::I32X4_Abs
+            | Self::I32X4_Neg
+            | Self::I32X4_All_True
+            | Self::I32X4_Bitmask
+            | Self::I32X4_Extend_Low_I16X8_S
+            | Self::I32X4_Extend_High_I16X8_S
+            | Self::I32X4_Extend_Low_I16X8_U
+            | Self::I32X4_Extend_High_I16X8_U
+            | Self::I32X4_Shl
+            | Self::I32X4_Shr_S
+            | Self::I32X4_Shr_U
+            | Self::I32X4_Add
+            | Self::I32X4_Sub
+            | Self::I32X4_Mul
+            | Self::I32X4_Min_S
+            | Self::I32X4_Min_U
+            | Self::I32X4_Max_S
+            | Self::I32X4_Max_U
+            | Self::I32X4_Dot_I16X8_S
+            | Self::I32X4_Extmul_Low_I16X8_S
+            | Self::I32X4_Extmul_High_I16X8_S
+            | Self::I32X4_Extmul_Low_I16X8_U
+            | Self::I32X4_Extmul_High_I16X8_U
+            | Self::I64X2_Abs
+            | Self::I64X2_Neg
+            | Self::I64X2_All_True
+            | Self::I64X2_Bitmask
+            | Self::I64X2_Extend_Low_I32X4_S
+            | Self::I64X2_Extend_High_I32X4_S
+            | Self::I64X2_Extend_Low_I32X4_U
+            | Self::I64X2_Extend_High_I32X4_U
+            | Self::I64X2_Shl
+            | Self::I64X2_Shr_S
+            | Self::I64X2_Shr_U
+            | Self::I64X2_Add
+            | Self::I64X2_Sub
+            | Self::I64X2_Mul
+            | Self::I64X2_Eq
+            | Self::I64X2_Ne
+            | Self::I64X2_Lt_S
+            | Self::I64X2_Gt_S
+            | Self::I64X2_Le_S
+            | Self::I64X2_Ge_S
+            | Self::I64X2_Extmul_Low_I32X4_S
+            | Self::I64X2_Extmul_High_I32X4_S
+            | Self::I64X2_Extmul_Low_I32X4_U
+            | Self::I64X2_Extmul_High_I32X4_U
+            | Self::F32X4_Abs
+            | Self::F32X4_Neg
+            | Self::F32X4_Sqrt
+            | Self::F32X4_Add
+            | Self::F32X4_Sub
+            | Self::F32X4_Mul
+            | Self::F32X4_Div
+            | Self::F32X4_Min
+            | Self::F32X4_Max
+            | Self::F32X4_Pmin
+            | Self::F32X4_Pmax
+            | Self::F64X2_Abs
+            | Self::F64X2_Neg
+            | Self::F64X2_Sqrt
+            | Self::F64X2_Add
+            | Self::F64X2_Sub
+            | Self::F64X2_Mul
+            | Self::F64X2_Div
+            | Self::F64X2_Min
+            | Self::F64X2_Max
+            | Self::F64X2_Pmin
+            | Self::F64X2_Pmax
+            | Self::I32X4_Trunc_Sat_F32X4_S
+            | Self::I32X4_Trunc_Sat_F32X4_U
+            | Self::F32X4_Convert_I32X4_S
+            | Self::F32X4_Convert_I32X4_U
+            | Self::I32X4_Trunc_Sat_F64X2_S_Zero
+            | Self::I32X4_Trunc_Sat_F64X2_U_Zero
+            | Self::F64X2_Convert_Low_I32X4_S
+            | Self::F64X2_Convert_Low_I32X4_U
+            | Self::I8X16_Relaxed_Swizzle
+            | Self::I32X4_Relaxed_Trunc_F32X4_S
+            | Self::I32X4_Relaxed_Trunc_F32X4_U
+            | Self::I32X4_Relaxed_Trunc_F64X2_S_Zero
+            | Self::I32X4_Relaxed_Trunc_F64X2_U_Zero
+            | Self::F32X4_Relaxed_Madd
+            | Self::F32X4_Relaxed_Nmadd
+            | Self::F64X2_Relaxed_Madd
+            | Self::F64X2_Relaxed_Nmadd
+            | Self::I8X16_Relaxed_Laneselect
+            | Self::I16X8_Relaxed_Laneselect
+            | Self::I32X4_Relaxed_Laneselect
+            | Self::I64X2_Relaxed_Laneselect
+            | Self::F32X4_Relaxed_Min
+            | Self::F32X4_Relaxed_Max
+            | Self::F64X2_Relaxed_Min
+            | Self::F64X2_Relaxed_Max
+            | Self::I16X8_Relaxed_Q15mulr_S
+            | Self::I16X8_Relaxed_Dot_I8X16_I7X16_S
+            | Self::I32X4_Relaxed_Dot_I8X16_I7X16_Add_S => {
+                data.push(0xFD);
+                self.simd_opcode().encode(data);
+            }
+        }
+    }
+}
 
 impl Parse<&mut IB> for MemArg {
     fn parse(value: &mut IB) -> Result<Self, Error> {
-        let a = value.read_uleb128(32) as u32;
-        let b = value.read_uleb128(32) as u32;
-        Ok(Self(a, b))
+        let a = u32::parse(&mut *value)?;
+        // Bit 6 of the alignment flags an explicit memory index (multi-memory).
+        let mem = match a & 0x40 {
+            0 => 0,
+            _ => u32::parse(value)?,
+        };
+        let b = u64::parse(value)?;
+        Ok(Self(a & !0x40, b, mem))
     }
 }
 
@@ -300,21 +3542,61 @@ impl Parse<&mut IB> for BlockType {
             return Err(Error::EndOfBuffer(Backtrace::capture()));
         }
 
-        if *value.first().unwrap() == 0x40 {
+        if peek_byte(value)? == 0x40 {
             value.drain(..1).next().unwrap();
             return Ok(Self::Empty);
         }
 
-        if let Ok(valtype) = ValType::parse(&mut *value) {
-            return Ok(Self::ValType(valtype));
+        if ValType::starts(peek_byte(value)?) {
+            return Ok(Self::ValType(ValType::parse(value)?));
         }
 
-        if value.len() < 3 {
-            return Err(Error::EndOfBuffer(Backtrace::capture()));
+        Ok(Self::X(crate::read_sleb(value, 33)?))
+    }
+}
+
+impl Parse<&mut IB> for Catch {
+    fn parse(value: &mut IB) -> Result<Self, Error> {
+        Ok(match read_byte(value)? {
+            0x00 => Self::Catch(u32::parse(&mut *value)?, u32::parse(value)?),
+            0x01 => Self::CatchRef(u32::parse(&mut *value)?, u32::parse(value)?),
+            0x02 => Self::CatchAll(u32::parse(value)?),
+            0x03 => Self::CatchAllRef(u32::parse(value)?),
+            byte => {
+                return Err(Error::InvalidModule(
+                    Backtrace::capture(),
+                    format!("invalid catch clause {byte:#04x}"),
+                ))
+            }
+        })
+    }
+}
+
+/// Parses instructions up to one of the `try` delimiters (`catch`, `catch_all`,
+/// `delegate` or `end`) and returns them with the delimiter consumed.
+fn parse_try_body(value: &mut IB) -> Result<(Vec<Instr>, u8), Error> {
+    let mut buffer = Vec::new();
+    loop {
+        match value.first() {
+            None => return Err(Error::EndOfBuffer(Backtrace::capture())),
+            Some(0x07 | 0x0B | 0x18 | 0x19) => return Ok((buffer, read_byte(value)?)),
+            Some(_) => buffer.push(Instr::parse(&mut *value)?),
         }
+    }
+}
 
-        Ok(Self::X(value.read_sleb128(33)))
+pub(crate) fn read_byte(value: &mut IB) -> Result<u8, Error> {
+    if value.is_empty() {
+        return Err(Error::EndOfBuffer(Backtrace::capture()));
     }
+    Ok(value.drain(..1).next().unwrap())
+}
+
+pub(crate) fn peek_byte(value: &IB) -> Result<u8, Error> {
+    value
+        .first()
+        .copied()
+        .ok_or_else(|| Error::EndOfBuffer(Backtrace::capture()))
 }
 
 impl Parse<&mut IB> for Instr {
@@ -332,7 +3614,7 @@ impl Parse<&mut IB> for Instr {
                 let block_type = BlockType::parse(&mut *value)?;
                 let mut buffer = Vec::new();
                 loop {
-                    if *value.first().unwrap() == 0x0B {
+                    if peek_byte(value)? == 0x0B {
                         value.drain(..1).next().unwrap();
                         break;
                     }
@@ -344,7 +3626,7 @@ impl Parse<&mut IB> for Instr {
                 let block_type = BlockType::parse(&mut *value)?;
                 let mut buffer = Vec::new();
                 loop {
-                    if *value.first().unwrap() == 0x0B {
+                    if peek_byte(value)? == 0x0B {
                         value.drain(..1).next().unwrap();
                         break;
                     }
@@ -356,7 +3638,7 @@ impl Parse<&mut IB> for Instr {
                 let block_type = BlockType::parse(&mut *value)?;
                 let mut buffer = Vec::new();
                 loop {
-                    match *value.first().unwrap() {
+                    match peek_byte(value)? {
                         0x0B => {
                             value.drain(..1).next().unwrap();
                             return Ok(Self::If(block_type, buffer));
@@ -365,7 +3647,7 @@ impl Parse<&mut IB> for Instr {
                             value.drain(..1).next().unwrap();
                             let mut buffer2 = Vec::new();
                             loop {
-                                if *value.first().unwrap() == 0x0B {
+                                if peek_byte(value)? == 0x0B {
                                     value.drain(..1).next().unwrap();
                                     break;
                                 }
@@ -379,6 +3661,52 @@ impl Parse<&mut IB> for Instr {
                     buffer.push(Instr::parse(&mut *value)?);
                 }
             }
+            0x06 => {
+                let block_type = BlockType::parse(&mut *value)?;
+                let (body, mut delimiter) = parse_try_body(value)?;
+                if delimiter == 0x18 {
+                    return Ok(Self::TryDelegate(block_type, body, u32::parse(value)?));
+                }
+                let mut catches = Vec::new();
+                let mut catch_all = None;
+                while delimiter != 0x0B {
+                    match delimiter {
+                        0x07 => {
+                            let tag = u32::parse(&mut *value)?;
+                            let (handler, next) = parse_try_body(value)?;
+                            catches.push((tag, handler));
+                            delimiter = next;
+                        }
+                        0x19 if catch_all.is_none() => {
+                            let (handler, next) = parse_try_body(value)?;
+                            catch_all = Some(handler);
+                            delimiter = next;
+                        }
+                        _ => {
+                            return Err(Error::InvalidModule(
+                                Backtrace::capture(),
+                                format!("unexpected {delimiter:#04x} in try"),
+                            ))
+                        }
+                    }
+                }
+                Self::Try(block_type, body, catches, catch_all)
+            }
+            0x08 => Self::Throw(u32::parse(value)?),
+            0x09 => Self::Rethrow(u32::parse(value)?),
+            0x0A => Self::ThrowRef,
+            0x1F => {
+                let block_type = BlockType::parse(&mut *value)?;
+                let catches = Vec::parse(&mut *value)?;
+                let (body, delimiter) = parse_try_body(value)?;
+                if delimiter != 0x0B {
+                    return Err(Error::InvalidModule(
+                        Backtrace::capture(),
+                        format!("unexpected {delimiter:#04x} in try_table"),
+                    ));
+                }
+                Self::TryTable(block_type, catches, body)
+            }
             0x0C => {
                 let label_idx = u32::parse(value)?;
                 Self::Br(label_idx)
@@ -402,11 +3730,20 @@ impl Parse<&mut IB> for Instr {
                 let t = u32::parse(&mut *value)?;
                 Self::CallIndirect(u, t)
             }
-            0xD0 => {
+            0x12 => Self::ReturnCall(u32::parse(value)?),
+            0x13 => {
                 let u = u32::parse(&mut *value)?;
-                Self::RefNull(u)
+                let t = u32::parse(&mut *value)?;
+                Self::ReturnCallIndirect(u, t)
             }
+            0x14 => Self::CallRef(u32::parse(value)?),
+            0x15 => Self::ReturnCallRef(u32::parse(value)?),
+            0xD0 => Self::RefNull(HeapType::parse(value)?),
             0xD1 => Self::RefIsNull,
+            0xD3 => Self::RefEq,
+            0xD4 => Self::RefAsNonNull,
+            0xD5 => Self::BrOnNull(u32::parse(value)?),
+            0xD6 => Self::BrOnNonNull(u32::parse(value)?),
             0x0D2 => {
                 let u = u32::parse(value)?;
                 Self::RefFunc(u)
@@ -458,34 +3795,21 @@ impl Parse<&mut IB> for Instr {
                     7 => Self::I64TructSatF64U,
                     8 => {
                         let a = u32::parse(value)?;
-                        let byte = value.drain(0..1).next().unwrap();
-                        if byte == 0x00 {
-                            Self::MemoryInit(a)
-                        } else {
-                            unimplemented!()
-                        }
+                        let b = u32::parse(value)?;
+                        Self::MemoryInit(a, b)
                     }
                     9 => {
                         let a = u32::parse(value)?;
                         Self::DataDrop(a)
                     }
                     10 => {
-                        let mut drain = value.drain(0..2);
-                        let byte1 = drain.next().unwrap();
-                        let byte2 = drain.next().unwrap();
-                        if byte1 == 0 && byte2 == 0 {
-                            Self::MemoryCopy
-                        } else {
-                            unimplemented!()
-                        }
+                        let a = u32::parse(value)?;
+                        let b = u32::parse(value)?;
+                        Self::MemoryCopy(a, b)
                     }
                     11 => {
-                        let byte = value.drain(0..1).next().unwrap();
-                        if byte == 0 {
-                            Self::MemoryFill
-                        } else {
-                            unimplemented!()
-                        }
+                        let a = u32::parse(value)?;
+                        Self::MemoryFill(a)
                     }
                     12 => {
                         let a = u32::parse(value)?;
@@ -514,115 +3838,651 @@ impl Parse<&mut IB> for Instr {
                         Self::TableFill(a)
                     }
                     _ => {
-                        unimplemented!()
+                        return Err(Error::InvalidModule(
+                            Backtrace::capture(),
+                            format!("unknown instruction 0xfc {u}"),
+                        ))
                     }
                 }
             }
 
-            // Quick
-            0x28..=0x3E => {
-                let a = MemArg::parse(value)?;
-                Self::I32Load(a)
-                // TODO Unimlemented
-            }
-
-            0x3F | 0x40 => {
-                let byte = value.drain(0..1).next().unwrap();
-                if byte == 0x00 {
-                    Self::MemorySize
-                    // TODO Unimplemented
-                } else {
-                    Self::parse(value)?
-                }
-            }
+            0x28 => Self::I32Load(MemArg::parse(value)?),
+            0x29 => Self::I64Load(MemArg::parse(value)?),
+            0x2A => Self::F32Load(MemArg::parse(value)?),
+            0x2B => Self::F64Load(MemArg::parse(value)?),
+            0x2C => Self::I32load8S(MemArg::parse(value)?),
+            0x2D => Self::I32Load8_u(MemArg::parse(value)?),
+            0x2E => Self::I32Load16_s(MemArg::parse(value)?),
+            0x2F => Self::I32Load16_u(MemArg::parse(value)?),
+            0x30 => Self::I64Load8_s(MemArg::parse(value)?),
+            0x31 => Self::I64Load8_u(MemArg::parse(value)?),
+            0x32 => Self::I64Load16_s(MemArg::parse(value)?),
+            0x33 => Self::I64Load16_u(MemArg::parse(value)?),
+            0x34 => Self::I64Load32_s(MemArg::parse(value)?),
+            0x35 => Self::I64Load32_u(MemArg::parse(value)?),
+            0x36 => Self::I32Store(MemArg::parse(value)?),
+            0x37 => Self::I64Store(MemArg::parse(value)?),
+            0x38 => Self::F32Store(MemArg::parse(value)?),
+            0x39 => Self::F64Store(MemArg::parse(value)?),
+            0x3A => Self::I32Store8(MemArg::parse(value)?),
+            0x3B => Self::I32Store16(MemArg::parse(value)?),
+            0x3C => Self::I64Store8(MemArg::parse(value)?),
+            0x3D => Self::I64Store16(MemArg::parse(value)?),
+            0x3E => Self::I64Store32(MemArg::parse(value)?),
+            0x3F => Self::MemorySize(u32::parse(value)?),
+            0x40 => Self::MemoryGrow(u32::parse(value)?),
 
             0x41 => Self::I32Const(i32::parse(value)?),
             0x42 => Self::I64Const(i64::parse(value)?),
             0x43 => Self::F32Const(f32::parse(value)?),
             0x44 => Self::F64Const(f64::parse(value)?),
 
-            0x45..=0xC4 => {
-                // TODO
-                Self::I32Eqz
+            0x45 => Self::I32Eqz,
+            0x46 => Self::I32Eq,
+            0x47 => Self::I32Ne,
+            0x48 => Self::I32Lts,
+            0x49 => Self::I32Ltu,
+            0x4A => Self::I32Gts,
+            0x4B => Self::I32Gtu,
+            0x4C => Self::I32Les,
+            0x4D => Self::I32Leu,
+            0x4E => Self::I32Ges,
+            0x4F => Self::I32Geu,
+            0x50 => Self::I64Eqz,
+            0x51 => Self::I64Eq,
+            0x52 => Self::I64Ne,
+            0x53 => Self::I64Lts,
+            0x54 => Self::I64Ltu,
+            0x55 => Self::I64Gts,
+            0x56 => Self::I64Gtu,
+            0x57 => Self::I64Les,
+            0x58 => Self::I64Leu,
+            0x59 => Self::I64Ges,
+            0x5A => Self::I64Geu,
+            0x5B => Self::F32Eq,
+            0x5C => Self::F32Ne,
+            0x5D => Self::F32Lt,
+            0x5E => Self::F32Gt,
+            0x5F => Self::F32Le,
+            0x60 => Self::F32Ge,
+            0x61 => Self::F64Eq,
+            0x62 => Self::F64Ne,
+            0x63 => Self::F64Lt,
+            0x64 => Self::F64Gt,
+            0x65 => Self::F64Le,
+            0x66 => Self::F64Ge,
+            0x67 => Self::I32Clz,
+            0x68 => Self::I32Ctz,
+            0x69 => Self::I32PopcCnt,
+            0x6A => Self::I32Add,
+            0x6B => Self::I32Sub,
+            0x6C => Self::I32Mul,
+            0x6D => Self::I32Divs,
+            0x6E => Self::I32Divu,
+            0x6F => Self::I32RemS,
+            0x70 => Self::I32Remu,
+            0x71 => Self::I32And,
+            0x72 => Self::I32Or,
+            0x73 => Self::I32Xor,
+            0x74 => Self::I32Shl,
+            0x75 => Self::I32Shrs,
+            0x76 => Self::I32Sgru,
+            0x77 => Self::I32Rotl,
+            0x78 => Self::I32Rotr,
+            0x79 => Self::I64Clz,
+            0x7A => Self::I64Ctz,
+            0x7B => Self::I64PopcCnt,
+            0x7C => Self::I64Add,
+            0x7D => Self::I64Sub,
+            0x7E => Self::I64Mul,
+            0x7F => Self::I64Divs,
+            0x80 => Self::I64Divu,
+            0x81 => Self::I64RemS,
+            0x82 => Self::I64Remu,
+            0x83 => Self::I64And,
+            0x84 => Self::I64Or,
+            0x85 => Self::I64Xor,
+            0x86 => Self::I64Shl,
+            0x87 => Self::I64Shrs,
+            0x88 => Self::I64Sgru,
+            0x89 => Self::I64Rotl,
+            0x8A => Self::I64Rotr,
+            0x8B => Self::F32Abs,
+            0x8C => Self::F32Neg,
+            0x8D => Self::F32Ceil,
+            0x8E => Self::F32Floor,
+            0x8F => Self::F32Trunc,
+            0x90 => Self::F32Nearest,
+            0x91 => Self::F32Sqrt,
+            0x92 => Self::F32Add,
+            0x93 => Self::F32Sub,
+            0x94 => Self::F32Mul,
+            0x95 => Self::F32Div,
+            0x96 => Self::F32Min,
+            0x97 => Self::F32Max,
+            0x98 => Self::F32CopySig,
+            0x99 => Self::F64Abs,
+            0x9A => Self::F64Neg,
+            0x9B => Self::F64Ceil,
+            0x9C => Self::F64Floor,
+            0x9D => Self::F64Trunc,
+            0x9E => Self::F64Nearest,
+            0x9F => Self::F64Sqrt,
+            0xA0 => Self::F64Add,
+            0xA1 => Self::F64Sub,
+            0xA2 => Self::F64Mul,
+            0xA3 => Self::F64Div,
+            0xA4 => Self::F64Min,
+            0xA5 => Self::F64Max,
+            0xA6 => Self::F64CopySig,
+            0xA7 => Self::I32WrapI64,
+            0xA8 => Self::I32TruncF32S,
+            0xA9 => Self::I32TruncF32U,
+            0xAA => Self::I32TruncF64S,
+            0xAB => Self::I32TruncF64U,
+            0xAC => Self::I64ExtendI32S,
+            0xAD => Self::I64ExtendI32U,
+            0xAE => Self::I64TruncF32S,
+            0xAF => Self::I64TruncF32U,
+            0xB0 => Self::I64TruncF64S,
+            0xB1 => Self::I64TruncF64U,
+            0xB2 => Self::F32ConvertI32S,
+            0xB3 => Self::F32ConvertI32U,
+            0xB4 => Self::F32ConvertI64S,
+            0xB5 => Self::F32ConvertI64U,
+            0xB6 => Self::F32DenoteF64,
+            0xB7 => Self::F64ConvertI32S,
+            0xB8 => Self::F64ConvertI32U,
+            0xB9 => Self::F64ConvertI64S,
+            0xBA => Self::F64ConvertI64U,
+            0xBB => Self::F64PromoteF32,
+            0xBC => Self::I32ReinterpetF32,
+            0xBD => Self::I64ReinterpetF64,
+            0xBE => Self::F32ReinterpetI32,
+            0xBF => Self::F64RetineroetI64,
+            0xC0 => Self::I32Extend8S,
+            0xC1 => Self::I32Extend16S,
+            0xC2 => Self::I64Extend8S,
+            0xC3 => Self::I64Extend16S,
+            0xC4 => Self::I64Extend32S,
+
+            0xFB => {
+                let op = u32::parse(value)?;
+                let mut idx = || u32::parse(&mut *value);
+                match op {
+                    0 => Self::StructNew(idx()?),
+                    1 => Self::StructNewDefault(idx()?),
+                    2 => Self::StructGet(idx()?, idx()?),
+                    3 => Self::StructGetS(idx()?, idx()?),
+                    4 => Self::StructGetU(idx()?, idx()?),
+                    5 => Self::StructSet(idx()?, idx()?),
+                    6 => Self::ArrayNew(idx()?),
+                    7 => Self::ArrayNewDefault(idx()?),
+                    8 => Self::ArrayNewFixed(idx()?, idx()?),
+                    9 => Self::ArrayNewData(idx()?, idx()?),
+                    10 => Self::ArrayNewElem(idx()?, idx()?),
+                    11 => Self::ArrayGet(idx()?),
+                    12 => Self::ArrayGetS(idx()?),
+                    13 => Self::ArrayGetU(idx()?),
+                    14 => Self::ArraySet(idx()?),
+                    15 => Self::ArrayLen,
+                    16 => Self::ArrayFill(idx()?),
+                    17 => Self::ArrayCopy(idx()?, idx()?),
+                    18 => Self::ArrayInitData(idx()?, idx()?),
+                    19 => Self::ArrayInitElem(idx()?, idx()?),
+                    20 | 21 => Self::RefTest(RefType(op == 21, HeapType::parse(value)?)),
+                    22 | 23 => Self::RefCast(RefType(op == 23, HeapType::parse(value)?)),
+                    24 | 25 => {
+                        let flags = read_byte(value)?;
+                        let label = u32::parse(&mut *value)?;
+                        let from = RefType(flags & 1 != 0, HeapType::parse(value)?);
+                        let to = RefType(flags & 2 != 0, HeapType::parse(value)?);
+                        match op {
+                            24 => Self::BrOnCast(label, from, to),
+                            _ => Self::BrOnCastFail(label, from, to),
+                        }
+                    }
+                    26 => Self::AnyConvertExtern,
+                    27 => Self::ExternConvertAny,
+                    28 => Self::RefI31,
+                    29 => Self::I31GetS,
+                    30 => Self::I31GetU,
+                    _ => {
+                        return Err(Error::InvalidModule(
+                            Backtrace::capture(),
+                            format!("unknown GC instruction 0xfb {op}"),
+                        ))
+                    }
+                }
+            }
+
+            0xFE => {
+                let byte = u32::parse(value)?;
+                match byte {
+                    0x03 => {
+                        read_byte(value)?;
+                        Self::AtomicFence
+                    }
+                    0x00 => Self::MemoryAtomicNotify(MemArg::parse(value)?),
+                    0x01 => Self::MemoryAtomicWait32(MemArg::parse(value)?),
+                    0x02 => Self::MemoryAtomicWait64(MemArg::parse(value)?),
+                    0x10 => Self::I32AtomicLoad(MemArg::parse(value)?),
+                    0x11 => Self::I64AtomicLoad(MemArg::parse(value)?),
+                    0x12 => Self::I32AtomicLoad8U(MemArg::parse(value)?),
+                    0x13 => Self::I32AtomicLoad16U(MemArg::parse(value)?),
+                    0x14 => Self::I64AtomicLoad8U(MemArg::parse(value)?),
+                    0x15 => Self::I64AtomicLoad16U(MemArg::parse(value)?),
+                    0x16 => Self::I64AtomicLoad32U(MemArg::parse(value)?),
+                    0x17 => Self::I32AtomicStore(MemArg::parse(value)?),
+                    0x18 => Self::I64AtomicStore(MemArg::parse(value)?),
+                    0x19 => Self::I32AtomicStore8(MemArg::parse(value)?),
+                    0x1A => Self::I32AtomicStore16(MemArg::parse(value)?),
+                    0x1B => Self::I64AtomicStore8(MemArg::parse(value)?),
+                    0x1C => Self::I64AtomicStore16(MemArg::parse(value)?),
+                    0x1D => Self::I64AtomicStore32(MemArg::parse(value)?),
+                    0x1E => Self::I32AtomicRmwAdd(MemArg::parse(value)?),
+                    0x1F => Self::I64AtomicRmwAdd(MemArg::parse(value)?),
+                    0x20 => Self::I32AtomicRmw8AddU(MemArg::parse(value)?),
+                    0x21 => Self::I32AtomicRmw16AddU(MemArg::parse(value)?),
+                    0x22 => Self::I64AtomicRmw8AddU(MemArg::parse(value)?),
+                    0x23 => Self::I64AtomicRmw16AddU(MemArg::parse(value)?),
+                    0x24 => Self::I64AtomicRmw32AddU(MemArg::parse(value)?),
+                    0x25 => Self::I32AtomicRmwSub(MemArg::parse(value)?),
+                    0x26 => Self::I64AtomicRmwSub(MemArg::parse(value)?),
+                    0x27 => Self::I32AtomicRmw8SubU(MemArg::parse(value)?),
+                    0x28 => Self::I32AtomicRmw16SubU(MemArg::parse(value)?),
+                    0x29 => Self::I64AtomicRmw8SubU(MemArg::parse(value)?),
+                    0x2A => Self::I64AtomicRmw16SubU(MemArg::parse(value)?),
+                    0x2B => Self::I64AtomicRmw32SubU(MemArg::parse(value)?),
+                    0x2C => Self::I32AtomicRmwAnd(MemArg::parse(value)?),
+                    0x2D => Self::I64AtomicRmwAnd(MemArg::parse(value)?),
+                    0x2E => Self::I32AtomicRmw8AndU(MemArg::parse(value)?),
+                    0x2F => Self::I32AtomicRmw16AndU(MemArg::parse(value)?),
+                    0x30 => Self::I64AtomicRmw8AndU(MemArg::parse(value)?),
+                    0x31 => Self::I64AtomicRmw16AndU(MemArg::parse(value)?),
+                    0x32 => Self::I64AtomicRmw32AndU(MemArg::parse(value)?),
+                    0x33 => Self::I32AtomicRmwOr(MemArg::parse(value)?),
+                    0x34 => Self::I64AtomicRmwOr(MemArg::parse(value)?),
+                    0x35 => Self::I32AtomicRmw8OrU(MemArg::parse(value)?),
+                    0x36 => Self::I32AtomicRmw16OrU(MemArg::parse(value)?),
+                    0x37 => Self::I64AtomicRmw8OrU(MemArg::parse(value)?),
+                    0x38 => Self::I64AtomicRmw16OrU(MemArg::parse(value)?),
+                    0x39 => Self::I64AtomicRmw32OrU(MemArg::parse(value)?),
+                    0x3A => Self::I32AtomicRmwXor(MemArg::parse(value)?),
+                    0x3B => Self::I64AtomicRmwXor(MemArg::parse(value)?),
+                    0x3C => Self::I32AtomicRmw8XorU(MemArg::parse(value)?),
+                    0x3D => Self::I32AtomicRmw16XorU(MemArg::parse(value)?),
+                    0x3E => Self::I64AtomicRmw8XorU(MemArg::parse(value)?),
+                    0x3F => Self::I64AtomicRmw16XorU(MemArg::parse(value)?),
+                    0x40 => Self::I64AtomicRmw32XorU(MemArg::parse(value)?),
+                    0x41 => Self::I32AtomicRmwXchg(MemArg::parse(value)?),
+                    0x42 => Self::I64AtomicRmwXchg(MemArg::parse(value)?),
+                    0x43 => Self::I32AtomicRmw8XchgU(MemArg::parse(value)?),
+                    0x44 => Self::I32AtomicRmw16XchgU(MemArg::parse(value)?),
+                    0x45 => Self::I64AtomicRmw8XchgU(MemArg::parse(value)?),
+                    0x46 => Self::I64AtomicRmw16XchgU(MemArg::parse(value)?),
+                    0x47 => Self::I64AtomicRmw32XchgU(MemArg::parse(value)?),
+                    0x48 => Self::I32AtomicRmwCmpxchg(MemArg::parse(value)?),
+                    0x49 => Self::I64AtomicRmwCmpxchg(MemArg::parse(value)?),
+                    0x4A => Self::I32AtomicRmw8CmpxchgU(MemArg::parse(value)?),
+                    0x4B => Self::I32AtomicRmw16CmpxchgU(MemArg::parse(value)?),
+                    0x4C => Self::I64AtomicRmw8CmpxchgU(MemArg::parse(value)?),
+                    0x4D => Self::I64AtomicRmw16CmpxchgU(MemArg::parse(value)?),
+                    0x4E => Self::I64AtomicRmw32CmpxchgU(MemArg::parse(value)?),
+                    _ => {
+                        return Err(Error::InvalidModule(
+                            Backtrace::capture(),
+                            format!("unknown atomic instruction 0xfe {byte}"),
+                        ))
+                    }
+                }
             }
 
             0xFD => {
                 let byte = u32::parse(value)?;
                 match byte {
-                    0..=11 | 92 | 93 => {
-                        // TODO
+                    12 => Self::V128_Const(crate::read_bytes(value)?),
+                    13 => Self::I8X16_Shuffle(crate::read_bytes(value)?),
+                    0 => Self::V128_Load(MemArg::parse(value)?),
+                    1 => Self::V128_Load_8x8_S(MemArg::parse(value)?),
+                    2 => Self::V128_Load_8x8_U(MemArg::parse(value)?),
+                    3 => Self::V128_Load_16x4_S(MemArg::parse(value)?),
+                    4 => Self::V128_Load_16x4_U(MemArg::parse(value)?),
+                    5 => Self::V128_Load_32x2_S(MemArg::parse(value)?),
+                    6 => Self::V128_Load_32x2_U(MemArg::parse(value)?),
+                    7 => Self::V128_Load_8_Splat(MemArg::parse(value)?),
+                    8 => Self::V128_Load_16_Splat(MemArg::parse(value)?),
+                    9 => Self::V128_Load_32_Splat(MemArg::parse(value)?),
+                    10 => Self::V128_Load_64_Splat(MemArg::parse(value)?),
+                    11 => Self::V128_Store(MemArg::parse(value)?),
+                    92 => Self::V128_Load_32_Zero(MemArg::parse(value)?),
+                    93 => Self::V128_Load_64_Zero(MemArg::parse(value)?),
+                    84 => {
                         let a = MemArg::parse(value)?;
-                        Self::V128_Load(a)
+                        let b = read_byte(value)?;
+                        Self::V128_Load_8_Lane(a, b)
                     }
-                    12 => {
-                        let mut drain = value.drain(0..16);
-                        Self::V128_Const([
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                        ])
+                    85 => {
+                        let a = MemArg::parse(value)?;
+                        let b = read_byte(value)?;
+                        Self::V128_Load_16_Lane(a, b)
                     }
-                    13 => {
-                        let mut drain = value.drain(0..16);
-                        Self::I8X16_Shuffle([
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                            drain.next().unwrap(),
-                        ])
+                    86 => {
+                        let a = MemArg::parse(value)?;
+                        let b = read_byte(value)?;
+                        Self::V128_Load_32_Lane(a, b)
                     }
-                    14..=20 => {
-                        // TODO
-                        Self::I8x16_Swizzle
+                    87 => {
+                        let a = MemArg::parse(value)?;
+                        let b = read_byte(value)?;
+                        Self::V128_Load_64_Lane(a, b)
                     }
-                    21..=34 => {
-                        // TODO
-                        let byte = value.drain(..1).next().unwrap();
-                        Self::I8X16_Extract_Lane_S(byte)
+                    88 => {
+                        let a = MemArg::parse(value)?;
+                        let b = read_byte(value)?;
+                        Self::V128_Store_8_Lane(a, b)
                     }
-                    35..=255 => {
-                        // TODO
-                        Self::I8X16_Eq
+                    89 => {
+                        let a = MemArg::parse(value)?;
+                        let b = read_byte(value)?;
+                        Self::V128_Store_16_Lane(a, b)
                     }
-                    84..=91 => {
+                    90 => {
                         let a = MemArg::parse(value)?;
-                        let b = value.drain(..1).next().unwrap();
-                        Self::V128_Load_8_Lane(a, b)
+                        let b = read_byte(value)?;
+                        Self::V128_Store_32_Lane(a, b)
                     }
+                    91 => {
+                        let a = MemArg::parse(value)?;
+                        let b = read_byte(value)?;
+                        Self::V128_Store_64_Lane(a, b)
+                    }
+                    21 => Self::I8X16_Extract_Lane_S(read_byte(value)?),
+                    22 => Self::I8X16_Extract_Lane_U(read_byte(value)?),
+                    23 => Self::I8X16_Replace_Lane(read_byte(value)?),
+                    24 => Self::I16X8_Extract_Lane_S(read_byte(value)?),
+                    25 => Self::I16X8_Extract_Lane_U(read_byte(value)?),
+                    26 => Self::I16X8_Replace_Lane(read_byte(value)?),
+                    27 => Self::I32X4_Extract_Lane(read_byte(value)?),
+                    28 => Self::I32X4_Replace_Lane(read_byte(value)?),
+                    29 => Self::I64X2_Extract_Lane(read_byte(value)?),
+                    30 => Self::I64X2_Replace_Lane(read_byte(value)?),
+                    31 => Self::F32X4_Extract_Lane(read_byte(value)?),
+                    32 => Self::F32X4_Replace_Lane(read_byte(value)?),
+                    33 => Self::F64X2_Extract_Lane(read_byte(value)?),
+                    34 => Self::F64X2_Replace_Lane(read_byte(value)?),
+                    14 => Self::I8x16_Swizzle,
+                    15 => Self::I8X16_Splat,
+                    16 => Self::I16X8_Splat,
+                    17 => Self::I32X4_Splat,
+                    18 => Self::I64X2_Splat,
+                    19 => Self::F32X4_Splat,
+                    20 => Self::F64X2_Splat,
+                    35 => Self::I8X16_Eq,
+                    36 => Self::I8X16_Ne,
+                    37 => Self::I8X16_Lt_S,
+                    38 => Self::I8X16_Lt_U,
+                    39 => Self::I8X16_Gt_S,
+                    40 => Self::I8X16_Gt_U,
+                    41 => Self::I8X16_Le_S,
+                    42 => Self::I8X16_Le_U,
+                    43 => Self::I8X16_Ge_S,
+                    44 => Self::I8X16_Ge_U,
+                    45 => Self::I16X8_Eq,
+                    46 => Self::I16X8_Ne,
+                    47 => Self::I16X8_Lt_S,
+                    48 => Self::I16X8_Lt_U,
+                    49 => Self::I16X8_Gt_S,
+                    50 => Self::I16X8_Gt_U,
+                    51 => Self::I16X8_Le_S,
+                    52 => Self::I16X8_Le_U,
+                    53 => Self::I16X8_Ge_S,
+                    54 => Self::I16X8_Ge_U,
+                    55 => Self::I32X4_Eq,
+                    56 => Self::I32X4_Ne,
+                    57 => Self::I32X4_Lt_S,
+                    58 => Self::I32X4_Lt_U,
+                    59 => Self::I32X4_Gt_S,
+                    60 => Self::I32X4_Gt_U,
+                    61 => Self::I32X4_Le_S,
+                    62 => Self::I32X4_Le_U,
+                    63 => Self::I32X4_Ge_S,
+                    64 => Self::I32X4_Ge_U,
+                    65 => Self::F32X4_Eq,
+                    66 => Self::F32X4_Ne,
+                    67 => Self::F32X4_Lt,
+                    68 => Self::F32X4_Gt,
+                    69 => Self::F32X4_Le,
+                    70 => Self::F32X4_Ge,
+                    71 => Self::F64X2_Eq,
+                    72 => Self::F64X2_Ne,
+                    73 => Self::F64X2_Lt,
+                    74 => Self::F64X2_Gt,
+                    75 => Self::F64X2_Le,
+                    76 => Self::F64X2_Ge,
+                    77 => Self::V128_Not,
+                    78 => Self::V128_And,
+                    79 => Self::V128_Andnot,
+                    80 => Self::V128_Or,
+                    81 => Self::V128_Xor,
+                    82 => Self::V128_Bitselect,
+                    83 => Self::V128_Any_True,
+                    94 => Self::F32X4_Demote_F64X2_Zero,
+                    95 => Self::F64X2_Promote_Low_F32X4,
+                    96 => Self::I8X16_Abs,
+                    97 => Self::I8X16_Neg,
+                    98 => Self::I8X16_Popcnt,
+                    99 => Self::I8X16_All_True,
+                    100 => Self::I8X16_Bitmask,
+                    101 => Self::I8X16_Narrow_I16X8_S,
+                    102 => Self::I8X16_Narrow_I16X8_U,
+                    103 => Self::F32X4_Ceil,
+                    104 => Self::F32X4_Floor,
+                    105 => Self::F32X4_Trunc,
+                    106 => Self::F32X4_Nearest,
+                    107 => Self::I8X16_Shl,
+                    108 => Self::I8X16_Shr_S,
+                    109 => Self::I8X16_Shr_U,
+                    110 => Self::I8X16_Add,
+                    111 => Self::I8X16_Add_Sat_S,
+                    112 => Self::I8X16_Add_Sat_U,
+                    113 => Self::I8X16_Sub,
+                    114 => Self::I8X16_Sub_Sat_S,
+                    115 => Self::I8X16_Sub_Sat_U,
+                    116 => Self::F64X2_Ceil,
+                    117 => Self::F64X2_Floor,
+                    118 => Self::I8X16_Min_S,
+                    119 => Self::I8X16_Min_U,
+                    120 => Self::I8X16_Max_S,
+                    121 => Self::I8X16_Max_U,
+                    122 => Self::F64X2_Trunc,
+                    123 => Self::I8X16_Avgr_U,
+                    124 => Self::I16X8_Extadd_Pairwise_I8X16_S,
+                    125 => Self::I16X8_Extadd_Pairwise_I8X16_U,
+                    126 => Self::I32X4_Extadd_Pairwise_I16X8_S,
+                    127 => Self::I32X4_Extadd_Pairwise_I16X8_U,
+                    128 => Self::I16X8_Abs,
+                    129 => Self::I16X8_Neg,
+                    130 => Self::I16X8_Q15mulr_Sat_S,
+                    131 => Self::I16X8_All_True,
+                    132 => Self::I16X8_Bitmask,
+                    133 => Self::I16X8_Narrow_I32X4_S,
+                    134 => Self::I16X8_Narrow_I32X4_U,
+                    135 => Self::I16X8_Extend_Low_I8X16_S,
+                    136 => Self::I16X8_Extend_High_I8X16_S,
+                    137 => Self::I16X8_Extend_Low_I8X16_U,
+                    138 => Self::I16X8_Extend_High_I8X16_U,
+                    139 => Self::I16X8_Shl,
+                    140 => Self::I16X8_Shr_S,
+                    141 => Self::I16X8_Shr_U,
+                    142 => Self::I16X8_Add,
+                    143 => Self::I16X8_Add_Sat_S,
+                    144 => Self::I16X8_Add_Sat_U,
+                    145 => Self::I16X8_Sub,
+                    146 => Self::I16X8_Sub_Sat_S,
+                    147 => Self::I16X8_Sub_Sat_U,
+                    148 => Self::F64X2_Nearest,
+                    149 => Self::I16X8_Mul,
+                    150 => Self::I16X8_Min_S,
+                    151 => Self::I16X8_Min_U,
+                    152 => Self::I16X8_Max_S,
+                    153 => Self::I16X8_Max_U,
+                    155 => Self::I16X8_Avgr_U,
+                    156 => Self::I16X8_Extmul_Low_I8X16_S,
+                    157 => Self::I16X8_Extmul_High_I8X16_S,
+                    158 => Self::I16X8_Extmul_Low_I8X16_U,
+                    159 => Self::I16X8_Extmul_High_I8X16_U,
+                    160 => Self::I32X4_Abs,
+                    161 => Self::I32X4_Neg,
+                    163 => Self::I32X4_All_True,
+                    164 => Self::I32X4_Bitmask,
+                    167 => Self::I32X4_Extend_Low_I16X8_S,
+                    168 => Self::I32X4_Extend_High_I16X8_S,
+                    169 => Self::I32X4_Extend_Low_I16X8_U,
+                    170 => Self::I32X4_Extend_High_I16X8_U,
+                    171 => Self::I32X4_Shl,
+                    172 => Self::I32X4_Shr_S,
+                    173 => Self::I32X4_Shr_U,
+                    174 => Self::I32X4_Add,
+                    177 => Self::I32X4_Sub,
+                    181 => Self::I32X4_Mul,
+                    182 => Self::I32X4_Min_S,
+                    183 => Self::I32X4_Min_U,
+                    184 => Self::I32X4_Max_S,
+                    185 => Self::I32X4_Max_U,
+                    186 => Self::I32X4_Dot_I16X8_S,
+                    188 => Self::I32X4_Extmul_Low_I16X8_S,
+                    189 => Self::I32X4_Extmul_High_I16X8_S,
+                    190 => Self::I32X4_Extmul_Low_I16X8_U,
+                    191 => Self::I32X4_Extmul_High_I16X8_U,
+                    192 => Self::I64X2_Abs,
+                    193 => Self::I64X2_Neg,
+                    195 => Self::I64X2_All_True,
+                    196 => Self::I64X2_Bitmask,
+                    199 => Self::I64X2_Extend_Low_I32X4_S,
+                    200 => Self::I64X2_Extend_High_I32X4_S,
+                    201 => Self::I64X2_Extend_Low_I32X4_U,
+                    202 => Self::I64X2_Extend_High_I32X4_U,
+                    203 => Self::I64X2_Shl,
+                    204 => Self::I64X2_Shr_S,
+                    205 => Self::I64X2_Shr_U,
+                    206 => Self::I64X2_Add,
+                    209 => Self::I64X2_Sub,
+                    213 => Self::I64X2_Mul,
+                    214 => Self::I64X2_Eq,
+                    215 => Self::I64X2_Ne,
+                    216 => Self::I64X2_Lt_S,
+                    217 => Self::I64X2_Gt_S,
+                    218 => Self::I64X2_Le_S,
+                    219 => Self::I64X2_Ge_S,
+                    220 => Self::I64X2_Extmul_Low_I32X4_S,
+                    221 => Self::I64X2_Extmul_High_I32X4_S,
+                    222 => Self::I64X2_Extmul_Low_I32X4_U,
+                    223 => Self::I64X2_Extmul_High_I32X4_U,
+                    224 => Self::F32X4_Abs,
+                    225 => Self::F32X4_Neg,
+                    227 => Self::F32X4_Sqrt,
+                    228 => Self::F32X4_Add,
+                    229 => Self::F32X4_Sub,
+                    230 => Self::F32X4_Mul,
+                    231 => Self::F32X4_Div,
+                    232 => Self::F32X4_Min,
+                    233 => Self::F32X4_Max,
+                    234 => Self::F32X4_Pmin,
+                    235 => Self::F32X4_Pmax,
+                    236 => Self::F64X2_Abs,
+                    237 => Self::F64X2_Neg,
+                    239 => Self::F64X2_Sqrt,
+                    240 => Self::F64X2_Add,
+                    241 => Self::F64X2_Sub,
+                    242 => Self::F64X2_Mul,
+                    243 => Self::F64X2_Div,
+                    244 => Self::F64X2_Min,
+                    245 => Self::F64X2_Max,
+                    246 => Self::F64X2_Pmin,
+                    247 => Self::F64X2_Pmax,
+                    248 => Self::I32X4_Trunc_Sat_F32X4_S,
+                    249 => Self::I32X4_Trunc_Sat_F32X4_U,
+                    250 => Self::F32X4_Convert_I32X4_S,
+                    251 => Self::F32X4_Convert_I32X4_U,
+                    252 => Self::I32X4_Trunc_Sat_F64X2_S_Zero,
+                    253 => Self::I32X4_Trunc_Sat_F64X2_U_Zero,
+                    254 => Self::F64X2_Convert_Low_I32X4_S,
+                    255 => Self::F64X2_Convert_Low_I32X4_U,
+                    0x100 => Self::I8X16_Relaxed_Swizzle,
+                    0x101 => Self::I32X4_Relaxed_Trunc_F32X4_S,
+                    0x102 => Self::I32X4_Relaxed_Trunc_F32X4_U,
+                    0x103 => Self::I32X4_Relaxed_Trunc_F64X2_S_Zero,
+                    0x104 => Self::I32X4_Relaxed_Trunc_F64X2_U_Zero,
+                    0x105 => Self::F32X4_Relaxed_Madd,
+                    0x106 => Self::F32X4_Relaxed_Nmadd,
+                    0x107 => Self::F64X2_Relaxed_Madd,
+                    0x108 => Self::F64X2_Relaxed_Nmadd,
+                    0x109 => Self::I8X16_Relaxed_Laneselect,
+                    0x10a => Self::I16X8_Relaxed_Laneselect,
+                    0x10b => Self::I32X4_Relaxed_Laneselect,
+                    0x10c => Self::I64X2_Relaxed_Laneselect,
+                    0x10d => Self::F32X4_Relaxed_Min,
+                    0x10e => Self::F32X4_Relaxed_Max,
+                    0x10f => Self::F64X2_Relaxed_Min,
+                    0x110 => Self::F64X2_Relaxed_Max,
+                    0x111 => Self::I16X8_Relaxed_Q15mulr_S,
+                    0x112 => Self::I16X8_Relaxed_Dot_I8X16_I7X16_S,
+                    0x113 => Self::I32X4_Relaxed_Dot_I8X16_I7X16_Add_S,
                     _ => {
-                        unimplemented!("{byte}")
+                        return Err(Error::InvalidModule(
+                            Backtrace::capture(),
+                            format!("unknown SIMD instruction 0xfd {byte}"),
+                        ))
                     }
                 }
             }
 
             _ => {
-                panic!("Unimplemented instruction: {byte}")
+                return Err(Error::InvalidModule(
+                    Backtrace::capture(),
+                    format!("unknown instruction {byte:#04x}"),
+                ))
             }
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Instr;
+    use crate::{Error, Parse};
+
+    #[test]
+    fn unknown_atomic_opcode() {
+        let mut data = vec![0xFE, 0x4F];
+        match Instr::parse(&mut data) {
+            Err(Error::InvalidModule(_, msg)) => {
+                assert_eq!(msg, "unknown atomic instruction 0xfe 79")
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_misc_opcode() {
+        let mut data = vec![0xFC, 0x7F];
+        match Instr::parse(&mut data) {
+            Err(Error::InvalidModule(_, msg)) => {
+                assert_eq!(msg, "unknown instruction 0xfc 127")
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut data = vec![0xFF];
+        match Instr::parse(&mut data) {
+            Err(Error::InvalidModule(_, msg)) => {
+                assert_eq!(msg, "unknown instruction 0xff")
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+}
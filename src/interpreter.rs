@@ -0,0 +1,2901 @@
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::rc::Rc;
+
+use crate::instructions::{BlockType, Catch, Instr, MemArg};
+use crate::modules::*;
+use crate::printer::natural_align;
+use crate::types::*;
+use crate::validate::validate;
+use crate::Error;
+
+pub type FuncAddr = usize;
+pub type TableAddr = usize;
+pub type MemAddr = usize;
+pub type GlobalAddr = usize;
+pub type ElemAddr = usize;
+pub type DataAddr = usize;
+pub type TagAddr = usize;
+pub type ExnAddr = usize;
+pub type InstanceAddr = usize;
+
+pub const PAGE_SIZE: usize = 0x10000;
+const MAX_PAGES: u64 = 0x10000;
+const MAX_PAGES64: u64 = 1 << 48;
+/// Default for `Config::max_call_depth`.
+pub const MAX_CALL_DEPTH: usize = 256;
+/// Default for `Config::max_locals`.
+pub const MAX_LOCALS: usize = 50_000;
+/// Default for `Config::max_table_elements`.
+pub const MAX_TABLE_ELEMENTS: u64 = 10_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128),
+    FuncRef(Option<FuncAddr>),
+    ExternRef(Option<u32>),
+    ExnRef(Option<ExnAddr>),
+}
+
+impl Value {
+    pub fn zero(ty: ValType) -> Self {
+        match ty {
+            ValType::NumType(NumType::I32) => Value::I32(0),
+            ValType::NumType(NumType::I64) => Value::I64(0),
+            ValType::NumType(NumType::F32) => Value::F32(0.0),
+            ValType::NumType(NumType::F64) => Value::F64(0.0),
+            ValType::VecType(VecType::V128) => Value::V128(0),
+            ValType::RefType(RefType(_, HeapType::Func | HeapType::NoFunc | HeapType::Type(_))) => {
+                Value::FuncRef(None)
+            }
+            ValType::RefType(RefType(_, HeapType::Exn | HeapType::NoExn)) => Value::ExnRef(None),
+            // There is no GC heap, so the only references of the `any` hierarchy
+            // are null and share the representation of null external references.
+            ValType::RefType(RefType(_, _)) => Value::ExternRef(None),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            Value::FuncRef(None) | Value::ExternRef(None) | Value::ExnRef(None)
+        )
+    }
+
+    /// Whether the value may be passed as `ty`. The concrete type of a function
+    /// reference is not checked.
+    pub fn fits(&self, ty: ValType) -> bool {
+        match ty {
+            ValType::RefType(RefType(nullable, _)) => {
+                Value::zero(ty).ty() == self.ty() && (nullable || !self.is_null())
+            }
+            _ => self.ty() == ty,
+        }
+    }
+
+    pub fn ty(&self) -> ValType {
+        match self {
+            Value::I32(_) => ValType::NumType(NumType::I32),
+            Value::I64(_) => ValType::NumType(NumType::I64),
+            Value::F32(_) => ValType::NumType(NumType::F32),
+            Value::F64(_) => ValType::NumType(NumType::F64),
+            Value::V128(_) => ValType::VecType(VecType::V128),
+            Value::FuncRef(_) => ValType::RefType(RefType::FUNCREF),
+            Value::ExternRef(_) => ValType::RefType(RefType::EXTERNREF),
+            Value::ExnRef(_) => ValType::RefType(RefType::EXNREF),
+        }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::I32(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::I32(value as i32)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::I64(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::F32(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::F64(value)
+    }
+}
+
+impl From<u128> for Value {
+    fn from(value: u128) -> Self {
+        Value::V128(value)
+    }
+}
+
+/// Runtime errors, displayed with the messages used by the spec test suite.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trap {
+    Unreachable,
+    MemoryOutOfBounds,
+    TableOutOfBounds,
+    UndefinedElement,
+    UninitializedElement,
+    IndirectCallTypeMismatch,
+    IntegerDivideByZero,
+    IntegerOverflow,
+    InvalidConversionToInteger,
+    CallStackExhausted,
+    OutOfFuel,
+    /// A function or table needs more than the `Config` allows.
+    ResourceLimit(&'static str),
+    UnalignedAtomic,
+    ExpectedSharedMemory,
+    NullExceptionReference,
+    NullFunctionReference,
+    NullReference,
+    /// An exception thrown by wasm that no handler caught.
+    Exception(ExnAddr),
+    Unsupported(&'static str),
+    Host(String),
+    /// A host function ended the program, e.g. WASI `proc_exit`.
+    Exit(i32),
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trap::Unreachable => write!(f, "unreachable"),
+            Trap::MemoryOutOfBounds => write!(f, "out of bounds memory access"),
+            Trap::TableOutOfBounds => write!(f, "out of bounds table access"),
+            Trap::UndefinedElement => write!(f, "undefined element"),
+            Trap::UninitializedElement => write!(f, "uninitialized element"),
+            Trap::IndirectCallTypeMismatch => write!(f, "indirect call type mismatch"),
+            Trap::IntegerDivideByZero => write!(f, "integer divide by zero"),
+            Trap::IntegerOverflow => write!(f, "integer overflow"),
+            Trap::InvalidConversionToInteger => write!(f, "invalid conversion to integer"),
+            Trap::CallStackExhausted => write!(f, "call stack exhausted"),
+            Trap::OutOfFuel => write!(f, "all fuel consumed"),
+            Trap::ResourceLimit(name) => write!(f, "{name} limit exceeded"),
+            Trap::UnalignedAtomic => write!(f, "unaligned atomic"),
+            Trap::ExpectedSharedMemory => write!(f, "expected shared memory"),
+            Trap::NullExceptionReference => write!(f, "null exception reference"),
+            Trap::NullFunctionReference => write!(f, "null function reference"),
+            Trap::NullReference => write!(f, "null reference"),
+            Trap::Exception(_) => write!(f, "uncaught exception"),
+            Trap::Unsupported(name) => write!(f, "unsupported instruction {name}"),
+            Trap::Host(msg) => write!(f, "{msg}"),
+            Trap::Exit(code) => write!(f, "exit with code {code}"),
+        }
+    }
+}
+
+pub type HostFunc = Rc<dyn Fn(&mut Caller, &[Value]) -> Result<Vec<Value>, Trap>>;
+
+#[derive(Clone)]
+pub enum FuncInst {
+    Wasm {
+        ty: FuncType,
+        instance: InstanceAddr,
+        code: Rc<Func>,
+    },
+    Host {
+        ty: FuncType,
+        func: HostFunc,
+    },
+}
+
+impl FuncInst {
+    pub fn ty(&self) -> &FuncType {
+        match self {
+            FuncInst::Wasm { ty, .. } | FuncInst::Host { ty, .. } => ty,
+        }
+    }
+}
+
+impl Debug for FuncInst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FuncInst::Wasm { ty, instance, .. } => f
+                .debug_struct("Wasm")
+                .field("ty", ty)
+                .field("instance", instance)
+                .finish(),
+            FuncInst::Host { ty, .. } => f.debug_struct("Host").field("ty", ty).finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TableInst {
+    pub ty: TableType,
+    pub elems: Vec<Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemInst {
+    pub ty: MemType,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GlobalInst {
+    pub ty: GlobalType,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct TagInst {
+    pub ty: FuncType,
+}
+
+/// A thrown exception: its tag and the payload values.
+#[derive(Debug, Clone)]
+pub struct ExnInst {
+    pub tag: TagAddr,
+    pub fields: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternVal {
+    Func(FuncAddr),
+    Table(TableAddr),
+    Mem(MemAddr),
+    Global(GlobalAddr),
+    Tag(TagAddr),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModuleInst {
+    pub types: Vec<SubType>,
+    pub funcs: Vec<FuncAddr>,
+    pub tables: Vec<TableAddr>,
+    pub mems: Vec<MemAddr>,
+    pub globals: Vec<GlobalAddr>,
+    pub tags: Vec<TagAddr>,
+    pub elems: Vec<ElemAddr>,
+    pub datas: Vec<DataAddr>,
+    pub exports: Vec<(String, ExternVal)>,
+}
+
+impl ModuleInst {
+    pub fn func_type(&self, idx: TypeIdx) -> Option<&FuncType> {
+        self.types.get(idx as usize)?.func()
+    }
+}
+
+/// Values provided for a module's imports, keyed by module and field name.
+#[derive(Debug, Clone, Default)]
+pub struct Imports {
+    defs: HashMap<(String, String), ExternVal>,
+}
+
+impl Imports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn define(&mut self, module: &str, name: &str, value: ExternVal) {
+        self.defs
+            .insert((module.to_string(), name.to_string()), value);
+    }
+
+    /// Makes every export of `instance` importable under the module name `module`.
+    pub fn instance(&mut self, store: &Store, module: &str, instance: InstanceAddr) {
+        for (name, value) in &store.instances[instance].exports {
+            self.define(module, name, *value);
+        }
+    }
+
+    pub fn get(&self, module: &str, name: &str) -> Option<ExternVal> {
+        self.defs
+            .get(&(module.to_string(), name.to_string()))
+            .copied()
+    }
+}
+
+/// Context given to host functions.
+pub struct Caller<'a> {
+    pub store: &'a mut Store,
+    /// The calling instance, `None` when invoked directly from the embedder.
+    pub instance: Option<InstanceAddr>,
+}
+
+impl Caller<'_> {
+    /// The first memory of the calling instance.
+    pub fn memory(&mut self) -> Option<&mut MemInst> {
+        let instance = &self.store.instances[self.instance?];
+        let addr = *instance.mems.first()?;
+        Some(&mut self.store.mems[addr])
+    }
+
+    pub fn export(&self, name: &str) -> Option<ExternVal> {
+        self.store.export(self.instance?, name)
+    }
+}
+
+/// Fuel charged per executed instruction, by mnemonic.
+#[derive(Debug, Clone)]
+pub struct Costs {
+    pub default: u64,
+    pub ops: HashMap<&'static str, u64>,
+    /// Charged per byte or element written by `memory.fill`, `memory.copy`,
+    /// `table.fill` and `table.copy`, on top of their own cost.
+    pub per_element: u64,
+}
+
+impl Default for Costs {
+    fn default() -> Self {
+        Self {
+            default: 1,
+            ops: HashMap::new(),
+            per_element: 1,
+        }
+    }
+}
+
+impl Costs {
+    pub fn set(&mut self, name: &'static str, cost: u64) -> &mut Self {
+        self.ops.insert(name, cost);
+        self
+    }
+
+    pub fn cost(&self, instr: &Instr) -> u64 {
+        self.ops.get(instr.name()).copied().unwrap_or(self.default)
+    }
+}
+
+/// Execution limits, to run untrusted code with bounded resources.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub costs: Costs,
+    /// Nested wasm calls and blocks allowed before trapping with `call stack
+    /// exhausted`. Both recurse on the host's stack, so they share the limit.
+    pub max_call_depth: usize,
+    /// Memories never grow past this many pages, whatever their declared maximum.
+    pub max_memory_pages: u32,
+    /// Parameters and declared locals of a function, checked when it is called.
+    pub max_locals: usize,
+    /// Tables are neither allocated nor grown past this many elements.
+    pub max_table_elements: u64,
+    /// Replace every NaN produced by float arithmetic with the positive
+    /// canonical NaN, so results do not depend on the host's NaN propagation.
+    pub canonicalize_nans: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            costs: Costs::default(),
+            max_call_depth: MAX_CALL_DEPTH,
+            max_memory_pages: MAX_PAGES as u32,
+            max_locals: MAX_LOCALS,
+            max_table_elements: MAX_TABLE_ELEMENTS,
+            canonicalize_nans: false,
+        }
+    }
+}
+
+/// All runtime objects, shared between the instances created in it.
+#[derive(Debug, Default)]
+pub struct Store {
+    pub config: Config,
+    pub funcs: Vec<FuncInst>,
+    pub tables: Vec<TableInst>,
+    pub mems: Vec<MemInst>,
+    pub globals: Vec<GlobalInst>,
+    pub tags: Vec<TagInst>,
+    pub exns: Vec<ExnInst>,
+    pub elems: Vec<Vec<Value>>,
+    pub datas: Vec<Vec<u8>>,
+    pub instances: Vec<ModuleInst>,
+    stack: Vec<Value>,
+    depth: usize,
+    /// Remaining fuel, `None` when execution is not metered.
+    fuel: Option<u64>,
+}
+
+struct Frame {
+    locals: Vec<Value>,
+    instance: InstanceAddr,
+}
+
+enum Flow {
+    Next,
+    Branch(u32),
+    Return,
+    /// `rethrow` of the exception caught by the handler at this depth.
+    Rethrow(u32),
+    /// An exception leaving a `try ... delegate` towards the label at this depth.
+    Delegate(u32, ExnAddr),
+    /// `return_call` of this function with its arguments on the stack.
+    TailCall(FuncAddr),
+}
+
+/// An address or length of a 32- or 64-bit memory or table.
+fn index(value: Value) -> u64 {
+    match value {
+        Value::I32(value) => value as u32 as u64,
+        Value::I64(value) => value as u64,
+        value => panic!("expected an index, found {value:?}"),
+    }
+}
+
+fn limits_match(actual: &Limits, expected: &Limits) -> bool {
+    actual.3 == expected.3
+        && actual.0 >= expected.0
+        && match (actual.1, expected.1) {
+            (_, None) => true,
+            (Some(actual), Some(expected)) => actual <= expected,
+            (None, Some(_)) => false,
+        }
+}
+
+/// `min` with NaN propagation and `-0 < +0`.
+fn f32_min(a: f32, b: f32) -> f32 {
+    match (a.is_nan() || b.is_nan(), a == b) {
+        (true, _) => a + b,
+        (_, true) => f32::from_bits(a.to_bits() | b.to_bits()),
+        _ => a.min(b),
+    }
+}
+
+fn f32_max(a: f32, b: f32) -> f32 {
+    match (a.is_nan() || b.is_nan(), a == b) {
+        (true, _) => a + b,
+        (_, true) => f32::from_bits(a.to_bits() & b.to_bits()),
+        _ => a.max(b),
+    }
+}
+
+fn f64_min(a: f64, b: f64) -> f64 {
+    match (a.is_nan() || b.is_nan(), a == b) {
+        (true, _) => a + b,
+        (_, true) => f64::from_bits(a.to_bits() | b.to_bits()),
+        _ => a.min(b),
+    }
+}
+
+fn f64_max(a: f64, b: f64) -> f64 {
+    match (a.is_nan() || b.is_nan(), a == b) {
+        (true, _) => a + b,
+        (_, true) => f64::from_bits(a.to_bits() & b.to_bits()),
+        _ => a.max(b),
+    }
+}
+
+/// A lane of a `v128`, stored little endian.
+trait Lane: Copy + Default {
+    fn read(bytes: &[u8]) -> Self;
+    fn write(self, bytes: &mut [u8]);
+}
+
+macro_rules! impl_lane {
+    ($($ty:ty),*) => {$(
+        impl Lane for $ty {
+            fn read(bytes: &[u8]) -> Self {
+                <$ty>::from_le_bytes(bytes.try_into().unwrap())
+            }
+
+            fn write(self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes())
+            }
+        }
+    )*};
+}
+
+impl_lane!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+fn unpack<T: Lane, const L: usize>(value: u128) -> [T; L] {
+    let bytes = value.to_le_bytes();
+    let n = 16 / L;
+    std::array::from_fn(|i| T::read(&bytes[i * n..i * n + n]))
+}
+
+fn pack<T: Lane, const L: usize>(lanes: [T; L]) -> u128 {
+    let mut bytes = [0; 16];
+    let n = 16 / L;
+    for (i, lane) in lanes.into_iter().enumerate() {
+        lane.write(&mut bytes[i * n..i * n + n]);
+    }
+    u128::from_le_bytes(bytes)
+}
+
+/// A vector with all bits set in the lanes that are `true`.
+fn mask<const L: usize>(lanes: [bool; L]) -> u128 {
+    let width = 128 / L;
+    let ones = u128::MAX >> (128 - width);
+    lanes
+        .iter()
+        .enumerate()
+        .filter(|(_, set)| **set)
+        .fold(0, |acc, (i, _)| acc | ones << (i * width))
+}
+
+/// The float type of the results, or of the result lanes, of instructions
+/// whose NaNs depend on the host. Loads, reinterpretations, lane moves and
+/// the sign operations only move bits around.
+fn nan_type(instr: &Instr) -> Option<NumType> {
+    match instr {
+        Instr::F32Ceil
+        | Instr::F32Floor
+        | Instr::F32Trunc
+        | Instr::F32Nearest
+        | Instr::F32Sqrt
+        | Instr::F32Add
+        | Instr::F32Sub
+        | Instr::F32Mul
+        | Instr::F32Div
+        | Instr::F32Min
+        | Instr::F32Max
+        | Instr::F32DenoteF64
+        | Instr::F32X4_Ceil
+        | Instr::F32X4_Floor
+        | Instr::F32X4_Trunc
+        | Instr::F32X4_Nearest
+        | Instr::F32X4_Sqrt
+        | Instr::F32X4_Add
+        | Instr::F32X4_Sub
+        | Instr::F32X4_Mul
+        | Instr::F32X4_Div
+        | Instr::F32X4_Min
+        | Instr::F32X4_Max
+        | Instr::F32X4_Demote_F64X2_Zero
+        | Instr::F32X4_Relaxed_Madd
+        | Instr::F32X4_Relaxed_Nmadd
+        | Instr::F32X4_Relaxed_Min
+        | Instr::F32X4_Relaxed_Max => Some(NumType::F32),
+        Instr::F64Ceil
+        | Instr::F64Floor
+        | Instr::F64Trunc
+        | Instr::F64Nearest
+        | Instr::F64Sqrt
+        | Instr::F64Add
+        | Instr::F64Sub
+        | Instr::F64Mul
+        | Instr::F64Div
+        | Instr::F64Min
+        | Instr::F64Max
+        | Instr::F64PromoteF32
+        | Instr::F64X2_Ceil
+        | Instr::F64X2_Floor
+        | Instr::F64X2_Trunc
+        | Instr::F64X2_Nearest
+        | Instr::F64X2_Sqrt
+        | Instr::F64X2_Add
+        | Instr::F64X2_Sub
+        | Instr::F64X2_Mul
+        | Instr::F64X2_Div
+        | Instr::F64X2_Min
+        | Instr::F64X2_Max
+        | Instr::F64X2_Promote_Low_F32X4
+        | Instr::F64X2_Relaxed_Madd
+        | Instr::F64X2_Relaxed_Nmadd
+        | Instr::F64X2_Relaxed_Min
+        | Instr::F64X2_Relaxed_Max => Some(NumType::F64),
+        _ => None,
+    }
+}
+
+fn canonical_f32(value: f32) -> f32 {
+    match value.is_nan() {
+        true => f32::from_bits(0x7FC0_0000),
+        false => value,
+    }
+}
+
+fn canonical_f64(value: f64) -> f64 {
+    match value.is_nan() {
+        true => f64::from_bits(0x7FF8_0000_0000_0000),
+        false => value,
+    }
+}
+
+/// Truncates towards zero, trapping unless the result lies in `min..max`.
+fn trunc(value: f64, min: f64, max: f64) -> Result<f64, Trap> {
+    if value.is_nan() {
+        return Err(Trap::InvalidConversionToInteger);
+    }
+    let value = value.trunc();
+    if value < min || value >= max {
+        return Err(Trap::IntegerOverflow);
+    }
+    Ok(value)
+}
+
+const I32_MIN: f64 = -2147483648.0;
+const I32_MAX: f64 = 2147483648.0;
+const U32_MAX: f64 = 4294967296.0;
+const I64_MIN: f64 = -9223372036854775808.0;
+const I64_MAX: f64 = 9223372036854775808.0;
+const U64_MAX: f64 = 18446744073709551616.0;
+
+impl Store {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Meters execution: every instruction consumes fuel according to
+    /// `Config::costs` and traps once the remaining fuel does not cover it.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    pub fn alloc_host_func(
+        &mut self,
+        ty: FuncType,
+        func: impl Fn(&mut Caller, &[Value]) -> Result<Vec<Value>, Trap> + 'static,
+    ) -> FuncAddr {
+        self.funcs.push(FuncInst::Host {
+            ty,
+            func: Rc::new(func),
+        });
+        self.funcs.len() - 1
+    }
+
+    pub fn alloc_table(&mut self, ty: TableType, init: Value) -> TableAddr {
+        let elems = vec![init; ty.1 .0 as usize];
+        self.tables.push(TableInst { ty, elems });
+        self.tables.len() - 1
+    }
+
+    pub fn alloc_memory(&mut self, ty: MemType) -> MemAddr {
+        let data = vec![0; ty.0 as usize * PAGE_SIZE];
+        self.mems.push(MemInst { ty, data });
+        self.mems.len() - 1
+    }
+
+    pub fn alloc_global(&mut self, ty: GlobalType, value: Value) -> GlobalAddr {
+        self.globals.push(GlobalInst { ty, value });
+        self.globals.len() - 1
+    }
+
+    pub fn alloc_tag(&mut self, ty: FuncType) -> TagAddr {
+        self.tags.push(TagInst { ty });
+        self.tags.len() - 1
+    }
+
+    pub fn export(&self, instance: InstanceAddr, name: &str) -> Option<ExternVal> {
+        self.instances[instance]
+            .exports
+            .iter()
+            .find(|(export, _)| export == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Validates and instantiates a module: resolves imports, allocates its
+    /// definitions, initializes segments and runs the start function.
+    pub fn instantiate(
+        &mut self,
+        module: &Module,
+        imports: &Imports,
+    ) -> Result<InstanceAddr, Error> {
+        validate(module)?;
+        let addr = self.instances.len();
+        let mut inst = ModuleInst {
+            types: module.types().into_iter().cloned().collect(),
+            ..Default::default()
+        };
+
+        for import in module.imports() {
+            let link = |msg: &str| {
+                Error::Link(
+                    Backtrace::capture(),
+                    format!("{msg}: {}.{}", import.module, import.name),
+                )
+            };
+            let value = imports
+                .get(&import.module, &import.name)
+                .ok_or_else(|| link("unknown import"))?;
+            match (&import.desc, value) {
+                (ImportDesc::TypeIdx(ty), ExternVal::Func(func))
+                    if Some(self.funcs[func].ty()) == inst.func_type(*ty) =>
+                {
+                    inst.funcs.push(func)
+                }
+                (ImportDesc::TableType(ty), ExternVal::Table(table)) => {
+                    let actual = &self.tables[table];
+                    let limits = Limits(
+                        actual.elems.len() as u64,
+                        actual.ty.1 .1,
+                        false,
+                        actual.ty.1 .3,
+                    );
+                    if actual.ty.0 != ty.0 || !limits_match(&limits, &ty.1) {
+                        return Err(link("incompatible import type"));
+                    }
+                    inst.tables.push(table)
+                }
+                (ImportDesc::MemType(ty), ExternVal::Mem(mem)) => {
+                    let actual = &self.mems[mem];
+                    let limits = Limits(
+                        (actual.data.len() / PAGE_SIZE) as u64,
+                        actual.ty.1,
+                        actual.ty.2,
+                        actual.ty.3,
+                    );
+                    if !limits_match(&limits, ty) || limits.2 != ty.2 {
+                        return Err(link("incompatible import type"));
+                    }
+                    inst.mems.push(mem)
+                }
+                (ImportDesc::GlobalType(ty), ExternVal::Global(global))
+                    if self.globals[global].ty == *ty =>
+                {
+                    inst.globals.push(global)
+                }
+                (ImportDesc::TagType(ty), ExternVal::Tag(tag))
+                    if Some(&self.tags[tag].ty) == inst.func_type(ty.0) =>
+                {
+                    inst.tags.push(tag)
+                }
+                _ => return Err(link("incompatible import type")),
+            }
+        }
+
+        let codes = module.codes();
+        for (i, ty) in module.funcs().iter().enumerate() {
+            self.funcs.push(FuncInst::Wasm {
+                ty: inst.func_type(*ty).unwrap().clone(),
+                instance: addr,
+                code: Rc::new(codes[i].1.clone()),
+            });
+            inst.funcs.push(self.funcs.len() - 1);
+        }
+        for ty in module.tables() {
+            if ty.1 .0 > self.config.max_table_elements {
+                return Err(Error::Trap(
+                    Backtrace::capture(),
+                    Trap::ResourceLimit("table elements"),
+                ));
+            }
+            let init = Value::zero(ValType::RefType(ty.0));
+            inst.tables.push(self.alloc_table(ty.clone(), init));
+        }
+        for ty in module.mems() {
+            if ty.0 > self.config.max_memory_pages as u64 {
+                return Err(Error::Link(
+                    Backtrace::capture(),
+                    format!("memory of {} pages exceeds the configured limit", ty.0),
+                ));
+            }
+            inst.mems.push(self.alloc_memory(ty.clone()));
+        }
+        for ty in module.tags() {
+            inst.tags
+                .push(self.alloc_tag(inst.func_type(ty.0).unwrap().clone()));
+        }
+        self.instances.push(inst);
+
+        let trap = |trap| Error::Trap(Backtrace::capture(), trap);
+        for (ty, init) in module.globals() {
+            let value = self.eval_const(addr, init).map_err(trap)?;
+            let global = self.alloc_global(ty.clone(), value);
+            self.instances[addr].globals.push(global);
+        }
+        for elem in module.elems() {
+            let values = match elem {
+                Elem::A(_, funcs)
+                | Elem::B(_, funcs)
+                | Elem::C(_, _, _, funcs)
+                | Elem::D(_, funcs) => {
+                    let inst = &self.instances[addr];
+                    funcs
+                        .iter()
+                        .map(|func| Value::FuncRef(Some(inst.funcs[*func as usize])))
+                        .collect()
+                }
+                Elem::E(_, exprs)
+                | Elem::F(_, exprs)
+                | Elem::G(_, _, _, exprs)
+                | Elem::H(_, exprs) => exprs
+                    .iter()
+                    .map(|expr| self.eval_const(addr, expr))
+                    .collect::<Result<_, _>>()
+                    .map_err(trap)?,
+            };
+            self.elems.push(values);
+            self.instances[addr].elems.push(self.elems.len() - 1);
+        }
+        for data in module.datas() {
+            let bytes = match data {
+                Data::A(_, bytes) | Data::B(bytes) | Data::C(_, _, bytes) => bytes.clone(),
+            };
+            self.datas.push(bytes);
+            self.instances[addr].datas.push(self.datas.len() - 1);
+        }
+        for (name, desc) in module.exports() {
+            let inst = &self.instances[addr];
+            let value = match desc {
+                ExportDesc::FuncIdx(idx) => ExternVal::Func(inst.funcs[*idx as usize]),
+                ExportDesc::TableIdx(idx) => ExternVal::Table(inst.tables[*idx as usize]),
+                ExportDesc::MemIdx(idx) => ExternVal::Mem(inst.mems[*idx as usize]),
+                ExportDesc::GlobalIdx(idx) => ExternVal::Global(inst.globals[*idx as usize]),
+                ExportDesc::TagIdx(idx) => ExternVal::Tag(inst.tags[*idx as usize]),
+            };
+            self.instances[addr].exports.push((name.clone(), value));
+        }
+
+        for (i, elem) in module.elems().iter().enumerate() {
+            let (table, offset, len) = match elem {
+                Elem::A(offset, funcs) => (0, offset, funcs.len()),
+                Elem::C(table, offset, _, funcs) => (*table, offset, funcs.len()),
+                Elem::E(offset, exprs) => (0, offset, exprs.len()),
+                Elem::G(table, offset, _, exprs) => (*table, offset, exprs.len()),
+                Elem::D(_, _) | Elem::H(_, _) => {
+                    let elem = self.instances[addr].elems[i];
+                    self.elems[elem].clear();
+                    continue;
+                }
+                Elem::B(_, _) | Elem::F(_, _) => continue,
+            };
+            let offset = index(self.eval_const(addr, offset).map_err(trap)?);
+            self.table_init(addr, table, i as u32, offset, 0, len as u32)
+                .map_err(trap)?;
+            let elem = self.instances[addr].elems[i];
+            self.elems[elem].clear();
+        }
+        for (i, data) in module.datas().iter().enumerate() {
+            let (mem, offset, len) = match data {
+                Data::A(offset, bytes) => (0, offset, bytes.len()),
+                Data::C(mem, offset, bytes) => (*mem, offset, bytes.len()),
+                Data::B(_) => continue,
+            };
+            let offset = index(self.eval_const(addr, offset).map_err(trap)?);
+            self.memory_init(addr, mem, i as u32, offset, 0, len as u32)
+                .map_err(trap)?;
+            let data = self.instances[addr].datas[i];
+            self.datas[data].clear();
+        }
+
+        if let Some(start) = module.start() {
+            let func = self.instances[addr].funcs[start as usize];
+            self.invoke(func, &[])?;
+        }
+        Ok(addr)
+    }
+
+    pub fn invoke(&mut self, func: FuncAddr, args: &[Value]) -> Result<Vec<Value>, Error> {
+        let ty = self.funcs[func].ty();
+        if args.len() != ty.0.len() || args.iter().zip(&ty.0).any(|(arg, ty)| !arg.fits(*ty)) {
+            return Err(Error::InvalidArguments(
+                Backtrace::capture(),
+                format!("expected {:?}, found {args:?}", ty.0),
+            ));
+        }
+        let height = self.stack.len();
+        self.stack.extend_from_slice(args);
+        match self.call(func, None) {
+            Ok(()) => Ok(self.stack.split_off(height)),
+            Err(trap) => {
+                self.stack.truncate(height);
+                Err(Error::Trap(Backtrace::capture(), trap))
+            }
+        }
+    }
+
+    pub fn invoke_export(
+        &mut self,
+        instance: InstanceAddr,
+        name: &str,
+        args: &[Value],
+    ) -> Result<Vec<Value>, Error> {
+        match self.export(instance, name) {
+            Some(ExternVal::Func(func)) => self.invoke(func, args),
+            _ => Err(Error::Link(
+                Backtrace::capture(),
+                format!("unknown function export {name}"),
+            )),
+        }
+    }
+
+    fn eval_const(&mut self, instance: InstanceAddr, expr: &Expr) -> Result<Value, Trap> {
+        let mut frame = Frame {
+            locals: Vec::new(),
+            instance,
+        };
+        let height = self.stack.len();
+        self.block(&expr.0, &mut frame)?;
+        let value = self
+            .stack
+            .pop()
+            .expect("constant expression without a value");
+        self.stack.truncate(height);
+        Ok(value)
+    }
+
+    /// Calls a function with its arguments on top of the stack. Tail calls
+    /// replace the finished frame, so they do not count towards the depth.
+    fn call(&mut self, mut addr: FuncAddr, mut caller: Option<InstanceAddr>) -> Result<(), Trap> {
+        loop {
+            match self.funcs[addr].clone() {
+                FuncInst::Host { ty, func } => {
+                    let args = self.stack.split_off(self.stack.len() - ty.0.len());
+                    let mut caller = Caller {
+                        store: self,
+                        instance: caller,
+                    };
+                    let results = func(&mut caller, &args)?;
+                    self.stack.extend(results);
+                    return Ok(());
+                }
+                FuncInst::Wasm { ty, instance, code } => {
+                    let declared: u64 = code.0.iter().map(|Locals(n, _)| *n as u64).sum();
+                    if ty.0.len() as u64 + declared > self.config.max_locals as u64 {
+                        return Err(Trap::ResourceLimit("locals"));
+                    }
+                    let mut locals = self.stack.split_off(self.stack.len() - ty.0.len());
+                    for Locals(n, ty) in &code.0 {
+                        locals.extend(std::iter::repeat_n(Value::zero(*ty), *n as usize));
+                    }
+                    let mut frame = Frame { locals, instance };
+                    let height = self.stack.len();
+                    match self.block(&code.1 .0, &mut frame)? {
+                        Flow::Delegate(_, exn) => return Err(Trap::Exception(exn)),
+                        Flow::TailCall(callee) => {
+                            self.unwind(height, self.funcs[callee].ty().0.len());
+                            addr = callee;
+                            caller = Some(instance);
+                        }
+                        _ => {
+                            self.unwind(height, ty.1.len());
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drops the values between `height` and the top `arity` values.
+    fn unwind(&mut self, height: usize, arity: usize) {
+        let top = self.stack.len() - arity;
+        self.stack.drain(height..top);
+    }
+
+    fn block_arity(&self, frame: &Frame, bt: &BlockType) -> (usize, usize) {
+        match bt {
+            BlockType::Empty => (0, 0),
+            BlockType::ValType(_) => (0, 1),
+            BlockType::X(idx) => {
+                let ty = self.instances[frame.instance]
+                    .func_type(*idx as u32)
+                    .unwrap();
+                (ty.0.len(), ty.1.len())
+            }
+        }
+    }
+
+    /// Runs a function body or the body of a block. Each nests on the host's
+    /// stack and counts towards `max_call_depth`.
+    fn block(&mut self, instrs: &[Instr], frame: &mut Frame) -> Result<Flow, Trap> {
+        if self.depth >= self.config.max_call_depth {
+            return Err(Trap::CallStackExhausted);
+        }
+        self.depth += 1;
+        let flow = self.instrs(instrs, frame);
+        self.depth -= 1;
+        flow
+    }
+
+    fn instrs(&mut self, instrs: &[Instr], frame: &mut Frame) -> Result<Flow, Trap> {
+        for instr in instrs {
+            if self.fuel.is_some() {
+                self.consume(self.config.costs.cost(instr))?;
+            }
+            match self.instr(instr, frame)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn consume(&mut self, cost: u64) -> Result<(), Trap> {
+        if let Some(fuel) = self.fuel {
+            self.fuel = Some(fuel.checked_sub(cost).ok_or(Trap::OutOfFuel)?);
+        }
+        Ok(())
+    }
+
+    /// Charges the elements written by a bulk memory or table instruction.
+    fn consume_elements(&mut self, n: u64) -> Result<(), Trap> {
+        self.consume(n.saturating_mul(self.config.costs.per_element))
+    }
+
+    /// Runs a block body and resolves branches targeting it.
+    fn label(
+        &mut self,
+        bt: &BlockType,
+        body: &[Instr],
+        frame: &mut Frame,
+        is_loop: bool,
+    ) -> Result<Flow, Trap> {
+        let (params, results) = self.block_arity(frame, bt);
+        loop {
+            let height = self.stack.len() - params;
+            return Ok(match self.block(body, frame)? {
+                Flow::Branch(0) if is_loop => {
+                    self.unwind(height, params);
+                    continue;
+                }
+                Flow::Branch(0) => {
+                    self.unwind(height, results);
+                    Flow::Next
+                }
+                Flow::Branch(depth) => Flow::Branch(depth - 1),
+                Flow::Rethrow(depth) => Flow::Rethrow(depth - 1),
+                Flow::Delegate(0, exn) => return Err(Trap::Exception(exn)),
+                Flow::Delegate(depth, exn) => Flow::Delegate(depth - 1, exn),
+                flow => flow,
+            });
+        }
+    }
+
+    /// Runs a legacy `try` block. When an exception escapes the body, the
+    /// matching handler runs in its place with the try block as its label.
+    fn try_catch(
+        &mut self,
+        bt: &BlockType,
+        body: &[Instr],
+        catches: &[(TagIdx, Vec<Instr>)],
+        catch_all: Option<&Vec<Instr>>,
+        frame: &mut Frame,
+    ) -> Result<Flow, Trap> {
+        let (params, results) = self.block_arity(frame, bt);
+        let height = self.stack.len() - params;
+        let exn = match self.label(bt, body, frame, false) {
+            Err(Trap::Exception(exn)) => exn,
+            flow => return flow,
+        };
+        let tags = &self.instances[frame.instance].tags;
+        let caught = catches
+            .iter()
+            .find(|(tag, _)| tags[*tag as usize] == self.exns[exn].tag);
+        let handler = match (caught, catch_all) {
+            (Some((_, handler)), _) => {
+                self.stack.truncate(height);
+                self.stack.extend(self.exns[exn].fields.clone());
+                handler
+            }
+            (None, Some(handler)) => {
+                self.stack.truncate(height);
+                handler
+            }
+            (None, None) => return Err(Trap::Exception(exn)),
+        };
+        Ok(match self.block(handler, frame)? {
+            Flow::Branch(0) => {
+                self.unwind(height, results);
+                Flow::Next
+            }
+            Flow::Branch(depth) => Flow::Branch(depth - 1),
+            Flow::Rethrow(0) => return Err(Trap::Exception(exn)),
+            Flow::Rethrow(depth) => Flow::Rethrow(depth - 1),
+            Flow::Delegate(0, exn) => return Err(Trap::Exception(exn)),
+            Flow::Delegate(depth, exn) => Flow::Delegate(depth - 1, exn),
+            flow => flow,
+        })
+    }
+
+    /// Runs a `try_table` block, branching to the label of the first matching
+    /// catch clause when an exception escapes the body.
+    fn try_table(
+        &mut self,
+        bt: &BlockType,
+        catches: &[Catch],
+        body: &[Instr],
+        frame: &mut Frame,
+    ) -> Result<Flow, Trap> {
+        let (params, _) = self.block_arity(frame, bt);
+        let height = self.stack.len() - params;
+        let exn = match self.label(bt, body, frame, false) {
+            Err(Trap::Exception(exn)) => exn,
+            flow => return flow,
+        };
+        let tags = &self.instances[frame.instance].tags;
+        let Some(catch) = catches.iter().find(|catch| match catch.tag() {
+            Some(tag) => tags[tag as usize] == self.exns[exn].tag,
+            None => true,
+        }) else {
+            return Err(Trap::Exception(exn));
+        };
+        self.stack.truncate(height);
+        if catch.tag().is_some() {
+            self.stack.extend(self.exns[exn].fields.clone());
+        }
+        if catch.is_ref() {
+            self.push(Value::ExnRef(Some(exn)));
+        }
+        Ok(Flow::Branch(catch.label()))
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("value stack underflow")
+    }
+
+    fn push(&mut self, value: impl Into<Value>) {
+        self.stack.push(value.into());
+    }
+
+    fn pop_i32(&mut self) -> i32 {
+        match self.pop() {
+            Value::I32(value) => value,
+            value => panic!("expected i32, found {value:?}"),
+        }
+    }
+
+    fn pop_i64(&mut self) -> i64 {
+        match self.pop() {
+            Value::I64(value) => value,
+            value => panic!("expected i64, found {value:?}"),
+        }
+    }
+
+    fn pop_index(&mut self) -> u64 {
+        index(self.pop())
+    }
+
+    fn push_index(&mut self, is64: bool, value: i64) {
+        match is64 {
+            true => self.push(value),
+            false => self.push(value as i32),
+        }
+    }
+
+    fn pop_f32(&mut self) -> f32 {
+        match self.pop() {
+            Value::F32(value) => value,
+            value => panic!("expected f32, found {value:?}"),
+        }
+    }
+
+    fn pop_f64(&mut self) -> f64 {
+        match self.pop() {
+            Value::F64(value) => value,
+            value => panic!("expected f64, found {value:?}"),
+        }
+    }
+
+    fn pop_v128(&mut self) -> u128 {
+        match self.pop() {
+            Value::V128(value) => value,
+            value => panic!("expected v128, found {value:?}"),
+        }
+    }
+
+    fn table_addr(&self, frame: &Frame, idx: TableIdx) -> TableAddr {
+        self.instances[frame.instance].tables[idx as usize]
+    }
+
+    fn mem_addr(&self, frame: &Frame, idx: MemIdx) -> MemAddr {
+        self.instances[frame.instance].mems[idx as usize]
+    }
+
+    /// Checks that `offset..offset + len` lies within `size`.
+    fn bounds(offset: u64, len: u64, size: usize, trap: Trap) -> Result<usize, Trap> {
+        match offset.checked_add(len) {
+            Some(end) if end <= size as u64 => Ok(offset as usize),
+            _ => Err(trap),
+        }
+    }
+
+    fn effective(
+        &mut self,
+        frame: &Frame,
+        memarg: &MemArg,
+        len: usize,
+    ) -> Result<(MemAddr, usize), Trap> {
+        let addr = self.mem_addr(frame, memarg.2);
+        let base = self.pop_index();
+        let size = self.mems[addr].data.len();
+        let ea = Self::bounds(
+            base.checked_add(memarg.1).ok_or(Trap::MemoryOutOfBounds)?,
+            len as u64,
+            size,
+            Trap::MemoryOutOfBounds,
+        )?;
+        Ok((addr, ea))
+    }
+
+    fn load<const N: usize>(&mut self, frame: &Frame, memarg: &MemArg) -> Result<[u8; N], Trap> {
+        let (addr, ea) = self.effective(frame, memarg, N)?;
+        Ok(self.mems[addr].data[ea..ea + N].try_into().unwrap())
+    }
+
+    fn store<const N: usize>(
+        &mut self,
+        frame: &Frame,
+        memarg: &MemArg,
+        bytes: [u8; N],
+    ) -> Result<(), Trap> {
+        let (addr, ea) = self.effective(frame, memarg, N)?;
+        self.mems[addr].data[ea..ea + N].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn table_init(
+        &mut self,
+        instance: InstanceAddr,
+        table: TableIdx,
+        elem: ElemIdx,
+        dst: u64,
+        src: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        let table = self.instances[instance].tables[table as usize];
+        let elem = self.instances[instance].elems[elem as usize];
+        let src = Self::bounds(
+            src as u64,
+            len as u64,
+            self.elems[elem].len(),
+            Trap::TableOutOfBounds,
+        )?;
+        let dst = Self::bounds(
+            dst,
+            len as u64,
+            self.tables[table].elems.len(),
+            Trap::TableOutOfBounds,
+        )?;
+        let values = &self.elems[elem][src..src + len as usize];
+        self.tables[table].elems[dst..dst + len as usize].copy_from_slice(values);
+        Ok(())
+    }
+
+    fn memory_init(
+        &mut self,
+        instance: InstanceAddr,
+        mem: MemIdx,
+        data: DataIdx,
+        dst: u64,
+        src: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        let mem = self.instances[instance].mems[mem as usize];
+        let data = self.instances[instance].datas[data as usize];
+        let src = Self::bounds(
+            src as u64,
+            len as u64,
+            self.datas[data].len(),
+            Trap::MemoryOutOfBounds,
+        )?;
+        let dst = Self::bounds(
+            dst,
+            len as u64,
+            self.mems[mem].data.len(),
+            Trap::MemoryOutOfBounds,
+        )?;
+        let bytes = &self.datas[data][src..src + len as usize];
+        self.mems[mem].data[dst..dst + len as usize].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Resolves the callee of `call_indirect` from the index on the stack.
+    fn indirect(&mut self, frame: &Frame, ty: TypeIdx, table: TableIdx) -> Result<FuncAddr, Trap> {
+        let table = self.table_addr(frame, table);
+        let idx = self.pop_index() as usize;
+        let func = match self.tables[table].elems.get(idx) {
+            None => return Err(Trap::UndefinedElement),
+            Some(Value::FuncRef(Some(func))) => *func,
+            Some(_) => return Err(Trap::UninitializedElement),
+        };
+        if Some(self.funcs[func].ty()) != self.instances[frame.instance].func_type(ty) {
+            return Err(Trap::IndirectCallTypeMismatch);
+        }
+        Ok(func)
+    }
+
+    /// Pops the callee of `call_ref`, whose type the validator already checked.
+    fn func_ref(&mut self) -> Result<FuncAddr, Trap> {
+        match self.pop() {
+            Value::FuncRef(Some(func)) => Ok(func),
+            _ => Err(Trap::NullFunctionReference),
+        }
+    }
+
+    fn instr(&mut self, instr: &Instr, frame: &mut Frame) -> Result<Flow, Trap> {
+        match instr {
+            Instr::UnReachable => return Err(Trap::Unreachable),
+            Instr::Nop => {}
+            Instr::Block(bt, body) => return self.label(bt, body, frame, false),
+            Instr::Loop(bt, body) => return self.label(bt, body, frame, true),
+            Instr::If(bt, body) => {
+                if self.pop_i32() != 0 {
+                    return self.label(bt, body, frame, false);
+                }
+            }
+            Instr::IfElse(bt, then, other) => {
+                let body = if self.pop_i32() != 0 { then } else { other };
+                return self.label(bt, body, frame, false);
+            }
+            Instr::Try(bt, body, catches, catch_all) => {
+                return self.try_catch(bt, body, catches, catch_all.as_ref(), frame)
+            }
+            Instr::TryDelegate(bt, body, label) => {
+                return match self.label(bt, body, frame, false) {
+                    Err(Trap::Exception(exn)) => Ok(Flow::Delegate(*label, exn)),
+                    flow => flow,
+                }
+            }
+            Instr::TryTable(bt, catches, body) => return self.try_table(bt, catches, body, frame),
+            Instr::Throw(tag) => {
+                let tag = self.instances[frame.instance].tags[*tag as usize];
+                let arity = self.tags[tag].ty.0.len();
+                let fields = self.stack.split_off(self.stack.len() - arity);
+                self.exns.push(ExnInst { tag, fields });
+                return Err(Trap::Exception(self.exns.len() - 1));
+            }
+            Instr::Rethrow(label) => return Ok(Flow::Rethrow(*label)),
+            Instr::ThrowRef => {
+                return match self.pop() {
+                    Value::ExnRef(Some(exn)) => Err(Trap::Exception(exn)),
+                    _ => Err(Trap::NullExceptionReference),
+                }
+            }
+            Instr::Br(label) => return Ok(Flow::Branch(*label)),
+            Instr::BrIf(label) => {
+                if self.pop_i32() != 0 {
+                    return Ok(Flow::Branch(*label));
+                }
+            }
+            Instr::BrOnNull(label) => {
+                let value = self.pop();
+                if value.is_null() {
+                    return Ok(Flow::Branch(*label));
+                }
+                self.push(value);
+            }
+            Instr::BrOnNonNull(label) => {
+                let value = self.pop();
+                if !value.is_null() {
+                    self.push(value);
+                    return Ok(Flow::Branch(*label));
+                }
+            }
+            Instr::BrTable(labels, default) => {
+                let idx = self.pop_i32() as u32 as usize;
+                return Ok(Flow::Branch(*labels.get(idx).unwrap_or(default)));
+            }
+            Instr::Return => return Ok(Flow::Return),
+            Instr::Call(idx) => {
+                let func = self.instances[frame.instance].funcs[*idx as usize];
+                self.call(func, Some(frame.instance))?;
+            }
+            Instr::CallIndirect(ty, table) => {
+                let func = self.indirect(frame, *ty, *table)?;
+                self.call(func, Some(frame.instance))?;
+            }
+            Instr::ReturnCall(idx) => {
+                let func = self.instances[frame.instance].funcs[*idx as usize];
+                return Ok(Flow::TailCall(func));
+            }
+            Instr::ReturnCallIndirect(ty, table) => {
+                return Ok(Flow::TailCall(self.indirect(frame, *ty, *table)?));
+            }
+            Instr::CallRef(_) => {
+                let func = self.func_ref()?;
+                self.call(func, Some(frame.instance))?;
+            }
+            Instr::ReturnCallRef(_) => return Ok(Flow::TailCall(self.func_ref()?)),
+            instr => self.plain(instr, frame)?,
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Everything but control flow and calls, kept out of `instr` so the
+    /// recursion through nested blocks and calls uses little stack.
+    #[inline(never)]
+    fn plain(&mut self, instr: &Instr, frame: &mut Frame) -> Result<(), Trap> {
+        macro_rules! un {
+            ($pop:ident, |$a:ident| $e:expr) => {{
+                let $a = self.$pop();
+                let value = $e;
+                self.push(value);
+            }};
+        }
+        macro_rules! bin {
+            ($pop:ident, |$a:ident, $b:ident| $e:expr) => {{
+                let $b = self.$pop();
+                let $a = self.$pop();
+                let value = $e;
+                self.push(value);
+            }};
+        }
+        macro_rules! load {
+            ($memarg:expr, $n:literal, |$bytes:ident| $e:expr) => {{
+                let $bytes = self.load::<$n>(frame, $memarg)?;
+                let value = $e;
+                self.push(value);
+            }};
+        }
+        macro_rules! store {
+            ($memarg:expr, $pop:ident, |$a:ident| $e:expr) => {{
+                let $a = self.$pop();
+                self.store(frame, $memarg, $e)?;
+            }};
+        }
+
+        match instr {
+            Instr::RefNull(heap) => self.push(Value::zero(ValType::RefType(RefType(true, *heap)))),
+            Instr::RefIsNull => {
+                let value = self.pop();
+                self.push(value.is_null());
+            }
+            Instr::RefAsNonNull => {
+                if self.stack.last().is_some_and(Value::is_null) {
+                    return Err(Trap::NullReference);
+                }
+            }
+            Instr::RefFunc(idx) => {
+                let func = self.instances[frame.instance].funcs[*idx as usize];
+                self.push(Value::FuncRef(Some(func)));
+            }
+            Instr::Drop => {
+                self.pop();
+            }
+            Instr::Select | Instr::SelectType(_) => {
+                let cond = self.pop_i32();
+                let other = self.pop();
+                let value = self.pop();
+                self.push(if cond != 0 { value } else { other });
+            }
+            Instr::LocalGet(idx) => self.push(frame.locals[*idx as usize]),
+            Instr::LocalSet(idx) => frame.locals[*idx as usize] = self.pop(),
+            Instr::LocalTee(idx) => {
+                frame.locals[*idx as usize] = *self.stack.last().expect("value stack underflow")
+            }
+            Instr::GlobalGet(idx) => {
+                let global = self.instances[frame.instance].globals[*idx as usize];
+                self.push(self.globals[global].value);
+            }
+            Instr::GlobalSet(idx) => {
+                let global = self.instances[frame.instance].globals[*idx as usize];
+                self.globals[global].value = self.pop();
+            }
+            Instr::TableGet(idx) => {
+                let table = self.table_addr(frame, *idx);
+                let i = self.pop_index() as usize;
+                let value = *self.tables[table]
+                    .elems
+                    .get(i)
+                    .ok_or(Trap::TableOutOfBounds)?;
+                self.push(value);
+            }
+            Instr::TableSet(idx) => {
+                let table = self.table_addr(frame, *idx);
+                let value = self.pop();
+                let i = self.pop_index() as usize;
+                *self.tables[table]
+                    .elems
+                    .get_mut(i)
+                    .ok_or(Trap::TableOutOfBounds)? = value;
+            }
+            Instr::TableSize(idx) => {
+                let table = self.table_addr(frame, *idx);
+                let table = &self.tables[table];
+                self.push_index(table.ty.1 .3, table.elems.len() as i64);
+            }
+            Instr::TableGrow(idx) => {
+                let table = self.table_addr(frame, *idx);
+                let n = self.pop_index();
+                let init = self.pop();
+                let limit = self.config.max_table_elements;
+                let table = &mut self.tables[table];
+                let is64 = table.ty.1 .3;
+                let old = table.elems.len() as u64;
+                let max = match is64 {
+                    true => table.ty.1 .1.unwrap_or(u64::MAX),
+                    false => table.ty.1 .1.unwrap_or(u32::MAX as u64),
+                }
+                .min(limit);
+                match old.checked_add(n) {
+                    Some(new) if new <= max => {
+                        table.elems.resize(new as usize, init);
+                        self.push_index(is64, old as i64);
+                    }
+                    _ => self.push_index(is64, -1),
+                }
+            }
+            Instr::TableFill(idx) => {
+                let table = self.table_addr(frame, *idx);
+                let n = self.pop_index();
+                let value = self.pop();
+                let i = self.pop_index();
+                let len = self.tables[table].elems.len();
+                let i = Self::bounds(i, n, len, Trap::TableOutOfBounds)?;
+                self.consume_elements(n)?;
+                self.tables[table].elems[i..i + n as usize].fill(value);
+            }
+            Instr::TableCopy(dst, src) => {
+                let dst = self.table_addr(frame, *dst);
+                let src = self.table_addr(frame, *src);
+                let n = self.pop_index();
+                let s = self.pop_index();
+                let d = self.pop_index();
+                let s = Self::bounds(s, n, self.tables[src].elems.len(), Trap::TableOutOfBounds)?;
+                let d = Self::bounds(d, n, self.tables[dst].elems.len(), Trap::TableOutOfBounds)?;
+                self.consume_elements(n)?;
+                let n = n as usize;
+                if dst == src {
+                    self.tables[dst].elems.copy_within(s..s + n, d);
+                } else {
+                    let values = self.tables[src].elems[s..s + n].to_vec();
+                    self.tables[dst].elems[d..d + n].copy_from_slice(&values);
+                }
+            }
+            Instr::TableInit(elem, table) => {
+                let n = self.pop_i32() as u32;
+                let s = self.pop_i32() as u32;
+                let d = self.pop_index();
+                self.table_init(frame.instance, *table, *elem, d, s, n)?;
+            }
+            Instr::ElemDrop(idx) => {
+                let elem = self.instances[frame.instance].elems[*idx as usize];
+                self.elems[elem].clear();
+            }
+
+            Instr::I32Load(m) => load!(m, 4, |b| i32::from_le_bytes(b)),
+            Instr::I64Load(m) => load!(m, 8, |b| i64::from_le_bytes(b)),
+            Instr::F32Load(m) => load!(m, 4, |b| f32::from_le_bytes(b)),
+            Instr::F64Load(m) => load!(m, 8, |b| f64::from_le_bytes(b)),
+            Instr::I32load8S(m) => load!(m, 1, |b| b[0] as i8 as i32),
+            Instr::I32Load8_u(m) => load!(m, 1, |b| b[0] as i32),
+            Instr::I32Load16_s(m) => load!(m, 2, |b| i16::from_le_bytes(b) as i32),
+            Instr::I32Load16_u(m) => load!(m, 2, |b| u16::from_le_bytes(b) as i32),
+            Instr::I64Load8_s(m) => load!(m, 1, |b| b[0] as i8 as i64),
+            Instr::I64Load8_u(m) => load!(m, 1, |b| b[0] as i64),
+            Instr::I64Load16_s(m) => load!(m, 2, |b| i16::from_le_bytes(b) as i64),
+            Instr::I64Load16_u(m) => load!(m, 2, |b| u16::from_le_bytes(b) as i64),
+            Instr::I64Load32_s(m) => load!(m, 4, |b| i32::from_le_bytes(b) as i64),
+            Instr::I64Load32_u(m) => load!(m, 4, |b| u32::from_le_bytes(b) as i64),
+            Instr::I32Store(m) => store!(m, pop_i32, |a| a.to_le_bytes()),
+            Instr::I64Store(m) => store!(m, pop_i64, |a| a.to_le_bytes()),
+            Instr::F32Store(m) => store!(m, pop_f32, |a| a.to_le_bytes()),
+            Instr::F64Store(m) => store!(m, pop_f64, |a| a.to_le_bytes()),
+            Instr::I32Store8(m) => store!(m, pop_i32, |a| [a as u8]),
+            Instr::I32Store16(m) => store!(m, pop_i32, |a| (a as u16).to_le_bytes()),
+            Instr::I64Store8(m) => store!(m, pop_i64, |a| [a as u8]),
+            Instr::I64Store16(m) => store!(m, pop_i64, |a| (a as u16).to_le_bytes()),
+            Instr::I64Store32(m) => store!(m, pop_i64, |a| (a as u32).to_le_bytes()),
+            Instr::MemorySize(idx) => {
+                let mem = &self.mems[self.mem_addr(frame, *idx)];
+                self.push_index(mem.ty.3, (mem.data.len() / PAGE_SIZE) as i64);
+            }
+            Instr::MemoryGrow(idx) => {
+                let mem = self.mem_addr(frame, *idx);
+                let n = self.pop_index();
+                let limit = self.config.max_memory_pages as u64;
+                let mem = &mut self.mems[mem];
+                let is64 = mem.ty.3;
+                let old = (mem.data.len() / PAGE_SIZE) as u64;
+                let pages = if is64 { MAX_PAGES64 } else { MAX_PAGES };
+                let max = mem.ty.1.unwrap_or(pages).min(limit);
+                match old.checked_add(n) {
+                    Some(new) if new <= max => {
+                        mem.data.resize(new as usize * PAGE_SIZE, 0);
+                        self.push_index(is64, old as i64);
+                    }
+                    _ => self.push_index(is64, -1),
+                }
+            }
+            Instr::MemoryInit(idx, mem) => {
+                let n = self.pop_i32() as u32;
+                let s = self.pop_i32() as u32;
+                let d = self.pop_index();
+                self.memory_init(frame.instance, *mem, *idx, d, s, n)?;
+            }
+            Instr::DataDrop(idx) => {
+                let data = self.instances[frame.instance].datas[*idx as usize];
+                self.datas[data].clear();
+            }
+            Instr::MemoryCopy(dst, src) => {
+                let dst = self.mem_addr(frame, *dst);
+                let src = self.mem_addr(frame, *src);
+                let n = self.pop_index();
+                let s = self.pop_index();
+                let d = self.pop_index();
+                let s = Self::bounds(s, n, self.mems[src].data.len(), Trap::MemoryOutOfBounds)?;
+                let d = Self::bounds(d, n, self.mems[dst].data.len(), Trap::MemoryOutOfBounds)?;
+                self.consume_elements(n)?;
+                let n = n as usize;
+                if dst == src {
+                    self.mems[dst].data.copy_within(s..s + n, d);
+                } else {
+                    let bytes = self.mems[src].data[s..s + n].to_vec();
+                    self.mems[dst].data[d..d + n].copy_from_slice(&bytes);
+                }
+            }
+            Instr::MemoryFill(idx) => {
+                let mem = self.mem_addr(frame, *idx);
+                let n = self.pop_index();
+                let value = self.pop_i32() as u8;
+                let d = self.pop_index();
+                let d = Self::bounds(d, n, self.mems[mem].data.len(), Trap::MemoryOutOfBounds)?;
+                self.consume_elements(n)?;
+                self.mems[mem].data[d..d + n as usize].fill(value);
+            }
+
+            Instr::I32Const(value) => self.push(*value),
+            Instr::I64Const(value) => self.push(*value),
+            Instr::F32Const(value) => self.push(*value),
+            Instr::F64Const(value) => self.push(*value),
+
+            Instr::I32Eqz => un!(pop_i32, |a| a == 0),
+            Instr::I32Eq => bin!(pop_i32, |a, b| a == b),
+            Instr::I32Ne => bin!(pop_i32, |a, b| a != b),
+            Instr::I32Lts => bin!(pop_i32, |a, b| a < b),
+            Instr::I32Ltu => bin!(pop_i32, |a, b| (a as u32) < (b as u32)),
+            Instr::I32Gts => bin!(pop_i32, |a, b| a > b),
+            Instr::I32Gtu => bin!(pop_i32, |a, b| (a as u32) > (b as u32)),
+            Instr::I32Les => bin!(pop_i32, |a, b| a <= b),
+            Instr::I32Leu => bin!(pop_i32, |a, b| (a as u32) <= (b as u32)),
+            Instr::I32Ges => bin!(pop_i32, |a, b| a >= b),
+            Instr::I32Geu => bin!(pop_i32, |a, b| (a as u32) >= (b as u32)),
+            Instr::I64Eqz => un!(pop_i64, |a| a == 0),
+            Instr::I64Eq => bin!(pop_i64, |a, b| a == b),
+            Instr::I64Ne => bin!(pop_i64, |a, b| a != b),
+            Instr::I64Lts => bin!(pop_i64, |a, b| a < b),
+            Instr::I64Ltu => bin!(pop_i64, |a, b| (a as u64) < (b as u64)),
+            Instr::I64Gts => bin!(pop_i64, |a, b| a > b),
+            Instr::I64Gtu => bin!(pop_i64, |a, b| (a as u64) > (b as u64)),
+            Instr::I64Les => bin!(pop_i64, |a, b| a <= b),
+            Instr::I64Leu => bin!(pop_i64, |a, b| (a as u64) <= (b as u64)),
+            Instr::I64Ges => bin!(pop_i64, |a, b| a >= b),
+            Instr::I64Geu => bin!(pop_i64, |a, b| (a as u64) >= (b as u64)),
+            Instr::F32Eq => bin!(pop_f32, |a, b| a == b),
+            Instr::F32Ne => bin!(pop_f32, |a, b| a != b),
+            Instr::F32Lt => bin!(pop_f32, |a, b| a < b),
+            Instr::F32Gt => bin!(pop_f32, |a, b| a > b),
+            Instr::F32Le => bin!(pop_f32, |a, b| a <= b),
+            Instr::F32Ge => bin!(pop_f32, |a, b| a >= b),
+            Instr::F64Eq => bin!(pop_f64, |a, b| a == b),
+            Instr::F64Ne => bin!(pop_f64, |a, b| a != b),
+            Instr::F64Lt => bin!(pop_f64, |a, b| a < b),
+            Instr::F64Gt => bin!(pop_f64, |a, b| a > b),
+            Instr::F64Le => bin!(pop_f64, |a, b| a <= b),
+            Instr::F64Ge => bin!(pop_f64, |a, b| a >= b),
+
+            Instr::I32Clz => un!(pop_i32, |a| a.leading_zeros() as i32),
+            Instr::I32Ctz => un!(pop_i32, |a| a.trailing_zeros() as i32),
+            Instr::I32PopcCnt => un!(pop_i32, |a| a.count_ones() as i32),
+            Instr::I32Add => bin!(pop_i32, |a, b| a.wrapping_add(b)),
+            Instr::I32Sub => bin!(pop_i32, |a, b| a.wrapping_sub(b)),
+            Instr::I32Mul => bin!(pop_i32, |a, b| a.wrapping_mul(b)),
+            Instr::I32Divs => bin!(pop_i32, |a, b| match (a, b) {
+                (_, 0) => return Err(Trap::IntegerDivideByZero),
+                (i32::MIN, -1) => return Err(Trap::IntegerOverflow),
+                _ => a / b,
+            }),
+            Instr::I32Divu => bin!(pop_i32, |a, b| match b {
+                0 => return Err(Trap::IntegerDivideByZero),
+                _ => ((a as u32) / (b as u32)) as i32,
+            }),
+            Instr::I32RemS => bin!(pop_i32, |a, b| match b {
+                0 => return Err(Trap::IntegerDivideByZero),
+                _ => a.wrapping_rem(b),
+            }),
+            Instr::I32Remu => bin!(pop_i32, |a, b| match b {
+                0 => return Err(Trap::IntegerDivideByZero),
+                _ => ((a as u32) % (b as u32)) as i32,
+            }),
+            Instr::I32And => bin!(pop_i32, |a, b| a & b),
+            Instr::I32Or => bin!(pop_i32, |a, b| a | b),
+            Instr::I32Xor => bin!(pop_i32, |a, b| a ^ b),
+            Instr::I32Shl => bin!(pop_i32, |a, b| a.wrapping_shl(b as u32)),
+            Instr::I32Shrs => bin!(pop_i32, |a, b| a.wrapping_shr(b as u32)),
+            Instr::I32Sgru => bin!(pop_i32, |a, b| (a as u32).wrapping_shr(b as u32) as i32),
+            Instr::I32Rotl => bin!(pop_i32, |a, b| a.rotate_left(b as u32 % 32)),
+            Instr::I32Rotr => bin!(pop_i32, |a, b| a.rotate_right(b as u32 % 32)),
+            Instr::I64Clz => un!(pop_i64, |a| a.leading_zeros() as i64),
+            Instr::I64Ctz => un!(pop_i64, |a| a.trailing_zeros() as i64),
+            Instr::I64PopcCnt => un!(pop_i64, |a| a.count_ones() as i64),
+            Instr::I64Add => bin!(pop_i64, |a, b| a.wrapping_add(b)),
+            Instr::I64Sub => bin!(pop_i64, |a, b| a.wrapping_sub(b)),
+            Instr::I64Mul => bin!(pop_i64, |a, b| a.wrapping_mul(b)),
+            Instr::I64Divs => bin!(pop_i64, |a, b| match (a, b) {
+                (_, 0) => return Err(Trap::IntegerDivideByZero),
+                (i64::MIN, -1) => return Err(Trap::IntegerOverflow),
+                _ => a / b,
+            }),
+            Instr::I64Divu => bin!(pop_i64, |a, b| match b {
+                0 => return Err(Trap::IntegerDivideByZero),
+                _ => ((a as u64) / (b as u64)) as i64,
+            }),
+            Instr::I64RemS => bin!(pop_i64, |a, b| match b {
+                0 => return Err(Trap::IntegerDivideByZero),
+                _ => a.wrapping_rem(b),
+            }),
+            Instr::I64Remu => bin!(pop_i64, |a, b| match b {
+                0 => return Err(Trap::IntegerDivideByZero),
+                _ => ((a as u64) % (b as u64)) as i64,
+            }),
+            Instr::I64And => bin!(pop_i64, |a, b| a & b),
+            Instr::I64Or => bin!(pop_i64, |a, b| a | b),
+            Instr::I64Xor => bin!(pop_i64, |a, b| a ^ b),
+            Instr::I64Shl => bin!(pop_i64, |a, b| a.wrapping_shl(b as u32)),
+            Instr::I64Shrs => bin!(pop_i64, |a, b| a.wrapping_shr(b as u32)),
+            Instr::I64Sgru => bin!(pop_i64, |a, b| (a as u64).wrapping_shr(b as u32) as i64),
+            Instr::I64Rotl => bin!(pop_i64, |a, b| a.rotate_left((b % 64) as u32)),
+            Instr::I64Rotr => bin!(pop_i64, |a, b| a.rotate_right((b % 64) as u32)),
+
+            Instr::F32Abs => un!(pop_f32, |a| f32::from_bits(a.to_bits() & 0x7FFF_FFFF)),
+            Instr::F32Neg => un!(pop_f32, |a| f32::from_bits(a.to_bits() ^ 0x8000_0000)),
+            Instr::F32Ceil => un!(pop_f32, |a| a.ceil()),
+            Instr::F32Floor => un!(pop_f32, |a| a.floor()),
+            Instr::F32Trunc => un!(pop_f32, |a| a.trunc()),
+            Instr::F32Nearest => un!(pop_f32, |a| a.round_ties_even()),
+            Instr::F32Sqrt => un!(pop_f32, |a| a.sqrt()),
+            Instr::F32Add => bin!(pop_f32, |a, b| a + b),
+            Instr::F32Sub => bin!(pop_f32, |a, b| a - b),
+            Instr::F32Mul => bin!(pop_f32, |a, b| a * b),
+            Instr::F32Div => bin!(pop_f32, |a, b| a / b),
+            Instr::F32Min => bin!(pop_f32, |a, b| f32_min(a, b)),
+            Instr::F32Max => bin!(pop_f32, |a, b| f32_max(a, b)),
+            Instr::F32CopySig => bin!(pop_f32, |a, b| a.copysign(b)),
+            Instr::F64Abs => un!(pop_f64, |a| f64::from_bits(a.to_bits() & !(1 << 63))),
+            Instr::F64Neg => un!(pop_f64, |a| f64::from_bits(a.to_bits() ^ (1 << 63))),
+            Instr::F64Ceil => un!(pop_f64, |a| a.ceil()),
+            Instr::F64Floor => un!(pop_f64, |a| a.floor()),
+            Instr::F64Trunc => un!(pop_f64, |a| a.trunc()),
+            Instr::F64Nearest => un!(pop_f64, |a| a.round_ties_even()),
+            Instr::F64Sqrt => un!(pop_f64, |a| a.sqrt()),
+            Instr::F64Add => bin!(pop_f64, |a, b| a + b),
+            Instr::F64Sub => bin!(pop_f64, |a, b| a - b),
+            Instr::F64Mul => bin!(pop_f64, |a, b| a * b),
+            Instr::F64Div => bin!(pop_f64, |a, b| a / b),
+            Instr::F64Min => bin!(pop_f64, |a, b| f64_min(a, b)),
+            Instr::F64Max => bin!(pop_f64, |a, b| f64_max(a, b)),
+            Instr::F64CopySig => bin!(pop_f64, |a, b| a.copysign(b)),
+
+            Instr::I32WrapI64 => un!(pop_i64, |a| a as i32),
+            Instr::I32TruncF32S => un!(pop_f32, |a| trunc(a as f64, I32_MIN, I32_MAX)? as i32),
+            Instr::I32TruncF32U => un!(pop_f32, |a| trunc(a as f64, 0.0, U32_MAX)? as u32 as i32),
+            Instr::I32TruncF64S => un!(pop_f64, |a| trunc(a, I32_MIN, I32_MAX)? as i32),
+            Instr::I32TruncF64U => un!(pop_f64, |a| trunc(a, 0.0, U32_MAX)? as u32 as i32),
+            Instr::I64ExtendI32S => un!(pop_i32, |a| a as i64),
+            Instr::I64ExtendI32U => un!(pop_i32, |a| a as u32 as i64),
+            Instr::I64TruncF32S => un!(pop_f32, |a| trunc(a as f64, I64_MIN, I64_MAX)? as i64),
+            Instr::I64TruncF32U => un!(pop_f32, |a| trunc(a as f64, 0.0, U64_MAX)? as u64 as i64),
+            Instr::I64TruncF64S => un!(pop_f64, |a| trunc(a, I64_MIN, I64_MAX)? as i64),
+            Instr::I64TruncF64U => un!(pop_f64, |a| trunc(a, 0.0, U64_MAX)? as u64 as i64),
+            Instr::F32ConvertI32S => un!(pop_i32, |a| a as f32),
+            Instr::F32ConvertI32U => un!(pop_i32, |a| a as u32 as f32),
+            Instr::F32ConvertI64S => un!(pop_i64, |a| a as f32),
+            Instr::F32ConvertI64U => un!(pop_i64, |a| a as u64 as f32),
+            Instr::F32DenoteF64 => un!(pop_f64, |a| a as f32),
+            Instr::F64ConvertI32S => un!(pop_i32, |a| a as f64),
+            Instr::F64ConvertI32U => un!(pop_i32, |a| a as u32 as f64),
+            Instr::F64ConvertI64S => un!(pop_i64, |a| a as f64),
+            Instr::F64ConvertI64U => un!(pop_i64, |a| a as u64 as f64),
+            Instr::F64PromoteF32 => un!(pop_f32, |a| a as f64),
+            Instr::I32ReinterpetF32 => un!(pop_f32, |a| a.to_bits() as i32),
+            Instr::I64ReinterpetF64 => un!(pop_f64, |a| a.to_bits() as i64),
+            Instr::F32ReinterpetI32 => un!(pop_i32, |a| f32::from_bits(a as u32)),
+            Instr::F64RetineroetI64 => un!(pop_i64, |a| f64::from_bits(a as u64)),
+            Instr::I32Extend8S => un!(pop_i32, |a| a as i8 as i32),
+            Instr::I32Extend16S => un!(pop_i32, |a| a as i16 as i32),
+            Instr::I64Extend8S => un!(pop_i64, |a| a as i8 as i64),
+            Instr::I64Extend16S => un!(pop_i64, |a| a as i16 as i64),
+            Instr::I64Extend32S => un!(pop_i64, |a| a as i32 as i64),
+            Instr::I32TruncSatF32S => un!(pop_f32, |a| a as i32),
+            Instr::I32TruncSatF32U => un!(pop_f32, |a| a as u32 as i32),
+            Instr::I32TruncSatF64S => un!(pop_f64, |a| a as i32),
+            Instr::I32TruncSatF64U => un!(pop_f64, |a| a as u32 as i32),
+            Instr::I64TruncSatF32S => un!(pop_f32, |a| a as i64),
+            Instr::I64TruncSatF32U => un!(pop_f32, |a| a as u64 as i64),
+            Instr::I64TructSatF64S => un!(pop_f64, |a| a as i64),
+            Instr::I64TructSatF64U => un!(pop_f64, |a| a as u64 as i64),
+
+            instr if instr.is_atomic() => self.atomic(instr, frame)?,
+            instr => self.simd(instr, frame)?,
+        }
+        if self.config.canonicalize_nans {
+            match (nan_type(instr), self.stack.last_mut()) {
+                (Some(_), Some(Value::F32(value))) => *value = canonical_f32(*value),
+                (Some(_), Some(Value::F64(value))) => *value = canonical_f64(*value),
+                (Some(NumType::F32), Some(Value::V128(value))) => {
+                    *value = pack(unpack::<f32, 4>(*value).map(canonical_f32))
+                }
+                (Some(NumType::F64), Some(Value::V128(value))) => {
+                    *value = pack(unpack::<f64, 2>(*value).map(canonical_f64))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Atomic instructions of the threads proposal. With a single thread of
+    /// execution they behave like their plain counterparts, `wait` can only
+    /// time out and `notify` never wakes anybody.
+    fn atomic(&mut self, instr: &Instr, frame: &Frame) -> Result<(), Trap> {
+        let Some(memarg) = instr.memarg() else {
+            // atomic.fence
+            return Ok(());
+        };
+        let bits = |value: Value| match value {
+            Value::I32(value) => value as u32 as u64,
+            Value::I64(value) => value as u64,
+            value => panic!("expected integer, found {value:?}"),
+        };
+        let width = 1 << natural_align(instr);
+        let mask = u64::MAX >> (64 - 8 * width);
+        let name = instr.name();
+        let op = name.rsplit('.').next().unwrap().trim_end_matches("_u");
+
+        let (operand, expected) = match op {
+            "notify" => (self.pop_i32() as u64, 0),
+            "wait32" | "wait64" => {
+                self.pop_i64();
+                (0, bits(self.pop()))
+            }
+            "cmpxchg" => {
+                let replacement = bits(self.pop());
+                (replacement, bits(self.pop()) & mask)
+            }
+            _ if op.starts_with("load") => (0, 0),
+            _ => (bits(self.pop()), 0),
+        };
+        let (addr, ea) = self.effective(frame, memarg, width)?;
+        if ea % width != 0 {
+            return Err(Trap::UnalignedAtomic);
+        }
+        let mem = &mut self.mems[addr];
+        let mut bytes = [0; 8];
+        bytes[..width].copy_from_slice(&mem.data[ea..ea + width]);
+        let old = u64::from_le_bytes(bytes);
+
+        let new = match op {
+            "notify" => {
+                self.push(0);
+                return Ok(());
+            }
+            "wait32" | "wait64" if !mem.ty.2 => return Err(Trap::ExpectedSharedMemory),
+            "wait32" | "wait64" => {
+                // Nothing can notify a single thread, so equal values time out.
+                self.push(if old == expected { 2 } else { 1 });
+                return Ok(());
+            }
+            "add" => old.wrapping_add(operand),
+            "sub" => old.wrapping_sub(operand),
+            "and" => old & operand,
+            "or" => old | operand,
+            "xor" => old ^ operand,
+            "xchg" => operand,
+            "cmpxchg" if old == expected => operand,
+            "cmpxchg" => old,
+            _ if op.starts_with("store") => operand,
+            _ => old,
+        };
+        mem.data[ea..ea + width].copy_from_slice(&new.to_le_bytes()[..width]);
+        if !op.starts_with("store") {
+            match name.starts_with("i64") {
+                true => self.push(old as i64),
+                false => self.push(old as u32 as i32),
+            }
+        }
+        Ok(())
+    }
+
+    fn simd(&mut self, instr: &Instr, frame: &mut Frame) -> Result<(), Trap> {
+        fn lanes<const N: usize, const L: usize>(value: u128) -> [[u8; N]; L] {
+            let bytes = value.to_le_bytes();
+            std::array::from_fn(|i| bytes[i * N..i * N + N].try_into().unwrap())
+        }
+        fn join<const N: usize, const L: usize>(lanes: [[u8; N]; L]) -> u128 {
+            u128::from_le_bytes(lanes.concat().try_into().unwrap())
+        }
+        fn splat<const N: usize>(bytes: [u8; N]) -> u128 {
+            u128::from_le_bytes(bytes.repeat(16 / N).try_into().unwrap())
+        }
+
+        macro_rules! extend {
+            ($m:expr, $from:ty, $to:ty) => {{
+                const N: usize = std::mem::size_of::<$from>();
+                let bytes = self.load::<8>(frame, $m)?;
+                let mut out = Vec::new();
+                for lane in bytes.chunks(N) {
+                    let lane = <$from>::from_le_bytes(lane.try_into().unwrap()) as $to;
+                    out.extend(lane.to_le_bytes());
+                }
+                self.push(u128::from_le_bytes(out.try_into().unwrap()));
+            }};
+        }
+        macro_rules! load_lane {
+            ($m:expr, $lane:expr, $n:literal) => {{
+                let value = self.pop_v128();
+                let bytes = self.load::<$n>(frame, $m)?;
+                let mut lanes = lanes::<$n, { 16 / $n }>(value);
+                lanes[*$lane as usize] = bytes;
+                self.push(join(lanes));
+            }};
+        }
+        macro_rules! store_lane {
+            ($m:expr, $lane:expr, $n:literal) => {{
+                let value = self.pop_v128();
+                self.store(frame, $m, lanes::<$n, { 16 / $n }>(value)[*$lane as usize])?;
+            }};
+        }
+        macro_rules! extract {
+            ($lane:expr, $n:literal, |$bytes:ident| $e:expr) => {{
+                let value = self.pop_v128();
+                let $bytes = lanes::<$n, { 16 / $n }>(value)[*$lane as usize];
+                let value = $e;
+                self.push(value);
+            }};
+        }
+        macro_rules! replace {
+            ($lane:expr, $n:literal, $pop:ident, |$a:ident| $e:expr) => {{
+                let $a = self.$pop();
+                let value = self.pop_v128();
+                let mut lanes = lanes::<$n, { 16 / $n }>(value);
+                lanes[*$lane as usize] = $e;
+                self.push(join(lanes));
+            }};
+        }
+        macro_rules! splat {
+            ($pop:ident, |$a:ident| $e:expr) => {{
+                let $a = self.$pop();
+                self.push(splat($e));
+            }};
+        }
+        macro_rules! un {
+            ($ty:ty, $l:literal, |$a:ident| $e:expr) => {{
+                let lanes = unpack::<$ty, $l>(self.pop_v128());
+                self.push(pack(lanes.map(|$a| $e)));
+            }};
+        }
+        macro_rules! bin {
+            ($ty:ty, $l:literal, |$a:ident, $b:ident| $e:expr) => {{
+                let rhs = unpack::<$ty, $l>(self.pop_v128());
+                let lhs = unpack::<$ty, $l>(self.pop_v128());
+                self.push(pack::<_, $l>(std::array::from_fn(|i| {
+                    let ($a, $b) = (lhs[i], rhs[i]);
+                    $e
+                })));
+            }};
+        }
+        macro_rules! madd {
+            ($ty:ty, $l:literal, |$a:ident, $b:ident, $c:ident| $e:expr) => {{
+                let addend = unpack::<$ty, $l>(self.pop_v128());
+                let rhs = unpack::<$ty, $l>(self.pop_v128());
+                let lhs = unpack::<$ty, $l>(self.pop_v128());
+                self.push(pack::<_, $l>(std::array::from_fn(|i| {
+                    let ($a, $b, $c) = (lhs[i], rhs[i], addend[i]);
+                    $e
+                })));
+            }};
+        }
+        macro_rules! bits {
+            (|$a:ident, $b:ident| $e:expr) => {{
+                let $b = self.pop_v128();
+                let $a = self.pop_v128();
+                self.push($e);
+            }};
+        }
+        macro_rules! cmp {
+            ($ty:ty, $l:literal, |$a:ident, $b:ident| $e:expr) => {{
+                let rhs = unpack::<$ty, $l>(self.pop_v128());
+                let lhs = unpack::<$ty, $l>(self.pop_v128());
+                self.push(mask::<$l>(std::array::from_fn(|i| {
+                    let ($a, $b) = (lhs[i], rhs[i]);
+                    $e
+                })));
+            }};
+        }
+        macro_rules! shift {
+            ($ty:ty, $l:literal, |$a:ident, $n:ident| $e:expr) => {{
+                let $n = self.pop_i32() as u32;
+                let lanes = unpack::<$ty, $l>(self.pop_v128());
+                self.push(pack(lanes.map(|$a| $e)));
+            }};
+        }
+        macro_rules! all_true {
+            ($ty:ty, $l:literal) => {{
+                let lanes = unpack::<$ty, $l>(self.pop_v128());
+                self.push(lanes.iter().all(|lane| *lane != 0));
+            }};
+        }
+        macro_rules! bitmask {
+            ($ty:ty, $l:literal) => {{
+                let lanes = unpack::<$ty, $l>(self.pop_v128());
+                let bits = lanes
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, lane)| acc | ((*lane < 0) as i32) << i);
+                self.push(bits);
+            }};
+        }
+        macro_rules! narrow {
+            ($from:ty, $lf:literal, $to:ty, $lt:literal) => {{
+                let hi = unpack::<$from, $lf>(self.pop_v128());
+                let lo = unpack::<$from, $lf>(self.pop_v128());
+                let sat = |lane: $from| lane.clamp(<$to>::MIN as $from, <$to>::MAX as $from) as $to;
+                self.push(pack::<$to, $lt>(std::array::from_fn(|i| match i {
+                    0..$lf => sat(lo[i]),
+                    _ => sat(hi[i - $lf]),
+                })));
+            }};
+        }
+        macro_rules! widen {
+            ($from:ty, $lf:literal, $to:ty, $lt:literal, $at:literal) => {{
+                let lanes = unpack::<$from, $lf>(self.pop_v128());
+                self.push(pack::<$to, $lt>(std::array::from_fn(|i| {
+                    lanes[$at + i] as $to
+                })));
+            }};
+        }
+        macro_rules! extmul {
+            ($from:ty, $lf:literal, $to:ty, $lt:literal, $at:literal) => {{
+                let rhs = unpack::<$from, $lf>(self.pop_v128());
+                let lhs = unpack::<$from, $lf>(self.pop_v128());
+                self.push(pack::<$to, $lt>(std::array::from_fn(|i| {
+                    (lhs[$at + i] as $to).wrapping_mul(rhs[$at + i] as $to)
+                })));
+            }};
+        }
+        macro_rules! pairwise {
+            ($from:ty, $lf:literal, $to:ty, $lt:literal) => {{
+                let lanes = unpack::<$from, $lf>(self.pop_v128());
+                self.push(pack::<$to, $lt>(std::array::from_fn(|i| {
+                    lanes[2 * i] as $to + lanes[2 * i + 1] as $to
+                })));
+            }};
+        }
+        // Converts lane by lane, zeroing the result lanes without a source lane.
+        macro_rules! convert {
+            ($from:ty, $lf:literal, $to:ty, $lt:literal, |$a:ident| $e:expr) => {{
+                let lanes = unpack::<$from, $lf>(self.pop_v128());
+                self.push(pack::<$to, $lt>(std::array::from_fn(|i| {
+                    lanes.get(i).map(|&$a| $e).unwrap_or_default()
+                })));
+            }};
+        }
+
+        match instr {
+            Instr::V128_Const(bytes) => self.push(u128::from_le_bytes(*bytes)),
+            Instr::V128_Load(m) => {
+                let bytes = self.load::<16>(frame, m)?;
+                self.push(u128::from_le_bytes(bytes));
+            }
+            Instr::V128_Store(m) => {
+                let value = self.pop_v128();
+                self.store(frame, m, value.to_le_bytes())?;
+            }
+            Instr::V128_Load_8x8_S(m) => extend!(m, i8, i16),
+            Instr::V128_Load_8x8_U(m) => extend!(m, u8, u16),
+            Instr::V128_Load_16x4_S(m) => extend!(m, i16, i32),
+            Instr::V128_Load_16x4_U(m) => extend!(m, u16, u32),
+            Instr::V128_Load_32x2_S(m) => extend!(m, i32, i64),
+            Instr::V128_Load_32x2_U(m) => extend!(m, u32, u64),
+            Instr::V128_Load_8_Splat(m) => {
+                let bytes = self.load::<1>(frame, m)?;
+                self.push(splat(bytes));
+            }
+            Instr::V128_Load_16_Splat(m) => {
+                let bytes = self.load::<2>(frame, m)?;
+                self.push(splat(bytes));
+            }
+            Instr::V128_Load_32_Splat(m) => {
+                let bytes = self.load::<4>(frame, m)?;
+                self.push(splat(bytes));
+            }
+            Instr::V128_Load_64_Splat(m) => {
+                let bytes = self.load::<8>(frame, m)?;
+                self.push(splat(bytes));
+            }
+            Instr::V128_Load_32_Zero(m) => {
+                let bytes = self.load::<4>(frame, m)?;
+                self.push(u32::from_le_bytes(bytes) as u128);
+            }
+            Instr::V128_Load_64_Zero(m) => {
+                let bytes = self.load::<8>(frame, m)?;
+                self.push(u64::from_le_bytes(bytes) as u128);
+            }
+            Instr::V128_Load_8_Lane(m, lane) => load_lane!(m, lane, 1),
+            Instr::V128_Load_16_Lane(m, lane) => load_lane!(m, lane, 2),
+            Instr::V128_Load_32_Lane(m, lane) => load_lane!(m, lane, 4),
+            Instr::V128_Load_64_Lane(m, lane) => load_lane!(m, lane, 8),
+            Instr::V128_Store_8_Lane(m, lane) => store_lane!(m, lane, 1),
+            Instr::V128_Store_16_Lane(m, lane) => store_lane!(m, lane, 2),
+            Instr::V128_Store_32_Lane(m, lane) => store_lane!(m, lane, 4),
+            Instr::V128_Store_64_Lane(m, lane) => store_lane!(m, lane, 8),
+            Instr::I8X16_Extract_Lane_S(lane) => extract!(lane, 1, |b| b[0] as i8 as i32),
+            Instr::I8X16_Extract_Lane_U(lane) => extract!(lane, 1, |b| b[0] as i32),
+            Instr::I16X8_Extract_Lane_S(lane) => {
+                extract!(lane, 2, |b| i16::from_le_bytes(b) as i32)
+            }
+            Instr::I16X8_Extract_Lane_U(lane) => {
+                extract!(lane, 2, |b| u16::from_le_bytes(b) as i32)
+            }
+            Instr::I32X4_Extract_Lane(lane) => extract!(lane, 4, |b| i32::from_le_bytes(b)),
+            Instr::I64X2_Extract_Lane(lane) => extract!(lane, 8, |b| i64::from_le_bytes(b)),
+            Instr::F32X4_Extract_Lane(lane) => extract!(lane, 4, |b| f32::from_le_bytes(b)),
+            Instr::F64X2_Extract_Lane(lane) => extract!(lane, 8, |b| f64::from_le_bytes(b)),
+            Instr::I8X16_Replace_Lane(lane) => replace!(lane, 1, pop_i32, |a| [a as u8]),
+            Instr::I16X8_Replace_Lane(lane) => {
+                replace!(lane, 2, pop_i32, |a| (a as u16).to_le_bytes())
+            }
+            Instr::I32X4_Replace_Lane(lane) => replace!(lane, 4, pop_i32, |a| a.to_le_bytes()),
+            Instr::I64X2_Replace_Lane(lane) => replace!(lane, 8, pop_i64, |a| a.to_le_bytes()),
+            Instr::F32X4_Replace_Lane(lane) => replace!(lane, 4, pop_f32, |a| a.to_le_bytes()),
+            Instr::F64X2_Replace_Lane(lane) => replace!(lane, 8, pop_f64, |a| a.to_le_bytes()),
+            Instr::I8X16_Shuffle(idxs) => {
+                let b = self.pop_v128().to_le_bytes();
+                let a = self.pop_v128().to_le_bytes();
+                let out: [u8; 16] = std::array::from_fn(|i| match idxs[i] {
+                    idx @ 0..16 => a[idx as usize],
+                    idx => b[(idx & 15) as usize],
+                });
+                self.push(u128::from_le_bytes(out));
+            }
+            Instr::I8x16_Swizzle | Instr::I8X16_Relaxed_Swizzle => {
+                let idxs = self.pop_v128().to_le_bytes();
+                let a = self.pop_v128().to_le_bytes();
+                let out: [u8; 16] =
+                    std::array::from_fn(|i| a.get(idxs[i] as usize).copied().unwrap_or(0));
+                self.push(u128::from_le_bytes(out));
+            }
+            Instr::I8X16_Splat => splat!(pop_i32, |a| [a as u8]),
+            Instr::I16X8_Splat => splat!(pop_i32, |a| (a as u16).to_le_bytes()),
+            Instr::I32X4_Splat => splat!(pop_i32, |a| a.to_le_bytes()),
+            Instr::I64X2_Splat => splat!(pop_i64, |a| a.to_le_bytes()),
+            Instr::F32X4_Splat => splat!(pop_f32, |a| a.to_le_bytes()),
+            Instr::F64X2_Splat => splat!(pop_f64, |a| a.to_le_bytes()),
+            Instr::V128_Not => {
+                let a = self.pop_v128();
+                self.push(!a);
+            }
+            Instr::V128_And => bits!(|a, b| a & b),
+            Instr::V128_Andnot => bits!(|a, b| a & !b),
+            Instr::V128_Or => bits!(|a, b| a | b),
+            Instr::V128_Xor => bits!(|a, b| a ^ b),
+            Instr::V128_Bitselect
+            | Instr::I8X16_Relaxed_Laneselect
+            | Instr::I16X8_Relaxed_Laneselect
+            | Instr::I32X4_Relaxed_Laneselect
+            | Instr::I64X2_Relaxed_Laneselect => {
+                let c = self.pop_v128();
+                let b = self.pop_v128();
+                let a = self.pop_v128();
+                self.push(a & c | b & !c);
+            }
+            Instr::V128_Any_True => {
+                let a = self.pop_v128();
+                self.push(a != 0);
+            }
+
+            Instr::I8X16_Eq => cmp!(i8, 16, |a, b| a == b),
+            Instr::I8X16_Ne => cmp!(i8, 16, |a, b| a != b),
+            Instr::I8X16_Lt_S => cmp!(i8, 16, |a, b| a < b),
+            Instr::I8X16_Lt_U => cmp!(u8, 16, |a, b| a < b),
+            Instr::I8X16_Gt_S => cmp!(i8, 16, |a, b| a > b),
+            Instr::I8X16_Gt_U => cmp!(u8, 16, |a, b| a > b),
+            Instr::I8X16_Le_S => cmp!(i8, 16, |a, b| a <= b),
+            Instr::I8X16_Le_U => cmp!(u8, 16, |a, b| a <= b),
+            Instr::I8X16_Ge_S => cmp!(i8, 16, |a, b| a >= b),
+            Instr::I8X16_Ge_U => cmp!(u8, 16, |a, b| a >= b),
+            Instr::I16X8_Eq => cmp!(i16, 8, |a, b| a == b),
+            Instr::I16X8_Ne => cmp!(i16, 8, |a, b| a != b),
+            Instr::I16X8_Lt_S => cmp!(i16, 8, |a, b| a < b),
+            Instr::I16X8_Lt_U => cmp!(u16, 8, |a, b| a < b),
+            Instr::I16X8_Gt_S => cmp!(i16, 8, |a, b| a > b),
+            Instr::I16X8_Gt_U => cmp!(u16, 8, |a, b| a > b),
+            Instr::I16X8_Le_S => cmp!(i16, 8, |a, b| a <= b),
+            Instr::I16X8_Le_U => cmp!(u16, 8, |a, b| a <= b),
+            Instr::I16X8_Ge_S => cmp!(i16, 8, |a, b| a >= b),
+            Instr::I16X8_Ge_U => cmp!(u16, 8, |a, b| a >= b),
+            Instr::I32X4_Eq => cmp!(i32, 4, |a, b| a == b),
+            Instr::I32X4_Ne => cmp!(i32, 4, |a, b| a != b),
+            Instr::I32X4_Lt_S => cmp!(i32, 4, |a, b| a < b),
+            Instr::I32X4_Lt_U => cmp!(u32, 4, |a, b| a < b),
+            Instr::I32X4_Gt_S => cmp!(i32, 4, |a, b| a > b),
+            Instr::I32X4_Gt_U => cmp!(u32, 4, |a, b| a > b),
+            Instr::I32X4_Le_S => cmp!(i32, 4, |a, b| a <= b),
+            Instr::I32X4_Le_U => cmp!(u32, 4, |a, b| a <= b),
+            Instr::I32X4_Ge_S => cmp!(i32, 4, |a, b| a >= b),
+            Instr::I32X4_Ge_U => cmp!(u32, 4, |a, b| a >= b),
+            Instr::I64X2_Eq => cmp!(i64, 2, |a, b| a == b),
+            Instr::I64X2_Ne => cmp!(i64, 2, |a, b| a != b),
+            Instr::I64X2_Lt_S => cmp!(i64, 2, |a, b| a < b),
+            Instr::I64X2_Gt_S => cmp!(i64, 2, |a, b| a > b),
+            Instr::I64X2_Le_S => cmp!(i64, 2, |a, b| a <= b),
+            Instr::I64X2_Ge_S => cmp!(i64, 2, |a, b| a >= b),
+            Instr::F32X4_Eq => cmp!(f32, 4, |a, b| a == b),
+            Instr::F32X4_Ne => cmp!(f32, 4, |a, b| a != b),
+            Instr::F32X4_Lt => cmp!(f32, 4, |a, b| a < b),
+            Instr::F32X4_Gt => cmp!(f32, 4, |a, b| a > b),
+            Instr::F32X4_Le => cmp!(f32, 4, |a, b| a <= b),
+            Instr::F32X4_Ge => cmp!(f32, 4, |a, b| a >= b),
+            Instr::F64X2_Eq => cmp!(f64, 2, |a, b| a == b),
+            Instr::F64X2_Ne => cmp!(f64, 2, |a, b| a != b),
+            Instr::F64X2_Lt => cmp!(f64, 2, |a, b| a < b),
+            Instr::F64X2_Gt => cmp!(f64, 2, |a, b| a > b),
+            Instr::F64X2_Le => cmp!(f64, 2, |a, b| a <= b),
+            Instr::F64X2_Ge => cmp!(f64, 2, |a, b| a >= b),
+
+            Instr::I8X16_Abs => un!(i8, 16, |a| a.wrapping_abs()),
+            Instr::I8X16_Neg => un!(i8, 16, |a| a.wrapping_neg()),
+            Instr::I8X16_Popcnt => un!(u8, 16, |a| a.count_ones() as u8),
+            Instr::I8X16_All_True => all_true!(i8, 16),
+            Instr::I8X16_Bitmask => bitmask!(i8, 16),
+            Instr::I8X16_Narrow_I16X8_S => narrow!(i16, 8, i8, 16),
+            Instr::I8X16_Narrow_I16X8_U => narrow!(i16, 8, u8, 16),
+            Instr::I8X16_Shl => shift!(i8, 16, |a, n| a.wrapping_shl(n)),
+            Instr::I8X16_Shr_S => shift!(i8, 16, |a, n| a.wrapping_shr(n)),
+            Instr::I8X16_Shr_U => shift!(u8, 16, |a, n| a.wrapping_shr(n)),
+            Instr::I8X16_Add => bin!(i8, 16, |a, b| a.wrapping_add(b)),
+            Instr::I8X16_Add_Sat_S => bin!(i8, 16, |a, b| a.saturating_add(b)),
+            Instr::I8X16_Add_Sat_U => bin!(u8, 16, |a, b| a.saturating_add(b)),
+            Instr::I8X16_Sub => bin!(i8, 16, |a, b| a.wrapping_sub(b)),
+            Instr::I8X16_Sub_Sat_S => bin!(i8, 16, |a, b| a.saturating_sub(b)),
+            Instr::I8X16_Sub_Sat_U => bin!(u8, 16, |a, b| a.saturating_sub(b)),
+            Instr::I8X16_Min_S => bin!(i8, 16, |a, b| a.min(b)),
+            Instr::I8X16_Min_U => bin!(u8, 16, |a, b| a.min(b)),
+            Instr::I8X16_Max_S => bin!(i8, 16, |a, b| a.max(b)),
+            Instr::I8X16_Max_U => bin!(u8, 16, |a, b| a.max(b)),
+            Instr::I8X16_Avgr_U => bin!(u8, 16, |a, b| (a as u16 + b as u16).div_ceil(2) as u8),
+
+            Instr::I16X8_Extadd_Pairwise_I8X16_S => pairwise!(i8, 16, i16, 8),
+            Instr::I16X8_Extadd_Pairwise_I8X16_U => pairwise!(u8, 16, u16, 8),
+            Instr::I32X4_Extadd_Pairwise_I16X8_S => pairwise!(i16, 8, i32, 4),
+            Instr::I32X4_Extadd_Pairwise_I16X8_U => pairwise!(u16, 8, u32, 4),
+            Instr::I16X8_Abs => un!(i16, 8, |a| a.wrapping_abs()),
+            Instr::I16X8_Neg => un!(i16, 8, |a| a.wrapping_neg()),
+            Instr::I16X8_Q15mulr_Sat_S | Instr::I16X8_Relaxed_Q15mulr_S => {
+                bin!(i16, 8, |a, b| ((a as i32 * b as i32 + 0x4000) >> 15)
+                    .clamp(i16::MIN as i32, i16::MAX as i32)
+                    as i16)
+            }
+            Instr::I16X8_All_True => all_true!(i16, 8),
+            Instr::I16X8_Bitmask => bitmask!(i16, 8),
+            Instr::I16X8_Narrow_I32X4_S => narrow!(i32, 4, i16, 8),
+            Instr::I16X8_Narrow_I32X4_U => narrow!(i32, 4, u16, 8),
+            Instr::I16X8_Extend_Low_I8X16_S => widen!(i8, 16, i16, 8, 0),
+            Instr::I16X8_Extend_High_I8X16_S => widen!(i8, 16, i16, 8, 8),
+            Instr::I16X8_Extend_Low_I8X16_U => widen!(u8, 16, u16, 8, 0),
+            Instr::I16X8_Extend_High_I8X16_U => widen!(u8, 16, u16, 8, 8),
+            Instr::I16X8_Shl => shift!(i16, 8, |a, n| a.wrapping_shl(n)),
+            Instr::I16X8_Shr_S => shift!(i16, 8, |a, n| a.wrapping_shr(n)),
+            Instr::I16X8_Shr_U => shift!(u16, 8, |a, n| a.wrapping_shr(n)),
+            Instr::I16X8_Add => bin!(i16, 8, |a, b| a.wrapping_add(b)),
+            Instr::I16X8_Add_Sat_S => bin!(i16, 8, |a, b| a.saturating_add(b)),
+            Instr::I16X8_Add_Sat_U => bin!(u16, 8, |a, b| a.saturating_add(b)),
+            Instr::I16X8_Sub => bin!(i16, 8, |a, b| a.wrapping_sub(b)),
+            Instr::I16X8_Sub_Sat_S => bin!(i16, 8, |a, b| a.saturating_sub(b)),
+            Instr::I16X8_Sub_Sat_U => bin!(u16, 8, |a, b| a.saturating_sub(b)),
+            Instr::I16X8_Mul => bin!(i16, 8, |a, b| a.wrapping_mul(b)),
+            Instr::I16X8_Min_S => bin!(i16, 8, |a, b| a.min(b)),
+            Instr::I16X8_Min_U => bin!(u16, 8, |a, b| a.min(b)),
+            Instr::I16X8_Max_S => bin!(i16, 8, |a, b| a.max(b)),
+            Instr::I16X8_Max_U => bin!(u16, 8, |a, b| a.max(b)),
+            Instr::I16X8_Avgr_U => bin!(u16, 8, |a, b| (a as u32 + b as u32).div_ceil(2) as u16),
+            Instr::I16X8_Extmul_Low_I8X16_S => extmul!(i8, 16, i16, 8, 0),
+            Instr::I16X8_Extmul_High_I8X16_S => extmul!(i8, 16, i16, 8, 8),
+            Instr::I16X8_Extmul_Low_I8X16_U => extmul!(u8, 16, u16, 8, 0),
+            Instr::I16X8_Extmul_High_I8X16_U => extmul!(u8, 16, u16, 8, 8),
+
+            Instr::I32X4_Abs => un!(i32, 4, |a| a.wrapping_abs()),
+            Instr::I32X4_Neg => un!(i32, 4, |a| a.wrapping_neg()),
+            Instr::I32X4_All_True => all_true!(i32, 4),
+            Instr::I32X4_Bitmask => bitmask!(i32, 4),
+            Instr::I32X4_Extend_Low_I16X8_S => widen!(i16, 8, i32, 4, 0),
+            Instr::I32X4_Extend_High_I16X8_S => widen!(i16, 8, i32, 4, 4),
+            Instr::I32X4_Extend_Low_I16X8_U => widen!(u16, 8, u32, 4, 0),
+            Instr::I32X4_Extend_High_I16X8_U => widen!(u16, 8, u32, 4, 4),
+            Instr::I32X4_Shl => shift!(i32, 4, |a, n| a.wrapping_shl(n)),
+            Instr::I32X4_Shr_S => shift!(i32, 4, |a, n| a.wrapping_shr(n)),
+            Instr::I32X4_Shr_U => shift!(u32, 4, |a, n| a.wrapping_shr(n)),
+            Instr::I32X4_Add => bin!(i32, 4, |a, b| a.wrapping_add(b)),
+            Instr::I32X4_Sub => bin!(i32, 4, |a, b| a.wrapping_sub(b)),
+            Instr::I32X4_Mul => bin!(i32, 4, |a, b| a.wrapping_mul(b)),
+            Instr::I32X4_Min_S => bin!(i32, 4, |a, b| a.min(b)),
+            Instr::I32X4_Min_U => bin!(u32, 4, |a, b| a.min(b)),
+            Instr::I32X4_Max_S => bin!(i32, 4, |a, b| a.max(b)),
+            Instr::I32X4_Max_U => bin!(u32, 4, |a, b| a.max(b)),
+            Instr::I32X4_Dot_I16X8_S => {
+                let b = unpack::<i16, 8>(self.pop_v128());
+                let a = unpack::<i16, 8>(self.pop_v128());
+                let dot: [i32; 4] = std::array::from_fn(|i| {
+                    let lo = a[2 * i] as i32 * b[2 * i] as i32;
+                    lo.wrapping_add(a[2 * i + 1] as i32 * b[2 * i + 1] as i32)
+                });
+                self.push(pack(dot));
+            }
+            Instr::I32X4_Extmul_Low_I16X8_S => extmul!(i16, 8, i32, 4, 0),
+            Instr::I32X4_Extmul_High_I16X8_S => extmul!(i16, 8, i32, 4, 4),
+            Instr::I32X4_Extmul_Low_I16X8_U => extmul!(u16, 8, u32, 4, 0),
+            Instr::I32X4_Extmul_High_I16X8_U => extmul!(u16, 8, u32, 4, 4),
+
+            Instr::I64X2_Abs => un!(i64, 2, |a| a.wrapping_abs()),
+            Instr::I64X2_Neg => un!(i64, 2, |a| a.wrapping_neg()),
+            Instr::I64X2_All_True => all_true!(i64, 2),
+            Instr::I64X2_Bitmask => bitmask!(i64, 2),
+            Instr::I64X2_Extend_Low_I32X4_S => widen!(i32, 4, i64, 2, 0),
+            Instr::I64X2_Extend_High_I32X4_S => widen!(i32, 4, i64, 2, 2),
+            Instr::I64X2_Extend_Low_I32X4_U => widen!(u32, 4, u64, 2, 0),
+            Instr::I64X2_Extend_High_I32X4_U => widen!(u32, 4, u64, 2, 2),
+            Instr::I64X2_Shl => shift!(i64, 2, |a, n| a.wrapping_shl(n)),
+            Instr::I64X2_Shr_S => shift!(i64, 2, |a, n| a.wrapping_shr(n)),
+            Instr::I64X2_Shr_U => shift!(u64, 2, |a, n| a.wrapping_shr(n)),
+            Instr::I64X2_Add => bin!(i64, 2, |a, b| a.wrapping_add(b)),
+            Instr::I64X2_Sub => bin!(i64, 2, |a, b| a.wrapping_sub(b)),
+            Instr::I64X2_Mul => bin!(i64, 2, |a, b| a.wrapping_mul(b)),
+            Instr::I64X2_Extmul_Low_I32X4_S => extmul!(i32, 4, i64, 2, 0),
+            Instr::I64X2_Extmul_High_I32X4_S => extmul!(i32, 4, i64, 2, 2),
+            Instr::I64X2_Extmul_Low_I32X4_U => extmul!(u32, 4, u64, 2, 0),
+            Instr::I64X2_Extmul_High_I32X4_U => extmul!(u32, 4, u64, 2, 2),
+
+            Instr::F32X4_Abs => un!(f32, 4, |a| a.abs()),
+            Instr::F32X4_Neg => un!(f32, 4, |a| -a),
+            Instr::F32X4_Sqrt => un!(f32, 4, |a| a.sqrt()),
+            Instr::F32X4_Ceil => un!(f32, 4, |a| a.ceil()),
+            Instr::F32X4_Floor => un!(f32, 4, |a| a.floor()),
+            Instr::F32X4_Trunc => un!(f32, 4, |a| a.trunc()),
+            Instr::F32X4_Nearest => un!(f32, 4, |a| a.round_ties_even()),
+            Instr::F32X4_Add => bin!(f32, 4, |a, b| a + b),
+            Instr::F32X4_Sub => bin!(f32, 4, |a, b| a - b),
+            Instr::F32X4_Mul => bin!(f32, 4, |a, b| a * b),
+            Instr::F32X4_Div => bin!(f32, 4, |a, b| a / b),
+            Instr::F32X4_Min | Instr::F32X4_Relaxed_Min => bin!(f32, 4, |a, b| f32_min(a, b)),
+            Instr::F32X4_Max | Instr::F32X4_Relaxed_Max => bin!(f32, 4, |a, b| f32_max(a, b)),
+            Instr::F32X4_Pmin => bin!(f32, 4, |a, b| if b < a { b } else { a }),
+            Instr::F32X4_Pmax => bin!(f32, 4, |a, b| if a < b { b } else { a }),
+            Instr::F64X2_Abs => un!(f64, 2, |a| a.abs()),
+            Instr::F64X2_Neg => un!(f64, 2, |a| -a),
+            Instr::F64X2_Sqrt => un!(f64, 2, |a| a.sqrt()),
+            Instr::F64X2_Ceil => un!(f64, 2, |a| a.ceil()),
+            Instr::F64X2_Floor => un!(f64, 2, |a| a.floor()),
+            Instr::F64X2_Trunc => un!(f64, 2, |a| a.trunc()),
+            Instr::F64X2_Nearest => un!(f64, 2, |a| a.round_ties_even()),
+            Instr::F64X2_Add => bin!(f64, 2, |a, b| a + b),
+            Instr::F64X2_Sub => bin!(f64, 2, |a, b| a - b),
+            Instr::F64X2_Mul => bin!(f64, 2, |a, b| a * b),
+            Instr::F64X2_Div => bin!(f64, 2, |a, b| a / b),
+            Instr::F64X2_Min | Instr::F64X2_Relaxed_Min => bin!(f64, 2, |a, b| f64_min(a, b)),
+            Instr::F64X2_Max | Instr::F64X2_Relaxed_Max => bin!(f64, 2, |a, b| f64_max(a, b)),
+            Instr::F64X2_Pmin => bin!(f64, 2, |a, b| if b < a { b } else { a }),
+            Instr::F64X2_Pmax => bin!(f64, 2, |a, b| if a < b { b } else { a }),
+
+            // `as` saturates and maps NaN to zero, as `trunc_sat` requires.
+            Instr::I32X4_Trunc_Sat_F32X4_S | Instr::I32X4_Relaxed_Trunc_F32X4_S => {
+                convert!(f32, 4, i32, 4, |a| a as i32)
+            }
+            Instr::I32X4_Trunc_Sat_F32X4_U | Instr::I32X4_Relaxed_Trunc_F32X4_U => {
+                convert!(f32, 4, u32, 4, |a| a as u32)
+            }
+            Instr::I32X4_Trunc_Sat_F64X2_S_Zero | Instr::I32X4_Relaxed_Trunc_F64X2_S_Zero => {
+                convert!(f64, 2, i32, 4, |a| a as i32)
+            }
+            Instr::I32X4_Trunc_Sat_F64X2_U_Zero | Instr::I32X4_Relaxed_Trunc_F64X2_U_Zero => {
+                convert!(f64, 2, u32, 4, |a| a as u32)
+            }
+            Instr::F32X4_Convert_I32X4_S => convert!(i32, 4, f32, 4, |a| a as f32),
+            Instr::F32X4_Convert_I32X4_U => convert!(u32, 4, f32, 4, |a| a as f32),
+            Instr::F64X2_Convert_Low_I32X4_S => convert!(i32, 4, f64, 2, |a| a as f64),
+            Instr::F64X2_Convert_Low_I32X4_U => convert!(u32, 4, f64, 2, |a| a as f64),
+            Instr::F32X4_Demote_F64X2_Zero => convert!(f64, 2, f32, 4, |a| a as f32),
+            Instr::F64X2_Promote_Low_F32X4 => convert!(f32, 4, f64, 2, |a| a as f64),
+
+            // The relaxed instructions behave like their deterministic
+            // counterparts; `madd` rounds the product before the addition.
+            Instr::F32X4_Relaxed_Madd => madd!(f32, 4, |a, b, c| a * b + c),
+            Instr::F32X4_Relaxed_Nmadd => madd!(f32, 4, |a, b, c| -(a * b) + c),
+            Instr::F64X2_Relaxed_Madd => madd!(f64, 2, |a, b, c| a * b + c),
+            Instr::F64X2_Relaxed_Nmadd => madd!(f64, 2, |a, b, c| -(a * b) + c),
+            Instr::I16X8_Relaxed_Dot_I8X16_I7X16_S => {
+                let b = unpack::<i8, 16>(self.pop_v128());
+                let a = unpack::<i8, 16>(self.pop_v128());
+                let dot: [i16; 8] = std::array::from_fn(|i| {
+                    let lo = a[2 * i] as i16 * b[2 * i] as i16;
+                    lo.wrapping_add(a[2 * i + 1] as i16 * b[2 * i + 1] as i16)
+                });
+                self.push(pack(dot));
+            }
+            Instr::I32X4_Relaxed_Dot_I8X16_I7X16_Add_S => {
+                let c = unpack::<i32, 4>(self.pop_v128());
+                let b = unpack::<i8, 16>(self.pop_v128());
+                let a = unpack::<i8, 16>(self.pop_v128());
+                let dot: [i32; 4] = std::array::from_fn(|i| {
+                    (4 * i..4 * i + 4)
+                        .map(|j| a[j] as i32 * b[j] as i32)
+                        .fold(c[i], i32::wrapping_add)
+                });
+                self.push(pack(dot));
+            }
+            instr => return Err(Trap::Unsupported(instr.name())),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::{pack, unpack, Config, ExternVal, Imports, Store, Trap, Value};
+    use crate::modules::Module;
+    use crate::types::{FuncType, NumType, ValType};
+    use crate::{Error, Parse};
+
+    const WAT: &str = r#"
+        (module
+          (import "env" "log" (func $log (param i32)))
+          (memory (export "memory") 1 2)
+          (table 2 funcref)
+          (global $calls (mut i32) (i32.const 0))
+          (elem (i32.const 0) $fac $div)
+          (data (i32.const 8) "\2a\00\00\00")
+          (type $unary (func (param i32) (result i32)))
+          (func $fac (export "fac") (param i64) (result i64)
+            (global.set $calls (i32.add (global.get $calls) (i32.const 1)))
+            (if (result i64) (i64.eqz (local.get 0))
+              (then (i64.const 1))
+              (else (i64.mul (local.get 0) (call $fac (i64.sub (local.get 0) (i64.const 1)))))))
+          (func $div (export "div") (param i32 i32) (result i32)
+            (i32.div_s (local.get 0) (local.get 1)))
+          (func (export "load") (param i32) (result i32)
+            (call $log (i32.load (local.get 0)))
+            (i32.load (local.get 0)))
+          (func (export "indirect") (param i32) (result i32)
+            (call_indirect (type $unary) (i32.const 1) (local.get 0)))
+          (func (export "grow") (result i32)
+            (drop (memory.grow (i32.const 1)))
+            (memory.grow (i32.const 1)))
+          (func (export "calls") (result i32) (global.get $calls))
+          (func $loop (export "loop") (param i32) (result i32) (local i32)
+            (block $done
+              (loop $next
+                (br_if $done (i32.eqz (local.get 0)))
+                (local.set 1 (i32.add (local.get 1) (local.get 0)))
+                (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                (br $next)))
+            (local.get 1))
+          (func $deep (export "deep") (call $deep))
+        )
+    "#;
+
+    fn trap(result: Result<Vec<Value>, Error>) -> Trap {
+        match result {
+            Err(Error::Trap(_, trap)) => trap,
+            other => panic!("expected a trap, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn execute() {
+        let module = Module::parse(WAT).unwrap();
+        let mut store = Store::new();
+        let logged = Rc::new(RefCell::new(Vec::new()));
+        let log = {
+            let logged = logged.clone();
+            let ty = FuncType(vec![ValType::NumType(NumType::I32)], vec![]);
+            store.alloc_host_func(ty, move |caller, args| {
+                assert!(caller.memory().is_some());
+                logged.borrow_mut().push(args[0]);
+                Ok(vec![])
+            })
+        };
+        let mut imports = Imports::new();
+        imports.define("env", "log", ExternVal::Func(log));
+        let instance = store.instantiate(&module, &imports).unwrap();
+
+        let mut call = |name: &str, args: &[Value]| store.invoke_export(instance, name, args);
+        assert_eq!(
+            call("fac", &[Value::I64(20)]).unwrap(),
+            [Value::I64(2432902008176640000)]
+        );
+        assert_eq!(call("calls", &[]).unwrap(), [Value::I32(21)]);
+        assert_eq!(
+            call("div", &[Value::I32(-7), Value::I32(2)]).unwrap(),
+            [Value::I32(-3)]
+        );
+        assert_eq!(
+            trap(call("div", &[Value::I32(1), Value::I32(0)])),
+            Trap::IntegerDivideByZero
+        );
+        assert_eq!(
+            trap(call("div", &[Value::I32(i32::MIN), Value::I32(-1)])),
+            Trap::IntegerOverflow
+        );
+        assert_eq!(call("load", &[Value::I32(8)]).unwrap(), [Value::I32(42)]);
+        assert_eq!(
+            trap(call("load", &[Value::I32(65533)])),
+            Trap::MemoryOutOfBounds
+        );
+        assert_eq!(
+            trap(call("indirect", &[Value::I32(1)])),
+            Trap::IndirectCallTypeMismatch
+        );
+        assert_eq!(
+            trap(call("indirect", &[Value::I32(2)])),
+            Trap::UndefinedElement
+        );
+        assert_eq!(call("grow", &[]).unwrap(), [Value::I32(-1)]);
+        assert_eq!(
+            call("loop", &[Value::I32(100)]).unwrap(),
+            [Value::I32(5050)]
+        );
+        assert_eq!(trap(call("deep", &[])), Trap::CallStackExhausted);
+        assert!(matches!(
+            call("fac", &[Value::I32(1)]),
+            Err(Error::InvalidArguments(_, _))
+        ));
+        assert_eq!(*logged.borrow(), [Value::I32(42)]);
+
+        assert!(matches!(
+            store.instantiate(&module, &Imports::new()),
+            Err(Error::Link(_, _))
+        ));
+
+        let invalid = Module::parse(r#"(func (export "f") (result i32) (i64.const 0))"#).unwrap();
+        assert!(matches!(
+            store.instantiate(&invalid, &Imports::new()),
+            Err(Error::InvalidModule(_, _))
+        ));
+    }
+
+    #[test]
+    fn nested_blocks() {
+        // Recursing inside deeply nested blocks must exhaust the depth limit
+        // long before the host's stack, which debug builds use a lot of.
+        let run = || {
+            let blocks = "(block ".repeat(100);
+            let ends = ")".repeat(100);
+            let wat = format!(r#"(func $rec (export "rec") {blocks}(call $rec){ends})"#);
+            let module = Module::parse(wat.as_str()).unwrap();
+            let mut store = Store::new();
+            let instance = store.instantiate(&module, &Imports::new()).unwrap();
+            assert_eq!(
+                trap(store.invoke_export(instance, "rec", &[])),
+                Trap::CallStackExhausted
+            );
+        };
+        std::thread::Builder::new()
+            .stack_size(32 << 20)
+            .spawn(run)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn limits() {
+        let module = Module::parse(
+            r#"
+            (memory 1 4)
+            (func (export "spin") (loop (br 0)))
+            (func $rec (export "rec") (param i32)
+              (if (local.get 0) (then (call $rec (i32.sub (local.get 0) (i32.const 1))))))
+            (func (export "grow") (param i32) (result i32) (memory.grow (local.get 0)))
+            (func (export "nan") (result f32) (f32.add (f32.const -nan:0x1234) (f32.const 1)))
+            (func (export "nans") (result v128)
+              (f32x4.add (v128.const f32x4 -nan:0x1234 1 nan:0x1 2) (v128.const f32x4 1 1 1 1)))
+            (func (export "fill") (param i32) (memory.fill (i32.const 0) (i32.const 0) (local.get 0)))
+            (func (export "locals") (local i64 i64 i64 i64))
+            (table $t 2 funcref)
+            (func (export "grow_table") (param i32) (result i32)
+              (table.grow $t (ref.null func) (local.get 0)))
+            "#,
+        )
+        .unwrap();
+        let mut config = Config {
+            max_call_depth: 10,
+            max_memory_pages: 2,
+            max_locals: 3,
+            max_table_elements: 3,
+            canonicalize_nans: true,
+            ..Default::default()
+        };
+        config.costs.set("br", 10);
+        let mut store = Store::with_config(config);
+        let instance = store.instantiate(&module, &Imports::new()).unwrap();
+
+        store.set_fuel(Some(100));
+        assert_eq!(
+            trap(store.invoke_export(instance, "spin", &[])),
+            Trap::OutOfFuel
+        );
+        assert!(store.fuel().unwrap() < 10);
+        store.set_fuel(Some(100));
+        let fill = |store: &mut Store, n| store.invoke_export(instance, "fill", &[Value::I32(n)]);
+        assert!(fill(&mut store, 90).is_ok());
+        assert_eq!(trap(fill(&mut store, 90)), Trap::OutOfFuel);
+        store.set_fuel(None);
+
+        assert_eq!(
+            trap(store.invoke_export(instance, "locals", &[])),
+            Trap::ResourceLimit("locals")
+        );
+        let grow_table =
+            |store: &mut Store, n| store.invoke_export(instance, "grow_table", &[Value::I32(n)]);
+        assert_eq!(grow_table(&mut store, 2).unwrap(), [Value::I32(-1)]);
+        assert_eq!(grow_table(&mut store, 1).unwrap(), [Value::I32(2)]);
+        let table = Module::parse("(table 4 funcref)").unwrap();
+        assert!(matches!(
+            store.instantiate(&table, &Imports::new()),
+            Err(Error::Trap(_, Trap::ResourceLimit("table elements")))
+        ));
+
+        // Each level takes a function body and an `if`.
+        assert!(store
+            .invoke_export(instance, "rec", &[Value::I32(4)])
+            .is_ok());
+        assert_eq!(
+            trap(store.invoke_export(instance, "rec", &[Value::I32(5)])),
+            Trap::CallStackExhausted
+        );
+        let grow = |store: &mut Store, n| store.invoke_export(instance, "grow", &[Value::I32(n)]);
+        assert_eq!(grow(&mut store, 2).unwrap(), [Value::I32(-1)]);
+        assert_eq!(grow(&mut store, 1).unwrap(), [Value::I32(1)]);
+
+        let [Value::F32(nan)] = store.invoke_export(instance, "nan", &[]).unwrap()[..] else {
+            panic!("expected an f32");
+        };
+        assert_eq!(nan.to_bits(), 0x7FC0_0000);
+        let [Value::V128(nans)] = store.invoke_export(instance, "nans", &[]).unwrap()[..] else {
+            panic!("expected a v128");
+        };
+        assert_eq!(
+            unpack::<f32, 4>(nans).map(f32::to_bits),
+            [0x7FC0_0000, 2f32.to_bits(), 0x7FC0_0000, 3f32.to_bits()]
+        );
+    }
+
+    #[test]
+    fn simd() {
+        let module = Module::parse(
+            r#"
+            (func (export "simd") (result v128 v128 i32 v128 v128 v128)
+              (i32x4.add (v128.const i32x4 1 2 3 4) (v128.const i32x4 10 20 30 40))
+              (i16x8.narrow_i32x4_s (v128.const i32x4 70000 -70000 5 -5) (v128.const i64x2 0 0))
+              (i8x16.bitmask (v128.const i8x16 -1 0 -1 0 0 0 0 0 0 0 0 0 0 0 0 -1))
+              (f32x4.min (v128.const f32x4 1 -0 -5 2) (v128.const f32x4 0 0 3 inf))
+              (i32x4.relaxed_dot_i8x16_i7x16_add_s
+                (v128.const i8x16 2 2 2 2 2 2 2 2 2 2 2 2 2 2 2 2)
+                (v128.const i8x16 3 3 3 3 3 3 3 3 3 3 3 3 3 3 3 3)
+                (v128.const i32x4 1 2 3 4))
+              (i64x2.extmul_high_i32x4_u (v128.const i32x4 0 0 -1 2) (v128.const i32x4 0 0 2 3)))
+            "#,
+        )
+        .unwrap();
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &Imports::new()).unwrap();
+        assert_eq!(
+            store.invoke_export(instance, "simd", &[]).unwrap(),
+            [
+                Value::V128(pack::<i32, 4>([11, 22, 33, 44])),
+                Value::V128(pack::<i16, 8>([32767, -32768, 5, -5, 0, 0, 0, 0])),
+                Value::I32(0b1000_0000_0000_0101),
+                Value::V128(pack::<f32, 4>([0.0, -0.0, -5.0, 2.0])),
+                Value::V128(pack::<i32, 4>([25, 26, 27, 28])),
+                Value::V128(pack::<u64, 2>([0x1_FFFF_FFFE, 6])),
+            ]
+        );
+    }
+
+    #[test]
+    fn atomics() {
+        let module = Module::parse(
+            r#"
+            (memory 1 1 shared)
+            (func (export "rmw") (result i32 i64 i32)
+              (i32.atomic.store (i32.const 0) (i32.const 40))
+              (drop (i32.atomic.rmw.add (i32.const 0) (i32.const 2)))
+              (i32.atomic.rmw8.cmpxchg_u (i32.const 0) (i32.const 0x12a) (i32.const 7))
+              (i64.atomic.load (i32.const 0))
+              (memory.atomic.wait32 (i32.const 0) (i32.const 7) (i64.const 0)))
+            (func (export "unaligned") (result i32) (i32.atomic.load (i32.const 2)))
+            "#,
+        )
+        .unwrap();
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &Imports::new()).unwrap();
+        assert_eq!(
+            store.invoke_export(instance, "rmw", &[]).unwrap(),
+            [Value::I32(42), Value::I64(7), Value::I32(2)]
+        );
+        assert_eq!(
+            trap(store.invoke_export(instance, "unaligned", &[])),
+            Trap::UnalignedAtomic
+        );
+    }
+
+    #[test]
+    fn memory64() {
+        let module = Module::parse(
+            r#"
+            (memory i64 1 2)
+            (table i64 2 funcref)
+            (data (i64.const 8) "hi")
+            (func (export "run") (result i64 i32 i64 i64)
+              (i64.store offset=16 (i64.const 0) (i64.const 5))
+              (i64.load (i64.const 16))
+              (i32.load8_u (i64.const 8))
+              (memory.grow (i64.const 1))
+              (table.size))
+            (func (export "oob") (result i64) (i64.load offset=0x100000000 (i64.const 0)))
+            "#,
+        )
+        .unwrap();
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &Imports::new()).unwrap();
+        assert_eq!(
+            store.invoke_export(instance, "run", &[]).unwrap(),
+            [Value::I64(5), Value::I32(104), Value::I64(1), Value::I64(2)]
+        );
+        assert_eq!(
+            trap(store.invoke_export(instance, "oob", &[])),
+            Trap::MemoryOutOfBounds
+        );
+    }
+
+    #[test]
+    fn multi_memory() {
+        let module = Module::parse(
+            r#"
+            (memory $a 1)
+            (memory $b i64 2)
+            (data $d "xyz")
+            (func (export "run") (result i32 i32 i64 i32)
+              (i32.store8 $b offset=1 (i64.const 0) (i32.const 7))
+              (memory.copy $a $b (i32.const 0) (i64.const 0) (i32.const 8))
+              (memory.init $b $d (i64.const 10) (i32.const 0) (i32.const 3))
+              (i32.load8_u $a (i32.const 1))
+              (i32.load8_u (i32.const 1))
+              (memory.size $b)
+              (i32.load8_u $b (i64.const 12)))
+            "#,
+        )
+        .unwrap();
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &Imports::new()).unwrap();
+        assert_eq!(
+            store.invoke_export(instance, "run", &[]).unwrap(),
+            [Value::I32(7), Value::I32(7), Value::I64(2), Value::I32(122)]
+        );
+    }
+
+    #[test]
+    fn exceptions() {
+        let module = Module::parse(
+            r#"
+            (tag $e (param i32))
+            (tag $f)
+            (func $throw (param i32) (throw $e (local.get 0)))
+            (func (export "catch") (param i32) (result i32)
+              (try (result i32)
+                (do (call $throw (local.get 0)) (i32.const 0))
+                (catch $e (i32.const 1) (i32.add))
+                (catch_all (i32.const -1))))
+            (func (export "rethrow") (result i32)
+              (try (result i32)
+                (do
+                  (try (do (throw $e (i32.const 5)))
+                    (catch_all (rethrow 0)))
+                  (i32.const 0))
+                (catch $e)))
+            (func (export "delegate") (result i32)
+              (try (result i32)
+                (do
+                  (block (try (do (throw $f)) (delegate 1)))
+                  (i32.const 0))
+                (catch $f (i32.const 2))))
+            (func (export "try_table") (result i32)
+              (block $h (result i32)
+                (try_table (catch $e $h) (call $throw (i32.const 9)))
+                (i32.const 0)))
+            (func (export "throw_ref")
+              (block $h (result exnref)
+                (try_table (catch_all_ref $h) (throw $f))
+                (unreachable))
+              (throw_ref))
+            "#,
+        )
+        .unwrap();
+        crate::validate::validate(&module).unwrap();
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &Imports::new()).unwrap();
+        let mut run = |name, args: &[Value]| store.invoke_export(instance, name, args);
+        assert_eq!(run("catch", &[Value::I32(41)]).unwrap(), [Value::I32(42)]);
+        assert_eq!(run("rethrow", &[]).unwrap(), [Value::I32(5)]);
+        assert_eq!(run("delegate", &[]).unwrap(), [Value::I32(2)]);
+        assert_eq!(run("try_table", &[]).unwrap(), [Value::I32(9)]);
+        assert!(matches!(trap(run("throw_ref", &[])), Trap::Exception(_)));
+    }
+
+    #[test]
+    fn tail_calls() {
+        let module = Module::parse(
+            r#"
+            (type $sum (func (param i64 i64) (result i64)))
+            (table funcref (elem $count))
+            (func $count (export "count") (param i64 i64) (result i64)
+              (if (result i64) (i64.eqz (local.get 0))
+                (then (local.get 1))
+                (else
+                  (return_call_indirect (type $sum)
+                    (i64.sub (local.get 0) (i64.const 1))
+                    (i64.add (local.get 1) (local.get 0))
+                    (i32.const 0)))))
+            (func (export "sum") (param i64) (result i64)
+              (return_call $count (local.get 0) (i64.const 0)))
+            "#,
+        )
+        .unwrap();
+        crate::validate::validate(&module).unwrap();
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &Imports::new()).unwrap();
+        assert_eq!(
+            store
+                .invoke_export(instance, "sum", &[Value::I64(100_000)])
+                .unwrap(),
+            [Value::I64(5_000_050_000)]
+        );
+    }
+
+    #[test]
+    fn typed_function_references() {
+        let module = Module::parse(
+            r#"
+            (type $unop (func (param i32) (result i32)))
+            (func $double (type $unop) (i32.add (local.get 0) (local.get 0)))
+            (elem declare func $double)
+            (func (export "apply") (param i32 (ref null $unop)) (result i32)
+              (local $f (ref $unop))
+              (block $null
+                (local.set $f (br_on_null $null (local.get 1)))
+                (return (call_ref $unop (local.get 0) (local.get $f))))
+              (i32.const -1))
+            (func (export "double") (param i32) (result i32)
+              (return_call_ref $unop (local.get 0) (ref.func $double)))
+            (func (export "force") (param (ref null $unop)) (result i32)
+              (call_ref $unop (i32.const 1) (ref.as_non_null (local.get 0))))
+            "#,
+        )
+        .unwrap();
+        crate::validate::validate(&module).unwrap();
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &Imports::new()).unwrap();
+        let mut invoke = |name, args: &[Value]| store.invoke_export(instance, name, args);
+        assert_eq!(
+            invoke("apply", &[Value::I32(4), Value::FuncRef(None)]).unwrap(),
+            [Value::I32(-1)]
+        );
+        assert_eq!(
+            invoke("double", &[Value::I32(21)]).unwrap(),
+            [Value::I32(42)]
+        );
+        assert!(matches!(
+            invoke("force", &[Value::FuncRef(None)]),
+            Err(Error::Trap(_, Trap::NullReference))
+        ));
+    }
+}
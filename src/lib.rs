@@ -3,9 +3,25 @@ use std::{
     fmt::{Debug, Display},
 };
 
+pub mod builder;
+pub mod callgraph;
+pub mod cfg;
+pub mod component;
+pub mod custom;
+pub mod features;
 pub mod instructions;
+pub mod interpreter;
 pub mod modules;
+pub mod printer;
+pub mod shake;
+pub mod size;
+pub mod sourcemap;
 pub mod types;
+pub mod validate;
+pub mod wasi;
+pub mod wast;
+pub mod wat;
+pub mod wit;
 
 pub(crate) type IB = std::vec::Vec<u8>;
 
@@ -18,6 +34,14 @@ pub enum Error {
     InvalidLimits(Backtrace, u8),
     InvalidFuncType(Backtrace, u8),
     InvalidGlobalType(Backtrace, u8),
+    InvalidCustomSec(Backtrace, String),
+    InvalidSourceMap(Backtrace, String),
+    InvalidWat(Backtrace, String),
+    InvalidModule(Backtrace, String),
+    InvalidComponent(Backtrace, String),
+    InvalidArguments(Backtrace, String),
+    Link(Backtrace, String),
+    Trap(Backtrace, interpreter::Trap),
     EndOfBuffer(Backtrace),
 }
 
@@ -32,6 +56,14 @@ impl Debug for Error {
             | Error::InvalidFuncType(b, _)
             | Error::InvalidGlobalType(b, _)
             | Error::EndOfBuffer(b) => Display::fmt(b, f),
+            Error::InvalidCustomSec(b, msg)
+            | Error::InvalidSourceMap(b, msg)
+            | Error::InvalidWat(b, msg)
+            | Error::InvalidModule(b, msg)
+            | Error::InvalidComponent(b, msg)
+            | Error::InvalidArguments(b, msg)
+            | Error::Link(b, msg) => write!(f, "{msg}\n{b}"),
+            Error::Trap(b, trap) => write!(f, "{trap}\n{b}"),
         }
     }
 }
@@ -47,7 +79,7 @@ impl Parse<&mut IB> for i32 {
     where
         Self: Sized,
     {
-        Ok(data.read_sleb128(32) as i32)
+        Ok(read_sleb(data, 32)? as i32)
     }
 }
 
@@ -56,7 +88,7 @@ impl Parse<&mut IB> for u32 {
     where
         Self: Sized,
     {
-        Ok(data.read_uleb128(32) as u32)
+        Ok(read_uleb(data, 32)? as u32)
     }
 }
 impl Parse<&mut IB> for i64 {
@@ -64,7 +96,7 @@ impl Parse<&mut IB> for i64 {
     where
         Self: Sized,
     {
-        Ok(data.read_sleb128(64))
+        read_sleb(data, 64)
     }
 }
 impl Parse<&mut IB> for u64 {
@@ -72,25 +104,88 @@ impl Parse<&mut IB> for u64 {
     where
         Self: Sized,
     {
-        Ok(data.read_uleb128(64))
+        read_uleb(data, 64)
     }
 }
 
+/// Reads an unsigned LEB128 of at most `bits` bits, rejecting truncated,
+/// overlong and out of range encodings.
+pub(crate) fn read_uleb(data: &mut IB, bits: u32) -> Result<u64, Error> {
+    let mut result = 0;
+    let mut shift = 0;
+    for (i, &byte) in data.iter().enumerate() {
+        let payload = (byte & 0x7F) as u64;
+        if shift + 7 >= bits {
+            if byte & 0x80 != 0 {
+                return Err(malformed("integer representation too long"));
+            }
+            if payload >> (bits - shift) != 0 {
+                return Err(malformed("integer too large"));
+            }
+        }
+        result |= payload << shift;
+        if byte & 0x80 == 0 {
+            data.drain(..=i);
+            return Ok(result);
+        }
+        shift += 7;
+    }
+    Err(Error::EndOfBuffer(Backtrace::capture()))
+}
+
+/// Reads a signed LEB128 of at most `bits` bits, rejecting truncated,
+/// overlong and out of range encodings.
+pub(crate) fn read_sleb(data: &mut IB, bits: u32) -> Result<i64, Error> {
+    let mut result = 0;
+    let mut shift = 0;
+    for (i, &byte) in data.iter().enumerate() {
+        let payload = (byte & 0x7F) as i64;
+        if shift + 7 >= bits {
+            if byte & 0x80 != 0 {
+                return Err(malformed("integer representation too long"));
+            }
+            // The bits past the value's width must all copy its sign bit.
+            let unused = 0x7F & (0x7F << (bits - shift - 1));
+            if payload & unused != 0 && payload & unused != unused {
+                return Err(malformed("integer too large"));
+            }
+        }
+        result |= payload << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                result |= -1 << shift;
+            }
+            data.drain(..=i);
+            return Ok(result);
+        }
+    }
+    Err(Error::EndOfBuffer(Backtrace::capture()))
+}
+
+/// Reads `N` bytes.
+pub(crate) fn read_bytes<const N: usize>(data: &mut IB) -> Result<[u8; N], Error> {
+    if data.len() < N {
+        return Err(Error::EndOfBuffer(Backtrace::capture()));
+    }
+    let mut bytes = [0; N];
+    bytes
+        .iter_mut()
+        .zip(data.drain(..N))
+        .for_each(|(byte, read)| *byte = read);
+    Ok(bytes)
+}
+
+pub(crate) fn malformed(msg: &str) -> Error {
+    Error::InvalidModule(Backtrace::capture(), msg.to_string())
+}
+
 impl Parse<&mut IB> for f32 {
     fn parse(data: &mut IB) -> Result<Self, Error>
     where
         Self: Sized,
     {
-        if data.len() < 4 {
-            return Err(Error::EndOfBuffer(Backtrace::capture()));
-        }
-        let mut drain = data.drain(0..4);
-        Ok(Self::from_le_bytes([
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-        ]))
+        Ok(Self::from_le_bytes(read_bytes(data)?))
     }
 }
 impl Parse<&mut IB> for f64 {
@@ -98,20 +193,7 @@ impl Parse<&mut IB> for f64 {
     where
         Self: Sized,
     {
-        if data.len() < 8 {
-            return Err(Error::EndOfBuffer(Backtrace::capture()));
-        }
-        let mut drain = data.drain(0..8);
-        Ok(Self::from_le_bytes([
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-            drain.next().unwrap(),
-        ]))
+        Ok(Self::from_le_bytes(read_bytes(data)?))
     }
 }
 
@@ -134,12 +216,8 @@ impl Parse<&mut IB> for String {
     where
         Self: Sized,
     {
-        let mut len = data.read_uleb128(32);
-        println!("{len}");
-
-        let buffer = data.drain(..len as usize).collect();
-
-        Ok(String::from_utf8(buffer).unwrap())
+        let buffer = Vec::<u8>::parse(data)?;
+        String::from_utf8(buffer).map_err(|_| malformed("malformed UTF-8 encoding"))
     }
 }
 
@@ -148,8 +226,11 @@ impl Parse<&mut IB> for Vec<u8> {
     where
         Self: Sized,
     {
-        let len = data.read_uleb128(32);
-        Ok(data.drain(..len as usize).collect())
+        let len = u32::parse(&mut *data)? as usize;
+        if len > data.len() {
+            return Err(Error::EndOfBuffer(Backtrace::capture()));
+        }
+        Ok(data.drain(..len).collect())
     }
 }
 
@@ -165,6 +246,81 @@ impl<T1: for<'a> Parse<&'a mut IB>, T2: for<'a> Parse<&'a mut IB>> Parse<&mut IB
     }
 }
 
+pub trait Encode {
+    fn encode(&self, data: &mut IB);
+}
+
+impl Encode for u8 {
+    fn encode(&self, data: &mut IB) {
+        data.push(*self);
+    }
+}
+
+impl Encode for u32 {
+    fn encode(&self, data: &mut IB) {
+        data.write_uleb128(*self as u64);
+    }
+}
+
+impl Encode for i32 {
+    fn encode(&self, data: &mut IB) {
+        data.write_sleb128(*self as i64);
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, data: &mut IB) {
+        data.write_uleb128(*self);
+    }
+}
+
+impl Encode for i64 {
+    fn encode(&self, data: &mut IB) {
+        data.write_sleb128(*self);
+    }
+}
+
+impl Encode for f32 {
+    fn encode(&self, data: &mut IB) {
+        data.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Encode for f64 {
+    fn encode(&self, data: &mut IB) {
+        data.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, data: &mut IB) {
+        data.write_uleb128(self.len() as u64);
+        data.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, data: &mut IB) {
+        self.as_str().encode(data)
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, data: &mut IB) {
+        data.write_uleb128(self.len() as u64);
+        for item in self {
+            item.encode(data);
+        }
+    }
+}
+
+impl<T1: Encode, T2: Encode> Encode for (T1, T2) {
+    fn encode(&self, data: &mut IB) {
+        self.0.encode(data);
+        self.1.encode(data);
+    }
+}
+
 pub trait Buffer {
     fn read_uleb128(&mut self, n: u8) -> u64;
     fn write_uleb128(&mut self, value: u64);
@@ -175,7 +331,7 @@ pub trait Buffer {
 impl Buffer for IB {
     fn read_uleb128(&mut self, n: u8) -> u64 {
         match self.first() {
-            Some(byte) if *byte < 128 && (*byte as u64) < (1 << n as u64) => {
+            Some(byte) if *byte < 128 && (n >= 64 || (*byte as u64) < (1 << n as u64)) => {
                 self.drain(..1).next().unwrap() as u64
             }
             Some(byte) if *byte >= 128 && n > 7 => {
@@ -204,10 +360,12 @@ impl Buffer for IB {
     fn read_sleb128(&mut self, n: u8) -> i64 {
         let byte = self.drain(..1).next();
         match byte {
-            Some(byte) if byte < 64 && (byte as i64) < (1 << (n - 1) as i64) => byte as i64,
+            Some(byte) if byte < 64 && (n > 7 || (byte as i64) < (1 << (n - 1) as i64)) => {
+                byte as i64
+            }
             Some(byte)
                 if (64..128).contains(&byte)
-                    && (byte as i64) >= (128i64 - (2 ^ (n - 1) as i64)) =>
+                    && (n > 7 || (byte as i64) >= (128i64 - (1i64 << (n - 1)))) =>
             {
                 let byte = byte as i64;
                 byte - 128
@@ -242,7 +400,30 @@ impl Buffer for IB {
 
 #[cfg(test)]
 mod leb128 {
-    use crate::Buffer;
+    use crate::{read_sleb, read_uleb, Buffer};
+
+    #[test]
+    fn strict() {
+        assert_eq!(
+            read_uleb(&mut vec![0x80, 0x80, 0x80, 0x80, 0x0f], 32).unwrap(),
+            0xf000_0000
+        );
+        assert_eq!(
+            read_sleb(&mut vec![0xff, 0xff, 0xff, 0xff, 0x07], 32).unwrap(),
+            i32::MAX as i64
+        );
+        assert_eq!(
+            read_sleb(&mut vec![0x80, 0x80, 0x80, 0x80, 0x78], 32).unwrap(),
+            i32::MIN as i64
+        );
+        assert_eq!(read_sleb(&mut vec![0x7f], 64).unwrap(), -1);
+        assert_eq!(read_sleb(&mut vec![0x80, 0x7f], 33).unwrap(), -128);
+        // Too long, unused bits set and truncated.
+        assert!(read_uleb(&mut vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x00], 32).is_err());
+        assert!(read_uleb(&mut vec![0x80, 0x80, 0x80, 0x80, 0x10], 32).is_err());
+        assert!(read_sleb(&mut vec![0xff, 0xff, 0xff, 0xff, 0x4f], 32).is_err());
+        assert!(read_uleb(&mut vec![0x80], 32).is_err());
+    }
 
     #[test]
     fn u32() {
@@ -280,6 +461,8 @@ mod leb128 {
 
         buffer.write_uleb128(u64::MAX);
         assert_eq!(buffer.read_uleb128(64), u64::MAX);
+        buffer.write_uleb128(1);
+        assert_eq!(buffer.read_uleb128(64), 1);
     }
 
     #[test]
@@ -294,5 +477,9 @@ mod leb128 {
         assert_eq!(buffer.read_sleb128(64), i64::MAX);
         buffer.write_sleb128(i64::MIN);
         assert_eq!(buffer.read_sleb128(64), i64::MIN);
+        buffer.write_sleb128(-1);
+        assert_eq!(buffer.read_sleb128(64), -1);
+        buffer.write_sleb128(1);
+        assert_eq!(buffer.read_sleb128(64), 1);
     }
 }
@@ -1,26 +1,33 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use wasm_parse::interpreter::{Imports, Store};
+use wasm_parse::modules::Module;
+use wasm_parse::wasi::{self, WasiCtx};
 use wasm_parse::Parse;
 
-fn main() {
-    let bytes = include_bytes!("../target/wasm32-wasi/debug/wasi-test.wasm");
-    let mut bytes = bytes.to_vec();
-    let module = wasm_parse::modules::Module::parse(&mut bytes).unwrap();
-    for section in module.sections {
-        match section {
-            wasm_parse::modules::Section::Custom(_) => {}
-            wasm_parse::modules::Section::Type(_) => {}
-            wasm_parse::modules::Section::Import(_) => {}
-            wasm_parse::modules::Section::Function(_) => {}
-            wasm_parse::modules::Section::Table(_) => {}
-            wasm_parse::modules::Section::Memory(_) => {}
-            wasm_parse::modules::Section::Global(_) => {}
-            wasm_parse::modules::Section::Export(_) => {}
-            wasm_parse::modules::Section::Start(_) => {}
-            wasm_parse::modules::Section::Element(_) => {}
-            wasm_parse::modules::Section::Code(code) => println!("Code {code:#?}"),
-            wasm_parse::modules::Section::Data(_) => {}
-            wasm_parse::modules::Section::DataCountSection(_) => {}
-            wasm_parse::modules::Section::Unknown(_) => {}
-        }
-    }
-    println!("Remains: {}", bytes.len());
+/// Runs a WASI command, such as `target/wasm32-wasip1/debug/wasi-test.wasm`
+/// built by `cargo build -p wasi-test --target wasm32-wasip1`.
+#[derive(Parser)]
+struct Args {
+    /// The module.
+    path: PathBuf,
+    /// Arguments passed to the program after its name.
+    args: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut bytes = std::fs::read(&args.path).unwrap();
+    let module = Module::parse(&mut bytes).unwrap();
+
+    let mut store = Store::new();
+    let mut imports = Imports::new();
+    WasiCtx::new()
+        .arg(args.path.display().to_string())
+        .args(args.args)
+        .link(&mut store, &mut imports, &module);
+    let instance = store.instantiate(&module, &imports).unwrap();
+    ExitCode::from(wasi::run(&mut store, instance).unwrap() as u8)
 }
@@ -1,9 +1,16 @@
 use core::panic;
+use std::any::Any;
 use std::backtrace::Backtrace;
+use std::fmt::Debug;
+use std::ops::Range;
 
-use crate::instructions::Instr;
-use crate::types::{FuncType, GlobalType, Limits, MemType, RefType, TableType, ValType};
-use crate::{Parse, IB};
+use crate::custom::CustomRegistry;
+use crate::features::WasmFeatures;
+use crate::instructions::{peek_byte, read_byte, Instr};
+use crate::types::{
+    FuncType, GlobalType, Limits, MemType, RecType, RefType, SubType, TableType, ValType,
+};
+use crate::{malformed, read_bytes, Encode, Parse, IB};
 
 pub type TypeIdx = u32;
 pub type FuncIdx = u32;
@@ -14,15 +21,19 @@ pub type ElemIdx = u32;
 pub type DataIdx = u32;
 pub type LocalIdx = u32;
 pub type LabelIdx = u32;
+pub type TagIdx = u32;
+pub type FieldIdx = u32;
 
 #[derive(Debug)]
 pub enum Section {
     Custom(CustomSec),
+    DecodedCustom(DecodedCustomSec),
     Type(TypeSec),
     Import(ImportSec),
     Function(FuncSec),
     Table(TableSec),
     Memory(MemSec),
+    Tag(TagSec),
     Global(GlobalSec),
     Export(ExportSec),
     Start(StartSec),
@@ -30,26 +41,50 @@ pub enum Section {
     Code(CodeSec),
     Data(DataSec),
     DataCountSection(DataCountSec),
-    Unknown(Vec<u8>),
+    Unknown(u8, Vec<u8>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CustomSec(pub String, pub Vec<u8>);
-pub type TypeSec = Vec<FuncType>;
-pub type ImportSec = Vec<Import>;
+
+pub trait CustomPayload: Any + Debug {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any + Debug> CustomPayload for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl dyn CustomPayload {
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+}
+
 #[derive(Debug)]
+pub struct DecodedCustomSec {
+    pub sec: CustomSec,
+    pub payload: Box<dyn CustomPayload>,
+}
+
+pub type TypeSec = Vec<RecType>;
+pub type ImportSec = Vec<Import>;
+#[derive(Debug, Clone, PartialEq)]
 pub struct Import {
     pub module: String,
     pub name: String,
     pub desc: ImportDesc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ImportDesc {
     TypeIdx(TypeIdx),
     TableType(TableType),
     MemType(MemType),
     GlobalType(GlobalType),
+    TagType(TagType),
 }
 
 pub type FuncSec = Vec<TypeIdx>;
@@ -59,24 +94,33 @@ pub type Table = TableType;
 pub type MemSec = Vec<Mem>;
 pub type Mem = MemType;
 
+/// Exception tag: attribute 0 (exception) and the type of its payload, which
+/// must have no results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagType(pub TypeIdx);
+
+pub type TagSec = Vec<Tag>;
+pub type Tag = TagType;
+
 pub type GlobalSec = Vec<Global>;
 pub type Global = (GlobalType, Expr);
 
 pub type ExportSec = Vec<Export>;
 pub type Export = (String, ExportDesc);
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ExportDesc {
     FuncIdx(FuncIdx),
     TableIdx(TableIdx),
     MemIdx(MemIdx),
     GlobalIdx(GlobalIdx),
+    TagIdx(TagIdx),
 }
 
 pub type StartSec = Start;
 pub type Start = FuncIdx;
 
 pub type ElemSec = Vec<Elem>;
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Elem {
     A(Expr, Vec<FuncIdx>),
     B(u8, Vec<FuncIdx>),
@@ -89,19 +133,19 @@ pub enum Elem {
 }
 
 pub type CodeSec = Vec<Code>;
-#[derive(Debug)]
-pub struct Code(u32, Func);
-#[derive(Debug)]
-pub struct Func(Vec<Locals>, Expr);
-#[derive(Debug)]
-pub struct Locals(u32, ValType);
+#[derive(Debug, Clone, PartialEq)]
+pub struct Code(pub u32, pub Func);
+#[derive(Debug, Clone, PartialEq)]
+pub struct Func(pub Vec<Locals>, pub Expr);
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locals(pub u32, pub ValType);
 
-#[derive(Debug)]
-pub struct Expr(Vec<Instr>);
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr(pub Vec<Instr>);
 
 pub type DataSec = Vec<Data>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Data {
     A(Expr, Vec<u8>),
     B(Vec<u8>),
@@ -118,6 +162,471 @@ pub struct Module {
     pub magic: u32,
     pub version: u32,
     pub sections: Vec<Section>,
+    pub(crate) layout: Option<Layout>,
+    /// Custom sections the registry failed to decode, by name. They are kept
+    /// as raw `Section::Custom`.
+    pub custom_errors: Vec<(String, crate::Error)>,
+}
+
+/// Byte ranges in a parsed binary. LEB128s may be padded, as `wasm-ld`
+/// does, so these can be larger than the re-encoded module.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layout {
+    pub len: usize,
+    /// One per section, from its id to the end of its content.
+    pub sections: Vec<Range<usize>>,
+    /// One per segment of the data section.
+    pub datas: Vec<Range<usize>>,
+}
+
+impl Module {
+    /// Where the sections were in the binary the module was parsed from,
+    /// `None` for modules built in memory or whose sections or data segments
+    /// were since added or removed.
+    pub fn layout(&self) -> Option<&Layout> {
+        self.layout.as_ref().filter(|layout| {
+            layout.sections.len() == self.sections.len() && layout.datas.len() == self.datas().len()
+        })
+    }
+
+    /// Forgets the layout, for callers that change sections in place.
+    pub fn clear_layout(&mut self) {
+        self.layout = None;
+    }
+
+    pub fn custom_sec(&self, name: &str) -> Option<&CustomSec> {
+        self.sections.iter().find_map(|section| match section {
+            Section::Custom(custom) if custom.0 == name => Some(custom),
+            Section::DecodedCustom(decoded) if decoded.sec.0 == name => Some(&decoded.sec),
+            _ => None,
+        })
+    }
+
+    /// Defined types in index order, recursion groups flattened.
+    pub fn types(&self) -> Vec<&SubType> {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Type(types) => Some(types.iter().flat_map(|group| &group.0).collect()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn imports(&self) -> &[Import] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Import(imports) => Some(imports.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn funcs(&self) -> &[TypeIdx] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Function(funcs) => Some(funcs.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn tables(&self) -> &[Table] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Table(tables) => Some(tables.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn mems(&self) -> &[Mem] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Memory(mems) => Some(mems.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Tag(tags) => Some(tags.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn globals(&self) -> &[Global] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Global(globals) => Some(globals.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn exports(&self) -> &[Export] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Export(exports) => Some(exports.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn start(&self) -> Option<FuncIdx> {
+        self.sections.iter().find_map(|section| match section {
+            Section::Start(start) => Some(*start),
+            _ => None,
+        })
+    }
+
+    pub fn elems(&self) -> &[Elem] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Element(elems) => Some(elems.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn codes(&self) -> &[Code] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Code(codes) => Some(codes.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn datas(&self) -> &[Data] {
+        self.sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Data(datas) => Some(datas.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn imported_funcs(&self) -> impl Iterator<Item = (&Import, TypeIdx)> {
+        self.imports()
+            .iter()
+            .filter_map(|import| match import.desc {
+                ImportDesc::TypeIdx(ty) => Some((import, ty)),
+                _ => None,
+            })
+    }
+
+    pub fn imported_tables(&self) -> impl Iterator<Item = (&Import, &TableType)> {
+        self.imports()
+            .iter()
+            .filter_map(|import| match &import.desc {
+                ImportDesc::TableType(ty) => Some((import, ty)),
+                _ => None,
+            })
+    }
+
+    pub fn imported_mems(&self) -> impl Iterator<Item = (&Import, &MemType)> {
+        self.imports()
+            .iter()
+            .filter_map(|import| match &import.desc {
+                ImportDesc::MemType(ty) => Some((import, ty)),
+                _ => None,
+            })
+    }
+
+    pub fn imported_globals(&self) -> impl Iterator<Item = (&Import, &GlobalType)> {
+        self.imports()
+            .iter()
+            .filter_map(|import| match &import.desc {
+                ImportDesc::GlobalType(ty) => Some((import, ty)),
+                _ => None,
+            })
+    }
+
+    pub fn imported_tags(&self) -> impl Iterator<Item = (&Import, &TagType)> {
+        self.imports()
+            .iter()
+            .filter_map(|import| match &import.desc {
+                ImportDesc::TagType(ty) => Some((import, ty)),
+                _ => None,
+            })
+    }
+
+    /// Type index of a function, counting imported functions first.
+    pub fn func_type_idx(&self, func: FuncIdx) -> Option<TypeIdx> {
+        let imported = self.imported_funcs().count();
+        match (func as usize).checked_sub(imported) {
+            Some(idx) => self.funcs().get(idx).copied(),
+            None => self.imported_funcs().nth(func as usize).map(|(_, ty)| ty),
+        }
+    }
+
+    pub fn func_type(&self, func: FuncIdx) -> Option<&FuncType> {
+        self.types().get(self.func_type_idx(func)? as usize)?.func()
+    }
+
+    pub fn custom_payload<T: Any>(&self, name: &str) -> Option<&T> {
+        self.sections.iter().find_map(|section| match section {
+            Section::DecodedCustom(decoded) if decoded.sec.0 == name => {
+                decoded.payload.downcast_ref()
+            }
+            _ => None,
+        })
+    }
+
+    /// Parses a binary module that may only use the proposals enabled in `features`.
+    pub fn parse_with_features(
+        data: &mut Vec<u8>,
+        features: &WasmFeatures,
+    ) -> Result<Self, crate::Error> {
+        let module = Self::parse(data)?;
+        features.check(&module)?;
+        Ok(module)
+    }
+}
+
+impl Encode for CustomSec {
+    fn encode(&self, data: &mut IB) {
+        self.0.encode(data);
+        data.extend_from_slice(&self.1);
+    }
+}
+
+impl Encode for Expr {
+    fn encode(&self, data: &mut IB) {
+        for instr in &self.0 {
+            instr.encode(data);
+        }
+        data.push(0x0B);
+    }
+}
+
+impl Encode for Import {
+    fn encode(&self, data: &mut IB) {
+        self.module.encode(data);
+        self.name.encode(data);
+        self.desc.encode(data);
+    }
+}
+
+impl Encode for ImportDesc {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::TypeIdx(idx) => {
+                data.push(0x00);
+                idx.encode(data);
+            }
+            Self::TableType(ty) => {
+                data.push(0x01);
+                ty.encode(data);
+            }
+            Self::MemType(ty) => {
+                data.push(0x02);
+                ty.encode(data);
+            }
+            Self::GlobalType(ty) => {
+                data.push(0x03);
+                ty.encode(data);
+            }
+            Self::TagType(ty) => {
+                data.push(0x04);
+                ty.encode(data);
+            }
+        }
+    }
+}
+
+impl Encode for ExportDesc {
+    fn encode(&self, data: &mut IB) {
+        let (kind, idx) = match self {
+            Self::FuncIdx(idx) => (0x00, idx),
+            Self::TableIdx(idx) => (0x01, idx),
+            Self::MemIdx(idx) => (0x02, idx),
+            Self::GlobalIdx(idx) => (0x03, idx),
+            Self::TagIdx(idx) => (0x04, idx),
+        };
+        data.push(kind);
+        idx.encode(data);
+    }
+}
+
+impl Encode for TagType {
+    fn encode(&self, data: &mut IB) {
+        data.push(0x00);
+        self.0.encode(data);
+    }
+}
+
+impl Encode for Elem {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::A(offset, funcs) => {
+                0u32.encode(data);
+                offset.encode(data);
+                funcs.encode(data);
+            }
+            Self::B(kind, funcs) => {
+                1u32.encode(data);
+                data.push(*kind);
+                funcs.encode(data);
+            }
+            Self::C(table, offset, kind, funcs) => {
+                2u32.encode(data);
+                table.encode(data);
+                offset.encode(data);
+                data.push(*kind);
+                funcs.encode(data);
+            }
+            Self::D(kind, funcs) => {
+                3u32.encode(data);
+                data.push(*kind);
+                funcs.encode(data);
+            }
+            Self::E(offset, exprs) => {
+                4u32.encode(data);
+                offset.encode(data);
+                exprs.encode(data);
+            }
+            Self::F(ty, exprs) => {
+                5u32.encode(data);
+                ty.encode(data);
+                exprs.encode(data);
+            }
+            Self::G(table, offset, ty, exprs) => {
+                6u32.encode(data);
+                table.encode(data);
+                offset.encode(data);
+                ty.encode(data);
+                exprs.encode(data);
+            }
+            Self::H(ty, exprs) => {
+                7u32.encode(data);
+                ty.encode(data);
+                exprs.encode(data);
+            }
+        }
+    }
+}
+
+impl Encode for Data {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::A(offset, bytes) => {
+                0u32.encode(data);
+                offset.encode(data);
+                bytes.encode(data);
+            }
+            Self::B(bytes) => {
+                1u32.encode(data);
+                bytes.encode(data);
+            }
+            Self::C(mem, offset, bytes) => {
+                2u32.encode(data);
+                mem.encode(data);
+                offset.encode(data);
+                bytes.encode(data);
+            }
+        }
+    }
+}
+
+impl Encode for Locals {
+    fn encode(&self, data: &mut IB) {
+        self.0.encode(data);
+        self.1.encode(data);
+    }
+}
+
+impl Encode for Func {
+    fn encode(&self, data: &mut IB) {
+        self.0.encode(data);
+        self.1.encode(data);
+    }
+}
+
+impl Encode for Code {
+    fn encode(&self, data: &mut IB) {
+        let mut body = IB::new();
+        self.1.encode(&mut body);
+        (body.len() as u32).encode(data);
+        data.extend(body);
+    }
+}
+
+impl Section {
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Custom(_) | Self::DecodedCustom(_) => 0,
+            Self::Type(_) => 1,
+            Self::Import(_) => 2,
+            Self::Function(_) => 3,
+            Self::Table(_) => 4,
+            Self::Memory(_) => 5,
+            Self::Global(_) => 6,
+            Self::Export(_) => 7,
+            Self::Start(_) => 8,
+            Self::Element(_) => 9,
+            Self::Code(_) => 10,
+            Self::Data(_) => 11,
+            Self::DataCountSection(_) => 12,
+            Self::Tag(_) => 13,
+            Self::Unknown(id, _) => *id,
+        }
+    }
+
+    pub fn encode_content(&self, data: &mut IB) {
+        match self {
+            Self::Custom(custom) => custom.encode(data),
+            Self::DecodedCustom(decoded) => decoded.sec.encode(data),
+            Self::Type(types) => types.encode(data),
+            Self::Import(imports) => imports.encode(data),
+            Self::Function(funcs) => funcs.encode(data),
+            Self::Table(tables) => tables.encode(data),
+            Self::Memory(mems) => mems.encode(data),
+            Self::Global(globals) => globals.encode(data),
+            Self::Export(exports) => exports.encode(data),
+            Self::Start(start) => start.encode(data),
+            Self::Element(elems) => elems.encode(data),
+            Self::Code(codes) => codes.encode(data),
+            Self::Data(datas) => datas.encode(data),
+            Self::DataCountSection(count) => count.encode(data),
+            Self::Tag(tags) => tags.encode(data),
+            Self::Unknown(_, bytes) => data.extend_from_slice(bytes),
+        }
+    }
+}
+
+impl Encode for Section {
+    fn encode(&self, data: &mut IB) {
+        let mut content = IB::new();
+        self.encode_content(&mut content);
+        data.push(self.id());
+        (content.len() as u32).encode(data);
+        data.extend(content);
+    }
+}
+
+impl Encode for Module {
+    fn encode(&self, data: &mut IB) {
+        data.extend_from_slice(&self.magic.to_le_bytes());
+        data.extend_from_slice(&self.version.to_le_bytes());
+        for section in &self.sections {
+            section.encode(data);
+        }
+    }
 }
 
 impl Parse<&mut IB> for Expr {
@@ -127,13 +636,11 @@ impl Parse<&mut IB> for Expr {
     {
         let mut buffer = Vec::new();
         loop {
-            if *data.first().unwrap() == 0x0B {
-                println!("End");
+            if peek_byte(data)? == 0x0B {
                 let _ = data.drain(..1).next().unwrap();
                 break;
             }
             let i = Instr::parse(data)?;
-            println!("Instr: {i:?}, until end: {}", data.len());
             buffer.push(i);
         }
         Ok(Self(buffer))
@@ -166,13 +673,31 @@ impl Parse<&mut IB> for ImportDesc {
             1 => Self::TableType(TableType::parse(data)?),
             2 => Self::MemType(Limits::parse(data)?),
             3 => Self::GlobalType(GlobalType::parse(data)?),
-            _ => {
-                unimplemented!("{byte}")
-            }
+            4 => Self::TagType(TagType::parse(data)?),
+            _ => return Err(malformed("malformed import kind")),
         })
     }
 }
 
+impl Parse<&mut IB> for TagType {
+    fn parse(data: &mut IB) -> Result<Self, crate::Error>
+    where
+        Self: Sized,
+    {
+        if data.is_empty() {
+            return Err(crate::Error::EndOfBuffer(Backtrace::capture()));
+        }
+        let attribute = data.drain(..1).next().unwrap();
+        if attribute != 0 {
+            return Err(crate::Error::InvalidModule(
+                Backtrace::capture(),
+                format!("invalid tag attribute {attribute}"),
+            ));
+        }
+        Ok(Self(u32::parse(data)?))
+    }
+}
+
 impl Parse<&mut IB> for Elem {
     fn parse(data: &mut IB) -> Result<Self, crate::Error>
     where
@@ -184,20 +709,14 @@ impl Parse<&mut IB> for Elem {
         let byte = u32::parse(data)?;
         Ok(match byte {
             0 => Self::A(Expr::parse(data)?, Vec::parse(data)?),
-            1 => {
-                let byte = data.drain(..1).next().unwrap();
-                Self::B(byte, Vec::parse(data)?)
-            }
+            1 => Self::B(read_byte(data)?, Vec::parse(data)?),
             2 => {
                 let a = u32::parse(data)?;
                 let b = Expr::parse(data)?;
-                let c = data.drain(..1).next().unwrap();
+                let c = read_byte(data)?;
                 Self::C(a, b, c, Vec::parse(data)?)
             }
-            3 => {
-                let byte = data.drain(..1).next().unwrap();
-                Self::D(byte, Vec::parse(data)?)
-            }
+            3 => Self::D(read_byte(data)?, Vec::parse(data)?),
             4 => Self::E(Expr::parse(&mut *data)?, Vec::parse(data)?),
             5 => Self::F(RefType::parse(&mut *data)?, Vec::parse(data)?),
             6 => Self::G(
@@ -207,9 +726,7 @@ impl Parse<&mut IB> for Elem {
                 Vec::parse(data)?,
             ),
             7 => Self::H(RefType::parse(&mut *data)?, Vec::parse(data)?),
-            _ => {
-                unimplemented!("{byte}")
-            }
+            _ => return Err(malformed("malformed elements segment kind")),
         })
     }
 }
@@ -228,7 +745,7 @@ impl Parse<&mut IB> for Data {
             0 => Self::A(Expr::parse(data)?, Vec::parse(data)?),
             1 => Self::B(Vec::parse(data)?),
             2 => Self::C(u32::parse(data)?, Expr::parse(data)?, Vec::parse(data)?),
-            _ => unimplemented!("{byte}"),
+            _ => return Err(malformed("malformed data segment kind")),
         })
     }
 }
@@ -247,7 +764,11 @@ impl Parse<&mut IB> for Func {
     where
         Self: Sized,
     {
-        Ok(Self(Vec::parse(&mut *data)?, Expr::parse(data)?))
+        let locals: Vec<Locals> = Vec::parse(&mut *data)?;
+        if locals.iter().map(|locals| locals.0 as u64).sum::<u64>() > u32::MAX as u64 {
+            return Err(malformed("too many locals"));
+        }
+        Ok(Self(locals, Expr::parse(data)?))
     }
 }
 impl Parse<&mut IB> for Code {
@@ -255,7 +776,66 @@ impl Parse<&mut IB> for Code {
     where
         Self: Sized,
     {
-        Ok(Self(u32::parse(data)?, Func::parse(data)?))
+        let size = u32::parse(&mut *data)?;
+        let mut body = split(data, size)?;
+        let func = Func::parse(&mut body)?;
+        if !body.is_empty() {
+            return Err(malformed("section size mismatch"));
+        }
+        Ok(Self(size, func))
+    }
+}
+
+/// Takes the next `size` bytes.
+fn split(data: &mut IB, size: u32) -> Result<IB, crate::Error> {
+    if size as usize > data.len() {
+        return Err(crate::Error::EndOfBuffer(Backtrace::capture()));
+    }
+    Ok(data.drain(..size as usize).collect())
+}
+
+impl Section {
+    /// Parses the content of a section with the given id, which must use up
+    /// all of `content`.
+    fn parse_content(id: u8, content: &mut IB) -> Result<Self, crate::Error> {
+        let section = match id {
+            0 => {
+                let name = String::parse(&mut *content)?;
+                Self::Custom(CustomSec(name, std::mem::take(content)))
+            }
+            1 => Self::Type(TypeSec::parse(content)?),
+            2 => Self::Import(ImportSec::parse(content)?),
+            3 => Self::Function(FuncSec::parse(content)?),
+            4 => Self::Table(TableSec::parse(content)?),
+            5 => Self::Memory(MemSec::parse(content)?),
+            6 => Self::Global(GlobalSec::parse(content)?),
+            7 => Self::Export(ExportSec::parse(content)?),
+            8 => Self::Start(StartSec::parse(content)?),
+            9 => Self::Element(ElemSec::parse(content)?),
+            10 => Self::Code(CodeSec::parse(content)?),
+            11 => Self::Data(DataSec::parse(content)?),
+            12 => Self::DataCountSection(DataCountSec::parse(content)?),
+            13 => Self::Tag(TagSec::parse(content)?),
+            _ => return Err(malformed("malformed section id")),
+        };
+        if !content.is_empty() {
+            return Err(malformed("section size mismatch"));
+        }
+        Ok(section)
+    }
+
+    /// Position of the section in the order the binary format requires,
+    /// `None` for custom sections, which may appear anywhere.
+    fn order(&self) -> Option<u8> {
+        match self.id() {
+            0 => None,
+            // Tags go between memories and globals, data counts before code.
+            13 => Some(6),
+            id @ 1..=5 => Some(id),
+            id @ 6..=9 => Some(id + 1),
+            12 => Some(11),
+            id => Some(id + 2),
+        }
     }
 }
 
@@ -264,35 +844,31 @@ impl Parse<&mut IB> for Section {
     where
         Self: Sized,
     {
-        let id = data.drain(..1).next().unwrap();
-        println!("Id: {id}");
-        let size = u32::parse(data)?;
-        println!("Size: {size}");
-        println!("Bytes: {}", data.len());
+        let id = read_byte(data)?;
+        let size = u32::parse(&mut *data)?;
+        Self::parse_content(id, &mut split(data, size)?)
+    }
+}
 
-        Ok(match id {
-            0 => {
-                let current_size = data.len();
-                let name = String::parse(data)?;
-                let readed = current_size - data.len();
-                let data = data.drain(..(size as usize) - readed).collect();
-                Self::Custom(CustomSec(name, data))
-            }
-            1 => Self::Type(TypeSec::parse(data)?),
-            2 => Self::Import(ImportSec::parse(data)?),
-            3 => Self::Function(FuncSec::parse(data)?),
-            4 => Self::Table(TableSec::parse(data)?),
-            5 => Self::Memory(MemSec::parse(data)?),
-            6 => Self::Global(GlobalSec::parse(data)?),
-            7 => Self::Export(ExportSec::parse(data)?),
-            8 => Self::Start(StartSec::parse(data)?),
-            9 => Self::Element(ElemSec::parse(data)?),
-            10 => Self::Code(CodeSec::parse(data)?),
-            11 => Self::Data(DataSec::parse(data)?),
-            12 => Self::DataCountSection(DataCountSec::parse(data)?),
-            _ => Self::Unknown(data.drain(..size as usize).collect()),
-        })
+/// Parses the content of a data section, recording where each segment was
+/// in a binary in which the content starts at `offset`.
+fn data_sec(
+    content: &mut IB,
+    offset: usize,
+    ranges: &mut Vec<Range<usize>>,
+) -> Result<DataSec, crate::Error> {
+    let len = offset + content.len();
+    let count = u32::parse(&mut *content)?;
+    let mut datas = Vec::new();
+    for _ in 0..count {
+        let start = len - content.len();
+        datas.push(Data::parse(&mut *content)?);
+        ranges.push(start..len - content.len());
+    }
+    if !content.is_empty() {
+        return Err(malformed("section size mismatch"));
     }
+    Ok(datas)
 }
 
 impl Parse<&mut IB> for ExportDesc {
@@ -309,9 +885,8 @@ impl Parse<&mut IB> for ExportDesc {
             1 => Self::TableIdx(u32::parse(data)?),
             2 => Self::MemIdx(u32::parse(data)?),
             3 => Self::GlobalIdx(u32::parse(data)?),
-            _ => {
-                unimplemented!("{byte}")
-            }
+            4 => Self::TagIdx(u32::parse(data)?),
+            _ => return Err(malformed("malformed export kind")),
         })
     }
 }
@@ -321,41 +896,129 @@ impl Parse<&mut IB> for Module {
     where
         Self: Sized,
     {
-        let magic;
-        let version;
-        {
-            let mut drain = data.drain(0..8);
-            magic = u32::from_le_bytes([
-                drain.next().unwrap(),
-                drain.next().unwrap(),
-                drain.next().unwrap(),
-                drain.next().unwrap(),
-            ]);
-            println!("Magic: {magic}");
-            version = u32::from_le_bytes([
-                drain.next().unwrap(),
-                drain.next().unwrap(),
-                drain.next().unwrap(),
-                drain.next().unwrap(),
-            ]);
-            println!("Version: {version}");
+        Self::parse((data, &CustomRegistry::new()))
+    }
+}
+
+impl Parse<(&mut IB, &CustomRegistry)> for Module {
+    fn parse((data, registry): (&mut IB, &CustomRegistry)) -> Result<Self, crate::Error>
+    where
+        Self: Sized,
+    {
+        let len = data.len();
+        let magic = read_bytes(data).map_err(|_| malformed("unexpected end"))?;
+        if u32::from_be_bytes(magic) != MAGIC {
+            return Err(malformed("magic header not detected"));
+        }
+        let version = read_bytes(data).map_err(|_| malformed("unexpected end"))?;
+        if u32::from_be_bytes(version) != VERSION {
+            return Err(malformed("unknown binary version"));
         }
 
         let mut sections = Vec::new();
+        let mut layout = Layout {
+            len,
+            ..Default::default()
+        };
 
-        loop {
-            if data.is_empty() {
-                break;
+        let mut custom_errors = Vec::new();
+        let mut last = 0;
+        while !data.is_empty() {
+            let start = len - data.len();
+            let id = read_byte(data)?;
+            let size = u32::parse(&mut *data)?;
+            let mut content = split(data, size)?;
+            let section = match id {
+                11 => {
+                    let offset = len - data.len() - content.len();
+                    Section::Data(data_sec(&mut content, offset, &mut layout.datas)?)
+                }
+                _ => match Section::parse_content(id, &mut content)? {
+                    Section::Custom(custom) => {
+                        let name = custom.0.clone();
+                        let (section, err) = registry.decode(custom);
+                        custom_errors.extend(err.map(|err| (name, err)));
+                        section
+                    }
+                    section => section,
+                },
+            };
+            if let Some(order) = section.order() {
+                if order <= last {
+                    return Err(malformed("unexpected content after last section"));
+                }
+                last = order;
             }
-            let section = Section::parse(data)?;
-            println!("Section: {section:?}");
+            layout.sections.push(start..len - data.len());
             sections.push(section);
         }
 
-        Ok(Self {
-            magic,
-            version,
+        let module = Self {
+            magic: u32::from_le_bytes(magic),
+            version: u32::from_le_bytes(version),
             sections,
-        })
+            layout: Some(layout),
+            custom_errors,
+        };
+        if module.funcs().len() != module.codes().len() {
+            return Err(malformed(
+                "function and code section have inconsistent lengths",
+            ));
+        }
+        let data_count = module.sections.iter().find_map(|section| match section {
+            Section::DataCountSection(count) => Some(*count as usize),
+            _ => None,
+        });
+        if data_count.is_some_and(|count| count != module.datas().len()) {
+            return Err(malformed(
+                "data count and data section have inconsistent lengths",
+            ));
+        }
+        Ok(module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Module;
+    use crate::Parse;
+
+    fn parse(bytes: &[u8]) -> Result<Module, crate::Error> {
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        module.extend_from_slice(bytes);
+        Module::parse(&mut module)
+    }
+
+    #[test]
+    fn malformed() {
+        for bytes in [&b"\0asn\x01\0\0\0"[..], b"\0asm\x02\0\0\0", b"\0as", b""] {
+            assert!(Module::parse(&mut bytes.to_vec()).is_err(), "{bytes:?}");
+        }
+        for bytes in [
+            // A type section declaring more bytes than there are.
+            &b"\x01\x05\x01\x60\0\0"[..],
+            // A type section with bytes left over.
+            b"\x01\x05\x01\x60\0\0\0",
+            b"\x0e\x01\0",
+            // A code body declared one byte longer than its function.
+            b"\x01\x04\x01\x60\0\0\x03\x02\x01\0\x0a\x05\x01\x03\0\x0b\x0b",
+            // The same section twice.
+            b"\x01\x04\x01\x60\0\0\x01\x04\x01\x60\0\0",
+            // A function without a body.
+            b"\x01\x04\x01\x60\0\0\x03\x02\x01\0",
+            b"\x02\x06\x01\0\0\x05\0\0",
+            b"\x07\x04\x01\0\x05\0",
+            b"\x09\x02\x01\x08",
+            b"\x0b\x02\x01\x03",
+            // A 32-bit integer in six bytes.
+            b"\x03\x07\x01\x80\x80\x80\x80\x80\0",
+            // A name that is not UTF-8.
+            b"\0\x02\x01\xff",
+        ] {
+            assert!(parse(bytes).is_err(), "{bytes:?}");
+        }
+        // Custom sections can go anywhere.
+        let module = parse(b"\0\x02\x01a\x01\x04\x01\x60\0\0\0\x02\x01b").unwrap();
+        assert_eq!(module.sections.len(), 3);
     }
 }
@@ -0,0 +1,1769 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::custom::{NameMap, NameSec};
+use crate::instructions::{BlockType, Catch, Instr, MemArg};
+use crate::modules::{
+    Code, CustomSec, Data, Elem, Export, ExportDesc, Expr, Global, Import, ImportDesc, Module,
+    Section,
+};
+use crate::types::{
+    CompType, FieldType, FuncType, GlobalType, HeapType, Limits, NumType, RefType, StorageType,
+    SubType, TableType, ValType, VecType,
+};
+use crate::{Encode, Parse, IB};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintOptions {
+    /// Print instructions as nested s-expressions instead of a flat sequence.
+    pub folded: bool,
+    /// Annotate items and instructions with their byte offset in the encoded module.
+    pub offsets: bool,
+}
+
+pub fn print(module: &Module, options: PrintOptions) -> String {
+    let mut printer = Printer {
+        module,
+        options,
+        names: Names::new(module),
+        out: String::new(),
+        indent: 0,
+    };
+    printer.module();
+    printer.out
+}
+
+#[derive(Default)]
+struct Names {
+    module: Option<String>,
+    funcs: HashMap<u32, String>,
+    locals: HashMap<u32, HashMap<u32, String>>,
+    types: HashMap<u32, String>,
+    tables: HashMap<u32, String>,
+    mems: HashMap<u32, String>,
+    globals: HashMap<u32, String>,
+    elems: HashMap<u32, String>,
+    datas: HashMap<u32, String>,
+    fields: HashMap<u32, HashMap<u32, String>>,
+    tags: HashMap<u32, String>,
+}
+
+impl Names {
+    fn new(module: &Module) -> Self {
+        let Some(Ok(names)) = module.custom_sec(NameSec::NAME).map(NameSec::parse) else {
+            return Self::default();
+        };
+        Self {
+            module: names.module.as_deref().and_then(sanitize),
+            funcs: ids(&names.funcs),
+            locals: names
+                .locals
+                .iter()
+                .map(|(func, locals)| (*func, ids(locals)))
+                .collect(),
+            types: ids(&names.types),
+            tables: ids(&names.tables),
+            mems: ids(&names.mems),
+            globals: ids(&names.globals),
+            elems: ids(&names.elems),
+            datas: ids(&names.datas),
+            fields: names
+                .fields
+                .iter()
+                .map(|(ty, fields)| (*ty, ids(fields)))
+                .collect(),
+            tags: ids(&names.tags),
+        }
+    }
+}
+
+fn sanitize(name: &str) -> Option<String> {
+    if name.is_empty() {
+        return None;
+    }
+    Some(
+        name.chars()
+            .map(|c| match c {
+                '0'..='9' | 'a'..='z' | 'A'..='Z' => c,
+                '!' | '#' | '$' | '%' | '&' | '\'' | '*' | '+' | '-' | '.' | '/' | ':' | '<'
+                | '=' | '>' | '?' | '@' | '\\' | '^' | '_' | '`' | '|' | '~' => c,
+                _ => '_',
+            })
+            .collect(),
+    )
+}
+
+fn ids(names: &NameMap) -> HashMap<u32, String> {
+    let mut used = HashSet::new();
+    let mut ids = HashMap::new();
+    for (idx, name) in names {
+        let Some(mut id) = sanitize(name) else {
+            continue;
+        };
+        if used.contains(&id) {
+            id = format!("{id}_{idx}");
+        }
+        if used.insert(id.clone()) {
+            ids.insert(*idx, id);
+        }
+    }
+    ids
+}
+
+pub(crate) fn valtype(ty: &ValType) -> String {
+    match ty {
+        ValType::NumType(NumType::I32) => "i32".to_string(),
+        ValType::NumType(NumType::I64) => "i64".to_string(),
+        ValType::NumType(NumType::F32) => "f32".to_string(),
+        ValType::NumType(NumType::F64) => "f64".to_string(),
+        ValType::VecType(VecType::V128) => "v128".to_string(),
+        ValType::RefType(ty) => reftype(ty),
+    }
+}
+
+pub(crate) fn reftype(ty: &RefType) -> String {
+    match ty {
+        RefType(true, HeapType::None) => "nullref".to_string(),
+        RefType(true, HeapType::NoFunc) => "nullfuncref".to_string(),
+        RefType(true, HeapType::NoExtern) => "nullexternref".to_string(),
+        RefType(true, HeapType::NoExn) => "nullexnref".to_string(),
+        RefType(true, HeapType::Type(_)) => format!("(ref null {})", heaptype(&ty.1)),
+        RefType(true, ht) => format!("{}ref", heaptype(ht)),
+        RefType(false, ht) => format!("(ref {})", heaptype(ht)),
+    }
+}
+
+fn heaptype(ty: &HeapType) -> String {
+    match ty {
+        HeapType::Func => "func".to_string(),
+        HeapType::Extern => "extern".to_string(),
+        HeapType::Exn => "exn".to_string(),
+        HeapType::Any => "any".to_string(),
+        HeapType::Eq => "eq".to_string(),
+        HeapType::I31 => "i31".to_string(),
+        HeapType::Struct => "struct".to_string(),
+        HeapType::Array => "array".to_string(),
+        HeapType::None => "none".to_string(),
+        HeapType::NoFunc => "nofunc".to_string(),
+        HeapType::NoExtern => "noextern".to_string(),
+        HeapType::NoExn => "noexn".to_string(),
+        HeapType::Type(idx) => idx.to_string(),
+    }
+}
+
+fn field_type(ty: &FieldType) -> String {
+    let storage = match ty.0 {
+        StorageType::ValType(ty) => valtype(&ty),
+        StorageType::I8 => "i8".to_string(),
+        StorageType::I16 => "i16".to_string(),
+    };
+    match ty.1 {
+        true => format!("(mut {storage})"),
+        false => storage,
+    }
+}
+
+pub(crate) fn f32_text(value: f32) -> String {
+    let bits = value.to_bits();
+    let sign = if bits >> 31 == 1 { "-" } else { "" };
+    if value.is_nan() {
+        let payload = bits & 0x007F_FFFF;
+        if payload == 0x0040_0000 {
+            format!("{sign}nan")
+        } else {
+            format!("{sign}nan:0x{payload:x}")
+        }
+    } else if value.is_infinite() {
+        format!("{sign}inf")
+    } else {
+        format!("{value:?}")
+    }
+}
+
+pub(crate) fn f64_text(value: f64) -> String {
+    let bits = value.to_bits();
+    let sign = if bits >> 63 == 1 { "-" } else { "" };
+    if value.is_nan() {
+        let payload = bits & 0x000F_FFFF_FFFF_FFFF;
+        if payload == 0x0008_0000_0000_0000 {
+            format!("{sign}nan")
+        } else {
+            format!("{sign}nan:0x{payload:x}")
+        }
+    } else if value.is_infinite() {
+        format!("{sign}inf")
+    } else {
+        format!("{value:?}")
+    }
+}
+
+pub(crate) fn string(bytes: &[u8]) -> String {
+    let mut out = String::from("\"");
+    for byte in bytes {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7E => out.push(*byte as char),
+            _ => write!(out, "\\{byte:02x}").unwrap(),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn encoded_len<T: Encode + ?Sized>(item: &T) -> usize {
+    let mut data = IB::new();
+    item.encode(&mut data);
+    data.len()
+}
+
+/// Natural alignment exponent of a memory instruction, used to omit `align=` when it is the default.
+pub(crate) fn natural_align(instr: &Instr) -> u32 {
+    match instr {
+        Instr::I32load8S(_)
+        | Instr::I32Load8_u(_)
+        | Instr::I64Load8_s(_)
+        | Instr::I64Load8_u(_)
+        | Instr::I32Store8(_)
+        | Instr::I64Store8(_)
+        | Instr::V128_Load_8_Splat(_)
+        | Instr::V128_Load_8_Lane(_, _)
+        | Instr::V128_Store_8_Lane(_, _)
+        | Instr::I32AtomicLoad8U(_)
+        | Instr::I64AtomicLoad8U(_)
+        | Instr::I32AtomicStore8(_)
+        | Instr::I64AtomicStore8(_)
+        | Instr::I32AtomicRmw8AddU(_)
+        | Instr::I64AtomicRmw8AddU(_)
+        | Instr::I32AtomicRmw8SubU(_)
+        | Instr::I64AtomicRmw8SubU(_)
+        | Instr::I32AtomicRmw8AndU(_)
+        | Instr::I64AtomicRmw8AndU(_)
+        | Instr::I32AtomicRmw8OrU(_)
+        | Instr::I64AtomicRmw8OrU(_)
+        | Instr::I32AtomicRmw8XorU(_)
+        | Instr::I64AtomicRmw8XorU(_)
+        | Instr::I32AtomicRmw8XchgU(_)
+        | Instr::I64AtomicRmw8XchgU(_)
+        | Instr::I32AtomicRmw8CmpxchgU(_)
+        | Instr::I64AtomicRmw8CmpxchgU(_) => 0,
+        Instr::I32Load16_s(_)
+        | Instr::I32Load16_u(_)
+        | Instr::I64Load16_s(_)
+        | Instr::I64Load16_u(_)
+        | Instr::I32Store16(_)
+        | Instr::I64Store16(_)
+        | Instr::V128_Load_16_Splat(_)
+        | Instr::V128_Load_16_Lane(_, _)
+        | Instr::V128_Store_16_Lane(_, _)
+        | Instr::I32AtomicLoad16U(_)
+        | Instr::I64AtomicLoad16U(_)
+        | Instr::I32AtomicStore16(_)
+        | Instr::I64AtomicStore16(_)
+        | Instr::I32AtomicRmw16AddU(_)
+        | Instr::I64AtomicRmw16AddU(_)
+        | Instr::I32AtomicRmw16SubU(_)
+        | Instr::I64AtomicRmw16SubU(_)
+        | Instr::I32AtomicRmw16AndU(_)
+        | Instr::I64AtomicRmw16AndU(_)
+        | Instr::I32AtomicRmw16OrU(_)
+        | Instr::I64AtomicRmw16OrU(_)
+        | Instr::I32AtomicRmw16XorU(_)
+        | Instr::I64AtomicRmw16XorU(_)
+        | Instr::I32AtomicRmw16XchgU(_)
+        | Instr::I64AtomicRmw16XchgU(_)
+        | Instr::I32AtomicRmw16CmpxchgU(_)
+        | Instr::I64AtomicRmw16CmpxchgU(_) => 1,
+        Instr::I32Load(_)
+        | Instr::F32Load(_)
+        | Instr::I64Load32_s(_)
+        | Instr::I64Load32_u(_)
+        | Instr::I32Store(_)
+        | Instr::F32Store(_)
+        | Instr::I64Store32(_)
+        | Instr::V128_Load_32_Splat(_)
+        | Instr::V128_Load_32_Zero(_)
+        | Instr::V128_Load_32_Lane(_, _)
+        | Instr::V128_Store_32_Lane(_, _)
+        | Instr::MemoryAtomicNotify(_)
+        | Instr::MemoryAtomicWait32(_)
+        | Instr::I32AtomicLoad(_)
+        | Instr::I64AtomicLoad32U(_)
+        | Instr::I32AtomicStore(_)
+        | Instr::I64AtomicStore32(_)
+        | Instr::I32AtomicRmwAdd(_)
+        | Instr::I64AtomicRmw32AddU(_)
+        | Instr::I32AtomicRmwSub(_)
+        | Instr::I64AtomicRmw32SubU(_)
+        | Instr::I32AtomicRmwAnd(_)
+        | Instr::I64AtomicRmw32AndU(_)
+        | Instr::I32AtomicRmwOr(_)
+        | Instr::I64AtomicRmw32OrU(_)
+        | Instr::I32AtomicRmwXor(_)
+        | Instr::I64AtomicRmw32XorU(_)
+        | Instr::I32AtomicRmwXchg(_)
+        | Instr::I64AtomicRmw32XchgU(_)
+        | Instr::I32AtomicRmwCmpxchg(_)
+        | Instr::I64AtomicRmw32CmpxchgU(_) => 2,
+        Instr::V128_Load(_) | Instr::V128_Store(_) => 4,
+        _ => 3,
+    }
+}
+
+fn is_unary(name: &str) -> bool {
+    let op = name.split('.').nth(1).unwrap_or_default();
+    [
+        "eqz",
+        "clz",
+        "ctz",
+        "popcnt",
+        "abs",
+        "neg",
+        "ceil",
+        "floor",
+        "trunc",
+        "nearest",
+        "sqrt",
+        "wrap",
+        "extend",
+        "convert",
+        "demote",
+        "promote",
+        "reinterpret",
+        "splat",
+        "extract_lane",
+        "not",
+        "any_true",
+        "all_true",
+        "bitmask",
+        "extadd",
+        "relaxed_trunc",
+    ]
+    .iter()
+    .any(|prefix| op.starts_with(prefix))
+}
+
+struct Node<'a> {
+    instr: &'a Instr,
+    offset: usize,
+    children: Vec<Node<'a>>,
+    results: Option<usize>,
+}
+
+struct Printer<'a> {
+    module: &'a Module,
+    options: PrintOptions,
+    names: Names,
+    out: String,
+    indent: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn line(&mut self, text: &str) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+    }
+
+    fn offset(&self, offset: usize) -> String {
+        if self.options.offsets {
+            format!("(;@{offset:x};) ")
+        } else {
+            String::new()
+        }
+    }
+
+    fn annotation(&self, offset: usize) -> String {
+        if self.options.offsets {
+            format!(" (;@{offset:x};)")
+        } else {
+            String::new()
+        }
+    }
+
+    fn id(name: Option<&String>, idx: u32) -> String {
+        match name {
+            Some(name) => format!("${name} (;{idx};)"),
+            None => format!("(;{idx};)"),
+        }
+    }
+
+    fn reference(name: Option<&String>, idx: u32) -> String {
+        match name {
+            Some(name) => format!("${name}"),
+            None => idx.to_string(),
+        }
+    }
+
+    fn func_ref(&self, idx: u32) -> String {
+        Self::reference(self.names.funcs.get(&idx), idx)
+    }
+
+    fn type_ref(&self, idx: u32) -> String {
+        Self::reference(self.names.types.get(&idx), idx)
+    }
+
+    fn table_ref(&self, idx: u32) -> String {
+        Self::reference(self.names.tables.get(&idx), idx)
+    }
+
+    fn mem_ref(&self, idx: u32) -> String {
+        Self::reference(self.names.mems.get(&idx), idx)
+    }
+
+    fn global_ref(&self, idx: u32) -> String {
+        Self::reference(self.names.globals.get(&idx), idx)
+    }
+
+    fn elem_ref(&self, idx: u32) -> String {
+        Self::reference(self.names.elems.get(&idx), idx)
+    }
+
+    fn data_ref(&self, idx: u32) -> String {
+        Self::reference(self.names.datas.get(&idx), idx)
+    }
+
+    fn tag_ref(&self, idx: u32) -> String {
+        Self::reference(self.names.tags.get(&idx), idx)
+    }
+
+    fn local_ref(&self, func: Option<u32>, idx: u32) -> String {
+        let name = func
+            .and_then(|func| self.names.locals.get(&func))
+            .and_then(|locals| locals.get(&idx));
+        Self::reference(name, idx)
+    }
+
+    fn field_ref(&self, ty: u32, idx: u32) -> String {
+        let name = self
+            .names
+            .fields
+            .get(&ty)
+            .and_then(|fields| fields.get(&idx));
+        Self::reference(name, idx)
+    }
+
+    fn func_type(ty: &FuncType) -> String {
+        let mut out = String::new();
+        if !ty.0.is_empty() {
+            out.push_str(" (param");
+            for param in &ty.0 {
+                write!(out, " {}", valtype(param)).unwrap();
+            }
+            out.push(')');
+        }
+        if !ty.1.is_empty() {
+            out.push_str(" (result");
+            for result in &ty.1 {
+                write!(out, " {}", valtype(result)).unwrap();
+            }
+            out.push(')');
+        }
+        out
+    }
+
+    fn func_type_at(&self, idx: u32) -> Option<&'a FuncType> {
+        self.module.types().get(idx as usize)?.func()
+    }
+
+    fn sub_type(&self, idx: u32, ty: &SubType) -> String {
+        let comp = match &ty.2 {
+            CompType::Func(ty) => format!("(func{})", Self::func_type(ty)),
+            CompType::Struct(fields) => {
+                let names = self.names.fields.get(&idx);
+                let mut out = String::from("(struct");
+                for (i, field) in fields.iter().enumerate() {
+                    match names.and_then(|names| names.get(&(i as u32))) {
+                        Some(name) => write!(out, " (field ${name} {})", field_type(field)),
+                        None => write!(out, " (field {})", field_type(field)),
+                    }
+                    .unwrap();
+                }
+                out + ")"
+            }
+            CompType::Array(field) => format!("(array {})", field_type(field)),
+        };
+        if ty.0 && ty.1.is_empty() {
+            return comp;
+        }
+        let mut out = String::from("(sub");
+        if ty.0 {
+            out.push_str(" final");
+        }
+        for idx in &ty.1 {
+            write!(out, " {}", self.type_ref(*idx)).unwrap();
+        }
+        format!("{out} {comp})")
+    }
+
+    fn type_use(&self, idx: u32) -> String {
+        let mut out = format!("(type {})", self.type_ref(idx));
+        if let Some(ty) = self.func_type_at(idx) {
+            out.push_str(&Self::func_type(ty));
+        }
+        out
+    }
+
+    fn limits(limits: &Limits) -> String {
+        let index = if limits.3 { "i64 " } else { "" };
+        let out = match limits.1 {
+            Some(max) => format!("{index}{} {max}", limits.0),
+            None => format!("{index}{}", limits.0),
+        };
+        match limits.2 {
+            true => out + " shared",
+            false => out,
+        }
+    }
+
+    fn table_type(ty: &TableType) -> String {
+        format!("{} {}", Self::limits(&ty.1), reftype(&ty.0))
+    }
+
+    fn global_type(ty: &GlobalType) -> String {
+        if ty.0 {
+            format!("(mut {})", valtype(&ty.1))
+        } else {
+            valtype(&ty.1).to_string()
+        }
+    }
+
+    fn block_type(&self, ty: &BlockType) -> String {
+        match ty {
+            BlockType::Empty => String::new(),
+            BlockType::ValType(ty) => format!(" (result {})", valtype(ty)),
+            BlockType::X(idx) => format!(" {}", self.type_use(*idx as u32)),
+        }
+    }
+
+    fn block_arity(&self, ty: &BlockType) -> (usize, usize) {
+        match ty {
+            BlockType::Empty => (0, 0),
+            BlockType::ValType(_) => (0, 1),
+            BlockType::X(idx) => self
+                .func_type_at(*idx as u32)
+                .map(|ty| (ty.0.len(), ty.1.len()))
+                .unwrap_or((0, 0)),
+        }
+    }
+
+    fn memarg(&self, instr: &Instr, memarg: &MemArg) -> String {
+        let mut out = String::new();
+        if memarg.2 != 0 {
+            write!(out, " {}", self.mem_ref(memarg.2)).unwrap();
+        }
+        if memarg.1 != 0 {
+            write!(out, " offset={}", memarg.1).unwrap();
+        }
+        if memarg.0 != natural_align(instr) {
+            write!(out, " align={}", 1u64 << memarg.0.min(63)).unwrap();
+        }
+        out
+    }
+
+    fn label(depth: usize, label: u32) -> String {
+        match depth.checked_sub(label as usize) {
+            Some(target) if target > 0 => format!("{label} (;@{target};)"),
+            _ => label.to_string(),
+        }
+    }
+
+    fn catches(&self, catches: &[Catch], depth: usize) -> String {
+        let mut out = String::new();
+        for catch in catches {
+            let label = Self::label(depth, catch.label());
+            match catch {
+                Catch::Catch(tag, _) => write!(out, " (catch {} {label})", self.tag_ref(*tag)),
+                Catch::CatchRef(tag, _) => {
+                    write!(out, " (catch_ref {} {label})", self.tag_ref(*tag))
+                }
+                Catch::CatchAll(_) => write!(out, " (catch_all {label})"),
+                Catch::CatchAllRef(_) => write!(out, " (catch_all_ref {label})"),
+            }
+            .unwrap();
+        }
+        out
+    }
+
+    /// Everything after the mnemonic of a non-block instruction.
+    fn immediates(&self, instr: &Instr, func: Option<u32>, depth: usize) -> String {
+        match instr {
+            Instr::Br(label)
+            | Instr::BrIf(label)
+            | Instr::BrOnNull(label)
+            | Instr::BrOnNonNull(label) => format!(" {}", Self::label(depth, *label)),
+            Instr::BrTable(labels, default) => {
+                let mut out = String::new();
+                for label in labels.iter().chain([default]) {
+                    write!(out, " {}", Self::label(depth, *label)).unwrap();
+                }
+                out
+            }
+            Instr::Rethrow(label) => format!(" {}", Self::label(depth, *label)),
+            Instr::Throw(tag) => format!(" {}", self.tag_ref(*tag)),
+            Instr::Call(idx) | Instr::ReturnCall(idx) | Instr::RefFunc(idx) => {
+                format!(" {}", self.func_ref(*idx))
+            }
+            Instr::CallIndirect(ty, table) | Instr::ReturnCallIndirect(ty, table) => {
+                if *table == 0 {
+                    format!(" {}", self.type_use(*ty))
+                } else {
+                    format!(" {} {}", self.table_ref(*table), self.type_use(*ty))
+                }
+            }
+            Instr::CallRef(ty) | Instr::ReturnCallRef(ty) => format!(" {}", self.type_ref(*ty)),
+            Instr::RefNull(HeapType::Type(ty)) => format!(" {}", self.type_ref(*ty)),
+            Instr::RefNull(ty) => format!(" {}", heaptype(ty)),
+            Instr::StructNew(ty)
+            | Instr::StructNewDefault(ty)
+            | Instr::ArrayNew(ty)
+            | Instr::ArrayNewDefault(ty)
+            | Instr::ArrayGet(ty)
+            | Instr::ArrayGetS(ty)
+            | Instr::ArrayGetU(ty)
+            | Instr::ArraySet(ty)
+            | Instr::ArrayFill(ty) => format!(" {}", self.type_ref(*ty)),
+            Instr::StructGet(ty, field)
+            | Instr::StructGetS(ty, field)
+            | Instr::StructGetU(ty, field)
+            | Instr::StructSet(ty, field) => {
+                format!(" {} {}", self.type_ref(*ty), self.field_ref(*ty, *field))
+            }
+            Instr::ArrayNewFixed(ty, n) => format!(" {} {n}", self.type_ref(*ty)),
+            Instr::ArrayNewData(ty, data) | Instr::ArrayInitData(ty, data) => {
+                format!(" {} {}", self.type_ref(*ty), self.data_ref(*data))
+            }
+            Instr::ArrayNewElem(ty, elem) | Instr::ArrayInitElem(ty, elem) => {
+                format!(" {} {}", self.type_ref(*ty), self.elem_ref(*elem))
+            }
+            Instr::ArrayCopy(dst, src) => {
+                format!(" {} {}", self.type_ref(*dst), self.type_ref(*src))
+            }
+            Instr::RefTest(ty) | Instr::RefCast(ty) => format!(" {}", reftype(ty)),
+            Instr::BrOnCast(label, from, to) | Instr::BrOnCastFail(label, from, to) => format!(
+                " {} {} {}",
+                Self::label(depth, *label),
+                reftype(from),
+                reftype(to)
+            ),
+            Instr::SelectType(types) => {
+                let mut out = String::from(" (result");
+                for ty in types {
+                    write!(out, " {}", valtype(ty)).unwrap();
+                }
+                out.push(')');
+                out
+            }
+            Instr::LocalGet(idx) | Instr::LocalSet(idx) | Instr::LocalTee(idx) => {
+                format!(" {}", self.local_ref(func, *idx))
+            }
+            Instr::GlobalGet(idx) | Instr::GlobalSet(idx) => {
+                format!(" {}", self.global_ref(*idx))
+            }
+            Instr::TableGet(idx)
+            | Instr::TableSet(idx)
+            | Instr::TableGrow(idx)
+            | Instr::TableSize(idx)
+            | Instr::TableFill(idx) => format!(" {}", self.table_ref(*idx)),
+            Instr::TableInit(elem, table) => {
+                format!(" {} {}", self.table_ref(*table), self.elem_ref(*elem))
+            }
+            Instr::ElemDrop(elem) => format!(" {}", self.elem_ref(*elem)),
+            Instr::TableCopy(dst, src) => {
+                format!(" {} {}", self.table_ref(*dst), self.table_ref(*src))
+            }
+            Instr::MemoryInit(data, 0) | Instr::DataDrop(data) => {
+                format!(" {}", self.data_ref(*data))
+            }
+            Instr::MemoryInit(data, mem) => {
+                format!(" {} {}", self.mem_ref(*mem), self.data_ref(*data))
+            }
+            Instr::MemorySize(mem) | Instr::MemoryGrow(mem) | Instr::MemoryFill(mem)
+                if *mem != 0 =>
+            {
+                format!(" {}", self.mem_ref(*mem))
+            }
+            Instr::MemoryCopy(dst, src) if (*dst, *src) != (0, 0) => {
+                format!(" {} {}", self.mem_ref(*dst), self.mem_ref(*src))
+            }
+            Instr::I32Const(value) => format!(" {value}"),
+            Instr::I64Const(value) => format!(" {value}"),
+            Instr::F32Const(value) => format!(" {}", f32_text(*value)),
+            Instr::F64Const(value) => format!(" {}", f64_text(*value)),
+            Instr::V128_Const(bytes) => {
+                let mut out = String::from(" i32x4");
+                for lane in bytes.chunks(4) {
+                    let lane = u32::from_le_bytes([lane[0], lane[1], lane[2], lane[3]]);
+                    write!(out, " 0x{lane:08x}").unwrap();
+                }
+                out
+            }
+            Instr::I8X16_Shuffle(lanes) => {
+                let mut out = String::new();
+                for lane in lanes {
+                    write!(out, " {lane}").unwrap();
+                }
+                out
+            }
+            Instr::I32Load(memarg)
+            | Instr::I64Load(memarg)
+            | Instr::F32Load(memarg)
+            | Instr::F64Load(memarg)
+            | Instr::I32load8S(memarg)
+            | Instr::I32Load8_u(memarg)
+            | Instr::I32Load16_s(memarg)
+            | Instr::I32Load16_u(memarg)
+            | Instr::I64Load8_s(memarg)
+            | Instr::I64Load8_u(memarg)
+            | Instr::I64Load16_s(memarg)
+            | Instr::I64Load16_u(memarg)
+            | Instr::I64Load32_s(memarg)
+            | Instr::I64Load32_u(memarg)
+            | Instr::I32Store(memarg)
+            | Instr::I64Store(memarg)
+            | Instr::F32Store(memarg)
+            | Instr::F64Store(memarg)
+            | Instr::I32Store8(memarg)
+            | Instr::I32Store16(memarg)
+            | Instr::I64Store8(memarg)
+            | Instr::I64Store16(memarg)
+            | Instr::I64Store32(memarg)
+            | Instr::V128_Load(memarg)
+            | Instr::V128_Load_8x8_S(memarg)
+            | Instr::V128_Load_8x8_U(memarg)
+            | Instr::V128_Load_16x4_S(memarg)
+            | Instr::V128_Load_16x4_U(memarg)
+            | Instr::V128_Load_32x2_S(memarg)
+            | Instr::V128_Load_32x2_U(memarg)
+            | Instr::V128_Load_8_Splat(memarg)
+            | Instr::V128_Load_16_Splat(memarg)
+            | Instr::V128_Load_32_Splat(memarg)
+            | Instr::V128_Load_64_Splat(memarg)
+            | Instr::V128_Load_32_Zero(memarg)
+            | Instr::V128_Load_64_Zero(memarg)
+            | Instr::V128_Store(memarg) => self.memarg(instr, memarg),
+            _ if instr.is_atomic() => instr
+                .memarg()
+                .map(|memarg| self.memarg(instr, memarg))
+                .unwrap_or_default(),
+            Instr::V128_Load_8_Lane(memarg, lane)
+            | Instr::V128_Load_16_Lane(memarg, lane)
+            | Instr::V128_Load_32_Lane(memarg, lane)
+            | Instr::V128_Load_64_Lane(memarg, lane)
+            | Instr::V128_Store_8_Lane(memarg, lane)
+            | Instr::V128_Store_16_Lane(memarg, lane)
+            | Instr::V128_Store_32_Lane(memarg, lane)
+            | Instr::V128_Store_64_Lane(memarg, lane) => {
+                format!("{} {lane}", self.memarg(instr, memarg))
+            }
+            Instr::I8X16_Extract_Lane_S(lane)
+            | Instr::I8X16_Extract_Lane_U(lane)
+            | Instr::I8X16_Replace_Lane(lane)
+            | Instr::I16X8_Extract_Lane_S(lane)
+            | Instr::I16X8_Extract_Lane_U(lane)
+            | Instr::I16X8_Replace_Lane(lane)
+            | Instr::I32X4_Extract_Lane(lane)
+            | Instr::I32X4_Replace_Lane(lane)
+            | Instr::I64X2_Extract_Lane(lane)
+            | Instr::I64X2_Replace_Lane(lane)
+            | Instr::F32X4_Extract_Lane(lane)
+            | Instr::F32X4_Replace_Lane(lane)
+            | Instr::F64X2_Extract_Lane(lane)
+            | Instr::F64X2_Replace_Lane(lane) => format!(" {lane}"),
+            _ => String::new(),
+        }
+    }
+
+    /// Values popped and pushed by an instruction, `None` when it is stack polymorphic.
+    fn arity(&self, instr: &Instr, labels: &[usize]) -> Option<(usize, usize)> {
+        let label = |label: &u32| {
+            labels
+                .len()
+                .checked_sub(*label as usize + 1)
+                .map(|idx| labels[idx])
+        };
+        Some(match instr {
+            Instr::UnReachable
+            | Instr::Br(_)
+            | Instr::BrTable(_, _)
+            | Instr::Return
+            | Instr::ReturnCall(_)
+            | Instr::ReturnCallIndirect(_, _)
+            | Instr::ReturnCallRef(_)
+            | Instr::Throw(_)
+            | Instr::Rethrow(_)
+            | Instr::ThrowRef => return None,
+            Instr::Nop | Instr::ElemDrop(_) | Instr::DataDrop(_) => (0, 0),
+            Instr::Block(ty, _)
+            | Instr::Loop(ty, _)
+            | Instr::Try(ty, _, _, _)
+            | Instr::TryDelegate(ty, _, _)
+            | Instr::TryTable(ty, _, _) => self.block_arity(ty),
+            Instr::If(ty, _) | Instr::IfElse(ty, _, _) => {
+                let (params, results) = self.block_arity(ty);
+                (params + 1, results)
+            }
+            Instr::BrIf(l) => {
+                let arity = label(l)?;
+                (arity + 1, arity)
+            }
+            Instr::Call(func) => {
+                let ty = self.module.func_type(*func)?;
+                (ty.0.len(), ty.1.len())
+            }
+            Instr::BrOnNull(l) => {
+                let arity = label(l)?;
+                (arity + 1, arity + 1)
+            }
+            Instr::BrOnNonNull(l) => {
+                let arity = label(l)?;
+                (arity, arity.checked_sub(1)?)
+            }
+            Instr::CallIndirect(ty, _) | Instr::CallRef(ty) => {
+                let ty = self.func_type_at(*ty)?;
+                (ty.0.len() + 1, ty.1.len())
+            }
+            Instr::RefNull(_)
+            | Instr::RefFunc(_)
+            | Instr::LocalGet(_)
+            | Instr::GlobalGet(_)
+            | Instr::TableSize(_)
+            | Instr::MemorySize(_)
+            | Instr::I32Const(_)
+            | Instr::I64Const(_)
+            | Instr::F32Const(_)
+            | Instr::F64Const(_)
+            | Instr::V128_Const(_) => (0, 1),
+            Instr::Drop | Instr::LocalSet(_) | Instr::GlobalSet(_) => (1, 0),
+            Instr::Select | Instr::SelectType(_) => (3, 1),
+            Instr::TableSet(_) => (2, 0),
+            Instr::TableGrow(_) => (2, 1),
+            Instr::TableInit(_, _)
+            | Instr::TableCopy(_, _)
+            | Instr::TableFill(_)
+            | Instr::MemoryInit(_, _)
+            | Instr::MemoryCopy(_, _)
+            | Instr::MemoryFill(_) => (3, 0),
+            Instr::I32Store(_)
+            | Instr::I64Store(_)
+            | Instr::F32Store(_)
+            | Instr::F64Store(_)
+            | Instr::I32Store8(_)
+            | Instr::I32Store16(_)
+            | Instr::I64Store8(_)
+            | Instr::I64Store16(_)
+            | Instr::I64Store32(_)
+            | Instr::V128_Store(_)
+            | Instr::V128_Store_8_Lane(_, _)
+            | Instr::V128_Store_16_Lane(_, _)
+            | Instr::V128_Store_32_Lane(_, _)
+            | Instr::V128_Store_64_Lane(_, _) => (2, 0),
+            Instr::V128_Load_8_Lane(_, _)
+            | Instr::V128_Load_16_Lane(_, _)
+            | Instr::V128_Load_32_Lane(_, _)
+            | Instr::V128_Load_64_Lane(_, _) => (2, 1),
+            Instr::LocalTee(_)
+            | Instr::TableGet(_)
+            | Instr::MemoryGrow(_)
+            | Instr::I32Load(_)
+            | Instr::I64Load(_)
+            | Instr::F32Load(_)
+            | Instr::F64Load(_)
+            | Instr::I32load8S(_)
+            | Instr::I32Load8_u(_)
+            | Instr::I32Load16_s(_)
+            | Instr::I32Load16_u(_)
+            | Instr::I64Load8_s(_)
+            | Instr::I64Load8_u(_)
+            | Instr::I64Load16_s(_)
+            | Instr::I64Load16_u(_)
+            | Instr::I64Load32_s(_)
+            | Instr::I64Load32_u(_)
+            | Instr::V128_Load(_)
+            | Instr::V128_Load_8x8_S(_)
+            | Instr::V128_Load_8x8_U(_)
+            | Instr::V128_Load_16x4_S(_)
+            | Instr::V128_Load_16x4_U(_)
+            | Instr::V128_Load_32x2_S(_)
+            | Instr::V128_Load_32x2_U(_)
+            | Instr::V128_Load_8_Splat(_)
+            | Instr::V128_Load_16_Splat(_)
+            | Instr::V128_Load_32_Splat(_)
+            | Instr::V128_Load_64_Splat(_)
+            | Instr::V128_Load_32_Zero(_)
+            | Instr::V128_Load_64_Zero(_) => (1, 1),
+            Instr::RefIsNull | Instr::RefAsNonNull => (1, 1),
+            Instr::StructNew(ty) => match &self.module.types().get(*ty as usize)?.2 {
+                CompType::Struct(fields) => (fields.len(), 1),
+                _ => return None,
+            },
+            Instr::StructNewDefault(_) => (0, 1),
+            Instr::StructGet(_, _) | Instr::StructGetS(_, _) | Instr::StructGetU(_, _) => (1, 1),
+            Instr::StructSet(_, _) => (2, 0),
+            Instr::ArrayNew(_) => (2, 1),
+            Instr::ArrayNewDefault(_) => (1, 1),
+            Instr::ArrayNewFixed(_, n) => (*n as usize, 1),
+            Instr::ArrayNewData(_, _) | Instr::ArrayNewElem(_, _) => (2, 1),
+            Instr::ArrayGet(_) | Instr::ArrayGetS(_) | Instr::ArrayGetU(_) => (2, 1),
+            Instr::ArraySet(_) => (3, 0),
+            Instr::ArrayLen => (1, 1),
+            Instr::ArrayFill(_) | Instr::ArrayInitData(_, _) | Instr::ArrayInitElem(_, _) => (4, 0),
+            Instr::ArrayCopy(_, _) => (5, 0),
+            Instr::RefTest(_) | Instr::RefCast(_) => (1, 1),
+            Instr::BrOnCast(l, _, _) | Instr::BrOnCastFail(l, _, _) => {
+                let arity = label(l)?;
+                (arity, arity)
+            }
+            Instr::RefEq => (2, 1),
+            Instr::V128_Bitselect
+            | Instr::F32X4_Relaxed_Madd
+            | Instr::F32X4_Relaxed_Nmadd
+            | Instr::F64X2_Relaxed_Madd
+            | Instr::F64X2_Relaxed_Nmadd
+            | Instr::I8X16_Relaxed_Laneselect
+            | Instr::I16X8_Relaxed_Laneselect
+            | Instr::I32X4_Relaxed_Laneselect
+            | Instr::I64X2_Relaxed_Laneselect
+            | Instr::I32X4_Relaxed_Dot_I8X16_I7X16_Add_S => (3, 1),
+            Instr::AnyConvertExtern
+            | Instr::ExternConvertAny
+            | Instr::RefI31
+            | Instr::I31GetS
+            | Instr::I31GetU => (1, 1),
+            Instr::AtomicFence => (0, 0),
+            instr if instr.is_atomic() => {
+                let name = instr.name();
+                if name.contains(".load") {
+                    (1, 1)
+                } else if name.contains(".store") {
+                    (2, 0)
+                } else if name.contains("cmpxchg") || name.contains("wait") {
+                    (3, 1)
+                } else {
+                    (2, 1)
+                }
+            }
+            instr if is_unary(instr.name()) => (1, 1),
+            _ => (2, 1),
+        })
+    }
+
+    fn module(&mut self) {
+        let mut header = String::from("(module");
+        if let Some(name) = &self.names.module {
+            write!(header, " ${name}").unwrap();
+        }
+        self.out.push_str(&header);
+        self.indent += 1;
+
+        let mut offset = 8;
+        let mut func_idx = self.module.imported_funcs().count() as u32;
+        let mut counts = [0u32; 5];
+        for section in &self.module.sections {
+            let mut content = IB::new();
+            section.encode_content(&mut content);
+            offset += 1 + encoded_len(&(content.len() as u32));
+            let start = offset;
+            match section {
+                Section::Custom(custom) => self.custom(custom, offset),
+                Section::DecodedCustom(decoded) => self.custom(&decoded.sec, offset),
+                Section::Type(groups) => {
+                    offset += encoded_len(&(groups.len() as u32));
+                    let mut idx = 0;
+                    for group in groups {
+                        let rec = group.0.len() != 1;
+                        if rec {
+                            let text = format!("{}(rec", self.offset(offset));
+                            self.line(&text);
+                            self.indent += 1;
+                            offset += 1 + encoded_len(&(group.0.len() as u32));
+                        }
+                        for ty in &group.0 {
+                            let text = format!(
+                                "{}(type {} {})",
+                                self.offset(offset),
+                                Self::id(self.names.types.get(&idx), idx),
+                                self.sub_type(idx, ty)
+                            );
+                            self.line(&text);
+                            offset += encoded_len(ty);
+                            idx += 1;
+                        }
+                        if rec {
+                            self.indent -= 1;
+                            self.out.push(')');
+                        }
+                    }
+                }
+                Section::Import(imports) => {
+                    offset += encoded_len(&(imports.len() as u32));
+                    for import in imports {
+                        self.import(import, offset, &mut counts);
+                        offset += encoded_len(import);
+                    }
+                }
+                Section::Function(_) | Section::DataCountSection(_) => {}
+                Section::Table(tables) => {
+                    offset += encoded_len(&(tables.len() as u32));
+                    for (idx, table) in tables.iter().enumerate() {
+                        let idx = counts[1] + idx as u32;
+                        let text = format!(
+                            "{}(table {} {})",
+                            self.offset(offset),
+                            Self::id(self.names.tables.get(&idx), idx),
+                            Self::table_type(table)
+                        );
+                        self.line(&text);
+                        offset += encoded_len(table);
+                    }
+                }
+                Section::Memory(mems) => {
+                    offset += encoded_len(&(mems.len() as u32));
+                    for (idx, mem) in mems.iter().enumerate() {
+                        let idx = counts[2] + idx as u32;
+                        let text = format!(
+                            "{}(memory {} {})",
+                            self.offset(offset),
+                            Self::id(self.names.mems.get(&idx), idx),
+                            Self::limits(mem)
+                        );
+                        self.line(&text);
+                        offset += encoded_len(mem);
+                    }
+                }
+                Section::Tag(tags) => {
+                    offset += encoded_len(&(tags.len() as u32));
+                    for (idx, tag) in tags.iter().enumerate() {
+                        let idx = counts[4] + idx as u32;
+                        let text = format!(
+                            "{}(tag {} {})",
+                            self.offset(offset),
+                            Self::id(self.names.tags.get(&idx), idx),
+                            self.type_use(tag.0)
+                        );
+                        self.line(&text);
+                        offset += encoded_len(tag);
+                    }
+                }
+                Section::Global(globals) => {
+                    offset += encoded_len(&(globals.len() as u32));
+                    for (idx, global) in globals.iter().enumerate() {
+                        self.global(counts[3] + idx as u32, global, offset);
+                        offset += encoded_len(global);
+                    }
+                }
+                Section::Export(exports) => {
+                    offset += encoded_len(&(exports.len() as u32));
+                    for export in exports {
+                        self.export(export, offset);
+                        offset += encoded_len(export);
+                    }
+                }
+                Section::Start(start) => {
+                    let text = format!("{}(start {})", self.offset(offset), self.func_ref(*start));
+                    self.line(&text);
+                }
+                Section::Element(elems) => {
+                    offset += encoded_len(&(elems.len() as u32));
+                    for (idx, elem) in elems.iter().enumerate() {
+                        self.elem(idx as u32, elem, offset);
+                        offset += encoded_len(elem);
+                    }
+                }
+                Section::Code(codes) => {
+                    offset += encoded_len(&(codes.len() as u32));
+                    for code in codes {
+                        self.func(func_idx, code, offset);
+                        offset += encoded_len(code);
+                        func_idx += 1;
+                    }
+                }
+                Section::Data(datas) => {
+                    offset += encoded_len(&(datas.len() as u32));
+                    for (idx, data) in datas.iter().enumerate() {
+                        self.data(idx as u32, data, offset);
+                        offset += encoded_len(data);
+                    }
+                }
+                Section::Unknown(id, bytes) => {
+                    let text = format!(
+                        "{};; unknown section {id}, {} bytes",
+                        self.offset(offset),
+                        bytes.len()
+                    );
+                    self.line(&text);
+                }
+            }
+            offset = start + content.len();
+        }
+
+        self.indent -= 1;
+        self.out.push_str(")\n");
+    }
+
+    fn custom(&mut self, custom: &CustomSec, offset: usize) {
+        if custom.0 == NameSec::NAME {
+            return;
+        }
+        let text = format!(
+            "{}(@custom {} {})",
+            self.offset(offset),
+            string(custom.0.as_bytes()),
+            string(&custom.1)
+        );
+        self.line(&text);
+    }
+
+    fn import(&mut self, import: &Import, offset: usize, counts: &mut [u32; 5]) {
+        let desc = match &import.desc {
+            ImportDesc::TypeIdx(ty) => {
+                let idx = counts[0];
+                counts[0] += 1;
+                format!(
+                    "(func {} {})",
+                    Self::id(self.names.funcs.get(&idx), idx),
+                    self.type_use(*ty)
+                )
+            }
+            ImportDesc::TableType(ty) => {
+                let idx = counts[1];
+                counts[1] += 1;
+                format!(
+                    "(table {} {})",
+                    Self::id(self.names.tables.get(&idx), idx),
+                    Self::table_type(ty)
+                )
+            }
+            ImportDesc::MemType(ty) => {
+                let idx = counts[2];
+                counts[2] += 1;
+                format!(
+                    "(memory {} {})",
+                    Self::id(self.names.mems.get(&idx), idx),
+                    Self::limits(ty)
+                )
+            }
+            ImportDesc::GlobalType(ty) => {
+                let idx = counts[3];
+                counts[3] += 1;
+                format!(
+                    "(global {} {})",
+                    Self::id(self.names.globals.get(&idx), idx),
+                    Self::global_type(ty)
+                )
+            }
+            ImportDesc::TagType(ty) => {
+                let idx = counts[4];
+                counts[4] += 1;
+                format!(
+                    "(tag {} {})",
+                    Self::id(self.names.tags.get(&idx), idx),
+                    self.type_use(ty.0)
+                )
+            }
+        };
+        let text = format!(
+            "{}(import {} {} {desc})",
+            self.offset(offset),
+            string(import.module.as_bytes()),
+            string(import.name.as_bytes())
+        );
+        self.line(&text);
+    }
+
+    fn export(&mut self, export: &Export, offset: usize) {
+        let desc = match &export.1 {
+            ExportDesc::FuncIdx(idx) => format!("(func {})", self.func_ref(*idx)),
+            ExportDesc::TableIdx(idx) => format!("(table {})", self.table_ref(*idx)),
+            ExportDesc::MemIdx(idx) => format!("(memory {})", self.mem_ref(*idx)),
+            ExportDesc::GlobalIdx(idx) => format!("(global {})", self.global_ref(*idx)),
+            ExportDesc::TagIdx(idx) => format!("(tag {})", self.tag_ref(*idx)),
+        };
+        let text = format!(
+            "{}(export {} {desc})",
+            self.offset(offset),
+            string(export.0.as_bytes())
+        );
+        self.line(&text);
+    }
+
+    /// Constant expressions are always printed folded, each instruction in its own parens.
+    fn const_expr(&self, expr: &Expr) -> String {
+        let mut out = String::new();
+        for instr in &expr.0 {
+            if !out.is_empty() {
+                out.push(' ');
+            }
+            write!(out, "({}{})", instr.name(), self.immediates(instr, None, 0)).unwrap();
+        }
+        out
+    }
+
+    fn offset_expr(&self, expr: &Expr) -> String {
+        if expr.0.len() == 1 {
+            self.const_expr(expr)
+        } else {
+            format!("(offset {})", self.const_expr(expr))
+        }
+    }
+
+    fn item_expr(&self, expr: &Expr) -> String {
+        if expr.0.len() == 1 {
+            self.const_expr(expr)
+        } else {
+            format!("(item {})", self.const_expr(expr))
+        }
+    }
+
+    fn global(&mut self, idx: u32, global: &Global, offset: usize) {
+        let text = format!(
+            "{}(global {} {} {})",
+            self.offset(offset),
+            Self::id(self.names.globals.get(&idx), idx),
+            Self::global_type(&global.0),
+            self.const_expr(&global.1)
+        );
+        self.line(&text);
+    }
+
+    fn elem(&mut self, idx: u32, elem: &Elem, offset: usize) {
+        let funcs = |funcs: &[u32]| {
+            let mut out = String::from("func");
+            for func in funcs {
+                write!(out, " {}", self.func_ref(*func)).unwrap();
+            }
+            out
+        };
+        let exprs = |ty: &RefType, exprs: &[Expr]| {
+            let mut out = reftype(ty).to_string();
+            for expr in exprs {
+                write!(out, " {}", self.item_expr(expr)).unwrap();
+            }
+            out
+        };
+        let body = match elem {
+            Elem::A(expr, fs) => format!("{} {}", self.offset_expr(expr), funcs(fs)),
+            Elem::B(_, fs) => funcs(fs),
+            Elem::C(table, expr, _, fs) => format!(
+                "(table {}) {} {}",
+                self.table_ref(*table),
+                self.offset_expr(expr),
+                funcs(fs)
+            ),
+            Elem::D(_, fs) => format!("declare {}", funcs(fs)),
+            Elem::E(expr, es) => format!(
+                "{} {}",
+                self.offset_expr(expr),
+                exprs(&RefType::FUNCREF, es)
+            ),
+            Elem::F(ty, es) => exprs(ty, es),
+            Elem::G(table, expr, ty, es) => format!(
+                "(table {}) {} {}",
+                self.table_ref(*table),
+                self.offset_expr(expr),
+                exprs(ty, es)
+            ),
+            Elem::H(ty, es) => format!("declare {}", exprs(ty, es)),
+        };
+        let text = format!(
+            "{}(elem {} {body})",
+            self.offset(offset),
+            Self::id(self.names.elems.get(&idx), idx)
+        );
+        self.line(&text);
+    }
+
+    fn data(&mut self, idx: u32, data: &Data, offset: usize) {
+        let body = match data {
+            Data::A(expr, bytes) => format!("{} {}", self.offset_expr(expr), string(bytes)),
+            Data::B(bytes) => string(bytes),
+            Data::C(mem, expr, bytes) => format!(
+                "(memory {}) {} {}",
+                self.mem_ref(*mem),
+                self.offset_expr(expr),
+                string(bytes)
+            ),
+        };
+        let text = format!(
+            "{}(data {} {body})",
+            self.offset(offset),
+            Self::id(self.names.datas.get(&idx), idx)
+        );
+        self.line(&text);
+    }
+
+    fn func(&mut self, idx: u32, code: &Code, offset: usize) {
+        let ty_idx = self.module.func_type_idx(idx).unwrap_or_default();
+        let mut text = format!(
+            "{}(func {} (type {})",
+            self.offset(offset),
+            Self::id(self.names.funcs.get(&idx), idx),
+            self.type_ref(ty_idx)
+        );
+        let ty = self.func_type_at(ty_idx);
+        let params = ty.map(|ty| ty.0.as_slice()).unwrap_or_default();
+        let local_names = self.names.locals.get(&idx).cloned();
+        let mut unnamed = Vec::new();
+        let flush = |text: &mut String, unnamed: &mut Vec<String>, kind: &str| {
+            if !unnamed.is_empty() {
+                write!(text, " ({kind} {})", unnamed.join(" ")).unwrap();
+                unnamed.clear();
+            }
+        };
+        for (local, param) in params.iter().enumerate() {
+            match local_names
+                .as_ref()
+                .and_then(|names| names.get(&(local as u32)))
+            {
+                Some(name) => {
+                    flush(&mut text, &mut unnamed, "param");
+                    write!(text, " (param ${name} {})", valtype(param)).unwrap();
+                }
+                None => unnamed.push(valtype(param)),
+            }
+        }
+        flush(&mut text, &mut unnamed, "param");
+        if let Some(ty) = ty {
+            if !ty.1.is_empty() {
+                text.push_str(&Self::func_type(&FuncType(Vec::new(), ty.1.clone())));
+            }
+        }
+        self.line(&text);
+        self.indent += 1;
+
+        let mut locals = String::new();
+        let mut local = params.len() as u32;
+        for group in &code.1 .0 {
+            for _ in 0..group.0 {
+                match local_names.as_ref().and_then(|names| names.get(&local)) {
+                    Some(name) => {
+                        flush(&mut locals, &mut unnamed, "local");
+                        write!(locals, " (local ${name} {})", valtype(&group.1)).unwrap();
+                    }
+                    None => unnamed.push(valtype(&group.1)),
+                }
+                local += 1;
+            }
+        }
+        flush(&mut locals, &mut unnamed, "local");
+        if !locals.is_empty() {
+            self.line(locals.trim_start());
+        }
+
+        let body_offset = offset + encoded_len(&code.0) + encoded_len(&code.1 .0);
+        let results = ty.map(|ty| ty.1.len()).unwrap_or_default();
+        if self.options.folded {
+            self.folded(&code.1 .1 .0, body_offset, Some(idx), &mut vec![results]);
+        } else {
+            self.flat(&code.1 .1 .0, body_offset, Some(idx), 0);
+        }
+
+        self.indent -= 1;
+        self.out.push(')');
+    }
+
+    fn flat(
+        &mut self,
+        instrs: &[Instr],
+        mut offset: usize,
+        func: Option<u32>,
+        depth: usize,
+    ) -> usize {
+        for instr in instrs {
+            let prefix = self.offset(offset);
+            match instr {
+                Instr::Block(ty, body) | Instr::Loop(ty, body) | Instr::If(ty, body) => {
+                    let text = format!(
+                        "{prefix}{}{}  ;; label = @{}",
+                        instr.name(),
+                        self.block_type(ty),
+                        depth + 1
+                    );
+                    self.line(&text);
+                    offset += 1 + encoded_len(ty);
+                    self.indent += 1;
+                    offset = self.flat(body, offset, func, depth + 1);
+                    self.indent -= 1;
+                    let text = format!("{}end", self.offset(offset));
+                    self.line(&text);
+                    offset += 1;
+                }
+                Instr::IfElse(ty, then, otherwise) => {
+                    let text = format!(
+                        "{prefix}if{}  ;; label = @{}",
+                        self.block_type(ty),
+                        depth + 1
+                    );
+                    self.line(&text);
+                    offset += 1 + encoded_len(ty);
+                    self.indent += 1;
+                    offset = self.flat(then, offset, func, depth + 1);
+                    self.indent -= 1;
+                    let text = format!("{}else", self.offset(offset));
+                    self.line(&text);
+                    offset += 1;
+                    self.indent += 1;
+                    offset = self.flat(otherwise, offset, func, depth + 1);
+                    self.indent -= 1;
+                    let text = format!("{}end", self.offset(offset));
+                    self.line(&text);
+                    offset += 1;
+                }
+                Instr::Try(ty, body, catches, catch_all) => {
+                    let text = format!(
+                        "{prefix}try{}  ;; label = @{}",
+                        self.block_type(ty),
+                        depth + 1
+                    );
+                    self.line(&text);
+                    offset += 1 + encoded_len(ty);
+                    self.indent += 1;
+                    offset = self.flat(body, offset, func, depth + 1);
+                    self.indent -= 1;
+                    for (tag, handler) in catches {
+                        let text = format!("{}catch {}", self.offset(offset), self.tag_ref(*tag));
+                        self.line(&text);
+                        offset += 1 + encoded_len(tag);
+                        self.indent += 1;
+                        offset = self.flat(handler, offset, func, depth + 1);
+                        self.indent -= 1;
+                    }
+                    if let Some(handler) = catch_all {
+                        let text = format!("{}catch_all", self.offset(offset));
+                        self.line(&text);
+                        offset += 1;
+                        self.indent += 1;
+                        offset = self.flat(handler, offset, func, depth + 1);
+                        self.indent -= 1;
+                    }
+                    let text = format!("{}end", self.offset(offset));
+                    self.line(&text);
+                    offset += 1;
+                }
+                Instr::TryDelegate(ty, body, label) => {
+                    let text = format!(
+                        "{prefix}try{}  ;; label = @{}",
+                        self.block_type(ty),
+                        depth + 1
+                    );
+                    self.line(&text);
+                    offset += 1 + encoded_len(ty);
+                    self.indent += 1;
+                    offset = self.flat(body, offset, func, depth + 1);
+                    self.indent -= 1;
+                    let text = format!(
+                        "{}delegate {}",
+                        self.offset(offset),
+                        Self::label(depth, *label)
+                    );
+                    self.line(&text);
+                    offset += 1 + encoded_len(label);
+                }
+                Instr::TryTable(ty, catches, body) => {
+                    let text = format!(
+                        "{prefix}try_table{}{}  ;; label = @{}",
+                        self.block_type(ty),
+                        self.catches(catches, depth),
+                        depth + 1
+                    );
+                    self.line(&text);
+                    offset += 1 + encoded_len(ty) + encoded_len(catches);
+                    self.indent += 1;
+                    offset = self.flat(body, offset, func, depth + 1);
+                    self.indent -= 1;
+                    let text = format!("{}end", self.offset(offset));
+                    self.line(&text);
+                    offset += 1;
+                }
+                _ => {
+                    let text = format!(
+                        "{prefix}{}{}",
+                        instr.name(),
+                        self.immediates(instr, func, depth)
+                    );
+                    self.line(&text);
+                    offset += encoded_len(instr);
+                }
+            }
+        }
+        offset
+    }
+
+    fn folded(
+        &mut self,
+        instrs: &[Instr],
+        mut offset: usize,
+        func: Option<u32>,
+        labels: &mut Vec<usize>,
+    ) {
+        let mut nodes: Vec<Node> = Vec::new();
+        for instr in instrs {
+            let (children, results) = match self.arity(instr, labels) {
+                Some((params, results)) => {
+                    let operands = nodes
+                        .iter()
+                        .rev()
+                        .take_while(|node| node.results == Some(1))
+                        .count();
+                    if operands >= params {
+                        (nodes.split_off(nodes.len() - params), Some(results))
+                    } else {
+                        (Vec::new(), Some(results))
+                    }
+                }
+                None => (Vec::new(), None),
+            };
+            nodes.push(Node {
+                instr,
+                offset,
+                children,
+                results,
+            });
+            offset += encoded_len(instr);
+        }
+        for node in &nodes {
+            self.node(node, func, labels);
+        }
+    }
+
+    fn node(&mut self, node: &Node, func: Option<u32>, labels: &mut Vec<usize>) {
+        let depth = labels.len() - 1;
+        match node.instr {
+            Instr::Block(ty, body) | Instr::Loop(ty, body) => {
+                let text = format!(
+                    "({}{}{}  ;; label = @{}",
+                    node.instr.name(),
+                    self.block_type(ty),
+                    self.annotation(node.offset),
+                    depth + 1
+                );
+                self.line(&text);
+                self.indent += 1;
+                for child in &node.children {
+                    self.node(child, func, labels);
+                }
+                let (params, results) = self.block_arity(ty);
+                let arity = if matches!(node.instr, Instr::Loop(_, _)) {
+                    params
+                } else {
+                    results
+                };
+                labels.push(arity);
+                self.folded(body, node.offset + 1 + encoded_len(ty), func, labels);
+                labels.pop();
+                self.indent -= 1;
+                self.out.push(')');
+            }
+            Instr::If(ty, then) | Instr::IfElse(ty, then, _) => {
+                let text = format!(
+                    "(if{}{}  ;; label = @{}",
+                    self.block_type(ty),
+                    self.annotation(node.offset),
+                    depth + 1
+                );
+                self.line(&text);
+                self.indent += 1;
+                for child in &node.children {
+                    self.node(child, func, labels);
+                }
+                labels.push(self.block_arity(ty).1);
+                self.line("(then");
+                self.indent += 1;
+                let start = node.offset + 1 + encoded_len(ty);
+                self.folded(then, start, func, labels);
+                self.indent -= 1;
+                self.out.push(')');
+                if let Instr::IfElse(_, _, otherwise) = node.instr {
+                    let start = start + then.iter().map(encoded_len).sum::<usize>() + 1;
+                    self.line("(else");
+                    self.indent += 1;
+                    self.folded(otherwise, start, func, labels);
+                    self.indent -= 1;
+                    self.out.push(')');
+                }
+                labels.pop();
+                self.indent -= 1;
+                self.out.push(')');
+            }
+            Instr::Try(ty, body, _, _) | Instr::TryDelegate(ty, body, _) => {
+                let text = format!(
+                    "(try{}{}  ;; label = @{}",
+                    self.block_type(ty),
+                    self.annotation(node.offset),
+                    depth + 1
+                );
+                self.line(&text);
+                self.indent += 1;
+                for child in &node.children {
+                    self.node(child, func, labels);
+                }
+                labels.push(self.block_arity(ty).1);
+                self.line("(do");
+                self.indent += 1;
+                let mut start = node.offset + 1 + encoded_len(ty);
+                self.folded(body, start, func, labels);
+                start += body.iter().map(encoded_len).sum::<usize>();
+                self.indent -= 1;
+                self.out.push(')');
+                match node.instr {
+                    Instr::Try(_, _, catches, catch_all) => {
+                        for (tag, handler) in catches {
+                            start += 1 + encoded_len(tag);
+                            let text = format!("(catch {}", self.tag_ref(*tag));
+                            self.line(&text);
+                            self.indent += 1;
+                            self.folded(handler, start, func, labels);
+                            start += handler.iter().map(encoded_len).sum::<usize>();
+                            self.indent -= 1;
+                            self.out.push(')');
+                        }
+                        if let Some(handler) = catch_all {
+                            self.line("(catch_all");
+                            self.indent += 1;
+                            self.folded(handler, start + 1, func, labels);
+                            self.indent -= 1;
+                            self.out.push(')');
+                        }
+                        labels.pop();
+                    }
+                    Instr::TryDelegate(_, _, label) => {
+                        labels.pop();
+                        let text = format!("(delegate {})", Self::label(depth, *label));
+                        self.line(&text);
+                    }
+                    _ => unreachable!(),
+                }
+                self.indent -= 1;
+                self.out.push(')');
+            }
+            Instr::TryTable(ty, catches, body) => {
+                let text = format!(
+                    "(try_table{}{}{}  ;; label = @{}",
+                    self.block_type(ty),
+                    self.catches(catches, depth),
+                    self.annotation(node.offset),
+                    depth + 1
+                );
+                self.line(&text);
+                self.indent += 1;
+                for child in &node.children {
+                    self.node(child, func, labels);
+                }
+                labels.push(self.block_arity(ty).1);
+                let start = node.offset + 1 + encoded_len(ty) + encoded_len(catches);
+                self.folded(body, start, func, labels);
+                labels.pop();
+                self.indent -= 1;
+                self.out.push(')');
+            }
+            instr => {
+                let text = format!(
+                    "({}{}{}",
+                    instr.name(),
+                    self.immediates(instr, func, depth),
+                    self.annotation(node.offset)
+                );
+                self.line(&text);
+                self.indent += 1;
+                for child in &node.children {
+                    self.node(child, func, labels);
+                }
+                self.indent -= 1;
+                self.out.push(')');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{print, PrintOptions};
+    use crate::custom::NameSec;
+    use crate::instructions::{BlockType, Instr};
+    use crate::modules::{Code, CustomSec, ExportDesc, Expr, Func, Module, Section};
+    use crate::types::{FuncType, NumType, RecType, ValType};
+
+    fn module() -> Module {
+        let i32 = ValType::NumType(NumType::I32);
+        let names = NameSec {
+            funcs: vec![(0, "add".to_string())],
+            locals: vec![(0, vec![(0, "a".to_string())])],
+            ..Default::default()
+        };
+        Module {
+            magic: 0x6D73_6100,
+            version: 1,
+            sections: vec![
+                Section::Type(vec![RecType(vec![
+                    FuncType(vec![i32, i32], vec![i32]).into()
+                ])]),
+                Section::Function(vec![0]),
+                Section::Export(vec![("add".to_string(), ExportDesc::FuncIdx(0))]),
+                Section::Code(vec![Code(
+                    0,
+                    Func(
+                        vec![],
+                        Expr(vec![
+                            Instr::Block(
+                                BlockType::ValType(i32),
+                                vec![
+                                    Instr::LocalGet(0),
+                                    Instr::LocalGet(1),
+                                    Instr::I32Add,
+                                    Instr::Br(0),
+                                ],
+                            ),
+                            Instr::Call(0),
+                        ]),
+                    ),
+                )]),
+                Section::Custom(CustomSec::from(&names)),
+            ],
+            layout: None,
+            custom_errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn flat() {
+        assert_eq!(
+            print(&module(), PrintOptions::default()),
+            r#"(module
+  (type (;0;) (func (param i32 i32) (result i32)))
+  (export "add" (func $add))
+  (func $add (;0;) (type 0) (param $a i32) (param i32) (result i32)
+    block (result i32)  ;; label = @1
+      local.get $a
+      local.get 1
+      i32.add
+      br 0 (;@1;)
+    end
+    call $add))
+"#
+        );
+    }
+
+    #[test]
+    fn folded() {
+        let options = PrintOptions {
+            folded: true,
+            offsets: true,
+        };
+        assert_eq!(
+            print(&module(), options),
+            r#"(module
+  (;@b;) (type (;0;) (func (param i32 i32) (result i32)))
+  (;@18;) (export "add" (func $add))
+  (;@21;) (func $add (;0;) (type 0) (param $a i32) (param i32) (result i32)
+    (block (result i32) (;@23;)  ;; label = @1
+      (i32.add (;@29;)
+        (local.get $a (;@25;))
+        (local.get 1 (;@27;)))
+      (br 0 (;@1;) (;@2a;)))
+    (call $add (;@2d;))))
+"#
+        );
+    }
+}
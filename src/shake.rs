@@ -0,0 +1,638 @@
+use std::collections::BTreeSet;
+
+use crate::callgraph::{visit, CallGraph};
+use crate::custom::NameSec;
+use crate::instructions::{BlockType, Instr};
+use crate::modules::{
+    CustomSec, Data, DecodedCustomSec, Elem, ExportDesc, Expr, FuncIdx, ImportDesc, Module, Section,
+};
+use crate::types::{CompType, FieldType, HeapType, RefType, StorageType, SubType, ValType};
+use crate::Parse;
+
+/// Number of items `shake` removed from each index space.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Removed {
+    pub funcs: usize,
+    pub types: usize,
+    pub globals: usize,
+    pub elems: usize,
+    pub datas: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Space {
+    Func,
+    Type,
+    Global,
+    Elem,
+    Data,
+}
+
+type Used = [BTreeSet<u32>; 5];
+
+fn record(used: &mut Used) -> impl FnMut(Space, &mut u32) + '_ {
+    move |space, idx| {
+        used[space as usize].insert(*idx);
+    }
+}
+
+/// Removes the functions not reachable from the exports, the start function and the active
+/// element segments, then the types, globals and passive segments nothing left refers to,
+/// renumbering the remaining references. Imports are kept as they are part of the module's
+/// interface.
+pub fn shake(module: &mut Module) -> Removed {
+    module.clear_layout();
+    let imported_funcs = module.imported_funcs().count() as u32;
+    let imported_globals = module.imported_globals().count() as u32;
+    let func_count = imported_funcs as usize + module.funcs().len();
+    let type_count = module.types().len();
+    let global_count = imported_globals as usize + module.globals().len();
+
+    let funcs = live_funcs(module);
+    let mut used: Used = Default::default();
+    used[Space::Func as usize] = funcs;
+
+    for (idx, elem) in module.elems().iter().enumerate() {
+        if matches!(elem, Elem::A(..) | Elem::C(..) | Elem::E(..) | Elem::G(..)) {
+            used[Space::Elem as usize].insert(idx as u32);
+        }
+    }
+    for (idx, data) in module.datas().iter().enumerate() {
+        if !matches!(data, Data::B(_)) {
+            used[Space::Data as usize].insert(idx as u32);
+        }
+    }
+    for (_, desc) in module.exports() {
+        if let ExportDesc::GlobalIdx(global) = desc {
+            used[Space::Global as usize].insert(*global);
+        }
+    }
+    used[Space::Global as usize].extend(0..imported_globals);
+
+    // Record what the remaining items refer to. Declarative segments only matter for the
+    // `ref.func`s they declare, so they go once those functions do.
+    for section in &mut module.sections {
+        match section {
+            Section::Import(imports) => {
+                for import in imports {
+                    import_indices(&mut import.desc, &mut record(&mut used));
+                }
+            }
+            Section::Function(funcs) => {
+                for (idx, ty) in funcs.iter().enumerate() {
+                    if used[Space::Func as usize].contains(&(imported_funcs + idx as u32)) {
+                        used[Space::Type as usize].insert(*ty);
+                    }
+                }
+            }
+            Section::Table(tables) => {
+                for table in tables {
+                    ref_type(&mut table.0, &mut record(&mut used));
+                }
+            }
+            Section::Tag(tags) => {
+                for tag in tags {
+                    used[Space::Type as usize].insert(tag.0);
+                }
+            }
+            Section::Code(codes) => {
+                for (idx, code) in codes.iter_mut().enumerate() {
+                    if used[Space::Func as usize].contains(&(imported_funcs + idx as u32)) {
+                        for locals in &mut code.1 .0 {
+                            val_type(&mut locals.1, &mut record(&mut used));
+                        }
+                        expr_indices(&mut code.1 .1, &mut record(&mut used));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for section in &mut module.sections {
+        match section {
+            Section::Element(elems) => {
+                for (idx, elem) in elems.iter_mut().enumerate() {
+                    if matches!(elem, Elem::D(..) | Elem::H(..)) {
+                        declared(elem, &used[Space::Func as usize]);
+                        if !elem_funcs(elem).is_empty() {
+                            used[Space::Elem as usize].insert(idx as u32);
+                        }
+                    }
+                    if used[Space::Elem as usize].contains(&(idx as u32)) {
+                        elem_indices(elem, &mut record(&mut used));
+                    }
+                }
+            }
+            Section::Data(datas) => {
+                for data in datas {
+                    if let Data::A(offset, _) | Data::C(_, offset, _) = data {
+                        expr_indices(offset, &mut record(&mut used));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    // Initializers only refer to earlier globals.
+    for section in &mut module.sections {
+        if let Section::Global(globals) = section {
+            for (idx, (ty, init)) in globals.iter_mut().enumerate().rev() {
+                if used[Space::Global as usize].contains(&(imported_globals + idx as u32)) {
+                    val_type(&mut ty.1, &mut record(&mut used));
+                    expr_indices(init, &mut record(&mut used));
+                }
+            }
+        }
+    }
+    // A recursion group is kept or removed as a whole, and types refer to each other.
+    loop {
+        let before = used[Space::Type as usize].len();
+        for section in &mut module.sections {
+            if let Section::Type(groups) = section {
+                let mut idx = 0;
+                for group in groups {
+                    let members = idx..idx + group.0.len() as u32;
+                    idx = members.end;
+                    if members
+                        .clone()
+                        .any(|ty| used[Space::Type as usize].contains(&ty))
+                    {
+                        used[Space::Type as usize].extend(members);
+                        for ty in &mut group.0 {
+                            sub_type(ty, &mut record(&mut used));
+                        }
+                    }
+                }
+            }
+        }
+        if used[Space::Type as usize].len() == before {
+            break;
+        }
+    }
+
+    let counts = [
+        func_count,
+        type_count,
+        global_count,
+        module.elems().len(),
+        module.datas().len(),
+    ];
+    let maps: Vec<Vec<Option<u32>>> = counts
+        .iter()
+        .zip(&used)
+        .map(|(count, used)| {
+            let mut next = 0;
+            (0..*count as u32)
+                .map(|idx| {
+                    used.contains(&idx).then(|| {
+                        next += 1;
+                        next - 1
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    let mut rename = |space: Space, idx: &mut u32| {
+        if let Some(Some(new)) = maps[space as usize].get(*idx as usize) {
+            *idx = *new;
+        }
+    };
+    let kept = |space: Space, idx: usize| maps[space as usize][idx].is_some();
+
+    for section in &mut module.sections {
+        match section {
+            Section::Type(groups) => {
+                let mut idx = 0;
+                groups.retain(|group| {
+                    let keep = kept(Space::Type, idx);
+                    idx += group.0.len();
+                    keep
+                });
+                for ty in groups.iter_mut().flat_map(|group| &mut group.0) {
+                    sub_type(ty, &mut rename);
+                }
+            }
+            Section::Import(imports) => {
+                for import in imports {
+                    import_indices(&mut import.desc, &mut rename);
+                }
+            }
+            Section::Function(funcs) => {
+                let mut idx = imported_funcs as usize;
+                funcs.retain(|_| {
+                    idx += 1;
+                    kept(Space::Func, idx - 1)
+                });
+                for ty in funcs {
+                    rename(Space::Type, ty);
+                }
+            }
+            Section::Table(tables) => {
+                for table in tables {
+                    ref_type(&mut table.0, &mut rename);
+                }
+            }
+            Section::Tag(tags) => {
+                for tag in tags {
+                    rename(Space::Type, &mut tag.0);
+                }
+            }
+            Section::Global(globals) => {
+                let mut idx = imported_globals as usize;
+                globals.retain(|_| {
+                    idx += 1;
+                    kept(Space::Global, idx - 1)
+                });
+                for (ty, init) in globals {
+                    val_type(&mut ty.1, &mut rename);
+                    expr_indices(init, &mut rename);
+                }
+            }
+            Section::Export(exports) => {
+                for (_, desc) in exports {
+                    match desc {
+                        ExportDesc::FuncIdx(func) => rename(Space::Func, func),
+                        ExportDesc::GlobalIdx(global) => rename(Space::Global, global),
+                        _ => {}
+                    }
+                }
+            }
+            Section::Start(func) => rename(Space::Func, func),
+            Section::Element(elems) => {
+                let mut idx = 0;
+                elems.retain(|_| {
+                    idx += 1;
+                    kept(Space::Elem, idx - 1)
+                });
+                for elem in elems {
+                    elem_indices(elem, &mut rename);
+                }
+            }
+            Section::Code(codes) => {
+                let mut idx = imported_funcs as usize;
+                codes.retain(|_| {
+                    idx += 1;
+                    kept(Space::Func, idx - 1)
+                });
+                for code in codes {
+                    for locals in &mut code.1 .0 {
+                        val_type(&mut locals.1, &mut rename);
+                    }
+                    expr_indices(&mut code.1 .1, &mut rename);
+                }
+            }
+            Section::Data(datas) => {
+                let mut idx = 0;
+                datas.retain(|_| {
+                    idx += 1;
+                    kept(Space::Data, idx - 1)
+                });
+                for data in datas {
+                    if let Data::A(offset, _) | Data::C(_, offset, _) = data {
+                        expr_indices(offset, &mut rename);
+                    }
+                }
+            }
+            Section::DataCountSection(count) => *count = used[Space::Data as usize].len() as u32,
+            Section::Custom(custom) if custom.0 == NameSec::NAME => {
+                if let Ok(names) = NameSec::parse(&*custom) {
+                    *custom = CustomSec::from(&rename_names(names, &maps));
+                }
+            }
+            Section::DecodedCustom(decoded) if decoded.sec.0 == NameSec::NAME => {
+                if let Ok(names) = NameSec::parse(&decoded.sec) {
+                    let names = rename_names(names, &maps);
+                    *decoded = DecodedCustomSec {
+                        sec: CustomSec::from(&names),
+                        payload: Box::new(names),
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let removed = |space: Space| counts[space as usize].saturating_sub(used[space as usize].len());
+    Removed {
+        funcs: removed(Space::Func),
+        types: removed(Space::Type),
+        globals: removed(Space::Global),
+        elems: removed(Space::Elem),
+        datas: removed(Space::Data),
+    }
+}
+
+/// Imports, plus everything reachable through calls, `ref.func` and the passive segments used by
+/// live code.
+fn live_funcs(module: &Module) -> BTreeSet<FuncIdx> {
+    let graph = CallGraph::new(module);
+    let imported = module.imported_funcs().count() as u32;
+    let elems = module.elems();
+
+    let mut stack: Vec<FuncIdx> = graph.roots().iter().copied().collect();
+    for elem in elems {
+        if matches!(elem, Elem::A(..) | Elem::C(..) | Elem::E(..) | Elem::G(..)) {
+            stack.extend(elem_funcs(elem));
+        }
+    }
+    for (_, init) in module.globals() {
+        visit(&init.0, &mut |instr| {
+            if let Instr::RefFunc(func) = instr {
+                stack.push(*func);
+            }
+        });
+    }
+
+    let mut live: BTreeSet<FuncIdx> = (0..imported).collect();
+    let codes = module.codes();
+    while let Some(func) = stack.pop() {
+        if !live.insert(func) {
+            continue;
+        }
+        stack.extend(graph.callees(func).map(|(callee, _)| callee));
+        let Some(code) = func
+            .checked_sub(imported)
+            .and_then(|idx| codes.get(idx as usize))
+        else {
+            continue;
+        };
+        visit(&code.1 .1 .0, &mut |instr| match instr {
+            Instr::RefFunc(func) => stack.push(*func),
+            Instr::TableInit(elem, _)
+            | Instr::ElemDrop(elem)
+            | Instr::ArrayNewElem(_, elem)
+            | Instr::ArrayInitElem(_, elem) => stack.extend(
+                elems
+                    .get(*elem as usize)
+                    .map(elem_funcs)
+                    .unwrap_or_default(),
+            ),
+            _ => {}
+        });
+    }
+    live
+}
+
+fn elem_funcs(elem: &Elem) -> Vec<FuncIdx> {
+    match elem {
+        Elem::A(_, funcs) | Elem::B(_, funcs) | Elem::C(_, _, _, funcs) | Elem::D(_, funcs) => {
+            funcs.clone()
+        }
+        Elem::E(_, exprs) | Elem::F(_, exprs) | Elem::G(_, _, _, exprs) | Elem::H(_, exprs) => {
+            let mut funcs = Vec::new();
+            for expr in exprs {
+                visit(&expr.0, &mut |instr| {
+                    if let Instr::RefFunc(func) = instr {
+                        funcs.push(*func);
+                    }
+                });
+            }
+            funcs
+        }
+    }
+}
+
+/// Drops the functions of a declarative segment that are no longer live.
+fn declared(elem: &mut Elem, live: &BTreeSet<FuncIdx>) {
+    match elem {
+        Elem::D(_, funcs) => funcs.retain(|func| live.contains(func)),
+        Elem::H(_, exprs) => exprs
+            .retain(|expr| !matches!(&expr.0[..], [Instr::RefFunc(func)] if !live.contains(func))),
+        _ => {}
+    }
+}
+
+fn rename_names(mut names: NameSec, maps: &[Vec<Option<u32>>]) -> NameSec {
+    fn map<T>(names: &mut Vec<(u32, T)>, map: &[Option<u32>]) {
+        names.retain_mut(|(idx, _)| match map.get(*idx as usize) {
+            Some(Some(new)) => {
+                *idx = *new;
+                true
+            }
+            _ => false,
+        });
+    }
+    let funcs = &maps[Space::Func as usize];
+    let types = &maps[Space::Type as usize];
+    map(&mut names.funcs, funcs);
+    map(&mut names.locals, funcs);
+    map(&mut names.labels, funcs);
+    map(&mut names.types, types);
+    map(&mut names.fields, types);
+    map(&mut names.globals, &maps[Space::Global as usize]);
+    map(&mut names.elems, &maps[Space::Elem as usize]);
+    map(&mut names.datas, &maps[Space::Data as usize]);
+    names
+}
+
+fn import_indices<F: FnMut(Space, &mut u32)>(desc: &mut ImportDesc, f: &mut F) {
+    match desc {
+        ImportDesc::TypeIdx(ty) => f(Space::Type, ty),
+        ImportDesc::TagType(tag) => f(Space::Type, &mut tag.0),
+        ImportDesc::TableType(table) => ref_type(&mut table.0, f),
+        ImportDesc::GlobalType(global) => val_type(&mut global.1, f),
+        ImportDesc::MemType(_) => {}
+    }
+}
+
+fn elem_indices<F: FnMut(Space, &mut u32)>(elem: &mut Elem, f: &mut F) {
+    match elem {
+        Elem::A(offset, funcs) | Elem::C(_, offset, _, funcs) => {
+            expr_indices(offset, f);
+            funcs.iter_mut().for_each(|func| f(Space::Func, func));
+        }
+        Elem::B(_, funcs) | Elem::D(_, funcs) => {
+            funcs.iter_mut().for_each(|func| f(Space::Func, func));
+        }
+        Elem::E(offset, exprs) => {
+            expr_indices(offset, f);
+            exprs.iter_mut().for_each(|expr| expr_indices(expr, f));
+        }
+        Elem::G(_, offset, ty, exprs) => {
+            expr_indices(offset, f);
+            ref_type(ty, f);
+            exprs.iter_mut().for_each(|expr| expr_indices(expr, f));
+        }
+        Elem::F(ty, exprs) | Elem::H(ty, exprs) => {
+            ref_type(ty, f);
+            exprs.iter_mut().for_each(|expr| expr_indices(expr, f));
+        }
+    }
+}
+
+fn expr_indices<F: FnMut(Space, &mut u32)>(expr: &mut Expr, f: &mut F) {
+    for instr in &mut expr.0 {
+        instr_indices(instr, f);
+    }
+}
+
+/// Calls `f` on every index the instruction refers to, including in nested blocks.
+fn instr_indices<F: FnMut(Space, &mut u32)>(instr: &mut Instr, f: &mut F) {
+    match instr {
+        Instr::Block(ty, _)
+        | Instr::Loop(ty, _)
+        | Instr::If(ty, _)
+        | Instr::IfElse(ty, _, _)
+        | Instr::Try(ty, _, _, _)
+        | Instr::TryDelegate(ty, _, _)
+        | Instr::TryTable(ty, _, _) => block_type(ty, f),
+        Instr::Call(func) | Instr::ReturnCall(func) | Instr::RefFunc(func) => f(Space::Func, func),
+        Instr::CallIndirect(ty, _)
+        | Instr::ReturnCallIndirect(ty, _)
+        | Instr::CallRef(ty)
+        | Instr::ReturnCallRef(ty)
+        | Instr::StructNew(ty)
+        | Instr::StructNewDefault(ty)
+        | Instr::StructGet(ty, _)
+        | Instr::StructGetS(ty, _)
+        | Instr::StructGetU(ty, _)
+        | Instr::StructSet(ty, _)
+        | Instr::ArrayNew(ty)
+        | Instr::ArrayNewDefault(ty)
+        | Instr::ArrayNewFixed(ty, _)
+        | Instr::ArrayGet(ty)
+        | Instr::ArrayGetS(ty)
+        | Instr::ArrayGetU(ty)
+        | Instr::ArraySet(ty)
+        | Instr::ArrayFill(ty) => f(Space::Type, ty),
+        Instr::ArrayCopy(dst, src) => {
+            f(Space::Type, dst);
+            f(Space::Type, src);
+        }
+        Instr::ArrayNewData(ty, data) | Instr::ArrayInitData(ty, data) => {
+            f(Space::Type, ty);
+            f(Space::Data, data);
+        }
+        Instr::ArrayNewElem(ty, elem) | Instr::ArrayInitElem(ty, elem) => {
+            f(Space::Type, ty);
+            f(Space::Elem, elem);
+        }
+        Instr::RefNull(heap) => heap_type(heap, f),
+        Instr::RefTest(ty) | Instr::RefCast(ty) => ref_type(ty, f),
+        Instr::BrOnCast(_, from, to) | Instr::BrOnCastFail(_, from, to) => {
+            ref_type(from, f);
+            ref_type(to, f);
+        }
+        Instr::SelectType(tys) => tys.iter_mut().for_each(|ty| val_type(ty, f)),
+        Instr::GlobalGet(global) | Instr::GlobalSet(global) => f(Space::Global, global),
+        Instr::TableInit(elem, _) | Instr::ElemDrop(elem) => f(Space::Elem, elem),
+        Instr::MemoryInit(data, _) | Instr::DataDrop(data) => f(Space::Data, data),
+        _ => {}
+    }
+    for block in instr.blocks_mut() {
+        for instr in block {
+            instr_indices(instr, f);
+        }
+    }
+}
+
+fn block_type<F: FnMut(Space, &mut u32)>(ty: &mut BlockType, f: &mut F) {
+    match ty {
+        BlockType::Empty => {}
+        BlockType::ValType(ty) => val_type(ty, f),
+        BlockType::X(idx) => {
+            let mut ty = *idx as u32;
+            f(Space::Type, &mut ty);
+            *idx = ty as i64;
+        }
+    }
+}
+
+fn heap_type<F: FnMut(Space, &mut u32)>(heap: &mut HeapType, f: &mut F) {
+    if let HeapType::Type(ty) = heap {
+        f(Space::Type, ty);
+    }
+}
+
+fn ref_type<F: FnMut(Space, &mut u32)>(ty: &mut RefType, f: &mut F) {
+    heap_type(&mut ty.1, f);
+}
+
+fn val_type<F: FnMut(Space, &mut u32)>(ty: &mut ValType, f: &mut F) {
+    if let ValType::RefType(ty) = ty {
+        ref_type(ty, f);
+    }
+}
+
+fn field_type<F: FnMut(Space, &mut u32)>(field: &mut FieldType, f: &mut F) {
+    if let StorageType::ValType(ty) = &mut field.0 {
+        val_type(ty, f);
+    }
+}
+
+fn sub_type<F: FnMut(Space, &mut u32)>(ty: &mut SubType, f: &mut F) {
+    for sup in &mut ty.1 {
+        f(Space::Type, sup);
+    }
+    match &mut ty.2 {
+        CompType::Func(func) => func
+            .0
+            .iter_mut()
+            .chain(&mut func.1)
+            .for_each(|ty| val_type(ty, f)),
+        CompType::Struct(fields) => fields.iter_mut().for_each(|field| field_type(field, f)),
+        CompType::Array(field) => field_type(field, f),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shake, Removed};
+    use crate::custom::NameSec;
+    use crate::modules::Module;
+    use crate::validate::validate;
+    use crate::{Encode, Parse};
+
+    #[test]
+    fn shake_module() {
+        let mut module = Module::parse(
+            r#"(module
+                (type $unused (func (param i64)))
+                (import "env" "log" (func $log (param i32)))
+                (memory 1)
+                (table 1 funcref)
+                (global $g (mut i32) (i32.const 1))
+                (global $dead_g i32 (i32.const 2))
+                (elem (i32.const 0) $a)
+                (data $d "abc")
+                (data $used "xyz")
+                (func $main (export "main")
+                    (call $helper (global.get $g))
+                    (memory.init $used (i32.const 0) (i32.const 0) (i32.const 1)))
+                (func $helper (param i32) (call $log (local.get 0)))
+                (func $a)
+                (func $dead (type $unused)
+                    (drop (global.get $dead_g))
+                    (memory.init $d (i32.const 0) (i32.const 0) (i32.const 1))
+                    (call $helper (i32.const 0))))"#,
+        )
+        .unwrap();
+        let removed = shake(&mut module);
+        assert_eq!(
+            removed,
+            Removed {
+                funcs: 1,
+                types: 1,
+                globals: 1,
+                elems: 0,
+                datas: 1,
+            }
+        );
+
+        let mut data = Vec::new();
+        module.encode(&mut data);
+        let module = Module::parse(&mut data).unwrap();
+        validate(&module).unwrap();
+        assert_eq!(module.codes().len(), 3);
+        assert_eq!(module.globals().len(), 1);
+        assert_eq!(module.datas().len(), 1);
+        let names = NameSec::parse(module.custom_sec(NameSec::NAME).unwrap()).unwrap();
+        let funcs: Vec<_> = names
+            .funcs
+            .iter()
+            .map(|(idx, name)| (*idx, name.as_str()))
+            .collect();
+        assert_eq!(funcs, [(0, "log"), (1, "main"), (2, "helper"), (3, "a")]);
+        assert_eq!(names.datas, [(0, "used".to_string())]);
+    }
+}
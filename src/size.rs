@@ -0,0 +1,399 @@
+use std::fmt::Write;
+
+use crate::callgraph::CallGraph;
+use crate::custom::NameSec;
+use crate::modules::{FuncIdx, Module, Section};
+use crate::{Encode, Parse, IB};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub name: String,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuncSize {
+    pub idx: FuncIdx,
+    pub name: String,
+    /// Size of the body, as recorded in the code section of a parsed binary.
+    pub size: usize,
+    /// Own size plus that of the functions only reachable through this one.
+    pub retained: usize,
+}
+
+/// Where the bytes of an encoded module go. Parsed binaries are measured as
+/// they were read, other modules as they would be encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeProfile {
+    pub total: usize,
+    pub sections: Vec<Item>,
+    pub funcs: Vec<FuncSize>,
+    pub datas: Vec<Item>,
+    pub customs: Vec<Item>,
+}
+
+fn encoded_len(item: &impl Encode) -> usize {
+    let mut data = IB::new();
+    item.encode(&mut data);
+    data.len()
+}
+
+fn section_name(section: &Section) -> String {
+    match section {
+        Section::Custom(custom) => format!("custom \"{}\"", custom.0),
+        Section::DecodedCustom(decoded) => format!("custom \"{}\"", decoded.sec.0),
+        Section::Type(_) => "type".to_string(),
+        Section::Import(_) => "import".to_string(),
+        Section::Function(_) => "function".to_string(),
+        Section::Table(_) => "table".to_string(),
+        Section::Memory(_) => "memory".to_string(),
+        Section::Tag(_) => "tag".to_string(),
+        Section::Global(_) => "global".to_string(),
+        Section::Export(_) => "export".to_string(),
+        Section::Start(_) => "start".to_string(),
+        Section::Element(_) => "element".to_string(),
+        Section::Code(_) => "code".to_string(),
+        Section::Data(_) => "data".to_string(),
+        Section::DataCountSection(_) => "datacount".to_string(),
+        Section::Unknown(id, _) => format!("unknown {id}"),
+    }
+}
+
+impl SizeProfile {
+    pub fn new(module: &Module) -> Self {
+        let layout = module.layout();
+        let section_sizes: Vec<_> = match layout {
+            Some(layout) => layout.sections.iter().map(|range| range.len()).collect(),
+            None => module.sections.iter().map(encoded_len).collect(),
+        };
+        let sections: Vec<_> = module
+            .sections
+            .iter()
+            .zip(&section_sizes)
+            .map(|(section, &size)| Item {
+                name: section_name(section),
+                size,
+            })
+            .collect();
+        let total = layout.map_or_else(
+            || 8 + sections.iter().map(|item| item.size).sum::<usize>(),
+            |layout| layout.len,
+        );
+
+        let customs = module
+            .sections
+            .iter()
+            .zip(&section_sizes)
+            .filter_map(|(section, &size)| {
+                let name = match section {
+                    Section::Custom(custom) => &custom.0,
+                    Section::DecodedCustom(decoded) => &decoded.sec.0,
+                    _ => return None,
+                };
+                Some(Item {
+                    name: name.clone(),
+                    size,
+                })
+            })
+            .collect();
+
+        let names = module
+            .custom_sec(NameSec::NAME)
+            .and_then(|sec| NameSec::parse(sec).ok())
+            .unwrap_or_default();
+        let datas = module
+            .datas()
+            .iter()
+            .enumerate()
+            .map(|(idx, data)| Item {
+                name: names
+                    .datas
+                    .iter()
+                    .find(|(data, _)| *data == idx as u32)
+                    .map_or_else(|| format!("data[{idx}]"), |(_, name)| name.clone()),
+                size: layout.map_or_else(|| encoded_len(data), |layout| layout.datas[idx].len()),
+            })
+            .collect();
+
+        let graph = CallGraph::new(module);
+        let imported = module.imported_funcs().count();
+        let mut shallow = vec![0; graph.len()];
+        for (idx, code) in module.codes().iter().enumerate() {
+            shallow[imported + idx] = match layout {
+                Some(_) => code.0 as usize,
+                None => encoded_len(&code.1),
+            };
+        }
+        // Every function counts towards itself and each of its dominators.
+        let idom = graph.dominators();
+        let reachable = graph.reachable();
+        let mut retained = shallow.clone();
+        for func in &reachable {
+            let mut dom = idom[*func as usize];
+            while let Some(func_dom) = dom {
+                retained[func_dom as usize] += shallow[*func as usize];
+                dom = idom[func_dom as usize];
+            }
+        }
+        let funcs = (0..graph.len() as FuncIdx)
+            .map(|func| FuncSize {
+                idx: func,
+                name: graph.name(func).to_string(),
+                size: shallow[func as usize],
+                retained: retained[func as usize],
+            })
+            .collect();
+
+        Self {
+            total,
+            sections,
+            funcs,
+            datas,
+            customs,
+        }
+    }
+
+    /// Human readable report, with functions and data segments largest first.
+    pub fn to_table(&self) -> String {
+        let percent = |size: usize| 100.0 * size as f64 / self.total.max(1) as f64;
+        let mut out = String::new();
+        let items = |out: &mut String, title: &str, items: &[Item]| {
+            if items.is_empty() {
+                return;
+            }
+            let _ = writeln!(out, "{title}\n{:>10} {:>7}  name", "bytes", "%");
+            for item in items {
+                let (size, name) = (item.size, &item.name);
+                let _ = writeln!(out, "{size:>10} {:>6.2}%  {name}", percent(size));
+            }
+            out.push('\n');
+        };
+        items(&mut out, "Sections", &self.sections);
+
+        let mut funcs: Vec<_> = self.funcs.iter().filter(|func| func.size > 0).collect();
+        funcs.sort_by_key(|func| (std::cmp::Reverse(func.retained), func.idx));
+        if !funcs.is_empty() {
+            let _ = writeln!(
+                out,
+                "Functions\n{:>10} {:>7} {:>10} {:>7}  name",
+                "shallow", "%", "retained", "%"
+            );
+            for func in funcs {
+                let _ = writeln!(
+                    out,
+                    "{:>10} {:>6.2}% {:>10} {:>6.2}%  {}",
+                    func.size,
+                    percent(func.size),
+                    func.retained,
+                    percent(func.retained),
+                    func.name
+                );
+            }
+            out.push('\n');
+        }
+
+        let mut datas = self.datas.clone();
+        datas.sort_by_key(|data| std::cmp::Reverse(data.size));
+        items(&mut out, "Data segments", &datas);
+        items(&mut out, "Custom sections", &self.customs);
+        let _ = writeln!(out, "Total: {} bytes", self.total);
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let items = |items: &[Item]| {
+            let items: Vec<_> = items
+                .iter()
+                .map(|item| {
+                    format!(
+                        "{{\"name\":{},\"size\":{}}}",
+                        json_string(&item.name),
+                        item.size
+                    )
+                })
+                .collect();
+            format!("[{}]", items.join(","))
+        };
+        let funcs: Vec<_> = self
+            .funcs
+            .iter()
+            .map(|func| {
+                format!(
+                    "{{\"index\":{},\"name\":{},\"size\":{},\"retained\":{}}}",
+                    func.idx,
+                    json_string(&func.name),
+                    func.size,
+                    func.retained
+                )
+            })
+            .collect();
+        format!(
+            "{{\"total\":{},\"sections\":{},\"functions\":[{}],\"data\":{},\"custom\":{}}}",
+            self.total,
+            items(&self.sections),
+            funcs.join(","),
+            items(&self.datas),
+            items(&self.customs)
+        )
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SizeProfile;
+    use crate::modules::{Data, Module, Section};
+    use crate::{Encode, Parse, IB};
+
+    /// A LEB128 padded to five bytes, as `wasm-ld` writes sizes.
+    fn padded(value: u32) -> [u8; 5] {
+        let mut bytes = [0; 5];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = (value >> (7 * i)) as u8 & 0x7f | if i < 4 { 0x80 } else { 0 };
+        }
+        bytes
+    }
+
+    #[test]
+    fn profile() {
+        let mut module = Module::parse(
+            r#"(module
+                (memory 1)
+                (data $greeting (i32.const 0) "hello world")
+                (func $main (export "main") (call $helper) (call $shared))
+                (func $helper (call $leaf) (call $shared))
+                (func $leaf (drop (i32.add (i32.const 1) (i32.const 2))))
+                (func $shared)
+                (func $dead (call $leaf)))"#,
+        )
+        .unwrap();
+        let profile = SizeProfile::new(&module);
+        let sizes: Vec<_> = profile.funcs.iter().map(|func| func.size).collect();
+        let retained: Vec<_> = profile.funcs.iter().map(|func| func.retained).collect();
+        let [main, helper, leaf, shared, dead] = sizes[..] else {
+            panic!("{sizes:?}");
+        };
+        assert_eq!(
+            retained,
+            [
+                main + helper + leaf + shared,
+                helper + leaf,
+                leaf,
+                shared,
+                dead
+            ]
+        );
+        assert_eq!(profile.funcs[0].name, "main");
+        assert_eq!(profile.datas[0].name, "greeting");
+        assert_eq!(
+            profile.total,
+            8 + profile.sections.iter().map(|item| item.size).sum::<usize>()
+        );
+        assert_eq!(profile.customs[0].name, "name");
+
+        let table = profile.to_table();
+        assert!(table.lines().any(|line| line.ends_with("%  main")));
+        let json = profile.to_json();
+        assert!(json.starts_with(&format!("{{\"total\":{},", profile.total)));
+        assert!(json.contains(&format!(
+            "{{\"index\":1,\"name\":\"helper\",\"size\":{helper},\"retained\":{}}}",
+            helper + leaf
+        )));
+
+        // Without a layout bodies are measured by encoding them, so stale
+        // recorded sizes don't matter.
+        for section in &mut module.sections {
+            if let Section::Code(codes) = section {
+                codes.iter_mut().for_each(|code| code.0 = 0);
+            }
+        }
+        assert_eq!(SizeProfile::new(&module), profile);
+    }
+
+    #[test]
+    fn padded_binary() {
+        let module = Module::parse(
+            r#"(module
+                (memory 1)
+                (data (i32.const 0) "hello world")
+                (func $main (export "main") (call $leaf))
+                (func $leaf (drop (i32.const 1))))"#,
+        )
+        .unwrap();
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        for section in &module.sections {
+            let mut content = IB::new();
+            match section {
+                Section::Code(codes) => {
+                    (codes.len() as u32).encode(&mut content);
+                    for code in codes {
+                        let mut body = IB::new();
+                        code.1.encode(&mut body);
+                        content.extend(padded(body.len() as u32));
+                        content.extend(body);
+                    }
+                }
+                section => section.encode_content(&mut content),
+            }
+            bytes.push(section.id());
+            bytes.extend(padded(content.len() as u32));
+            bytes.extend(content);
+        }
+        let len = bytes.len();
+        let parsed = Module::parse(&mut bytes).unwrap();
+
+        let profile = SizeProfile::new(&parsed);
+        let encoded = SizeProfile::new(&module);
+        assert_eq!(profile.total, len);
+        assert_eq!(
+            profile.total,
+            8 + profile.sections.iter().map(|item| item.size).sum::<usize>()
+        );
+        for (section, (item, encoded)) in module
+            .sections
+            .iter()
+            .zip(profile.sections.iter().zip(&encoded.sections))
+        {
+            let padding = match section {
+                Section::Code(codes) => 4 * (1 + codes.len()),
+                _ => 4,
+            };
+            assert_eq!(item.size, encoded.size + padding, "{}", item.name);
+        }
+        // Bodies don't include their size, data segments are unpadded.
+        assert_eq!(profile.funcs, encoded.funcs);
+        assert_eq!(profile.datas, encoded.datas);
+
+        // A segment added after parsing isn't in the layout, so it is dropped
+        // rather than indexed past its end.
+        let mut parsed = parsed;
+        let mut module = module;
+        for module in [&mut parsed, &mut module] {
+            for section in &mut module.sections {
+                if let Section::Data(datas) = section {
+                    datas.push(Data::B(b"added".to_vec()));
+                }
+            }
+        }
+        assert!(parsed.layout().is_none());
+        assert_eq!(SizeProfile::new(&parsed), SizeProfile::new(&module));
+    }
+}
@@ -0,0 +1,452 @@
+use std::backtrace::Backtrace;
+use std::collections::BTreeMap;
+
+use crate::{Error, Parse};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    pub name: Option<String>,
+}
+
+/// Resolves a byte offset in the module binary to a position in the original source.
+/// Lines and columns are 1 based. Only source maps implement it, DWARF line
+/// tables are not read.
+pub trait SourceLookup {
+    fn lookup(&self, offset: u32) -> Option<SourceLocation>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub generated_line: u32,
+    pub generated_column: u32,
+    pub source: Option<u32>,
+    pub line: u32,
+    pub column: u32,
+    pub name: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMap {
+    pub version: u32,
+    pub file: Option<String>,
+    pub source_root: Option<String>,
+    pub sources: Vec<String>,
+    pub names: Vec<String>,
+    pub mappings: Vec<Mapping>,
+}
+
+impl SourceLookup for SourceMap {
+    fn lookup(&self, offset: u32) -> Option<SourceLocation> {
+        // WebAssembly source maps put everything on the first generated line,
+        // with the column being the byte offset in the binary.
+        let index = self
+            .mappings
+            .partition_point(|m| m.generated_line == 0 && m.generated_column <= offset);
+        let mapping = self.mappings[..index].last()?;
+        let source = self.sources.get(mapping.source? as usize)?;
+        let file = match &self.source_root {
+            Some(root) if !root.is_empty() && !root.ends_with('/') => format!("{root}/{source}"),
+            Some(root) => format!("{root}{source}"),
+            None => source.clone(),
+        };
+        Some(SourceLocation {
+            file,
+            line: mapping.line + 1,
+            column: mapping.column + 1,
+            name: mapping
+                .name
+                .and_then(|name| self.names.get(name as usize).cloned()),
+        })
+    }
+}
+
+impl Parse<&str> for SourceMap {
+    fn parse(data: &str) -> Result<Self, Error> {
+        let mut parser = JsonParser {
+            data: data.as_bytes(),
+            pos: 0,
+        };
+        let json = parser.value()?;
+        let Json::Object(mut object) = json else {
+            return Err(invalid("expected a json object"));
+        };
+
+        let version = match object.remove("version") {
+            Some(Json::Number(version)) => version as u32,
+            _ => return Err(invalid("missing version")),
+        };
+        if version != 3 {
+            return Err(invalid(&format!("unsupported version {version}")));
+        }
+
+        let string = |value: Option<Json>| match value {
+            Some(Json::String(s)) => Some(s),
+            _ => None,
+        };
+        let strings = |value: Option<Json>| -> Result<Vec<String>, Error> {
+            match value {
+                Some(Json::Array(items)) => items
+                    .into_iter()
+                    .map(|item| match item {
+                        Json::String(s) => Ok(s),
+                        Json::Null => Ok(String::new()),
+                        _ => Err(invalid("expected a string")),
+                    })
+                    .collect(),
+                None => Ok(Vec::new()),
+                _ => Err(invalid("expected an array")),
+            }
+        };
+
+        let file = string(object.remove("file"));
+        let source_root = string(object.remove("sourceRoot"));
+        let sources = strings(object.remove("sources"))?;
+        let names = strings(object.remove("names"))?;
+        let Some(mappings) = string(object.remove("mappings")) else {
+            return Err(invalid("missing mappings"));
+        };
+        let mut mappings = parse_mappings(&mappings)?;
+        mappings.sort_by_key(|m| (m.generated_line, m.generated_column));
+
+        Ok(Self {
+            version,
+            file,
+            source_root,
+            sources,
+            names,
+            mappings,
+        })
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::InvalidSourceMap(Backtrace::capture(), msg.to_string())
+}
+
+fn parse_mappings(data: &str) -> Result<Vec<Mapping>, Error> {
+    let mut mappings = Vec::new();
+    let mut source = 0i64;
+    let mut line = 0i64;
+    let mut column = 0i64;
+    let mut name = 0i64;
+
+    for (generated_line, group) in data.split(';').enumerate() {
+        let mut generated_column = 0i64;
+        for segment in group.split(',').filter(|s| !s.is_empty()) {
+            let fields = decode_vlq(segment)?;
+            let mut mapping = Mapping {
+                generated_line: generated_line as u32,
+                generated_column: advance(&mut generated_column, fields[0], "generated column")?,
+                source: None,
+                line: 0,
+                column: 0,
+                name: None,
+            };
+            match fields.len() {
+                1 => {}
+                4 | 5 => {
+                    mapping.source = Some(advance(&mut source, fields[1], "source index")?);
+                    mapping.line = advance(&mut line, fields[2], "line")?;
+                    mapping.column = advance(&mut column, fields[3], "column")?;
+                    if let Some(&delta) = fields.get(4) {
+                        mapping.name = Some(advance(&mut name, delta, "name index")?);
+                    }
+                }
+                n => return Err(invalid(&format!("segment with {n} fields"))),
+            }
+            mappings.push(mapping);
+        }
+    }
+    Ok(mappings)
+}
+
+/// Adds a relative field to its running value, which must stay a `u32`.
+fn advance(value: &mut i64, delta: i64, field: &str) -> Result<u32, Error> {
+    *value = value
+        .checked_add(delta)
+        .ok_or_else(|| invalid(&format!("{field} out of range")))?;
+    u32::try_from(*value).map_err(|_| invalid(&format!("{field} out of range")))
+}
+
+fn decode_vlq(segment: &str) -> Result<Vec<i64>, Error> {
+    let mut fields = Vec::new();
+    let mut value = 0i64;
+    let mut shift = 0;
+    for c in segment.bytes() {
+        let digit = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(invalid(&format!("invalid base64 digit {}", c as char))),
+        } as i64;
+        if shift > 60 {
+            return Err(invalid("vlq overflow"));
+        }
+        value += (digit & 31) << shift;
+        if digit & 32 != 0 {
+            shift += 5;
+        } else {
+            let negative = value & 1 == 1;
+            value >>= 1;
+            fields.push(if negative { -value } else { value });
+            value = 0;
+            shift = 0;
+        }
+    }
+    if shift != 0 {
+        return Err(invalid("unterminated vlq"));
+    }
+    Ok(fields)
+}
+
+#[derive(Debug)]
+enum Json {
+    Null,
+    Bool,
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+struct JsonParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_ws(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.data.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        self.skip_ws();
+        if self.data.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(invalid(&format!(
+                "expected `{}` at {}",
+                byte as char, self.pos
+            )))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, Error> {
+        self.skip_ws();
+        match self.data.get(self.pos) {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut object = BTreeMap::new();
+                self.skip_ws();
+                if self.data.get(self.pos) == Some(&b'}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(object));
+                }
+                loop {
+                    self.skip_ws();
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    object.insert(key, self.value()?);
+                    self.skip_ws();
+                    match self.data.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(Json::Object(object));
+                        }
+                        _ => return Err(invalid(&format!("unexpected byte at {}", self.pos))),
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut array = Vec::new();
+                self.skip_ws();
+                if self.data.get(self.pos) == Some(&b']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(array));
+                }
+                loop {
+                    array.push(self.value()?);
+                    self.skip_ws();
+                    match self.data.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(Json::Array(array));
+                        }
+                        _ => return Err(invalid(&format!("unexpected byte at {}", self.pos))),
+                    }
+                }
+            }
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b't') => self.keyword("true", Json::Bool),
+            Some(b'f') => self.keyword("false", Json::Bool),
+            Some(b'n') => self.keyword("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.pos;
+                while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') =
+                    self.data.get(self.pos)
+                {
+                    self.pos += 1;
+                }
+                let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap();
+                text.parse()
+                    .map(Json::Number)
+                    .map_err(|_| invalid(&format!("invalid number {text}")))
+            }
+            _ => Err(invalid(&format!("unexpected byte at {}", self.pos))),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, Error> {
+        if self.data[self.pos..].starts_with(keyword.as_bytes()) {
+            self.pos += keyword.len();
+            Ok(value)
+        } else {
+            Err(invalid(&format!("unexpected byte at {}", self.pos)))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect(b'"')?;
+        let mut buffer = Vec::new();
+        loop {
+            let Some(&byte) = self.data.get(self.pos) else {
+                return Err(invalid("unterminated string"));
+            };
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escape) = self.data.get(self.pos) else {
+                        return Err(invalid("unterminated string"));
+                    };
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            if (0xD800..0xDC00).contains(&code)
+                                && self.data[self.pos..].starts_with(b"\\u")
+                            {
+                                self.pos += 2;
+                                let low = self.hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(invalid("invalid surrogate pair"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            // Lone surrogates are not characters.
+                            char::from_u32(code).ok_or_else(|| invalid("invalid surrogate pair"))?
+                        }
+                        _ => return Err(invalid("invalid escape")),
+                    };
+                    let mut utf8 = [0; 4];
+                    buffer.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                }
+                _ => buffer.push(byte),
+            }
+        }
+        String::from_utf8(buffer).map_err(|_| invalid("invalid utf8"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, Error> {
+        let digits = self
+            .data
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| invalid("unterminated escape"))?;
+        self.pos += 4;
+        let digits = std::str::from_utf8(digits).map_err(|_| invalid("invalid escape"))?;
+        u32::from_str_radix(digits, 16).map_err(|_| invalid("invalid escape"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_vlq, SourceLocation, SourceLookup, SourceMap};
+    use crate::Parse;
+
+    #[test]
+    fn vlq() {
+        assert_eq!(decode_vlq("AAAA").unwrap(), vec![0, 0, 0, 0]);
+        assert_eq!(decode_vlq("gBACD").unwrap(), vec![16, 0, 1, -1]);
+        assert_eq!(decode_vlq("2HAAA").unwrap(), vec![123, 0, 0, 0]);
+    }
+
+    #[test]
+    fn lookup() {
+        let map = SourceMap::parse(
+            r#"{
+                "version": 3,
+                "sources": ["src/main.rs", "src/lib.rs"],
+                "names": ["main"],
+                "mappings": "gBAAAA,EAAE,ECEAA,I"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(map.lookup(15), None);
+        assert_eq!(
+            map.lookup(16),
+            Some(SourceLocation {
+                file: "src/main.rs".to_string(),
+                line: 1,
+                column: 1,
+                name: Some("main".to_string()),
+            })
+        );
+        assert_eq!(map.lookup(19).unwrap().column, 3);
+        assert_eq!(
+            map.lookup(20),
+            Some(SourceLocation {
+                file: "src/lib.rs".to_string(),
+                line: 3,
+                column: 3,
+                name: Some("main".to_string()),
+            })
+        );
+        assert_eq!(map.lookup(24), None);
+    }
+
+    #[test]
+    fn negative() {
+        let map = |mappings: &str| {
+            SourceMap::parse(&*format!(
+                r#"{{"version": 3, "sources": ["a", "b"], "names": ["n"], "mappings": "{mappings}"}}"#
+            ))
+        };
+        assert!(map("AACA,AADA").is_ok());
+        for mappings in ["D", "ADAA", "AADA", "AAAD", "AAAAD", "AAAA;AAAD"] {
+            assert!(map(mappings).is_err(), "{mappings}");
+        }
+    }
+
+    #[test]
+    fn surrogates() {
+        let map = |sources: &str| {
+            SourceMap::parse(&*format!(
+                r#"{{"version": 3, "sources": [{sources}], "mappings": "AAAA"}}"#
+            ))
+        };
+        assert_eq!(map(r#""\uD83D\uDE00""#).unwrap().sources, ["\u{1F600}"]);
+        assert!(map(r#""\uD800\u0041""#).is_err());
+        assert!(map(r#""\uD800""#).is_err());
+        assert!(map(r#""\uDE00x""#).is_err());
+    }
+}
@@ -1,8 +1,8 @@
 use std::backtrace::Backtrace;
 
-use crate::{Error, Parse, IB};
+use crate::{modules::TypeIdx, Buffer, Encode, Error, Parse, IB};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum NumType {
     I32,
     I64,
@@ -10,18 +10,80 @@ pub enum NumType {
     F64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum VecType {
     V128,
 }
 
-#[derive(Debug)]
-pub enum RefType {
-    FuncRef,
-    ExternRef,
+/// Abstract heap types, or a concrete one referring to a type index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeapType {
+    Func,
+    Extern,
+    Exn,
+    Any,
+    Eq,
+    I31,
+    Struct,
+    Array,
+    None,
+    NoFunc,
+    NoExtern,
+    NoExn,
+    Type(TypeIdx),
 }
 
-#[derive(Debug)]
+impl HeapType {
+    /// Encoding of an abstract heap type, which is also the shorthand for the
+    /// nullable reference to it.
+    pub fn code(&self) -> Option<u8> {
+        Some(match self {
+            Self::Func => 0x70,
+            Self::Extern => 0x6F,
+            Self::Exn => 0x69,
+            Self::Any => 0x6E,
+            Self::Eq => 0x6D,
+            Self::I31 => 0x6C,
+            Self::Struct => 0x6B,
+            Self::Array => 0x6A,
+            Self::None => 0x71,
+            Self::NoFunc => 0x73,
+            Self::NoExtern => 0x72,
+            Self::NoExn => 0x74,
+            Self::Type(_) => return None,
+        })
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            0x70 => Self::Func,
+            0x6F => Self::Extern,
+            0x69 => Self::Exn,
+            0x6E => Self::Any,
+            0x6D => Self::Eq,
+            0x6C => Self::I31,
+            0x6B => Self::Struct,
+            0x6A => Self::Array,
+            0x71 => Self::None,
+            0x73 => Self::NoFunc,
+            0x72 => Self::NoExtern,
+            0x74 => Self::NoExn,
+            _ => return None,
+        })
+    }
+}
+
+/// Whether the reference is nullable, and the heap type it refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RefType(pub bool, pub HeapType);
+
+impl RefType {
+    pub const FUNCREF: Self = Self(true, HeapType::Func);
+    pub const EXTERNREF: Self = Self(true, HeapType::Extern);
+    pub const EXNREF: Self = Self(true, HeapType::Exn);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ValType {
     NumType(NumType),
     VecType(VecType),
@@ -30,19 +92,83 @@ pub enum ValType {
 
 pub type ResultType = Vec<ValType>;
 
-#[derive(Debug)]
-pub struct FuncType(ResultType, ResultType);
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuncType(pub ResultType, pub ResultType);
+
+/// Packed types only occur as struct and array fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageType {
+    ValType(ValType),
+    I8,
+    I16,
+}
+
+impl StorageType {
+    /// Type of the field's values on the operand stack.
+    pub fn unpacked(&self) -> ValType {
+        match self {
+            Self::ValType(ty) => *ty,
+            Self::I8 | Self::I16 => ValType::NumType(NumType::I32),
+        }
+    }
+}
+
+/// Storage type and whether the field is mutable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldType(pub StorageType, pub bool);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompType {
+    Func(FuncType),
+    Struct(Vec<FieldType>),
+    Array(FieldType),
+}
 
-#[derive(Debug)]
-pub struct Limits(u32, Option<u32>);
+/// Whether the type is final, its declared supertypes and its structure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubType(pub bool, pub Vec<TypeIdx>, pub CompType);
+
+impl SubType {
+    pub fn func(&self) -> Option<&FuncType> {
+        match &self.2 {
+            CompType::Func(ty) => Some(ty),
+            _ => None,
+        }
+    }
+}
+
+impl From<FuncType> for SubType {
+    fn from(ty: FuncType) -> Self {
+        Self(true, Vec::new(), CompType::Func(ty))
+    }
+}
+
+/// A recursion group, whose types may refer to each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecType(pub Vec<SubType>);
+
+/// Minimum, optional maximum, whether the memory is shared between threads and
+/// whether the memory or table is indexed with i64 (memory64 and table64).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits(pub u64, pub Option<u64>, pub bool, pub bool);
+
+impl Limits {
+    /// Type of addresses, sizes and lengths used with this memory or table.
+    pub fn index_type(&self) -> ValType {
+        match self.3 {
+            true => ValType::NumType(NumType::I64),
+            false => ValType::NumType(NumType::I32),
+        }
+    }
+}
 
 pub type MemType = Limits;
 
-#[derive(Debug)]
-pub struct TableType(RefType, Limits);
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableType(pub RefType, pub Limits);
 
-#[derive(Debug)]
-pub struct GlobalType(bool, ValType);
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalType(pub bool, pub ValType);
 
 impl Parse<u8> for NumType {
     fn parse(value: u8) -> Result<Self, Error> {
@@ -66,24 +192,60 @@ impl Parse<u8> for VecType {
     }
 }
 
-impl Parse<u8> for RefType {
-    fn parse(value: u8) -> Result<Self, Error> {
-        Ok(match value {
-            0x70 => Self::FuncRef,
-            0x6F => Self::ExternRef,
-            _ => return Err(Error::InvalidRefType(Backtrace::capture(), value)),
+impl Parse<&mut IB> for HeapType {
+    fn parse(value: &mut IB) -> Result<Self, Error> {
+        if value.is_empty() {
+            return Err(Error::EndOfBuffer(Backtrace::capture()));
+        }
+
+        match crate::read_sleb(value, 33)? {
+            idx @ 0..=0xFFFF_FFFF => Ok(Self::Type(idx as TypeIdx)),
+            code => {
+                let code = code as u8 & 0x7F;
+                Self::from_code(code)
+                    .ok_or_else(|| Error::InvalidRefType(Backtrace::capture(), code))
+            }
+        }
+    }
+}
+
+impl Parse<&mut IB> for RefType {
+    fn parse(value: &mut IB) -> Result<Self, Error> {
+        if value.is_empty() {
+            return Err(Error::EndOfBuffer(Backtrace::capture()));
+        }
+
+        let byte = value.drain(0..1).next().unwrap();
+        Ok(match byte {
+            0x63 => Self(true, HeapType::parse(value)?),
+            0x64 => Self(false, HeapType::parse(value)?),
+            _ => match HeapType::from_code(byte) {
+                Some(heap) => Self(true, heap),
+                None => return Err(Error::InvalidRefType(Backtrace::capture(), byte)),
+            },
         })
     }
 }
 
-impl Parse<u8> for ValType {
-    fn parse(value: u8) -> Result<Self, Error> {
-        Ok(match value {
-            0x6F..=0x70 => Self::RefType(RefType::parse(value)?),
-            0x7B => Self::VecType(VecType::V128),
+impl ValType {
+    /// Whether `byte` can start the encoding of a value type.
+    pub fn starts(byte: u8) -> bool {
+        matches!(byte, 0x63 | 0x64 | 0x69..=0x74 | 0x7B..=0x7F)
+    }
+}
+
+impl Parse<&mut IB> for ValType {
+    fn parse(value: &mut IB) -> Result<Self, Error> {
+        if value.is_empty() {
+            return Err(Error::EndOfBuffer(Backtrace::capture()));
+        }
+
+        Ok(match *value.first().unwrap() {
+            0x63 | 0x64 | 0x69..=0x74 => Self::RefType(RefType::parse(value)?),
+            0x7B => Self::VecType(VecType::parse(value)?),
             0x7C..=0x7F => Self::NumType(NumType::parse(value)?),
 
-            _ => return Err(Error::InvalidValType(Backtrace::capture(), value)),
+            byte => return Err(Error::InvalidValType(Backtrace::capture(), byte)),
         })
     }
 }
@@ -91,7 +253,9 @@ impl Parse<u8> for ValType {
 impl<T: for<'a> Parse<&'a mut IB>> Parse<&mut IB> for Vec<T> {
     fn parse(value: &mut IB) -> Result<Self, Error> {
         let len = u32::parse(&mut *value)?;
-        let mut buffer = std::vec::Vec::<T>::with_capacity(len as usize);
+        // Every element takes at least a byte, so a bogus length can't
+        // reserve more than the input.
+        let mut buffer = std::vec::Vec::<T>::with_capacity(value.len().min(len as usize));
         for _ in 0..len {
             buffer.push(T::parse(value)?)
         }
@@ -118,6 +282,71 @@ impl Parse<&mut IB> for FuncType {
     }
 }
 
+impl Parse<&mut IB> for StorageType {
+    fn parse(value: &mut IB) -> Result<Self, Error> {
+        match value.first() {
+            Some(0x78) => {
+                value.drain(0..1);
+                Ok(Self::I8)
+            }
+            Some(0x77) => {
+                value.drain(0..1);
+                Ok(Self::I16)
+            }
+            _ => Ok(Self::ValType(ValType::parse(value)?)),
+        }
+    }
+}
+
+impl Parse<&mut IB> for FieldType {
+    fn parse(value: &mut IB) -> Result<Self, Error> {
+        let ty = StorageType::parse(&mut *value)?;
+        Ok(Self(ty, mutability(value)?))
+    }
+}
+
+impl Parse<&mut IB> for CompType {
+    fn parse(value: &mut IB) -> Result<Self, Error> {
+        match value.first() {
+            None => Err(Error::EndOfBuffer(Backtrace::capture())),
+            Some(0x5F) => {
+                value.drain(0..1);
+                Ok(Self::Struct(Vec::parse(value)?))
+            }
+            Some(0x5E) => {
+                value.drain(0..1);
+                Ok(Self::Array(FieldType::parse(value)?))
+            }
+            Some(_) => Ok(Self::Func(FuncType::parse(value)?)),
+        }
+    }
+}
+
+impl Parse<&mut IB> for SubType {
+    fn parse(value: &mut IB) -> Result<Self, Error> {
+        match value.first() {
+            Some(&byte @ (0x4F | 0x50)) => {
+                value.drain(0..1);
+                let supers = Vec::parse(&mut *value)?;
+                Ok(Self(byte == 0x4F, supers, CompType::parse(value)?))
+            }
+            _ => Ok(Self(true, Vec::new(), CompType::parse(value)?)),
+        }
+    }
+}
+
+impl Parse<&mut IB> for RecType {
+    fn parse(value: &mut IB) -> Result<Self, Error> {
+        match value.first() {
+            Some(0x4E) => {
+                value.drain(0..1);
+                Ok(Self(Vec::parse(value)?))
+            }
+            _ => Ok(Self(vec![SubType::parse(value)?])),
+        }
+    }
+}
+
 impl Parse<&mut IB> for Limits {
     fn parse(value: &mut IB) -> Result<Self, Error> {
         if value.is_empty() {
@@ -126,11 +355,18 @@ impl Parse<&mut IB> for Limits {
 
         let byte = value.drain(0..1).next().unwrap();
 
-        Ok(match byte {
-            0 => Self(u32::parse(value)?, None),
-            1 => Self(u32::parse(&mut *value)?, Some(u32::parse(value)?)),
-            _ => return Err(Error::InvalidLimits(Backtrace::capture(), byte)),
-        })
+        if byte > 7 {
+            return Err(Error::InvalidLimits(Backtrace::capture(), byte));
+        }
+        let shared = byte & 2 != 0;
+        let is64 = byte & 4 != 0;
+        let mut bound = || match is64 {
+            true => u64::parse(&mut *value),
+            false => u32::parse(&mut *value).map(u64::from),
+        };
+        let min = bound()?;
+        let max = if byte & 1 != 0 { Some(bound()?) } else { None };
+        Ok(Self(min, max, shared, is64))
     }
 }
 
@@ -143,10 +379,163 @@ impl Parse<&mut IB> for TableType {
 impl Parse<&mut IB> for GlobalType {
     fn parse(value: &mut IB) -> Result<Self, Error> {
         let valtype = ValType::parse(&mut *value)?;
-        if value.is_empty() {
-            return Err(Error::EndOfBuffer(Backtrace::capture()));
+        Ok(Self(mutability(value)?, valtype))
+    }
+}
+
+fn mutability(value: &mut IB) -> Result<bool, Error> {
+    match value.first() {
+        None => Err(Error::EndOfBuffer(Backtrace::capture())),
+        Some(&byte @ (0 | 1)) => {
+            value.drain(0..1);
+            Ok(byte == 1)
         }
-        let byte = value.drain(0..1).next().unwrap();
-        Ok(Self(byte > 0, valtype))
+        Some(_) => Err(Error::InvalidModule(
+            Backtrace::capture(),
+            "malformed mutability".to_string(),
+        )),
+    }
+}
+
+impl Encode for NumType {
+    fn encode(&self, data: &mut IB) {
+        data.push(match self {
+            Self::I32 => 0x7F,
+            Self::I64 => 0x7E,
+            Self::F32 => 0x7D,
+            Self::F64 => 0x7C,
+        })
+    }
+}
+
+impl Encode for VecType {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::V128 => data.push(0x7B),
+        }
+    }
+}
+
+impl Encode for HeapType {
+    fn encode(&self, data: &mut IB) {
+        match (self, self.code()) {
+            (Self::Type(idx), _) => data.write_sleb128(*idx as i64),
+            (_, code) => data.push(code.unwrap()),
+        }
+    }
+}
+
+impl Encode for RefType {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self(true, HeapType::Type(_)) => data.push(0x63),
+            Self(true, ht) => return ht.encode(data),
+            Self(false, _) => data.push(0x64),
+        }
+        self.1.encode(data);
+    }
+}
+
+impl Encode for ValType {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::NumType(t) => t.encode(data),
+            Self::VecType(t) => t.encode(data),
+            Self::RefType(t) => t.encode(data),
+        }
+    }
+}
+
+impl Encode for FuncType {
+    fn encode(&self, data: &mut IB) {
+        data.push(0x60);
+        self.0.encode(data);
+        self.1.encode(data);
+    }
+}
+
+impl Encode for StorageType {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::ValType(ty) => ty.encode(data),
+            Self::I8 => data.push(0x78),
+            Self::I16 => data.push(0x77),
+        }
+    }
+}
+
+impl Encode for FieldType {
+    fn encode(&self, data: &mut IB) {
+        self.0.encode(data);
+        data.push(self.1 as u8);
+    }
+}
+
+impl Encode for CompType {
+    fn encode(&self, data: &mut IB) {
+        match self {
+            Self::Func(ty) => ty.encode(data),
+            Self::Struct(fields) => {
+                data.push(0x5F);
+                fields.encode(data);
+            }
+            Self::Array(field) => {
+                data.push(0x5E);
+                field.encode(data);
+            }
+        }
+    }
+}
+
+impl Encode for SubType {
+    fn encode(&self, data: &mut IB) {
+        if !self.0 || !self.1.is_empty() {
+            data.push(if self.0 { 0x4F } else { 0x50 });
+            self.1.encode(data);
+        }
+        self.2.encode(data);
+    }
+}
+
+impl Encode for RecType {
+    fn encode(&self, data: &mut IB) {
+        match &self.0[..] {
+            [ty] => ty.encode(data),
+            _ => {
+                data.push(0x4E);
+                self.0.encode(data);
+            }
+        }
+    }
+}
+
+impl Encode for Limits {
+    fn encode(&self, data: &mut IB) {
+        let flags = if self.2 { 2 } else { 0 } | if self.3 { 4 } else { 0 };
+        match self.1 {
+            None => {
+                data.push(flags);
+                self.0.encode(data);
+            }
+            Some(max) => {
+                data.push(flags | 1);
+                self.0.encode(data);
+                max.encode(data);
+            }
+        }
+    }
+}
+
+impl Encode for TableType {
+    fn encode(&self, data: &mut IB) {
+        self.0.encode(data);
+        self.1.encode(data);
+    }
+}
+
+impl Encode for GlobalType {
+    fn encode(&self, data: &mut IB) {
+        self.1.encode(data);
+        data.push(self.0 as u8);
     }
 }
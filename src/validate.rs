@@ -0,0 +1,1911 @@
+//! Module validation, following the algorithm in the appendix of the spec.
+//! Errors use the messages expected by `assert_invalid` in the spec test suite.
+
+use std::backtrace::Backtrace;
+use std::collections::HashSet;
+
+use crate::features::WasmFeatures;
+use crate::instructions::{BlockType, Instr};
+use crate::modules::*;
+use crate::printer::natural_align;
+use crate::types::*;
+use crate::Error;
+
+const I32: ValType = ValType::NumType(NumType::I32);
+const I64: ValType = ValType::NumType(NumType::I64);
+const F32: ValType = ValType::NumType(NumType::F32);
+const F64: ValType = ValType::NumType(NumType::F64);
+const V128: ValType = ValType::VecType(VecType::V128);
+const EXNREF: ValType = ValType::RefType(RefType::EXNREF);
+const EQREF: ValType = ValType::RefType(RefType(true, HeapType::Eq));
+const I31REF: ValType = ValType::RefType(RefType(true, HeapType::I31));
+const ARRAYREF: ValType = ValType::RefType(RefType(true, HeapType::Array));
+
+const MAX_PAGES: u64 = 0x10000;
+const MAX_PAGES64: u64 = 1 << 48;
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::InvalidModule(Backtrace::capture(), msg.into())
+}
+
+fn defaultable(ty: ValType) -> bool {
+    !matches!(ty, ValType::RefType(RefType(false, _)))
+}
+
+/// Reference to a concrete struct or array type.
+fn typed_ref(nullable: bool, idx: TypeIdx) -> ValType {
+    ValType::RefType(RefType(nullable, HeapType::Type(idx)))
+}
+
+pub fn validate(module: &Module) -> Result<(), Error> {
+    validate_with(module, &WasmFeatures::default())
+}
+
+/// Validates a module that may only use the proposals enabled in `features`.
+pub fn validate_with(module: &Module, features: &WasmFeatures) -> Result<(), Error> {
+    features.check(module)?;
+    Context::new(module, features).module()
+}
+
+/// The index spaces of the module being validated.
+struct Context<'a> {
+    module: &'a Module,
+    features: &'a WasmFeatures,
+    types: Vec<&'a SubType>,
+    funcs: Vec<TypeIdx>,
+    tables: Vec<TableType>,
+    mems: Vec<MemType>,
+    globals: Vec<GlobalType>,
+    tags: Vec<TypeIdx>,
+    elems: Vec<RefType>,
+    data_count: Option<u32>,
+    /// Functions that may be referenced with `ref.func` in function bodies.
+    refs: HashSet<FuncIdx>,
+}
+
+impl<'a> Context<'a> {
+    fn new(module: &'a Module, features: &'a WasmFeatures) -> Self {
+        let mut ctx = Self {
+            module,
+            features,
+            types: module.types(),
+            funcs: Vec::new(),
+            tables: Vec::new(),
+            mems: Vec::new(),
+            globals: Vec::new(),
+            tags: Vec::new(),
+            elems: Vec::new(),
+            data_count: module.sections.iter().find_map(|section| match section {
+                Section::DataCountSection(count) => Some(*count),
+                _ => None,
+            }),
+            refs: HashSet::new(),
+        };
+        for import in module.imports() {
+            match &import.desc {
+                ImportDesc::TypeIdx(ty) => ctx.funcs.push(*ty),
+                ImportDesc::TableType(ty) => ctx.tables.push(ty.clone()),
+                ImportDesc::MemType(ty) => ctx.mems.push(ty.clone()),
+                ImportDesc::GlobalType(ty) => ctx.globals.push(ty.clone()),
+                ImportDesc::TagType(ty) => ctx.tags.push(ty.0),
+            }
+        }
+        ctx.funcs.extend(module.funcs());
+        ctx.tables.extend(module.tables().iter().cloned());
+        ctx.mems.extend(module.mems().iter().cloned());
+        ctx.globals
+            .extend(module.globals().iter().map(|(ty, _)| ty.clone()));
+        ctx.tags.extend(module.tags().iter().map(|ty| ty.0));
+        for elem in module.elems() {
+            ctx.elems.push(match elem {
+                Elem::A(_, funcs)
+                | Elem::B(_, funcs)
+                | Elem::C(_, _, _, funcs)
+                | Elem::D(_, funcs) => {
+                    ctx.refs.extend(funcs);
+                    RefType::FUNCREF
+                }
+                Elem::E(_, exprs) => {
+                    ctx.refs.extend(exprs.iter().flat_map(funcs_in));
+                    RefType::FUNCREF
+                }
+                Elem::F(ty, exprs) | Elem::G(_, _, ty, exprs) | Elem::H(ty, exprs) => {
+                    ctx.refs.extend(exprs.iter().flat_map(funcs_in));
+                    *ty
+                }
+            });
+        }
+        for (_, init) in module.globals() {
+            ctx.refs.extend(funcs_in(init));
+        }
+        for (_, desc) in module.exports() {
+            if let ExportDesc::FuncIdx(func) = desc {
+                ctx.refs.insert(*func);
+            }
+        }
+        ctx
+    }
+
+    fn module(&self) -> Result<(), Error> {
+        for import in self.module.imports() {
+            match &import.desc {
+                ImportDesc::TypeIdx(ty) => {
+                    self.ty(*ty)?;
+                }
+                ImportDesc::TableType(ty) => table(ty)?,
+                ImportDesc::MemType(ty) => memory(ty)?,
+                ImportDesc::GlobalType(_) => {}
+                ImportDesc::TagType(_) => {}
+            }
+        }
+        for ty in self.module.funcs() {
+            self.ty(*ty)?;
+        }
+        for (idx, ty) in self.types.iter().enumerate() {
+            let valtypes: Vec<ValType> = match &ty.2 {
+                CompType::Func(ty) => ty.0.iter().chain(&ty.1).copied().collect(),
+                CompType::Struct(fields) => fields.iter().map(|field| field.0.unpacked()).collect(),
+                CompType::Array(field) => vec![field.0.unpacked()],
+            };
+            for valtype in valtypes {
+                self.valtype(valtype)?;
+            }
+            if ty.1.len() > 1 {
+                return Err(invalid(format!("type {idx} has more than one supertype")));
+            }
+            for sup in &ty.1 {
+                if *sup as usize >= idx {
+                    return Err(invalid(format!("unknown type {sup}")));
+                }
+                let parent = self.types[*sup as usize];
+                if parent.0 || !self.comp_subtype(&ty.2, &parent.2) {
+                    return Err(invalid(format!(
+                        "sub type {idx} does not match super type {sup}"
+                    )));
+                }
+            }
+        }
+        for ty in self.module.tables() {
+            table(ty)?;
+            self.valtype(ValType::RefType(ty.0))?;
+            // Non-nullable tables would need an initializer expression.
+            if !ty.0 .0 {
+                return Err(invalid("type mismatch"));
+            }
+        }
+        for ty in self.module.mems() {
+            memory(ty)?;
+        }
+        for idx in 0..self.tags.len() {
+            if !self.tag(idx as TagIdx)?.1.is_empty() {
+                return Err(invalid("non-empty tag result type"));
+            }
+        }
+
+        let imported = self.globals.len() - self.module.globals().len();
+        for (i, (ty, init)) in self.module.globals().iter().enumerate() {
+            self.valtype(ty.1)?;
+            self.const_expr(init, ty.1, imported + i)?;
+        }
+
+        let mut names = HashSet::new();
+        for (name, desc) in self.module.exports() {
+            if !names.insert(name) {
+                return Err(invalid("duplicate export name"));
+            }
+            match desc {
+                ExportDesc::FuncIdx(idx) => {
+                    self.func(*idx)?;
+                }
+                ExportDesc::TableIdx(idx) => {
+                    self.table(*idx)?;
+                }
+                ExportDesc::MemIdx(idx) => {
+                    self.mem(*idx)?;
+                }
+                ExportDesc::GlobalIdx(idx) => {
+                    self.global(*idx)?;
+                }
+                ExportDesc::TagIdx(idx) => {
+                    self.tag(*idx)?;
+                }
+            }
+        }
+
+        if let Some(start) = self.module.start() {
+            let ty = self.func(start)?;
+            if !ty.0.is_empty() || !ty.1.is_empty() {
+                return Err(invalid("start function"));
+            }
+        }
+
+        for (elem, ty) in self.module.elems().iter().zip(&self.elems) {
+            let (active, funcs, exprs) = match elem {
+                Elem::A(offset, funcs) => (Some((0, offset)), &funcs[..], &[][..]),
+                Elem::B(_, funcs) | Elem::D(_, funcs) => (None, &funcs[..], &[][..]),
+                Elem::C(table, offset, _, funcs) => (Some((*table, offset)), &funcs[..], &[][..]),
+                Elem::E(offset, exprs) => (Some((0, offset)), &[][..], &exprs[..]),
+                Elem::F(_, exprs) | Elem::H(_, exprs) => (None, &[][..], &exprs[..]),
+                Elem::G(table, offset, _, exprs) => (Some((*table, offset)), &[][..], &exprs[..]),
+            };
+            if let Some((table, offset)) = active {
+                let table = self.table(table)?;
+                if !self.ref_subtype(*ty, table.0) {
+                    return Err(invalid("type mismatch"));
+                }
+                self.const_expr(offset, table.1.index_type(), self.globals.len())?;
+            }
+            for func in funcs {
+                self.func(*func)?;
+            }
+            for expr in exprs {
+                self.const_expr(expr, ValType::RefType(*ty), self.globals.len())?;
+            }
+        }
+
+        for data in self.module.datas() {
+            let (mem, offset) = match data {
+                Data::A(offset, _) => (0, offset),
+                Data::C(mem, offset, _) => (*mem, offset),
+                Data::B(_) => continue,
+            };
+            let ty = self.mem(mem)?.index_type();
+            self.const_expr(offset, ty, self.globals.len())?;
+        }
+        if let Some(count) = self.data_count {
+            if count as usize != self.module.datas().len() {
+                return Err(invalid(
+                    "data count and data section have inconsistent lengths",
+                ));
+            }
+        }
+
+        let codes = self.module.codes();
+        if codes.len() != self.module.funcs().len() {
+            return Err(invalid(
+                "function and code section have inconsistent lengths",
+            ));
+        }
+        for (ty, code) in self.module.funcs().iter().zip(codes) {
+            self.code(self.ty(*ty)?, &code.1)?;
+        }
+        Ok(())
+    }
+
+    fn sub(&self, idx: TypeIdx) -> Result<&'a SubType, Error> {
+        self.types
+            .get(idx as usize)
+            .copied()
+            .ok_or_else(|| invalid(format!("unknown type {idx}")))
+    }
+
+    fn ty(&self, idx: TypeIdx) -> Result<&'a FuncType, Error> {
+        self.sub(idx)?
+            .func()
+            .ok_or_else(|| invalid(format!("type {idx} is not a function type")))
+    }
+
+    fn struct_type(&self, idx: TypeIdx) -> Result<&'a [FieldType], Error> {
+        match &self.sub(idx)?.2 {
+            CompType::Struct(fields) => Ok(fields),
+            _ => Err(invalid(format!("type {idx} is not a struct type"))),
+        }
+    }
+
+    fn field(&self, ty: TypeIdx, idx: FieldIdx) -> Result<FieldType, Error> {
+        self.struct_type(ty)?
+            .get(idx as usize)
+            .copied()
+            .ok_or_else(|| invalid(format!("unknown field {idx}")))
+    }
+
+    fn array_type(&self, idx: TypeIdx) -> Result<FieldType, Error> {
+        match &self.sub(idx)?.2 {
+            CompType::Array(field) => Ok(*field),
+            _ => Err(invalid(format!("type {idx} is not an array type"))),
+        }
+    }
+
+    /// Top of the hierarchy a heap type belongs to.
+    fn top(&self, heap: HeapType) -> HeapType {
+        match heap {
+            HeapType::Func | HeapType::NoFunc => HeapType::Func,
+            HeapType::Extern | HeapType::NoExtern => HeapType::Extern,
+            HeapType::Exn | HeapType::NoExn => HeapType::Exn,
+            HeapType::Type(idx) if self.sub(idx).is_ok_and(|ty| ty.func().is_some()) => {
+                HeapType::Func
+            }
+            _ => HeapType::Any,
+        }
+    }
+
+    /// Concrete types match their declared supertypes and the abstract type
+    /// of their kind, bottom types match everything in their hierarchy.
+    fn heap_subtype(&self, actual: HeapType, expected: HeapType) -> bool {
+        if actual == expected {
+            return true;
+        }
+        match (actual, expected) {
+            (HeapType::Type(idx), HeapType::Type(_)) => self.sub(idx).is_ok_and(|ty| {
+                ty.1.iter()
+                    .any(|sup| *sup < idx && self.heap_subtype(HeapType::Type(*sup), expected))
+            }),
+            (HeapType::Type(idx), _) => match self.sub(idx).map(|ty| &ty.2) {
+                Ok(CompType::Func(_)) => expected == HeapType::Func,
+                Ok(CompType::Struct(_)) => self.heap_subtype(HeapType::Struct, expected),
+                Ok(CompType::Array(_)) => self.heap_subtype(HeapType::Array, expected),
+                Err(_) => false,
+            },
+            (HeapType::None | HeapType::NoFunc | HeapType::NoExtern | HeapType::NoExn, _) => {
+                self.top(actual) == self.top(expected)
+            }
+            (HeapType::I31 | HeapType::Struct | HeapType::Array, HeapType::Eq | HeapType::Any) => {
+                true
+            }
+            (HeapType::Eq, HeapType::Any) => true,
+            _ => false,
+        }
+    }
+
+    fn ref_subtype(&self, actual: RefType, expected: RefType) -> bool {
+        (!actual.0 || expected.0) && self.heap_subtype(actual.1, expected.1)
+    }
+
+    /// Whether a value of type `actual` may be used where `expected` is required.
+    fn subtype(&self, actual: ValType, expected: ValType) -> bool {
+        match (actual, expected) {
+            (ValType::RefType(actual), ValType::RefType(expected)) => {
+                self.ref_subtype(actual, expected)
+            }
+            _ => actual == expected,
+        }
+    }
+
+    fn subtypes(&self, actual: &[ValType], expected: &[ValType]) -> bool {
+        actual.len() == expected.len()
+            && actual
+                .iter()
+                .zip(expected)
+                .all(|(actual, expected)| self.subtype(*actual, *expected))
+    }
+
+    fn storage_subtype(&self, actual: StorageType, expected: StorageType) -> bool {
+        match (actual, expected) {
+            (StorageType::ValType(actual), StorageType::ValType(expected)) => {
+                self.subtype(actual, expected)
+            }
+            _ => actual == expected,
+        }
+    }
+
+    /// Mutable fields are invariant, immutable ones covariant.
+    fn field_subtype(&self, actual: FieldType, expected: FieldType) -> bool {
+        actual.1 == expected.1
+            && self.storage_subtype(actual.0, expected.0)
+            && (!actual.1 || self.storage_subtype(expected.0, actual.0))
+    }
+
+    fn comp_subtype(&self, actual: &CompType, expected: &CompType) -> bool {
+        match (actual, expected) {
+            (CompType::Func(actual), CompType::Func(expected)) => {
+                self.subtypes(&expected.0, &actual.0) && self.subtypes(&actual.1, &expected.1)
+            }
+            (CompType::Struct(actual), CompType::Struct(expected)) => {
+                actual.len() >= expected.len()
+                    && actual
+                        .iter()
+                        .zip(expected)
+                        .all(|(actual, expected)| self.field_subtype(*actual, *expected))
+            }
+            (CompType::Array(actual), CompType::Array(expected)) => {
+                self.field_subtype(*actual, *expected)
+            }
+            _ => false,
+        }
+    }
+
+    /// Value types may only refer to types that exist.
+    fn valtype(&self, ty: ValType) -> Result<(), Error> {
+        if let ValType::RefType(RefType(_, HeapType::Type(idx))) = ty {
+            self.sub(idx)?;
+        }
+        Ok(())
+    }
+
+    fn func(&self, idx: FuncIdx) -> Result<&'a FuncType, Error> {
+        match self.funcs.get(idx as usize) {
+            Some(ty) => self.ty(*ty),
+            None => Err(invalid(format!("unknown function {idx}"))),
+        }
+    }
+
+    fn table(&self, idx: TableIdx) -> Result<&TableType, Error> {
+        self.tables
+            .get(idx as usize)
+            .ok_or_else(|| invalid(format!("unknown table {idx}")))
+    }
+
+    fn mem(&self, idx: MemIdx) -> Result<&MemType, Error> {
+        self.mems
+            .get(idx as usize)
+            .ok_or_else(|| invalid(format!("unknown memory {idx}")))
+    }
+
+    fn global(&self, idx: GlobalIdx) -> Result<&GlobalType, Error> {
+        self.globals
+            .get(idx as usize)
+            .ok_or_else(|| invalid(format!("unknown global {idx}")))
+    }
+
+    fn tag(&self, idx: TagIdx) -> Result<&'a FuncType, Error> {
+        match self.tags.get(idx as usize) {
+            Some(ty) => self.ty(*ty),
+            None => Err(invalid(format!("unknown tag {idx}"))),
+        }
+    }
+
+    fn elem(&self, idx: ElemIdx) -> Result<RefType, Error> {
+        self.elems
+            .get(idx as usize)
+            .copied()
+            .ok_or_else(|| invalid(format!("unknown elem segment {idx}")))
+    }
+
+    fn data(&self, idx: DataIdx) -> Result<(), Error> {
+        match self.data_count {
+            None => Err(invalid("data count section required")),
+            Some(count) if idx >= count => Err(invalid(format!("unknown data segment {idx}"))),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// A constant expression producing `ty`, which may read the first `globals` globals.
+    fn const_expr(&self, expr: &Expr, ty: ValType, globals: usize) -> Result<(), Error> {
+        for instr in &expr.0 {
+            match instr {
+                Instr::I32Const(_)
+                | Instr::I64Const(_)
+                | Instr::F32Const(_)
+                | Instr::F64Const(_)
+                | Instr::V128_Const(_)
+                | Instr::RefNull(_)
+                | Instr::RefFunc(_)
+                | Instr::StructNew(_)
+                | Instr::StructNewDefault(_)
+                | Instr::ArrayNew(_)
+                | Instr::ArrayNewDefault(_)
+                | Instr::ArrayNewFixed(_, _)
+                | Instr::RefI31
+                | Instr::AnyConvertExtern
+                | Instr::ExternConvertAny => {}
+                Instr::I32Add
+                | Instr::I32Sub
+                | Instr::I32Mul
+                | Instr::I64Add
+                | Instr::I64Sub
+                | Instr::I64Mul
+                    if self.features.extended_const => {}
+                Instr::GlobalGet(idx) => {
+                    if *idx as usize >= globals {
+                        return Err(invalid(format!("unknown global {idx}")));
+                    }
+                    if self.globals[*idx as usize].0 {
+                        return Err(invalid("constant expression required"));
+                    }
+                }
+                _ => return Err(invalid("constant expression required")),
+            }
+        }
+        let mut validator = Validator::new(self, Vec::new(), vec![ty]);
+        validator.const_expr = true;
+        validator.body(&expr.0)
+    }
+
+    fn code(&self, ty: &FuncType, func: &Func) -> Result<(), Error> {
+        // Locals are kept as runs, a function may declare billions of them.
+        let mut locals: Vec<_> = (1..).zip(ty.0.iter().copied()).collect();
+        let mut count = locals.len() as u64;
+        for Locals(n, ty) in &func.0 {
+            count += *n as u64;
+            if count > u32::MAX as u64 {
+                return Err(invalid("too many locals"));
+            }
+            self.valtype(*ty)?;
+            if *n > 0 {
+                locals.push((count, *ty));
+            }
+        }
+        let mut validator = Validator::new(self, locals, ty.1.clone());
+        validator.params = ty.0.len() as u64;
+        validator.body(&func.1 .0)
+    }
+}
+
+/// Functions referenced by `ref.func` in a constant expression.
+fn funcs_in(expr: &Expr) -> impl Iterator<Item = FuncIdx> + '_ {
+    expr.0.iter().filter_map(|instr| match instr {
+        Instr::RefFunc(func) => Some(*func),
+        _ => None,
+    })
+}
+
+fn limits(limits: &Limits, max: u64, range: &str) -> Result<(), Error> {
+    if limits.0 > max || limits.1.is_some_and(|m| m > max) {
+        return Err(invalid(range));
+    }
+    if limits.1.is_some_and(|m| m < limits.0) {
+        return Err(invalid("size minimum must not be greater than maximum"));
+    }
+    Ok(())
+}
+
+fn table(ty: &TableType) -> Result<(), Error> {
+    if ty.1 .2 {
+        return Err(invalid("tables cannot be shared"));
+    }
+    match ty.1 .3 {
+        true => limits(&ty.1, u64::MAX, "table size must be at most 2^64-1"),
+        false => limits(&ty.1, u32::MAX as u64, "table size must be at most 2^32-1"),
+    }
+}
+
+fn memory(ty: &MemType) -> Result<(), Error> {
+    if ty.2 && ty.1.is_none() {
+        return Err(invalid("shared memory must have maximum"));
+    }
+    match ty.3 {
+        true => limits(
+            ty,
+            MAX_PAGES64,
+            "memory size must be at most 2^48 pages (256TiB)",
+        ),
+        false => limits(
+            ty,
+            MAX_PAGES,
+            "memory size must be at most 65536 pages (4GiB)",
+        ),
+    }
+}
+
+struct Frame {
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    is_loop: bool,
+    /// Handler of a legacy `try`, the only kind of label `rethrow` may target.
+    is_catch: bool,
+    height: usize,
+    /// Height of `Validator::set`, locals initialized inside the block are
+    /// uninitialized again after it.
+    inits: usize,
+    unreachable: bool,
+}
+
+impl Frame {
+    fn label(&self) -> &[ValType] {
+        match self.is_loop {
+            true => &self.params,
+            false => &self.results,
+        }
+    }
+}
+
+/// Type checks a function body or constant expression. `None` on the operand
+/// stack is the unknown type left by unreachable code.
+struct Validator<'a, 'b> {
+    ctx: &'b Context<'a>,
+    /// Runs of locals of the same type, as the index one past the run and the type.
+    locals: Vec<(u64, ValType)>,
+    /// Parameters are initialized by the caller, other locals only if they
+    /// have a default value.
+    params: u64,
+    /// Non-defaultable locals that have been initialized.
+    inits: HashSet<LocalIdx>,
+    /// Locals that were initialized by a `local.set` or `local.tee`.
+    set: Vec<LocalIdx>,
+    results: Vec<ValType>,
+    stack: Vec<Option<ValType>>,
+    frames: Vec<Frame>,
+    const_expr: bool,
+}
+
+impl<'a, 'b> Validator<'a, 'b> {
+    fn new(ctx: &'b Context<'a>, locals: Vec<(u64, ValType)>, results: Vec<ValType>) -> Self {
+        Self {
+            ctx,
+            params: 0,
+            inits: HashSet::new(),
+            set: Vec::new(),
+            locals,
+            results,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            const_expr: false,
+        }
+    }
+
+    fn body(&mut self, instrs: &[Instr]) -> Result<(), Error> {
+        let results = self.results.clone();
+        self.push_frame(Vec::new(), results, false);
+        self.instrs(instrs)?;
+        self.pop_frame()?;
+        Ok(())
+    }
+
+    fn push(&mut self, ty: Option<ValType>) {
+        self.stack.push(ty);
+    }
+
+    fn push_vals(&mut self, types: &[ValType]) {
+        self.stack.extend(types.iter().map(|ty| Some(*ty)));
+    }
+
+    fn pop(&mut self) -> Result<Option<ValType>, Error> {
+        let frame = self.frames.last().unwrap();
+        if self.stack.len() == frame.height {
+            return match frame.unreachable {
+                true => Ok(None),
+                false => Err(invalid("type mismatch")),
+            };
+        }
+        Ok(self.stack.pop().unwrap())
+    }
+
+    fn pop_expect(&mut self, expected: ValType) -> Result<Option<ValType>, Error> {
+        match self.pop()? {
+            Some(actual) if !self.ctx.subtype(actual, expected) => Err(invalid("type mismatch")),
+            _ => Ok(Some(expected)),
+        }
+    }
+
+    fn pop_vals(&mut self, types: &[ValType]) -> Result<Vec<Option<ValType>>, Error> {
+        let mut popped = Vec::with_capacity(types.len());
+        for ty in types.iter().rev() {
+            popped.push(self.pop_expect(*ty)?);
+        }
+        popped.reverse();
+        Ok(popped)
+    }
+
+    fn op(&mut self, params: &[ValType], results: &[ValType]) -> Result<(), Error> {
+        self.pop_vals(params)?;
+        self.push_vals(results);
+        Ok(())
+    }
+
+    fn push_frame(&mut self, params: Vec<ValType>, results: Vec<ValType>, is_loop: bool) {
+        self.frames.push(Frame {
+            height: self.stack.len(),
+            inits: self.set.len(),
+            unreachable: false,
+            is_loop,
+            is_catch: false,
+            results,
+            params: params.clone(),
+        });
+        self.push_vals(&params);
+    }
+
+    fn pop_frame(&mut self) -> Result<Frame, Error> {
+        let results = self.frames.last().unwrap().results.clone();
+        self.pop_vals(&results)?;
+        let frame = self.frames.pop().unwrap();
+        if self.stack.len() != frame.height {
+            return Err(invalid("type mismatch"));
+        }
+        for idx in self.set.drain(frame.inits..) {
+            self.inits.remove(&idx);
+        }
+        Ok(frame)
+    }
+
+    /// Pops a reference, `None` if its type is unknown.
+    fn pop_ref(&mut self) -> Result<Option<RefType>, Error> {
+        match self.pop()? {
+            Some(ValType::RefType(ty)) => Ok(Some(ty)),
+            Some(_) => Err(invalid("type mismatch")),
+            None => Ok(None),
+        }
+    }
+
+    fn unreachable(&mut self) {
+        let frame = self.frames.last_mut().unwrap();
+        self.stack.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn label(&self, idx: LabelIdx) -> Result<Vec<ValType>, Error> {
+        self.frames
+            .len()
+            .checked_sub(idx as usize + 1)
+            .map(|i| self.frames[i].label().to_vec())
+            .ok_or_else(|| invalid(format!("unknown label {idx}")))
+    }
+
+    fn block_type(&self, ty: &BlockType) -> Result<(Vec<ValType>, Vec<ValType>), Error> {
+        Ok(match ty {
+            BlockType::Empty => (Vec::new(), Vec::new()),
+            BlockType::ValType(ty) => (Vec::new(), vec![*ty]),
+            BlockType::X(idx) => {
+                let ty = self.ctx.ty(*idx as u32)?;
+                (ty.0.clone(), ty.1.clone())
+            }
+        })
+    }
+
+    /// The callee of a tail call returns directly to our caller, so its
+    /// results must be the results of the current function.
+    fn tail_call(&mut self, ty: &FuncType) -> Result<(), Error> {
+        if !self.ctx.subtypes(&ty.1, &self.results) {
+            return Err(invalid("type mismatch"));
+        }
+        self.pop_vals(&ty.0)?;
+        self.unreachable();
+        Ok(())
+    }
+
+    /// A `catch` or `catch_all` handler, starting with the exception payload.
+    fn handler(
+        &mut self,
+        payload: Vec<ValType>,
+        results: Vec<ValType>,
+        body: &[Instr],
+    ) -> Result<(), Error> {
+        self.push_frame(payload, results, false);
+        self.frames.last_mut().unwrap().is_catch = true;
+        self.instrs(body)?;
+        self.pop_frame()?;
+        Ok(())
+    }
+
+    /// Element type of a mutable array.
+    fn mutable_array(&self, idx: TypeIdx) -> Result<FieldType, Error> {
+        let field = self.ctx.array_type(idx)?;
+        if !field.1 {
+            return Err(invalid("array is immutable"));
+        }
+        Ok(field)
+    }
+
+    /// Element type of an array that can be initialized from a data segment.
+    fn numeric_array(&self, idx: TypeIdx) -> Result<FieldType, Error> {
+        let field = self.ctx.array_type(idx)?;
+        if let StorageType::ValType(ValType::RefType(_)) = field.0 {
+            return Err(invalid("array type is not numeric or vector"));
+        }
+        Ok(field)
+    }
+
+    /// Element type of an array that can be initialized from element segment `elem`.
+    fn elem_array(&self, idx: TypeIdx, elem: ElemIdx) -> Result<FieldType, Error> {
+        let field = self.ctx.array_type(idx)?;
+        let ty = ValType::RefType(self.ctx.elem(elem)?);
+        if !self.ctx.storage_subtype(StorageType::ValType(ty), field.0) {
+            return Err(invalid("type mismatch"));
+        }
+        Ok(field)
+    }
+
+    /// Checks that `struct.get` or `array.get` and their signed variants
+    /// match whether the field is packed.
+    fn packed(instr: &Instr, field: FieldType) -> Result<ValType, Error> {
+        let signed = matches!(
+            instr,
+            Instr::StructGetS(..)
+                | Instr::StructGetU(..)
+                | Instr::ArrayGetS(_)
+                | Instr::ArrayGetU(_)
+        );
+        match field.0 {
+            StorageType::ValType(_) if signed => Err(invalid("field is unpacked")),
+            StorageType::I8 | StorageType::I16 if !signed => Err(invalid("field is packed")),
+            storage => Ok(storage.unpacked()),
+        }
+    }
+
+    /// `br_on_cast` and `br_on_cast_fail`, where `branch` is the type sent to
+    /// the label and `fallthrough` the type left on the stack.
+    fn br_on_cast(
+        &mut self,
+        idx: LabelIdx,
+        from: RefType,
+        to: RefType,
+        branch: RefType,
+        fallthrough: RefType,
+    ) -> Result<(), Error> {
+        self.ctx.valtype(ValType::RefType(from))?;
+        self.ctx.valtype(ValType::RefType(to))?;
+        if !self.ctx.ref_subtype(to, from) {
+            return Err(invalid("type mismatch"));
+        }
+        let label = self.label(idx)?;
+        let Some((ValType::RefType(last), rest)) = label.split_last() else {
+            return Err(invalid("type mismatch"));
+        };
+        if !self.ctx.ref_subtype(branch, *last) {
+            return Err(invalid("type mismatch"));
+        }
+        self.pop_expect(ValType::RefType(from))?;
+        self.op(rest, rest)?;
+        self.push(Some(ValType::RefType(fallthrough)));
+        Ok(())
+    }
+
+    fn local(&self, idx: LocalIdx) -> Result<ValType, Error> {
+        let run = self.locals.partition_point(|(end, _)| *end <= idx as u64);
+        self.locals
+            .get(run)
+            .map(|(_, ty)| *ty)
+            .ok_or_else(|| invalid(format!("unknown local {idx}")))
+    }
+
+    fn initialized(&self, idx: LocalIdx, ty: ValType) -> bool {
+        (idx as u64) < self.params || defaultable(ty) || self.inits.contains(&idx)
+    }
+
+    fn init(&mut self, idx: LocalIdx, ty: ValType) {
+        if !self.initialized(idx, ty) {
+            self.inits.insert(idx);
+            self.set.push(idx);
+        }
+    }
+
+    fn instrs(&mut self, instrs: &[Instr]) -> Result<(), Error> {
+        for instr in instrs {
+            self.instr(instr)?;
+        }
+        Ok(())
+    }
+
+    fn instr(&mut self, instr: &Instr) -> Result<(), Error> {
+        if let Some(memarg) = instr.memarg() {
+            if !self.ctx.mem(memarg.2)?.3 && memarg.1 > u32::MAX as u64 {
+                return Err(invalid("offset out of range"));
+            }
+            if memarg.0 > natural_align(instr) {
+                return Err(invalid("alignment must not be larger than natural"));
+            }
+            if instr.is_atomic() && memarg.0 != natural_align(instr) {
+                return Err(invalid("alignment must be equal to natural"));
+            }
+        }
+        if let Some((lane, lanes)) = lane(instr) {
+            if lane >= lanes {
+                return Err(invalid("invalid lane index"));
+            }
+        }
+        if let Some((params, results)) = signature(instr) {
+            if let Some(memarg) = instr.memarg() {
+                // The address operand follows the index type of the memory.
+                let mut params = params.to_vec();
+                params[0] = self.ctx.mem(memarg.2)?.index_type();
+                return self.op(&params, results);
+            }
+            return self.op(params, results);
+        }
+
+        match instr {
+            Instr::Block(ty, body) | Instr::Loop(ty, body) => {
+                let (params, results) = self.block_type(ty)?;
+                self.pop_vals(&params)?;
+                self.push_frame(params, results, matches!(instr, Instr::Loop(_, _)));
+                self.instrs(body)?;
+                let frame = self.pop_frame()?;
+                self.push_vals(&frame.results);
+            }
+            Instr::Try(ty, body, catches, catch_all) => {
+                let (params, results) = self.block_type(ty)?;
+                self.pop_vals(&params)?;
+                self.push_frame(params, results.clone(), false);
+                self.instrs(body)?;
+                self.pop_frame()?;
+                for (tag, handler) in catches {
+                    let tag = self.ctx.tag(*tag)?.0.clone();
+                    self.handler(tag, results.clone(), handler)?;
+                }
+                if let Some(handler) = catch_all {
+                    self.handler(Vec::new(), results.clone(), handler)?;
+                }
+                self.push_vals(&results);
+            }
+            Instr::TryDelegate(ty, body, label) => {
+                let (params, results) = self.block_type(ty)?;
+                self.pop_vals(&params)?;
+                self.push_frame(params, results, false);
+                self.instrs(body)?;
+                let frame = self.pop_frame()?;
+                // The delegate label is counted from outside the try block, where
+                // the function body itself is the outermost valid target.
+                self.label(*label)?;
+                self.push_vals(&frame.results);
+            }
+            Instr::Throw(tag) => {
+                let params = self.ctx.tag(*tag)?.0.clone();
+                self.pop_vals(&params)?;
+                self.unreachable();
+            }
+            Instr::Rethrow(idx) => {
+                let frame = self
+                    .frames
+                    .len()
+                    .checked_sub(*idx as usize + 1)
+                    .map(|i| &self.frames[i])
+                    .ok_or_else(|| invalid(format!("unknown label {idx}")))?;
+                if !frame.is_catch {
+                    return Err(invalid("invalid rethrow label"));
+                }
+                self.unreachable();
+            }
+            Instr::ThrowRef => {
+                self.pop_expect(EXNREF)?;
+                self.unreachable();
+            }
+            Instr::TryTable(ty, catches, body) => {
+                for catch in catches {
+                    let mut values = match catch.tag() {
+                        Some(tag) => self.ctx.tag(tag)?.0.clone(),
+                        None => Vec::new(),
+                    };
+                    if catch.is_ref() {
+                        values.push(EXNREF);
+                    }
+                    if !self.ctx.subtypes(&values, &self.label(catch.label())?) {
+                        return Err(invalid("type mismatch"));
+                    }
+                }
+                let (params, results) = self.block_type(ty)?;
+                self.pop_vals(&params)?;
+                self.push_frame(params, results, false);
+                self.instrs(body)?;
+                let frame = self.pop_frame()?;
+                self.push_vals(&frame.results);
+            }
+            Instr::If(ty, body) | Instr::IfElse(ty, body, _) => {
+                let (params, results) = self.block_type(ty)?;
+                self.pop_expect(I32)?;
+                self.pop_vals(&params)?;
+                self.push_frame(params.clone(), results.clone(), false);
+                self.instrs(body)?;
+                self.pop_frame()?;
+                match instr {
+                    Instr::IfElse(_, _, other) => {
+                        self.push_frame(params, results.clone(), false);
+                        self.instrs(other)?;
+                        self.pop_frame()?;
+                    }
+                    _ if params != results => return Err(invalid("type mismatch")),
+                    _ => {}
+                }
+                self.push_vals(&results);
+            }
+            Instr::Br(idx) => {
+                let label = self.label(*idx)?;
+                self.pop_vals(&label)?;
+                self.unreachable();
+            }
+            Instr::BrIf(idx) => {
+                let label = self.label(*idx)?;
+                self.pop_expect(I32)?;
+                self.op(&label, &label)?;
+            }
+            Instr::BrOnNull(idx) => {
+                let label = self.label(*idx)?;
+                let ty = self.pop_ref()?;
+                self.op(&label, &label)?;
+                self.push(ty.map(|RefType(_, heap)| ValType::RefType(RefType(false, heap))));
+            }
+            Instr::BrOnNonNull(idx) => {
+                let label = self.label(*idx)?;
+                let Some((ValType::RefType(last), rest)) = label.split_last() else {
+                    return Err(invalid("type mismatch"));
+                };
+                if let Some(RefType(_, heap)) = self.pop_ref()? {
+                    if !self.ctx.ref_subtype(RefType(false, heap), *last) {
+                        return Err(invalid("type mismatch"));
+                    }
+                }
+                self.op(rest, rest)?;
+            }
+            Instr::BrTable(labels, default) => {
+                self.pop_expect(I32)?;
+                let arity = self.label(*default)?.len();
+                for idx in labels {
+                    let label = self.label(*idx)?;
+                    if label.len() != arity {
+                        return Err(invalid("type mismatch"));
+                    }
+                    let popped = self.pop_vals(&label)?;
+                    self.stack.extend(popped);
+                }
+                let label = self.label(*default)?;
+                self.pop_vals(&label)?;
+                self.unreachable();
+            }
+            Instr::UnReachable => self.unreachable(),
+            Instr::Nop => {}
+            Instr::Return => {
+                let results = self.results.clone();
+                self.pop_vals(&results)?;
+                self.unreachable();
+            }
+            Instr::Call(idx) => {
+                let ty = self.ctx.func(*idx)?;
+                self.op(&ty.0, &ty.1)?;
+            }
+            Instr::CallIndirect(ty, table) => {
+                if !self
+                    .ctx
+                    .ref_subtype(self.ctx.table(*table)?.0, RefType::FUNCREF)
+                {
+                    return Err(invalid("type mismatch"));
+                }
+                let idx = self.ctx.table(*table)?.1.index_type();
+                let ty = self.ctx.ty(*ty)?;
+                self.pop_expect(idx)?;
+                self.op(&ty.0, &ty.1)?;
+            }
+            Instr::ReturnCall(idx) => {
+                let ty = self.ctx.func(*idx)?;
+                self.tail_call(ty)?;
+            }
+            Instr::ReturnCallIndirect(ty, table) => {
+                if !self
+                    .ctx
+                    .ref_subtype(self.ctx.table(*table)?.0, RefType::FUNCREF)
+                {
+                    return Err(invalid("type mismatch"));
+                }
+                let idx = self.ctx.table(*table)?.1.index_type();
+                let ty = self.ctx.ty(*ty)?;
+                self.pop_expect(idx)?;
+                self.tail_call(ty)?;
+            }
+            Instr::CallRef(idx) => {
+                let ty = self.ctx.ty(*idx)?;
+                self.pop_expect(ValType::RefType(RefType(true, HeapType::Type(*idx))))?;
+                self.op(&ty.0, &ty.1)?;
+            }
+            Instr::ReturnCallRef(idx) => {
+                let ty = self.ctx.ty(*idx)?;
+                self.pop_expect(ValType::RefType(RefType(true, HeapType::Type(*idx))))?;
+                self.tail_call(ty)?;
+            }
+            Instr::RefNull(heap) => {
+                let ty = ValType::RefType(RefType(true, *heap));
+                self.ctx.valtype(ty)?;
+                self.push(Some(ty));
+            }
+            Instr::RefAsNonNull => {
+                let ty = self.pop_ref()?;
+                self.push(ty.map(|RefType(_, heap)| ValType::RefType(RefType(false, heap))));
+            }
+            Instr::RefIsNull => {
+                self.pop_ref()?;
+                self.push(Some(I32));
+            }
+            Instr::RefFunc(idx) => {
+                self.ctx.func(*idx)?;
+                if !self.const_expr && !self.ctx.refs.contains(idx) {
+                    return Err(invalid("undeclared function reference"));
+                }
+                let ty = HeapType::Type(self.ctx.funcs[*idx as usize]);
+                self.push(Some(ValType::RefType(RefType(false, ty))));
+            }
+            Instr::Drop => {
+                self.pop()?;
+            }
+            Instr::Select => {
+                self.pop_expect(I32)?;
+                let t1 = self.pop()?;
+                let t2 = self.pop()?;
+                let is_num = |ty: Option<ValType>| !matches!(ty, Some(ValType::RefType(_)));
+                if !is_num(t1) || !is_num(t2) {
+                    return Err(invalid("type mismatch"));
+                }
+                if t1.is_some() && t2.is_some() && t1 != t2 {
+                    return Err(invalid("type mismatch"));
+                }
+                self.push(t1.or(t2));
+            }
+            Instr::SelectType(types) => {
+                let [ty] = types[..] else {
+                    return Err(invalid("invalid result arity"));
+                };
+                self.op(&[ty, ty, I32], &[ty])?;
+            }
+            Instr::LocalGet(idx) => {
+                let ty = self.local(*idx)?;
+                if !self.initialized(*idx, ty) {
+                    return Err(invalid("uninitialized local"));
+                }
+                self.push(Some(ty));
+            }
+            Instr::LocalSet(idx) => {
+                let ty = self.local(*idx)?;
+                self.pop_expect(ty)?;
+                self.init(*idx, ty);
+            }
+            Instr::LocalTee(idx) => {
+                let ty = self.local(*idx)?;
+                self.op(&[ty], &[ty])?;
+                self.init(*idx, ty);
+            }
+            Instr::GlobalGet(idx) => {
+                let ty = self.ctx.global(*idx)?.1;
+                self.push(Some(ty));
+            }
+            Instr::GlobalSet(idx) => {
+                let GlobalType(mutable, ty) = *self.ctx.global(*idx)?;
+                if !mutable {
+                    return Err(invalid("global is immutable"));
+                }
+                self.pop_expect(ty)?;
+            }
+            Instr::TableGet(idx) => {
+                let table = self.ctx.table(*idx)?;
+                let (ty, idx) = (ValType::RefType(table.0), table.1.index_type());
+                self.op(&[idx], &[ty])?;
+            }
+            Instr::TableSet(idx) => {
+                let table = self.ctx.table(*idx)?;
+                let (ty, idx) = (ValType::RefType(table.0), table.1.index_type());
+                self.op(&[idx, ty], &[])?;
+            }
+            Instr::TableInit(elem, table) => {
+                let table = self.ctx.table(*table)?;
+                if !self.ctx.ref_subtype(self.ctx.elem(*elem)?, table.0) {
+                    return Err(invalid("type mismatch"));
+                }
+                let idx = table.1.index_type();
+                self.op(&[idx, I32, I32], &[])?;
+            }
+            Instr::ElemDrop(elem) => {
+                self.ctx.elem(*elem)?;
+            }
+            Instr::TableCopy(dst, src) => {
+                let (dst, src) = (self.ctx.table(*dst)?, self.ctx.table(*src)?);
+                if !self.ctx.ref_subtype(src.0, dst.0) {
+                    return Err(invalid("type mismatch"));
+                }
+                // The length is only 64-bit when both tables are.
+                let len = match dst.1 .3 && src.1 .3 {
+                    true => I64,
+                    false => I32,
+                };
+                self.op(&[dst.1.index_type(), src.1.index_type(), len], &[])?;
+            }
+            Instr::TableGrow(idx) => {
+                let table = self.ctx.table(*idx)?;
+                let (ty, idx) = (ValType::RefType(table.0), table.1.index_type());
+                self.op(&[ty, idx], &[idx])?;
+            }
+            Instr::TableSize(idx) => {
+                let idx = self.ctx.table(*idx)?.1.index_type();
+                self.push(Some(idx));
+            }
+            Instr::TableFill(idx) => {
+                let table = self.ctx.table(*idx)?;
+                let (ty, idx) = (ValType::RefType(table.0), table.1.index_type());
+                self.op(&[idx, ty, idx], &[])?;
+            }
+            Instr::MemorySize(mem) => {
+                let idx = self.ctx.mem(*mem)?.index_type();
+                self.push(Some(idx));
+            }
+            Instr::MemoryGrow(mem) => {
+                let idx = self.ctx.mem(*mem)?.index_type();
+                self.op(&[idx], &[idx])?;
+            }
+            Instr::MemoryInit(data, mem) => {
+                let idx = self.ctx.mem(*mem)?.index_type();
+                self.ctx.data(*data)?;
+                self.op(&[idx, I32, I32], &[])?;
+            }
+            Instr::DataDrop(data) => self.ctx.data(*data)?,
+            Instr::MemoryCopy(dst, src) => {
+                let (dst, src) = (self.ctx.mem(*dst)?, self.ctx.mem(*src)?);
+                let len = match dst.3 && src.3 {
+                    true => I64,
+                    false => I32,
+                };
+                self.op(&[dst.index_type(), src.index_type(), len], &[])?;
+            }
+            Instr::MemoryFill(mem) => {
+                let idx = self.ctx.mem(*mem)?.index_type();
+                self.op(&[idx, I32, idx], &[])?;
+            }
+            Instr::RefEq => self.op(&[EQREF, EQREF], &[I32])?,
+            Instr::StructNew(idx) => {
+                let fields: Vec<ValType> = self
+                    .ctx
+                    .struct_type(*idx)?
+                    .iter()
+                    .map(|field| field.0.unpacked())
+                    .collect();
+                self.op(&fields, &[typed_ref(false, *idx)])?;
+            }
+            Instr::StructNewDefault(idx) => {
+                let fields = self.ctx.struct_type(*idx)?;
+                if !fields.iter().all(|field| defaultable(field.0.unpacked())) {
+                    return Err(invalid("field type is not defaultable"));
+                }
+                self.push(Some(typed_ref(false, *idx)));
+            }
+            Instr::StructGet(ty, idx) | Instr::StructGetS(ty, idx) | Instr::StructGetU(ty, idx) => {
+                let result = Self::packed(instr, self.ctx.field(*ty, *idx)?)?;
+                self.op(&[typed_ref(true, *ty)], &[result])?;
+            }
+            Instr::StructSet(ty, idx) => {
+                let field = self.ctx.field(*ty, *idx)?;
+                if !field.1 {
+                    return Err(invalid("field is immutable"));
+                }
+                self.op(&[typed_ref(true, *ty), field.0.unpacked()], &[])?;
+            }
+            Instr::ArrayNew(idx) => {
+                let elem = self.ctx.array_type(*idx)?.0.unpacked();
+                self.op(&[elem, I32], &[typed_ref(false, *idx)])?;
+            }
+            Instr::ArrayNewDefault(idx) => {
+                if !defaultable(self.ctx.array_type(*idx)?.0.unpacked()) {
+                    return Err(invalid("array type is not defaultable"));
+                }
+                self.op(&[I32], &[typed_ref(false, *idx)])?;
+            }
+            Instr::ArrayNewFixed(idx, n) => {
+                let elem = self.ctx.array_type(*idx)?.0.unpacked();
+                // Once the stack is exhausted the remaining operands are either
+                // unknown or missing, so there is no need to pop all of them.
+                for _ in 0..(*n).min(self.stack.len() as u32 + 1) {
+                    self.pop_expect(elem)?;
+                }
+                self.push(Some(typed_ref(false, *idx)));
+            }
+            Instr::ArrayNewData(idx, data) => {
+                self.numeric_array(*idx)?;
+                self.ctx.data(*data)?;
+                self.op(&[I32, I32], &[typed_ref(false, *idx)])?;
+            }
+            Instr::ArrayNewElem(idx, elem) => {
+                self.elem_array(*idx, *elem)?;
+                self.op(&[I32, I32], &[typed_ref(false, *idx)])?;
+            }
+            Instr::ArrayGet(idx) | Instr::ArrayGetS(idx) | Instr::ArrayGetU(idx) => {
+                let result = Self::packed(instr, self.ctx.array_type(*idx)?)?;
+                self.op(&[typed_ref(true, *idx), I32], &[result])?;
+            }
+            Instr::ArraySet(idx) => {
+                let elem = self.mutable_array(*idx)?.0.unpacked();
+                self.op(&[typed_ref(true, *idx), I32, elem], &[])?;
+            }
+            Instr::ArrayLen => self.op(&[ARRAYREF], &[I32])?,
+            Instr::ArrayFill(idx) => {
+                let elem = self.mutable_array(*idx)?.0.unpacked();
+                self.op(&[typed_ref(true, *idx), I32, elem, I32], &[])?;
+            }
+            Instr::ArrayCopy(dst, src) => {
+                let field = self.mutable_array(*dst)?;
+                if !self
+                    .ctx
+                    .storage_subtype(self.ctx.array_type(*src)?.0, field.0)
+                {
+                    return Err(invalid("type mismatch"));
+                }
+                let params = [typed_ref(true, *dst), I32, typed_ref(true, *src), I32, I32];
+                self.op(&params, &[])?;
+            }
+            Instr::ArrayInitData(idx, data) => {
+                self.mutable_array(*idx)?;
+                self.numeric_array(*idx)?;
+                self.ctx.data(*data)?;
+                self.op(&[typed_ref(true, *idx), I32, I32, I32], &[])?;
+            }
+            Instr::ArrayInitElem(idx, elem) => {
+                self.mutable_array(*idx)?;
+                self.elem_array(*idx, *elem)?;
+                self.op(&[typed_ref(true, *idx), I32, I32, I32], &[])?;
+            }
+            Instr::RefTest(ty) | Instr::RefCast(ty) => {
+                self.ctx.valtype(ValType::RefType(*ty))?;
+                let top = RefType(true, self.ctx.top(ty.1));
+                self.pop_expect(ValType::RefType(top))?;
+                self.push(Some(match instr {
+                    Instr::RefTest(_) => I32,
+                    _ => ValType::RefType(*ty),
+                }));
+            }
+            Instr::BrOnCast(idx, from, to) => {
+                let diff = RefType(from.0 && !to.0, from.1);
+                self.br_on_cast(*idx, *from, *to, *to, diff)?;
+            }
+            Instr::BrOnCastFail(idx, from, to) => {
+                let diff = RefType(from.0 && !to.0, from.1);
+                self.br_on_cast(*idx, *from, *to, diff, *to)?;
+            }
+            Instr::AnyConvertExtern | Instr::ExternConvertAny => {
+                let (from, to) = match instr {
+                    Instr::AnyConvertExtern => (HeapType::Extern, HeapType::Any),
+                    _ => (HeapType::Any, HeapType::Extern),
+                };
+                // The conversion keeps the nullability of its operand.
+                let nullable = match self.pop_ref()? {
+                    Some(ty) if !self.ctx.ref_subtype(ty, RefType(true, from)) => {
+                        return Err(invalid("type mismatch"));
+                    }
+                    Some(ty) => ty.0,
+                    None => true,
+                };
+                self.push(Some(ValType::RefType(RefType(nullable, to))));
+            }
+            Instr::RefI31 => {
+                self.op(&[I32], &[ValType::RefType(RefType(false, HeapType::I31))])?;
+            }
+            Instr::I31GetS | Instr::I31GetU => self.op(&[I31REF], &[I32])?,
+            instr => unreachable!("{} has a fixed signature", instr.name()),
+        }
+        Ok(())
+    }
+}
+
+/// Lane immediate and number of lanes of SIMD lane instructions.
+fn lane(instr: &Instr) -> Option<(u8, u8)> {
+    Some(match instr {
+        Instr::I8X16_Extract_Lane_S(lane)
+        | Instr::I8X16_Extract_Lane_U(lane)
+        | Instr::I8X16_Replace_Lane(lane)
+        | Instr::V128_Load_8_Lane(_, lane)
+        | Instr::V128_Store_8_Lane(_, lane) => (*lane, 16),
+        Instr::I16X8_Extract_Lane_S(lane)
+        | Instr::I16X8_Extract_Lane_U(lane)
+        | Instr::I16X8_Replace_Lane(lane)
+        | Instr::V128_Load_16_Lane(_, lane)
+        | Instr::V128_Store_16_Lane(_, lane) => (*lane, 8),
+        Instr::I32X4_Extract_Lane(lane)
+        | Instr::I32X4_Replace_Lane(lane)
+        | Instr::F32X4_Extract_Lane(lane)
+        | Instr::F32X4_Replace_Lane(lane)
+        | Instr::V128_Load_32_Lane(_, lane)
+        | Instr::V128_Store_32_Lane(_, lane) => (*lane, 4),
+        Instr::I64X2_Extract_Lane(lane)
+        | Instr::I64X2_Replace_Lane(lane)
+        | Instr::F64X2_Extract_Lane(lane)
+        | Instr::F64X2_Replace_Lane(lane)
+        | Instr::V128_Load_64_Lane(_, lane)
+        | Instr::V128_Store_64_Lane(_, lane) => (*lane, 2),
+        Instr::I8X16_Shuffle(lanes) => (lanes.iter().copied().max().unwrap_or(0), 32),
+        _ => return None,
+    })
+}
+
+type Signature = (&'static [ValType], &'static [ValType]);
+
+/// Operand and result types of instructions whose typing only depends on the opcode.
+fn signature(instr: &Instr) -> Option<Signature> {
+    use Instr::*;
+    Some(match instr {
+        I32Const(_) => (&[], &[I32]),
+        I64Const(_) => (&[], &[I64]),
+        F32Const(_) => (&[], &[F32]),
+        F64Const(_) => (&[], &[F64]),
+        V128_Const(_) => (&[], &[V128]),
+
+        I32Load(_) | I32load8S(_) | I32Load8_u(_) | I32Load16_s(_) | I32Load16_u(_) => {
+            (&[I32], &[I32])
+        }
+        I64Load(_) | I64Load8_s(_) | I64Load8_u(_) | I64Load16_s(_) | I64Load16_u(_)
+        | I64Load32_s(_) | I64Load32_u(_) => (&[I32], &[I64]),
+        F32Load(_) => (&[I32], &[F32]),
+        F64Load(_) => (&[I32], &[F64]),
+        I32Store(_) | I32Store8(_) | I32Store16(_) => (&[I32, I32], &[]),
+        I64Store(_) | I64Store8(_) | I64Store16(_) | I64Store32(_) => (&[I32, I64], &[]),
+        F32Store(_) => (&[I32, F32], &[]),
+        F64Store(_) => (&[I32, F64], &[]),
+
+        I32Eqz | I32Clz | I32Ctz | I32PopcCnt | I32Extend8S | I32Extend16S => (&[I32], &[I32]),
+        I32Eq | I32Ne | I32Lts | I32Ltu | I32Gts | I32Gtu | I32Les | I32Leu | I32Ges | I32Geu
+        | I32Add | I32Sub | I32Mul | I32Divs | I32Divu | I32RemS | I32Remu | I32And | I32Or
+        | I32Xor | I32Shl | I32Shrs | I32Sgru | I32Rotl | I32Rotr => (&[I32, I32], &[I32]),
+        I64Eqz => (&[I64], &[I32]),
+        I64Clz | I64Ctz | I64PopcCnt | I64Extend8S | I64Extend16S | I64Extend32S => {
+            (&[I64], &[I64])
+        }
+        I64Eq | I64Ne | I64Lts | I64Ltu | I64Gts | I64Gtu | I64Les | I64Leu | I64Ges | I64Geu => {
+            (&[I64, I64], &[I32])
+        }
+        I64Add | I64Sub | I64Mul | I64Divs | I64Divu | I64RemS | I64Remu | I64And | I64Or
+        | I64Xor | I64Shl | I64Shrs | I64Sgru | I64Rotl | I64Rotr => (&[I64, I64], &[I64]),
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt => (&[F32], &[F32]),
+        F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32CopySig => (&[F32, F32], &[F32]),
+        F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge => (&[F32, F32], &[I32]),
+        F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt => (&[F64], &[F64]),
+        F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64CopySig => (&[F64, F64], &[F64]),
+        F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge => (&[F64, F64], &[I32]),
+
+        I32WrapI64 => (&[I64], &[I32]),
+        I32TruncF32S | I32TruncF32U | I32TruncSatF32S | I32TruncSatF32U | I32ReinterpetF32 => {
+            (&[F32], &[I32])
+        }
+        I32TruncF64S | I32TruncF64U | I32TruncSatF64S | I32TruncSatF64U => (&[F64], &[I32]),
+        I64ExtendI32S | I64ExtendI32U => (&[I32], &[I64]),
+        I64TruncF32S | I64TruncF32U | I64TruncSatF32S | I64TruncSatF32U => (&[F32], &[I64]),
+        I64TruncF64S | I64TruncF64U | I64TructSatF64S | I64TructSatF64U | I64ReinterpetF64 => {
+            (&[F64], &[I64])
+        }
+        F32ConvertI32S | F32ConvertI32U | F32ReinterpetI32 => (&[I32], &[F32]),
+        F32ConvertI64S | F32ConvertI64U => (&[I64], &[F32]),
+        F32DenoteF64 => (&[F64], &[F32]),
+        F64ConvertI32S | F64ConvertI32U => (&[I32], &[F64]),
+        F64ConvertI64S | F64ConvertI64U | F64RetineroetI64 => (&[I64], &[F64]),
+        F64PromoteF32 => (&[F32], &[F64]),
+
+        V128_Load(_)
+        | V128_Load_8x8_S(_)
+        | V128_Load_8x8_U(_)
+        | V128_Load_16x4_S(_)
+        | V128_Load_16x4_U(_)
+        | V128_Load_32x2_S(_)
+        | V128_Load_32x2_U(_)
+        | V128_Load_8_Splat(_)
+        | V128_Load_16_Splat(_)
+        | V128_Load_32_Splat(_)
+        | V128_Load_64_Splat(_)
+        | V128_Load_32_Zero(_)
+        | V128_Load_64_Zero(_) => (&[I32], &[V128]),
+        V128_Store(_) => (&[I32, V128], &[]),
+        V128_Load_8_Lane(..)
+        | V128_Load_16_Lane(..)
+        | V128_Load_32_Lane(..)
+        | V128_Load_64_Lane(..) => (&[I32, V128], &[V128]),
+        V128_Store_8_Lane(..)
+        | V128_Store_16_Lane(..)
+        | V128_Store_32_Lane(..)
+        | V128_Store_64_Lane(..) => (&[I32, V128], &[]),
+        I8X16_Shuffle(_) | I8x16_Swizzle | I8X16_Eq => (&[V128, V128], &[V128]),
+        V128_Not
+        | F32X4_Demote_F64X2_Zero
+        | F64X2_Promote_Low_F32X4
+        | I8X16_Abs
+        | I8X16_Neg
+        | I8X16_Popcnt
+        | F32X4_Ceil
+        | F32X4_Floor
+        | F32X4_Trunc
+        | F32X4_Nearest
+        | F64X2_Ceil
+        | F64X2_Floor
+        | F64X2_Trunc
+        | I16X8_Extadd_Pairwise_I8X16_S
+        | I16X8_Extadd_Pairwise_I8X16_U
+        | I32X4_Extadd_Pairwise_I16X8_S
+        | I32X4_Extadd_Pairwise_I16X8_U
+        | I16X8_Abs
+        | I16X8_Neg
+        | I16X8_Extend_Low_I8X16_S
+        | I16X8_Extend_High_I8X16_S
+        | I16X8_Extend_Low_I8X16_U
+        | I16X8_Extend_High_I8X16_U
+        | F64X2_Nearest
+        | I32X4_Abs
+        | I32X4_Neg
+        | I32X4_Extend_Low_I16X8_S
+        | I32X4_Extend_High_I16X8_S
+        | I32X4_Extend_Low_I16X8_U
+        | I32X4_Extend_High_I16X8_U
+        | I64X2_Abs
+        | I64X2_Neg
+        | I64X2_Extend_Low_I32X4_S
+        | I64X2_Extend_High_I32X4_S
+        | I64X2_Extend_Low_I32X4_U
+        | I64X2_Extend_High_I32X4_U
+        | F32X4_Abs
+        | F32X4_Neg
+        | F32X4_Sqrt
+        | F64X2_Abs
+        | F64X2_Neg
+        | F64X2_Sqrt
+        | I32X4_Trunc_Sat_F32X4_S
+        | I32X4_Trunc_Sat_F32X4_U
+        | F32X4_Convert_I32X4_S
+        | F32X4_Convert_I32X4_U
+        | I32X4_Trunc_Sat_F64X2_S_Zero
+        | I32X4_Trunc_Sat_F64X2_U_Zero
+        | F64X2_Convert_Low_I32X4_S
+        | F64X2_Convert_Low_I32X4_U
+        | I32X4_Relaxed_Trunc_F32X4_S
+        | I32X4_Relaxed_Trunc_F32X4_U
+        | I32X4_Relaxed_Trunc_F64X2_S_Zero
+        | I32X4_Relaxed_Trunc_F64X2_U_Zero => (&[V128], &[V128]),
+        I8X16_Ne
+        | I8X16_Lt_S
+        | I8X16_Lt_U
+        | I8X16_Gt_S
+        | I8X16_Gt_U
+        | I8X16_Le_S
+        | I8X16_Le_U
+        | I8X16_Ge_S
+        | I8X16_Ge_U
+        | I16X8_Eq
+        | I16X8_Ne
+        | I16X8_Lt_S
+        | I16X8_Lt_U
+        | I16X8_Gt_S
+        | I16X8_Gt_U
+        | I16X8_Le_S
+        | I16X8_Le_U
+        | I16X8_Ge_S
+        | I16X8_Ge_U
+        | I32X4_Eq
+        | I32X4_Ne
+        | I32X4_Lt_S
+        | I32X4_Lt_U
+        | I32X4_Gt_S
+        | I32X4_Gt_U
+        | I32X4_Le_S
+        | I32X4_Le_U
+        | I32X4_Ge_S
+        | I32X4_Ge_U
+        | F32X4_Eq
+        | F32X4_Ne
+        | F32X4_Lt
+        | F32X4_Gt
+        | F32X4_Le
+        | F32X4_Ge
+        | F64X2_Eq
+        | F64X2_Ne
+        | F64X2_Lt
+        | F64X2_Gt
+        | F64X2_Le
+        | F64X2_Ge
+        | V128_And
+        | V128_Andnot
+        | V128_Or
+        | V128_Xor
+        | I8X16_Narrow_I16X8_S
+        | I8X16_Narrow_I16X8_U
+        | I8X16_Add
+        | I8X16_Add_Sat_S
+        | I8X16_Add_Sat_U
+        | I8X16_Sub
+        | I8X16_Sub_Sat_S
+        | I8X16_Sub_Sat_U
+        | I8X16_Min_S
+        | I8X16_Min_U
+        | I8X16_Max_S
+        | I8X16_Max_U
+        | I8X16_Avgr_U
+        | I16X8_Q15mulr_Sat_S
+        | I16X8_Narrow_I32X4_S
+        | I16X8_Narrow_I32X4_U
+        | I16X8_Add
+        | I16X8_Add_Sat_S
+        | I16X8_Add_Sat_U
+        | I16X8_Sub
+        | I16X8_Sub_Sat_S
+        | I16X8_Sub_Sat_U
+        | I16X8_Mul
+        | I16X8_Min_S
+        | I16X8_Min_U
+        | I16X8_Max_S
+        | I16X8_Max_U
+        | I16X8_Avgr_U
+        | I16X8_Extmul_Low_I8X16_S
+        | I16X8_Extmul_High_I8X16_S
+        | I16X8_Extmul_Low_I8X16_U
+        | I16X8_Extmul_High_I8X16_U
+        | I32X4_Add
+        | I32X4_Sub
+        | I32X4_Mul
+        | I32X4_Min_S
+        | I32X4_Min_U
+        | I32X4_Max_S
+        | I32X4_Max_U
+        | I32X4_Dot_I16X8_S
+        | I32X4_Extmul_Low_I16X8_S
+        | I32X4_Extmul_High_I16X8_S
+        | I32X4_Extmul_Low_I16X8_U
+        | I32X4_Extmul_High_I16X8_U
+        | I64X2_Add
+        | I64X2_Sub
+        | I64X2_Mul
+        | I64X2_Eq
+        | I64X2_Ne
+        | I64X2_Lt_S
+        | I64X2_Gt_S
+        | I64X2_Le_S
+        | I64X2_Ge_S
+        | I64X2_Extmul_Low_I32X4_S
+        | I64X2_Extmul_High_I32X4_S
+        | I64X2_Extmul_Low_I32X4_U
+        | I64X2_Extmul_High_I32X4_U
+        | F32X4_Add
+        | F32X4_Sub
+        | F32X4_Mul
+        | F32X4_Div
+        | F32X4_Min
+        | F32X4_Max
+        | F32X4_Pmin
+        | F32X4_Pmax
+        | F64X2_Add
+        | F64X2_Sub
+        | F64X2_Mul
+        | F64X2_Div
+        | F64X2_Min
+        | F64X2_Max
+        | F64X2_Pmin
+        | F64X2_Pmax
+        | I8X16_Relaxed_Swizzle
+        | F32X4_Relaxed_Min
+        | F32X4_Relaxed_Max
+        | F64X2_Relaxed_Min
+        | F64X2_Relaxed_Max
+        | I16X8_Relaxed_Q15mulr_S
+        | I16X8_Relaxed_Dot_I8X16_I7X16_S => (&[V128, V128], &[V128]),
+        V128_Bitselect
+        | F32X4_Relaxed_Madd
+        | F32X4_Relaxed_Nmadd
+        | F64X2_Relaxed_Madd
+        | F64X2_Relaxed_Nmadd
+        | I8X16_Relaxed_Laneselect
+        | I16X8_Relaxed_Laneselect
+        | I32X4_Relaxed_Laneselect
+        | I64X2_Relaxed_Laneselect
+        | I32X4_Relaxed_Dot_I8X16_I7X16_Add_S => (&[V128, V128, V128], &[V128]),
+        I8X16_Shl | I8X16_Shr_S | I8X16_Shr_U | I16X8_Shl | I16X8_Shr_S | I16X8_Shr_U
+        | I32X4_Shl | I32X4_Shr_S | I32X4_Shr_U | I64X2_Shl | I64X2_Shr_S | I64X2_Shr_U => {
+            (&[V128, I32], &[V128])
+        }
+        V128_Any_True | I8X16_All_True | I8X16_Bitmask | I16X8_All_True | I16X8_Bitmask
+        | I32X4_All_True | I32X4_Bitmask | I64X2_All_True | I64X2_Bitmask => (&[V128], &[I32]),
+        I8X16_Extract_Lane_S(_)
+        | I8X16_Extract_Lane_U(_)
+        | I16X8_Extract_Lane_S(_)
+        | I16X8_Extract_Lane_U(_)
+        | I32X4_Extract_Lane(_) => (&[V128], &[I32]),
+        I64X2_Extract_Lane(_) => (&[V128], &[I64]),
+        F32X4_Extract_Lane(_) => (&[V128], &[F32]),
+        F64X2_Extract_Lane(_) => (&[V128], &[F64]),
+        I8X16_Replace_Lane(_) | I16X8_Replace_Lane(_) | I32X4_Replace_Lane(_) => {
+            (&[V128, I32], &[V128])
+        }
+        I64X2_Replace_Lane(_) => (&[V128, I64], &[V128]),
+        F32X4_Replace_Lane(_) => (&[V128, F32], &[V128]),
+        F64X2_Replace_Lane(_) => (&[V128, F64], &[V128]),
+        I8X16_Splat | I16X8_Splat | I32X4_Splat => (&[I32], &[V128]),
+        I64X2_Splat => (&[I64], &[V128]),
+        F32X4_Splat => (&[F32], &[V128]),
+        F64X2_Splat => (&[F64], &[V128]),
+        MemoryAtomicNotify(_) => (&[I32, I32], &[I32]),
+        MemoryAtomicWait32(_) => (&[I32, I32, I64], &[I32]),
+        MemoryAtomicWait64(_) => (&[I32, I64, I64], &[I32]),
+        AtomicFence => (&[], &[]),
+        I32AtomicLoad(_) | I32AtomicLoad8U(_) | I32AtomicLoad16U(_) => (&[I32], &[I32]),
+        I64AtomicLoad(_) | I64AtomicLoad8U(_) | I64AtomicLoad16U(_) | I64AtomicLoad32U(_) => {
+            (&[I32], &[I64])
+        }
+        I32AtomicStore(_) | I32AtomicStore8(_) | I32AtomicStore16(_) => (&[I32, I32], &[]),
+        I64AtomicStore(_) | I64AtomicStore8(_) | I64AtomicStore16(_) | I64AtomicStore32(_) => {
+            (&[I32, I64], &[])
+        }
+        I32AtomicRmwAdd(_)
+        | I32AtomicRmw8AddU(_)
+        | I32AtomicRmw16AddU(_)
+        | I32AtomicRmwSub(_)
+        | I32AtomicRmw8SubU(_)
+        | I32AtomicRmw16SubU(_)
+        | I32AtomicRmwAnd(_)
+        | I32AtomicRmw8AndU(_)
+        | I32AtomicRmw16AndU(_)
+        | I32AtomicRmwOr(_)
+        | I32AtomicRmw8OrU(_)
+        | I32AtomicRmw16OrU(_)
+        | I32AtomicRmwXor(_)
+        | I32AtomicRmw8XorU(_)
+        | I32AtomicRmw16XorU(_)
+        | I32AtomicRmwXchg(_)
+        | I32AtomicRmw8XchgU(_)
+        | I32AtomicRmw16XchgU(_) => (&[I32, I32], &[I32]),
+        I64AtomicRmwAdd(_)
+        | I64AtomicRmw8AddU(_)
+        | I64AtomicRmw16AddU(_)
+        | I64AtomicRmw32AddU(_)
+        | I64AtomicRmwSub(_)
+        | I64AtomicRmw8SubU(_)
+        | I64AtomicRmw16SubU(_)
+        | I64AtomicRmw32SubU(_)
+        | I64AtomicRmwAnd(_)
+        | I64AtomicRmw8AndU(_)
+        | I64AtomicRmw16AndU(_)
+        | I64AtomicRmw32AndU(_)
+        | I64AtomicRmwOr(_)
+        | I64AtomicRmw8OrU(_)
+        | I64AtomicRmw16OrU(_)
+        | I64AtomicRmw32OrU(_)
+        | I64AtomicRmwXor(_)
+        | I64AtomicRmw8XorU(_)
+        | I64AtomicRmw16XorU(_)
+        | I64AtomicRmw32XorU(_)
+        | I64AtomicRmwXchg(_)
+        | I64AtomicRmw8XchgU(_)
+        | I64AtomicRmw16XchgU(_)
+        | I64AtomicRmw32XchgU(_) => (&[I32, I64], &[I64]),
+        I32AtomicRmwCmpxchg(_) | I32AtomicRmw8CmpxchgU(_) | I32AtomicRmw16CmpxchgU(_) => {
+            (&[I32, I32, I32], &[I32])
+        }
+        I64AtomicRmwCmpxchg(_)
+        | I64AtomicRmw8CmpxchgU(_)
+        | I64AtomicRmw16CmpxchgU(_)
+        | I64AtomicRmw32CmpxchgU(_) => (&[I32, I64, I64], &[I64]),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, validate_with};
+    use crate::features::WasmFeatures;
+    use crate::modules::Module;
+    use crate::Parse;
+
+    fn check(wat: &str) -> Result<(), String> {
+        validate(&Module::parse(wat).unwrap()).map_err(|err| match err {
+            crate::Error::InvalidModule(_, msg) => msg,
+            err => panic!("{err:?}"),
+        })
+    }
+
+    #[test]
+    fn validate_modules() {
+        check(
+            r#"(module
+                 (memory 1)
+                 (global $g (mut i32) (i32.const 0))
+                 (func $f (export "f") (param i32) (result i32)
+                   (block (result i32)
+                     (br_if 0 (local.get 0) (i32.const 1))
+                     (drop)
+                     (unreachable)
+                     (i32.add))
+                   (global.set $g (i32.load (i32.const 0)))
+                   (select (i32.const 1) (i32.const 2) (global.get $g))
+                   (i32.add)
+                   (drop (ref.func $f))))"#,
+        )
+        .unwrap();
+        check(
+            r#"(module
+                 (rec
+                   (type $point (sub (struct (field $x i32) (field $y (mut i32)))))
+                   (type $point3 (sub final $point
+                     (struct (field i32) (field (mut i32)) (field i8))))
+                   (type $bytes (array (mut i8))))
+                 (global (ref $point) (struct.new $point (i32.const 1) (i32.const 2)))
+                 (func (param $p (ref null $point)) (param $b (ref $bytes)) (result i32)
+                   (struct.set $point $y (local.get $p) (i32.const 3))
+                   (drop (ref.cast (ref $point3) (local.get $p)))
+                   (array.fill $bytes (local.get $b) (i32.const 0) (i32.const 7) (i32.const 1))
+                   (drop (ref.i31 (array.get_u $bytes (local.get $b) (i32.const 0))))
+                   (drop (array.new_fixed $bytes 2 (i32.const 1) (i32.const 2)))
+                   (block $l (result anyref)
+                     (br_on_cast $l anyref (ref $point)
+                       (any.convert_extern (extern.convert_any (local.get $p))))
+                     (drop)
+                     (ref.null none))
+                   (drop)
+                   (struct.get $point $x (local.get $p))))"#,
+        )
+        .unwrap();
+
+        let invalid = [
+            ("(func (result i32) (i64.const 0))", "type mismatch"),
+            ("(func (local.get 0) (drop))", "unknown local 0"),
+            ("(func (br 1))", "unknown label 1"),
+            ("(func (drop (i32.load (i32.const 0))))", "unknown memory 0"),
+            (
+                "(memory 1) (func (drop (memory.size 1)))",
+                "unknown memory 1",
+            ),
+            (
+                "(memory 1) (func (drop (i32.load align=8 (i32.const 0))))",
+                "alignment must not be larger than natural",
+            ),
+            (
+                "(global i32 (i32.const 0)) (func (global.set 0 (i32.const 1)))",
+                "global is immutable",
+            ),
+            (
+                "(func $f (drop (ref.func $f)))",
+                "undeclared function reference",
+            ),
+            (
+                "(global (mut i32) (i32.const 0)) (global i32 (global.get 0))",
+                "constant expression required",
+            ),
+            (
+                "(func (export \"a\")) (func (export \"a\"))",
+                "duplicate export name",
+            ),
+            ("(memory 1 shared)", "shared memory must have maximum"),
+            (
+                "(memory 1 1 shared) (func (drop (i32.atomic.load align=1 (i32.const 0))))",
+                "alignment must be equal to natural",
+            ),
+            (
+                "(memory 1) (func (drop (i32.load offset=0x100000000 (i32.const 0))))",
+                "offset out of range",
+            ),
+            (
+                "(memory 2 1)",
+                "size minimum must not be greater than maximum",
+            ),
+            ("(tag (result i32))", "non-empty tag result type"),
+            ("(func (block (rethrow 0)))", "invalid rethrow label"),
+            (
+                "(tag $e (param i32)) (func (block $l (try_table (catch $e $l))))",
+                "type mismatch",
+            ),
+            (
+                "(func $f (result i64) (i64.const 0)) (func (return_call $f))",
+                "type mismatch",
+            ),
+            (
+                "(type $t (func)) (func (local (ref $t)) (drop (local.get 0)))",
+                "uninitialized local",
+            ),
+            (
+                "(type $t (func)) (func (param funcref) (call_ref $t (local.get 0)))",
+                "type mismatch",
+            ),
+            (
+                "(type $a (struct)) (type $b (sub $a (struct (field i32))))",
+                "sub type 1 does not match super type 0",
+            ),
+            (
+                "(type $s (struct (field i32))) (func (param (ref $s)) (struct.set $s 0 (local.get 0) (i32.const 0)))",
+                "field is immutable",
+            ),
+            (
+                "(type $a (array i8)) (func (param (ref $a)) (drop (array.get $a (local.get 0) (i32.const 0))))",
+                "field is packed",
+            ),
+        ];
+        for (wat, msg) in invalid {
+            assert_eq!(check(wat), Err(msg.to_string()), "{wat}");
+        }
+    }
+
+    #[test]
+    fn extended_const() {
+        let wat = r#"(module
+            (import "env" "base" (global i32))
+            (global i32 (i32.add (global.get 0) (i32.const 1)))
+            (global i64 (i64.mul (i64.const 2) (i64.sub (i64.const 5) (i64.const 1)))))"#;
+        check(wat).unwrap();
+        let features = WasmFeatures {
+            extended_const: false,
+            ..WasmFeatures::default()
+        };
+        assert!(validate_with(&Module::parse(wat).unwrap(), &features).is_err());
+        assert_eq!(
+            check("(module (global i32 (i32.div_s (i32.const 1) (i32.const 1))))"),
+            Err("constant expression required".to_string())
+        );
+    }
+
+    #[test]
+    fn huge_local_count() {
+        // A single function declaring 0xFFFFFFFF i32 locals and reading the
+        // second to last one.
+        let mut data = vec![0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+        data.extend([0x01, 0x04, 0x01, 0x60, 0x00, 0x00]);
+        data.extend([0x03, 0x02, 0x01, 0x00]);
+        data.extend([
+            0x0A, 0x11, 0x01, 0x0F, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x7F,
+        ]);
+        data.extend([0x20, 0xFE, 0xFF, 0xFF, 0xFF, 0x0F, 0x1A, 0x0B]);
+        validate(&Module::parse(&mut data).unwrap()).unwrap();
+
+        let err = check("(module (func (local i32 i64) (drop (local.get 2))))");
+        assert_eq!(err, Err("unknown local 2".to_string()));
+        check("(module (func (param f32) (local i32 i64) (drop (i64.eqz (local.get 2)))))")
+            .unwrap();
+    }
+}
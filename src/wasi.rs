@@ -0,0 +1,840 @@
+//! A WASI preview1 host for the interpreter.
+//!
+//! Files are only reachable through preopened directories; paths that would
+//! leave them, lexically or through symlinks, are rejected with `notcapable`.
+
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::interpreter::{ExternVal, Imports, InstanceAddr, Store, Trap, Value};
+use crate::modules::{ImportDesc, Module};
+use crate::types::{FuncType, NumType, ValType};
+use crate::Error;
+
+pub const MODULE: &str = "wasi_snapshot_preview1";
+
+pub type Errno = u16;
+
+pub const ERRNO_SUCCESS: Errno = 0;
+pub const ERRNO_ACCES: Errno = 2;
+pub const ERRNO_BADF: Errno = 8;
+pub const ERRNO_EXIST: Errno = 20;
+pub const ERRNO_FAULT: Errno = 21;
+pub const ERRNO_INVAL: Errno = 28;
+pub const ERRNO_IO: Errno = 29;
+pub const ERRNO_ISDIR: Errno = 31;
+pub const ERRNO_LOOP: Errno = 32;
+pub const ERRNO_NOENT: Errno = 44;
+pub const ERRNO_NOSYS: Errno = 52;
+pub const ERRNO_NOTDIR: Errno = 54;
+pub const ERRNO_SPIPE: Errno = 70;
+pub const ERRNO_NOTCAPABLE: Errno = 76;
+
+const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+const FILETYPE_DIRECTORY: u8 = 3;
+const FILETYPE_REGULAR_FILE: u8 = 4;
+
+const LOOKUPFLAGS_SYMLINK_FOLLOW: u32 = 1;
+const OFLAGS_CREAT: u32 = 1;
+const OFLAGS_DIRECTORY: u32 = 2;
+const OFLAGS_EXCL: u32 = 4;
+const OFLAGS_TRUNC: u32 = 8;
+const FDFLAGS_APPEND: u32 = 1;
+const RIGHTS_FD_READ: u64 = 1 << 1;
+const RIGHTS_FD_WRITE: u64 = 1 << 6;
+
+enum Handle {
+    Reader(Box<dyn Read>),
+    Writer(Box<dyn Write>),
+    File(File),
+    Dir {
+        host: PathBuf,
+        /// The guest name of a preopened directory.
+        preopen: Option<String>,
+    },
+}
+
+impl Handle {
+    fn filetype(&self) -> u8 {
+        match self {
+            Handle::Reader(_) | Handle::Writer(_) => FILETYPE_CHARACTER_DEVICE,
+            Handle::File(_) => FILETYPE_REGULAR_FILE,
+            Handle::Dir { .. } => FILETYPE_DIRECTORY,
+        }
+    }
+}
+
+/// State of a WASI program: arguments, environment, open descriptors and
+/// the random source.
+pub struct WasiCtx {
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    fds: Vec<Option<Handle>>,
+    rng: u64,
+    start: Instant,
+    /// Set once the program called `proc_exit`.
+    pub exit_code: Option<i32>,
+}
+
+impl Default for WasiCtx {
+    fn default() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self {
+            args: Vec::new(),
+            env: Vec::new(),
+            fds: vec![
+                Some(Handle::Reader(Box::new(io::stdin()))),
+                Some(Handle::Writer(Box::new(io::stdout()))),
+                Some(Handle::Writer(Box::new(io::stderr()))),
+            ],
+            rng: seed,
+            start: Instant::now(),
+            exit_code: None,
+        }
+    }
+}
+
+impl WasiCtx {
+    /// A context inheriting the host's standard streams, with no arguments,
+    /// environment or preopened directories.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<S: Into<String>>(mut self, args: impl IntoIterator<Item = S>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Makes `random_get` deterministic.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = seed;
+        self
+    }
+
+    pub fn stdin(mut self, stdin: impl Read + 'static) -> Self {
+        self.fds[0] = Some(Handle::Reader(Box::new(stdin)));
+        self
+    }
+
+    pub fn stdout(mut self, stdout: impl Write + 'static) -> Self {
+        self.fds[1] = Some(Handle::Writer(Box::new(stdout)));
+        self
+    }
+
+    pub fn stderr(mut self, stderr: impl Write + 'static) -> Self {
+        self.fds[2] = Some(Handle::Writer(Box::new(stderr)));
+        self
+    }
+
+    /// Exposes the host directory `host` to the program as `guest`.
+    pub fn preopen_dir(mut self, host: impl Into<PathBuf>, guest: impl Into<String>) -> Self {
+        self.fds.push(Some(Handle::Dir {
+            host: host.into(),
+            preopen: Some(guest.into()),
+        }));
+        self
+    }
+
+    /// Allocates the WASI functions in `store` and defines them in `imports`.
+    /// Functions `module` imports from WASI that are not implemented here
+    /// are defined as well, returning `nosys`.
+    pub fn link(
+        self,
+        store: &mut Store,
+        imports: &mut Imports,
+        module: &Module,
+    ) -> Rc<RefCell<WasiCtx>> {
+        let ctx = Rc::new(RefCell::new(self));
+
+        for (name, params, handler) in FUNCS {
+            let params = params
+                .chars()
+                .map(|param| match param {
+                    'I' => ValType::NumType(NumType::I64),
+                    _ => ValType::NumType(NumType::I32),
+                })
+                .collect();
+            let ty = FuncType(params, vec![ValType::NumType(NumType::I32)]);
+            let ctx = ctx.clone();
+            let func = store.alloc_host_func(ty, move |caller, args| {
+                // WASI accesses the `memory` export, which with multiple
+                // memories need not be the first one.
+                let Some(ExternVal::Mem(mem)) = caller.export("memory") else {
+                    return Err(Trap::Host(format!("{name} requires a memory export")));
+                };
+                let mem = &mut caller.store.mems[mem].data;
+                let errno = match handler(&mut ctx.borrow_mut(), mem, args) {
+                    Ok(()) => ERRNO_SUCCESS,
+                    Err(errno) => errno,
+                };
+                Ok(vec![Value::I32(errno as i32)])
+            });
+            imports.define(MODULE, name, ExternVal::Func(func));
+        }
+
+        let exit = ctx.clone();
+        let ty = FuncType(vec![ValType::NumType(NumType::I32)], vec![]);
+        let func = store.alloc_host_func(ty, move |_, args| {
+            let code = arg(args, 0) as i32;
+            exit.borrow_mut().exit_code = Some(code);
+            Err(Trap::Exit(code))
+        });
+        imports.define(MODULE, "proc_exit", ExternVal::Func(func));
+
+        for import in module.imports() {
+            let ImportDesc::TypeIdx(ty) = import.desc else {
+                continue;
+            };
+            if import.module != MODULE || imports.get(MODULE, &import.name).is_some() {
+                continue;
+            }
+            let Some(ty) = module.types().get(ty as usize).and_then(|ty| ty.func()) else {
+                continue;
+            };
+            let results = ty.1.clone();
+            let func = store.alloc_host_func(ty.clone(), move |_, _| {
+                Ok(results
+                    .iter()
+                    .map(|ty| match ty {
+                        ValType::NumType(NumType::I32) => Value::I32(ERRNO_NOSYS as i32),
+                        ty => Value::zero(*ty),
+                    })
+                    .collect())
+            });
+            imports.define(MODULE, &import.name, ExternVal::Func(func));
+        }
+
+        ctx
+    }
+
+    fn next_random(&mut self) -> u64 {
+        // splitmix64
+        self.rng = self.rng.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn fd(&mut self, fd: u32) -> Result<&mut Handle, Errno> {
+        self.fds
+            .get_mut(fd as usize)
+            .and_then(Option::as_mut)
+            .ok_or(ERRNO_BADF)
+    }
+
+    fn insert(&mut self, handle: Handle) -> u32 {
+        match self.fds.iter().position(Option::is_none) {
+            Some(fd) => {
+                self.fds[fd] = Some(handle);
+                fd as u32
+            }
+            None => {
+                self.fds.push(Some(handle));
+                self.fds.len() as u32 - 1
+            }
+        }
+    }
+}
+
+/// Runs the `_start` export of a WASI command, returning its exit code.
+pub fn run(store: &mut Store, instance: InstanceAddr) -> Result<i32, Error> {
+    match store.invoke_export(instance, "_start", &[]) {
+        Ok(_) => Ok(0),
+        Err(Error::Trap(_, Trap::Exit(code))) => Ok(code),
+        Err(err) => Err(err),
+    }
+}
+
+/// Resolves `path` below `root`, refusing anything that leaves it. Symlinks
+/// are resolved here rather than by the host so that their targets are
+/// checked too; one in the last component is only followed with `follow`.
+fn resolve(root: &Path, path: &str, follow: bool) -> Result<PathBuf, Errno> {
+    let root = root.canonicalize().map_err(errno)?;
+    // Never contains a symlink, so it can't be outside `root`.
+    let mut resolved = root.clone();
+    let mut pending: Vec<_> = Path::new(path)
+        .components()
+        .map(|part| PathBuf::from(part.as_os_str()))
+        .collect();
+    pending.reverse();
+    let mut links = 0;
+    while let Some(component) = pending.pop() {
+        match component.components().next() {
+            Some(Component::Normal(part)) => {
+                let next = resolved.join(part);
+                let is_link = next
+                    .symlink_metadata()
+                    .is_ok_and(|meta| meta.file_type().is_symlink());
+                if !is_link || (pending.is_empty() && !follow) {
+                    resolved = next;
+                    continue;
+                }
+                links += 1;
+                if links > 32 {
+                    return Err(ERRNO_LOOP);
+                }
+                let target = next.read_link().map_err(errno)?;
+                let target = match target.strip_prefix(&root) {
+                    Ok(inside) => {
+                        resolved = root.clone();
+                        inside.to_path_buf()
+                    }
+                    Err(_) if target.is_absolute() => return Err(ERRNO_NOTCAPABLE),
+                    Err(_) => target,
+                };
+                pending.extend(
+                    target
+                        .components()
+                        .rev()
+                        .map(|part| PathBuf::from(part.as_os_str())),
+                );
+            }
+            Some(Component::ParentDir) => {
+                if resolved == root {
+                    return Err(ERRNO_NOTCAPABLE);
+                }
+                resolved.pop();
+            }
+            Some(Component::CurDir) | None => {}
+            Some(Component::RootDir | Component::Prefix(_)) => return Err(ERRNO_NOTCAPABLE),
+        }
+    }
+    Ok(resolved)
+}
+
+fn errno(err: io::Error) -> Errno {
+    match err.kind() {
+        io::ErrorKind::NotFound => ERRNO_NOENT,
+        io::ErrorKind::PermissionDenied => ERRNO_ACCES,
+        io::ErrorKind::AlreadyExists => ERRNO_EXIST,
+        io::ErrorKind::InvalidInput => ERRNO_INVAL,
+        _ => ERRNO_IO,
+    }
+}
+
+fn arg(args: &[Value], index: usize) -> u32 {
+    match args.get(index) {
+        Some(Value::I32(value)) => *value as u32,
+        _ => 0,
+    }
+}
+
+fn arg64(args: &[Value], index: usize) -> u64 {
+    match args.get(index) {
+        Some(Value::I64(value)) => *value as u64,
+        _ => 0,
+    }
+}
+
+fn bytes(mem: &mut [u8], ptr: u32, len: u32) -> Result<&mut [u8], Errno> {
+    let start = ptr as usize;
+    let end = start.checked_add(len as usize).ok_or(ERRNO_FAULT)?;
+    mem.get_mut(start..end).ok_or(ERRNO_FAULT)
+}
+
+fn store<const N: usize>(mem: &mut [u8], ptr: u32, value: [u8; N]) -> Result<(), Errno> {
+    bytes(mem, ptr, N as u32)?.copy_from_slice(&value);
+    Ok(())
+}
+
+fn load(mem: &mut [u8], ptr: u32) -> Result<u32, Errno> {
+    let bytes = bytes(mem, ptr, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads `len` (buf, buf_len) pairs starting at `ptr`. Like `readv` and
+/// `writev`, refuses buffers whose lengths add up to more than fits the
+/// returned size.
+fn iovecs(mem: &mut [u8], ptr: u32, len: u32) -> Result<Vec<(u32, u32)>, Errno> {
+    let iovecs = (0..len)
+        .map(|i| {
+            let iovec = i
+                .checked_mul(8)
+                .and_then(|offset| ptr.checked_add(offset))
+                .ok_or(ERRNO_FAULT)?;
+            let len = iovec.checked_add(4).ok_or(ERRNO_FAULT)?;
+            Ok((load(mem, iovec)?, load(mem, len)?))
+        })
+        .collect::<Result<Vec<_>, Errno>>()?;
+    iovecs
+        .iter()
+        .try_fold(0u32, |total, (_, len)| total.checked_add(*len))
+        .ok_or(ERRNO_INVAL)?;
+    Ok(iovecs)
+}
+
+fn string(mem: &mut [u8], ptr: u32, len: u32) -> Result<String, Errno> {
+    String::from_utf8(bytes(mem, ptr, len)?.to_vec()).map_err(|_| ERRNO_INVAL)
+}
+
+type Handler = fn(&mut WasiCtx, &mut [u8], &[Value]) -> Result<(), Errno>;
+
+/// Functions returning an errno, with their parameters (`i` for i32, `I` for i64).
+const FUNCS: &[(&str, &str, Handler)] = &[
+    ("args_get", "ii", args_get),
+    ("args_sizes_get", "ii", args_sizes_get),
+    ("environ_get", "ii", environ_get),
+    ("environ_sizes_get", "ii", environ_sizes_get),
+    ("clock_res_get", "ii", clock_res_get),
+    ("clock_time_get", "iIi", clock_time_get),
+    ("random_get", "ii", random_get),
+    ("fd_close", "i", fd_close),
+    ("fd_fdstat_get", "ii", fd_fdstat_get),
+    ("fd_filestat_get", "ii", fd_filestat_get),
+    ("fd_prestat_get", "ii", fd_prestat_get),
+    ("fd_prestat_dir_name", "iii", fd_prestat_dir_name),
+    ("fd_read", "iiii", fd_read),
+    ("fd_write", "iiii", fd_write),
+    ("fd_seek", "iIii", fd_seek),
+    ("path_open", "iiiiiIIii", path_open),
+    ("sched_yield", "", sched_yield),
+];
+
+/// Writes NUL-terminated `strings` to `buf` and pointers to them to `list`.
+fn strings(
+    mem: &mut [u8],
+    strings: impl Iterator<Item = String>,
+    list: u32,
+    mut buf: u32,
+) -> Result<(), Errno> {
+    for (i, string) in strings.enumerate() {
+        let ptr = u32::try_from(i)
+            .ok()
+            .and_then(|i| i.checked_mul(4))
+            .and_then(|offset| list.checked_add(offset))
+            .ok_or(ERRNO_FAULT)?;
+        store(mem, ptr, buf.to_le_bytes())?;
+        let len = u32::try_from(string.len())
+            .ok()
+            .and_then(|len| len.checked_add(1))
+            .ok_or(ERRNO_FAULT)?;
+        let dest = bytes(mem, buf, len)?;
+        dest[..string.len()].copy_from_slice(string.as_bytes());
+        dest[string.len()] = 0;
+        buf = buf.checked_add(len).ok_or(ERRNO_FAULT)?;
+    }
+    Ok(())
+}
+
+fn sizes(
+    mem: &mut [u8],
+    strings: impl Iterator<Item = usize>,
+    count: u32,
+    size: u32,
+) -> Result<(), Errno> {
+    let (n, total) = strings.fold((0u32, 0u32), |(n, total), len| {
+        (n + 1, total.saturating_add(len as u32 + 1))
+    });
+    store(mem, count, n.to_le_bytes())?;
+    store(mem, size, total.to_le_bytes())
+}
+
+fn args_get(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    strings(mem, ctx.args.iter().cloned(), arg(args, 0), arg(args, 1))
+}
+
+fn args_sizes_get(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    sizes(
+        mem,
+        ctx.args.iter().map(String::len),
+        arg(args, 0),
+        arg(args, 1),
+    )
+}
+
+fn environ_get(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    let env = ctx.env.iter().map(|(key, value)| format!("{key}={value}"));
+    strings(mem, env, arg(args, 0), arg(args, 1))
+}
+
+fn environ_sizes_get(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    let env = ctx
+        .env
+        .iter()
+        .map(|(key, value)| key.len() + 1 + value.len());
+    sizes(mem, env, arg(args, 0), arg(args, 1))
+}
+
+fn clock_res_get(_: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    match arg(args, 0) {
+        0..=3 => store(mem, arg(args, 1), 1u64.to_le_bytes()),
+        _ => Err(ERRNO_INVAL),
+    }
+}
+
+fn clock_time_get(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    let time = match arg(args, 0) {
+        0 => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| ERRNO_IO)?
+            .as_nanos(),
+        // Monotonic and cpu time clocks all count from the context's creation.
+        1..=3 => ctx.start.elapsed().as_nanos(),
+        _ => return Err(ERRNO_INVAL),
+    };
+    store(mem, arg(args, 2), (time as u64).to_le_bytes())
+}
+
+fn random_get(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    for chunk in bytes(mem, arg(args, 0), arg(args, 1))?.chunks_mut(8) {
+        let random = ctx.next_random().to_le_bytes();
+        chunk.copy_from_slice(&random[..chunk.len()]);
+    }
+    Ok(())
+}
+
+fn fd_close(ctx: &mut WasiCtx, _: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    ctx.fd(arg(args, 0))?;
+    ctx.fds[arg(args, 0) as usize] = None;
+    Ok(())
+}
+
+fn fd_fdstat_get(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    let filetype = ctx.fd(arg(args, 0))?.filetype();
+    let mut fdstat = [0; 24];
+    fdstat[0] = filetype;
+    fdstat[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+    fdstat[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+    store(mem, arg(args, 1), fdstat)
+}
+
+fn fd_filestat_get(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    let handle = ctx.fd(arg(args, 0))?;
+    let mut filestat = [0; 64];
+    filestat[16] = handle.filetype();
+    filestat[24..32].copy_from_slice(&1u64.to_le_bytes());
+    let metadata = match handle {
+        Handle::File(file) => Some(file.metadata().map_err(errno)?),
+        Handle::Dir { host, .. } => Some(host.metadata().map_err(errno)?),
+        _ => None,
+    };
+    if let Some(metadata) = metadata {
+        let nanos = |time: io::Result<SystemTime>| {
+            time.ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |time| time.as_nanos() as u64)
+        };
+        filestat[32..40].copy_from_slice(&metadata.len().to_le_bytes());
+        filestat[40..48].copy_from_slice(&nanos(metadata.accessed()).to_le_bytes());
+        filestat[48..56].copy_from_slice(&nanos(metadata.modified()).to_le_bytes());
+        filestat[56..64].copy_from_slice(&nanos(metadata.modified()).to_le_bytes());
+    }
+    store(mem, arg(args, 1), filestat)
+}
+
+fn fd_prestat_get(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    match ctx.fd(arg(args, 0))? {
+        Handle::Dir {
+            preopen: Some(name),
+            ..
+        } => {
+            let mut prestat = [0; 8];
+            prestat[4..].copy_from_slice(&(name.len() as u32).to_le_bytes());
+            store(mem, arg(args, 1), prestat)
+        }
+        _ => Err(ERRNO_BADF),
+    }
+}
+
+fn fd_prestat_dir_name(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    match ctx.fd(arg(args, 0))? {
+        Handle::Dir {
+            preopen: Some(name),
+            ..
+        } => {
+            let dest = bytes(mem, arg(args, 1), arg(args, 2))?;
+            let len = name.len().min(dest.len());
+            dest[..len].copy_from_slice(&name.as_bytes()[..len]);
+            Ok(())
+        }
+        _ => Err(ERRNO_BADF),
+    }
+}
+
+fn fd_read(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    let iovecs = iovecs(mem, arg(args, 1), arg(args, 2))?;
+    let reader: &mut dyn Read = match ctx.fd(arg(args, 0))? {
+        Handle::Reader(reader) => reader,
+        Handle::File(file) => file,
+        Handle::Dir { .. } => return Err(ERRNO_ISDIR),
+        Handle::Writer(_) => return Err(ERRNO_BADF),
+    };
+    let mut total = 0u32;
+    for (buf, len) in iovecs {
+        let dest = bytes(mem, buf, len)?;
+        let read = reader.read(dest).map_err(errno)?;
+        // Bounded by the sum of the lengths, which `iovecs` checked.
+        total += read as u32;
+        if read < dest.len() {
+            break;
+        }
+    }
+    store(mem, arg(args, 3), total.to_le_bytes())
+}
+
+fn fd_write(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    let iovecs = iovecs(mem, arg(args, 1), arg(args, 2))?;
+    let writer: &mut dyn Write = match ctx.fd(arg(args, 0))? {
+        Handle::Writer(writer) => writer,
+        Handle::File(file) => file,
+        Handle::Dir { .. } => return Err(ERRNO_ISDIR),
+        Handle::Reader(_) => return Err(ERRNO_BADF),
+    };
+    let mut total = 0u32;
+    for (buf, len) in iovecs {
+        writer.write_all(bytes(mem, buf, len)?).map_err(errno)?;
+        // Bounded by the sum of the lengths, which `iovecs` checked.
+        total += len;
+    }
+    writer.flush().map_err(errno)?;
+    store(mem, arg(args, 3), total.to_le_bytes())
+}
+
+fn fd_seek(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    let Handle::File(file) = ctx.fd(arg(args, 0))? else {
+        return Err(ERRNO_SPIPE);
+    };
+    let offset = arg64(args, 1) as i64;
+    let pos = match arg(args, 2) {
+        0 => SeekFrom::Start(u64::try_from(offset).map_err(|_| ERRNO_INVAL)?),
+        1 => SeekFrom::Current(offset),
+        2 => SeekFrom::End(offset),
+        _ => return Err(ERRNO_INVAL),
+    };
+    let offset = file.seek(pos).map_err(errno)?;
+    store(mem, arg(args, 3), offset.to_le_bytes())
+}
+
+fn path_open(ctx: &mut WasiCtx, mem: &mut [u8], args: &[Value]) -> Result<(), Errno> {
+    let Handle::Dir { host, .. } = ctx.fd(arg(args, 0))? else {
+        return Err(ERRNO_NOTDIR);
+    };
+    let path = string(mem, arg(args, 2), arg(args, 3))?;
+    let follow = arg(args, 1) & LOOKUPFLAGS_SYMLINK_FOLLOW != 0;
+    let path = resolve(host, &path, follow)?;
+    let (oflags, rights, fdflags) = (arg(args, 4), arg64(args, 5), arg(args, 7));
+    // Like `O_NOFOLLOW`, a symlink that is not followed can't be opened.
+    if path
+        .symlink_metadata()
+        .is_ok_and(|meta| meta.file_type().is_symlink())
+    {
+        return Err(ERRNO_LOOP);
+    }
+
+    let handle = if oflags & OFLAGS_DIRECTORY != 0 || path.is_dir() {
+        if !path.is_dir() {
+            return Err(ERRNO_NOTDIR);
+        }
+        Handle::Dir {
+            host: path,
+            preopen: None,
+        }
+    } else {
+        let write = rights & RIGHTS_FD_WRITE != 0;
+        let file = OpenOptions::new()
+            .read(rights & RIGHTS_FD_READ != 0 || !write)
+            .write(write)
+            .append(fdflags & FDFLAGS_APPEND != 0)
+            .create(oflags & OFLAGS_CREAT != 0)
+            .create_new(oflags & OFLAGS_CREAT != 0 && oflags & OFLAGS_EXCL != 0)
+            .truncate(oflags & OFLAGS_TRUNC != 0)
+            .open(path)
+            .map_err(errno)?;
+        Handle::File(file)
+    };
+    let fd = ctx.insert(handle);
+    store(mem, arg(args, 8), fd.to_le_bytes())
+}
+
+fn sched_yield(_: &mut WasiCtx, _: &mut [u8], _: &[Value]) -> Result<(), Errno> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    use super::{run, WasiCtx, ERRNO_LOOP, ERRNO_NOTCAPABLE, ERRNO_SUCCESS};
+    use crate::interpreter::{ExternVal, Imports, Store, Value};
+    use crate::modules::Module;
+    use crate::Parse;
+
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    const WAT: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "path_open"
+            (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "sock_accept" (func $sock_accept (param i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 16) "hello\n")
+          (data (i32.const 32) "input.txt")
+          (data (i32.const 48) "../secret")
+          (func $print (param i32 i32)
+            (i32.store (i32.const 0) (local.get 0))
+            (i32.store (i32.const 4) (local.get 1))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))))
+          (func $open (param i32 i32) (result i32)
+            (call $path_open (i32.const 3) (i32.const 0) (local.get 0) (local.get 1)
+              (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 12)))
+          (func (export "_start")
+            (call $print (i32.const 16) (i32.const 6))
+            (if (i32.ne (call $open (i32.const 48) (i32.const 9)) (i32.const 76))
+              (then unreachable))
+            (if (call $open (i32.const 32) (i32.const 9))
+              (then unreachable))
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 32))
+            (drop (call $fd_read (i32.load (i32.const 12)) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (call $print (i32.const 64) (i32.load (i32.const 8)))
+            (if (i32.ne (call $sock_accept (i32.const 0) (i32.const 0) (i32.const 0)) (i32.const 52))
+              (then unreachable))
+            (drop (call $random_get (i32.const 128) (i32.const 16)))
+            (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+            (call $proc_exit (i32.load (i32.const 0)))
+            unreachable))
+    "#;
+
+    #[test]
+    fn command() {
+        let dir = std::env::temp_dir().join(format!("wasm-parse-wasi-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("input.txt"), "from a file\n").unwrap();
+
+        let module = Module::parse(WAT).unwrap();
+        let mut random = Vec::new();
+        for _ in 0..2 {
+            let stdout = Output::default();
+            let mut store = Store::new();
+            let mut imports = Imports::new();
+            let ctx = WasiCtx::new()
+                .args(["prog", "arg"])
+                .seed(7)
+                .stdout(stdout.clone())
+                .preopen_dir(&dir, ".")
+                .link(&mut store, &mut imports, &module);
+            let instance = store.instantiate(&module, &imports).unwrap();
+
+            assert_eq!(run(&mut store, instance).unwrap(), 2);
+            assert_eq!(ctx.borrow().exit_code, Some(2));
+            assert_eq!(&*stdout.0.borrow(), b"hello\nfrom a file\n");
+            let Some(ExternVal::Mem(mem)) = store.export(instance, "memory") else {
+                panic!("memory is exported");
+            };
+            random.push(store.mems[mem].data[128..144].to_vec());
+        }
+        assert_eq!(random[0], random[1]);
+        assert_ne!(random[0], vec![0; 16]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn wasi_test() {
+        let module = Module::parse(include_str!("../wasi-test/main.wat")).unwrap();
+        let stdout = Output::default();
+        let mut store = Store::new();
+        let mut imports = Imports::new();
+        WasiCtx::new()
+            .args(["wasi-test", "arg"])
+            .env("KEY", "value")
+            .stdout(stdout.clone())
+            .link(&mut store, &mut imports, &module);
+        let instance = store.instantiate(&module, &imports).unwrap();
+
+        assert_eq!(run(&mut store, instance).unwrap(), 0);
+        assert_eq!(&*stdout.0.borrow(), b"Hello, world!\n");
+        let Some(ExternVal::Mem(mem)) = store.export(instance, "memory") else {
+            panic!("memory is exported");
+        };
+        let data = &store.mems[mem].data;
+        assert_eq!(&data[512..526], b"wasi-test\0arg\0");
+        assert_eq!(&data[896..906], b"KEY=value\0");
+        let scratch = store.instances[instance].mems[0];
+        assert!(store.mems[scratch].data.iter().all(|&byte| byte == 0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks() {
+        let module = Module::parse(
+            r#"(module
+                (import "wasi_snapshot_preview1" "path_open"
+                  (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 32) "link")
+                (data (i32.const 48) "inner")
+                (func (export "open") (param i32 i32 i32) (result i32)
+                  (call $path_open (i32.const 3) (local.get 0) (local.get 1) (local.get 2)
+                    (i32.const 1) (i64.const 64) (i64.const 0) (i32.const 0) (i32.const 0))))"#,
+        )
+        .unwrap();
+        let id = std::process::id();
+        let dir = std::env::temp_dir().join(format!("wasm-parse-symlinks-{id}"));
+        let outside = std::env::temp_dir().join(format!("outside-{id}"));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("input.txt"), "").unwrap();
+        std::os::unix::fs::symlink(&outside, dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("input.txt", dir.join("inner")).unwrap();
+
+        let mut store = Store::new();
+        let mut imports = Imports::new();
+        WasiCtx::new()
+            .preopen_dir(&dir, ".")
+            .link(&mut store, &mut imports, &module);
+        let instance = store.instantiate(&module, &imports).unwrap();
+        let mut open = |follow: i32, path: i32, len: i32| {
+            let args = [Value::I32(follow), Value::I32(path), Value::I32(len)];
+            match store.invoke_export(instance, "open", &args).unwrap()[..] {
+                [Value::I32(errno)] => errno as u16,
+                ref results => panic!("{results:?}"),
+            }
+        };
+
+        // A dangling symlink to outside the preopen must not create its target.
+        assert_eq!(open(1, 32, 4), ERRNO_NOTCAPABLE);
+        assert_eq!(open(0, 32, 4), ERRNO_LOOP);
+        assert!(!outside.exists());
+        assert_eq!(open(1, 48, 5), ERRNO_SUCCESS);
+        assert_eq!(open(0, 48, 5), ERRNO_LOOP);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,469 @@
+//! Runner for the `.wast` scripts of the WebAssembly spec test suite.
+//!
+//! Modules go through the parser and validator and are instantiated in the
+//! interpreter, with the `spectest` host module available for imports.
+
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::interpreter::{ExternVal, Imports, InstanceAddr, Store, Trap, Value};
+use crate::modules::Module;
+use crate::types::{FuncType, GlobalType, Limits, NumType, RefType, TableType, ValType};
+use crate::validate::validate;
+use crate::wat::{f32_lit, f64_lit, int_lit, module_from, sexprs, Sexpr};
+use crate::Error;
+
+/// Outcome of a script: the number of passed commands and a message per failure.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub passed: usize,
+    pub failures: Vec<String>,
+}
+
+/// An expected result of `assert_return`.
+#[derive(Debug)]
+enum Expected {
+    Value(Value),
+    CanonicalNan(NumType),
+    ArithmeticNan(NumType),
+    /// Expected lanes of a `f32x4` or `f64x2` vector that may contain NaN patterns.
+    Lanes(Vec<Expected>),
+    FuncRef,
+    ExternRef,
+    Either(Vec<Expected>),
+}
+
+pub struct Wast {
+    store: Store,
+    imports: Imports,
+    current: Option<InstanceAddr>,
+    named: HashMap<String, InstanceAddr>,
+}
+
+impl Default for Wast {
+    fn default() -> Self {
+        let mut store = Store::new();
+        let mut imports = Imports::new();
+        spectest(&mut store, &mut imports);
+        Self {
+            store,
+            imports,
+            current: None,
+            named: HashMap::new(),
+        }
+    }
+}
+
+/// The `spectest` module the test suite imports from.
+fn spectest(store: &mut Store, imports: &mut Imports) {
+    let i32 = ValType::NumType(NumType::I32);
+    let i64 = ValType::NumType(NumType::I64);
+    let f32 = ValType::NumType(NumType::F32);
+    let f64 = ValType::NumType(NumType::F64);
+    let prints = [
+        ("print", vec![]),
+        ("print_i32", vec![i32]),
+        ("print_i64", vec![i64]),
+        ("print_f32", vec![f32]),
+        ("print_f64", vec![f64]),
+        ("print_i32_f32", vec![i32, f32]),
+        ("print_f64_f64", vec![f64, f64]),
+    ];
+    for (name, params) in prints {
+        let func = store.alloc_host_func(FuncType(params, vec![]), |_, _| Ok(Vec::new()));
+        imports.define("spectest", name, ExternVal::Func(func));
+    }
+    let globals = [
+        ("global_i32", Value::I32(666)),
+        ("global_i64", Value::I64(666)),
+        ("global_f32", Value::F32(666.6)),
+        ("global_f64", Value::F64(666.6)),
+    ];
+    for (name, value) in globals {
+        let global = store.alloc_global(GlobalType(false, value.ty()), value);
+        imports.define("spectest", name, ExternVal::Global(global));
+    }
+    let table = store.alloc_table(
+        TableType(RefType::FUNCREF, Limits(10, Some(20), false, false)),
+        Value::FuncRef(None),
+    );
+    imports.define("spectest", "table", ExternVal::Table(table));
+    let memory = store.alloc_memory(Limits(1, Some(2), false, false));
+    imports.define("spectest", "memory", ExternVal::Mem(memory));
+}
+
+fn line(src: &str, pos: usize) -> usize {
+    src[..pos.min(src.len())].matches('\n').count() + 1
+}
+
+fn string(sexpr: Option<&Sexpr>) -> Result<String, String> {
+    match sexpr {
+        Some(Sexpr::Str(bytes, _)) => String::from_utf8(bytes.clone()).map_err(|e| e.to_string()),
+        _ => Err("expected a string".to_string()),
+    }
+}
+
+fn nan(text: &str, ty: NumType) -> Option<Expected> {
+    match text {
+        "nan:canonical" => Some(Expected::CanonicalNan(ty)),
+        "nan:arithmetic" => Some(Expected::ArithmeticNan(ty)),
+        _ => None,
+    }
+}
+
+/// A `v128.const` shape and its lanes, as bytes or as per lane expectations.
+fn v128(items: &[Sexpr], patterns: bool) -> Result<Expected, String> {
+    let shape = items.get(1).and_then(Sexpr::atom).unwrap_or_default();
+    let (lanes, bits) = match shape {
+        "i8x16" => (16, 8),
+        "i16x8" => (8, 16),
+        "i32x4" | "f32x4" => (4, 32),
+        "i64x2" | "f64x2" => (2, 64),
+        _ => return Err(format!("unknown vector shape {shape}")),
+    };
+    let texts: Vec<&str> = items[2..].iter().filter_map(Sexpr::atom).collect();
+    if texts.len() != lanes {
+        return Err("wrong number of lane literals".to_string());
+    }
+    let mut bytes = Vec::new();
+    let mut expected = Vec::new();
+    for text in texts {
+        let lane = match shape {
+            "f32x4" => match nan(text, NumType::F32).filter(|_| patterns) {
+                Some(nan) => {
+                    expected.push(nan);
+                    Some(0)
+                }
+                None => f32_lit(text).map(|value| {
+                    expected.push(Expected::Value(Value::F32(value)));
+                    value.to_bits() as u64
+                }),
+            },
+            "f64x2" => match nan(text, NumType::F64).filter(|_| patterns) {
+                Some(nan) => {
+                    expected.push(nan);
+                    Some(0)
+                }
+                None => f64_lit(text).map(|value| {
+                    expected.push(Expected::Value(Value::F64(value)));
+                    value.to_bits()
+                }),
+            },
+            _ => int_lit(text, bits),
+        }
+        .ok_or_else(|| format!("invalid lane literal {text}"))?;
+        bytes.extend(&lane.to_le_bytes()[..bits as usize / 8]);
+    }
+    match shape {
+        "f32x4" | "f64x2" if patterns => Ok(Expected::Lanes(expected)),
+        _ => Ok(Expected::Value(Value::V128(u128::from_le_bytes(
+            bytes.try_into().unwrap(),
+        )))),
+    }
+}
+
+/// An argument or expected result. Patterns (NaN classes, non-null
+/// references, alternatives) are only accepted when `patterns` is set.
+fn expected(sexpr: &Sexpr, patterns: bool) -> Result<Expected, String> {
+    let Sexpr::List(items, _) = sexpr else {
+        return Err("expected a constant".to_string());
+    };
+    let head = sexpr.head().unwrap_or_default();
+    let arg = items.get(1).and_then(Sexpr::atom);
+    let invalid = || format!("invalid constant ({head} {})", arg.unwrap_or_default());
+    let value = match (head, arg) {
+        ("i32.const", Some(text)) => Value::I32(int_lit(text, 32).ok_or_else(invalid)? as i32),
+        ("i64.const", Some(text)) => Value::I64(int_lit(text, 64).ok_or_else(invalid)? as i64),
+        ("f32.const", Some(text)) => match nan(text, NumType::F32).filter(|_| patterns) {
+            Some(nan) => return Ok(nan),
+            None => Value::F32(f32_lit(text).ok_or_else(invalid)?),
+        },
+        ("f64.const", Some(text)) => match nan(text, NumType::F64).filter(|_| patterns) {
+            Some(nan) => return Ok(nan),
+            None => Value::F64(f64_lit(text).ok_or_else(invalid)?),
+        },
+        ("v128.const", _) => return v128(items, patterns),
+        ("ref.null", Some("func" | "funcref")) => Value::FuncRef(None),
+        ("ref.null", Some("extern" | "externref")) => Value::ExternRef(None),
+        ("ref.null", Some("exn" | "exnref")) => Value::ExnRef(None),
+        ("ref.extern", Some(text)) => {
+            Value::ExternRef(Some(int_lit(text, 32).ok_or_else(invalid)? as u32))
+        }
+        ("ref.extern", None) if patterns => return Ok(Expected::ExternRef),
+        ("ref.func", None) if patterns => return Ok(Expected::FuncRef),
+        ("either", _) if patterns => {
+            return items[1..]
+                .iter()
+                .map(|item| expected(item, true))
+                .collect::<Result<_, _>>()
+                .map(Expected::Either)
+        }
+        _ => return Err(invalid()),
+    };
+    Ok(Expected::Value(value))
+}
+
+fn value(sexpr: &Sexpr) -> Result<Value, String> {
+    match expected(sexpr, false)? {
+        Expected::Value(value) => Ok(value),
+        pattern => Err(format!("{pattern:?} is not a value")),
+    }
+}
+
+fn is_nan32(bits: u32, canonical: bool) -> bool {
+    match canonical {
+        true => bits & 0x7fff_ffff == 0x7fc0_0000,
+        false => bits & 0x7fc0_0000 == 0x7fc0_0000,
+    }
+}
+
+fn is_nan64(bits: u64, canonical: bool) -> bool {
+    match canonical {
+        true => bits & 0x7fff_ffff_ffff_ffff == 0x7ff8_0000_0000_0000,
+        false => bits & 0x7ff8_0000_0000_0000 == 0x7ff8_0000_0000_0000,
+    }
+}
+
+fn matches(expected: &Expected, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Expected::Value(Value::F32(e)), Value::F32(a)) => e.to_bits() == a.to_bits(),
+        (Expected::Value(Value::F64(e)), Value::F64(a)) => e.to_bits() == a.to_bits(),
+        (Expected::Value(e), a) => e == a,
+        (Expected::CanonicalNan(NumType::F32), Value::F32(a)) => is_nan32(a.to_bits(), true),
+        (Expected::ArithmeticNan(NumType::F32), Value::F32(a)) => is_nan32(a.to_bits(), false),
+        (Expected::CanonicalNan(NumType::F64), Value::F64(a)) => is_nan64(a.to_bits(), true),
+        (Expected::ArithmeticNan(NumType::F64), Value::F64(a)) => is_nan64(a.to_bits(), false),
+        (Expected::Lanes(lanes), Value::V128(bits)) => {
+            let width = 128 / lanes.len();
+            lanes.iter().enumerate().all(|(i, lane)| {
+                let bits = bits >> (i * width);
+                let value = match width {
+                    32 => Value::F32(f32::from_bits(bits as u32)),
+                    _ => Value::F64(f64::from_bits(bits as u64)),
+                };
+                matches(lane, &value)
+            })
+        }
+        (Expected::FuncRef, Value::FuncRef(Some(_))) => true,
+        (Expected::ExternRef, Value::ExternRef(Some(_))) => true,
+        (Expected::Either(alternatives), a) => alternatives.iter().any(|e| matches(e, a)),
+        _ => false,
+    }
+}
+
+impl Wast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs every command of a script. Only a script that is not made of
+    /// s-expressions is an error, failing commands are collected in the report.
+    pub fn run(&mut self, src: &str) -> Result<Report, Error> {
+        let mut report = Report::default();
+        for command in sexprs(src)? {
+            let result = catch_unwind(AssertUnwindSafe(|| self.command(src, &command)))
+                .unwrap_or_else(|panic| {
+                    let msg = panic
+                        .downcast_ref::<String>()
+                        .map(String::as_str)
+                        .or_else(|| panic.downcast_ref::<&str>().copied())
+                        .unwrap_or("unknown panic");
+                    Err(format!("panicked: {msg}"))
+                });
+            match result {
+                Ok(()) => report.passed += 1,
+                Err(msg) => {
+                    let head = command.head().unwrap_or_default();
+                    let line = line(src, command.pos());
+                    report.failures.push(format!("{line}: {head}: {msg}"));
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    fn command(&mut self, src: &str, command: &Sexpr) -> Result<(), String> {
+        let Sexpr::List(items, _) = command else {
+            return Err("expected a command".to_string());
+        };
+        let message = || string(items.get(2));
+        match command.head().unwrap_or_default() {
+            "module" => {
+                let module = self.module(src, command)?;
+                let instance = self
+                    .instantiate(&module)
+                    .map_err(|err| format!("{err:?}"))?;
+                self.current = Some(instance);
+                if let Some(id) = items.get(1).and_then(Sexpr::atom) {
+                    if id.starts_with('$') {
+                        self.named.insert(id.to_string(), instance);
+                    }
+                }
+                Ok(())
+            }
+            "register" => {
+                let name = string(items.get(1))?;
+                let instance = self.instance(items.get(2).and_then(Sexpr::atom))?;
+                self.imports.instance(&self.store, &name, instance);
+                Ok(())
+            }
+            "invoke" | "get" => self
+                .action(command)?
+                .map(|_| ())
+                .map_err(|err| format!("{err:?}")),
+            "assert_return" => {
+                let action = items.get(1).ok_or("missing action")?;
+                let results = self.action(action)?.map_err(|err| format!("{err:?}"))?;
+                let expected = items[2..]
+                    .iter()
+                    .map(|item| expected(item, true))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let ok = results.len() == expected.len()
+                    && expected.iter().zip(&results).all(|(e, a)| matches(e, a));
+                match ok {
+                    true => Ok(()),
+                    false => Err(format!("expected {expected:?}, got {results:?}")),
+                }
+            }
+            "assert_trap" | "assert_exhaustion" | "assert_uninstantiable" => {
+                let target = items.get(1).ok_or("missing action")?;
+                let result = match target.head() {
+                    Some("module") => {
+                        let module = self.module(src, target)?;
+                        self.instantiate(&module).map(|_| Vec::new())
+                    }
+                    _ => self.action(target)?,
+                };
+                let expected = message()?;
+                match result {
+                    Err(Error::Trap(_, trap)) if trap.to_string().starts_with(&expected) => Ok(()),
+                    Err(Error::Trap(_, trap)) => Err(format!("expected {expected}, got {trap}")),
+                    Err(err) => Err(format!("expected {expected}, got {err:?}")),
+                    Ok(results) => Err(format!("expected {expected}, got {results:?}")),
+                }
+            }
+            "assert_exception" => {
+                let action = items.get(1).ok_or("missing action")?;
+                match self.action(action)? {
+                    Err(Error::Trap(_, Trap::Exception(_))) => Ok(()),
+                    Err(err) => Err(format!("expected an exception, got {err:?}")),
+                    Ok(results) => Err(format!("expected an exception, got {results:?}")),
+                }
+            }
+            "assert_invalid" => {
+                let target = items.get(1).ok_or("missing module")?;
+                let module = module_from(src, target).map_err(|err| format!("{err:?}"))?;
+                match validate(&module) {
+                    Err(_) => Ok(()),
+                    Ok(()) => Err(format!("expected {}, module is valid", message()?)),
+                }
+            }
+            "assert_malformed" => {
+                let target = items.get(1).ok_or("missing module")?;
+                match module_from(src, target) {
+                    Err(_) => Ok(()),
+                    Ok(_) => Err(format!("expected {}, module parsed", message()?)),
+                }
+            }
+            "assert_unlinkable" => {
+                let target = items.get(1).ok_or("missing module")?;
+                let module = self.module(src, target)?;
+                match self.instantiate(&module) {
+                    Err(Error::Link(_, _)) => Ok(()),
+                    Err(err) => Err(format!("expected {}, got {err:?}", message()?)),
+                    Ok(_) => Err(format!("expected {}, module linked", message()?)),
+                }
+            }
+            head => Err(format!("unsupported command {head}")),
+        }
+    }
+
+    /// Parses and validates a `(module ...)`.
+    fn module(&self, src: &str, sexpr: &Sexpr) -> Result<Module, String> {
+        let module = module_from(src, sexpr).map_err(|err| format!("{err:?}"))?;
+        validate(&module).map_err(|err| format!("{err:?}"))?;
+        Ok(module)
+    }
+
+    fn instantiate(&mut self, module: &Module) -> Result<InstanceAddr, Error> {
+        self.store.instantiate(module, &self.imports)
+    }
+
+    fn instance(&self, id: Option<&str>) -> Result<InstanceAddr, String> {
+        match id {
+            Some(id) if id.starts_with('$') => self
+                .named
+                .get(id)
+                .copied()
+                .ok_or_else(|| format!("unknown module {id}")),
+            _ => self
+                .current
+                .ok_or_else(|| "no module instantiated".to_string()),
+        }
+    }
+
+    /// Performs an `invoke` or `get`; the outer error is a malformed action.
+    fn action(&mut self, action: &Sexpr) -> Result<Result<Vec<Value>, Error>, String> {
+        let Sexpr::List(items, _) = action else {
+            return Err("expected an action".to_string());
+        };
+        let id = items.get(1).and_then(Sexpr::atom);
+        let at = if id.is_some() { 2 } else { 1 };
+        let instance = self.instance(id)?;
+        let name = string(items.get(at))?;
+        match action.head() {
+            Some("invoke") => {
+                let args = items[at + 1..]
+                    .iter()
+                    .map(value)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(self.store.invoke_export(instance, &name, &args))
+            }
+            Some("get") => match self.store.export(instance, &name) {
+                Some(ExternVal::Global(global)) => Ok(Ok(vec![self.store.globals[global].value])),
+                _ => Err(format!("unknown global export {name}")),
+            },
+            _ => Err("expected an action".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Wast;
+
+    const WAST: &str = r#"
+        (module $m
+          (import "spectest" "global_i32" (global $g i32))
+          (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+          (func (export "div") (param f32 f32) (result f32)
+            (f32.div (local.get 0) (local.get 1)))
+          (func (export "g") (result i32) (global.get $g))
+          (func $loop (export "loop") (call $loop)))
+        (register "m" $m)
+        (module
+          (import "m" "add" (func $add (param i32 i32) (result i32)))
+          (func (export "twice") (param i32) (result i32)
+            (call $add (local.get 0) (local.get 0))))
+        (assert_return (invoke "twice" (i32.const 21)) (i32.const 42))
+        (assert_return (invoke $m "div" (f32.const 0) (f32.const 0)) (f32.const nan:canonical))
+        (assert_return (invoke $m "g") (i32.const 666))
+        (assert_exhaustion (invoke $m "loop") "call stack exhausted")
+        (assert_trap (module (func $start unreachable) (start $start)) "unreachable")
+        (assert_invalid (module (func (result i32) (i64.const 0))) "type mismatch")
+        (assert_malformed (module quote "(func (i32.const))") "unexpected token")
+        (assert_malformed (module binary "\00asn" "\01\00\00\00") "magic header not detected")
+        (assert_malformed (module binary "\00asm" "\01\00\00\00" "\0e\01\00") "malformed section id")
+        (assert_unlinkable (module (import "m" "missing" (func))) "unknown import")
+        (assert_return (invoke "twice" (i32.const 1)) (i32.const 3))
+    "#;
+
+    #[test]
+    fn script() {
+        let report = Wast::new().run(WAST).unwrap();
+        assert_eq!(report.passed, 13, "{:#?}", report.failures);
+        assert_eq!(report.failures.len(), 1);
+        assert!(report.failures[0].contains("assert_return"));
+    }
+}
@@ -0,0 +1,2976 @@
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+
+use crate::custom::{NameMap, NameSec};
+use crate::instructions::{BlockType, Catch, Instr, MemArg};
+use crate::modules::*;
+use crate::printer::natural_align;
+use crate::types::*;
+use crate::{Encode, Error, Parse, IB};
+
+/// A parsed s-expression, every node keeps its byte offset in the source text.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Sexpr {
+    Atom(String, usize),
+    Str(Vec<u8>, usize),
+    List(Vec<Sexpr>, usize),
+}
+
+impl Sexpr {
+    pub(crate) fn pos(&self) -> usize {
+        match self {
+            Sexpr::Atom(_, pos) | Sexpr::Str(_, pos) | Sexpr::List(_, pos) => *pos,
+        }
+    }
+
+    pub(crate) fn atom(&self) -> Option<&str> {
+        match self {
+            Sexpr::Atom(atom, _) => Some(atom),
+            _ => None,
+        }
+    }
+
+    /// The items of a list whose first element is the keyword `head`.
+    pub(crate) fn list(&self, head: &str) -> Option<&[Sexpr]> {
+        match self {
+            Sexpr::List(items, _) if items.first().and_then(Sexpr::atom) == Some(head) => {
+                Some(items)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn head(&self) -> Option<&str> {
+        match self {
+            Sexpr::List(items, _) => items.first().and_then(Sexpr::atom),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn error(src: &str, pos: usize, msg: impl Into<String>) -> Error {
+    let before = &src[..pos.min(src.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    Error::InvalidWat(
+        Backtrace::capture(),
+        format!("{line}:{column}: {}", msg.into()),
+    )
+}
+
+/// Splits the text into top level s-expressions, dropping comments.
+pub(crate) fn sexprs(src: &str) -> Result<Vec<Sexpr>, Error> {
+    let bytes = src.as_bytes();
+    let mut stack: Vec<(Vec<Sexpr>, usize)> = vec![(Vec::new(), 0)];
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            b' ' | b'\t' | b'\n' | b'\r' => i += 1,
+            b';' if bytes.get(i + 1) == Some(&b';') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'(' if bytes.get(i + 1) == Some(&b';') => {
+                let mut depth = 0;
+                loop {
+                    match (bytes.get(i), bytes.get(i + 1)) {
+                        (Some(b'('), Some(b';')) => {
+                            depth += 1;
+                            i += 2;
+                        }
+                        (Some(b';'), Some(b')')) => {
+                            depth -= 1;
+                            i += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        (Some(_), _) => i += 1,
+                        (None, _) => return Err(error(src, start, "unclosed block comment")),
+                    }
+                }
+            }
+            b'(' => {
+                stack.push((Vec::new(), i));
+                i += 1;
+            }
+            b')' => {
+                if stack.len() == 1 {
+                    return Err(error(src, i, "unexpected `)`"));
+                }
+                let (items, pos) = stack.pop().unwrap();
+                stack.last_mut().unwrap().0.push(Sexpr::List(items, pos));
+                i += 1;
+            }
+            b'"' => {
+                i += 1;
+                let mut out = Vec::new();
+                loop {
+                    match bytes.get(i) {
+                        None | Some(b'\n') => return Err(error(src, start, "unclosed string")),
+                        Some(b'"') => break,
+                        Some(b'\\') => {
+                            let escape = bytes
+                                .get(i + 1)
+                                .ok_or_else(|| error(src, i, "unclosed string"))?;
+                            i += 2;
+                            match escape {
+                                b't' => out.push(b'\t'),
+                                b'n' => out.push(b'\n'),
+                                b'r' => out.push(b'\r'),
+                                b'"' => out.push(b'"'),
+                                b'\'' => out.push(b'\''),
+                                b'\\' => out.push(b'\\'),
+                                b'u' => {
+                                    let end = src[i..]
+                                        .find('}')
+                                        .filter(|_| bytes.get(i) == Some(&b'{'))
+                                        .ok_or_else(|| error(src, i, "invalid unicode escape"))?;
+                                    let hex = src[i + 1..i + end].replace('_', "");
+                                    let c = u32::from_str_radix(&hex, 16)
+                                        .ok()
+                                        .and_then(char::from_u32)
+                                        .ok_or_else(|| error(src, i, "invalid unicode escape"))?;
+                                    out.extend(c.to_string().as_bytes());
+                                    i += end + 1;
+                                }
+                                hi if hi.is_ascii_hexdigit() => {
+                                    let lo = bytes
+                                        .get(i)
+                                        .filter(|lo| lo.is_ascii_hexdigit())
+                                        .ok_or_else(|| error(src, i, "invalid escape"))?;
+                                    let hex = [*hi, *lo];
+                                    let hex = std::str::from_utf8(&hex).unwrap();
+                                    out.push(u8::from_str_radix(hex, 16).unwrap());
+                                    i += 1;
+                                }
+                                _ => return Err(error(src, i - 2, "invalid escape")),
+                            }
+                        }
+                        Some(byte) => {
+                            out.push(*byte);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+                stack.last_mut().unwrap().0.push(Sexpr::Str(out, start));
+            }
+            _ => {
+                while i < bytes.len()
+                    && !matches!(bytes[i], b' ' | b'\t' | b'\n' | b'\r' | b'(' | b')' | b'"')
+                    && !(bytes[i] == b';' && bytes.get(i + 1) == Some(&b';'))
+                {
+                    i += 1;
+                }
+                let atom = src[start..i].to_string();
+                stack.last_mut().unwrap().0.push(Sexpr::Atom(atom, start));
+            }
+        }
+    }
+    if stack.len() > 1 {
+        return Err(error(src, stack.last().unwrap().1, "unclosed `(`"));
+    }
+    Ok(stack.pop().unwrap().0)
+}
+
+fn is_id(atom: &str) -> bool {
+    atom.len() > 1 && atom.starts_with('$')
+}
+
+/// Magnitude and sign of an integer literal, `_` separators and `0x` prefixes allowed.
+fn int(text: &str) -> Option<(bool, u64)> {
+    let (neg, text) = match text.as_bytes().first()? {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let (radix, digits) = match text.strip_prefix("0x") {
+        Some(digits) => (16, digits),
+        None => (10, text),
+    };
+    if digits.is_empty()
+        || digits.starts_with('_')
+        || digits.ends_with('_')
+        || digits.contains("__")
+    {
+        return None;
+    }
+    u64::from_str_radix(&digits.replace('_', ""), radix)
+        .ok()
+        .filter(|_| !digits.starts_with('+'))
+        .map(|value| (neg, value))
+}
+
+pub(crate) fn u32_lit(text: &str) -> Option<u32> {
+    if text.starts_with(['+', '-']) {
+        return None;
+    }
+    int(text).and_then(|(_, value)| u32::try_from(value).ok())
+}
+
+pub(crate) fn u64_lit(text: &str) -> Option<u64> {
+    if text.starts_with(['+', '-']) {
+        return None;
+    }
+    int(text).map(|(_, value)| value)
+}
+
+/// An integer literal of `bits` width, signed or unsigned interpretation, as raw bits.
+pub(crate) fn int_lit(text: &str, bits: u32) -> Option<u64> {
+    let (neg, value) = int(text)?;
+    let max = if bits == 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    };
+    if neg {
+        if value > 1 << (bits - 1) {
+            return None;
+        }
+        Some(value.wrapping_neg() & max)
+    } else if value <= max {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// Rounds a hexadecimal float (the text after `0x`) to nearest even, returning its bits.
+fn hex_float(text: &str, mant_bits: i64, exp_bits: i64) -> Option<u64> {
+    let (num, exp) = match text.find(['p', 'P']) {
+        Some(i) => {
+            let (neg, exp) = int(&text[i + 1..]).filter(|_| !text[i + 1..].contains('x'))?;
+            let exp = i64::try_from(exp).ok()?.min(1 << 20);
+            (&text[..i], if neg { -exp } else { exp })
+        }
+        None => (text, 0),
+    };
+    let (whole, frac) = num.split_once('.').unwrap_or((num, ""));
+    if whole.is_empty() || whole.starts_with('_') || frac.starts_with('_') {
+        return None;
+    }
+    let mut m: u128 = 0;
+    let mut e = exp;
+    let mut sticky = false;
+    for (c, is_frac) in whole
+        .chars()
+        .map(|c| (c, false))
+        .chain(frac.chars().map(|c| (c, true)))
+    {
+        if c == '_' {
+            continue;
+        }
+        let digit = c.to_digit(16)? as u128;
+        if m >> 120 == 0 {
+            m = m * 16 + digit;
+            if is_frac {
+                e -= 4;
+            }
+        } else {
+            sticky |= digit != 0;
+            if !is_frac {
+                e += 4;
+            }
+        }
+    }
+    if m == 0 {
+        return Some(0);
+    }
+    let bias = (1 << (exp_bits - 1)) - 1;
+    let exponent = 128 - m.leading_zeros() as i64 - 1 + e;
+    let mut keep = exponent.max(1 - bias);
+    let shift = keep - mant_bits - e;
+    let mut s = if shift <= 0 {
+        m << -shift
+    } else if shift >= 128 {
+        0
+    } else {
+        let dropped = m & ((1 << shift) - 1);
+        let half = 1 << (shift - 1);
+        let s = m >> shift;
+        if dropped > half || (dropped == half && (sticky || s & 1 == 1)) {
+            s + 1
+        } else {
+            s
+        }
+    };
+    if s == 1 << (mant_bits + 1) {
+        s >>= 1;
+        keep += 1;
+    }
+    if s >> mant_bits == 0 {
+        return Some(s as u64);
+    }
+    let biased = keep + bias;
+    if biased >= (1 << exp_bits) - 1 {
+        return None;
+    }
+    Some(((biased as u64) << mant_bits) | (s as u64 & ((1 << mant_bits) - 1)))
+}
+
+/// Float literal bits for a format with `mant_bits` of mantissa and `exp_bits` of exponent.
+fn float_lit(text: &str, mant_bits: i64, exp_bits: i64) -> Option<u64> {
+    let (neg, body) = match text.as_bytes().first()? {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let exp_mask = ((1u64 << exp_bits) - 1) << mant_bits;
+    let bits = if body == "inf" {
+        exp_mask
+    } else if body == "nan" {
+        exp_mask | 1 << (mant_bits - 1)
+    } else if let Some(payload) = body.strip_prefix("nan:0x") {
+        let payload = u64::from_str_radix(&payload.replace('_', ""), 16).ok()?;
+        if payload == 0 || payload >> mant_bits != 0 {
+            return None;
+        }
+        exp_mask | payload
+    } else if let Some(hex) = body.strip_prefix("0x") {
+        hex_float(hex, mant_bits, exp_bits)?
+    } else {
+        if !body.starts_with(|c: char| c.is_ascii_digit())
+            || body.contains("__")
+            || body.contains("_.")
+            || body.contains("._")
+            || body.ends_with('_')
+        {
+            return None;
+        }
+        let body = body.replace('_', "");
+        if mant_bits == 23 {
+            let value = body.parse::<f32>().ok().filter(|v| v.is_finite())?;
+            value.to_bits() as u64
+        } else {
+            let value = body.parse::<f64>().ok().filter(|v| v.is_finite())?;
+            value.to_bits()
+        }
+    };
+    Some(if neg {
+        bits | 1 << (mant_bits + exp_bits)
+    } else {
+        bits
+    })
+}
+
+pub(crate) fn f32_lit(text: &str) -> Option<f32> {
+    float_lit(text, 23, 8).map(|bits| f32::from_bits(bits as u32))
+}
+
+pub(crate) fn f64_lit(text: &str) -> Option<f64> {
+    float_lit(text, 52, 11).map(f64::from_bits)
+}
+
+pub(crate) fn valtype(text: &str) -> Option<ValType> {
+    Some(match text {
+        "i32" => ValType::NumType(NumType::I32),
+        "i64" => ValType::NumType(NumType::I64),
+        "f32" => ValType::NumType(NumType::F32),
+        "f64" => ValType::NumType(NumType::F64),
+        "v128" => ValType::VecType(VecType::V128),
+        _ => ValType::RefType(reftype(text)?),
+    })
+}
+
+fn reftype(text: &str) -> Option<RefType> {
+    match text {
+        "funcref" => Some(RefType::FUNCREF),
+        "externref" => Some(RefType::EXTERNREF),
+        "exnref" => Some(RefType::EXNREF),
+        "anyref" => Some(RefType(true, HeapType::Any)),
+        "eqref" => Some(RefType(true, HeapType::Eq)),
+        "i31ref" => Some(RefType(true, HeapType::I31)),
+        "structref" => Some(RefType(true, HeapType::Struct)),
+        "arrayref" => Some(RefType(true, HeapType::Array)),
+        "nullref" => Some(RefType(true, HeapType::None)),
+        "nullfuncref" => Some(RefType(true, HeapType::NoFunc)),
+        "nullexternref" => Some(RefType(true, HeapType::NoExtern)),
+        "nullexnref" => Some(RefType(true, HeapType::NoExn)),
+        _ => None,
+    }
+}
+
+/// The index spaces of a module, name to index.
+#[derive(Default)]
+struct Spaces {
+    types: HashMap<String, u32>,
+    funcs: HashMap<String, u32>,
+    tables: HashMap<String, u32>,
+    mems: HashMap<String, u32>,
+    globals: HashMap<String, u32>,
+    tags: HashMap<String, u32>,
+    elems: HashMap<String, u32>,
+    datas: HashMap<String, u32>,
+    /// Field names of each struct type.
+    fields: HashMap<u32, HashMap<String, u32>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Space {
+    Func,
+    Table,
+    Mem,
+    Global,
+    Tag,
+}
+
+struct Wat<'a> {
+    src: &'a str,
+    spaces: Spaces,
+    names: NameSec,
+    types: TypeSec,
+    imports: ImportSec,
+    funcs: FuncSec,
+    tables: TableSec,
+    mems: MemSec,
+    tags: TagSec,
+    globals: GlobalSec,
+    exports: ExportSec,
+    start: Option<FuncIdx>,
+    elems: ElemSec,
+    codes: CodeSec,
+    datas: DataSec,
+    customs: Vec<CustomSec>,
+    data_count: bool,
+}
+
+/// Local and label names of the function being parsed.
+struct FuncCtx {
+    locals: HashMap<String, u32>,
+    labels: Vec<Option<String>>,
+}
+
+impl Parse<&str> for Module {
+    fn parse(data: &str) -> Result<Self, Error> {
+        let sexprs = sexprs(data)?;
+        match sexprs.as_slice() {
+            [module] if module.head() == Some("module") => module_from(data, module),
+            fields => Wat::new(data).module(None, fields),
+        }
+    }
+}
+
+/// Builds a module from a `(module ...)` s-expression, including the
+/// `(module binary ...)` and `(module quote ...)` forms.
+pub(crate) fn module_from(src: &str, sexpr: &Sexpr) -> Result<Module, Error> {
+    let items = sexpr
+        .list("module")
+        .ok_or_else(|| error(src, sexpr.pos(), "expected `(module ...)`"))?;
+    let mut pos = 1;
+    let id = items.get(pos).and_then(Sexpr::atom).filter(|a| is_id(a));
+    if id.is_some() {
+        pos += 1;
+    }
+    match items.get(pos).and_then(Sexpr::atom) {
+        Some(kind @ ("binary" | "quote")) => {
+            let mut bytes = Vec::new();
+            for item in &items[pos + 1..] {
+                match item {
+                    Sexpr::Str(str, _) => bytes.extend(str),
+                    _ => return Err(error(src, item.pos(), "expected a string")),
+                }
+            }
+            if kind == "binary" {
+                Module::parse(&mut bytes)
+            } else {
+                let text = String::from_utf8(bytes)
+                    .map_err(|_| error(src, sexpr.pos(), "malformed UTF-8 encoding"))?;
+                Module::parse(format!("(module {text})").as_str())
+            }
+        }
+        _ => Wat::new(src).module(id, &items[pos..]),
+    }
+}
+
+impl<'a> Wat<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            spaces: Spaces::default(),
+            names: NameSec::default(),
+            types: Vec::new(),
+            imports: Vec::new(),
+            funcs: Vec::new(),
+            tables: Vec::new(),
+            mems: Vec::new(),
+            tags: Vec::new(),
+            globals: Vec::new(),
+            exports: Vec::new(),
+            start: None,
+            elems: Vec::new(),
+            codes: Vec::new(),
+            datas: Vec::new(),
+            customs: Vec::new(),
+            data_count: false,
+        }
+    }
+
+    fn error(&self, pos: usize, msg: impl Into<String>) -> Error {
+        error(self.src, pos, msg)
+    }
+
+    fn module(mut self, id: Option<&str>, fields: &[Sexpr]) -> Result<Module, Error> {
+        self.names.module = id.map(|id| id[1..].to_string());
+        self.assign(fields)?;
+        self.typedefs(fields)?;
+        for field in fields {
+            self.field(field)?;
+        }
+        Ok(self.finish())
+    }
+
+    /// First pass: gives every definition its index so fields can refer
+    /// to each other regardless of order. Imports come before definitions.
+    fn assign(&mut self, fields: &[Sexpr]) -> Result<(), Error> {
+        let mut imported = [0u32; 5];
+        let mut defined = Vec::new();
+        let mut types = 0;
+        let mut elems = 0;
+        let mut datas = 0;
+        for field in fields {
+            let Sexpr::List(items, pos) = field else {
+                return Err(self.error(field.pos(), "expected a module field"));
+            };
+            let head = field.head().unwrap_or_default();
+            let id = items.get(1).and_then(Sexpr::atom).filter(|a| is_id(a));
+            let space = match head {
+                "func" => Space::Func,
+                "table" => Space::Table,
+                "memory" => Space::Mem,
+                "global" => Space::Global,
+                "tag" => Space::Tag,
+                "import" => {
+                    let Some(desc) = items.get(3) else {
+                        return Err(self.error(*pos, "expected an import description"));
+                    };
+                    let space = match desc.head() {
+                        Some("func") => Space::Func,
+                        Some("table") => Space::Table,
+                        Some("memory") => Space::Mem,
+                        Some("global") => Space::Global,
+                        Some("tag") => Space::Tag,
+                        _ => return Err(self.error(desc.pos(), "unknown import kind")),
+                    };
+                    let Sexpr::List(desc, _) = desc else {
+                        unreachable!()
+                    };
+                    let id = desc.get(1).and_then(Sexpr::atom).filter(|a| is_id(a));
+                    let idx = imported[space as usize];
+                    imported[space as usize] += 1;
+                    self.name(space, id, idx, desc[0].pos())?;
+                    continue;
+                }
+                "type" | "rec" => {
+                    for (items, pos) in self.group(field)? {
+                        let id = items.get(1).and_then(Sexpr::atom).filter(|a| is_id(a));
+                        if let Some(id) = id {
+                            self.insert_name(|s| &mut s.types, id, types, pos)?;
+                            self.names.types.push((types, id[1..].to_string()));
+                        }
+                        self.field_names(items, types)?;
+                        types += 1;
+                    }
+                    continue;
+                }
+                "elem" => {
+                    if let Some(id) = id {
+                        self.insert_name(|s| &mut s.elems, id, elems, *pos)?;
+                        self.names.elems.push((elems, id[1..].to_string()));
+                    }
+                    elems += 1;
+                    continue;
+                }
+                "data" => {
+                    if let Some(id) = id {
+                        self.insert_name(|s| &mut s.datas, id, datas, *pos)?;
+                        self.names.datas.push((datas, id[1..].to_string()));
+                    }
+                    datas += 1;
+                    continue;
+                }
+                _ => continue,
+            };
+            let inline_import = items
+                .iter()
+                .skip(1)
+                .any(|item| item.head() == Some("import"));
+            if inline_import {
+                let idx = imported[space as usize];
+                imported[space as usize] += 1;
+                self.name(space, id, idx, *pos)?;
+            } else {
+                defined.push((space, id, *pos));
+                let inline = |head| items.iter().any(|item| item.head() == Some(head));
+                if space == Space::Table && inline("elem") {
+                    elems += 1;
+                }
+                if space == Space::Mem && inline("data") {
+                    datas += 1;
+                }
+            }
+        }
+        for (space, id, pos) in defined {
+            let idx = imported[space as usize];
+            imported[space as usize] += 1;
+            self.name(space, id, idx, pos)?;
+        }
+        Ok(())
+    }
+
+    fn insert_name(
+        &mut self,
+        space: fn(&mut Spaces) -> &mut HashMap<String, u32>,
+        id: &str,
+        idx: u32,
+        pos: usize,
+    ) -> Result<(), Error> {
+        if space(&mut self.spaces)
+            .insert(id.to_string(), idx)
+            .is_some()
+        {
+            return Err(self.error(pos, format!("duplicate identifier {id}")));
+        }
+        Ok(())
+    }
+
+    fn name(&mut self, space: Space, id: Option<&str>, idx: u32, pos: usize) -> Result<(), Error> {
+        let Some(id) = id else {
+            return Ok(());
+        };
+        let names = match space {
+            Space::Func => &mut self.names.funcs,
+            Space::Table => &mut self.names.tables,
+            Space::Mem => &mut self.names.mems,
+            Space::Global => &mut self.names.globals,
+            Space::Tag => &mut self.names.tags,
+        };
+        names.push((idx, id[1..].to_string()));
+        let map: fn(&mut Spaces) -> &mut HashMap<String, u32> = match space {
+            Space::Func => |s| &mut s.funcs,
+            Space::Table => |s| &mut s.tables,
+            Space::Mem => |s| &mut s.mems,
+            Space::Global => |s| &mut s.globals,
+            Space::Tag => |s| &mut s.tags,
+        };
+        self.insert_name(map, id, idx, pos)
+    }
+
+    fn finish(mut self) -> Module {
+        for names in [
+            &mut self.names.funcs,
+            &mut self.names.tables,
+            &mut self.names.mems,
+            &mut self.names.globals,
+            &mut self.names.tags,
+        ] {
+            names.sort_by_key(|(idx, _)| *idx);
+        }
+        let mut sections = Vec::new();
+        if !self.types.is_empty() {
+            sections.push(Section::Type(self.types));
+        }
+        if !self.imports.is_empty() {
+            sections.push(Section::Import(self.imports));
+        }
+        if !self.funcs.is_empty() {
+            sections.push(Section::Function(self.funcs));
+        }
+        if !self.tables.is_empty() {
+            sections.push(Section::Table(self.tables));
+        }
+        if !self.mems.is_empty() {
+            sections.push(Section::Memory(self.mems));
+        }
+        if !self.tags.is_empty() {
+            sections.push(Section::Tag(self.tags));
+        }
+        if !self.globals.is_empty() {
+            sections.push(Section::Global(self.globals));
+        }
+        if !self.exports.is_empty() {
+            sections.push(Section::Export(self.exports));
+        }
+        if let Some(start) = self.start {
+            sections.push(Section::Start(start));
+        }
+        if !self.elems.is_empty() {
+            sections.push(Section::Element(self.elems));
+        }
+        if self.data_count {
+            sections.push(Section::DataCountSection(self.datas.len() as u32));
+        }
+        if !self.codes.is_empty() {
+            sections.push(Section::Code(self.codes));
+        }
+        if !self.datas.is_empty() {
+            sections.push(Section::Data(self.datas));
+        }
+        sections.extend(self.customs.into_iter().map(Section::Custom));
+        if self.names != NameSec::default() {
+            sections.push(Section::Custom(CustomSec::from(&self.names)));
+        }
+        Module {
+            magic: u32::from_le_bytes(*b"\0asm"),
+            version: 1,
+            sections,
+            layout: None,
+            custom_errors: Vec::new(),
+        }
+    }
+
+    fn field(&mut self, field: &Sexpr) -> Result<(), Error> {
+        let Sexpr::List(items, pos) = field else {
+            unreachable!()
+        };
+        let pos = *pos;
+        match field.head().unwrap_or_default() {
+            "type" | "rec" => Ok(()),
+            "import" => self.import(items, pos),
+            "func" => self.func(items),
+            "table" => self.table(items),
+            "memory" => self.memory(items),
+            "global" => self.global(items),
+            "tag" => self.tag(items),
+            "export" => {
+                let name = self.name_str(items, 1, pos)?;
+                let desc = items
+                    .get(2)
+                    .ok_or_else(|| self.error(pos, "expected an export description"))?;
+                let Sexpr::List(desc, desc_pos) = desc else {
+                    return Err(self.error(desc.pos(), "expected an export description"));
+                };
+                let mut at = 1;
+                let desc = match desc.first().and_then(Sexpr::atom) {
+                    Some("func") => ExportDesc::FuncIdx(self.index(desc, &mut at, Space::Func)?),
+                    Some("table") => {
+                        ExportDesc::TableIdx(self.index(desc, &mut at, Space::Table)?)
+                    }
+                    Some("memory") => ExportDesc::MemIdx(self.index(desc, &mut at, Space::Mem)?),
+                    Some("global") => {
+                        ExportDesc::GlobalIdx(self.index(desc, &mut at, Space::Global)?)
+                    }
+                    Some("tag") => ExportDesc::TagIdx(self.index(desc, &mut at, Space::Tag)?),
+                    _ => return Err(self.error(*desc_pos, "unknown export kind")),
+                };
+                self.exports.push((name, desc));
+                Ok(())
+            }
+            "start" => {
+                let mut at = 1;
+                self.start = Some(self.index(items, &mut at, Space::Func)?);
+                Ok(())
+            }
+            "elem" => self.elem(items, pos),
+            "data" => self.data(items, pos),
+            "@custom" => {
+                let name = self.name_str(items, 1, pos)?;
+                let mut bytes = Vec::new();
+                for item in &items[2..] {
+                    match item {
+                        Sexpr::Str(str, _) => bytes.extend(str),
+                        // placement such as `(after code)` is not tracked
+                        Sexpr::List(_, _) => {}
+                        Sexpr::Atom(_, pos) => return Err(self.error(*pos, "expected a string")),
+                    }
+                }
+                self.customs.push(CustomSec(name, bytes));
+                Ok(())
+            }
+            head if head.starts_with('@') => Ok(()),
+            head => Err(self.error(pos, format!("unknown module field `{head}`"))),
+        }
+    }
+
+    fn name_str(&self, items: &[Sexpr], at: usize, pos: usize) -> Result<String, Error> {
+        match items.get(at) {
+            Some(Sexpr::Str(bytes, pos)) => String::from_utf8(bytes.clone())
+                .map_err(|_| self.error(*pos, "malformed UTF-8 encoding")),
+            Some(item) => Err(self.error(item.pos(), "expected a string")),
+            None => Err(self.error(pos, "expected a string")),
+        }
+    }
+
+    /// Skips the optional `$id` and collects inline `(export "name")` and `(import "m" "n")`.
+    #[allow(clippy::type_complexity)]
+    fn inline(
+        &self,
+        items: &[Sexpr],
+        at: &mut usize,
+    ) -> Result<(Vec<String>, Option<(String, String)>), Error> {
+        if items.get(*at).and_then(Sexpr::atom).is_some_and(is_id) {
+            *at += 1;
+        }
+        let mut exports = Vec::new();
+        let mut import = None;
+        while let Some(item) = items.get(*at) {
+            if let Some(export) = item.list("export") {
+                exports.push(self.name_str(export, 1, item.pos())?);
+            } else if let Some(inline) = item.list("import") {
+                import = Some((
+                    self.name_str(inline, 1, item.pos())?,
+                    self.name_str(inline, 2, item.pos())?,
+                ));
+            } else {
+                break;
+            }
+            *at += 1;
+        }
+        Ok((exports, import))
+    }
+
+    fn export_all(&mut self, exports: Vec<String>, desc: ExportDesc) {
+        for name in exports {
+            self.exports.push((name, desc.clone()));
+        }
+    }
+
+    fn space_len(&self, space: Space) -> u32 {
+        let imported = self
+            .imports
+            .iter()
+            .filter(|import| {
+                matches!(
+                    (&import.desc, space),
+                    (ImportDesc::TypeIdx(_), Space::Func)
+                        | (ImportDesc::TableType(_), Space::Table)
+                        | (ImportDesc::MemType(_), Space::Mem)
+                        | (ImportDesc::GlobalType(_), Space::Global)
+                        | (ImportDesc::TagType(_), Space::Tag)
+                )
+            })
+            .count();
+        let defined = match space {
+            Space::Func => self.funcs.len(),
+            Space::Table => self.tables.len(),
+            Space::Mem => self.mems.len(),
+            Space::Global => self.globals.len(),
+            Space::Tag => self.tags.len(),
+        };
+        (imported + defined) as u32
+    }
+
+    fn import(&mut self, items: &[Sexpr], pos: usize) -> Result<(), Error> {
+        let module = self.name_str(items, 1, pos)?;
+        let name = self.name_str(items, 2, pos)?;
+        let desc = &items[3];
+        let Sexpr::List(desc_items, _) = desc else {
+            unreachable!()
+        };
+        let mut at = 1;
+        if desc_items.get(at).and_then(Sexpr::atom).is_some_and(is_id) {
+            at += 1;
+        }
+        let desc = match desc.head() {
+            Some("func") => ImportDesc::TypeIdx(self.typeuse(desc_items, &mut at)?.0),
+            Some("table") => ImportDesc::TableType(self.tabletype(desc_items, &mut at)?),
+            Some("memory") => ImportDesc::MemType(self.limits(desc_items, &mut at)?),
+            Some("tag") => ImportDesc::TagType(TagType(self.typeuse(desc_items, &mut at)?.0)),
+            _ => ImportDesc::GlobalType(self.globaltype(desc_items, &mut at)?),
+        };
+        self.end(desc_items, at)?;
+        self.imports.push(Import { module, name, desc });
+        Ok(())
+    }
+
+    fn end(&self, items: &[Sexpr], at: usize) -> Result<(), Error> {
+        match items.get(at) {
+            Some(item) => Err(self.error(item.pos(), "unexpected token")),
+            None => Ok(()),
+        }
+    }
+
+    fn func(&mut self, items: &[Sexpr]) -> Result<(), Error> {
+        let mut at = 1;
+        let (exports, import) = self.inline(items, &mut at)?;
+        let idx = self.space_len(Space::Func);
+        let (ty, params) = self.typeuse(items, &mut at)?;
+        self.export_all(exports, ExportDesc::FuncIdx(idx));
+        if let Some((module, name)) = import {
+            self.end(items, at)?;
+            self.imports.push(Import {
+                module,
+                name,
+                desc: ImportDesc::TypeIdx(ty),
+            });
+            return Ok(());
+        }
+
+        let mut ctx = FuncCtx {
+            locals: HashMap::new(),
+            labels: Vec::new(),
+        };
+        let mut local_names = NameMap::new();
+        let mut count = 0;
+        for (i, name) in params.iter().enumerate() {
+            if let Some(name) = name {
+                ctx.locals.insert(name.clone(), i as u32);
+                local_names.push((i as u32, name[1..].to_string()));
+            }
+            count += 1;
+        }
+        let mut locals: Vec<Locals> = Vec::new();
+        while let Some(local) = items.get(at).and_then(|item| item.list("local")) {
+            let mut types = Vec::new();
+            let mut i = 1;
+            if let Some(id) = local.get(1).and_then(Sexpr::atom).filter(|a| is_id(a)) {
+                if ctx.locals.insert(id.to_string(), count).is_some() {
+                    return Err(self.error(local[1].pos(), format!("duplicate local {id}")));
+                }
+                local_names.push((count, id[1..].to_string()));
+                i += 1;
+                types.push(self.valtype(local, &mut i)?);
+                self.end(local, i)?;
+            } else {
+                while i < local.len() {
+                    types.push(self.valtype(local, &mut i)?);
+                }
+            }
+            for ty in types {
+                count += 1;
+                match locals.last_mut() {
+                    Some(Locals(n, last)) if *last == ty => *n += 1,
+                    _ => locals.push(Locals(1, ty)),
+                }
+            }
+            at += 1;
+        }
+        let mut body = Vec::new();
+        self.instrs(items, &mut at, &mut ctx, &mut body)?;
+        if let Some(item) = items.get(at) {
+            return Err(self.error(item.pos(), "unexpected token"));
+        }
+        if !local_names.is_empty() {
+            self.names.locals.push((idx, local_names));
+        }
+        let func = Func(locals, Expr(body));
+        let mut content = IB::new();
+        func.encode(&mut content);
+        self.funcs.push(ty);
+        self.codes.push(Code(content.len() as u32, func));
+        Ok(())
+    }
+
+    fn table(&mut self, items: &[Sexpr]) -> Result<(), Error> {
+        let mut at = 1;
+        let (exports, import) = self.inline(items, &mut at)?;
+        let idx = self.space_len(Space::Table);
+        self.export_all(exports, ExportDesc::TableIdx(idx));
+        if let Some((module, name)) = import {
+            let ty = self.tabletype(items, &mut at)?;
+            self.end(items, at)?;
+            self.imports.push(Import {
+                module,
+                name,
+                desc: ImportDesc::TableType(ty),
+            });
+            return Ok(());
+        }
+        let is64 = items.get(at).and_then(Sexpr::atom) == Some("i64");
+        let inner = at + usize::from(is64);
+        let inline = items.get(inner + 1).and_then(|item| item.list("elem"));
+        let ty = match items.get(inner) {
+            Some(item) => self.reftype(item)?,
+            None => None,
+        };
+        match (ty, inline) {
+            (Some(ty), Some(elem)) => {
+                let mut i = 1;
+                let funcs = elem.get(1).is_some_and(|item| item.atom().is_some());
+                let offset = Expr(vec![match is64 {
+                    true => Instr::I64Const(0),
+                    false => Instr::I32Const(0),
+                }]);
+                let elem = if funcs {
+                    let mut idxs = Vec::new();
+                    while i < elem.len() {
+                        idxs.push(self.index(elem, &mut i, Space::Func)?);
+                    }
+                    match idx {
+                        0 => Elem::A(offset, idxs),
+                        _ => Elem::C(idx, offset, 0, idxs),
+                    }
+                } else {
+                    let mut exprs = Vec::new();
+                    while i < elem.len() {
+                        exprs.push(self.item(elem, &mut i)?);
+                    }
+                    match idx {
+                        0 if ty == RefType::FUNCREF => Elem::E(offset, exprs),
+                        _ => Elem::G(idx, offset, ty, exprs),
+                    }
+                };
+                let len = match &elem {
+                    Elem::A(_, idxs) | Elem::C(_, _, _, idxs) => idxs.len(),
+                    Elem::E(_, exprs) | Elem::G(_, _, _, exprs) => exprs.len(),
+                    _ => unreachable!(),
+                } as u64;
+                self.elems.push(elem);
+                self.end(items, inner + 2)?;
+                self.tables
+                    .push(TableType(ty, Limits(len, Some(len), false, is64)));
+            }
+            _ => {
+                let ty = self.tabletype(items, &mut at)?;
+                self.end(items, at)?;
+                self.tables.push(ty);
+            }
+        }
+        Ok(())
+    }
+
+    fn memory(&mut self, items: &[Sexpr]) -> Result<(), Error> {
+        let mut at = 1;
+        let (exports, import) = self.inline(items, &mut at)?;
+        let idx = self.space_len(Space::Mem);
+        self.export_all(exports, ExportDesc::MemIdx(idx));
+        if let Some((module, name)) = import {
+            let ty = self.limits(items, &mut at)?;
+            self.end(items, at)?;
+            self.imports.push(Import {
+                module,
+                name,
+                desc: ImportDesc::MemType(ty),
+            });
+            return Ok(());
+        }
+        let is64 = items.get(at).and_then(Sexpr::atom) == Some("i64");
+        let inner = at + usize::from(is64);
+        if let Some(data) = items.get(inner).and_then(|item| item.list("data")) {
+            let bytes = self.strings(data, 1)?;
+            let pages = bytes.len().div_ceil(0x10000) as u64;
+            let offset = Expr(vec![match is64 {
+                true => Instr::I64Const(0),
+                false => Instr::I32Const(0),
+            }]);
+            self.datas.push(match idx {
+                0 => Data::A(offset, bytes),
+                _ => Data::C(idx, offset, bytes),
+            });
+            self.end(items, inner + 1)?;
+            self.mems.push(Limits(pages, Some(pages), false, is64));
+        } else {
+            let limits = self.limits(items, &mut at)?;
+            self.end(items, at)?;
+            self.mems.push(limits);
+        }
+        Ok(())
+    }
+
+    fn global(&mut self, items: &[Sexpr]) -> Result<(), Error> {
+        let mut at = 1;
+        let (exports, import) = self.inline(items, &mut at)?;
+        let idx = self.space_len(Space::Global);
+        self.export_all(exports, ExportDesc::GlobalIdx(idx));
+        let ty = self.globaltype(items, &mut at)?;
+        if let Some((module, name)) = import {
+            self.end(items, at)?;
+            self.imports.push(Import {
+                module,
+                name,
+                desc: ImportDesc::GlobalType(ty),
+            });
+            return Ok(());
+        }
+        let expr = self.expr(items, &mut at)?;
+        self.globals.push((ty, expr));
+        Ok(())
+    }
+
+    fn tag(&mut self, items: &[Sexpr]) -> Result<(), Error> {
+        let mut at = 1;
+        let (exports, import) = self.inline(items, &mut at)?;
+        let idx = self.space_len(Space::Tag);
+        self.export_all(exports, ExportDesc::TagIdx(idx));
+        let ty = TagType(self.typeuse(items, &mut at)?.0);
+        self.end(items, at)?;
+        match import {
+            Some((module, name)) => self.imports.push(Import {
+                module,
+                name,
+                desc: ImportDesc::TagType(ty),
+            }),
+            None => self.tags.push(ty),
+        }
+        Ok(())
+    }
+
+    fn elem(&mut self, items: &[Sexpr], pos: usize) -> Result<(), Error> {
+        let mut at = 1;
+        if items.get(at).and_then(Sexpr::atom).is_some_and(is_id) {
+            at += 1;
+        }
+        let declare = items.get(at).and_then(Sexpr::atom) == Some("declare");
+        if declare {
+            at += 1;
+        }
+        let mut table = None;
+        if let Some(list) = items.get(at).and_then(|item| item.list("table")) {
+            let mut i = 1;
+            table = Some(self.index(list, &mut i, Space::Table)?);
+            self.end(list, i)?;
+            at += 1;
+        } else if !declare
+            && Self::is_index(items, at)
+            && items.get(at + 1).is_some_and(|item| item.head().is_some())
+        {
+            // The abbreviated `(elem x offset ...)`
+            table = Some(self.index(items, &mut at, Space::Table)?);
+        }
+        let mut offset = None;
+        match items.get(at) {
+            Some(item @ Sexpr::List(list, _)) if !declare => {
+                let mut ctx = FuncCtx {
+                    locals: HashMap::new(),
+                    labels: Vec::new(),
+                };
+                let mut body = Vec::new();
+                if item.head() == Some("offset") {
+                    let mut i = 1;
+                    self.instrs(list, &mut i, &mut ctx, &mut body)?;
+                    self.end(list, i)?;
+                } else if item.head() != Some("item") {
+                    let mut i = at;
+                    self.instr(items, &mut i, &mut ctx, &mut body)?;
+                }
+                if item.head() != Some("item") {
+                    offset = Some(Expr(body));
+                    at += 1;
+                }
+            }
+            _ => {}
+        }
+        if table.is_some() && offset.is_none() {
+            return Err(self.error(pos, "expected an offset expression"));
+        }
+
+        // `func idx*`, a bare `idx*` list, or `reftype item*`
+        let kind = items.get(at).and_then(Sexpr::atom);
+        let ty = match items.get(at) {
+            Some(item) => self.reftype(item)?,
+            None => None,
+        };
+        let funcs = if kind == Some("func") {
+            at += 1;
+            true
+        } else {
+            ty.is_none()
+        };
+        if ty.is_some() {
+            at += 1;
+        }
+        let elem = if funcs {
+            let mut idxs = Vec::new();
+            while at < items.len() {
+                idxs.push(self.index(items, &mut at, Space::Func)?);
+            }
+            match (offset, table, declare) {
+                (Some(offset), None | Some(0), _) => Elem::A(offset, idxs),
+                (Some(offset), Some(table), _) => Elem::C(table, offset, 0, idxs),
+                (None, _, false) => Elem::B(0, idxs),
+                (None, _, true) => Elem::D(0, idxs),
+            }
+        } else {
+            let ty = ty.unwrap();
+            let mut exprs = Vec::new();
+            while at < items.len() {
+                exprs.push(self.item(items, &mut at)?);
+            }
+            match (offset, table.unwrap_or(0), declare) {
+                (Some(offset), 0, _) if ty == RefType::FUNCREF => Elem::E(offset, exprs),
+                (Some(offset), table, _) => Elem::G(table, offset, ty, exprs),
+                (None, _, false) => Elem::F(ty, exprs),
+                (None, _, true) => Elem::H(ty, exprs),
+            }
+        };
+        self.elems.push(elem);
+        Ok(())
+    }
+
+    /// An element expression, `(item instr*)` or a single folded instruction.
+    fn item(&mut self, items: &[Sexpr], at: &mut usize) -> Result<Expr, Error> {
+        let mut ctx = FuncCtx {
+            locals: HashMap::new(),
+            labels: Vec::new(),
+        };
+        let mut body = Vec::new();
+        match items.get(*at) {
+            Some(item) if item.head() == Some("item") => {
+                let Sexpr::List(list, _) = item else {
+                    unreachable!()
+                };
+                let mut i = 1;
+                self.instrs(list, &mut i, &mut ctx, &mut body)?;
+                self.end(list, i)?;
+                *at += 1;
+            }
+            Some(Sexpr::List(_, _)) => self.instr(items, at, &mut ctx, &mut body)?,
+            Some(item) => return Err(self.error(item.pos(), "expected an element expression")),
+            None => return Err(self.error(items[0].pos(), "expected an element expression")),
+        }
+        Ok(Expr(body))
+    }
+
+    fn data(&mut self, items: &[Sexpr], pos: usize) -> Result<(), Error> {
+        let mut at = 1;
+        if items.get(at).and_then(Sexpr::atom).is_some_and(is_id) {
+            at += 1;
+        }
+        let mut mem = None;
+        if let Some(list) = items.get(at).and_then(|item| item.list("memory")) {
+            let mut i = 1;
+            mem = Some(self.index(list, &mut i, Space::Mem)?);
+            self.end(list, i)?;
+            at += 1;
+        } else if Self::is_index(items, at)
+            && items.get(at + 1).is_some_and(|item| item.head().is_some())
+        {
+            // The abbreviated `(data x offset ...)`
+            mem = Some(self.index(items, &mut at, Space::Mem)?);
+        }
+        let mut offset = None;
+        if let Some(item @ Sexpr::List(list, _)) = items.get(at) {
+            let mut ctx = FuncCtx {
+                locals: HashMap::new(),
+                labels: Vec::new(),
+            };
+            let mut body = Vec::new();
+            if item.head() == Some("offset") {
+                let mut i = 1;
+                self.instrs(list, &mut i, &mut ctx, &mut body)?;
+                self.end(list, i)?;
+            } else {
+                let mut i = at;
+                self.instr(items, &mut i, &mut ctx, &mut body)?;
+            }
+            offset = Some(Expr(body));
+            at += 1;
+        }
+        if mem.is_some() && offset.is_none() {
+            return Err(self.error(pos, "expected an offset expression"));
+        }
+        let bytes = self.strings(items, at)?;
+        self.datas.push(match (mem, offset) {
+            (None | Some(0), Some(offset)) => Data::A(offset, bytes),
+            (Some(mem), Some(offset)) => Data::C(mem, offset, bytes),
+            (_, None) => Data::B(bytes),
+        });
+        Ok(())
+    }
+
+    fn strings(&self, items: &[Sexpr], at: usize) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        for item in &items[at.min(items.len())..] {
+            match item {
+                Sexpr::Str(str, _) => bytes.extend(str),
+                _ => return Err(self.error(item.pos(), "expected a string")),
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn valtype(&self, items: &[Sexpr], at: &mut usize) -> Result<ValType, Error> {
+        let item = items
+            .get(*at)
+            .ok_or_else(|| self.error(items[0].pos(), "expected a value type"))?;
+        let ty = match item.atom() {
+            Some(text) => valtype(text),
+            None => self.reftype(item)?.map(ValType::RefType),
+        }
+        .ok_or_else(|| self.error(item.pos(), "unknown value type"))?;
+        *at += 1;
+        Ok(ty)
+    }
+
+    /// A reference type abbreviation or `(ref null? heaptype)`, `None` if the
+    /// item is neither.
+    fn reftype(&self, item: &Sexpr) -> Result<Option<RefType>, Error> {
+        let Some(list) = item.list("ref") else {
+            return Ok(item.atom().and_then(reftype));
+        };
+        let mut i = 1;
+        let nullable = list.get(i).and_then(Sexpr::atom) == Some("null");
+        if nullable {
+            i += 1;
+        }
+        let heap = self.heaptype(list, &mut i)?;
+        self.end(list, i)?;
+        Ok(Some(RefType(nullable, heap)))
+    }
+
+    fn heaptype(&self, items: &[Sexpr], at: &mut usize) -> Result<HeapType, Error> {
+        let heap = match items.get(*at).and_then(Sexpr::atom) {
+            Some("func") => HeapType::Func,
+            Some("extern") => HeapType::Extern,
+            Some("exn") => HeapType::Exn,
+            Some("any") => HeapType::Any,
+            Some("eq") => HeapType::Eq,
+            Some("i31") => HeapType::I31,
+            Some("struct") => HeapType::Struct,
+            Some("array") => HeapType::Array,
+            Some("none") => HeapType::None,
+            Some("nofunc") => HeapType::NoFunc,
+            Some("noextern") => HeapType::NoExtern,
+            Some("noexn") => HeapType::NoExn,
+            _ => {
+                return Ok(HeapType::Type(self.index_in(
+                    items,
+                    at,
+                    &self.spaces.types,
+                )?))
+            }
+        };
+        *at += 1;
+        Ok(heap)
+    }
+
+    fn limits(&self, items: &[Sexpr], at: &mut usize) -> Result<Limits, Error> {
+        let is64 = match items.get(*at).and_then(Sexpr::atom) {
+            Some(ty @ ("i32" | "i64")) => {
+                *at += 1;
+                ty == "i64"
+            }
+            _ => false,
+        };
+        let mut number = || {
+            let value = items
+                .get(*at)
+                .and_then(Sexpr::atom)
+                .and_then(|value| match is64 {
+                    true => u64_lit(value),
+                    false => u32_lit(value).map(u64::from),
+                });
+            if value.is_some() {
+                *at += 1;
+            }
+            value
+        };
+        let min = number().ok_or_else(|| self.error(items[0].pos(), "expected limits"))?;
+        let max = number();
+        let shared = items.get(*at).and_then(Sexpr::atom) == Some("shared");
+        if shared {
+            *at += 1;
+        }
+        Ok(Limits(min, max, shared, is64))
+    }
+
+    fn tabletype(&self, items: &[Sexpr], at: &mut usize) -> Result<TableType, Error> {
+        let limits = self.limits(items, at)?;
+        let item = items
+            .get(*at)
+            .ok_or_else(|| self.error(items[0].pos(), "expected a reference type"))?;
+        let ty = self
+            .reftype(item)?
+            .ok_or_else(|| self.error(item.pos(), "unknown reference type"))?;
+        *at += 1;
+        Ok(TableType(ty, limits))
+    }
+
+    fn globaltype(&self, items: &[Sexpr], at: &mut usize) -> Result<GlobalType, Error> {
+        if let Some(list) = items.get(*at).and_then(|item| item.list("mut")) {
+            let mut i = 1;
+            let ty = self.valtype(list, &mut i)?;
+            self.end(list, i)?;
+            *at += 1;
+            return Ok(GlobalType(true, ty));
+        }
+        Ok(GlobalType(false, self.valtype(items, at)?))
+    }
+
+    /// `(param ...)*`, returning the types and the names of single named params.
+    #[allow(clippy::type_complexity)]
+    fn params(
+        &self,
+        items: &[Sexpr],
+        at: &mut usize,
+    ) -> Result<(Vec<ValType>, Vec<Option<String>>), Error> {
+        let mut types = Vec::new();
+        let mut names = Vec::new();
+        while let Some(param) = items.get(*at).and_then(|item| item.list("param")) {
+            let mut i = 1;
+            if let Some(id) = param.get(1).and_then(Sexpr::atom).filter(|a| is_id(a)) {
+                i += 1;
+                types.push(self.valtype(param, &mut i)?);
+                names.push(Some(id.to_string()));
+                self.end(param, i)?;
+            } else {
+                while i < param.len() {
+                    types.push(self.valtype(param, &mut i)?);
+                    names.push(None);
+                }
+            }
+            *at += 1;
+        }
+        Ok((types, names))
+    }
+
+    /// The `(type ...)` definitions of a type or rec field.
+    fn group<'s>(&self, field: &'s Sexpr) -> Result<Vec<(&'s [Sexpr], usize)>, Error> {
+        let types = match field {
+            Sexpr::List(items, _) if field.head() == Some("rec") => &items[1..],
+            field => std::slice::from_ref(field),
+        };
+        types
+            .iter()
+            .map(|ty| match ty.list("type") {
+                Some(items) => Ok((items, ty.pos())),
+                None => Err(self.error(ty.pos(), "expected `(type ...)`")),
+            })
+            .collect()
+    }
+
+    /// Names the fields of a struct type definition.
+    fn field_names(&mut self, items: &[Sexpr], ty: u32) -> Result<(), Error> {
+        let Some(mut comp) = items.last() else {
+            return Ok(());
+        };
+        if let Some(sub) = comp.list("sub") {
+            comp = sub.last().unwrap_or(comp);
+        }
+        let Some(fields) = comp.list("struct") else {
+            return Ok(());
+        };
+        let mut idx = 0;
+        let mut names = Vec::new();
+        for field in &fields[1..] {
+            let Some(field) = field.list("field") else {
+                return Err(self.error(field.pos(), "expected `(field ...)`"));
+            };
+            match field.get(1).and_then(Sexpr::atom).filter(|a| is_id(a)) {
+                Some(id) => {
+                    let space = self.spaces.fields.entry(ty).or_default();
+                    if space.insert(id.to_string(), idx).is_some() {
+                        return Err(
+                            self.error(field[1].pos(), format!("duplicate identifier {id}"))
+                        );
+                    }
+                    names.push((idx, id[1..].to_string()));
+                    idx += 1;
+                }
+                None => idx += field.len() as u32 - 1,
+            }
+        }
+        if !names.is_empty() {
+            self.names.fields.push((ty, names));
+        }
+        Ok(())
+    }
+
+    /// Second pass over type definitions, once every type has its index.
+    fn typedefs(&mut self, fields: &[Sexpr]) -> Result<(), Error> {
+        for field in fields {
+            if !matches!(field.head(), Some("type" | "rec")) {
+                continue;
+            }
+            let mut group = Vec::new();
+            for (items, pos) in self.group(field)? {
+                let mut at = 1;
+                if items.get(at).and_then(Sexpr::atom).is_some_and(is_id) {
+                    at += 1;
+                }
+                let item = items
+                    .get(at)
+                    .ok_or_else(|| self.error(pos, "expected a composite type"))?;
+                let ty = match item.list("sub") {
+                    Some(sub) => {
+                        let mut i = 1;
+                        let is_final = sub.get(i).and_then(Sexpr::atom) == Some("final");
+                        if is_final {
+                            i += 1;
+                        }
+                        let mut supers = Vec::new();
+                        while Self::is_index(sub, i) {
+                            supers.push(self.index_in(sub, &mut i, &self.spaces.types)?);
+                        }
+                        let comp = sub
+                            .get(i)
+                            .ok_or_else(|| self.error(item.pos(), "expected a composite type"))?;
+                        self.end(sub, i + 1)?;
+                        SubType(is_final, supers, self.comptype(comp)?)
+                    }
+                    None => SubType(true, Vec::new(), self.comptype(item)?),
+                };
+                self.end(items, at + 1)?;
+                group.push(ty);
+            }
+            self.types.push(RecType(group));
+        }
+        Ok(())
+    }
+
+    fn comptype(&self, item: &Sexpr) -> Result<CompType, Error> {
+        let mut at = 1;
+        let comp = match (item.head(), item) {
+            (Some("func"), Sexpr::List(items, _)) => {
+                let (params, _) = self.params(items, &mut at)?;
+                let results = self.results(items, &mut at)?;
+                self.end(items, at)?;
+                CompType::Func(FuncType(params, results))
+            }
+            (Some("struct"), Sexpr::List(items, _)) => {
+                let mut fields = Vec::new();
+                for field in &items[1..] {
+                    let field = field.list("field").unwrap();
+                    let mut i = 1;
+                    if field.get(i).and_then(Sexpr::atom).is_some_and(is_id) {
+                        i += 1;
+                        fields.push(self.fieldtype(field, &mut i)?);
+                    }
+                    while i < field.len() {
+                        fields.push(self.fieldtype(field, &mut i)?);
+                    }
+                    self.end(field, i)?;
+                }
+                CompType::Struct(fields)
+            }
+            (Some("array"), Sexpr::List(items, _)) => {
+                let field = self.fieldtype(items, &mut at)?;
+                self.end(items, at)?;
+                CompType::Array(field)
+            }
+            _ => return Err(self.error(item.pos(), "expected a composite type")),
+        };
+        Ok(comp)
+    }
+
+    /// `storagetype` or `(mut storagetype)`.
+    fn fieldtype(&self, items: &[Sexpr], at: &mut usize) -> Result<FieldType, Error> {
+        if let Some(list) = items.get(*at).and_then(|item| item.list("mut")) {
+            let mut i = 1;
+            let ty = self.storagetype(list, &mut i)?;
+            self.end(list, i)?;
+            *at += 1;
+            return Ok(FieldType(ty, true));
+        }
+        Ok(FieldType(self.storagetype(items, at)?, false))
+    }
+
+    fn storagetype(&self, items: &[Sexpr], at: &mut usize) -> Result<StorageType, Error> {
+        let ty = match items.get(*at).and_then(Sexpr::atom) {
+            Some("i8") => StorageType::I8,
+            Some("i16") => StorageType::I16,
+            _ => return Ok(StorageType::ValType(self.valtype(items, at)?)),
+        };
+        *at += 1;
+        Ok(ty)
+    }
+
+    /// Function type with the given index, as far as types are defined so far.
+    fn func_type(&self, idx: TypeIdx) -> Option<&FuncType> {
+        self.types
+            .iter()
+            .flat_map(|group| &group.0)
+            .nth(idx as usize)?
+            .func()
+    }
+
+    fn results(&self, items: &[Sexpr], at: &mut usize) -> Result<Vec<ValType>, Error> {
+        let mut types = Vec::new();
+        while let Some(result) = items.get(*at).and_then(|item| item.list("result")) {
+            let mut i = 1;
+            while i < result.len() {
+                types.push(self.valtype(result, &mut i)?);
+            }
+            *at += 1;
+        }
+        Ok(types)
+    }
+
+    /// Index of an implicit function type, reusing an equal type in its own
+    /// recursion group.
+    fn type_idx(&mut self, ty: FuncType) -> u32 {
+        let ty = RecType(vec![ty.into()]);
+        let mut idx = 0;
+        for group in &self.types {
+            if *group == ty {
+                return idx;
+            }
+            idx += group.0.len() as u32;
+        }
+        self.types.push(ty);
+        idx
+    }
+
+    /// `(type x)? (param ...)* (result ...)*`, adding an implicit type when needed.
+    #[allow(clippy::type_complexity)]
+    fn typeuse(
+        &mut self,
+        items: &[Sexpr],
+        at: &mut usize,
+    ) -> Result<(TypeIdx, Vec<Option<String>>), Error> {
+        let mut explicit = None;
+        if let Some(list) = items.get(*at).and_then(|item| item.list("type")) {
+            let mut i = 1;
+            explicit = Some(self.index_in(list, &mut i, &self.spaces.types)?);
+            self.end(list, i)?;
+            *at += 1;
+        }
+        let start = *at;
+        let (params, mut names) = self.params(items, at)?;
+        let results = self.results(items, at)?;
+        match explicit {
+            Some(idx) => {
+                let ty = self
+                    .func_type(idx)
+                    .ok_or_else(|| self.error(items[start - 1].pos(), "unknown type"))?;
+                if *at != start && *ty != FuncType(params, results) {
+                    return Err(self.error(items[start].pos(), "inline function type mismatch"));
+                }
+                names.resize(ty.0.len(), None);
+                Ok((idx, names))
+            }
+            None => Ok((self.type_idx(FuncType(params, results)), names)),
+        }
+    }
+
+    fn blocktype(&mut self, items: &[Sexpr], at: &mut usize) -> Result<BlockType, Error> {
+        let has_type = items
+            .get(*at)
+            .is_some_and(|item| item.head() == Some("type"));
+        let start = *at;
+        let (params, names) = self.params(items, at)?;
+        let results = self.results(items, at)?;
+        if has_type || !params.is_empty() {
+            *at = start;
+            let (idx, _) = self.typeuse(items, at)?;
+            return Ok(BlockType::X(idx as i64));
+        }
+        if names.iter().any(Option::is_some) {
+            return Err(self.error(items[start].pos(), "block params cannot be named"));
+        }
+        Ok(match results.as_slice() {
+            [] => BlockType::Empty,
+            [ty] => BlockType::ValType(*ty),
+            _ => BlockType::X(self.type_idx(FuncType(params, results)) as i64),
+        })
+    }
+
+    /// A field of struct type `ty`, by name or index.
+    fn field_idx(&self, items: &[Sexpr], at: &mut usize, ty: u32) -> Result<u32, Error> {
+        let empty = HashMap::new();
+        self.index_in(items, at, self.spaces.fields.get(&ty).unwrap_or(&empty))
+    }
+
+    /// A reference type immediate of `ref.test`, `ref.cast` and `br_on_cast`.
+    fn reftype_imm(&self, items: &[Sexpr], at: &mut usize) -> Result<RefType, Error> {
+        let item = items
+            .get(*at)
+            .ok_or_else(|| self.error(items[0].pos(), "expected a reference type"))?;
+        let ty = self
+            .reftype(item)?
+            .ok_or_else(|| self.error(item.pos(), "expected a reference type"))?;
+        *at += 1;
+        Ok(ty)
+    }
+
+    fn index_in(
+        &self,
+        items: &[Sexpr],
+        at: &mut usize,
+        names: &HashMap<String, u32>,
+    ) -> Result<u32, Error> {
+        let item = items
+            .get(*at)
+            .ok_or_else(|| self.error(items[0].pos(), "expected an index"))?;
+        let idx = match item.atom() {
+            Some(id) if is_id(id) => *names
+                .get(id)
+                .ok_or_else(|| self.error(item.pos(), format!("unknown identifier {id}")))?,
+            Some(number) => {
+                u32_lit(number).ok_or_else(|| self.error(item.pos(), "expected an index"))?
+            }
+            None => return Err(self.error(item.pos(), "expected an index")),
+        };
+        *at += 1;
+        Ok(idx)
+    }
+
+    fn index(&self, items: &[Sexpr], at: &mut usize, space: Space) -> Result<u32, Error> {
+        let names = match space {
+            Space::Func => &self.spaces.funcs,
+            Space::Table => &self.spaces.tables,
+            Space::Mem => &self.spaces.mems,
+            Space::Global => &self.spaces.globals,
+            Space::Tag => &self.spaces.tags,
+        };
+        self.index_in(items, at, names)
+    }
+
+    fn is_index(items: &[Sexpr], at: usize) -> bool {
+        items
+            .get(at)
+            .and_then(Sexpr::atom)
+            .is_some_and(|atom| is_id(atom) || u32_lit(atom).is_some())
+    }
+
+    fn label(&self, items: &[Sexpr], at: &mut usize, ctx: &FuncCtx) -> Result<u32, Error> {
+        let item = items
+            .get(*at)
+            .ok_or_else(|| self.error(items[0].pos(), "expected a label"))?;
+        let depth = match item.atom() {
+            Some(id) if is_id(id) => ctx
+                .labels
+                .iter()
+                .rev()
+                .position(|label| label.as_deref() == Some(id))
+                .ok_or_else(|| self.error(item.pos(), format!("unknown label {id}")))?
+                as u32,
+            Some(number) => {
+                u32_lit(number).ok_or_else(|| self.error(item.pos(), "expected a label"))?
+            }
+            None => return Err(self.error(item.pos(), "expected a label")),
+        };
+        *at += 1;
+        Ok(depth)
+    }
+
+    fn local(&self, items: &[Sexpr], at: &mut usize, ctx: &FuncCtx) -> Result<u32, Error> {
+        self.index_in(items, at, &ctx.locals)
+    }
+
+    /// A constant expression such as a global initializer, up to the end of `items`.
+    fn expr(&mut self, items: &[Sexpr], at: &mut usize) -> Result<Expr, Error> {
+        let mut ctx = FuncCtx {
+            locals: HashMap::new(),
+            labels: Vec::new(),
+        };
+        let mut body = Vec::new();
+        self.instrs(items, at, &mut ctx, &mut body)?;
+        self.end(items, *at)?;
+        Ok(Expr(body))
+    }
+
+    /// Instructions until the end of `items` or a keyword ending a block body.
+    fn instrs(
+        &mut self,
+        items: &[Sexpr],
+        at: &mut usize,
+        ctx: &mut FuncCtx,
+        out: &mut Vec<Instr>,
+    ) -> Result<(), Error> {
+        while let Some(item) = items.get(*at) {
+            if matches!(
+                item.atom(),
+                Some("end" | "else" | "catch" | "catch_all" | "delegate")
+            ) {
+                break;
+            }
+            self.instr(items, at, ctx, out)?;
+        }
+        Ok(())
+    }
+
+    /// `try_table` catch clauses, whose labels are resolved outside the block.
+    fn catches(&self, items: &[Sexpr], at: &mut usize, ctx: &FuncCtx) -> Result<Vec<Catch>, Error> {
+        let mut catches = Vec::new();
+        while let Some(Sexpr::List(clause, pos)) = items.get(*at) {
+            let mut i = 1;
+            let catch = match clause.first().and_then(Sexpr::atom) {
+                Some("catch") => Catch::Catch(
+                    self.index(clause, &mut i, Space::Tag)?,
+                    self.label(clause, &mut i, ctx)?,
+                ),
+                Some("catch_ref") => Catch::CatchRef(
+                    self.index(clause, &mut i, Space::Tag)?,
+                    self.label(clause, &mut i, ctx)?,
+                ),
+                Some("catch_all") => Catch::CatchAll(self.label(clause, &mut i, ctx)?),
+                Some("catch_all_ref") => Catch::CatchAllRef(self.label(clause, &mut i, ctx)?),
+                _ => break,
+            };
+            if i != clause.len() {
+                return Err(self.error(*pos, "unexpected token"));
+            }
+            catches.push(catch);
+            *at += 1;
+        }
+        Ok(catches)
+    }
+
+    fn block_label(&self, items: &[Sexpr], at: &mut usize) -> Option<String> {
+        let id = items.get(*at).and_then(Sexpr::atom).filter(|a| is_id(a))?;
+        *at += 1;
+        Some(id.to_string())
+    }
+
+    /// Consumes a block terminator keyword and its optional matching label.
+    fn terminator(
+        &self,
+        items: &[Sexpr],
+        at: &mut usize,
+        keyword: &str,
+        label: &Option<String>,
+    ) -> Result<(), Error> {
+        match items.get(*at) {
+            Some(item) if item.atom() == Some(keyword) => *at += 1,
+            Some(item) => return Err(self.error(item.pos(), format!("expected `{keyword}`"))),
+            None => return Err(self.error(items[0].pos(), format!("expected `{keyword}`"))),
+        }
+        if let Some(id) = items.get(*at).and_then(Sexpr::atom).filter(|a| is_id(a)) {
+            if label.as_deref() != Some(id) {
+                return Err(self.error(items[*at].pos(), "mismatching label"));
+            }
+            *at += 1;
+        }
+        Ok(())
+    }
+
+    fn instr(
+        &mut self,
+        items: &[Sexpr],
+        at: &mut usize,
+        ctx: &mut FuncCtx,
+        out: &mut Vec<Instr>,
+    ) -> Result<(), Error> {
+        let item = &items[*at];
+        let Sexpr::List(list, pos) = item else {
+            return self.flat(items, at, ctx, out);
+        };
+        *at += 1;
+        let pos = *pos;
+        let name = item
+            .head()
+            .ok_or_else(|| self.error(pos, "expected an instruction"))?;
+        if name.starts_with('@') {
+            return Ok(());
+        }
+        let mut i = 1;
+        match name {
+            "block" | "loop" => {
+                let label = self.block_label(list, &mut i);
+                let bt = self.blocktype(list, &mut i)?;
+                ctx.labels.push(label);
+                let mut body = Vec::new();
+                self.instrs(list, &mut i, ctx, &mut body)?;
+                ctx.labels.pop();
+                self.end(list, i)?;
+                out.push(match name {
+                    "block" => Instr::Block(bt, body),
+                    _ => Instr::Loop(bt, body),
+                });
+            }
+            "if" => {
+                let label = self.block_label(list, &mut i);
+                let bt = self.blocktype(list, &mut i)?;
+                while i < list.len() && list[i].head() != Some("then") {
+                    self.instr(list, &mut i, ctx, out)?;
+                }
+                ctx.labels.push(label);
+                let mut then = Vec::new();
+                let mut other = None;
+                if let Some(body) = list.get(i).and_then(|item| item.list("then")) {
+                    let mut j = 1;
+                    self.instrs(body, &mut j, ctx, &mut then)?;
+                    self.end(body, j)?;
+                    i += 1;
+                } else {
+                    return Err(self.error(pos, "expected `(then ...)`"));
+                }
+                if let Some(body) = list.get(i).and_then(|item| item.list("else")) {
+                    let mut j = 1;
+                    let mut instrs = Vec::new();
+                    self.instrs(body, &mut j, ctx, &mut instrs)?;
+                    self.end(body, j)?;
+                    other = Some(instrs);
+                    i += 1;
+                }
+                ctx.labels.pop();
+                self.end(list, i)?;
+                out.push(match other {
+                    Some(other) => Instr::IfElse(bt, then, other),
+                    None => Instr::If(bt, then),
+                });
+            }
+            "try" => {
+                let label = self.block_label(list, &mut i);
+                let bt = self.blocktype(list, &mut i)?;
+                while i < list.len() && list[i].head() != Some("do") {
+                    self.instr(list, &mut i, ctx, out)?;
+                }
+                let Some(body) = list.get(i).and_then(|item| item.list("do")) else {
+                    return Err(self.error(pos, "expected `(do ...)`"));
+                };
+                i += 1;
+                ctx.labels.push(label);
+                let mut j = 1;
+                let mut instrs = Vec::new();
+                self.instrs(body, &mut j, ctx, &mut instrs)?;
+                self.end(body, j)?;
+                if let Some(delegate) = list.get(i).and_then(|item| item.list("delegate")) {
+                    ctx.labels.pop();
+                    let mut j = 1;
+                    let depth = self.label(delegate, &mut j, ctx)?;
+                    self.end(delegate, j)?;
+                    self.end(list, i + 1)?;
+                    out.push(Instr::TryDelegate(bt, instrs, depth));
+                    return Ok(());
+                }
+                let mut catches = Vec::new();
+                while let Some(handler) = list.get(i).and_then(|item| item.list("catch")) {
+                    let mut j = 1;
+                    let tag = self.index(handler, &mut j, Space::Tag)?;
+                    let mut body = Vec::new();
+                    self.instrs(handler, &mut j, ctx, &mut body)?;
+                    self.end(handler, j)?;
+                    catches.push((tag, body));
+                    i += 1;
+                }
+                let mut catch_all = None;
+                if let Some(handler) = list.get(i).and_then(|item| item.list("catch_all")) {
+                    let mut j = 1;
+                    let mut body = Vec::new();
+                    self.instrs(handler, &mut j, ctx, &mut body)?;
+                    self.end(handler, j)?;
+                    catch_all = Some(body);
+                    i += 1;
+                }
+                ctx.labels.pop();
+                self.end(list, i)?;
+                out.push(Instr::Try(bt, instrs, catches, catch_all));
+            }
+            "try_table" => {
+                let label = self.block_label(list, &mut i);
+                let bt = self.blocktype(list, &mut i)?;
+                let catches = self.catches(list, &mut i, ctx)?;
+                ctx.labels.push(label);
+                let mut body = Vec::new();
+                self.instrs(list, &mut i, ctx, &mut body)?;
+                ctx.labels.pop();
+                self.end(list, i)?;
+                out.push(Instr::TryTable(bt, catches, body));
+            }
+            _ => {
+                let instr = self.plain(list, &mut i, ctx)?;
+                while i < list.len() {
+                    self.instr(list, &mut i, ctx, out)?;
+                }
+                out.push(instr);
+            }
+        }
+        Ok(())
+    }
+
+    fn flat(
+        &mut self,
+        items: &[Sexpr],
+        at: &mut usize,
+        ctx: &mut FuncCtx,
+        out: &mut Vec<Instr>,
+    ) -> Result<(), Error> {
+        let item = &items[*at];
+        let name = item
+            .atom()
+            .ok_or_else(|| self.error(item.pos(), "expected an instruction"))?;
+        match name {
+            "block" | "loop" | "if" => {
+                *at += 1;
+                let label = self.block_label(items, at);
+                let bt = self.blocktype(items, at)?;
+                ctx.labels.push(label.clone());
+                let mut body = Vec::new();
+                self.instrs(items, at, ctx, &mut body)?;
+                let mut other = None;
+                if name == "if" && items.get(*at).and_then(Sexpr::atom) == Some("else") {
+                    self.terminator(items, at, "else", &label)?;
+                    let mut instrs = Vec::new();
+                    self.instrs(items, at, ctx, &mut instrs)?;
+                    other = Some(instrs);
+                }
+                ctx.labels.pop();
+                self.terminator(items, at, "end", &label)?;
+                out.push(match (name, other) {
+                    ("block", _) => Instr::Block(bt, body),
+                    ("loop", _) => Instr::Loop(bt, body),
+                    (_, Some(other)) => Instr::IfElse(bt, body, other),
+                    (_, None) => Instr::If(bt, body),
+                });
+            }
+            "try" => {
+                *at += 1;
+                let label = self.block_label(items, at);
+                let bt = self.blocktype(items, at)?;
+                ctx.labels.push(label.clone());
+                let mut body = Vec::new();
+                self.instrs(items, at, ctx, &mut body)?;
+                if items.get(*at).and_then(Sexpr::atom) == Some("delegate") {
+                    *at += 1;
+                    ctx.labels.pop();
+                    let depth = self.label(items, at, ctx)?;
+                    out.push(Instr::TryDelegate(bt, body, depth));
+                    return Ok(());
+                }
+                let mut catches = Vec::new();
+                while items.get(*at).and_then(Sexpr::atom) == Some("catch") {
+                    *at += 1;
+                    let tag = self.index(items, at, Space::Tag)?;
+                    let mut handler = Vec::new();
+                    self.instrs(items, at, ctx, &mut handler)?;
+                    catches.push((tag, handler));
+                }
+                let mut catch_all = None;
+                if items.get(*at).and_then(Sexpr::atom) == Some("catch_all") {
+                    self.terminator(items, at, "catch_all", &label)?;
+                    let mut handler = Vec::new();
+                    self.instrs(items, at, ctx, &mut handler)?;
+                    catch_all = Some(handler);
+                }
+                ctx.labels.pop();
+                self.terminator(items, at, "end", &label)?;
+                out.push(Instr::Try(bt, body, catches, catch_all));
+            }
+            "try_table" => {
+                *at += 1;
+                let label = self.block_label(items, at);
+                let bt = self.blocktype(items, at)?;
+                let catches = self.catches(items, at, ctx)?;
+                ctx.labels.push(label.clone());
+                let mut body = Vec::new();
+                self.instrs(items, at, ctx, &mut body)?;
+                ctx.labels.pop();
+                self.terminator(items, at, "end", &label)?;
+                out.push(Instr::TryTable(bt, catches, body));
+            }
+            _ => {
+                *at += 1;
+                let instr = self.plain(items, at, ctx)?;
+                out.push(instr);
+            }
+        }
+        Ok(())
+    }
+
+    fn memarg(&self, items: &[Sexpr], at: &mut usize, instr: &Instr) -> Result<MemArg, Error> {
+        // The lane index of a lane instruction looks like a memory index, so
+        // the first index is only the memory when another one follows.
+        let mut next = *at + 1;
+        while items
+            .get(next)
+            .and_then(Sexpr::atom)
+            .is_some_and(|atom| atom.starts_with("offset=") || atom.starts_with("align="))
+        {
+            next += 1;
+        }
+        let mem = match Self::is_index(items, *at)
+            && (!instr.name().ends_with("_lane") || Self::is_index(items, next))
+        {
+            true => self.index(items, at, Space::Mem)?,
+            false => 0,
+        };
+        let mut offset = 0;
+        let mut align = natural_align(instr);
+        if let Some(value) = items
+            .get(*at)
+            .and_then(Sexpr::atom)
+            .and_then(|a| a.strip_prefix("offset="))
+        {
+            offset =
+                u64_lit(value).ok_or_else(|| self.error(items[*at].pos(), "invalid offset"))?;
+            *at += 1;
+        }
+        if let Some(value) = items
+            .get(*at)
+            .and_then(Sexpr::atom)
+            .and_then(|a| a.strip_prefix("align="))
+        {
+            let value = u32_lit(value)
+                .filter(|value| value.is_power_of_two())
+                .ok_or_else(|| self.error(items[*at].pos(), "alignment must be a power of two"))?;
+            align = value.trailing_zeros();
+            *at += 1;
+        }
+        Ok(MemArg(align, offset, mem))
+    }
+
+    fn lane(&self, items: &[Sexpr], at: &mut usize) -> Result<u8, Error> {
+        let item = items
+            .get(*at)
+            .ok_or_else(|| self.error(items[0].pos(), "expected a lane index"))?;
+        let lane = item
+            .atom()
+            .and_then(u32_lit)
+            .and_then(|lane| u8::try_from(lane).ok())
+            .ok_or_else(|| self.error(item.pos(), "malformed lane index"))?;
+        *at += 1;
+        Ok(lane)
+    }
+
+    fn literal(&self, items: &[Sexpr], at: &mut usize) -> Result<(&'a str, usize), Error> {
+        let item = items
+            .get(*at)
+            .ok_or_else(|| self.error(items[0].pos(), "expected a constant"))?;
+        let Some(atom) = item.atom() else {
+            return Err(self.error(item.pos(), "expected a constant"));
+        };
+        *at += 1;
+        let pos = item.pos();
+        Ok((&self.src[pos..pos + atom.len()], pos))
+    }
+
+    fn v128(&self, items: &[Sexpr], at: &mut usize) -> Result<[u8; 16], Error> {
+        let (shape, pos) = self.literal(items, at)?;
+        let (lanes, bits) = match shape {
+            "i8x16" => (16, 8),
+            "i16x8" => (8, 16),
+            "i32x4" | "f32x4" => (4, 32),
+            "i64x2" | "f64x2" => (2, 64),
+            _ => return Err(self.error(pos, "unknown vector shape")),
+        };
+        let mut bytes = Vec::new();
+        for _ in 0..lanes {
+            let (text, pos) = self.literal(items, at)?;
+            let bits_value = match shape {
+                "f32x4" => f32_lit(text).map(|v| v.to_bits() as u64),
+                "f64x2" => f64_lit(text).map(f64::to_bits),
+                _ => int_lit(text, bits),
+            }
+            .ok_or_else(|| self.error(pos, "constant out of range"))?;
+            bytes.extend(&bits_value.to_le_bytes()[..bits as usize / 8]);
+        }
+        Ok(bytes.try_into().unwrap())
+    }
+
+    /// A non-block instruction, `at` points after the mnemonic and is advanced past its immediates.
+    fn plain(&mut self, items: &[Sexpr], at: &mut usize, ctx: &FuncCtx) -> Result<Instr, Error> {
+        let name_item = &items[*at - 1];
+        let name = name_item.atom().unwrap_or_default();
+        let pos = name_item.pos();
+        let instr = match name {
+            "br" => Instr::Br(self.label(items, at, ctx)?),
+            "br_if" => Instr::BrIf(self.label(items, at, ctx)?),
+            "br_on_null" => Instr::BrOnNull(self.label(items, at, ctx)?),
+            "br_on_non_null" => Instr::BrOnNonNull(self.label(items, at, ctx)?),
+            "br_table" => {
+                let mut labels = Vec::new();
+                while Self::is_index(items, *at) {
+                    labels.push(self.label(items, at, ctx)?);
+                }
+                let default = labels
+                    .pop()
+                    .ok_or_else(|| self.error(pos, "expected a label"))?;
+                Instr::BrTable(labels, default)
+            }
+            "throw" => Instr::Throw(self.index(items, at, Space::Tag)?),
+            "rethrow" => Instr::Rethrow(self.label(items, at, ctx)?),
+            "call" => Instr::Call(self.index(items, at, Space::Func)?),
+            "return_call" => Instr::ReturnCall(self.index(items, at, Space::Func)?),
+            "call_ref" => Instr::CallRef(self.index_in(items, at, &self.spaces.types)?),
+            "return_call_ref" => {
+                Instr::ReturnCallRef(self.index_in(items, at, &self.spaces.types)?)
+            }
+            "call_indirect" | "return_call_indirect" => {
+                let table = match Self::is_index(items, *at) {
+                    true => self.index(items, at, Space::Table)?,
+                    false => 0,
+                };
+                let (ty, names) = self.typeuse(items, at)?;
+                if names.iter().any(Option::is_some) {
+                    return Err(self.error(pos, format!("{name} params cannot be named")));
+                }
+                match name {
+                    "call_indirect" => Instr::CallIndirect(ty, table),
+                    _ => Instr::ReturnCallIndirect(ty, table),
+                }
+            }
+            "ref.null" => Instr::RefNull(self.heaptype(items, at)?),
+            "ref.func" => Instr::RefFunc(self.index(items, at, Space::Func)?),
+            "ref.test" => Instr::RefTest(self.reftype_imm(items, at)?),
+            "ref.cast" => Instr::RefCast(self.reftype_imm(items, at)?),
+            "br_on_cast" | "br_on_cast_fail" => {
+                let label = self.label(items, at, ctx)?;
+                let from = self.reftype_imm(items, at)?;
+                let to = self.reftype_imm(items, at)?;
+                match name {
+                    "br_on_cast" => Instr::BrOnCast(label, from, to),
+                    _ => Instr::BrOnCastFail(label, from, to),
+                }
+            }
+            "struct.new" | "struct.new_default" | "array.new" | "array.new_default"
+            | "array.get" | "array.get_s" | "array.get_u" | "array.set" | "array.fill" => {
+                let ty = self.index_in(items, at, &self.spaces.types)?;
+                match name {
+                    "struct.new" => Instr::StructNew(ty),
+                    "struct.new_default" => Instr::StructNewDefault(ty),
+                    "array.new" => Instr::ArrayNew(ty),
+                    "array.new_default" => Instr::ArrayNewDefault(ty),
+                    "array.get" => Instr::ArrayGet(ty),
+                    "array.get_s" => Instr::ArrayGetS(ty),
+                    "array.get_u" => Instr::ArrayGetU(ty),
+                    "array.set" => Instr::ArraySet(ty),
+                    _ => Instr::ArrayFill(ty),
+                }
+            }
+            "struct.get" | "struct.get_s" | "struct.get_u" | "struct.set" => {
+                let ty = self.index_in(items, at, &self.spaces.types)?;
+                let field = self.field_idx(items, at, ty)?;
+                match name {
+                    "struct.get" => Instr::StructGet(ty, field),
+                    "struct.get_s" => Instr::StructGetS(ty, field),
+                    "struct.get_u" => Instr::StructGetU(ty, field),
+                    _ => Instr::StructSet(ty, field),
+                }
+            }
+            "array.new_fixed" => {
+                let ty = self.index_in(items, at, &self.spaces.types)?;
+                let (text, pos) = self.literal(items, at)?;
+                let n = u32_lit(text).ok_or_else(|| self.error(pos, "expected an integer"))?;
+                Instr::ArrayNewFixed(ty, n)
+            }
+            "array.new_data" | "array.init_data" => {
+                self.data_count = true;
+                let ty = self.index_in(items, at, &self.spaces.types)?;
+                let data = self.index_in(items, at, &self.spaces.datas)?;
+                match name {
+                    "array.new_data" => Instr::ArrayNewData(ty, data),
+                    _ => Instr::ArrayInitData(ty, data),
+                }
+            }
+            "array.new_elem" | "array.init_elem" => {
+                let ty = self.index_in(items, at, &self.spaces.types)?;
+                let elem = self.index_in(items, at, &self.spaces.elems)?;
+                match name {
+                    "array.new_elem" => Instr::ArrayNewElem(ty, elem),
+                    _ => Instr::ArrayInitElem(ty, elem),
+                }
+            }
+            "array.copy" => Instr::ArrayCopy(
+                self.index_in(items, at, &self.spaces.types)?,
+                self.index_in(items, at, &self.spaces.types)?,
+            ),
+            "select" => match items.get(*at).and_then(Sexpr::head) {
+                Some("result") => Instr::SelectType(self.results(items, at)?),
+                _ => Instr::Select,
+            },
+            "local.get" => Instr::LocalGet(self.local(items, at, ctx)?),
+            "local.set" => Instr::LocalSet(self.local(items, at, ctx)?),
+            "local.tee" => Instr::LocalTee(self.local(items, at, ctx)?),
+            "global.get" => Instr::GlobalGet(self.index(items, at, Space::Global)?),
+            "global.set" => Instr::GlobalSet(self.index(items, at, Space::Global)?),
+            "table.get" | "table.set" | "table.grow" | "table.size" | "table.fill" => {
+                let idx = match Self::is_index(items, *at) {
+                    true => self.index(items, at, Space::Table)?,
+                    false => 0,
+                };
+                match name {
+                    "table.get" => Instr::TableGet(idx),
+                    "table.set" => Instr::TableSet(idx),
+                    "table.grow" => Instr::TableGrow(idx),
+                    "table.size" => Instr::TableSize(idx),
+                    _ => Instr::TableFill(idx),
+                }
+            }
+            "table.init" => {
+                let table = match Self::is_index(items, *at + 1) {
+                    true => self.index(items, at, Space::Table)?,
+                    false => 0,
+                };
+                Instr::TableInit(self.index_in(items, at, &self.spaces.elems)?, table)
+            }
+            "table.copy" => match Self::is_index(items, *at) {
+                true => Instr::TableCopy(
+                    self.index(items, at, Space::Table)?,
+                    self.index(items, at, Space::Table)?,
+                ),
+                false => Instr::TableCopy(0, 0),
+            },
+            "elem.drop" => Instr::ElemDrop(self.index_in(items, at, &self.spaces.elems)?),
+            "memory.init" => {
+                self.data_count = true;
+                let mem = match Self::is_index(items, *at + 1) {
+                    true => self.index(items, at, Space::Mem)?,
+                    false => 0,
+                };
+                Instr::MemoryInit(self.index_in(items, at, &self.spaces.datas)?, mem)
+            }
+            "memory.size" | "memory.grow" | "memory.fill" => {
+                let idx = match Self::is_index(items, *at) {
+                    true => self.index(items, at, Space::Mem)?,
+                    false => 0,
+                };
+                match name {
+                    "memory.size" => Instr::MemorySize(idx),
+                    "memory.grow" => Instr::MemoryGrow(idx),
+                    _ => Instr::MemoryFill(idx),
+                }
+            }
+            "memory.copy" => match Self::is_index(items, *at) {
+                true => Instr::MemoryCopy(
+                    self.index(items, at, Space::Mem)?,
+                    self.index(items, at, Space::Mem)?,
+                ),
+                false => Instr::MemoryCopy(0, 0),
+            },
+            "data.drop" => {
+                self.data_count = true;
+                Instr::DataDrop(self.index_in(items, at, &self.spaces.datas)?)
+            }
+            "i32.const" => {
+                let (text, pos) = self.literal(items, at)?;
+                Instr::I32Const(
+                    int_lit(text, 32).ok_or_else(|| self.error(pos, "constant out of range"))?
+                        as u32 as i32,
+                )
+            }
+            "i64.const" => {
+                let (text, pos) = self.literal(items, at)?;
+                Instr::I64Const(
+                    int_lit(text, 64).ok_or_else(|| self.error(pos, "constant out of range"))?
+                        as i64,
+                )
+            }
+            "f32.const" => {
+                let (text, pos) = self.literal(items, at)?;
+                Instr::F32Const(
+                    f32_lit(text).ok_or_else(|| self.error(pos, "constant out of range"))?,
+                )
+            }
+            "f64.const" => {
+                let (text, pos) = self.literal(items, at)?;
+                Instr::F64Const(
+                    f64_lit(text).ok_or_else(|| self.error(pos, "constant out of range"))?,
+                )
+            }
+            "v128.const" => Instr::V128_Const(self.v128(items, at)?),
+            "i8x16.shuffle" => {
+                let mut lanes = [0; 16];
+                for lane in &mut lanes {
+                    *lane = self.lane(items, at)?;
+                }
+                Instr::I8X16_Shuffle(lanes)
+            }
+            _ => {
+                let mut instr = match name {
+                    "unreachable" => Instr::UnReachable,
+                    "nop" => Instr::Nop,
+                    "return" => Instr::Return,
+                    "throw_ref" => Instr::ThrowRef,
+                    "ref.is_null" => Instr::RefIsNull,
+                    "ref.as_non_null" => Instr::RefAsNonNull,
+                    "ref.eq" => Instr::RefEq,
+                    "ref.i31" => Instr::RefI31,
+                    "i31.get_s" => Instr::I31GetS,
+                    "i31.get_u" => Instr::I31GetU,
+                    "array.len" => Instr::ArrayLen,
+                    "any.convert_extern" => Instr::AnyConvertExtern,
+                    "extern.convert_any" => Instr::ExternConvertAny,
+                    "drop" => Instr::Drop,
+                    "select" => Instr::Select,
+                    "i32.load" => Instr::I32Load(MemArg(0, 0, 0)),
+                    "i64.load" => Instr::I64Load(MemArg(0, 0, 0)),
+                    "f32.load" => Instr::F32Load(MemArg(0, 0, 0)),
+                    "f64.load" => Instr::F64Load(MemArg(0, 0, 0)),
+                    "i32.load8_s" => Instr::I32load8S(MemArg(0, 0, 0)),
+                    "i32.load8_u" => Instr::I32Load8_u(MemArg(0, 0, 0)),
+                    "i32.load16_s" => Instr::I32Load16_s(MemArg(0, 0, 0)),
+                    "i32.load16_u" => Instr::I32Load16_u(MemArg(0, 0, 0)),
+                    "i64.load8_s" => Instr::I64Load8_s(MemArg(0, 0, 0)),
+                    "i64.load8_u" => Instr::I64Load8_u(MemArg(0, 0, 0)),
+                    "i64.load16_s" => Instr::I64Load16_s(MemArg(0, 0, 0)),
+                    "i64.load16_u" => Instr::I64Load16_u(MemArg(0, 0, 0)),
+                    "i64.load32_s" => Instr::I64Load32_s(MemArg(0, 0, 0)),
+                    "i64.load32_u" => Instr::I64Load32_u(MemArg(0, 0, 0)),
+                    "i32.store" => Instr::I32Store(MemArg(0, 0, 0)),
+                    "i64.store" => Instr::I64Store(MemArg(0, 0, 0)),
+                    "f32.store" => Instr::F32Store(MemArg(0, 0, 0)),
+                    "f64.store" => Instr::F64Store(MemArg(0, 0, 0)),
+                    "i32.store8" => Instr::I32Store8(MemArg(0, 0, 0)),
+                    "i32.store16" => Instr::I32Store16(MemArg(0, 0, 0)),
+                    "i64.store8" => Instr::I64Store8(MemArg(0, 0, 0)),
+                    "i64.store16" => Instr::I64Store16(MemArg(0, 0, 0)),
+                    "i64.store32" => Instr::I64Store32(MemArg(0, 0, 0)),
+                    "i32.eqz" => Instr::I32Eqz,
+                    "i32.eq" => Instr::I32Eq,
+                    "i32.ne" => Instr::I32Ne,
+                    "i32.lt_s" => Instr::I32Lts,
+                    "i32.lt_u" => Instr::I32Ltu,
+                    "i32.gt_s" => Instr::I32Gts,
+                    "i32.gt_u" => Instr::I32Gtu,
+                    "i32.le_s" => Instr::I32Les,
+                    "i32.le_u" => Instr::I32Leu,
+                    "i32.ge_s" => Instr::I32Ges,
+                    "i32.ge_u" => Instr::I32Geu,
+                    "i64.eqz" => Instr::I64Eqz,
+                    "i64.eq" => Instr::I64Eq,
+                    "i64.ne" => Instr::I64Ne,
+                    "i64.lt_s" => Instr::I64Lts,
+                    "i64.lt_u" => Instr::I64Ltu,
+                    "i64.gt_s" => Instr::I64Gts,
+                    "i64.gt_u" => Instr::I64Gtu,
+                    "i64.le_s" => Instr::I64Les,
+                    "i64.le_u" => Instr::I64Leu,
+                    "i64.ge_s" => Instr::I64Ges,
+                    "i64.ge_u" => Instr::I64Geu,
+                    "f32.eq" => Instr::F32Eq,
+                    "f32.ne" => Instr::F32Ne,
+                    "f32.lt" => Instr::F32Lt,
+                    "f32.gt" => Instr::F32Gt,
+                    "f32.le" => Instr::F32Le,
+                    "f32.ge" => Instr::F32Ge,
+                    "f64.eq" => Instr::F64Eq,
+                    "f64.ne" => Instr::F64Ne,
+                    "f64.lt" => Instr::F64Lt,
+                    "f64.gt" => Instr::F64Gt,
+                    "f64.le" => Instr::F64Le,
+                    "f64.ge" => Instr::F64Ge,
+                    "i32.clz" => Instr::I32Clz,
+                    "i32.ctz" => Instr::I32Ctz,
+                    "i32.popcnt" => Instr::I32PopcCnt,
+                    "i32.add" => Instr::I32Add,
+                    "i32.sub" => Instr::I32Sub,
+                    "i32.mul" => Instr::I32Mul,
+                    "i32.div_s" => Instr::I32Divs,
+                    "i32.div_u" => Instr::I32Divu,
+                    "i32.rem_s" => Instr::I32RemS,
+                    "i32.rem_u" => Instr::I32Remu,
+                    "i32.and" => Instr::I32And,
+                    "i32.or" => Instr::I32Or,
+                    "i32.xor" => Instr::I32Xor,
+                    "i32.shl" => Instr::I32Shl,
+                    "i32.shr_s" => Instr::I32Shrs,
+                    "i32.shr_u" => Instr::I32Sgru,
+                    "i32.rotl" => Instr::I32Rotl,
+                    "i32.rotr" => Instr::I32Rotr,
+                    "i64.clz" => Instr::I64Clz,
+                    "i64.ctz" => Instr::I64Ctz,
+                    "i64.popcnt" => Instr::I64PopcCnt,
+                    "i64.add" => Instr::I64Add,
+                    "i64.sub" => Instr::I64Sub,
+                    "i64.mul" => Instr::I64Mul,
+                    "i64.div_s" => Instr::I64Divs,
+                    "i64.div_u" => Instr::I64Divu,
+                    "i64.rem_s" => Instr::I64RemS,
+                    "i64.rem_u" => Instr::I64Remu,
+                    "i64.and" => Instr::I64And,
+                    "i64.or" => Instr::I64Or,
+                    "i64.xor" => Instr::I64Xor,
+                    "i64.shl" => Instr::I64Shl,
+                    "i64.shr_s" => Instr::I64Shrs,
+                    "i64.shr_u" => Instr::I64Sgru,
+                    "i64.rotl" => Instr::I64Rotl,
+                    "i64.rotr" => Instr::I64Rotr,
+                    "f32.abs" => Instr::F32Abs,
+                    "f32.neg" => Instr::F32Neg,
+                    "f32.ceil" => Instr::F32Ceil,
+                    "f32.floor" => Instr::F32Floor,
+                    "f32.trunc" => Instr::F32Trunc,
+                    "f32.nearest" => Instr::F32Nearest,
+                    "f32.sqrt" => Instr::F32Sqrt,
+                    "f32.add" => Instr::F32Add,
+                    "f32.sub" => Instr::F32Sub,
+                    "f32.mul" => Instr::F32Mul,
+                    "f32.div" => Instr::F32Div,
+                    "f32.min" => Instr::F32Min,
+                    "f32.max" => Instr::F32Max,
+                    "f32.copysign" => Instr::F32CopySig,
+                    "f64.abs" => Instr::F64Abs,
+                    "f64.neg" => Instr::F64Neg,
+                    "f64.ceil" => Instr::F64Ceil,
+                    "f64.floor" => Instr::F64Floor,
+                    "f64.trunc" => Instr::F64Trunc,
+                    "f64.nearest" => Instr::F64Nearest,
+                    "f64.sqrt" => Instr::F64Sqrt,
+                    "f64.add" => Instr::F64Add,
+                    "f64.sub" => Instr::F64Sub,
+                    "f64.mul" => Instr::F64Mul,
+                    "f64.div" => Instr::F64Div,
+                    "f64.min" => Instr::F64Min,
+                    "f64.max" => Instr::F64Max,
+                    "f64.copysign" => Instr::F64CopySig,
+                    "i32.wrap_i64" => Instr::I32WrapI64,
+                    "i32.trunc_f32_s" => Instr::I32TruncF32S,
+                    "i32.trunc_f32_u" => Instr::I32TruncF32U,
+                    "i32.trunc_f64_s" => Instr::I32TruncF64S,
+                    "i32.trunc_f64_u" => Instr::I32TruncF64U,
+                    "i64.extend_i32_s" => Instr::I64ExtendI32S,
+                    "i64.extend_i32_u" => Instr::I64ExtendI32U,
+                    "i64.trunc_f32_s" => Instr::I64TruncF32S,
+                    "i64.trunc_f32_u" => Instr::I64TruncF32U,
+                    "i64.trunc_f64_s" => Instr::I64TruncF64S,
+                    "i64.trunc_f64_u" => Instr::I64TruncF64U,
+                    "f32.convert_i32_s" => Instr::F32ConvertI32S,
+                    "f32.convert_i32_u" => Instr::F32ConvertI32U,
+                    "f32.convert_i64_s" => Instr::F32ConvertI64S,
+                    "f32.convert_i64_u" => Instr::F32ConvertI64U,
+                    "f32.demote_f64" => Instr::F32DenoteF64,
+                    "f64.convert_i32_s" => Instr::F64ConvertI32S,
+                    "f64.convert_i32_u" => Instr::F64ConvertI32U,
+                    "f64.convert_i64_s" => Instr::F64ConvertI64S,
+                    "f64.convert_i64_u" => Instr::F64ConvertI64U,
+                    "f64.promote_f32" => Instr::F64PromoteF32,
+                    "i32.reinterpret_f32" => Instr::I32ReinterpetF32,
+                    "i64.reinterpret_f64" => Instr::I64ReinterpetF64,
+                    "f32.reinterpret_i32" => Instr::F32ReinterpetI32,
+                    "f64.reinterpret_i64" => Instr::F64RetineroetI64,
+                    "i32.extend8_s" => Instr::I32Extend8S,
+                    "i32.extend16_s" => Instr::I32Extend16S,
+                    "i64.extend8_s" => Instr::I64Extend8S,
+                    "i64.extend16_s" => Instr::I64Extend16S,
+                    "i64.extend32_s" => Instr::I64Extend32S,
+                    "i32.trunc_sat_f32_s" => Instr::I32TruncSatF32S,
+                    "i32.trunc_sat_f32_u" => Instr::I32TruncSatF32U,
+                    "i32.trunc_sat_f64_s" => Instr::I32TruncSatF64S,
+                    "i32.trunc_sat_f64_u" => Instr::I32TruncSatF64U,
+                    "i64.trunc_sat_f32_s" => Instr::I64TruncSatF32S,
+                    "i64.trunc_sat_f32_u" => Instr::I64TruncSatF32U,
+                    "i64.trunc_sat_f64_s" => Instr::I64TructSatF64S,
+                    "i64.trunc_sat_f64_u" => Instr::I64TructSatF64U,
+                    "v128.load" => Instr::V128_Load(MemArg(0, 0, 0)),
+                    "v128.load8x8_s" => Instr::V128_Load_8x8_S(MemArg(0, 0, 0)),
+                    "v128.load8x8_u" => Instr::V128_Load_8x8_U(MemArg(0, 0, 0)),
+                    "v128.load16x4_s" => Instr::V128_Load_16x4_S(MemArg(0, 0, 0)),
+                    "v128.load16x4_u" => Instr::V128_Load_16x4_U(MemArg(0, 0, 0)),
+                    "v128.load32x2_s" => Instr::V128_Load_32x2_S(MemArg(0, 0, 0)),
+                    "v128.load32x2_u" => Instr::V128_Load_32x2_U(MemArg(0, 0, 0)),
+                    "v128.load8_splat" => Instr::V128_Load_8_Splat(MemArg(0, 0, 0)),
+                    "v128.load16_splat" => Instr::V128_Load_16_Splat(MemArg(0, 0, 0)),
+                    "v128.load32_splat" => Instr::V128_Load_32_Splat(MemArg(0, 0, 0)),
+                    "v128.load64_splat" => Instr::V128_Load_64_Splat(MemArg(0, 0, 0)),
+                    "v128.store" => Instr::V128_Store(MemArg(0, 0, 0)),
+                    "v128.load32_zero" => Instr::V128_Load_32_Zero(MemArg(0, 0, 0)),
+                    "v128.load64_zero" => Instr::V128_Load_64_Zero(MemArg(0, 0, 0)),
+                    "v128.load8_lane" => Instr::V128_Load_8_Lane(MemArg(0, 0, 0), 0),
+                    "v128.load16_lane" => Instr::V128_Load_16_Lane(MemArg(0, 0, 0), 0),
+                    "v128.load32_lane" => Instr::V128_Load_32_Lane(MemArg(0, 0, 0), 0),
+                    "v128.load64_lane" => Instr::V128_Load_64_Lane(MemArg(0, 0, 0), 0),
+                    "v128.store8_lane" => Instr::V128_Store_8_Lane(MemArg(0, 0, 0), 0),
+                    "v128.store16_lane" => Instr::V128_Store_16_Lane(MemArg(0, 0, 0), 0),
+                    "v128.store32_lane" => Instr::V128_Store_32_Lane(MemArg(0, 0, 0), 0),
+                    "v128.store64_lane" => Instr::V128_Store_64_Lane(MemArg(0, 0, 0), 0),
+                    "i8x16.extract_lane_s" => Instr::I8X16_Extract_Lane_S(0),
+                    "i8x16.extract_lane_u" => Instr::I8X16_Extract_Lane_U(0),
+                    "i8x16.replace_lane" => Instr::I8X16_Replace_Lane(0),
+                    "i16x8.extract_lane_s" => Instr::I16X8_Extract_Lane_S(0),
+                    "i16x8.extract_lane_u" => Instr::I16X8_Extract_Lane_U(0),
+                    "i16x8.replace_lane" => Instr::I16X8_Replace_Lane(0),
+                    "i32x4.extract_lane" => Instr::I32X4_Extract_Lane(0),
+                    "i32x4.replace_lane" => Instr::I32X4_Replace_Lane(0),
+                    "i64x2.extract_lane" => Instr::I64X2_Extract_Lane(0),
+                    "i64x2.replace_lane" => Instr::I64X2_Replace_Lane(0),
+                    "f32x4.extract_lane" => Instr::F32X4_Extract_Lane(0),
+                    "f32x4.replace_lane" => Instr::F32X4_Replace_Lane(0),
+                    "f64x2.extract_lane" => Instr::F64X2_Extract_Lane(0),
+                    "f64x2.replace_lane" => Instr::F64X2_Replace_Lane(0),
+                    "i8x16.swizzle" => Instr::I8x16_Swizzle,
+                    "i8x16.splat" => Instr::I8X16_Splat,
+                    "i16x8.splat" => Instr::I16X8_Splat,
+                    "i32x4.splat" => Instr::I32X4_Splat,
+                    "i64x2.splat" => Instr::I64X2_Splat,
+                    "f32x4.splat" => Instr::F32X4_Splat,
+                    "f64x2.splat" => Instr::F64X2_Splat,
+                    "i8x16.eq" => Instr::I8X16_Eq,
+                    "i8x16.ne" => Instr::I8X16_Ne,
+                    "i8x16.lt_s" => Instr::I8X16_Lt_S,
+                    "i8x16.lt_u" => Instr::I8X16_Lt_U,
+                    "i8x16.gt_s" => Instr::I8X16_Gt_S,
+                    "i8x16.gt_u" => Instr::I8X16_Gt_U,
+                    "i8x16.le_s" => Instr::I8X16_Le_S,
+                    "i8x16.le_u" => Instr::I8X16_Le_U,
+                    "i8x16.ge_s" => Instr::I8X16_Ge_S,
+                    "i8x16.ge_u" => Instr::I8X16_Ge_U,
+                    "i16x8.eq" => Instr::I16X8_Eq,
+                    "i16x8.ne" => Instr::I16X8_Ne,
+                    "i16x8.lt_s" => Instr::I16X8_Lt_S,
+                    "i16x8.lt_u" => Instr::I16X8_Lt_U,
+                    "i16x8.gt_s" => Instr::I16X8_Gt_S,
+                    "i16x8.gt_u" => Instr::I16X8_Gt_U,
+                    "i16x8.le_s" => Instr::I16X8_Le_S,
+                    "i16x8.le_u" => Instr::I16X8_Le_U,
+                    "i16x8.ge_s" => Instr::I16X8_Ge_S,
+                    "i16x8.ge_u" => Instr::I16X8_Ge_U,
+                    "i32x4.eq" => Instr::I32X4_Eq,
+                    "i32x4.ne" => Instr::I32X4_Ne,
+                    "i32x4.lt_s" => Instr::I32X4_Lt_S,
+                    "i32x4.lt_u" => Instr::I32X4_Lt_U,
+                    "i32x4.gt_s" => Instr::I32X4_Gt_S,
+                    "i32x4.gt_u" => Instr::I32X4_Gt_U,
+                    "i32x4.le_s" => Instr::I32X4_Le_S,
+                    "i32x4.le_u" => Instr::I32X4_Le_U,
+                    "i32x4.ge_s" => Instr::I32X4_Ge_S,
+                    "i32x4.ge_u" => Instr::I32X4_Ge_U,
+                    "f32x4.eq" => Instr::F32X4_Eq,
+                    "f32x4.ne" => Instr::F32X4_Ne,
+                    "f32x4.lt" => Instr::F32X4_Lt,
+                    "f32x4.gt" => Instr::F32X4_Gt,
+                    "f32x4.le" => Instr::F32X4_Le,
+                    "f32x4.ge" => Instr::F32X4_Ge,
+                    "f64x2.eq" => Instr::F64X2_Eq,
+                    "f64x2.ne" => Instr::F64X2_Ne,
+                    "f64x2.lt" => Instr::F64X2_Lt,
+                    "f64x2.gt" => Instr::F64X2_Gt,
+                    "f64x2.le" => Instr::F64X2_Le,
+                    "f64x2.ge" => Instr::F64X2_Ge,
+                    "v128.not" => Instr::V128_Not,
+                    "v128.and" => Instr::V128_And,
+                    "v128.andnot" => Instr::V128_Andnot,
+                    "v128.or" => Instr::V128_Or,
+                    "v128.xor" => Instr::V128_Xor,
+                    "v128.bitselect" => Instr::V128_Bitselect,
+                    "v128.any_true" => Instr::V128_Any_True,
+                    "f32x4.demote_f64x2_zero" => Instr::F32X4_Demote_F64X2_Zero,
+                    "f64x2.promote_low_f32x4" => Instr::F64X2_Promote_Low_F32X4,
+                    "i8x16.abs" => Instr::I8X16_Abs,
+                    "i8x16.neg" => Instr::I8X16_Neg,
+                    "i8x16.popcnt" => Instr::I8X16_Popcnt,
+                    "i8x16.all_true" => Instr::I8X16_All_True,
+                    "i8x16.bitmask" => Instr::I8X16_Bitmask,
+                    "i8x16.narrow_i16x8_s" => Instr::I8X16_Narrow_I16X8_S,
+                    "i8x16.narrow_i16x8_u" => Instr::I8X16_Narrow_I16X8_U,
+                    "f32x4.ceil" => Instr::F32X4_Ceil,
+                    "f32x4.floor" => Instr::F32X4_Floor,
+                    "f32x4.trunc" => Instr::F32X4_Trunc,
+                    "f32x4.nearest" => Instr::F32X4_Nearest,
+                    "i8x16.shl" => Instr::I8X16_Shl,
+                    "i8x16.shr_s" => Instr::I8X16_Shr_S,
+                    "i8x16.shr_u" => Instr::I8X16_Shr_U,
+                    "i8x16.add" => Instr::I8X16_Add,
+                    "i8x16.add_sat_s" => Instr::I8X16_Add_Sat_S,
+                    "i8x16.add_sat_u" => Instr::I8X16_Add_Sat_U,
+                    "i8x16.sub" => Instr::I8X16_Sub,
+                    "i8x16.sub_sat_s" => Instr::I8X16_Sub_Sat_S,
+                    "i8x16.sub_sat_u" => Instr::I8X16_Sub_Sat_U,
+                    "f64x2.ceil" => Instr::F64X2_Ceil,
+                    "f64x2.floor" => Instr::F64X2_Floor,
+                    "i8x16.min_s" => Instr::I8X16_Min_S,
+                    "i8x16.min_u" => Instr::I8X16_Min_U,
+                    "i8x16.max_s" => Instr::I8X16_Max_S,
+                    "i8x16.max_u" => Instr::I8X16_Max_U,
+                    "f64x2.trunc" => Instr::F64X2_Trunc,
+                    "i8x16.avgr_u" => Instr::I8X16_Avgr_U,
+                    "i16x8.extadd_pairwise_i8x16_s" => Instr::I16X8_Extadd_Pairwise_I8X16_S,
+                    "i16x8.extadd_pairwise_i8x16_u" => Instr::I16X8_Extadd_Pairwise_I8X16_U,
+                    "i32x4.extadd_pairwise_i16x8_s" => Instr::I32X4_Extadd_Pairwise_I16X8_S,
+                    "i32x4.extadd_pairwise_i16x8_u" => Instr::I32X4_Extadd_Pairwise_I16X8_U,
+                    "i16x8.abs" => Instr::I16X8_Abs,
+                    "i16x8.neg" => Instr::I16X8_Neg,
+                    "i16x8.q15mulr_sat_s" => Instr::I16X8_Q15mulr_Sat_S,
+                    "i16x8.all_true" => Instr::I16X8_All_True,
+                    "i16x8.bitmask" => Instr::I16X8_Bitmask,
+                    "i16x8.narrow_i32x4_s" => Instr::I16X8_Narrow_I32X4_S,
+                    "i16x8.narrow_i32x4_u" => Instr::I16X8_Narrow_I32X4_U,
+                    "i16x8.extend_low_i8x16_s" => Instr::I16X8_Extend_Low_I8X16_S,
+                    "i16x8.extend_high_i8x16_s" => Instr::I16X8_Extend_High_I8X16_S,
+                    "i16x8.extend_low_i8x16_u" => Instr::I16X8_Extend_Low_I8X16_U,
+                    "i16x8.extend_high_i8x16_u" => Instr::I16X8_Extend_High_I8X16_U,
+                    "i16x8.shl" => Instr::I16X8_Shl,
+                    "i16x8.shr_s" => Instr::I16X8_Shr_S,
+                    "i16x8.shr_u" => Instr::I16X8_Shr_U,
+                    "i16x8.add" => Instr::I16X8_Add,
+                    "i16x8.add_sat_s" => Instr::I16X8_Add_Sat_S,
+                    "i16x8.add_sat_u" => Instr::I16X8_Add_Sat_U,
+                    "i16x8.sub" => Instr::I16X8_Sub,
+                    "i16x8.sub_sat_s" => Instr::I16X8_Sub_Sat_S,
+                    "i16x8.sub_sat_u" => Instr::I16X8_Sub_Sat_U,
+                    "f64x2.nearest" => Instr::F64X2_Nearest,
+                    "i16x8.mul" => Instr::I16X8_Mul,
+                    "i16x8.min_s" => Instr::I16X8_Min_S,
+                    "i16x8.min_u" => Instr::I16X8_Min_U,
+                    "i16x8.max_s" => Instr::I16X8_Max_S,
+                    "i16x8.max_u" => Instr::I16X8_Max_U,
+                    "i16x8.avgr_u" => Instr::I16X8_Avgr_U,
+                    "i16x8.extmul_low_i8x16_s" => Instr::I16X8_Extmul_Low_I8X16_S,
+                    "i16x8.extmul_high_i8x16_s" => Instr::I16X8_Extmul_High_I8X16_S,
+                    "i16x8.extmul_low_i8x16_u" => Instr::I16X8_Extmul_Low_I8X16_U,
+                    "i16x8.extmul_high_i8x16_u" => Instr::I16X8_Extmul_High_I8X16_U,
+                    "i32x4.abs" => Instr::I32X4_Abs,
+                    "i32x4.neg" => Instr::I32X4_Neg,
+                    "i32x4.all_true" => Instr::I32X4_All_True,
+                    "i32x4.bitmask" => Instr::I32X4_Bitmask,
+                    "i32x4.extend_low_i16x8_s" => Instr::I32X4_Extend_Low_I16X8_S,
+                    "i32x4.extend_high_i16x8_s" => Instr::I32X4_Extend_High_I16X8_S,
+                    "i32x4.extend_low_i16x8_u" => Instr::I32X4_Extend_Low_I16X8_U,
+                    "i32x4.extend_high_i16x8_u" => Instr::I32X4_Extend_High_I16X8_U,
+                    "i32x4.shl" => Instr::I32X4_Shl,
+                    "i32x4.shr_s" => Instr::I32X4_Shr_S,
+                    "i32x4.shr_u" => Instr::I32X4_Shr_U,
+                    "i32x4.add" => Instr::I32X4_Add,
+                    "i32x4.sub" => Instr::I32X4_Sub,
+                    "i32x4.mul" => Instr::I32X4_Mul,
+                    "i32x4.min_s" => Instr::I32X4_Min_S,
+                    "i32x4.min_u" => Instr::I32X4_Min_U,
+                    "i32x4.max_s" => Instr::I32X4_Max_S,
+                    "i32x4.max_u" => Instr::I32X4_Max_U,
+                    "i32x4.dot_i16x8_s" => Instr::I32X4_Dot_I16X8_S,
+                    "i32x4.extmul_low_i16x8_s" => Instr::I32X4_Extmul_Low_I16X8_S,
+                    "i32x4.extmul_high_i16x8_s" => Instr::I32X4_Extmul_High_I16X8_S,
+                    "i32x4.extmul_low_i16x8_u" => Instr::I32X4_Extmul_Low_I16X8_U,
+                    "i32x4.extmul_high_i16x8_u" => Instr::I32X4_Extmul_High_I16X8_U,
+                    "i64x2.abs" => Instr::I64X2_Abs,
+                    "i64x2.neg" => Instr::I64X2_Neg,
+                    "i64x2.all_true" => Instr::I64X2_All_True,
+                    "i64x2.bitmask" => Instr::I64X2_Bitmask,
+                    "i64x2.extend_low_i32x4_s" => Instr::I64X2_Extend_Low_I32X4_S,
+                    "i64x2.extend_high_i32x4_s" => Instr::I64X2_Extend_High_I32X4_S,
+                    "i64x2.extend_low_i32x4_u" => Instr::I64X2_Extend_Low_I32X4_U,
+                    "i64x2.extend_high_i32x4_u" => Instr::I64X2_Extend_High_I32X4_U,
+                    "i64x2.shl" => Instr::I64X2_Shl,
+                    "i64x2.shr_s" => Instr::I64X2_Shr_S,
+                    "i64x2.shr_u" => Instr::I64X2_Shr_U,
+                    "i64x2.add" => Instr::I64X2_Add,
+                    "i64x2.sub" => Instr::I64X2_Sub,
+                    "i64x2.mul" => Instr::I64X2_Mul,
+                    "i64x2.eq" => Instr::I64X2_Eq,
+                    "i64x2.ne" => Instr::I64X2_Ne,
+                    "i64x2.lt_s" => Instr::I64X2_Lt_S,
+                    "i64x2.gt_s" => Instr::I64X2_Gt_S,
+                    "i64x2.le_s" => Instr::I64X2_Le_S,
+                    "i64x2.ge_s" => Instr::I64X2_Ge_S,
+                    "i64x2.extmul_low_i32x4_s" => Instr::I64X2_Extmul_Low_I32X4_S,
+                    "i64x2.extmul_high_i32x4_s" => Instr::I64X2_Extmul_High_I32X4_S,
+                    "i64x2.extmul_low_i32x4_u" => Instr::I64X2_Extmul_Low_I32X4_U,
+                    "i64x2.extmul_high_i32x4_u" => Instr::I64X2_Extmul_High_I32X4_U,
+                    "f32x4.abs" => Instr::F32X4_Abs,
+                    "f32x4.neg" => Instr::F32X4_Neg,
+                    "f32x4.sqrt" => Instr::F32X4_Sqrt,
+                    "f32x4.add" => Instr::F32X4_Add,
+                    "f32x4.sub" => Instr::F32X4_Sub,
+                    "f32x4.mul" => Instr::F32X4_Mul,
+                    "f32x4.div" => Instr::F32X4_Div,
+                    "f32x4.min" => Instr::F32X4_Min,
+                    "f32x4.max" => Instr::F32X4_Max,
+                    "f32x4.pmin" => Instr::F32X4_Pmin,
+                    "f32x4.pmax" => Instr::F32X4_Pmax,
+                    "f64x2.abs" => Instr::F64X2_Abs,
+                    "f64x2.neg" => Instr::F64X2_Neg,
+                    "f64x2.sqrt" => Instr::F64X2_Sqrt,
+                    "f64x2.add" => Instr::F64X2_Add,
+                    "f64x2.sub" => Instr::F64X2_Sub,
+                    "f64x2.mul" => Instr::F64X2_Mul,
+                    "f64x2.div" => Instr::F64X2_Div,
+                    "f64x2.min" => Instr::F64X2_Min,
+                    "f64x2.max" => Instr::F64X2_Max,
+                    "f64x2.pmin" => Instr::F64X2_Pmin,
+                    "f64x2.pmax" => Instr::F64X2_Pmax,
+                    "i32x4.trunc_sat_f32x4_s" => Instr::I32X4_Trunc_Sat_F32X4_S,
+                    "i32x4.trunc_sat_f32x4_u" => Instr::I32X4_Trunc_Sat_F32X4_U,
+                    "f32x4.convert_i32x4_s" => Instr::F32X4_Convert_I32X4_S,
+                    "f32x4.convert_i32x4_u" => Instr::F32X4_Convert_I32X4_U,
+                    "i32x4.trunc_sat_f64x2_s_zero" => Instr::I32X4_Trunc_Sat_F64X2_S_Zero,
+                    "i32x4.trunc_sat_f64x2_u_zero" => Instr::I32X4_Trunc_Sat_F64X2_U_Zero,
+                    "f64x2.convert_low_i32x4_s" => Instr::F64X2_Convert_Low_I32X4_S,
+                    "f64x2.convert_low_i32x4_u" => Instr::F64X2_Convert_Low_I32X4_U,
+                    "i8x16.relaxed_swizzle" => Instr::I8X16_Relaxed_Swizzle,
+                    "i32x4.relaxed_trunc_f32x4_s" => Instr::I32X4_Relaxed_Trunc_F32X4_S,
+                    "i32x4.relaxed_trunc_f32x4_u" => Instr::I32X4_Relaxed_Trunc_F32X4_U,
+                    "i32x4.relaxed_trunc_f64x2_s_zero" => Instr::I32X4_Relaxed_Trunc_F64X2_S_Zero,
+                    "i32x4.relaxed_trunc_f64x2_u_zero" => Instr::I32X4_Relaxed_Trunc_F64X2_U_Zero,
+                    "f32x4.relaxed_madd" => Instr::F32X4_Relaxed_Madd,
+                    "f32x4.relaxed_nmadd" => Instr::F32X4_Relaxed_Nmadd,
+                    "f64x2.relaxed_madd" => Instr::F64X2_Relaxed_Madd,
+                    "f64x2.relaxed_nmadd" => Instr::F64X2_Relaxed_Nmadd,
+                    "i8x16.relaxed_laneselect" => Instr::I8X16_Relaxed_Laneselect,
+                    "i16x8.relaxed_laneselect" => Instr::I16X8_Relaxed_Laneselect,
+                    "i32x4.relaxed_laneselect" => Instr::I32X4_Relaxed_Laneselect,
+                    "i64x2.relaxed_laneselect" => Instr::I64X2_Relaxed_Laneselect,
+                    "f32x4.relaxed_min" => Instr::F32X4_Relaxed_Min,
+                    "f32x4.relaxed_max" => Instr::F32X4_Relaxed_Max,
+                    "f64x2.relaxed_min" => Instr::F64X2_Relaxed_Min,
+                    "f64x2.relaxed_max" => Instr::F64X2_Relaxed_Max,
+                    "i16x8.relaxed_q15mulr_s" => Instr::I16X8_Relaxed_Q15mulr_S,
+                    "i16x8.relaxed_dot_i8x16_i7x16_s" => Instr::I16X8_Relaxed_Dot_I8X16_I7X16_S,
+                    "i32x4.relaxed_dot_i8x16_i7x16_add_s" => {
+                        Instr::I32X4_Relaxed_Dot_I8X16_I7X16_Add_S
+                    }
+                    "memory.atomic.notify" => Instr::MemoryAtomicNotify(MemArg(0, 0, 0)),
+                    "memory.atomic.wait32" => Instr::MemoryAtomicWait32(MemArg(0, 0, 0)),
+                    "memory.atomic.wait64" => Instr::MemoryAtomicWait64(MemArg(0, 0, 0)),
+                    "i32.atomic.load" => Instr::I32AtomicLoad(MemArg(0, 0, 0)),
+                    "i64.atomic.load" => Instr::I64AtomicLoad(MemArg(0, 0, 0)),
+                    "i32.atomic.load8_u" => Instr::I32AtomicLoad8U(MemArg(0, 0, 0)),
+                    "i32.atomic.load16_u" => Instr::I32AtomicLoad16U(MemArg(0, 0, 0)),
+                    "i64.atomic.load8_u" => Instr::I64AtomicLoad8U(MemArg(0, 0, 0)),
+                    "i64.atomic.load16_u" => Instr::I64AtomicLoad16U(MemArg(0, 0, 0)),
+                    "i64.atomic.load32_u" => Instr::I64AtomicLoad32U(MemArg(0, 0, 0)),
+                    "i32.atomic.store" => Instr::I32AtomicStore(MemArg(0, 0, 0)),
+                    "i64.atomic.store" => Instr::I64AtomicStore(MemArg(0, 0, 0)),
+                    "i32.atomic.store8" => Instr::I32AtomicStore8(MemArg(0, 0, 0)),
+                    "i32.atomic.store16" => Instr::I32AtomicStore16(MemArg(0, 0, 0)),
+                    "i64.atomic.store8" => Instr::I64AtomicStore8(MemArg(0, 0, 0)),
+                    "i64.atomic.store16" => Instr::I64AtomicStore16(MemArg(0, 0, 0)),
+                    "i64.atomic.store32" => Instr::I64AtomicStore32(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw.add" => Instr::I32AtomicRmwAdd(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw.add" => Instr::I64AtomicRmwAdd(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw8.add_u" => Instr::I32AtomicRmw8AddU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw16.add_u" => Instr::I32AtomicRmw16AddU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw8.add_u" => Instr::I64AtomicRmw8AddU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw16.add_u" => Instr::I64AtomicRmw16AddU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw32.add_u" => Instr::I64AtomicRmw32AddU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw.sub" => Instr::I32AtomicRmwSub(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw.sub" => Instr::I64AtomicRmwSub(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw8.sub_u" => Instr::I32AtomicRmw8SubU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw16.sub_u" => Instr::I32AtomicRmw16SubU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw8.sub_u" => Instr::I64AtomicRmw8SubU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw16.sub_u" => Instr::I64AtomicRmw16SubU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw32.sub_u" => Instr::I64AtomicRmw32SubU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw.and" => Instr::I32AtomicRmwAnd(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw.and" => Instr::I64AtomicRmwAnd(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw8.and_u" => Instr::I32AtomicRmw8AndU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw16.and_u" => Instr::I32AtomicRmw16AndU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw8.and_u" => Instr::I64AtomicRmw8AndU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw16.and_u" => Instr::I64AtomicRmw16AndU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw32.and_u" => Instr::I64AtomicRmw32AndU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw.or" => Instr::I32AtomicRmwOr(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw.or" => Instr::I64AtomicRmwOr(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw8.or_u" => Instr::I32AtomicRmw8OrU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw16.or_u" => Instr::I32AtomicRmw16OrU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw8.or_u" => Instr::I64AtomicRmw8OrU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw16.or_u" => Instr::I64AtomicRmw16OrU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw32.or_u" => Instr::I64AtomicRmw32OrU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw.xor" => Instr::I32AtomicRmwXor(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw.xor" => Instr::I64AtomicRmwXor(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw8.xor_u" => Instr::I32AtomicRmw8XorU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw16.xor_u" => Instr::I32AtomicRmw16XorU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw8.xor_u" => Instr::I64AtomicRmw8XorU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw16.xor_u" => Instr::I64AtomicRmw16XorU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw32.xor_u" => Instr::I64AtomicRmw32XorU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw.xchg" => Instr::I32AtomicRmwXchg(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw.xchg" => Instr::I64AtomicRmwXchg(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw8.xchg_u" => Instr::I32AtomicRmw8XchgU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw16.xchg_u" => Instr::I32AtomicRmw16XchgU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw8.xchg_u" => Instr::I64AtomicRmw8XchgU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw16.xchg_u" => Instr::I64AtomicRmw16XchgU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw32.xchg_u" => Instr::I64AtomicRmw32XchgU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw.cmpxchg" => Instr::I32AtomicRmwCmpxchg(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw.cmpxchg" => Instr::I64AtomicRmwCmpxchg(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw8.cmpxchg_u" => Instr::I32AtomicRmw8CmpxchgU(MemArg(0, 0, 0)),
+                    "i32.atomic.rmw16.cmpxchg_u" => Instr::I32AtomicRmw16CmpxchgU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw8.cmpxchg_u" => Instr::I64AtomicRmw8CmpxchgU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw16.cmpxchg_u" => Instr::I64AtomicRmw16CmpxchgU(MemArg(0, 0, 0)),
+                    "i64.atomic.rmw32.cmpxchg_u" => Instr::I64AtomicRmw32CmpxchgU(MemArg(0, 0, 0)),
+                    "atomic.fence" => Instr::AtomicFence,
+                    _ => return Err(self.error(pos, format!("unknown operator `{name}`"))),
+                };
+                if instr.memarg().is_some() {
+                    let memarg = self.memarg(items, at, &instr)?;
+                    *instr.memarg_mut().unwrap() = memarg;
+                }
+                if let Some(lane) = instr.lane_mut() {
+                    *lane = self.lane(items, at)?;
+                }
+                instr
+            }
+        };
+        Ok(instr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{f32_lit, f64_lit, int_lit};
+    use crate::modules::Module;
+    use crate::printer::{print, PrintOptions};
+    use crate::{Encode, Parse};
+
+    const WAT: &str = r#"
+        (module $m
+          (type $bin (func (param i32 i32) (result i32)))
+          (import "env" "log" (func $log (param i32)))
+          (import "env" "mem" (memory 1))
+          (global $count (mut i32) (i32.const 0))
+          (table $t 2 funcref)
+          (elem (table $t) (i32.const 0) func $add $fac)
+          (data (i32.const 16) "hi\00\ff")
+          (data $passive "lazy")
+          (func $add (export "add") (type $bin) (param $a i32) (param $b i32) (result i32)
+            (i32.add (local.get $a) (local.get $b)))
+          (func $fac (param $n i32) (result i32) (local $acc i32)
+            i32.const 1
+            local.set $acc
+            block $done
+              loop $next
+                local.get $n
+                i32.eqz
+                br_if $done
+                (local.set $acc (i32.mul (local.get $acc) (local.get $n)))
+                (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                br $next
+              end
+            end
+            (if (result i32) (i32.gt_u (local.get $acc) (i32.const 0x7fff_ffff))
+              (then (i32.const -1))
+              (else (local.get $acc)))
+            (call $log (global.get $count))
+            (i32.store offset=4 align=1 (i32.const 0) (call_indirect (type $bin) (i32.const 1) (i32.const 2) (i32.const 0)))
+            (memory.init $passive (i32.const 0) (i32.const 0) (i32.const 4))
+            (drop (f64.const -0x1.8p3))
+            (drop (v128.const i32x4 1 2 3 4))
+            (drop (i32x4.bitmask (i16x8.shl (v128.const i32x4 1 2 3 4) (i32.const 1))))
+            (drop (f32x4.relaxed_madd (v128.const i32x4 0 0 0 0) (v128.const i32x4 0 0 0 0) (v128.const i32x4 0 0 0 0)))
+            (drop (select (result f32) (f32.const nan:0x200000) (f32.const inf) (i32.const 1)))
+          )
+          (start $main)
+          (func $main)
+        )
+    "#;
+
+    fn bytes(module: &Module) -> Vec<u8> {
+        let mut data = Vec::new();
+        module.encode(&mut data);
+        data
+    }
+
+    #[test]
+    fn roundtrip() {
+        let module = Module::parse(WAT).unwrap();
+        let binary = bytes(&module);
+        let decoded = Module::parse(&mut binary.clone()).unwrap();
+        assert_eq!(bytes(&decoded), binary);
+
+        for folded in [false, true] {
+            let text = print(
+                &decoded,
+                PrintOptions {
+                    folded,
+                    offsets: false,
+                },
+            );
+            let reparsed = Module::parse(text.as_str()).unwrap();
+            assert_eq!(bytes(&reparsed), binary, "{text}");
+        }
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(int_lit("-0x8000_0000", 32), Some(0x8000_0000));
+        assert_eq!(int_lit("4294967295", 32), Some(u32::MAX as u64));
+        assert_eq!(int_lit("4294967296", 32), None);
+        assert_eq!(int_lit("-129", 8), None);
+        assert_eq!(f32_lit("0x1p-149"), Some(f32::from_bits(1)));
+        assert_eq!(f32_lit("0x1.fffffep127"), Some(f32::MAX));
+        assert_eq!(f32_lit("0x1.ffffffp127"), None);
+        assert_eq!(f32_lit("0x1.000001p0"), Some(1.0));
+        assert_eq!(f32_lit("0x1.000003p0"), Some(f32::from_bits(0x3F80_0002)));
+        assert_eq!(f64_lit("-0.0").map(f64::to_bits), Some(1 << 63));
+        assert_eq!(f64_lit("1_000.5e-1"), Some(100.05));
+        assert_eq!(
+            f64_lit("-nan").map(f64::to_bits),
+            Some(0xFFF8_0000_0000_0000)
+        );
+        assert!(Module::parse("(module (func (i32.const 0x1_0000_0000)))").is_err());
+    }
+
+    #[test]
+    fn segment_index() {
+        for (short, long) in [
+            (
+                r#"(module (memory 1) (data 0 (i32.const 0) "a"))"#,
+                r#"(module (memory 1) (data (memory 0) (i32.const 0) "a"))"#,
+            ),
+            (
+                r#"(module (memory 1) (memory $m 1) (data $d $m (offset (i32.const 0)) "a"))"#,
+                r#"(module (memory 1) (memory $m 1) (data $d (memory 1) (i32.const 0) "a"))"#,
+            ),
+            (
+                "(module (table 1 funcref) (elem 0 (i32.const 0) 0) (func))",
+                "(module (table 1 funcref) (elem (table 0) (i32.const 0) func 0) (func))",
+            ),
+            (
+                "(module (table 1 funcref) (table $t 1 funcref) (elem $e $t (offset (i32.const 0)) func 0) (func))",
+                "(module (table 1 funcref) (table $t 1 funcref) (elem $e (table 1) (i32.const 0) func 0) (func))",
+            ),
+        ] {
+            let short = Module::parse(short).unwrap();
+            assert_eq!(bytes(&short), bytes(&Module::parse(long).unwrap()));
+        }
+    }
+}
@@ -0,0 +1,779 @@
+use std::backtrace::Backtrace;
+use std::fmt::{Display, Write};
+
+use crate::component::{
+    Alias, AliasTarget, Component, ComponentDecl, ComponentFuncType, ComponentImport,
+    ComponentSection, ComponentValType, DefType, DefValType, ExternDesc, ExternName, InstanceDecl,
+    PrimValType, Results, Sort, TypeBound,
+};
+use crate::modules::{Module, Section};
+use crate::{Error, Parse};
+
+/// Prefix of the custom section `wit-bindgen` uses to embed the world a module targets.
+pub const COMPONENT_TYPE: &str = "component-type";
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::InvalidComponent(Backtrace::capture(), msg.into())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Prim(PrimValType),
+    Named(String),
+    List(Box<Type>),
+    Tuple(Vec<Type>),
+    Option(Box<Type>),
+    Result(Option<Box<Type>>, Option<Box<Type>>),
+    Own(String),
+    Borrow(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeDefKind {
+    Record(Vec<(String, Type)>),
+    Variant(Vec<(String, Option<Type>)>),
+    Enum(Vec<String>),
+    Flags(Vec<String>),
+    Resource,
+    Alias(Type),
+    /// Interface the type is imported from and its name there.
+    Use(String, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDef {
+    pub name: String,
+    pub kind: TypeDefKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FuncResults {
+    Anon(Type),
+    Named(Vec<(String, Type)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Func {
+    pub params: Vec<(String, Type)>,
+    pub results: FuncResults,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Interface {
+    pub name: String,
+    pub types: Vec<TypeDef>,
+    pub funcs: Vec<(String, Func)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldItem {
+    Interface(Interface),
+    Func(String, Func),
+    Type(TypeDef),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct World {
+    pub name: String,
+    pub imports: Vec<WorldItem>,
+    pub exports: Vec<WorldItem>,
+}
+
+/// Worlds described by a component: those encoded as component types, the ones embedded in
+/// `component-type` custom sections and, if it has any, its own imports and exports as `root`.
+pub fn worlds(component: &Component) -> Result<Vec<World>, Error> {
+    let mut resolver = Resolver::default();
+    resolver.scopes.push(Scope::default());
+    let mut root = World {
+        name: "root".to_string(),
+        ..Default::default()
+    };
+    for section in &component.sections {
+        match section {
+            ComponentSection::Custom(custom) if custom.0.starts_with(COMPONENT_TYPE) => {
+                let inner = Component::parse(&mut custom.1.clone())?;
+                resolver.worlds.extend(worlds(&inner)?);
+            }
+            ComponentSection::CoreModule(module) => resolver.worlds.extend(module_worlds(module)?),
+            ComponentSection::Component(inner) => resolver.worlds.extend(worlds(inner)?),
+            ComponentSection::Instance(instances) => {
+                let scope = resolver.scope();
+                scope
+                    .instances
+                    .extend(instances.iter().map(|_| String::new()));
+            }
+            ComponentSection::Alias(aliases) => {
+                for alias in aliases {
+                    resolver.alias(alias)?;
+                }
+            }
+            ComponentSection::Type(types) => {
+                for ty in types {
+                    let entry = resolver.def_type(ty)?;
+                    resolver.scope().types.push(entry);
+                }
+            }
+            ComponentSection::Import(imports) => {
+                for ComponentImport(name, desc) in imports {
+                    root.imports.extend(resolver.extern_item(name, desc)?);
+                }
+            }
+            ComponentSection::Export(exports) => {
+                for export in exports {
+                    match &export.2 {
+                        Some(desc) => root.exports.extend(resolver.extern_item(&export.0, desc)?),
+                        None if export.1 .0 == Sort::Type => {
+                            let entry = resolver.entry(export.1 .1)?.clone();
+                            resolver.scope().types.push(entry);
+                        }
+                        None if export.1 .0 == Sort::Instance => {
+                            resolver.scope().instances.push(export.0 .0.clone());
+                        }
+                        None => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if !root.imports.is_empty() || !root.exports.is_empty() {
+        resolver.worlds.push(root);
+    }
+    Ok(resolver.worlds)
+}
+
+/// Worlds embedded by bindings generators in the `component-type` custom sections of a core module.
+pub fn module_worlds(module: &Module) -> Result<Vec<World>, Error> {
+    let mut worlds = Vec::new();
+    for section in &module.sections {
+        let custom = match section {
+            Section::Custom(custom) => custom,
+            Section::DecodedCustom(decoded) => &decoded.sec,
+            _ => continue,
+        };
+        if custom.0.starts_with(COMPONENT_TYPE) {
+            let component = Component::parse(&mut custom.1.clone())?;
+            worlds.extend(self::worlds(&component)?);
+        }
+    }
+    Ok(worlds)
+}
+
+/// What an index of the type index space resolves to.
+#[derive(Debug, Clone)]
+enum Entry {
+    /// A named type, with the interface it was aliased from.
+    Named(Option<String>, String),
+    Def(TypeDefKind),
+    Func(Func),
+    Instance(Interface),
+    Component(World),
+}
+
+#[derive(Default)]
+struct Scope {
+    types: Vec<Entry>,
+    instances: Vec<String>,
+}
+
+#[derive(Default)]
+struct Resolver {
+    scopes: Vec<Scope>,
+    worlds: Vec<World>,
+}
+
+impl Resolver {
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn entry(&self, idx: u32) -> Result<&Entry, Error> {
+        self.outer(0, idx)
+    }
+
+    fn outer(&self, count: u32, idx: u32) -> Result<&Entry, Error> {
+        self.scopes
+            .len()
+            .checked_sub(count as usize + 1)
+            .and_then(|scope| self.scopes[scope].types.get(idx as usize))
+            .ok_or_else(|| invalid(format!("unknown type {idx}")))
+    }
+
+    fn alias(&mut self, Alias(sort, target): &Alias) -> Result<(), Error> {
+        match (sort, target) {
+            (Sort::Type, AliasTarget::Export(instance, name)) => {
+                let iface = self.scope().instances.get(*instance as usize).cloned();
+                let iface = iface.filter(|iface| !iface.is_empty());
+                self.scope().types.push(Entry::Named(iface, name.clone()));
+            }
+            (Sort::Type, AliasTarget::Outer(count, idx)) => {
+                let entry = self.outer(*count, *idx)?.clone();
+                self.scope().types.push(entry);
+            }
+            (Sort::Instance, _) => self.scope().instances.push(String::new()),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn val(&self, ty: &ComponentValType) -> Result<Type, Error> {
+        match ty {
+            ComponentValType::Primitive(prim) => Ok(Type::Prim(*prim)),
+            ComponentValType::Type(idx) => match self.entry(*idx)? {
+                Entry::Named(_, name) => Ok(Type::Named(name.clone())),
+                Entry::Def(TypeDefKind::Alias(ty)) => Ok(ty.clone()),
+                _ => Err(invalid(format!("type {idx} is not a named value type"))),
+            },
+        }
+    }
+
+    fn resource(&self, idx: u32) -> Result<String, Error> {
+        match self.entry(idx)? {
+            Entry::Named(_, name) => Ok(name.clone()),
+            _ => Err(invalid(format!("type {idx} is not a named resource"))),
+        }
+    }
+
+    fn def_val(&self, ty: &DefValType) -> Result<TypeDefKind, Error> {
+        let boxed = |ty: &ComponentValType| self.val(ty).map(Box::new);
+        Ok(match ty {
+            DefValType::Primitive(prim) => TypeDefKind::Alias(Type::Prim(*prim)),
+            DefValType::Record(fields) => TypeDefKind::Record(self.named(fields)?),
+            DefValType::Variant(cases) => TypeDefKind::Variant(
+                cases
+                    .iter()
+                    .map(|(name, ty)| {
+                        Ok((
+                            name.clone(),
+                            ty.as_ref().map(|ty| self.val(ty)).transpose()?,
+                        ))
+                    })
+                    .collect::<Result<_, Error>>()?,
+            ),
+            DefValType::List(ty) => TypeDefKind::Alias(Type::List(boxed(ty)?)),
+            DefValType::Tuple(tys) => TypeDefKind::Alias(Type::Tuple(
+                tys.iter()
+                    .map(|ty| self.val(ty))
+                    .collect::<Result<_, _>>()?,
+            )),
+            DefValType::Flags(names) => TypeDefKind::Flags(names.clone()),
+            DefValType::Enum(names) => TypeDefKind::Enum(names.clone()),
+            DefValType::Option(ty) => TypeDefKind::Alias(Type::Option(boxed(ty)?)),
+            DefValType::Result(ok, err) => TypeDefKind::Alias(Type::Result(
+                ok.as_ref().map(boxed).transpose()?,
+                err.as_ref().map(boxed).transpose()?,
+            )),
+            DefValType::Own(idx) => TypeDefKind::Alias(Type::Own(self.resource(*idx)?)),
+            DefValType::Borrow(idx) => TypeDefKind::Alias(Type::Borrow(self.resource(*idx)?)),
+        })
+    }
+
+    fn named(&self, fields: &[(String, ComponentValType)]) -> Result<Vec<(String, Type)>, Error> {
+        fields
+            .iter()
+            .map(|(name, ty)| Ok((name.clone(), self.val(ty)?)))
+            .collect()
+    }
+
+    fn func(&self, ComponentFuncType(params, results): &ComponentFuncType) -> Result<Func, Error> {
+        Ok(Func {
+            params: self.named(params)?,
+            results: match results {
+                Results::Unnamed(ty) => FuncResults::Anon(self.val(ty)?),
+                Results::Named(results) => FuncResults::Named(self.named(results)?),
+            },
+        })
+    }
+
+    fn def_type(&mut self, ty: &DefType) -> Result<Entry, Error> {
+        Ok(match ty {
+            DefType::DefVal(ty) => Entry::Def(self.def_val(ty)?),
+            DefType::Func(ty) => Entry::Func(self.func(ty)?),
+            DefType::Component(decls) => Entry::Component(self.component(decls)?),
+            DefType::Instance(decls) => Entry::Instance(self.instance(decls)?),
+            DefType::Resource(_) => Entry::Def(TypeDefKind::Resource),
+        })
+    }
+
+    fn component(&mut self, decls: &[ComponentDecl]) -> Result<World, Error> {
+        self.scopes.push(Scope::default());
+        let mut world = World::default();
+        for decl in decls {
+            match decl {
+                ComponentDecl::Import(ComponentImport(name, desc)) => {
+                    world.imports.extend(self.extern_item(name, desc)?);
+                }
+                ComponentDecl::Instance(InstanceDecl::Export(name, desc)) => {
+                    world.exports.extend(self.extern_item(name, desc)?);
+                }
+                ComponentDecl::Instance(decl) => self.decl(decl)?,
+            }
+        }
+        self.scopes.pop();
+        Ok(world)
+    }
+
+    fn instance(&mut self, decls: &[InstanceDecl]) -> Result<Interface, Error> {
+        self.scopes.push(Scope::default());
+        let mut iface = Interface::default();
+        for decl in decls {
+            match decl {
+                InstanceDecl::Export(name, desc) => match self.extern_item(name, desc)? {
+                    Some(WorldItem::Func(name, func)) => iface.funcs.push((name, func)),
+                    Some(WorldItem::Type(def)) => iface.types.push(def),
+                    _ => {}
+                },
+                decl => self.decl(decl)?,
+            }
+        }
+        self.scopes.pop();
+        Ok(iface)
+    }
+
+    fn decl(&mut self, decl: &InstanceDecl) -> Result<(), Error> {
+        match decl {
+            InstanceDecl::Type(ty) => {
+                let entry = self.def_type(ty)?;
+                self.scope().types.push(entry);
+            }
+            InstanceDecl::Alias(alias) => self.alias(alias)?,
+            // Core types live in their own index space.
+            InstanceDecl::CoreType(_) | InstanceDecl::Export(..) => {}
+        }
+        Ok(())
+    }
+
+    /// Imports or exports `desc` under `name`, adding it to the index spaces of the current scope.
+    fn extern_item(
+        &mut self,
+        ExternName(name, _): &ExternName,
+        desc: &ExternDesc,
+    ) -> Result<Option<WorldItem>, Error> {
+        Ok(match desc {
+            ExternDesc::Instance(idx) => {
+                let Entry::Instance(iface) = self.entry(*idx)? else {
+                    return Err(invalid(format!("type {idx} is not an instance type")));
+                };
+                let iface = Interface {
+                    name: name.clone(),
+                    ..iface.clone()
+                };
+                self.scope().instances.push(name.clone());
+                Some(WorldItem::Interface(iface))
+            }
+            ExternDesc::Func(idx) => {
+                let Entry::Func(func) = self.entry(*idx)? else {
+                    return Err(invalid(format!("type {idx} is not a function type")));
+                };
+                Some(WorldItem::Func(name.clone(), func.clone()))
+            }
+            ExternDesc::Type(bound) => {
+                let kind = match bound {
+                    TypeBound::SubResource => TypeDefKind::Resource,
+                    TypeBound::Eq(idx) => match self.entry(*idx)? {
+                        Entry::Named(Some(iface), orig) => {
+                            TypeDefKind::Use(iface.clone(), orig.clone())
+                        }
+                        Entry::Named(None, orig) => TypeDefKind::Alias(Type::Named(orig.clone())),
+                        Entry::Def(kind) => kind.clone(),
+                        _ => return Err(invalid(format!("type {idx} is not a value type"))),
+                    },
+                };
+                self.scope().types.push(Entry::Named(None, name.clone()));
+                Some(WorldItem::Type(TypeDef {
+                    name: name.clone(),
+                    kind,
+                }))
+            }
+            ExternDesc::Component(idx) => {
+                let Entry::Component(world) = self.entry(*idx)? else {
+                    return Err(invalid(format!("type {idx} is not a component type")));
+                };
+                let world = World {
+                    name: name.clone(),
+                    ..world.clone()
+                };
+                self.worlds.push(world);
+                None
+            }
+            ExternDesc::Module(_) | ExternDesc::Value(_) => None,
+        })
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "bool",
+    "borrow",
+    "char",
+    "constructor",
+    "enum",
+    "export",
+    "f32",
+    "f64",
+    "flags",
+    "from",
+    "func",
+    "future",
+    "import",
+    "include",
+    "interface",
+    "list",
+    "option",
+    "own",
+    "package",
+    "record",
+    "resource",
+    "result",
+    "s16",
+    "s32",
+    "s64",
+    "s8",
+    "static",
+    "stream",
+    "string",
+    "tuple",
+    "type",
+    "u16",
+    "u32",
+    "u64",
+    "u8",
+    "use",
+    "variant",
+    "with",
+    "world",
+];
+
+fn ident(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("%{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// The interface part of a qualified `namespace:package/interface@version` name.
+fn short(name: &str) -> &str {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    name.split('@').next().unwrap_or(name)
+}
+
+fn qualified(name: &str) -> bool {
+    name.contains(':')
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Prim(prim) => f.write_str(match prim {
+                PrimValType::Bool => "bool",
+                PrimValType::S8 => "s8",
+                PrimValType::U8 => "u8",
+                PrimValType::S16 => "s16",
+                PrimValType::U16 => "u16",
+                PrimValType::S32 => "s32",
+                PrimValType::U32 => "u32",
+                PrimValType::S64 => "s64",
+                PrimValType::U64 => "u64",
+                PrimValType::F32 => "f32",
+                PrimValType::F64 => "f64",
+                PrimValType::Char => "char",
+                PrimValType::String => "string",
+                PrimValType::ErrorContext => "error-context",
+            }),
+            Self::Named(name) | Self::Own(name) => f.write_str(&ident(name)),
+            Self::List(ty) => write!(f, "list<{ty}>"),
+            Self::Tuple(tys) => {
+                let tys: Vec<_> = tys.iter().map(Type::to_string).collect();
+                write!(f, "tuple<{}>", tys.join(", "))
+            }
+            Self::Option(ty) => write!(f, "option<{ty}>"),
+            Self::Result(None, None) => f.write_str("result"),
+            Self::Result(Some(ok), None) => write!(f, "result<{ok}>"),
+            Self::Result(None, Some(err)) => write!(f, "result<_, {err}>"),
+            Self::Result(Some(ok), Some(err)) => write!(f, "result<{ok}, {err}>"),
+            Self::Borrow(name) => write!(f, "borrow<{}>", ident(name)),
+        }
+    }
+}
+
+/// Print worlds, and the interfaces they reference by qualified name, as WIT text.
+pub fn print(worlds: &[World]) -> String {
+    let mut out = String::new();
+    let mut printed = Vec::new();
+    for world in worlds {
+        for item in world.imports.iter().chain(&world.exports) {
+            if let WorldItem::Interface(iface) = item {
+                if qualified(&iface.name) && !printed.contains(&iface.name) {
+                    printed.push(iface.name.clone());
+                    let _ = writeln!(out, "interface {} {{", ident(short(&iface.name)));
+                    interface(&mut out, iface, 1);
+                    out.push_str("}\n\n");
+                }
+            }
+        }
+    }
+    for world in worlds {
+        let _ = writeln!(out, "world {} {{", ident(short(&world.name)));
+        for (keyword, items) in [("import", &world.imports), ("export", &world.exports)] {
+            for item in items {
+                match item {
+                    WorldItem::Interface(iface) if qualified(&iface.name) => {
+                        let _ = writeln!(out, "    {keyword} {};", iface.name);
+                    }
+                    WorldItem::Interface(iface) => {
+                        let _ = writeln!(out, "    {keyword} {}: interface {{", ident(&iface.name));
+                        interface(&mut out, iface, 2);
+                        out.push_str("    }\n");
+                    }
+                    WorldItem::Func(name, func) => {
+                        let _ = writeln!(
+                            out,
+                            "    {keyword} {}: func{};",
+                            ident(name),
+                            signature(func, 0)
+                        );
+                    }
+                    WorldItem::Type(def) => type_def(&mut out, def, &[], 1),
+                }
+            }
+        }
+        out.push_str("}\n\n");
+    }
+    out.truncate(out.trim_end().len() + 1);
+    out
+}
+
+fn interface(out: &mut String, iface: &Interface, depth: usize) {
+    for def in &iface.types {
+        type_def(out, def, &iface.funcs, depth);
+    }
+    for (name, func) in &iface.funcs {
+        if !name.starts_with('[') {
+            let _ = writeln!(
+                out,
+                "{}{}: func{};",
+                "    ".repeat(depth),
+                ident(name),
+                signature(func, 0)
+            );
+        }
+    }
+}
+
+/// Parameters and results, skipping the first `skip` parameters.
+fn signature(func: &Func, skip: usize) -> String {
+    let named = |fields: &[(String, Type)]| {
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|(name, ty)| format!("{}: {ty}", ident(name)))
+            .collect();
+        fields.join(", ")
+    };
+    let params = named(func.params.get(skip..).unwrap_or_default());
+    match &func.results {
+        FuncResults::Anon(ty) => format!("({params}) -> {ty}"),
+        FuncResults::Named(results) if results.is_empty() => format!("({params})"),
+        FuncResults::Named(results) => format!("({params}) -> ({})", named(results)),
+    }
+}
+
+fn type_def(out: &mut String, def: &TypeDef, funcs: &[(String, Func)], depth: usize) {
+    let indent = "    ".repeat(depth);
+    let name = ident(&def.name);
+    let block = |out: &mut String, keyword: &str, lines: Vec<String>| {
+        let _ = writeln!(out, "{indent}{keyword} {name} {{");
+        for line in lines {
+            let _ = writeln!(out, "{indent}    {line}");
+        }
+        let _ = writeln!(out, "{indent}}}");
+    };
+    match &def.kind {
+        TypeDefKind::Record(fields) => {
+            let fields = fields
+                .iter()
+                .map(|(field, ty)| format!("{}: {ty},", ident(field)));
+            block(out, "record", fields.collect());
+        }
+        TypeDefKind::Variant(cases) => {
+            let cases = cases.iter().map(|(case, ty)| match ty {
+                Some(ty) => format!("{}({ty}),", ident(case)),
+                None => format!("{},", ident(case)),
+            });
+            block(out, "variant", cases.collect());
+        }
+        TypeDefKind::Enum(cases) => {
+            block(
+                out,
+                "enum",
+                cases
+                    .iter()
+                    .map(|case| format!("{},", ident(case)))
+                    .collect(),
+            );
+        }
+        TypeDefKind::Flags(flags) => {
+            block(
+                out,
+                "flags",
+                flags
+                    .iter()
+                    .map(|flag| format!("{},", ident(flag)))
+                    .collect(),
+            );
+        }
+        TypeDefKind::Resource => {
+            let mut methods = Vec::new();
+            for (func_name, func) in funcs {
+                let Some((kind, rest)) =
+                    func_name.strip_prefix('[').and_then(|n| n.split_once(']'))
+                else {
+                    continue;
+                };
+                let (resource, method) = rest.split_once('.').unwrap_or((rest, ""));
+                if resource != def.name {
+                    continue;
+                }
+                match kind {
+                    "constructor" => {
+                        let params = Func {
+                            params: func.params.clone(),
+                            results: FuncResults::Named(Vec::new()),
+                        };
+                        methods.push(format!("constructor{};", signature(&params, 0)));
+                    }
+                    "method" => {
+                        methods.push(format!("{}: func{};", ident(method), signature(func, 1)))
+                    }
+                    "static" => methods.push(format!(
+                        "{}: static func{};",
+                        ident(method),
+                        signature(func, 0)
+                    )),
+                    _ => {}
+                }
+            }
+            if methods.is_empty() {
+                let _ = writeln!(out, "{indent}resource {name};");
+            } else {
+                block(out, "resource", methods);
+            }
+        }
+        TypeDefKind::Alias(ty) => {
+            let _ = writeln!(out, "{indent}type {name} = {ty};");
+        }
+        TypeDefKind::Use(iface, orig) if *orig == def.name => {
+            let _ = writeln!(out, "{indent}use {iface}.{{{name}}};");
+        }
+        TypeDefKind::Use(iface, orig) => {
+            let _ = writeln!(out, "{indent}use {iface}.{{{} as {name}}};", ident(orig));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::{ComponentExport, SortIdx};
+
+    fn name(name: &str) -> ExternName {
+        ExternName(name.to_string(), None)
+    }
+
+    fn func(params: &[(&str, ComponentValType)], results: Results) -> InstanceDecl {
+        let params = params
+            .iter()
+            .map(|(name, ty)| (name.to_string(), *ty))
+            .collect();
+        InstanceDecl::Type(DefType::Func(ComponentFuncType(params, results)))
+    }
+
+    #[test]
+    fn extract_world() {
+        use ComponentValType::{Primitive, Type as Idx};
+        let api = DefType::Instance(vec![
+            InstanceDecl::Type(DefType::DefVal(DefValType::Record(vec![
+                ("x".to_string(), Primitive(PrimValType::U32)),
+                ("y".to_string(), Primitive(PrimValType::U32)),
+            ]))),
+            InstanceDecl::Export(name("point"), ExternDesc::Type(TypeBound::Eq(0))),
+            InstanceDecl::Export(name("counter"), ExternDesc::Type(TypeBound::SubResource)),
+            InstanceDecl::Type(DefType::DefVal(DefValType::Own(2))),
+            func(&[("start", Idx(1))], Results::Unnamed(Idx(3))),
+            InstanceDecl::Export(name("[constructor]counter"), ExternDesc::Func(4)),
+            InstanceDecl::Type(DefType::DefVal(DefValType::Borrow(2))),
+            func(
+                &[("self", Idx(5))],
+                Results::Unnamed(Primitive(PrimValType::U32)),
+            ),
+            InstanceDecl::Export(name("[method]counter.get"), ExternDesc::Func(6)),
+            func(&[("p", Idx(1))], Results::Named(vec![])),
+            InstanceDecl::Export(name("log"), ExternDesc::Func(7)),
+        ]);
+        let world = DefType::Component(vec![
+            ComponentDecl::Instance(InstanceDecl::Type(api)),
+            ComponentDecl::Import(ComponentImport(
+                name("demo:app/api"),
+                ExternDesc::Instance(0),
+            )),
+            ComponentDecl::Instance(InstanceDecl::Alias(Alias(
+                Sort::Type,
+                AliasTarget::Export(0, "point".to_string()),
+            ))),
+            ComponentDecl::Import(ComponentImport(
+                name("point"),
+                ExternDesc::Type(TypeBound::Eq(1)),
+            )),
+            ComponentDecl::Instance(func(
+                &[("p", Idx(2))],
+                Results::Unnamed(Primitive(PrimValType::String)),
+            )),
+            ComponentDecl::Instance(InstanceDecl::Export(name("render"), ExternDesc::Func(3))),
+        ]);
+        let package = DefType::Component(vec![
+            ComponentDecl::Instance(InstanceDecl::Type(world)),
+            ComponentDecl::Instance(InstanceDecl::Export(
+                name("demo:app/plugin"),
+                ExternDesc::Component(0),
+            )),
+        ]);
+        let component = Component {
+            version: 0x0D,
+            layer: 1,
+            sections: vec![
+                ComponentSection::Type(vec![package]),
+                ComponentSection::Export(vec![ComponentExport(
+                    name("plugin"),
+                    SortIdx(Sort::Type, 0),
+                    None,
+                )]),
+            ],
+        };
+
+        let worlds = worlds(&component).unwrap();
+        assert_eq!(worlds.len(), 1);
+        assert_eq!(
+            print(&worlds),
+            "\
+interface api {
+    record point {
+        x: u32,
+        y: u32,
+    }
+    resource counter {
+        constructor(start: point);
+        get: func() -> u32;
+    }
+    log: func(p: point);
+}
+
+world plugin {
+    import demo:app/api;
+    use demo:app/api.{point};
+    export render: func(p: point) -> string;
+}
+"
+        );
+    }
+}
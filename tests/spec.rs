@@ -0,0 +1,150 @@
+//! Runs the WebAssembly spec test suite and reports results per proposal.
+//!
+//! The suite is https://github.com/WebAssembly/testsuite at the commit pinned
+//! in `tests/testsuite.rev`, fetched into `tests/testsuite` by
+//! `tests/update-testsuite.sh`, which CI runs; pass it a commit to change the
+//! pin. Scripts at its root are reported as `core`, scripts under
+//! `proposals/<name>` as `<name>`. The test is skipped when the directory is
+//! missing, except in CI. Failures in the implemented proposals fail the
+//! test, those in other proposals only do when `WAST_STRICT` is set.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use wasm_parse::wast::Wast;
+
+/// Proposals whose failures fail the test. `gc` is left out because the
+/// interpreter does not execute the GC instructions yet, and `relaxed-simd`
+/// until its scripts have been run against the interpreter.
+const IMPLEMENTED: [&str; 8] = [
+    "core",
+    "exception-handling",
+    "extended-const",
+    "function-references",
+    "memory64",
+    "multi-memory",
+    "tail-call",
+    "threads",
+];
+
+const GC: &str = "the interpreter does not execute GC instructions";
+
+/// Scripts at the root of the suite that are not run, with the reason.
+const SKIPPED: &[(&str, &str)] = &[
+    ("array.wast", GC),
+    ("array_copy.wast", GC),
+    ("array_fill.wast", GC),
+    ("array_init_data.wast", GC),
+    ("array_init_elem.wast", GC),
+    ("array_new_data.wast", GC),
+    ("array_new_elem.wast", GC),
+    ("br_on_cast.wast", GC),
+    ("br_on_cast_fail.wast", GC),
+    ("extern.wast", GC),
+    ("i31.wast", GC),
+    ("ref_cast.wast", GC),
+    ("ref_eq.wast", GC),
+    ("ref_test.wast", GC),
+    ("struct.wast", GC),
+    ("type-subtyping.wast", GC),
+];
+
+fn scripts(dir: &Path) -> Vec<PathBuf> {
+    let mut scripts: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wast"))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+fn run() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/testsuite");
+    if !root.is_dir() {
+        assert!(
+            std::env::var_os("CI").is_none(),
+            "no test suite at {}",
+            root.display()
+        );
+        eprintln!("skipping: no test suite at {}", root.display());
+        return;
+    }
+
+    let pin = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/testsuite.rev");
+    if let Ok(revision) = fs::read_to_string(pin) {
+        println!("test suite revision {}", revision.trim());
+    }
+    let mut suites = vec![("core".to_string(), scripts(&root))];
+    let mut proposals: Vec<_> = fs::read_dir(root.join("proposals"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    proposals.sort();
+    for dir in proposals {
+        let name = dir.file_name().unwrap().to_string_lossy().into_owned();
+        suites.push((name, scripts(&dir)));
+    }
+
+    let mut totals = BTreeMap::new();
+    for (proposal, scripts) in suites {
+        let (passed, failed) = totals.entry(proposal.clone()).or_insert((0, 0));
+        for script in scripts {
+            let name = script.strip_prefix(&root).unwrap().display().to_string();
+            if let Some((_, reason)) = SKIPPED.iter().find(|(skipped, _)| *skipped == name) {
+                println!("{name}: skipped, {reason}");
+                continue;
+            }
+            let src = match fs::read_to_string(&script) {
+                Ok(src) => src,
+                Err(err) => {
+                    eprintln!("{name}: {err}");
+                    *failed += 1;
+                    continue;
+                }
+            };
+            match Wast::new().run(&src) {
+                Ok(report) => {
+                    *passed += report.passed;
+                    *failed += report.failures.len();
+                    for failure in &report.failures {
+                        eprintln!("{name}:{failure}");
+                    }
+                }
+                Err(err) => {
+                    eprintln!("{name}: {err:?}");
+                    *failed += 1;
+                }
+            }
+        }
+    }
+
+    println!("{:<24} {:>8} {:>8}", "proposal", "passed", "failed");
+    for (proposal, (passed, failed)) in &totals {
+        println!("{proposal:<24} {passed:>8} {failed:>8}");
+    }
+    let strict = std::env::var_os("WAST_STRICT").is_some();
+    let failed: usize = totals
+        .iter()
+        .filter(|(proposal, _)| strict || IMPLEMENTED.contains(&proposal.as_str()))
+        .map(|(_, (_, failed))| failed)
+        .sum();
+    assert_eq!(failed, 0, "spec test suite failures");
+}
+
+#[test]
+fn testsuite() {
+    // Deeply nested and recursive scripts need more than the default test stack.
+    std::thread::Builder::new()
+        .stack_size(256 << 20)
+        .spawn(run)
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -0,0 +1,30 @@
+#!/bin/sh
+# Fetches the WebAssembly spec test suite at the commit pinned in
+# tests/testsuite.rev into tests/testsuite. Given a commit, pins that one
+# first.
+#
+# Usage: tests/update-testsuite.sh [commit]
+set -eu
+
+tests="$(dirname "$0")"
+pin="$tests/testsuite.rev"
+dir="$tests/testsuite"
+
+if [ $# -gt 0 ]; then
+    echo "$1" > "$pin"
+fi
+if [ ! -s "$pin" ]; then
+    echo "no pinned revision in $pin; run $0 <commit>" >&2
+    exit 1
+fi
+rev="$(cat "$pin")"
+
+tmp="$(mktemp -d)"
+trap 'rm -rf "$tmp"' EXIT
+git -C "$tmp" init -q
+git -C "$tmp" fetch -q --depth 1 https://github.com/WebAssembly/testsuite "$rev"
+git -C "$tmp" checkout -q FETCH_HEAD
+
+rm -rf "$dir"
+mkdir -p "$dir"
+git -C "$tmp" archive HEAD | tar -x -C "$dir"
@@ -0,0 +1,37 @@
+;; `src/main.rs` as a wasm32-wasip1 command runs it: read the arguments and
+;; environment, print to stdout and exit. CI runs the program cargo builds;
+;; this hand-written copy lets the WASI host's tests check the same calls,
+;; and a few faults, without a wasm32-wasip1 toolchain.
+(module
+  (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "args_get" (func $args_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "environ_sizes_get" (func $environ_sizes_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "environ_get" (func $environ_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+  ;; Not exported, so the host must not touch it.
+  (memory $scratch 1)
+  (memory $memory (export "memory") 1)
+  (data (memory $memory) (i32.const 1024) "Hello, world!\n")
+  (func $check (param i32)
+    (if (local.get 0) (then (call $proc_exit (local.get 0)))))
+  (func (export "_start")
+    (call $check (call $args_sizes_get (i32.const 0) (i32.const 4)))
+    (call $check (call $args_get (i32.const 256) (i32.const 512)))
+    (call $check (call $environ_sizes_get (i32.const 8) (i32.const 12)))
+    (call $check (call $environ_get (i32.const 768) (i32.const 896)))
+    ;; An iovec array running off the end of memory is a fault, not a trap.
+    (if (i32.ne (call $fd_write (i32.const 1) (i32.const -4) (i32.const 1) (i32.const 16))
+          (i32.const 21))
+      (then unreachable))
+    ;; So are lengths adding up to more than the returned size can hold.
+    (i32.store $memory (i32.const 36) (i32.const 0x80000000))
+    (i32.store $memory (i32.const 44) (i32.const 0x80000000))
+    (if (i32.ne (call $fd_write (i32.const 1) (i32.const 32) (i32.const 2) (i32.const 48))
+          (i32.const 28))
+      (then unreachable))
+    (i32.store $memory (i32.const 16) (i32.const 1024))
+    (i32.store $memory (i32.const 20) (i32.const 14))
+    (call $check (call $fd_write (i32.const 1) (i32.const 16) (i32.const 1) (i32.const 24)))
+    (call $proc_exit (i32.const 0))
+    unreachable))